@@ -0,0 +1,159 @@
+// restic-manager dashboard: polls the status API of `restic-manager serve`.
+// The token is kept in the browser's local storage and sent as a bearer token.
+
+const REFRESH_MS = 60 * 1000;
+const TOKEN_KEY = "restic-manager-token";
+
+const $ = (id) => document.getElementById(id);
+
+function token() {
+  return localStorage.getItem(TOKEN_KEY) || "";
+}
+
+async function api(method, path, body) {
+  const response = await fetch(path, {
+    method,
+    headers: { Authorization: `Bearer ${token()}`, "Content-Type": "application/json" },
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  const json = await response.json().catch(() => ({}));
+  if (!response.ok) {
+    throw new Error(json.error || `${response.status} ${response.statusText}`);
+  }
+  return json;
+}
+
+function showMessage(text, isError) {
+  const message = $("message");
+  message.textContent = text;
+  message.className = isError ? "error" : "";
+  message.hidden = !text;
+}
+
+function formatDuration(secs) {
+  if (secs === null || secs === undefined) return "";
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  if (hours > 0) return minutes > 0 ? `${hours}h ${minutes}m` : `${hours}h`;
+  if (minutes > 0) return `${minutes}m`;
+  return `${secs}s`;
+}
+
+function formatSize(bytes) {
+  if (bytes === null || bytes === undefined) return "";
+  const units = ["B", "KiB", "MiB", "GiB", "TiB"];
+  let size = bytes;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit += 1;
+  }
+  return `${size.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+function cell(text, className) {
+  const td = document.createElement("td");
+  td.textContent = text;
+  if (className) td.className = className;
+  return td;
+}
+
+function badge(health) {
+  const td = document.createElement("td");
+  const span = document.createElement("span");
+  span.className = `badge ${health || ""}`;
+  span.textContent = health || "unknown";
+  td.appendChild(span);
+  return td;
+}
+
+function triggerButton(service) {
+  const td = document.createElement("td");
+  const button = document.createElement("button");
+  button.textContent = "Back up now";
+  button.addEventListener("click", async () => {
+    button.disabled = true;
+    try {
+      await api("POST", `/trigger/${encodeURIComponent(service)}`, { note: "dashboard" });
+      showMessage(`Backup of ${service} started`, false);
+    } catch (e) {
+      showMessage(`Failed to start the backup of ${service}: ${e.message}`, true);
+    } finally {
+      button.disabled = false;
+    }
+  });
+  td.appendChild(button);
+  return td;
+}
+
+function renderServices(services) {
+  const rows = [];
+  for (const service of services) {
+    if (!service.enabled) {
+      const row = document.createElement("tr");
+      row.append(cell(service.name), cell("disabled", "muted"), cell(""), cell(""), cell(""), cell(""), cell(""));
+      rows.push(row);
+      continue;
+    }
+    service.destinations.forEach((dest, index) => {
+      const row = document.createElement("tr");
+      const latest = dest.error
+        ? dest.error
+        : dest.age_secs === null ? "no snapshots" : `${formatDuration(dest.age_secs)} ago`;
+      row.append(
+        cell(index === 0 ? service.name : ""),
+        cell(dest.destination),
+        badge(dest.health),
+        cell(latest, dest.error ? "muted" : ""),
+        cell(String(dest.snapshots)),
+        cell(formatSize(dest.total_size)),
+        index === 0 ? triggerButton(service.name) : cell(""),
+      );
+      rows.push(row);
+    });
+  }
+  $("services").replaceChildren(...rows);
+}
+
+function renderRuns(runs) {
+  const rows = runs.slice().reverse().map((run) => {
+    const row = document.createElement("tr");
+    row.append(
+      cell(new Date(run.started_at).toLocaleString()),
+      cell(run.service),
+      cell(run.destination),
+      cell(run.success ? "success" : run.error || "failed", run.success ? "" : "muted"),
+      cell(formatDuration(run.duration_secs)),
+      cell(formatSize(run.data_added)),
+      cell(run.trigger || ""),
+    );
+    return row;
+  });
+  $("runs").replaceChildren(...rows);
+}
+
+async function refresh() {
+  if (!token()) {
+    showMessage("Enter an API token with the read scope (and trigger to start backups).", false);
+    return;
+  }
+  try {
+    const [status, history] = await Promise.all([api("GET", "/api/services"), api("GET", "/api/history?limit=20")]);
+    renderServices(status.services);
+    renderRuns(history.runs);
+    $("refreshed").textContent = new Date().toLocaleTimeString();
+    if ($("message").className === "error") showMessage("", false);
+  } catch (e) {
+    showMessage(`Failed to load the status: ${e.message}`, true);
+  }
+}
+
+$("token").value = token();
+$("token-form").addEventListener("submit", (event) => {
+  event.preventDefault();
+  localStorage.setItem(TOKEN_KEY, $("token").value.trim());
+  refresh();
+});
+
+refresh();
+setInterval(refresh, REFRESH_MS);
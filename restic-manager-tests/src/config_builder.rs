@@ -4,7 +4,7 @@
 
 use restic_manager::config::{
     Config, Destination, DestinationType, GlobalConfig,
-    NotificationConfig, Profile, RetentionPolicy, ServiceConfig, BackupConfig,
+    NotificationConfig, Profile, RetentionPolicy, ServerConfig, ServiceConfig, BackupConfig,
 };
 use std::collections::HashMap;
 use std::fs;
@@ -53,6 +53,7 @@ impl ConfigBuilder {
             log_max_size_mb: 10,
             default_excludes: vec![],
             use_system_restic: false,
+            ..Default::default()
         };
 
         Self {
@@ -79,6 +80,7 @@ impl ConfigBuilder {
                 dest_type: DestinationType::Local,
                 url: backup_path.display().to_string(),
                 description: "Local test destination".to_string(),
+                ..Default::default()
             },
         );
 
@@ -115,6 +117,9 @@ impl ConfigBuilder {
         self.global.retention_weekly = retention.weekly;
         self.global.retention_monthly = retention.monthly;
         self.global.retention_yearly = retention.yearly;
+        self.global.retention_hourly = retention.hourly;
+        self.global.retention_last = retention.last;
+        self.global.retention_within = retention.within;
         self
     }
 
@@ -126,6 +131,7 @@ impl ConfigBuilder {
                 dest_type: DestinationType::Local,
                 url: path.display().to_string(),
                 description: format!("Local destination: {}", name),
+                ..Default::default()
             },
         );
         self
@@ -139,6 +145,7 @@ impl ConfigBuilder {
                 dest_type: DestinationType::Sftp,
                 url: url.to_string(),
                 description: format!("SFTP destination: {}", name),
+                ..Default::default()
             },
         );
         self
@@ -156,15 +163,28 @@ impl ConfigBuilder {
             name.to_string(),
             ServiceConfig {
                 enabled: true,
+                disabled_until: None,
+                tags: Vec::new(),
+                hostname: None,
                 profile: None,
                 description: format!("Test service: {}", name),
                 schedule: "0 2 * * *".to_string(),
                 targets: vec!["local".to_string()],
                 timeout_seconds: None,
+                time_budget_minutes: None,
+                max_age_hours: None,
                 retention_daily: None,
                 retention_weekly: None,
                 retention_monthly: None,
                 retention_yearly: None,
+                retention_hourly: None,
+                retention_last: None,
+                retention_within: None,
+                retention: Default::default(),
+                retries: None,
+                retry_delay_seconds: None,
+                retry_backoff_multiplier: None,
+                post_backup_verify: None,
                 notify_on: vec![],
                 config: None,
             },
@@ -184,15 +204,28 @@ impl ConfigBuilder {
             name.to_string(),
             ServiceConfig {
                 enabled: false,
+                disabled_until: None,
+                tags: Vec::new(),
+                hostname: None,
                 profile: None,
                 description: format!("Disabled service: {}", name),
                 schedule: "0 2 * * *".to_string(),
                 targets: vec!["local".to_string()],
                 timeout_seconds: None,
+                time_budget_minutes: None,
+                max_age_hours: None,
                 retention_daily: None,
                 retention_weekly: None,
                 retention_monthly: None,
                 retention_yearly: None,
+                retention_hourly: None,
+                retention_last: None,
+                retention_within: None,
+                retention: Default::default(),
+                retries: None,
+                retry_delay_seconds: None,
+                retry_backoff_multiplier: None,
+                post_backup_verify: None,
                 notify_on: vec![],
                 config: None,
             },
@@ -206,22 +239,50 @@ impl ConfigBuilder {
             name.to_string(),
             ServiceConfig {
                 enabled: true,
+                disabled_until: None,
+                tags: Vec::new(),
+                hostname: None,
                 profile: None,
                 description: format!("Service with paths: {}", name),
                 schedule: "0 2 * * *".to_string(),
                 targets: vec!["local".to_string()],
                 timeout_seconds: None,
+                time_budget_minutes: None,
+                max_age_hours: None,
                 retention_daily: None,
                 retention_weekly: None,
                 retention_monthly: None,
                 retention_yearly: None,
+                retention_hourly: None,
+                retention_last: None,
+                retention_within: None,
+                retention: Default::default(),
+                retries: None,
+                retry_delay_seconds: None,
+                retry_backoff_multiplier: None,
+                post_backup_verify: None,
                 notify_on: vec![],
                 config: Some(BackupConfig {
                     paths,
+                    files_from: vec![],
+                    files_from_verbatim: vec![],
                     volumes: vec![],
+                    containers: vec![],
                     excludes: vec![],
+                    iexcludes: vec![],
+                    exclude_files: vec![],
+                    exclude_larger_than: None,
+                    one_file_system: None,
                     pre_backup_hooks: vec![],
                     post_backup_hooks: vec![],
+                    commands: vec![],
+                    on_failure_hooks: vec![],
+                    on_success_hooks: vec![],
+                    restore_test_hooks: vec![],
+                    compose_project: None,
+                    compose_file: None,
+                    stop_during_backup: false,
+                    pause_containers: None,
                 }),
             },
         );
@@ -234,22 +295,50 @@ impl ConfigBuilder {
             name.to_string(),
             ServiceConfig {
                 enabled: true,
+                disabled_until: None,
+                tags: Vec::new(),
+                hostname: None,
                 profile: None,
                 description: format!("Service with volumes: {}", name),
                 schedule: "0 2 * * *".to_string(),
                 targets: vec!["local".to_string()],
                 timeout_seconds: None,
+                time_budget_minutes: None,
+                max_age_hours: None,
                 retention_daily: None,
                 retention_weekly: None,
                 retention_monthly: None,
                 retention_yearly: None,
+                retention_hourly: None,
+                retention_last: None,
+                retention_within: None,
+                retention: Default::default(),
+                retries: None,
+                retry_delay_seconds: None,
+                retry_backoff_multiplier: None,
+                post_backup_verify: None,
                 notify_on: vec![],
                 config: Some(BackupConfig {
                     paths: vec![],
+                    files_from: vec![],
+                    files_from_verbatim: vec![],
                     volumes,
+                    containers: vec![],
                     excludes: vec![],
+                    iexcludes: vec![],
+                    exclude_files: vec![],
+                    exclude_larger_than: None,
+                    one_file_system: None,
                     pre_backup_hooks: vec![],
                     post_backup_hooks: vec![],
+                    commands: vec![],
+                    on_failure_hooks: vec![],
+                    on_success_hooks: vec![],
+                    restore_test_hooks: vec![],
+                    compose_project: None,
+                    compose_file: None,
+                    stop_during_backup: false,
+                    pause_containers: None,
                 }),
             },
         );
@@ -286,22 +375,32 @@ impl ConfigBuilder {
     /// Build the Config
     pub fn build(self) -> Config {
         Config {
+            include: Vec::new(),
             global: self.global,
             destinations: self.destinations,
             services: self.services,
             profiles: self.profiles,
             notifications: self.notifications,
+            server: ServerConfig::default(),
+            experimental: Default::default(),
+            integrations: Default::default(),
+            discovery: Default::default(),
         }
     }
 
     /// Keep the temp directory (don't delete on drop)
     pub fn persist(self) -> (Config, TempDir) {
         let config = Config {
+            include: Vec::new(),
             global: self.global,
             destinations: self.destinations,
             services: self.services,
             profiles: self.profiles,
             notifications: self.notifications,
+            server: ServerConfig::default(),
+            experimental: Default::default(),
+            integrations: Default::default(),
+            discovery: Default::default(),
         };
         (config, self.temp_dir)
     }
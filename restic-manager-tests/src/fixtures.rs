@@ -12,6 +12,8 @@ pub fn sample_snapshot() -> Snapshot {
         time: "2025-12-28T10:30:00.000000000Z".to_string(),
         hostname: "test-host".to_string(),
         paths: vec!["/data".to_string()],
+        tags: vec![],
+        ..Default::default()
     }
 }
 
@@ -24,6 +26,8 @@ pub fn sample_snapshots(count: usize) -> Vec<Snapshot> {
             time: format!("2025-12-{:02}T10:30:00.000000000Z", 28 - (i % 28)),
             hostname: "test-host".to_string(),
             paths: vec!["/data".to_string()],
+            tags: vec![],
+            ..Default::default()
         })
         .collect()
 }
@@ -36,6 +40,8 @@ pub fn snapshot_with_time(time: &str) -> Snapshot {
         time: time.to_string(),
         hostname: "test-host".to_string(),
         paths: vec!["/data".to_string()],
+        tags: vec![],
+        ..Default::default()
     }
 }
 
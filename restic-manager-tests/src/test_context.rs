@@ -127,8 +127,8 @@ impl<T: std::fmt::Debug, E: std::fmt::Debug> ResultAssertions<T> for Result<T, E
     }
 
     fn assert_err(self) {
-        if self.is_ok() {
-            panic!("Expected Err, got Ok: {:?}", self.unwrap());
+        if let Ok(v) = self {
+            panic!("Expected Err, got Ok: {:?}", v);
         }
     }
 
@@ -7,7 +7,7 @@ use test_utils::{
     sample_snapshot, sample_snapshots,
 };
 use restic_manager::config::resolve_all_services;
-use restic_manager::utils::restic::ResticEnv;
+use restic_manager::utils::restic::{ResticEnv, RestoreLimits};
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -22,7 +22,7 @@ fn test_restore_with_snapshot_id() {
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(60);
 
-    let result = mock.restore_snapshot(&env, &snapshot.id, None, &[], timeout);
+    let result = mock.restore_snapshot(&env, &snapshot.id, None, &[], &RestoreLimits::default(), timeout);
     assert!(result.is_ok());
     assert!(mock.restore_called());
 }
@@ -45,6 +45,7 @@ fn test_restore_to_target_directory() {
         &snapshot.id,
         Some(target_dir.to_str().unwrap()),
         &[],
+        &RestoreLimits::default(),
         timeout,
     );
 
@@ -63,7 +64,7 @@ fn test_restore_specific_paths() {
     let timeout = Duration::from_secs(60);
 
     let paths = vec!["data/important.txt".to_string(), "config/".to_string()];
-    let result = mock.restore_snapshot(&env, &snapshot.id, None, &paths, timeout);
+    let result = mock.restore_snapshot(&env, &snapshot.id, None, &paths, &RestoreLimits::default(), timeout);
 
     assert!(result.is_ok());
 }
@@ -167,7 +168,7 @@ fn test_restore_failure_handling() {
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(60);
 
-    let result = mock.restore_snapshot(&env, "abc123", None, &[], timeout);
+    let result = mock.restore_snapshot(&env, "abc123", None, &[], &RestoreLimits::default(), timeout);
     assert!(result.is_err());
 }
 
@@ -164,6 +164,7 @@ fn test_run_applies_retention() {
         weekly: 4,
         monthly: 6,
         yearly: 1,
+        ..Default::default()
     };
 
     let result = mock.apply_retention(&env, &retention, timeout);
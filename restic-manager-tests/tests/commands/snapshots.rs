@@ -6,7 +6,11 @@ use test_utils::{
     ConfigBuilder, MockResticOps, ResticOperations,
     sample_snapshot, sample_snapshots, snapshot_with_time,
 };
-use restic_manager::utils::restic::ResticEnv;
+use restic_manager::commands::{snapshots::snapshots, CommandContext, Outcome};
+use restic_manager::config::resolve_all_services;
+use restic_manager::utils::restic::{format_bytes, RepoStats, ResticEnv, SnapshotSummary};
+use restic_manager::utils::restic_ops::mock::ResticCall;
+use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -163,10 +167,63 @@ fn test_snapshots_stats() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_stats("2.5 GiB total");
+    let mock = MockResticOps::new().with_stats(RepoStats { total_size: 2_684_354_560, ..Default::default() });
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(30);
 
     let stats = mock.get_stats(&env, timeout).unwrap();
-    assert!(stats.contains("2.5 GiB"));
+    assert_eq!(format_bytes(stats.total_size), "2.50 GiB");
+}
+
+#[test]
+fn test_snapshots_command_with_mocked_restic() {
+    let (config, _temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    let resolved = resolve_all_services(&config).unwrap();
+
+    let mock = MockResticOps::new().with_snapshots(sample_snapshots(3));
+    let ctx = CommandContext::new(config, resolved).with_ops(Arc::new(mock.clone()));
+
+    let outcome = snapshots(&ctx, "app", None, &[], false).unwrap();
+    assert_eq!(outcome, Outcome::Success);
+    let calls = mock.get_calls();
+    assert!(matches!(calls.as_slice(), [ResticCall::ListSnapshots, ResticCall::GetStats]));
+
+    let err = snapshots(&ctx, "missing", None, &[], false).unwrap_err();
+    assert!(err.to_string().contains("not found"));
+    assert_eq!(mock.get_calls().len(), 2);
+}
+
+#[test]
+fn test_snapshots_sizes_with_mocked_restic() {
+    let (config, _temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    let resolved = resolve_all_services(&config).unwrap();
+
+    // Only the snapshot without a summary needs `restic stats`
+    let mut snapshots_list = sample_snapshots(2);
+    snapshots_list[1].summary = Some(SnapshotSummary { total_bytes_processed: 4096, data_added: 1024, ..Default::default() });
+    let without_summary = snapshots_list[0].id.clone();
+
+    let mock = MockResticOps::new()
+        .with_snapshots(snapshots_list)
+        .with_stats(RepoStats { total_size: 2048, ..Default::default() });
+    let ctx = CommandContext::new(config, resolved).with_ops(Arc::new(mock.clone()));
+
+    assert_eq!(snapshots(&ctx, "app", None, &[], true).unwrap(), Outcome::Success);
+    let calls = mock.get_calls();
+    assert_eq!(calls.len(), 3, "unexpected calls: {:?}", calls);
+    assert!(matches!(&calls[1], ResticCall::SnapshotStats { snapshot_id } if *snapshot_id == without_summary));
+    assert!(matches!(calls[2], ResticCall::GetStats));
+}
+
+#[test]
+fn test_snapshots_sizes_skip_stats_on_cold_storage() {
+    let (mut config, _temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    config.destinations.get_mut("local").unwrap().cold_storage = true;
+    let resolved = resolve_all_services(&config).unwrap();
+
+    let mock = MockResticOps::new().with_snapshots(sample_snapshots(3));
+    let ctx = CommandContext::new(config, resolved).with_ops(Arc::new(mock.clone()));
+
+    assert_eq!(snapshots(&ctx, "app", None, &[], true).unwrap(), Outcome::Success);
+    assert!(!mock.get_calls().iter().any(|c| matches!(c, ResticCall::SnapshotStats { .. })));
 }
@@ -3,11 +3,10 @@
 //! The status command displays backup status and health metrics.
 
 use test_utils::{
-    ConfigBuilder, MockResticOps, ResticOperations,
-    sample_snapshot, sample_snapshots, snapshot_with_time,
+    ConfigBuilder, MockResticOps, ResticOperations, sample_snapshots, snapshot_with_time,
 };
 use restic_manager::config::resolve_all_services;
-use restic_manager::utils::restic::ResticEnv;
+use restic_manager::utils::restic::{format_bytes, RepoStats, ResticEnv};
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -77,13 +76,13 @@ fn test_status_repository_stats() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_stats("1.5 GiB");
+    let mock = MockResticOps::new().with_stats(RepoStats { total_size: 1_610_612_736, ..Default::default() });
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(30);
 
     let stats = mock.get_stats(&env, timeout).unwrap();
 
-    assert_eq!(stats, "1.5 GiB");
+    assert_eq!(format_bytes(stats.total_size), "1.50 GiB");
 }
 
 #[test]
@@ -110,7 +109,7 @@ fn test_status_multi_destination() {
         .build();
 
     let resolved = resolve_all_services(&config).unwrap();
-    let service = resolved.get("multi-target").unwrap();
+    let _service = resolved.get("multi-target").unwrap();
 
     // Service should have access to multiple destinations
     // (though the default service only targets "local")
@@ -150,6 +149,7 @@ fn test_status_retention_policy() {
             weekly: 4,
             monthly: 6,
             yearly: 1,
+            ..Default::default()
         })
         .add_service("test")
         .build();
@@ -182,3 +182,26 @@ fn test_status_shows_latest_snapshot_info() {
     assert!(latest.time.contains("2025-12-28T15:30"));
     assert_eq!(latest.hostname, "backup-server");
 }
+
+#[test]
+fn test_status_all_exit_code_reflects_worst_health() {
+    use restic_manager::commands::{exit_code, status::status, CommandContext, Outcome};
+    use std::sync::Arc;
+
+    let (mut config, _temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    let resolved = resolve_all_services(&config).unwrap();
+    let old_snapshot = || MockResticOps::new().with_snapshots(vec![snapshot_with_time("2020-01-01T02:00:00Z")]);
+
+    // Far older than a daily schedule allows
+    let ctx = CommandContext::new(config.clone(), resolved.clone()).with_ops(Arc::new(old_snapshot()));
+    assert_eq!(status(&ctx, None, true).unwrap(), Outcome::Failed(exit_code::HEALTH_CRITICAL));
+
+    // Within a generous max_age_hours
+    config.services.get_mut("app").unwrap().max_age_hours = Some(1_000_000);
+    let ctx = CommandContext::new(config.clone(), resolve_all_services(&config).unwrap()).with_ops(Arc::new(old_snapshot()));
+    assert_eq!(status(&ctx, None, true).unwrap(), Outcome::Success);
+
+    // An unreadable repository is critical
+    let ctx = CommandContext::new(config, resolved).with_ops(Arc::new(MockResticOps::new().with_failing_list()));
+    assert_eq!(status(&ctx, None, true).unwrap(), Outcome::Failed(exit_code::HEALTH_CRITICAL));
+}
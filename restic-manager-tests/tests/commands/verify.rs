@@ -6,7 +6,7 @@ use test_utils::{
     ConfigBuilder, MockResticOps, ResticOperations,
 };
 use restic_manager::config::resolve_all_services;
-use restic_manager::utils::restic::ResticEnv;
+use restic_manager::utils::restic::{format_bytes, CheckCategory, CheckIssue, CheckResult, RepoStats, ResticEnv};
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -37,12 +37,13 @@ fn test_verify_repository_check() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_check_result("no errors found");
+    let mock = MockResticOps::new().with_check_result(CheckResult::Ok);
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(60);
 
     let result = mock.check_repository(&env, false, timeout).unwrap();
-    assert!(result.contains("no errors"));
+    assert!(result.passed());
+    assert!(result.issues().is_empty());
     assert!(mock.check_called());
 }
 
@@ -52,13 +53,18 @@ fn test_verify_with_read_data() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_check_result("data verification passed");
+    let warning = CheckIssue {
+        category: CheckCategory::Unused,
+        message: "pack 5d4e21ef: not referenced in any index".to_string(),
+    };
+    let mock = MockResticOps::new().with_check_result(CheckResult::Warnings(vec![warning]));
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(300);
 
     // read_data = true runs more thorough check
     let result = mock.check_repository(&env, true, timeout).unwrap();
-    assert!(result.contains("verification"));
+    assert!(result.passed());
+    assert_eq!(result.issues()[0].category, CheckCategory::Unused);
 }
 
 #[test]
@@ -134,10 +140,10 @@ fn test_verify_stats_retrieval() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_stats("Repository size: 5.2 GiB, deduplicated");
+    let mock = MockResticOps::new().with_stats(RepoStats { total_size: 5_583_457_485, ..Default::default() });
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(30);
 
     let stats = mock.get_stats(&env, timeout).unwrap();
-    assert!(stats.contains("5.2 GiB"));
+    assert_eq!(format_bytes(stats.total_size), "5.20 GiB");
 }
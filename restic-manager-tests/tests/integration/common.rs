@@ -24,8 +24,8 @@ impl Drop for ContainerGuard {
 /// Helper to stop and remove a Docker container
 /// The -v flag also removes anonymous volumes associated with the container
 fn cleanup_container(name: &str) {
-    let _ = Command::new("docker").args(&["stop", name]).output();
-    let _ = Command::new("docker").args(&["rm", "-v", name]).output();
+    let _ = Command::new("docker").args(["stop", name]).output();
+    let _ = Command::new("docker").args(["rm", "-v", name]).output();
 }
 
 /// Guard that ensures Docker volume cleanup on drop (even on panic)
@@ -48,6 +48,6 @@ impl Drop for VolumeGuard {
 /// Helper to cleanup a Docker volume
 fn cleanup_volume(name: &str) {
     let _ = Command::new("docker")
-        .args(&["volume", "rm", name])
+        .args(["volume", "rm", name])
         .output();
 }
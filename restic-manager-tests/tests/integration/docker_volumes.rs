@@ -5,6 +5,7 @@
 
 use super::common::VolumeGuard;
 use anyhow::Result;
+use restic_manager::config::ContainerRuntime;
 use restic_manager::utils::docker::{
     archive_volume, get_volume_size, list_volumes, restore_volume, volume_exists,
 };
@@ -15,7 +16,7 @@ use tempfile::TempDir;
 /// Helper to check if Docker is available
 fn is_docker_available() -> bool {
     Command::new("docker")
-        .args(&["ps"])
+        .args(["ps"])
         .output()
         .map(|o| o.status.success())
         .unwrap_or(false)
@@ -25,12 +26,12 @@ fn is_docker_available() -> bool {
 fn create_test_volume(name: &str, content: &str) -> Result<()> {
     // Create volume
     Command::new("docker")
-        .args(&["volume", "create", name])
+        .args(["volume", "create", name])
         .output()?;
 
     // Add test data
     Command::new("docker")
-        .args(&[
+        .args([
             "run",
             "--rm",
             "-v",
@@ -48,14 +49,14 @@ fn create_test_volume(name: &str, content: &str) -> Result<()> {
 /// Helper to cleanup test volume
 fn cleanup_volume(name: &str) {
     let _ = Command::new("docker")
-        .args(&["volume", "rm", name])
+        .args(["volume", "rm", name])
         .output();
 }
 
 /// Helper to read data from volume
 fn read_volume_data(name: &str) -> Result<String> {
     let output = Command::new("docker")
-        .args(&[
+        .args([
             "run",
             "--rm",
             "-v",
@@ -86,7 +87,7 @@ fn test_list_docker_volumes() {
     let _guard = VolumeGuard::new(test_volume.to_string());
 
     // List volumes
-    let volumes = list_volumes(timeout).expect("Failed to list volumes");
+    let volumes = list_volumes(ContainerRuntime::Docker, timeout).expect("Failed to list volumes");
 
     // Verify test volume is in the list
     assert!(
@@ -117,7 +118,7 @@ fn test_volume_archive_restore() {
     let _guard = VolumeGuard::new(test_volume.to_string());
 
     // Archive the volume
-    archive_volume(test_volume, &archive_path, timeout).expect("Failed to archive volume");
+    archive_volume(ContainerRuntime::Docker, test_volume, &archive_path, timeout).expect("Failed to archive volume");
 
     // Verify archive file exists
     assert!(archive_path.exists(), "Archive file should exist");
@@ -130,17 +131,17 @@ fn test_volume_archive_restore() {
     cleanup_volume(test_volume);
 
     // Verify volume is gone
-    let exists = volume_exists(test_volume, timeout).expect("Failed to check volume exists");
+    let exists = volume_exists(ContainerRuntime::Docker, test_volume, timeout).expect("Failed to check volume exists");
     assert!(!exists, "Volume should be deleted");
 
     // Create new empty volume for restoration
     Command::new("docker")
-        .args(&["volume", "create", test_volume])
+        .args(["volume", "create", test_volume])
         .output()
         .expect("Failed to create volume");
 
     // Restore the volume
-    restore_volume(test_volume, &archive_path, timeout).expect("Failed to restore volume");
+    restore_volume(ContainerRuntime::Docker, test_volume, &archive_path, timeout).expect("Failed to restore volume");
 
     // Verify data integrity
     let restored_content = read_volume_data(test_volume).expect("Failed to read restored data");
@@ -181,7 +182,7 @@ fn test_archive_multiple_volumes() {
     let mut archives = Vec::new();
     for (name, _) in &volumes {
         let archive_path = temp_dir.path().join(format!("{}.tar.gz", name));
-        archive_volume(name, &archive_path, timeout).expect("Failed to archive volume");
+        archive_volume(ContainerRuntime::Docker, name, &archive_path, timeout).expect("Failed to archive volume");
         assert!(archive_path.exists(), "Archive should exist");
         archives.push(archive_path);
     }
@@ -210,7 +211,7 @@ fn test_get_volume_size() {
 
     // Add a file with known size (1KB)
     Command::new("docker")
-        .args(&[
+        .args([
             "run",
             "--rm",
             "-v",
@@ -226,7 +227,7 @@ fn test_get_volume_size() {
         .expect("Failed to create test file");
 
     // Get volume size
-    let size = get_volume_size(test_volume, timeout).expect("Failed to get volume size");
+    let size = get_volume_size(ContainerRuntime::Docker, test_volume, timeout).expect("Failed to get volume size");
 
     // Volume should be at least 1KB (plus filesystem overhead)
     assert!(size >= 1024, "Volume size should be at least 1KB");
@@ -253,11 +254,11 @@ fn test_exact_volume_name_matching() {
     let _guard = VolumeGuard::new(exact_name.to_string());
 
     // Test exact match
-    let exists = volume_exists(exact_name, timeout).expect("Failed to check exact volume");
+    let exists = volume_exists(ContainerRuntime::Docker, exact_name, timeout).expect("Failed to check exact volume");
     assert!(exists, "Exact volume name should exist");
 
     // Test that prefix doesn't match
-    let exists = volume_exists(prefix_name, timeout).expect("Failed to check prefix");
+    let exists = volume_exists(ContainerRuntime::Docker, prefix_name, timeout).expect("Failed to check prefix");
     assert!(
         !exists,
         "Prefix-only name should not match longer volume name"
@@ -13,7 +13,7 @@ use tempfile::TempDir;
 /// Helper to check if Docker is available
 fn is_docker_available() -> bool {
     Command::new("docker")
-        .args(&["ps"])
+        .args(["ps"])
         .output()
         .map(|o| o.status.success())
         .unwrap_or(false)
@@ -22,7 +22,7 @@ fn is_docker_available() -> bool {
 /// Helper to start a PostgreSQL container
 fn start_postgres_container(name: &str) -> Result<()> {
     Command::new("docker")
-        .args(&[
+        .args([
             "run",
             "-d",
             "--name",
@@ -42,7 +42,7 @@ fn start_postgres_container(name: &str) -> Result<()> {
     for _ in 0..30 {
         // First check if server is accepting connections
         let ready_result = Command::new("docker")
-            .args(&[
+            .args([
                 "exec",
                 name,
                 "pg_isready",
@@ -54,7 +54,7 @@ fn start_postgres_container(name: &str) -> Result<()> {
         if ready_result.map(|o| o.status.success()).unwrap_or(false) {
             // Server is accepting connections, now verify it can execute queries
             let query_result = Command::new("docker")
-                .args(&[
+                .args([
                     "exec",
                     name,
                     "psql",
@@ -85,7 +85,7 @@ fn start_postgres_container(name: &str) -> Result<()> {
 /// Helper to execute SQL in container
 fn exec_sql(container: &str, sql: &str) -> Result<String> {
     let output = Command::new("docker")
-        .args(&[
+        .args([
             "exec",
             container,
             "psql",
@@ -120,7 +120,7 @@ fn verify_test_data(container: &str) -> Result<i32> {
 /// Helper to dump database
 fn dump_database(container: &str, output_path: &str) -> Result<()> {
     let output = Command::new("docker")
-        .args(&[
+        .args([
             "exec",
             container,
             "pg_dump",
@@ -203,7 +203,7 @@ fn test_postgres_backup_restore_cycle() {
     // Restore from dump
     let dump_content = std::fs::read_to_string(&dump_path).expect("Failed to read dump");
     let mut cmd = Command::new("docker");
-    cmd.args(&[
+    cmd.args([
         "exec",
         "-i",
         container_name,
@@ -3,7 +3,9 @@
 //! These tests verify config parsing, validation, and profile resolution.
 
 use test_utils::{ConfigBuilder, TestContext};
-use restic_manager::config::{load_config, resolve_all_services};
+use restic_manager::config::discovery::{merge_discovered, services_from_labels};
+use restic_manager::config::{load_config, parse_tag, resolve_all_services, ApiScope, ApiToken, ArchiveCompression, CommandSource, Destination, ContainerRuntime, DestinationType, DiscoveryConfig, DockerBackend, MqttConfig, NotificationConfig, PasswordSource, PauseMode, PostBackupVerify, Profile, RepositoryCompression, TagRetention};
+use restic_manager::utils::restic::build_repository_url;
 use std::fs;
 
 #[test]
@@ -124,6 +126,27 @@ fn test_config_service_resolution() {
     assert!(!services.get("service3").unwrap().enabled);
 }
 
+#[test]
+fn test_config_disabled_until() {
+    let mut config = ConfigBuilder::minimal()
+        .add_service("paused")
+        .add_service("resumed")
+        .build();
+    config.services.get_mut("paused").unwrap().disabled_until = "2999-01-01".parse().ok();
+    config.services.get_mut("resumed").unwrap().disabled_until = "2020-01-01".parse().ok();
+
+    let services = resolve_all_services(&config).unwrap();
+
+    let paused = services.get("paused").unwrap();
+    assert!(!paused.enabled);
+    assert_eq!(paused.disabled_until.unwrap().to_string(), "2999-01-01");
+
+    // Past the date the service is enabled again
+    let resumed = services.get("resumed").unwrap();
+    assert!(resumed.enabled);
+    assert!(resumed.disabled_until.is_none());
+}
+
 #[test]
 fn test_config_with_paths_and_volumes() {
     let config = ConfigBuilder::minimal()
@@ -214,6 +237,7 @@ fn test_config_retention_policy() {
             weekly: 8,
             monthly: 12,
             yearly: 2,
+            ..Default::default()
         })
         .add_service("test")
         .build();
@@ -242,3 +266,1070 @@ fn test_config_service_has_backup_config() {
     let resolved_service = resolved.get("files").unwrap();
     assert!(resolved_service.config.is_some());
 }
+
+#[test]
+fn test_config_azure_destination_with_credentials() {
+    let mut destination = Destination {
+        dest_type: DestinationType::Azure,
+        url: "azure:backups:/".to_string(),
+        description: "Azure".to_string(),
+        ..Default::default()
+    };
+    destination.env.insert("AZURE_ACCOUNT_NAME".to_string(), "account".to_string());
+    destination.env.insert("AZURE_ACCOUNT_KEY".to_string(), "secret".to_string());
+
+    let builder = ConfigBuilder::minimal()
+        .add_destination("azure", destination)
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let loaded = load_config(&config_path);
+    assert!(loaded.is_ok(), "Azure destination should load: {:?}", loaded.err());
+
+    let loaded_config = loaded.unwrap();
+    let azure = loaded_config.destinations.get("azure").unwrap();
+    assert_eq!(azure.dest_type, DestinationType::Azure);
+    assert_eq!(azure.env.get("AZURE_ACCOUNT_NAME").unwrap(), "account");
+}
+
+#[test]
+fn test_config_azure_destination_with_sas() {
+    let mut destination = Destination {
+        dest_type: DestinationType::Azure,
+        url: "azure:backups:/".to_string(),
+        ..Default::default()
+    };
+    destination.env.insert("AZURE_ACCOUNT_NAME".to_string(), "account".to_string());
+    destination.env.insert("AZURE_ACCOUNT_SAS".to_string(), "sv=2024-11-04&sig=...".to_string());
+
+    let builder = ConfigBuilder::minimal()
+        .add_destination("azure", destination.clone())
+        .add_service("test");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let loaded = load_config(&config_path).unwrap();
+    assert!(loaded.destinations["azure"].credentials_warning().is_none());
+
+    // Without key or SAS (managed identity) the config loads with a warning
+    destination.env.remove("AZURE_ACCOUNT_SAS");
+    config.destinations.insert("azure".to_string(), destination.clone());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    if std::env::var("AZURE_ACCOUNT_KEY").is_err() && std::env::var("AZURE_ACCOUNT_SAS").is_err() {
+        assert!(loaded.destinations["azure"].credentials_warning().is_some());
+    }
+
+    // The account name stays required
+    if std::env::var("AZURE_ACCOUNT_NAME").is_err() {
+        destination.env.remove("AZURE_ACCOUNT_NAME");
+        config.destinations.insert("azure".to_string(), destination);
+        fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        let err = load_config(&config_path).unwrap_err().to_string();
+        assert!(err.contains("AZURE_ACCOUNT_NAME"), "unexpected error: {}", err);
+    }
+}
+
+#[test]
+fn test_config_append_only_destination() {
+    let destination = Destination {
+        dest_type: DestinationType::Local,
+        url: "rest:https://backup.example.com/".to_string(),
+        append_only: true,
+        ..Default::default()
+    };
+
+    let builder = ConfigBuilder::minimal()
+        .add_destination("worm", destination)
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let loaded_config = load_config(&config_path).unwrap();
+    let worm = loaded_config.destinations.get("worm").unwrap();
+    assert!(worm.append_only);
+    assert!(!worm.allows_deletion(&loaded_config.global));
+    assert!(!loaded_config.global.trusted_host);
+}
+
+#[test]
+fn test_config_gcs_destination_missing_credentials_file() {
+    let mut destination = Destination {
+        dest_type: DestinationType::Gcs,
+        url: "gs:backups:/".to_string(),
+        description: "GCS".to_string(),
+        ..Default::default()
+    };
+    destination.env.insert(
+        "GOOGLE_APPLICATION_CREDENTIALS".to_string(),
+        "/nonexistent/credentials.json".to_string(),
+    );
+
+    let builder = ConfigBuilder::minimal()
+        .add_destination("gcs", destination)
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let result = load_config(&config_path);
+    assert!(result.is_err(), "Missing GCS credentials file should fail validation");
+}
+
+/// Write a root config that includes `services.d/*.toml` and return its path
+fn write_root_config_with_include(builder: &ConfigBuilder) -> std::path::PathBuf {
+    let password_file = builder.password_file().to_string_lossy().replace('\\', "/");
+    let docker_base = builder.temp_dir().join("docker").to_string_lossy().replace('\\', "/");
+    let backup_path = builder.temp_dir().join("backups").to_string_lossy().replace('\\', "/");
+    fs::create_dir_all(builder.temp_dir().join("docker")).unwrap();
+    fs::create_dir_all(builder.temp_dir().join("services.d")).unwrap();
+
+    let config_content = format!(r#"
+include = ["services.d/*.toml"]
+
+[global]
+restic_password_file = "{}"
+docker_base = "{}"
+
+[destinations.local]
+type = "local"
+url = "{}"
+"#,
+        password_file, docker_base, backup_path
+    );
+
+    let config_path = builder.temp_dir().join("config.toml");
+    fs::write(&config_path, config_content).unwrap();
+    config_path
+}
+
+#[test]
+fn test_config_include_merges_services() {
+    let builder = ConfigBuilder::new();
+    let config_path = write_root_config_with_include(&builder);
+    let services_dir = builder.temp_dir().join("services.d");
+
+    fs::write(services_dir.join("app.toml"), r#"
+[services.app]
+schedule = "0 2 * * *"
+targets = ["local"]
+"#).unwrap();
+    fs::write(services_dir.join("db.toml"), r#"
+[profiles.nightly]
+targets = ["local"]
+
+[services.db]
+profile = "nightly"
+schedule = "0 3 * * *"
+"#).unwrap();
+
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.services.len(), 2);
+    assert!(config.services.contains_key("app"));
+    assert!(config.services.contains_key("db"));
+    assert!(config.profiles.contains_key("nightly"));
+
+    let resolved = resolve_all_services(&config).unwrap();
+    assert_eq!(resolved.get("db").unwrap().targets, vec!["local".to_string()]);
+}
+
+#[test]
+fn test_config_include_duplicate_service() {
+    let builder = ConfigBuilder::new();
+    let config_path = write_root_config_with_include(&builder);
+    let services_dir = builder.temp_dir().join("services.d");
+
+    let service = r#"
+[services.app]
+schedule = "0 2 * * *"
+targets = ["local"]
+"#;
+    fs::write(services_dir.join("a.toml"), service).unwrap();
+    fs::write(services_dir.join("b.toml"), service).unwrap();
+
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("Duplicate service 'app'"), "unexpected error: {}", err);
+    assert!(err.contains("a.toml") && err.contains("b.toml"));
+}
+
+#[test]
+fn test_config_include_rejects_global_section() {
+    let builder = ConfigBuilder::new();
+    let config_path = write_root_config_with_include(&builder);
+    let services_dir = builder.temp_dir().join("services.d");
+
+    fs::write(services_dir.join("bad.toml"), r#"
+[global]
+docker_base = "/tmp"
+"#).unwrap();
+
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("bad.toml"), "error should name the included file: {}", err);
+}
+
+#[test]
+fn test_config_keyring_password_source_without_file() {
+    let builder = ConfigBuilder::new();
+    let docker_base = builder.temp_dir().join("docker").to_string_lossy().replace('\\', "/");
+    let backup_path = builder.temp_dir().join("backups").to_string_lossy().replace('\\', "/");
+    fs::create_dir_all(builder.temp_dir().join("docker")).unwrap();
+
+    let config_content = format!(r#"
+[global]
+docker_base = "{}"
+password_source = "keyring"
+
+[destinations.local]
+type = "local"
+url = "{}"
+
+[services.test]
+schedule = "0 2 * * *"
+targets = ["local"]
+"#,
+        docker_base, backup_path
+    );
+
+    let config_path = builder.temp_dir().join("config.toml");
+    fs::write(&config_path, config_content).unwrap();
+
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.global.password_source, PasswordSource::Keyring);
+    let local = config.destinations.get("local").unwrap();
+    assert_eq!(local.password_source(&config.global), PasswordSource::Keyring);
+}
+
+#[test]
+fn test_config_telegram_requires_token_and_chat_id() {
+    let builder = ConfigBuilder::minimal()
+        .with_notifications(NotificationConfig {
+            telegram_bot_token: "123456:ABC".to_string(),
+            ..Default::default()
+        })
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("telegram_chat_id"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_sendmail_requires_command() {
+    let builder = ConfigBuilder::minimal()
+        .with_notifications(NotificationConfig {
+            sendmail_to: vec!["ops@example.com".to_string()],
+            sendmail_command: " ".to_string(),
+            ..Default::default()
+        })
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+    assert!(config.notifications.has_channels());
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("sendmail_command"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_api_tokens_must_be_unique() {
+    let (mut config, temp_dir) = ConfigBuilder::minimal().add_service("test").persist();
+    config.server.webhook_token = "secret".to_string();
+    config.server.tokens.insert(
+        "dashboard".to_string(),
+        ApiToken { token: "secret".to_string(), scopes: vec![ApiScope::Read] },
+    );
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("server.tokens.dashboard"), "unexpected error: {}", err);
+
+    config.server.tokens.get_mut("dashboard").unwrap().token = "read-only".to_string();
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.server.tokens["dashboard"].scopes, vec![ApiScope::Read]);
+}
+
+#[test]
+fn test_config_verify_read_data_subset() {
+    let (mut config, temp_dir) = ConfigBuilder::minimal().add_service("test").persist();
+    config.global.verify_schedule = Some("0 4 * * 0".to_string());
+    config.global.verify_read_data_subset = Some("5 percent".to_string());
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("verify_read_data_subset"), "unexpected error: {}", err);
+
+    config.global.verify_read_data_subset = Some("5%".to_string());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.global.verify_schedule.as_deref(), Some("0 4 * * 0"));
+    assert_eq!(loaded.global.verify_read_data_subset.as_deref(), Some("5%"));
+}
+
+#[test]
+fn test_config_healthchecks_slug_requires_ping_key() {
+    let mut notifications = NotificationConfig::default();
+    notifications
+        .healthchecks
+        .checks
+        .insert("test".to_string(), "test-backup".to_string());
+
+    let builder = ConfigBuilder::minimal()
+        .with_notifications(notifications.clone())
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("ping_key"), "unexpected error: {}", err);
+
+    // A UUID needs no ping key
+    notifications
+        .healthchecks
+        .checks
+        .insert("test".to_string(), "5bf66975-d4c7-4bf5-bcc8-b8d8a82ea278".to_string());
+    let mut config = config;
+    config.notifications = notifications;
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert!(loaded.notifications.healthchecks.ping_url("test").unwrap().ends_with("b8d8a82ea278"));
+}
+
+#[test]
+fn test_config_tag_retention_rules() {
+    let builder = ConfigBuilder::minimal().add_service("test");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let tags = &mut config.services.get_mut("test").unwrap().retention.tags;
+    tags.insert("pre-upgrade".to_string(), TagRetention { forever: true, ..Default::default() });
+    tags.insert("manual".to_string(), TagRetention { within: Some("90d".to_string()), ..Default::default() });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let loaded = load_config(&config_path).unwrap();
+    let resolved = resolve_all_services(&loaded).unwrap();
+    let retention = &resolved.get("test").unwrap().retention;
+    assert!(retention.tags["pre-upgrade"].forever);
+    assert_eq!(retention.tags["manual"].within.as_deref(), Some("90d"));
+
+    // A rule must either keep forever or define a window
+    config.services.get_mut("test").unwrap().retention.tags
+        .insert("broken".to_string(), TagRetention::default());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("tag 'broken'"), "unexpected error: {}", err);
+
+    config.services.get_mut("test").unwrap().retention.tags
+        .insert("broken".to_string(), TagRetention { within: Some("90 days".to_string()), ..Default::default() });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("invalid retention duration"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_retention_extensions() {
+    let mut config = ConfigBuilder::minimal()
+        .add_profile("frequent", Profile {
+            targets: vec!["local".to_string()],
+            retention_hourly: Some(24),
+            retention_within: Some("2d".to_string()),
+            ..Default::default()
+        })
+        .add_service("test")
+        .build();
+    config.global.retention_last = 3;
+    let service = config.services.get_mut("test").unwrap();
+    service.profile = Some("frequent".to_string());
+    service.retention_within = Some("3d".to_string());
+
+    let resolved = resolve_all_services(&config).unwrap();
+    let retention = &resolved.get("test").unwrap().retention;
+    assert_eq!(retention.hourly, 24);
+    assert_eq!(retention.last, 3);
+    assert_eq!(retention.within.as_deref(), Some("3d"));
+
+    config.services.get_mut("test").unwrap().retention_within = Some("3 days".to_string());
+    let err = resolve_all_services(&config).unwrap_err().to_string();
+    assert!(err.contains("invalid retention_within"), "unexpected error: {}", err);
+
+    // At least one rule must keep snapshots
+    let service = config.services.get_mut("test").unwrap();
+    service.profile = None;
+    service.retention_within = None;
+    config.global.retention_last = 0;
+    for value in [&mut service.retention_daily, &mut service.retention_weekly, &mut service.retention_monthly, &mut service.retention_yearly] {
+        *value = Some(0);
+    }
+    let err = resolve_all_services(&config).unwrap_err().to_string();
+    assert!(err.contains("keeps no snapshots"), "unexpected error: {}", err);
+
+    // Large counts are valid and don't overflow
+    config.global.retention_last = u32::MAX;
+    config.services.get_mut("test").unwrap().retention_daily = Some(u32::MAX);
+    assert!(resolve_all_services(&config).is_ok());
+}
+
+#[test]
+fn test_config_command_sources() {
+    let builder = ConfigBuilder::minimal().add_service_with_paths("test", vec![]);
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let backup = config.services.get_mut("test").unwrap().config.as_mut().unwrap();
+    backup.commands.push(CommandSource {
+        name: "crontab".to_string(),
+        command: "crontab -l".to_string(),
+        filename: None,
+        timeout_seconds: None,
+    });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let loaded = load_config(&config_path).unwrap();
+    let commands = &loaded.services["test"].config.as_ref().unwrap().commands;
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].stdin_filename(), "crontab.txt");
+
+    // Two sources can't be stored under the same file name
+    let backup = config.services.get_mut("test").unwrap().config.as_mut().unwrap();
+    backup.commands.push(CommandSource {
+        name: "crontab-root".to_string(),
+        command: "sudo crontab -l".to_string(),
+        filename: Some("crontab.txt".to_string()),
+        timeout_seconds: None,
+    });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("crontab.txt"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_retry_policy_precedence() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("flaky", Profile {
+            targets: vec!["local".to_string()],
+            retries: Some(3),
+            retry_delay_seconds: Some(10),
+            ..Default::default()
+        })
+        .add_service("plain")
+        .add_service("remote");
+    let (mut config, temp_dir) = builder.persist();
+    config.global.retries = 1;
+    let remote = config.services.get_mut("remote").unwrap();
+    remote.profile = Some("flaky".to_string());
+    remote.retry_delay_seconds = Some(5);
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+
+    let plain = &resolved["plain"].retry;
+    assert_eq!((plain.retries, plain.delay_seconds), (1, 30));
+
+    let remote = &resolved["remote"].retry;
+    assert_eq!((remote.retries, remote.delay_seconds), (3, 5));
+    assert_eq!(remote.backoff_multiplier, 2.0);
+
+    config.global.retry_backoff_multiplier = 0.5;
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("retry_backoff_multiplier"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_snapshot_tags() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("tagged", Profile {
+            targets: vec!["local".to_string()],
+            tags: vec!["nightly".to_string(), "db".to_string()],
+            ..Default::default()
+        })
+        .add_service("plain")
+        .add_service("profiled");
+    let (mut config, temp_dir) = builder.persist();
+    let profiled = config.services.get_mut("profiled").unwrap();
+    profiled.profile = Some("tagged".to_string());
+    profiled.tags = vec!["db".to_string(), "postgres".to_string()];
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+
+    assert!(resolved["plain"].tags.is_empty());
+    assert_eq!(resolved["profiled"].tags, vec!["nightly", "db", "postgres"]);
+
+    config.services.get_mut("plain").unwrap().tags = vec!["a,b".to_string()];
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("tag"), "unexpected error: {}", err);
+
+    // Tags given to `run --tag` follow the same rule
+    assert_eq!(parse_tag("pre-upgrade").unwrap(), "pre-upgrade");
+    assert!(parse_tag("upgrade, then migrate").is_err());
+    assert!(parse_tag(" ").is_err());
+}
+
+#[test]
+fn test_config_hostname() {
+    let builder = ConfigBuilder::minimal().add_service("plain").add_service("moved");
+    let (mut config, temp_dir) = builder.persist();
+    config.global.hostname = Some("nas".to_string());
+    config.services.get_mut("moved").unwrap().hostname = Some("old-nas".to_string());
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+
+    assert_eq!(resolved["plain"].hostname.as_deref(), Some("nas"));
+    assert_eq!(resolved["moved"].hostname.as_deref(), Some("old-nas"));
+
+    config.services.get_mut("plain").unwrap().hostname = Some("my host".to_string());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("hostname"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_exclude_options() {
+    let builder = ConfigBuilder::minimal().add_service_with_paths("files", vec!["data".to_string()]);
+    let (mut config, temp_dir) = builder.persist();
+    config.global.exclude_larger_than = Some("2G".to_string());
+    config.global.one_file_system = true;
+    let backup = config.services.get_mut("files").unwrap().config.as_mut().unwrap();
+    backup.iexcludes = vec!["*.JPG".to_string()];
+    backup.exclude_files = vec!["excludes.txt".to_string()];
+    backup.one_file_system = Some(false);
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    let files = loaded.services["files"].config.as_ref().unwrap();
+    assert_eq!(files.iexcludes, vec!["*.JPG"]);
+    assert_eq!(files.exclude_files, vec!["excludes.txt"]);
+    assert_eq!(files.one_file_system, Some(false));
+    assert_eq!(loaded.global.exclude_larger_than.as_deref(), Some("2G"));
+
+    config.services.get_mut("files").unwrap().config.as_mut().unwrap().exclude_larger_than = Some("big".to_string());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("exclude_larger_than"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_bandwidth_limits() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("offsite", Profile {
+            targets: vec!["local".to_string()],
+            limit_upload_kbps: Some(2000),
+            ..Default::default()
+        })
+        .add_service("plain")
+        .add_service("throttled");
+    let (mut config, temp_dir) = builder.persist();
+    config.services.get_mut("throttled").unwrap().profile = Some("offsite".to_string());
+    config.global.limit_download_kbps = Some(5000);
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+
+    assert_eq!(resolved["plain"].limit_upload_kbps, None);
+    assert_eq!(resolved["throttled"].limit_upload_kbps, Some(2000));
+
+    config.destinations.get_mut("local").unwrap().limit_upload_kbps = Some(0);
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("bandwidth"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_destination_compression() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let local = config.destinations.get_mut("local").unwrap();
+    local.compression = Some(RepositoryCompression::Max);
+    local.pack_size_mb = Some(64);
+
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("compression = \"max\""));
+    fs::write(&config_path, toml_str).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.destinations["local"].compression, Some(RepositoryCompression::Max));
+    assert_eq!(loaded.destinations["local"].pack_size_mb, Some(64));
+
+    config.destinations.get_mut("local").unwrap().pack_size_mb = Some(512);
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("pack_size_mb"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_post_backup_verify() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("checked", Profile {
+            targets: vec!["local".to_string()],
+            post_backup_verify: Some(PostBackupVerify::Metadata),
+            ..Default::default()
+        })
+        .add_service("plain")
+        .add_service("profiled")
+        .add_service("sampled");
+    let (mut config, temp_dir) = builder.persist();
+    config.services.get_mut("profiled").unwrap().profile = Some("checked".to_string());
+    let sampled = config.services.get_mut("sampled").unwrap();
+    sampled.profile = Some("checked".to_string());
+    sampled.post_backup_verify = Some(PostBackupVerify::Sample);
+
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("post_backup_verify = \"sample\""));
+    fs::write(&config_path, toml_str).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+
+    assert_eq!(resolved["plain"].post_backup_verify, PostBackupVerify::Off);
+    assert_eq!(resolved["profiled"].post_backup_verify, PostBackupVerify::Metadata);
+    assert_eq!(resolved["sampled"].post_backup_verify, PostBackupVerify::Sample);
+}
+
+#[test]
+fn test_config_stop_during_backup_needs_compose_stack() {
+    let builder = ConfigBuilder::minimal().add_service_with_paths("app", vec![]);
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let backup = config.services.get_mut("app").unwrap().config.as_mut().unwrap();
+    backup.stop_during_backup = true;
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("compose_project or compose_file"), "unexpected error: {}", err);
+
+    let backup = config.services.get_mut("app").unwrap().config.as_mut().unwrap();
+    backup.compose_file = Some("app/docker-compose.yml".to_string());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert!(loaded.services["app"].config.as_ref().unwrap().stop_during_backup);
+}
+
+#[test]
+fn test_config_human_durations_and_sizes() {
+    let builder = ConfigBuilder::new();
+    let password_file = builder.password_file().to_path_buf()
+        .to_string_lossy().replace('\\', "/");
+    let docker_base = builder.temp_dir().join("docker")
+        .to_string_lossy().replace('\\', "/");
+    let backup_path = builder.temp_dir().join("backups")
+        .to_string_lossy().replace('\\', "/");
+
+    let (_, temp_dir) = builder.persist();
+
+    let config_content = format!(r#"
+[global]
+restic_password_file = "{}"
+docker_base = "{}"
+default_timeout_seconds = "2h30m"
+long_running_threshold_minutes = "3h"
+retry_delay_seconds = 45
+log_max_size_mb = "1G"
+
+[destinations.local]
+type = "local"
+url = "{}"
+
+[services.test]
+schedule = "0 2 * * *"
+targets = ["local"]
+timeout_seconds = "90m"
+"#,
+        password_file,
+        docker_base,
+        backup_path
+    );
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, config_content).unwrap();
+
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.global.default_timeout_seconds, 9000);
+    assert_eq!(config.global.long_running_threshold_minutes, 180);
+    assert_eq!(config.global.retry_delay_seconds, 45);
+    assert_eq!(config.global.log_max_size_mb, 1024);
+    assert_eq!(config.services["test"].timeout_seconds, Some(5400));
+
+    // Invalid values name the problem
+    let content = fs::read_to_string(&config_path).unwrap().replace("\"90m\"", "\"soon\"");
+    fs::write(&config_path, content).unwrap();
+    let err = format!("{:#}", load_config(&config_path).unwrap_err());
+    assert!(err.contains("Invalid duration 'soon'"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_config_pause_containers() {
+    let builder = ConfigBuilder::minimal().add_service_with_paths("db", vec![]);
+    let (config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_str = toml::to_string_pretty(&config).unwrap()
+        + "\n[services.db.config.pause_containers]\nlabel = \"backup.pause=db\"\nmode = \"stop\"\nmax_pause_seconds = \"5m\"\n";
+    fs::write(&config_path, toml_str).unwrap();
+
+    let loaded = load_config(&config_path).unwrap();
+    let pause = loaded.services["db"].config.as_ref().unwrap().pause_containers.as_ref().unwrap();
+    assert_eq!(pause.label, "backup.pause=db");
+    assert_eq!(pause.mode, PauseMode::Stop);
+    assert_eq!(pause.max_pause_seconds, 300);
+}
+
+#[test]
+fn test_config_experimental_flags() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    // Disabled unless the section says otherwise
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert!(!loaded.experimental.parallel_backups);
+    assert!(loaded.experimental.enabled().is_empty());
+
+    config.experimental.parallel_backups = true;
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("[experimental]"));
+    fs::write(&config_path, toml_str).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert!(loaded.experimental.parallel_backups);
+    assert_eq!(loaded.experimental.enabled(), vec!["parallel_backups"]);
+
+    config.experimental.stream_volumes = true;
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.experimental.enabled(), vec!["parallel_backups", "stream_volumes"]);
+}
+
+#[test]
+fn test_config_docker_backend() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert_eq!(load_config(&config_path).unwrap().global.docker_backend, DockerBackend::Cli);
+
+    config.global.docker_backend = DockerBackend::Api;
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("docker_backend = \"api\""));
+    fs::write(&config_path, toml_str).unwrap();
+    assert_eq!(load_config(&config_path).unwrap().global.docker_backend, DockerBackend::Api);
+
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap().replace("\"api\"", "\"socket\"")).unwrap();
+    assert!(load_config(&config_path).is_err());
+}
+
+#[test]
+fn test_config_container_runtime() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert_eq!(load_config(&config_path).unwrap().global.container_runtime, ContainerRuntime::Docker);
+
+    config.global.container_runtime = ContainerRuntime::Podman;
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("container_runtime = \"podman\""));
+    fs::write(&config_path, toml_str).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.global.container_runtime, ContainerRuntime::Podman);
+    assert_eq!(loaded.global.container_runtime.binary(), "podman");
+}
+
+#[test]
+fn test_config_mqtt_integration() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).unwrap().integrations.mqtt.is_none());
+
+    // Only the host is required
+    fs::write(
+        &config_path,
+        format!("{}\n[integrations.mqtt]\nhost = \"broker.lan\"\n", toml::to_string_pretty(&config).unwrap()),
+    )
+    .unwrap();
+    let mqtt = load_config(&config_path).unwrap().integrations.mqtt.unwrap();
+    assert_eq!(mqtt.port, 1883);
+    assert_eq!(mqtt.topic_prefix, "restic-manager");
+    assert_eq!(mqtt.discovery_prefix, "homeassistant");
+
+    config.integrations.mqtt = Some(MqttConfig { password: Some("secret".to_string()), ..mqtt.clone() });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+
+    config.integrations.mqtt = Some(MqttConfig { host: " ".to_string(), ..mqtt });
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+}
+
+#[test]
+fn test_config_discovery() {
+    let builder = ConfigBuilder::minimal().add_service("app");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    config.discovery = DiscoveryConfig { enabled: true, targets: vec!["offsite".to_string()], ..Default::default() };
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+
+    config.discovery.targets = vec!["local".to_string()];
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let mut config = load_config(&config_path).unwrap();
+
+    // Configured services win; discovered ones must still be valid
+    let volume = |name: &str, targets: &str| restic_manager::utils::docker::VolumeInfo {
+        name: name.to_string(),
+        labels: [("restic-manager.enable", "true"), ("restic-manager.targets", targets)]
+            .iter()
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    };
+    let volumes = vec![volume("app", ""), volume("media", ""), volume("photos", "offsite")];
+    let discovered = services_from_labels(&config.discovery, &[], &volumes);
+    assert_eq!(discovered.services.len(), 3);
+
+    let added = merge_discovered(&mut config, discovered);
+    assert_eq!(added, vec!["media"]);
+    assert_eq!(config.services["app"].description, "Test service: app");
+    assert_eq!(config.services["media"].config.as_ref().unwrap().volumes, vec!["media"]);
+    assert!(resolve_all_services(&config).is_ok());
+}
+
+#[test]
+fn test_config_time_budget() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("bulk", Profile {
+            targets: vec!["local".to_string()],
+            time_budget_minutes: Some(90),
+            ..Default::default()
+        })
+        .add_service("plain")
+        .add_service("media")
+        .add_service("photos");
+    let (mut config, temp_dir) = builder.persist();
+    config.services.get_mut("media").unwrap().profile = Some("bulk".to_string());
+    let photos = config.services.get_mut("photos").unwrap();
+    photos.profile = Some("bulk".to_string());
+    photos.time_budget_minutes = Some(30);
+
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap().replace("time_budget_minutes = 30", "time_budget_minutes = \"30m\"");
+    fs::write(&config_path, toml_str).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+    assert_eq!(resolved["plain"].time_budget_minutes, None);
+    assert_eq!(resolved["media"].time_budget_minutes, Some(90));
+    assert_eq!(resolved["photos"].time_budget_minutes, Some(30));
+
+    config.services.get_mut("plain").unwrap().time_budget_minutes = Some(0);
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+}
+
+#[test]
+fn test_config_max_backup_age() {
+    let builder = ConfigBuilder::minimal()
+        .add_profile("weekly", Profile {
+            targets: vec!["local".to_string()],
+            max_age_hours: Some(8 * 24),
+            ..Default::default()
+        })
+        .add_service("daily")
+        .add_service("archive")
+        .add_service("photos");
+    let (mut config, temp_dir) = builder.persist();
+    let daily = config.services.get_mut("daily").unwrap();
+    daily.schedule = "0 2 * * *".to_string();
+    daily.timeout_seconds = Some(3600);
+    config.services.get_mut("archive").unwrap().profile = Some("weekly".to_string());
+    let photos = config.services.get_mut("photos").unwrap();
+    photos.profile = Some("weekly".to_string());
+    photos.max_age_hours = Some(36);
+
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap().replace("max_age_hours = 36", "max_age_hours = \"36h\"");
+    fs::write(&config_path, toml_str).unwrap();
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+    let hours = |h: u64| std::time::Duration::from_secs(h * 3600);
+    // Derived from the schedule: a day between runs plus the timeout
+    assert_eq!(resolved["daily"].max_backup_age(), hours(25));
+    assert_eq!(resolved["archive"].max_backup_age(), hours(8 * 24));
+    assert_eq!(resolved["photos"].max_backup_age(), hours(36));
+
+    config.services.get_mut("daily").unwrap().max_age_hours = Some(0);
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+}
+
+#[test]
+fn test_config_containers() {
+    let builder = ConfigBuilder::minimal().add_service_with_volumes("app", vec!["app_db".to_string()]);
+    let (config, temp_dir) = builder.persist();
+
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("containers = []"));
+    fs::write(&config_path, toml_str.replace("containers = []", "containers = [\"app\", \"app-worker\"]")).unwrap();
+
+    let resolved = resolve_all_services(&load_config(&config_path).unwrap()).unwrap();
+    let backup = resolved["app"].config.as_ref().unwrap();
+    assert_eq!(backup.containers, vec!["app", "app-worker"]);
+    assert_eq!(backup.volumes, vec!["app_db"]);
+}
+
+#[test]
+fn test_config_prune_delay() {
+    let (mut config, temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    assert_eq!(config.global.prune_delay_days, 0);
+
+    config.global.prune_delay_days = 14;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert_eq!(load_config(&config_path).unwrap().global.prune_delay_days, 14);
+}
+
+#[test]
+fn test_config_volume_archive_compression() {
+    let (mut config, temp_dir) = ConfigBuilder::minimal().add_service("app").persist();
+    assert_eq!(config.global.volume_archive_compression, ArchiveCompression::Gzip);
+
+    config.global.volume_archive_compression = ArchiveCompression::None;
+    let config_path = temp_dir.path().join("config.toml");
+    let toml_str = toml::to_string_pretty(&config).unwrap();
+    assert!(toml_str.contains("volume_archive_compression = \"none\""));
+    fs::write(&config_path, toml_str.replace("\"none\"", "\"zstd\"")).unwrap();
+
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(loaded.global.volume_archive_compression, ArchiveCompression::Zstd);
+    assert_eq!(loaded.global.volume_archive_compression.extension(), "tar.zst");
+}
+
+#[test]
+fn test_config_custom_destination() {
+    let mut destination = Destination {
+        dest_type: DestinationType::Custom,
+        url: "backups:/restic".to_string(),
+        repository_prefix: Some("swift:".to_string()),
+        required_env: vec!["OS_AUTH_URL".to_string(), "RM_TEST_SWIFT_UNSET_TOKEN".to_string()],
+        ..Default::default()
+    };
+    destination.env.insert("OS_AUTH_URL".to_string(), "https://auth.example.com/v3".to_string());
+
+    let builder = ConfigBuilder::minimal()
+        .add_destination("swift", destination.clone())
+        .add_service("test");
+    let (mut config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+
+    // Required variables must be set
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("RM_TEST_SWIFT_UNSET_TOKEN"), "unexpected error: {}", err);
+
+    destination.env.insert("RM_TEST_SWIFT_UNSET_TOKEN".to_string(), "token".to_string());
+    config.destinations.insert("swift".to_string(), destination.clone());
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    let loaded = load_config(&config_path).unwrap();
+    assert_eq!(
+        build_repository_url(&loaded.destinations["swift"], "app", None),
+        "swift:backups:/restic/app"
+    );
+
+    // A custom backend needs its prefix
+    destination.repository_prefix = None;
+    config.destinations.insert("swift".to_string(), destination);
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+    assert!(load_config(&config_path).is_err());
+}
+
+#[test]
+fn test_config_destination_named_like_shared_keyring_entry() {
+    let builder = ConfigBuilder::minimal()
+        .add_destination("@shared", Destination { url: "/backups/shared".to_string(), ..Default::default() })
+        .add_service("test");
+    let (config, temp_dir) = builder.persist();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+    let err = load_config(&config_path).unwrap_err().to_string();
+    assert!(err.contains("reserved"), "unexpected error: {}", err);
+}
+
+/// Destination blocks of a TOML text, uncommented (`# [destinations.x]`
+/// examples are parsed too)
+fn destination_blocks(text: &str) -> Vec<String> {
+    let is_toml_line = |line: &str| {
+        line.starts_with('[')
+            || line.split_once(" = ").is_some_and(|(key, _)| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '"')
+            })
+    };
+
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        let uncommented = line.strip_prefix("# ").unwrap_or(line);
+        if uncommented.starts_with("[destinations.") {
+            let block = current.get_or_insert_with(String::new);
+            block.push_str(uncommented);
+            block.push('\n');
+        } else if current.is_some() && is_toml_line(uncommented) && !uncommented.starts_with('[') {
+            let block = current.as_mut().unwrap();
+            block.push_str(uncommented);
+            block.push('\n');
+        } else if let Some(block) = current.take() {
+            blocks.push(block);
+        }
+    }
+    blocks.extend(current);
+    blocks
+}
+
+#[test]
+fn test_documented_destinations_parse() {
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut checked = 0;
+
+    for file in ["README.md", "config.example.toml"] {
+        let text = fs::read_to_string(root.join(file)).unwrap();
+        for block in destination_blocks(&text) {
+            let value: toml::Value = toml::from_str(&block)
+                .unwrap_or_else(|e| panic!("{}: invalid TOML in\n{}\n{}", file, block, e));
+            for (name, destination) in value["destinations"].as_table().unwrap() {
+                destination
+                    .clone()
+                    .try_into::<Destination>()
+                    .unwrap_or_else(|e| panic!("{}: destination '{}' doesn't parse: {}", file, name, e));
+                checked += 1;
+            }
+        }
+    }
+
+    assert!(checked >= 10, "only {} documented destinations found", checked);
+}
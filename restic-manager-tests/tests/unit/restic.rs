@@ -4,7 +4,7 @@
 
 use test_utils::{sample_snapshot, sample_snapshots, MockResticOps, ResticOperations};
 use restic_manager::config::{Destination, DestinationType, RetentionPolicy};
-use restic_manager::utils::restic::{build_repository_url, ResticEnv};
+use restic_manager::utils::restic::{build_repository_url, format_bytes, CheckResult, RepoStats, ResticEnv, RestoreLimits};
 use std::path::PathBuf;
 use std::time::Duration;
 use tempfile::TempDir;
@@ -15,6 +15,7 @@ fn test_build_repository_url_with_trailing_slash() {
         dest_type: DestinationType::Sftp,
         url: "sftp://user@host/backups/".to_string(),
         description: "Test".to_string(),
+        ..Default::default()
     };
 
     let url = build_repository_url(&destination, "postgres", None);
@@ -27,6 +28,7 @@ fn test_build_repository_url_without_trailing_slash() {
         dest_type: DestinationType::Sftp,
         url: "sftp://user@host/backups".to_string(),
         description: "Test".to_string(),
+        ..Default::default()
     };
 
     let url = build_repository_url(&destination, "postgres", None);
@@ -39,6 +41,7 @@ fn test_build_repository_url_with_suffix() {
         dest_type: DestinationType::Local,
         url: "/tmp/backups".to_string(),
         description: "Test".to_string(),
+        ..Default::default()
     };
 
     let url = build_repository_url(&destination, "postgres", Some("-prod"));
@@ -51,6 +54,7 @@ fn test_build_repository_url_local() {
         dest_type: DestinationType::Local,
         url: "/var/backups".to_string(),
         description: "Test".to_string(),
+        ..Default::default()
     };
 
     let url = build_repository_url(&destination, "myservice", None);
@@ -183,7 +187,7 @@ fn test_mock_restic_ops_restore() {
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(60);
 
-    let result = mock.restore_snapshot(&env, "abc123", Some("/tmp/restore"), &[], timeout);
+    let result = mock.restore_snapshot(&env, "abc123", Some("/tmp/restore"), &[], &RestoreLimits::default(), timeout);
 
     assert!(result.is_ok());
     assert!(mock.restore_called());
@@ -199,7 +203,7 @@ fn test_mock_restic_ops_restore_failure() {
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(60);
 
-    let result = mock.restore_snapshot(&env, "abc123", None, &[], timeout);
+    let result = mock.restore_snapshot(&env, "abc123", None, &[], &RestoreLimits::default(), timeout);
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Mock restore failure"));
@@ -211,13 +215,13 @@ fn test_mock_restic_ops_check() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_check_result("no errors found");
+    let mock = MockResticOps::new().with_check_result(CheckResult::Ok);
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(300);
 
     let result = mock.check_repository(&env, false, timeout).unwrap();
 
-    assert_eq!(result, "no errors found");
+    assert_eq!(result, CheckResult::Ok);
 }
 
 #[test]
@@ -226,13 +230,19 @@ fn test_mock_restic_ops_stats() {
     let password_file = temp_dir.path().join("password");
     std::fs::write(&password_file, "test").unwrap();
 
-    let mock = MockResticOps::new().with_stats("2.5 GiB");
+    let mock = MockResticOps::new().with_stats(RepoStats {
+        total_size: 2_684_354_560,
+        total_file_count: 1200,
+        snapshots_count: 14,
+    });
     let env = ResticEnv::new(&password_file, "/tmp/repo");
     let timeout = Duration::from_secs(30);
 
     let stats = mock.get_stats(&env, timeout).unwrap();
 
-    assert_eq!(stats, "2.5 GiB");
+    assert_eq!(format_bytes(stats.total_size), "2.50 GiB");
+    assert_eq!(stats.total_file_count, 1200);
+    assert_eq!(stats.snapshots_count, 14);
 }
 
 #[test]
@@ -249,6 +259,7 @@ fn test_mock_restic_ops_apply_retention() {
         weekly: 4,
         monthly: 6,
         yearly: 1,
+        ..Default::default()
     };
 
     let result = mock.apply_retention(&env, &retention, timeout);
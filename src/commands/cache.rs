@@ -0,0 +1,107 @@
+//! `cache clean`: remove restic's local caches of repositories that
+//! weren't used for a while
+
+use super::{CommandContext, Outcome};
+use crate::config::Config;
+use crate::utils::restic;
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// `cache` subcommands
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Remove caches of repositories not used for a while (restic cache --cleanup)
+    Clean {
+        /// Remove caches unused for this many days
+        #[arg(long, default_value_t = 30)]
+        max_age: u32,
+    },
+}
+
+/// Run a cache subcommand
+pub fn cache(ctx: &CommandContext, action: &CacheAction) -> Result<Outcome> {
+    match action {
+        CacheAction::Clean { max_age } => clean(&ctx.config, *max_age),
+    }
+}
+
+/// Clean every cache directory in use (the global one and destination
+/// overrides)
+fn clean(config: &Config, max_age_days: u32) -> Result<Outcome> {
+    let mut failed = 0;
+    for cache_dir in cache_dirs(config) {
+        let location = cache_dir
+            .as_ref()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|| "default location".to_string());
+        say!("Cache: {}", location);
+
+        match restic::cleanup_cache(cache_dir.as_deref(), max_age_days, Duration::from_secs(300)) {
+            Ok(report) if report.is_empty() => say!("  {} Nothing to clean\n", output::ok()),
+            Ok(report) => say!("  {} {}\n", output::ok(), report),
+            Err(e) => {
+                say_error!("  {} {:#}\n", output::fail(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed == 0 {
+        Ok(Outcome::Success)
+    } else {
+        Ok(Outcome::Failed(1))
+    }
+}
+
+/// Cache directories restic uses for the configured destinations (`None`
+/// is restic's default location)
+fn cache_dirs(config: &Config) -> Vec<Option<PathBuf>> {
+    let mut dirs = vec![config.global.restic_cache_dir.clone()];
+    let mut names: Vec<&String> = config.destinations.keys().collect();
+    names.sort();
+    for name in names {
+        let destination = &config.destinations[name];
+        if let Some(dir) = destination.env.get("RESTIC_CACHE_DIR") {
+            let dir = Some(PathBuf::from(dir));
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Destination;
+
+    #[test]
+    fn test_cache_dirs() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [global]
+            restic_password_file = "/tmp/password"
+            docker_base = "/srv"
+            restic_cache_dir = "/var/cache/restic"
+            "#,
+        )
+        .unwrap();
+        let destination = |dir: Option<&str>| Destination {
+            env: dir.map(|d| ("RESTIC_CACHE_DIR".to_string(), d.to_string())).into_iter().collect(),
+            ..Default::default()
+        };
+        config.destinations.insert("local".to_string(), destination(None));
+        config.destinations.insert("usb".to_string(), destination(Some("/mnt/usb/cache")));
+        config.destinations.insert("nas".to_string(), destination(Some("/var/cache/restic")));
+
+        assert_eq!(
+            cache_dirs(&config),
+            vec![Some(PathBuf::from("/var/cache/restic")), Some(PathBuf::from("/mnt/usb/cache"))]
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! `check-destinations`: reachability and latency of every repository
+//!
+//! Each repository of the enabled services is opened with
+//! `restic cat config` (a few concurrently). Failures are classified into
+//! authentication errors, missing repositories, timeouts and unreachable
+//! backends, and the response time is reported per repository and
+//! averaged per destination, e.g. before trusting a new offsite target.
+
+use super::{exit_code, exit_code_of, CommandContext, Outcome};
+use crate::utils::output;
+use crate::utils::restic;
+use crate::{say, say_error};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Repositories checked at the same time
+const CONCURRENCY: usize = 4;
+
+/// Timeout of opening one repository
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Outcome of opening a repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reachability {
+    Ok,
+    /// Wrong password or rejected backend credentials
+    AuthFailed,
+    /// The backend answered, but there is no repository (run `setup`)
+    NotInitialized,
+    TimedOut,
+    Unreachable,
+}
+
+impl Reachability {
+    fn describe(self) -> &'static str {
+        match self {
+            Reachability::Ok => "reachable",
+            Reachability::AuthFailed => "authentication failed",
+            Reachability::NotInitialized => "not initialized",
+            Reachability::TimedOut => "timed out",
+            Reachability::Unreachable => "unreachable",
+        }
+    }
+}
+
+/// Result of checking one repository
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoCheck {
+    pub service: String,
+    pub destination: String,
+    pub reachability: Reachability,
+    /// Time until restic answered, in milliseconds
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Checks of one destination summed up
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DestinationSummary {
+    pub destination: String,
+    pub repositories: usize,
+    pub reachable: usize,
+    /// Average latency of the reachable repositories
+    pub average_latency_ms: Option<u64>,
+}
+
+/// Check the repositories of the enabled services, on one destination or all
+pub fn check_destinations(ctx: &CommandContext, destination: Option<&str>, json: bool) -> Result<Outcome> {
+    if let Some(name) = destination {
+        ctx.destination(name)?;
+    }
+    let mut repos: Vec<(String, String)> = ctx
+        .resolved
+        .values()
+        .filter(|s| s.enabled)
+        .flat_map(|s| s.targets.iter().map(|t| (s.name.clone(), t.clone())))
+        .filter(|(_, t)| destination.is_none_or(|d| d == t))
+        .collect();
+    repos.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)));
+
+    if !json {
+        say!("Checking {} repositories...\n", repos.len());
+    }
+    let results = collect(ctx, repos);
+    let summaries = summarize(&results);
+    let failed = results.iter().filter(|r| r.reachability != Reachability::Ok).count();
+
+    if json {
+        let output = serde_json::json!({ "repositories": results, "destinations": summaries });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print_tables(&results, &summaries);
+    }
+
+    if failed == 0 {
+        Ok(Outcome::Success)
+    } else {
+        if !json {
+            say_error!("\n{} {} of {} repositories could not be opened", output::fail(), failed, results.len());
+        }
+        Ok(Outcome::Failed(exit_code::RESTIC))
+    }
+}
+
+/// Check the repositories, `CONCURRENCY` at a time, keeping their order
+fn collect(ctx: &CommandContext, repos: Vec<(String, String)>) -> Vec<RepoCheck> {
+    let queue = Mutex::new(repos.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..CONCURRENCY {
+            scope.spawn(|| loop {
+                let Some((index, (service, destination))) = queue.lock().unwrap_or_else(|e| e.into_inner()).next()
+                else {
+                    break;
+                };
+                let env = ctx.service(&service).and_then(|service| ctx.repository_env(service, &destination));
+                let start = Instant::now();
+                let result = env.and_then(|env| restic::check_access(&env, CHECK_TIMEOUT));
+                let latency_ms = start.elapsed().as_millis() as u64;
+                let (reachability, error) = match result {
+                    Ok(()) => (Reachability::Ok, None),
+                    Err(e) => (classify(&e), Some(format!("{:#}", e))),
+                };
+                results.lock().unwrap_or_else(|e| e.into_inner()).push((
+                    index,
+                    RepoCheck { service, destination, reachability, latency_ms, error },
+                ));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, check)| check).collect()
+}
+
+/// Why a repository couldn't be opened
+fn classify(error: &anyhow::Error) -> Reachability {
+    if exit_code_of(error) == exit_code::TIMEOUT {
+        return Reachability::TimedOut;
+    }
+    classify_message(&format!("{:#}", error))
+}
+
+fn classify_message(message: &str) -> Reachability {
+    let message = message.to_lowercase();
+    if ["wrong password", "no key found", "access denied", "permission denied", "unauthorized", "403"]
+        .iter()
+        .any(|m| message.contains(m))
+    {
+        Reachability::AuthFailed
+    } else if ["is there a repository at the following location", "repository does not exist", "unable to open config file"]
+        .iter()
+        .any(|m| message.contains(m))
+    {
+        Reachability::NotInitialized
+    } else {
+        Reachability::Unreachable
+    }
+}
+
+/// Sum up the checks per destination
+pub fn summarize(results: &[RepoCheck]) -> Vec<DestinationSummary> {
+    let mut summaries: BTreeMap<&str, (DestinationSummary, u64)> = BTreeMap::new();
+    for result in results {
+        let (summary, latency_total) = summaries.entry(&result.destination).or_insert_with(|| {
+            (DestinationSummary { destination: result.destination.clone(), ..Default::default() }, 0)
+        });
+        summary.repositories += 1;
+        if result.reachability == Reachability::Ok {
+            summary.reachable += 1;
+            *latency_total += result.latency_ms;
+        }
+    }
+
+    summaries
+        .into_values()
+        .map(|(mut summary, latency_total)| {
+            summary.average_latency_ms = (summary.reachable > 0).then(|| latency_total / summary.reachable as u64);
+            summary
+        })
+        .collect()
+}
+
+fn print_tables(results: &[RepoCheck], summaries: &[DestinationSummary]) {
+    say!("{:<12} {:<20} {:>9}  {}", "Destination", "Service", "Latency", "Result");
+    say!("{}", "-".repeat(70));
+    for result in results {
+        let marker = if result.reachability == Reachability::Ok { output::ok() } else { output::fail() };
+        let detail = match result.error {
+            Some(ref error) => format!(
+                "{}: {}",
+                result.reachability.describe(),
+                error.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim()
+            ),
+            None => result.reachability.describe().to_string(),
+        };
+        say!(
+            "{:<12} {:<20} {:>7}ms  {} {}",
+            result.destination, result.service, result.latency_ms, marker, detail
+        );
+    }
+
+    if summaries.is_empty() {
+        return;
+    }
+
+    say!("\nPer destination:");
+    say!("{:<20} {:>12} {:>12}", "Destination", "Reachable", "Avg latency");
+    say!("{}", "-".repeat(46));
+    for summary in summaries {
+        say!(
+            "{:<20} {:>12} {:>12}",
+            summary.destination,
+            format!("{}/{}", summary.reachable, summary.repositories),
+            summary.average_latency_ms.map_or_else(|| "-".to_string(), |ms| format!("{}ms", ms))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(destination: &str, reachability: Reachability, latency_ms: u64) -> RepoCheck {
+        RepoCheck {
+            service: "app".to_string(),
+            destination: destination.to_string(),
+            reachability,
+            latency_ms,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_message() {
+        assert_eq!(
+            classify_message("Opening the repository failed: Fatal: wrong password or no key found"),
+            Reachability::AuthFailed
+        );
+        assert_eq!(
+            classify_message("Fatal: unable to open config file: Stat: 403 Forbidden\nIs there a repository at the following location?"),
+            Reachability::AuthFailed
+        );
+        assert_eq!(
+            classify_message("Fatal: unable to open config file: <config/> does not exist\nIs there a repository at the following location?"),
+            Reachability::NotInitialized
+        );
+        assert_eq!(
+            classify_message("Fatal: unable to open repository at sftp:backup@nas:/srv: ssh: connect to host nas port 22: No route to host"),
+            Reachability::Unreachable
+        );
+    }
+
+    #[test]
+    fn test_classify_timeout() {
+        let error = anyhow::Error::new(crate::error::BackupError::Timeout(Duration::from_secs(60)));
+        assert_eq!(classify(&error), Reachability::TimedOut);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let summaries = summarize(&[
+            check("local", Reachability::Ok, 10),
+            check("local", Reachability::Ok, 30),
+            check("offsite", Reachability::AuthFailed, 900),
+        ]);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].reachable, 2);
+        assert_eq!(summaries[0].average_latency_ms, Some(20));
+        assert_eq!(summaries[1].repositories, 1);
+        assert_eq!(summaries[1].reachable, 0);
+        assert_eq!(summaries[1].average_latency_ms, None);
+    }
+}
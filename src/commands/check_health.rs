@@ -0,0 +1,241 @@
+//! `check-health`: backup freshness as a Nagios/Icinga plugin
+//!
+//! Prints a single status line with performance data (age and snapshot
+//! count per destination) and exits with the plugin codes: 0 OK, 1 WARNING,
+//! 2 CRITICAL, 3 UNKNOWN. Thresholds default to the service's expected
+//! backup age (see `status`) and twice that. A destination without
+//! snapshots, or whose repository can't be read, is critical.
+
+use super::{CommandContext, Outcome};
+use crate::config::ResolvedServiceConfig;
+use crate::managers::notification::format_duration;
+use anyhow::Result;
+use std::time::Duration;
+
+/// Timeout of listing the snapshots of one repository
+const LIST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Plugin state, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PluginState {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl PluginState {
+    /// Exit code of the state by the plugin conventions
+    pub fn exit_code(self) -> i32 {
+        match self {
+            PluginState::Ok => 0,
+            PluginState::Warning => 1,
+            PluginState::Critical => 2,
+            PluginState::Unknown => 3,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PluginState::Ok => "OK",
+            PluginState::Warning => "WARNING",
+            PluginState::Critical => "CRITICAL",
+            PluginState::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// State of a latest backup of this age
+    fn of_age(age: Duration, warn: Duration, crit: Duration) -> Self {
+        if age >= crit {
+            PluginState::Critical
+        } else if age >= warn {
+            PluginState::Warning
+        } else {
+            PluginState::Ok
+        }
+    }
+}
+
+/// Result of checking one destination
+#[derive(Debug, Clone)]
+struct DestinationCheck {
+    destination: String,
+    state: PluginState,
+    snapshots: usize,
+    /// Age of the latest snapshot
+    age: Option<Duration>,
+    /// Why the destination couldn't be checked
+    problem: Option<String>,
+}
+
+/// Check the age of a service's latest backup on its destinations
+pub fn check_health(
+    ctx: &CommandContext,
+    service: &str,
+    destination: Option<&str>,
+    warn: Option<Duration>,
+    crit: Option<Duration>,
+) -> Result<Outcome> {
+    let service_config = ctx.service(service)?;
+    let targets = match super::choose_destination(service, service_config, destination)? {
+        Some(dest_name) => vec![dest_name],
+        None => service_config.targets.clone(),
+    };
+
+    let max_age = service_config.max_backup_age();
+    let warn = warn.unwrap_or(max_age);
+    let crit = crit.unwrap_or(max_age * 2);
+    if warn >= crit {
+        anyhow::bail!("--warn must be shorter than --crit");
+    }
+
+    let checks: Vec<DestinationCheck> = targets
+        .iter()
+        // Listing snapshots would read from the archive tier
+        .filter(|dest_name| !ctx.config.destinations.get(*dest_name).is_some_and(|d| d.cold_storage))
+        .map(|dest_name| check_destination(ctx, service_config, dest_name, warn, crit))
+        .collect();
+
+    let (state, line) = status_line(service, &checks, warn, crit);
+    println!("{}", line);
+    Ok(match state {
+        PluginState::Ok => Outcome::Success,
+        state => Outcome::Failed(state.exit_code()),
+    })
+}
+
+fn check_destination(
+    ctx: &CommandContext,
+    service: &ResolvedServiceConfig,
+    dest_name: &str,
+    warn: Duration,
+    crit: Duration,
+) -> DestinationCheck {
+    let mut check = DestinationCheck {
+        destination: dest_name.to_string(),
+        state: PluginState::Critical,
+        snapshots: 0,
+        age: None,
+        problem: None,
+    };
+
+    match ctx.repository_env(service, dest_name).and_then(|env| ctx.ops.list_snapshots(&env, LIST_TIMEOUT)) {
+        Ok(snapshots) => {
+            check.snapshots = snapshots.len();
+            // Snapshots are listed oldest first
+            match snapshots.last().and_then(|latest| chrono::DateTime::parse_from_rfc3339(&latest.time).ok()) {
+                Some(taken) => {
+                    let age = chrono::Utc::now().signed_duration_since(taken).to_std().unwrap_or_default();
+                    check.age = Some(age);
+                    check.state = PluginState::of_age(age, warn, crit);
+                }
+                None => check.problem = Some("no snapshots".to_string()),
+            }
+        }
+        Err(e) => {
+            let message = format!("{:#}", e);
+            check.problem = Some(message.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim().to_string());
+        }
+    }
+    check
+}
+
+/// Worst state and the plugin output line
+/// (`BACKUP <STATE> - <summary> | <perfdata>`)
+fn status_line(service: &str, checks: &[DestinationCheck], warn: Duration, crit: Duration) -> (PluginState, String) {
+    if checks.is_empty() {
+        return (
+            PluginState::Unknown,
+            format!("BACKUP UNKNOWN - {}: no destinations to check (cold storage only)", service),
+        );
+    }
+
+    let state = checks.iter().map(|check| check.state).max().unwrap_or(PluginState::Unknown);
+    let details: Vec<String> = checks
+        .iter()
+        .map(|check| match (&check.problem, check.age) {
+            (Some(problem), _) => format!("{} {}", check.destination, problem),
+            (None, Some(age)) => format!("{} {} old", check.destination, format_duration(age.as_secs())),
+            (None, None) => check.destination.clone(),
+        })
+        .collect();
+    let perfdata: Vec<String> = checks
+        .iter()
+        .flat_map(|check| {
+            [
+                format!(
+                    "'{}_age'={};{};{};0",
+                    check.destination,
+                    check.age.map_or_else(|| "U".to_string(), |age| format!("{}s", age.as_secs())),
+                    warn.as_secs(),
+                    crit.as_secs()
+                ),
+                format!("'{}_snapshots'={};;;0", check.destination, check.snapshots),
+            ]
+        })
+        .collect();
+
+    let line = format!(
+        "BACKUP {} - {}: {} (warn {}, crit {}) | {}",
+        state.label(),
+        service,
+        details.join(", "),
+        format_duration(warn.as_secs()),
+        format_duration(crit.as_secs()),
+        perfdata.join(" ")
+    );
+    (state, line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(destination: &str, state: PluginState, age_secs: Option<u64>, problem: Option<&str>) -> DestinationCheck {
+        DestinationCheck {
+            destination: destination.to_string(),
+            state,
+            snapshots: if age_secs.is_some() { 14 } else { 0 },
+            age: age_secs.map(Duration::from_secs),
+            problem: problem.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_plugin_state_of_age() {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+        assert_eq!(PluginState::of_age(hours(2), hours(26), hours(50)), PluginState::Ok);
+        assert_eq!(PluginState::of_age(hours(26), hours(26), hours(50)), PluginState::Warning);
+        assert_eq!(PluginState::of_age(hours(60), hours(26), hours(50)), PluginState::Critical);
+        assert_eq!(PluginState::Unknown.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_status_line() {
+        let warn = Duration::from_secs(26 * 3600);
+        let crit = Duration::from_secs(50 * 3600);
+        let (state, line) = status_line(
+            "app",
+            &[
+                check("local", PluginState::Ok, Some(7200), None),
+                check("offsite", PluginState::Warning, Some(100_000), None),
+            ],
+            warn,
+            crit,
+        );
+        assert_eq!(state, PluginState::Warning);
+        assert_eq!(
+            line,
+            "BACKUP WARNING - app: local 2h old, offsite 27h 46m old (warn 26h, crit 50h) | \
+             'local_age'=7200s;93600;180000;0 'local_snapshots'=14;;;0 \
+             'offsite_age'=100000s;93600;180000;0 'offsite_snapshots'=14;;;0"
+        );
+
+        let (state, line) = status_line("app", &[check("local", PluginState::Critical, None, Some("no snapshots"))], warn, crit);
+        assert_eq!(state, PluginState::Critical);
+        assert!(line.starts_with("BACKUP CRITICAL - app: local no snapshots"));
+        assert!(line.ends_with("'local_age'=U;93600;180000;0 'local_snapshots'=0;;;0"));
+
+        assert_eq!(status_line("app", &[], warn, crit).0, PluginState::Unknown);
+    }
+}
@@ -0,0 +1,81 @@
+//! `context`: manage named configuration contexts (no config needed)
+
+use super::Outcome;
+use crate::config::contexts::Contexts;
+use crate::utils::output;
+use crate::say;
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// `context` subcommands
+#[derive(Subcommand)]
+pub enum ContextAction {
+    /// List registered contexts
+    List,
+
+    /// Switch the current context
+    Use {
+        /// Context name
+        name: String,
+    },
+
+    /// Register a context (or update its config path)
+    Add {
+        /// Context name
+        name: String,
+
+        /// Path to the context's configuration file
+        config: PathBuf,
+
+        /// Optional description
+        #[arg(short, long, default_value = "")]
+        description: String,
+    },
+}
+
+/// Run a context subcommand
+pub fn context(action: &ContextAction) -> Result<Outcome> {
+    let path = Contexts::default_path();
+    let mut contexts = Contexts::load(&path)?;
+
+    match action {
+        ContextAction::List => {
+            if contexts.contexts.is_empty() {
+                say!("No contexts registered.");
+                say!("Add one with: restic-manager context add <name> <config-path>");
+                return Ok(Outcome::Success);
+            }
+
+            for (name, entry) in &contexts.contexts {
+                let marker = if contexts.current.as_deref() == Some(name.as_str()) { "*" } else { " " };
+                print!("{} {:<20} {}", marker, name, entry.config.display());
+                if !entry.description.is_empty() {
+                    print!("  ({})", entry.description);
+                }
+                say!();
+            }
+        }
+        ContextAction::Use { name } => {
+            contexts.use_context(name)?;
+            contexts.save(&path)?;
+            say!("{} Switched to context '{}'", output::ok(), name);
+        }
+        ContextAction::Add { name, config, description } => {
+            // Store absolute paths so the context works from any directory (and in cron)
+            let config_path = std::path::absolute(config)?;
+            if !config_path.exists() {
+                say!("{} {} does not exist yet", output::warn(), config_path.display());
+            }
+
+            contexts.add(name, config_path.clone(), description.clone());
+            if contexts.current.is_none() {
+                contexts.current = Some(name.clone());
+            }
+            contexts.save(&path)?;
+            say!("{} Registered context '{}' -> {}", output::ok(), name, config_path.display());
+        }
+    }
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,488 @@
+//! `doctor`: pre-flight checks of the host
+//!
+//! Checks what scheduled backups depend on (the restic binary, passwords,
+//! the work and log directories, Docker and the configured volumes, the
+//! backed up paths, the cron jobs and the repositories) and prints a
+//! pass/warn/fail table. `--fix` applies the safe corrections: tightening
+//! the permissions of the password file and creating missing directories.
+//! Repositories and the crontab are left to `setup`.
+
+use super::{exit_code, CommandContext, Outcome};
+use crate::config::{BackupConfig, Config, GlobalConfig, PasswordSource};
+use crate::utils::docker_ops;
+use crate::utils::output;
+use crate::utils::{command, cron, humanize, restic, restic_installer, secrets};
+use crate::{say, say_error};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Oldest supported restic (repository format 2, `--compression`, `--pack-size`)
+const MIN_RESTIC_VERSION: (u32, u32, u32) = (0, 14, 0);
+
+/// Free space below which the work and log directories are warned about
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Timeout of each Docker, password command and repository check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Result of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Correction `--fix` can apply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Make a file readable by its owner only
+    RestrictPermissions(PathBuf),
+    /// Create a missing directory
+    CreateDir(PathBuf),
+}
+
+impl Fix {
+    fn apply(&self) -> Result<String> {
+        match self {
+            Fix::RestrictPermissions(path) => {
+                restrict_permissions(path)?;
+                Ok(format!("permissions of {} set to 600", path.display()))
+            }
+            Fix::CreateDir(path) => {
+                fs::create_dir_all(path).context(format!("Failed to create {}", path.display()))?;
+                Ok(format!("created {}", path.display()))
+            }
+        }
+    }
+}
+
+/// One row of the report
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<Fix>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status, detail: detail.into(), fix: None }
+    }
+
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, Status::Pass, detail)
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, Status::Warn, detail)
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, Status::Fail, detail)
+    }
+
+    fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Run all checks and print the report; fails if any check failed
+pub fn doctor(ctx: &CommandContext, use_system_restic: bool, fix: bool) -> Result<Outcome> {
+    say!("=== restic-manager doctor ===\n");
+
+    let mut checks = vec![check_restic(use_system_restic)];
+    let restic_available = checks[0].status != Status::Fail;
+    checks.extend(check_passwords(&ctx.config));
+    checks.extend(check_directories(&ctx.config.global));
+    checks.extend(check_docker(ctx));
+    checks.extend(check_paths(ctx));
+    checks.extend(check_cron(ctx));
+    if restic_available {
+        checks.extend(check_repositories(ctx));
+    }
+
+    if fix {
+        apply_fixes(&mut checks);
+    }
+    print_report(&checks);
+
+    let fixable = checks.iter().filter(|c| c.fix.is_some()).count();
+    if fixable > 0 {
+        say!("\nRun with --fix to apply {} safe correction(s)", fixable);
+    }
+
+    if checks.iter().any(|c| c.status == Status::Fail) {
+        Ok(Outcome::Failed(exit_code::FAILURE))
+    } else {
+        Ok(Outcome::Success)
+    }
+}
+
+fn check_restic(use_system_restic: bool) -> Check {
+    const NAME: &str = "restic binary";
+    if !restic_installer::restic_exists(use_system_restic) {
+        return Check::fail(NAME, "not found (run: restic-manager setup-restic)");
+    }
+    let version = restic_installer::get_restic_version(use_system_restic)
+        .ok()
+        .and_then(|output| restic::parse_restic_version(&output));
+    match version {
+        Some(version) if version >= MIN_RESTIC_VERSION => {
+            Check::pass(NAME, format!("{}.{}.{}", version.0, version.1, version.2))
+        }
+        Some(version) => Check::fail(
+            NAME,
+            format!(
+                "{}.{}.{} is too old, {}.{}.{} or newer is needed (run: restic-manager update-restic)",
+                version.0, version.1, version.2, MIN_RESTIC_VERSION.0, MIN_RESTIC_VERSION.1, MIN_RESTIC_VERSION.2
+            ),
+        ),
+        None => Check::warn(NAME, "version could not be determined"),
+    }
+}
+
+/// Check every distinct password source of the destinations
+fn check_passwords(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut seen = HashSet::new();
+    let mut names: Vec<_> = config.destinations.keys().collect();
+    names.sort();
+
+    for name in names {
+        let destination = &config.destinations[name];
+        match destination.password_source(&config.global) {
+            PasswordSource::Keyring => {
+                let check_name = format!("keyring password ({})", name);
+                checks.push(match secrets::resolve_password(name) {
+                    Ok(_) => Check::pass(check_name, "found"),
+                    Err(e) => Check::fail(check_name, format!("{:#}", e)),
+                });
+            }
+            PasswordSource::File => match destination.password_command(&config.global) {
+                Some(password_command) => {
+                    if seen.insert(password_command.to_string()) {
+                        let check_name = format!("password command ({})", name);
+                        checks.push(match secrets::check_password_command(password_command, CHECK_TIMEOUT) {
+                            Ok(()) => Check::pass(check_name, password_command),
+                            Err(e) => Check::fail(check_name, format!("{}: {:#}", password_command, e)),
+                        });
+                    }
+                }
+                None => {
+                    if seen.insert(String::new()) {
+                        checks.push(check_password_file(&config.global.restic_password_file));
+                    }
+                }
+            },
+        }
+    }
+    checks
+}
+
+fn check_password_file(path: &Path) -> Check {
+    const NAME: &str = "password file";
+    if path.as_os_str().is_empty() {
+        return Check::fail(NAME, "restic_password_file is not set");
+    }
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Check::fail(NAME, format!("{} is not readable: {}", path.display(), e)),
+    };
+    if content.trim().is_empty() {
+        return Check::fail(NAME, format!("{} is empty", path.display()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            let mode = metadata.permissions().mode();
+            if is_group_or_world_accessible(mode) {
+                return Check::warn(
+                    NAME,
+                    format!("{} is accessible by other users (mode {:o})", path.display(), mode & 0o777),
+                )
+                .with_fix(Fix::RestrictPermissions(path.to_path_buf()));
+            }
+        }
+    }
+
+    Check::pass(NAME, path.display().to_string())
+}
+
+/// Whether group or others have any access in a Unix file mode
+fn is_group_or_world_accessible(mode: u32) -> bool {
+    mode & 0o077 != 0
+}
+
+fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .context(format!("Failed to change the permissions of {}", path.display()))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Existence and free space of the work and log directories
+fn check_directories(global: &GlobalConfig) -> Vec<Check> {
+    [("work directory", global.work_dir()), ("log directory", global.log_directory.clone())]
+        .into_iter()
+        .map(|(name, dir)| check_directory(name, &dir))
+        .collect()
+}
+
+fn check_directory(name: &str, dir: &Path) -> Check {
+    if !dir.is_dir() {
+        return Check::warn(name, format!("{} does not exist", dir.display())).with_fix(Fix::CreateDir(dir.to_path_buf()));
+    }
+    match command::free_space(dir) {
+        Ok(free) if free < LOW_DISK_SPACE => Check::warn(
+            name,
+            format!("only {} free in {}", humanize::format_bytes(free), dir.display()),
+        ),
+        Ok(free) => Check::pass(name, format!("{} free in {}", humanize::format_bytes(free), dir.display())),
+        Err(e) => Check::warn(name, format!("free space of {} unknown: {:#}", dir.display(), e)),
+    }
+}
+
+/// Whether a service needs the container runtime
+fn uses_docker(config: &BackupConfig) -> bool {
+    !config.volumes.is_empty()
+        || !config.containers.is_empty()
+        || config.compose_project.is_some()
+        || config.compose_file.is_some()
+        || config.pause_containers.is_some()
+}
+
+/// Reachability of the container runtime and the volumes of enabled services
+fn check_docker(ctx: &CommandContext) -> Vec<Check> {
+    let mut services: Vec<_> = ctx
+        .resolved
+        .values()
+        .filter(|s| s.enabled && s.config.as_ref().is_some_and(uses_docker))
+        .collect();
+    if services.is_empty() {
+        return Vec::new();
+    }
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    const NAME: &str = "docker daemon";
+    let global = &ctx.config.global;
+    let volumes = docker_ops::for_backend(global.docker_backend, global.container_runtime)
+        .and_then(|docker| docker.list_volumes(CHECK_TIMEOUT));
+    let volumes = match volumes {
+        Ok(volumes) => volumes,
+        Err(e) => return vec![Check::fail(NAME, format!("{:#}", e))],
+    };
+
+    let mut checks = vec![Check::pass(NAME, format!("{} volumes", volumes.len()))];
+    for service in services {
+        let wanted = service.config.as_ref().map(|c| c.volumes.as_slice()).unwrap_or_default();
+        if wanted.is_empty() {
+            continue;
+        }
+        let name = format!("volumes ({})", service.name);
+        let missing: Vec<_> = wanted.iter().filter(|v| !volumes.contains(v)).map(String::as_str).collect();
+        checks.push(if missing.is_empty() {
+            Check::pass(name, format!("{} found", wanted.len()))
+        } else {
+            Check::fail(name, format!("missing: {}", missing.join(", ")))
+        });
+    }
+    checks
+}
+
+/// Existence of the paths and file lists enabled services back up
+fn check_paths(ctx: &CommandContext) -> Vec<Check> {
+    let docker_base = &ctx.config.global.docker_base;
+    let mut services: Vec<_> = ctx.resolved.values().filter(|s| s.enabled).collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut checks = Vec::new();
+    for service in services {
+        let Some(ref config) = service.config else {
+            continue;
+        };
+        let paths: Vec<_> = config.paths.iter().chain(&config.files_from).collect();
+        if paths.is_empty() {
+            continue;
+        }
+        let name = format!("paths ({})", service.name);
+        let missing: Vec<_> = paths
+            .iter()
+            .filter(|path| !resolve_path(docker_base, path).exists())
+            .map(|path| path.as_str())
+            .collect();
+        checks.push(if missing.is_empty() {
+            Check::pass(name, format!("{} found", paths.len()))
+        } else {
+            Check::fail(name, format!("missing: {}", missing.join(", ")))
+        });
+    }
+    checks
+}
+
+/// A configured path (relative to docker_base or absolute)
+fn resolve_path(docker_base: &Path, path: &str) -> PathBuf {
+    if Path::new(path).is_absolute() {
+        PathBuf::from(path)
+    } else {
+        docker_base.join(path)
+    }
+}
+
+/// Cron jobs of the scheduled services
+fn check_cron(ctx: &CommandContext) -> Vec<Check> {
+    if cfg!(windows) {
+        return Vec::new();
+    }
+    const NAME: &str = "cron jobs";
+    // Temporarily disabled services keep their job
+    let mut scheduled: Vec<&str> = ctx
+        .resolved
+        .values()
+        .filter(|s| s.enabled || s.disabled_until.is_some())
+        .map(|s| s.name.as_str())
+        .collect();
+    if scheduled.is_empty() {
+        return Vec::new();
+    }
+    scheduled.sort();
+
+    let check = match cron::missing_cron_jobs(&scheduled) {
+        Ok(missing) if missing.is_empty() => Check::pass(NAME, format!("{} installed", scheduled.len())),
+        Ok(missing) => Check::warn(
+            NAME,
+            format!("missing for {} (run: restic-manager setup --cron-only)", missing.join(", ")),
+        ),
+        Err(e) => Check::warn(NAME, format!("crontab could not be read: {:#}", e)),
+    };
+    vec![check]
+}
+
+/// Whether every repository of the enabled services can be opened
+fn check_repositories(ctx: &CommandContext) -> Vec<Check> {
+    let mut services: Vec<_> = ctx.resolved.values().filter(|s| s.enabled).collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut checks = Vec::new();
+    for service in services {
+        for dest_name in &service.targets {
+            let name = format!("repository ({} @ {})", service.name, dest_name);
+            let result = ctx
+                .repository_env(service, dest_name)
+                .and_then(|env| restic::check_access(&env, CHECK_TIMEOUT));
+            checks.push(match result {
+                Ok(()) => Check::pass(name, "reachable"),
+                Err(e) => Check::fail(name, first_line(&format!("{:#}", e))),
+            });
+        }
+    }
+    checks
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim().to_string()
+}
+
+/// Apply the fixes of the checks, turning fixed checks into passes
+fn apply_fixes(checks: &mut [Check]) {
+    for check in checks.iter_mut() {
+        let Some(fix) = check.fix.take() else {
+            continue;
+        };
+        match fix.apply() {
+            Ok(done) => {
+                check.status = Status::Pass;
+                check.detail = format!("fixed: {}", done);
+            }
+            Err(e) => {
+                check.detail = format!("{} (fix failed: {:#})", check.detail, e);
+            }
+        }
+    }
+}
+
+fn print_report(checks: &[Check]) {
+    for check in checks {
+        let marker = match check.status {
+            Status::Pass => output::ok(),
+            Status::Warn => output::warn(),
+            Status::Fail => output::fail(),
+        };
+        if check.status == Status::Fail {
+            say_error!("{} {:<36} {}", marker, check.name, check.detail);
+        } else {
+            say!("{} {:<36} {}", marker, check.name, check.detail);
+        }
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    say!(
+        "\n{} passed, {} warning(s), {} failed",
+        count(Status::Pass),
+        count(Status::Warn),
+        count(Status::Fail)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_group_or_world_accessible() {
+        assert!(!is_group_or_world_accessible(0o100600));
+        assert!(!is_group_or_world_accessible(0o100400));
+        assert!(is_group_or_world_accessible(0o100640));
+        assert!(is_group_or_world_accessible(0o100604));
+    }
+
+    #[test]
+    fn test_check_directory_missing_is_fixable() {
+        let dir = std::env::temp_dir().join(format!("restic-manager-doctor-{}", std::process::id()));
+        let mut checks = vec![check_directory("work directory", &dir)];
+        assert_eq!(checks[0].status, Status::Warn);
+        assert_eq!(checks[0].fix, Some(Fix::CreateDir(dir.clone())));
+
+        apply_fixes(&mut checks);
+        assert_eq!(checks[0].status, Status::Pass);
+        assert!(dir.is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_password_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("restic-manager-doctor-pw-{}", std::process::id()));
+        fs::write(&path, "secret\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut checks = vec![check_password_file(&path)];
+        assert_eq!(checks[0].status, Status::Warn);
+        apply_fixes(&mut checks);
+        assert_eq!(checks[0].status, Status::Pass);
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(check_password_file(&path).status, Status::Pass);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(check_password_file(&path).status, Status::Fail);
+    }
+
+    #[test]
+    fn test_first_line() {
+        assert_eq!(first_line("\nOpening the repository failed: Fatal: wrong password\nmore"), "Opening the repository failed: Fatal: wrong password");
+    }
+}
@@ -0,0 +1,46 @@
+//! `forecast`: estimate the next backup's upload
+
+use super::Outcome;
+use crate::managers::backup::BackupManager;
+use crate::utils::restic::format_bytes;
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+
+/// Print a dry-run summary per destination
+pub fn forecast(backup_manager: &BackupManager, service: &str) -> Result<Outcome> {
+    say!("=== Backup Forecast: {} ===\n", service);
+
+    let forecast = backup_manager.forecast_service(service, false)?;
+
+    for (volume, size) in &forecast.volumes {
+        match size {
+            Ok(size) => say!("Volume {}: {} (archived at backup time, not included below)", volume, format_bytes(*size)),
+            Err(e) => say_error!("Volume {}: size unknown: {}", volume, e),
+        }
+    }
+    if !forecast.volumes.is_empty() {
+        say!();
+    }
+
+    for (target_name, result) in forecast.destinations {
+        say!("Destination: {}", target_name);
+        match result {
+            Ok(dry_run) => {
+                let summary = dry_run.summary;
+                say!("  Files: {} new, {} changed, {} unmodified",
+                    summary.files_new, summary.files_changed, summary.files_unmodified);
+                say!("  Processed: {} ({} files)",
+                    format_bytes(summary.total_bytes_processed),
+                    summary.total_files_processed);
+                say!("  To upload: {}", format_bytes(summary.data_added));
+            }
+            Err(e) => {
+                say_error!("  {} Forecast failed: {}", output::fail(), e);
+            }
+        }
+        say!();
+    }
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,197 @@
+//! `init`: interactive first-time configuration (no config needed)
+
+use super::Outcome;
+use crate::config::wizard::{self, WizardAnswers, WizardDestination, WizardService};
+use crate::config::DestinationType;
+use crate::utils::docker;
+use crate::utils::output;
+use crate::say;
+use anyhow::Result;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+use std::path::{Path, PathBuf};
+
+/// Walk through creating a first config at `config_path`
+pub fn init(config_path: &Path) -> Result<Outcome> {
+    say!("Restic Manager Setup");
+    say!("====================");
+    say!();
+
+    if config_path.exists() {
+        let overwrite = Confirm::new()
+            .with_prompt(format!("{} already exists. Overwrite it?", config_path.display()))
+            .default(false)
+            .interact()?;
+        if !overwrite {
+            say!("Aborted.");
+            return Ok(Outcome::Cancelled);
+        }
+    }
+
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+
+    // Password file
+    let password_file: String = Input::new()
+        .with_prompt("Repository password file")
+        .default(home.join(".restic-password").display().to_string())
+        .interact_text()?;
+    let password_file = PathBuf::from(password_file);
+
+    if password_file.exists() {
+        say!("{} Using existing password file", output::ok());
+    } else if Confirm::new()
+        .with_prompt("Password file does not exist. Generate a random password?")
+        .default(true)
+        .interact()?
+    {
+        wizard::write_password_file(&password_file, &wizard::generate_password())?;
+        say!("{} Generated password file {}", output::ok(), password_file.display());
+        say!("  Store a copy somewhere safe - without it backups cannot be restored!");
+    } else {
+        say!("{} Create {} before running backups", output::warn(), password_file.display());
+    }
+
+    let docker_base: String = Input::new()
+        .with_prompt("Base directory for service paths")
+        .default(home.join("docker").display().to_string())
+        .interact_text()?;
+
+    let log_directory: String = Input::new()
+        .with_prompt("Log directory")
+        .default(home.join("logs").display().to_string())
+        .interact_text()?;
+
+    // Destinations
+    say!();
+    say!("Destinations");
+    say!("------------");
+    let type_labels = ["local", "sftp", "s3"];
+    let mut destinations: Vec<WizardDestination> = Vec::new();
+    loop {
+        let type_index = Select::new()
+            .with_prompt("Destination type")
+            .items(&type_labels)
+            .default(0)
+            .interact()?;
+        let (dest_type, url_hint) = match type_index {
+            0 => (DestinationType::Local, "/mnt/backup/restic"),
+            1 => (DestinationType::Sftp, "sftp:user@host:/backups/restic"),
+            _ => (DestinationType::S3, "s3:s3.amazonaws.com/bucket-name"),
+        };
+
+        let name: String = Input::new()
+            .with_prompt("Destination name")
+            .default(type_labels[type_index].to_string())
+            .validate_with(|input: &String| -> Result<(), String> {
+                if destinations.iter().any(|d| &d.name == input) {
+                    Err(format!("Destination '{}' already exists", input))
+                } else {
+                    Ok(())
+                }
+            })
+            .interact_text()?;
+
+        let url: String = Input::new()
+            .with_prompt("Repository URL")
+            .default(url_hint.to_string())
+            .interact_text()?;
+
+        destinations.push(WizardDestination { name, dest_type, url });
+
+        if !Confirm::new().with_prompt("Add another destination?").default(false).interact()? {
+            break;
+        }
+    }
+
+    // Services
+    say!();
+    say!("Services");
+    say!("--------");
+    let available_volumes = match docker::list_volumes(Default::default(), std::time::Duration::from_secs(30)) {
+        Ok(volumes) => volumes,
+        Err(e) => {
+            say!("Could not list Docker volumes ({}), volumes must be entered manually", e);
+            Vec::new()
+        }
+    };
+    let destination_names: Vec<String> = destinations.iter().map(|d| d.name.clone()).collect();
+
+    let mut services: Vec<WizardService> = Vec::new();
+    while Confirm::new()
+        .with_prompt(if services.is_empty() { "Add a service?" } else { "Add another service?" })
+        .default(services.is_empty())
+        .interact()?
+    {
+        let name: String = Input::new().with_prompt("Service name").interact_text()?;
+
+        let schedule: String = Input::new()
+            .with_prompt("Schedule (cron)")
+            .default("0 2 * * *".to_string())
+            .interact_text()?;
+
+        let targets = if destination_names.len() == 1 {
+            destination_names.clone()
+        } else {
+            let defaults = vec![true; destination_names.len()];
+            let selected = MultiSelect::new()
+                .with_prompt("Destinations (space to toggle)")
+                .items(&destination_names)
+                .defaults(&defaults)
+                .interact()?;
+            selected.into_iter().map(|i| destination_names[i].clone()).collect()
+        };
+
+        let paths: String = Input::new()
+            .with_prompt("Paths to back up (comma separated, relative to base directory)")
+            .allow_empty(true)
+            .interact_text()?;
+        let paths = split_list(&paths);
+
+        let volumes = if available_volumes.is_empty() {
+            let volumes: String = Input::new()
+                .with_prompt("Docker volumes (comma separated)")
+                .allow_empty(true)
+                .interact_text()?;
+            split_list(&volumes)
+        } else {
+            let selected = MultiSelect::new()
+                .with_prompt("Docker volumes (space to toggle)")
+                .items(&available_volumes)
+                .interact()?;
+            selected.into_iter().map(|i| available_volumes[i].clone()).collect()
+        };
+
+        services.push(WizardService { name, schedule, targets, paths, volumes });
+    }
+
+    let answers = WizardAnswers {
+        password_file,
+        docker_base: PathBuf::from(docker_base),
+        log_directory: PathBuf::from(log_directory),
+        destinations,
+        services,
+    };
+
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(config_path, wizard::render_config(&answers))?;
+
+    say!();
+    say!("{} Configuration written to {}", output::ok(), config_path.display());
+    say!();
+    say!("Next steps:");
+    say!("  restic-manager --config {} validate", config_path.display());
+    say!("  restic-manager --config {} setup", config_path.display());
+
+    Ok(Outcome::Success)
+}
+
+/// Split a comma separated answer into trimmed, non-empty entries
+fn split_list(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,352 @@
+//! `key`: manage the keys (passwords) of many repositories at once
+//!
+//! restic's key commands work on one repository; `key list`, `key add` and
+//! `key passwd` run on every repository of the selected services and
+//! destinations, so rotating a password is one command instead of one per
+//! repository. After `key passwd` the new password has to be stored where
+//! the config reads it from (password file, password command or keyring).
+
+use super::{exit_code, CommandContext, Outcome};
+use crate::utils::output;
+use crate::utils::restic::{self, ResticEnv};
+use crate::utils::secrets::write_private;
+use crate::{say, say_error, say_warn};
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Timeout of each restic key command
+const KEY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `key` subcommands
+#[derive(Subcommand)]
+pub enum KeyAction {
+    /// List the keys of the repositories
+    List {
+        /// Only this service (default: all services)
+        #[arg(short, long)]
+        service: Option<String>,
+
+        /// Only this destination (default: all of the services' destinations)
+        #[arg(short, long)]
+        destination: Option<String>,
+    },
+
+    /// Add a key to the repositories (prompts for its password)
+    Add {
+        /// Only this service (default: all services)
+        #[arg(short, long)]
+        service: Option<String>,
+
+        /// Only this destination (default: all of the services' destinations)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Read the new password from this file instead of prompting
+        #[arg(long, value_name = "PATH")]
+        new_password_file: Option<PathBuf>,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Change the password of the key the repositories are opened with
+    Passwd {
+        /// Only this service (default: all services)
+        #[arg(short, long)]
+        service: Option<String>,
+
+        /// Only this destination (default: all of the services' destinations)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Read the new password from this file instead of prompting
+        #[arg(long, value_name = "PATH")]
+        new_password_file: Option<PathBuf>,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Remove a key from one repository
+    Remove {
+        /// Service whose repository to use
+        #[arg(short, long)]
+        service: String,
+
+        /// Destination (required if the service has multiple targets)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// ID of the key (see `key list`)
+        id: String,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Run a key subcommand
+pub fn key(ctx: &CommandContext, action: &KeyAction) -> Result<Outcome> {
+    match action {
+        KeyAction::List { service, destination } => {
+            list(ctx, &repositories(ctx, service.as_deref(), destination.as_deref())?)
+        }
+        KeyAction::Add { service, destination, new_password_file, yes } => {
+            let repos = repositories(ctx, service.as_deref(), destination.as_deref())?;
+            change(ctx, &repos, KeyChange::Add, new_password_file.as_deref(), *yes)
+        }
+        KeyAction::Passwd { service, destination, new_password_file, yes } => {
+            let repos = repositories(ctx, service.as_deref(), destination.as_deref())?;
+            change(ctx, &repos, KeyChange::Passwd, new_password_file.as_deref(), *yes)
+        }
+        KeyAction::Remove { service, destination, id, yes } => {
+            remove(ctx, service, destination.as_deref(), id, *yes)
+        }
+    }
+}
+
+/// (service, destination) of the selected repositories
+fn repositories(ctx: &CommandContext, service: Option<&str>, destination: Option<&str>) -> Result<Vec<(String, String)>> {
+    if let Some(name) = destination {
+        ctx.destination(name)?;
+    }
+    let services = match service {
+        Some(name) => vec![ctx.service(name)?],
+        None => ctx.resolved.values().collect(),
+    };
+
+    let mut repos: Vec<(String, String)> = services
+        .into_iter()
+        .flat_map(|s| s.targets.iter().map(|t| (s.name.clone(), t.clone())))
+        .filter(|(_, t)| destination.is_none_or(|d| d == t))
+        .collect();
+    repos.sort();
+
+    if repos.is_empty() {
+        anyhow::bail!("No repositories match the given service and destination");
+    }
+    Ok(repos)
+}
+
+fn env_of(ctx: &CommandContext, service: &str, dest_name: &str) -> Result<ResticEnv> {
+    ctx.repository_env(ctx.service(service)?, dest_name)
+}
+
+fn list(ctx: &CommandContext, repos: &[(String, String)]) -> Result<Outcome> {
+    let mut failed = 0;
+    for (service, dest_name) in repos {
+        say!("{} @ {}", service, dest_name);
+        match env_of(ctx, service, dest_name).and_then(|env| restic::list_keys(&env, KEY_TIMEOUT)) {
+            Ok(keys) => {
+                for key in keys {
+                    say!(
+                        "  {} {:<10} {:<24} {}",
+                        if key.current { "*" } else { " " },
+                        key.id.chars().take(8).collect::<String>(),
+                        format!("{}@{}", key.user_name, key.host_name),
+                        key.created
+                    );
+                }
+            }
+            Err(e) => {
+                say_error!("  {} {:#}", output::fail(), e);
+                failed += 1;
+            }
+        }
+    }
+    say!("\n* = key of the configured password");
+
+    if failed == 0 {
+        Ok(Outcome::Success)
+    } else {
+        Ok(Outcome::Failed(exit_code::RESTIC))
+    }
+}
+
+/// Key change applied to every selected repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyChange {
+    Add,
+    Passwd,
+}
+
+fn change(
+    ctx: &CommandContext,
+    repos: &[(String, String)],
+    kind: KeyChange,
+    new_password_file: Option<&Path>,
+    yes: bool,
+) -> Result<Outcome> {
+    let verb = match kind {
+        KeyChange::Add => "Add a key to",
+        KeyChange::Passwd => "Change the password of",
+    };
+    if !yes {
+        say!("{} {} repositories:", verb, repos.len());
+        for (service, dest_name) in repos {
+            say!("  {} @ {}", service, dest_name);
+        }
+        let confirm = dialoguer::Confirm::new().with_prompt("Continue?").default(false).interact()?;
+        if !confirm {
+            say!("Cancelled.");
+            return Ok(Outcome::Cancelled);
+        }
+    }
+
+    let password = NewPassword::obtain(&ctx.config.global.scratch_dir(), new_password_file)?;
+
+    let mut changed = 0;
+    let mut failed = Vec::new();
+    for (service, dest_name) in repos {
+        let env = match env_of(ctx, service, dest_name) {
+            Ok(env) => env,
+            Err(e) => {
+                say_error!("{} {} @ {}: {:#}", output::fail(), service, dest_name, e);
+                failed.push(format!("{} @ {}", service, dest_name));
+                continue;
+            }
+        };
+        // Replacing a key removes the old one, which append-only
+        // repositories refuse
+        if kind == KeyChange::Passwd && env.is_append_only() {
+            say_warn!("{} {} @ {}: skipped, the destination is append-only", output::warn(), service, dest_name);
+            continue;
+        }
+
+        tracing::info!(target: "audit", service = %service, destination = %dest_name, "restic key {:?}", kind);
+        let result = match kind {
+            KeyChange::Add => restic::add_key(&env, password.path(), KEY_TIMEOUT),
+            KeyChange::Passwd => restic::change_key_password(&env, password.path(), KEY_TIMEOUT),
+        };
+        match result {
+            Ok(()) => {
+                say!("{} {} @ {}", output::ok(), service, dest_name);
+                changed += 1;
+            }
+            Err(e) => {
+                say_error!("{} {} @ {}: {:#}", output::fail(), service, dest_name, e);
+                failed.push(format!("{} @ {}", service, dest_name));
+            }
+        }
+    }
+
+    if kind == KeyChange::Passwd && changed > 0 {
+        say!("\nChanged the password of {} repositories. Store the new password where the config", changed);
+        say!("reads it from (restic_password_file, restic_password_command or `secret set`)");
+        say!("before the next backup.");
+        if !failed.is_empty() {
+            say_error!("These repositories still use the old password: {}", failed.join(", "));
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(Outcome::Success)
+    } else {
+        Ok(Outcome::Failed(exit_code::RESTIC))
+    }
+}
+
+fn remove(ctx: &CommandContext, service: &str, destination: Option<&str>, id: &str, yes: bool) -> Result<Outcome> {
+    let service_config = ctx.service(service)?;
+    let dest_name = super::choose_destination(service, service_config, destination)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Service '{}' has multiple destinations, use --destination (available: {})",
+            service,
+            service_config.targets.join(", ")
+        )
+    })?;
+    let env = ctx.repository_env(service_config, &dest_name)?;
+
+    if env.is_append_only() {
+        say_error!("Error: Destination '{}' is append-only; keys must be removed from a trusted host", dest_name);
+        return Ok(Outcome::Failed(exit_code::FAILURE));
+    }
+
+    if !yes {
+        let confirm = dialoguer::Confirm::new()
+            .with_prompt(format!("Remove key {} from {} @ {}?", id, service, dest_name))
+            .default(false)
+            .interact()?;
+        if !confirm {
+            say!("Cancelled.");
+            return Ok(Outcome::Cancelled);
+        }
+    }
+
+    tracing::info!(target: "audit", service = %service, destination = %dest_name, "restic key remove {}", id);
+    restic::remove_key(&env, id, KEY_TIMEOUT)?;
+    say!("{} Removed key {} from {} @ {}", output::ok(), id, service, dest_name);
+    Ok(Outcome::Success)
+}
+
+/// File holding the new password for restic; a prompted password is
+/// written to the work directory (readable by the owner only) and removed
+/// again on drop
+struct NewPassword {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl NewPassword {
+    fn obtain(work_dir: &Path, given: Option<&Path>) -> Result<Self> {
+        if let Some(path) = given {
+            if fs::read_to_string(path).map(|p| p.trim().is_empty()).unwrap_or(true) {
+                anyhow::bail!("New password file {} is missing or empty", path.display());
+            }
+            return Ok(Self { path: path.to_path_buf(), temporary: false });
+        }
+
+        let password = dialoguer::Password::new()
+            .with_prompt("New repository password")
+            .with_confirmation("Confirm password", "Passwords don't match")
+            .interact()?;
+
+        fs::create_dir_all(work_dir).context("Failed to create the work directory")?;
+        let path = work_dir.join(format!("new-password-{}", std::process::id()));
+        write_private(&path, &password)?;
+        Ok(Self { path, temporary: true })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for NewPassword {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_password_from_file() {
+        let dir = std::env::temp_dir().join(format!("restic-manager-key-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("new-password");
+
+        assert!(NewPassword::obtain(&dir, Some(&path)).is_err());
+        write_private(&path, "correct horse\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // A given file is used as is and kept
+        drop(NewPassword::obtain(&dir, Some(&path)).unwrap());
+        assert!(path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,27 @@
+//! `maintain`: repository housekeeping for a service
+
+use super::Outcome;
+use crate::managers::backup::BackupManager;
+use crate::managers::maintenance::MaintenanceReport;
+use crate::utils::restic::PruneOptions;
+use crate::say;
+use anyhow::Result;
+
+/// Run maintenance and print one report per destination
+pub fn maintain(
+    backup_manager: &BackupManager,
+    service: &str,
+    destination: Option<&str>,
+    options: &PruneOptions,
+) -> Result<Outcome> {
+    let reports: Vec<MaintenanceReport> = backup_manager.maintain_service(service, destination, options)?;
+    for report in &reports {
+        say!("{}", report);
+    }
+
+    if reports.iter().all(|r| r.success()) {
+        Ok(Outcome::Success)
+    } else {
+        Ok(Outcome::Failed(1))
+    }
+}
@@ -0,0 +1,347 @@
+//! CLI command handlers
+//!
+//! Each subcommand lives in its own module. Handlers get everything they
+//! need passed in instead of reaching for globals (a [`CommandContext`] for
+//! commands that work on a loaded config, the backup manager for backups),
+//! and return an [`Outcome`] instead of exiting the process, so `main`
+//! decides how to exit. The handlers are part of the library, so they can
+//! be tested with mocked restic operations.
+
+pub mod cache;
+pub mod check_destinations;
+pub mod check_health;
+pub mod context;
+pub mod doctor;
+pub mod forecast;
+pub mod init;
+pub mod key;
+pub mod maintain;
+pub mod raw;
+pub mod report;
+pub mod restic;
+pub mod restore;
+pub mod resume;
+pub mod run;
+pub mod secret;
+pub mod setup;
+pub mod snapshots;
+pub mod stats;
+pub mod status;
+pub mod test_restore;
+pub mod validate;
+pub mod verify;
+
+use crate::config::{Config, ConfigError, Destination, ResolvedServiceConfig};
+use crate::error::BackupError;
+use crate::utils::command::{CommandCancelled, CommandTimeout};
+use crate::utils::locker;
+use crate::utils::restic::ResticEnv;
+use crate::utils::{RealResticOps, ResticOperations};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Resolved services by name
+pub type Services = HashMap<String, ResolvedServiceConfig>;
+
+/// What the handlers of config-based commands work on
+pub struct CommandContext {
+    pub config: Config,
+    /// Resolved services by name
+    pub resolved: Services,
+    /// Restic operations (the real restic binary outside tests)
+    pub ops: Arc<dyn ResticOperations>,
+}
+
+impl CommandContext {
+    /// Context using the real restic binary
+    pub fn new(config: Config, resolved: Services) -> Self {
+        Self { config, resolved, ops: Arc::new(RealResticOps::new()) }
+    }
+
+    /// Use other restic operations (e.g. a mock)
+    #[allow(dead_code)]
+    pub fn with_ops(mut self, ops: Arc<dyn ResticOperations>) -> Self {
+        self.ops = ops;
+        self
+    }
+
+    /// Look up a service by name
+    pub fn service(&self, name: &str) -> Result<&ResolvedServiceConfig> {
+        find_service(&self.resolved, name)
+    }
+
+    /// Look up a destination by name
+    pub fn destination(&self, name: &str) -> Result<&Destination> {
+        find_destination(&self.config, name)
+    }
+
+    /// Restic environment for a service's repository on a destination
+    pub fn repository_env(&self, service: &ResolvedServiceConfig, dest_name: &str) -> Result<ResticEnv> {
+        repository_env(&self.config, service, dest_name, self.destination(dest_name)?)
+    }
+}
+
+/// How a command finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    /// The user declined a confirmation prompt
+    Cancelled,
+    /// The command ran but failed; exit with this code
+    Failed(i32),
+}
+
+impl Outcome {
+    /// Process exit code for this outcome
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Success | Outcome::Cancelled => 0,
+            Outcome::Failed(code) => *code,
+        }
+    }
+}
+
+/// Process exit codes (documented in the README's "Exit codes" section)
+pub mod exit_code {
+    /// Any other error
+    pub const FAILURE: i32 = 1;
+    /// The configuration is invalid or can't be read
+    pub const CONFIG: i32 = 2;
+    /// Another process holds the service's lock or the repository's lock
+    pub const LOCK_HELD: i32 = 3;
+    /// Some destinations or services failed, others succeeded
+    pub const PARTIAL: i32 = 4;
+    /// restic failed (a backup, check, restore or other restic command)
+    pub const RESTIC: i32 = 5;
+    /// A command ran longer than its timeout
+    pub const TIMEOUT: i32 = 6;
+    /// `status --all`: a latest backup is overdue (warning health)
+    pub const HEALTH_WARNING: i32 = 7;
+    /// `status --all`: a latest backup is far overdue, missing or unreadable
+    /// (critical health)
+    pub const HEALTH_CRITICAL: i32 = 8;
+    /// Interrupted by SIGINT/SIGTERM
+    pub const INTERRUPTED: i32 = 130;
+}
+
+/// Exit code of a command that failed with `error`
+pub fn exit_code_of(error: &anyhow::Error) -> i32 {
+    if error.chain().any(|cause| cause.downcast_ref::<ConfigError>().is_some()) {
+        return exit_code::CONFIG;
+    }
+    if locker::already_running(error).is_some() || locker::repository_busy(error).is_some() {
+        return exit_code::LOCK_HELD;
+    }
+    if error.downcast_ref::<CommandCancelled>().is_some() {
+        return exit_code::INTERRUPTED;
+    }
+    if error.downcast_ref::<CommandTimeout>().is_some() {
+        return exit_code::TIMEOUT;
+    }
+    match BackupError::of(error) {
+        Some(BackupError::LockHeld { .. }) => exit_code::LOCK_HELD,
+        Some(BackupError::PartialFailure { .. }) => exit_code::PARTIAL,
+        Some(BackupError::RepoInit { .. } | BackupError::ResticFailed { .. }) => exit_code::RESTIC,
+        Some(BackupError::Timeout(_)) => exit_code::TIMEOUT,
+        Some(BackupError::HookFailed { .. } | BackupError::VolumeMissing(_)) | None => exit_code::FAILURE,
+    }
+}
+
+/// Look up a service by name
+pub fn find_service<'a>(services: &'a Services, name: &str) -> Result<&'a ResolvedServiceConfig> {
+    services
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Service '{}' not found in configuration", name))
+}
+
+/// Look up a destination by name
+pub fn find_destination<'a>(config: &'a Config, name: &str) -> Result<&'a Destination> {
+    config
+        .destinations
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Destination '{}' not found", name))
+}
+
+/// Destination to use for a service
+///
+/// An explicitly requested destination must be one of the service's targets.
+/// Without one, a service with a single target uses it; otherwise `None` is
+/// returned and the caller decides (prompt, error or all targets).
+pub fn choose_destination(
+    service_name: &str,
+    service: &ResolvedServiceConfig,
+    requested: Option<&str>,
+) -> Result<Option<String>> {
+    match requested {
+        Some(dest) if service.targets.iter().any(|t| t == dest) => Ok(Some(dest.to_string())),
+        Some(dest) => anyhow::bail!(
+            "Service '{}' does not use destination '{}' (available: {})",
+            service_name,
+            dest,
+            service.targets.join(", ")
+        ),
+        None if service.targets.len() == 1 => Ok(Some(service.targets[0].clone())),
+        None => Ok(None),
+    }
+}
+
+/// Restic environment for a service's repository on a destination, limited
+/// to the service's host name and bandwidth
+pub fn repository_env(
+    config: &Config,
+    service: &ResolvedServiceConfig,
+    dest_name: &str,
+    destination: &Destination,
+) -> Result<ResticEnv> {
+    let repo_url = crate::utils::restic::build_repository_url(destination, &service.name, None);
+    Ok(ResticEnv::for_destination(&config.global, dest_name, destination, &repo_url)?
+        .with_host(service.hostname.as_deref())
+        .with_limits(service.limit_upload_kbps, service.limit_download_kbps))
+}
+
+/// Format a restic snapshot timestamp as "YYYY-MM-DD HH:MM:SS"
+pub fn format_snapshot_time(time: &str) -> String {
+    match time.split_once('T') {
+        Some((date, rest)) => {
+            let time_part = rest.split(['.', '+', 'Z']).next().unwrap_or("");
+            format!("{} {}", date, time_part)
+        }
+        None => time.to_string(),
+    }
+}
+
+/// Parse a point in time given on the command line
+///
+/// Accepts RFC 3339 (`2024-06-01T03:00:00+02:00`) or a local time
+/// (`2024-06-01 03:00`, optionally with seconds or a `T`), which is
+/// interpreted in the system timezone.
+pub fn parse_point_in_time(input: &str) -> Result<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid time '{}' (expected e.g. \"2024-06-01 03:00\" or \"2024-06-01T03:00:00+02:00\")",
+                input
+            )
+        })?;
+
+    // A time repeated when the clocks go back resolves to the first one
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| anyhow::anyhow!("'{}' doesn't exist in the local timezone (DST change)", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service_with_targets(targets: &[&str]) -> ResolvedServiceConfig {
+        ResolvedServiceConfig {
+            name: "app".to_string(),
+            enabled: true,
+            disabled_until: None,
+            description: String::new(),
+            schedule: "0 2 * * *".to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            timeout_seconds: 3600,
+            time_budget_minutes: None,
+            max_age_hours: None,
+            retention: crate::config::RetentionPolicy {
+                daily: 7,
+                weekly: 4,
+                monthly: 6,
+                yearly: 0,
+                ..Default::default()
+            },
+            retry: Default::default(),
+            post_backup_verify: Default::default(),
+            notify_on: Vec::new(),
+            tags: Vec::new(),
+            hostname: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            config: None,
+        }
+    }
+
+    #[test]
+    fn test_choose_destination() {
+        let single = service_with_targets(&["local"]);
+        assert_eq!(choose_destination("app", &single, None).unwrap().as_deref(), Some("local"));
+
+        let multiple = service_with_targets(&["local", "remote"]);
+        assert_eq!(choose_destination("app", &multiple, None).unwrap(), None);
+        assert_eq!(
+            choose_destination("app", &multiple, Some("remote")).unwrap().as_deref(),
+            Some("remote")
+        );
+
+        let err = choose_destination("app", &multiple, Some("nas")).unwrap_err().to_string();
+        assert!(err.contains("does not use destination 'nas'"));
+        assert!(err.contains("local, remote"));
+    }
+
+    #[test]
+    fn test_parse_point_in_time() {
+        assert_eq!(
+            parse_point_in_time("2024-06-01T03:00:00+02:00").unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 1, 0, 0).unwrap()
+        );
+
+        let local = Local.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_point_in_time("2024-06-01 03:00").unwrap(), local);
+        assert_eq!(parse_point_in_time(" 2024-06-01T03:00:00 ").unwrap(), local);
+
+        assert!(parse_point_in_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_format_snapshot_time() {
+        assert_eq!(
+            format_snapshot_time("2025-01-15T02:00:13.123456789+01:00"),
+            "2025-01-15 02:00:13"
+        );
+        assert_eq!(format_snapshot_time("2025-01-15T02:00:13Z"), "2025-01-15 02:00:13");
+        assert_eq!(format_snapshot_time("garbage"), "garbage");
+    }
+
+    #[test]
+    fn test_outcome_exit_codes() {
+        assert_eq!(Outcome::Success.exit_code(), 0);
+        assert_eq!(Outcome::Cancelled.exit_code(), 0);
+        assert_eq!(Outcome::Failed(3).exit_code(), 3);
+    }
+
+    #[test]
+    fn test_exit_code_of() {
+        use anyhow::Context;
+        use std::time::Duration;
+
+        let config: anyhow::Result<()> = Err(ConfigError::ProfileNotFound("db".to_string()).into());
+        assert_eq!(exit_code_of(&config.context("Failed to load config").unwrap_err()), exit_code::CONFIG);
+
+        let running = locker::AlreadyRunning { service: "app".to_string(), holder: None };
+        assert_eq!(exit_code_of(&running.into()), exit_code::LOCK_HELD);
+        let locked = BackupError::restic_failed("Backup", "repository is already locked by PID 1");
+        assert_eq!(exit_code_of(&locked.into()), exit_code::LOCK_HELD);
+
+        let partial = BackupError::PartialFailure { summary: "1 of 2 failed".to_string() };
+        assert_eq!(exit_code_of(&partial.into()), exit_code::PARTIAL);
+
+        let restic: anyhow::Result<()> = Err(BackupError::restic_failed("Prune", "Fatal: wrong password").into());
+        assert_eq!(exit_code_of(&restic.context("Backup failed for 1 destination(s)").unwrap_err()), exit_code::RESTIC);
+
+        assert_eq!(exit_code_of(&BackupError::Timeout(Duration::from_secs(5)).into()), exit_code::TIMEOUT);
+        assert_eq!(exit_code_of(&anyhow::anyhow!("disk full")), exit_code::FAILURE);
+    }
+}
@@ -0,0 +1,72 @@
+//! `raw`: run restic directly against a service's repository
+
+use super::{CommandContext, Outcome};
+use crate::utils::restic;
+use crate::{say, say_error};
+use anyhow::Result;
+
+/// Run restic with the given arguments, confirming modifying commands
+pub fn raw(
+    ctx: &CommandContext,
+    service: &str,
+    destination: Option<&str>,
+    yes: bool,
+    args: &[String],
+) -> Result<Outcome> {
+    let service_config = ctx.service(service)?;
+
+    let dest_name = super::choose_destination(service, service_config, destination)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Service '{}' has multiple destinations, use --destination (available: {})",
+            service,
+            service_config.targets.join(", ")
+        )
+    })?;
+
+    let dest = ctx.destination(&dest_name)?;
+    let env = ctx.repository_env(service_config, &dest_name)?;
+
+    if env.is_append_only() && restic::is_deleting_command(args) {
+        if let Err(flag) = restic::raw_subcommand(args) {
+            say_error!(
+                "Error: Destination '{}' is append-only and '{}' hides the restic subcommand; put it after the subcommand",
+                dest_name, flag
+            );
+            return Ok(Outcome::Failed(1));
+        }
+        say_error!("Error: Destination '{}' is append-only; forget/prune must run from a trusted host", dest_name);
+        say_error!("(set trusted_host = true in the config on that host)");
+        return Ok(Outcome::Failed(1));
+    }
+
+    let read_only = restic::is_read_only_command(args);
+    if !read_only && !yes {
+        say!("Repository: {}", restic::build_repository_url(dest, service, None));
+        say!("Command: restic {}", args.join(" "));
+        let confirm = dialoguer::Confirm::new()
+            .with_prompt("This command may modify the repository. Continue?")
+            .default(false)
+            .interact()?;
+
+        if !confirm {
+            say!("Cancelled.");
+            return Ok(Outcome::Cancelled);
+        }
+    }
+
+    tracing::info!(
+        target: "audit",
+        service = %service,
+        destination = %dest_name,
+        read_only,
+        "raw restic command: restic {}",
+        args.join(" ")
+    );
+
+    let status = restic::run_passthrough(&env, args)?;
+    if status.success() {
+        Ok(Outcome::Success)
+    } else {
+        Ok(Outcome::Failed(status.code().unwrap_or(1)))
+    }
+}
@@ -0,0 +1,30 @@
+//! `report`: the machine-readable report of a service's last run
+//!
+//! Prints `<service>.report.json` from the newest run log directory that
+//! has one, or with `--schema` the JSON Schema those reports follow.
+
+use super::{CommandContext, Outcome};
+use crate::managers::run_logs::RunLogs;
+use crate::managers::run_report::RunReport;
+use anyhow::{Context, Result};
+use std::fs;
+
+/// Print the JSON Schema of run reports
+pub fn schema() -> Result<Outcome> {
+    println!("{}", RunReport::json_schema());
+    Ok(Outcome::Success)
+}
+
+/// Print the report of a service's last run
+pub fn report(ctx: &CommandContext, service: &str) -> Result<Outcome> {
+    ctx.service(service)?;
+    let config = &ctx.config;
+
+    let path = RunLogs::latest_report(&config.global.log_directory, service).context(format!(
+        "No run report for '{}' (reports are kept with the last keep_run_logs = {} runs)",
+        service, config.global.keep_run_logs
+    ))?;
+    let json = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    print!("{}", json);
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,77 @@
+//! `setup-restic`, `update-restic` and `restic-version` (no config needed)
+
+use super::Outcome;
+use crate::utils::restic_installer;
+use crate::utils::output;
+use crate::say;
+use anyhow::Result;
+
+fn missing_restic(use_system_restic: bool) -> Outcome {
+    if use_system_restic {
+        say!("System restic not found in PATH.");
+    } else {
+        say!("Managed restic not found. Run 'restic-manager setup-restic' first.");
+    }
+    Outcome::Failed(1)
+}
+
+/// Download the managed restic binary if it isn't installed yet
+pub fn setup_restic() -> Result<Outcome> {
+    say!("Setting up managed restic binary...");
+
+    if restic_installer::local_restic_exists() {
+        say!("{} Managed restic is already installed", output::ok());
+        let version = restic_installer::get_restic_version(false)?;
+        say!("  Version: {}", version);
+        say!("  Binary: {}", restic_installer::get_restic_bin_path().display());
+    } else {
+        say!("Downloading restic from GitHub...");
+        restic_installer::download_restic()?;
+        let version = restic_installer::get_restic_version(false)?;
+        say!("{} Restic installed successfully", output::ok());
+        say!("  Version: {}", version);
+        say!("  Binary: {}", restic_installer::get_restic_bin_path().display());
+        say!();
+        say!("To use this binary, ensure use_system_restic = false in your config (default).");
+    }
+
+    Ok(Outcome::Success)
+}
+
+/// Update the restic binary in use
+pub fn update_restic(use_system_restic: bool) -> Result<Outcome> {
+    say!("Updating restic...");
+
+    if !restic_installer::restic_exists(use_system_restic) {
+        return Ok(missing_restic(use_system_restic));
+    }
+
+    let old_version = restic_installer::get_restic_version(use_system_restic)?;
+    say!("Current version: {}", old_version);
+
+    restic_installer::update_restic(use_system_restic)?;
+
+    let new_version = restic_installer::get_restic_version(use_system_restic)?;
+    say!("{} Updated to: {}", output::ok(), new_version);
+
+    Ok(Outcome::Success)
+}
+
+/// Show the version and location of the restic binary in use
+pub fn restic_version(use_system_restic: bool) -> Result<Outcome> {
+    if !restic_installer::restic_exists(use_system_restic) {
+        return Ok(missing_restic(use_system_restic));
+    }
+
+    let version = restic_installer::get_restic_version(use_system_restic)?;
+    say!("Restic version: {}", version);
+    say!("Binary location: {}", restic_installer::get_restic_command(use_system_restic));
+
+    if use_system_restic {
+        say!("Source: System PATH (use_system_restic = true)");
+    } else {
+        say!("Source: Managed binary (use_system_restic = false)");
+    }
+
+    Ok(Outcome::Success)
+}
@@ -0,0 +1,464 @@
+//! `restore`: interactively restore a service (or one of its Docker
+//! volumes) from a snapshot
+
+use super::{CommandContext, Outcome};
+use crate::config::Config;
+use crate::utils::docker_ops::{self, DockerOperations};
+use crate::utils::scratch::ScratchDir;
+use crate::utils::{docker, restic};
+use crate::utils::output;
+use crate::{say, say_error, say_warn};
+use anyhow::{Context, Result};
+use dialoguer::{Confirm, Select};
+use std::path::Path;
+use std::time::Duration;
+
+/// What to restore, as given on the command line
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    pub service: String,
+    /// Snapshot ID (prompted for if not given)
+    pub snapshot: Option<String>,
+    /// Restore the newest snapshot at or before this time instead
+    pub at: Option<String>,
+    /// Destination (prompted for if the service has several)
+    pub destination: Option<String>,
+    /// Target directory (restores in place if not given)
+    pub target: Option<String>,
+    /// Restore only these paths
+    pub paths: Vec<String>,
+    /// Skip the pre-restore snapshot of the current files
+    pub no_pre_restore_snapshot: bool,
+    /// Download bandwidth limit in KiB/s (defaults to the configured limits)
+    pub limit_download_kbps: Option<u32>,
+    /// Run restic at the lowest CPU and I/O priority
+    pub nice: bool,
+    /// Restore this Docker volume from its archive instead of files
+    pub volume: Option<String>,
+    /// Remove and recreate the volume before restoring it
+    pub recreate_volume: bool,
+    /// Extract the volume archive without checking the free space first
+    pub skip_space_check: bool,
+    /// Only preview what the restore would write
+    pub dry_run: bool,
+    /// Never prompt: use the latest snapshot if none is given, fail if the
+    /// destination is ambiguous, and skip the confirmation
+    pub yes: bool,
+    /// Accept the retrieval latency and cost of restoring from cold storage
+    pub acknowledge_cold_storage: bool,
+}
+
+/// Restore a service, confirming with the user first
+pub fn restore(ctx: &CommandContext, options: &RestoreOptions) -> Result<Outcome> {
+    let service = &options.service;
+    let service_config = ctx.service(service)?;
+    let at = options.at.as_deref().map(super::parse_point_in_time).transpose()?;
+
+    say!("=== Restore Service: {} ===\n", service);
+
+    // Determine which destination to use
+    let dest_name = match super::choose_destination(service, service_config, options.destination.as_deref())? {
+        Some(dest_name) => dest_name,
+        None if options.yes => anyhow::bail!(
+            "Service '{}' has multiple destinations, use --destination (available: {})",
+            service,
+            service_config.targets.join(", ")
+        ),
+        None => {
+            say!("Multiple destinations available. Select one:");
+            let selection = Select::new()
+                .items(&service_config.targets)
+                .default(0)
+                .interact()?;
+            service_config.targets[selection].clone()
+        }
+    };
+
+    let dest = ctx.destination(&dest_name)?;
+
+    say!("Using destination: {} ({})\n", dest_name, dest.url);
+
+    if dest.cold_storage {
+        say!("{} '{}' is cold storage: data may first have to be retrieved from the archive", output::warn(), dest_name);
+        say!("  tier, which can take hours and is billed per GB retrieved.");
+        if !options.acknowledge_cold_storage && !options.dry_run {
+            anyhow::bail!(
+                "Restoring from cold storage destination '{}' needs --acknowledge-cold-storage",
+                dest_name
+            );
+        }
+        say!();
+    }
+
+    let env = ctx.repository_env(service_config, &dest_name)?;
+
+    // Get snapshots
+    let snapshots = ctx.ops.list_snapshots(&env, Duration::from_secs(60))?;
+
+    if snapshots.is_empty() {
+        anyhow::bail!("No snapshots found for service '{}'", service);
+    }
+
+    // Determine which snapshot to restore
+    let snapshot_id = if let Some(ref snap_id) = options.snapshot {
+        // Verify snapshot exists
+        if !snapshots.iter().any(|s| s.id.starts_with(snap_id) || s.short_id == *snap_id) {
+            anyhow::bail!("Snapshot '{}' not found", snap_id);
+        }
+        snap_id.clone()
+    } else if let Some(at) = at {
+        let snapshot = restic::snapshot_at(&snapshots, at).context(format!(
+            "No snapshot of '{}' on '{}' at or before {}",
+            service,
+            dest_name,
+            at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S %Z")
+        ))?;
+        say!(
+            "Newest snapshot at or before {}: {} - {}",
+            at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+            snapshot.short_id,
+            super::format_snapshot_time(&snapshot.time)
+        );
+        snapshot.short_id.clone()
+    } else if options.yes {
+        // Snapshots are listed oldest first
+        let latest = &snapshots[snapshots.len() - 1];
+        say!(
+            "Using latest snapshot: {} - {}",
+            latest.short_id,
+            super::format_snapshot_time(&latest.time)
+        );
+        latest.short_id.clone()
+    } else {
+        // Interactive snapshot selection
+        say!("Available snapshots:");
+        let items: Vec<String> = snapshots
+            .iter()
+            .map(|s| format!("{} - {} ({})", s.short_id, super::format_snapshot_time(&s.time), s.hostname))
+            .collect();
+
+        let selection = Select::new()
+            .with_prompt("Select snapshot to restore")
+            .items(&items)
+            .default(items.len() - 1) // Default to most recent
+            .interact()?;
+
+        snapshots[selection].short_id.clone()
+    };
+
+    say!("\nSelected snapshot: {}\n", snapshot_id);
+
+    let limits = restic::RestoreLimits {
+        limit_download_kbps: options.limit_download_kbps,
+        nice: options.nice,
+    };
+
+    if let Some(ref volume) = options.volume {
+        return restore_docker_volume(&ctx.config, &env, &snapshot_id, volume, options, &limits);
+    }
+
+    // Show what will be restored
+    say!("Preview of snapshot contents:");
+    match restic::list_snapshot_files(&env, &snapshot_id, Duration::from_secs(30)) {
+        Ok(files) => {
+            let preview_count = 10;
+            for file in files.iter().take(preview_count) {
+                say!("  {}", file);
+            }
+            if files.len() > preview_count {
+                say!("  ... and {} more files", files.len() - preview_count);
+            }
+            say!("\nTotal: {} items", files.len());
+        }
+        Err(e) => {
+            say_warn!("Warning: Could not list snapshot contents: {}", e);
+        }
+    }
+
+    // Determine target directory
+    let target_dir = if let Some(ref t) = options.target {
+        Some(t.as_str())
+    } else {
+        say!("\nNo target directory specified.");
+        say!("Restore will overwrite original locations!");
+        None
+    };
+
+    let pre_restore_snapshot =
+        target_dir.is_none() && ctx.config.global.pre_restore_snapshot && !options.no_pre_restore_snapshot;
+
+    if let Some(t) = target_dir {
+        say!("\nRestore target: {}", t);
+    } else {
+        say!("\nRestore target: Original locations (IN-PLACE)");
+        if pre_restore_snapshot {
+            say!("The current files are backed up first (tag: {})", restic::PRE_RESTORE_TAG);
+        }
+    }
+
+    if !options.paths.is_empty() {
+        say!("Restoring specific paths:");
+        for p in &options.paths {
+            say!("  {}", p);
+        }
+    }
+
+    if options.dry_run {
+        return preview_restore(&env, &snapshot_id, target_dir, &options.paths);
+    }
+
+    say!();
+
+    // Confirmation
+    if !options.yes && !confirm("Do you want to proceed with the restore?")? {
+        say!("Restore cancelled.");
+        return Ok(Outcome::Cancelled);
+    }
+
+    if pre_restore_snapshot {
+        let snapshot_paths = snapshots
+            .iter()
+            .find(|s| s.id.starts_with(&snapshot_id) || s.short_id == snapshot_id)
+            .map(|s| s.paths.clone())
+            .unwrap_or_default();
+        let current_paths = restic::pre_restore_paths(&snapshot_paths, &options.paths);
+
+        if current_paths.is_empty() {
+            say!("\nNothing to snapshot before restore (paths don't exist yet)");
+        } else {
+            say!("\nTaking pre-restore snapshot of {} path(s)...", current_paths.len());
+            let tags = vec![
+                restic::PRE_RESTORE_TAG.to_string(),
+                format!("restore-of-{}", snapshot_id),
+            ];
+            match restic::backup_with_tags(
+                &env,
+                &current_paths,
+                &restic::FilesFrom::default(),
+                &restic::Excludes::default(),
+                &tags,
+                Duration::from_secs(service_config.timeout_seconds),
+            ) {
+                Ok(summary) => {
+                    let id = summary.snapshot_id.as_deref().unwrap_or("unknown");
+                    say!("{} Pre-restore snapshot: {}", output::ok(), id);
+                    say!(
+                        "  Roll back with: restic-manager restore --service {} --destination {} --snapshot {}",
+                        service, dest_name, id
+                    );
+                }
+                Err(e) => {
+                    say_error!("\n{} Pre-restore snapshot failed: {}", output::fail(), e);
+                    say_error!("Restore aborted; use --no-pre-restore-snapshot to restore anyway.");
+                    return Ok(Outcome::Failed(1));
+                }
+            }
+        }
+    }
+
+    say!("\nStarting restore...\n");
+
+    // Perform restore with longer timeout (30 minutes)
+    let restore_timeout = Duration::from_secs(1800);
+
+    match restic::restore_snapshot(&env, &snapshot_id, target_dir, &options.paths, &limits, restore_timeout) {
+        Ok(()) => {
+            say!("\n{} Restore completed successfully!", output::ok());
+            if let Some(t) = target_dir {
+                say!("Files restored to: {}", t);
+            } else {
+                say!("Files restored to original locations");
+            }
+            Ok(Outcome::Success)
+        }
+        Err(e) => {
+            say_error!("\n{} Restore failed: {}", output::fail(), e);
+            Ok(Outcome::Failed(1))
+        }
+    }
+}
+
+/// Ask for confirmation (default: no)
+fn confirm(prompt: &str) -> Result<bool> {
+    Ok(Confirm::new().with_prompt(prompt).default(false).interact()?)
+}
+
+/// Show what a restore would write, without restoring anything
+fn preview_restore(
+    env: &restic::ResticEnv,
+    snapshot_id: &str,
+    target_dir: Option<&str>,
+    paths: &[String],
+) -> Result<Outcome> {
+    say!("\nDry run: checking what the restore would change...\n");
+
+    let preview = restic::preview_restore(env, snapshot_id, target_dir, paths, Duration::from_secs(600))?;
+
+    let preview_count = 10;
+    for (label, files) in [("Overwritten", &preview.overwritten_files), ("New", &preview.new_files)] {
+        if files.is_empty() {
+            continue;
+        }
+        say!("{} ({}):", label, files.len());
+        for file in files.iter().take(preview_count) {
+            say!("  {}", file);
+        }
+        if files.len() > preview_count {
+            say!("  ... and {} more", files.len() - preview_count);
+        }
+        say!();
+    }
+
+    say!(
+        "Total: {} files ({}) in snapshot",
+        preview.total_files,
+        restic::format_bytes(preview.total_bytes)
+    );
+    say!(
+        "  {} new, {} overwritten, {} unchanged",
+        preview.new_files.len(),
+        preview.overwritten_files.len(),
+        preview.unchanged_files
+    );
+    say!("  {} would be written", restic::format_bytes(preview.bytes_to_write));
+    say!("\nDry run: nothing was restored.");
+
+    Ok(Outcome::Success)
+}
+
+/// Restore a Docker volume from the archive of it in a snapshot
+///
+/// The archive is restored to a temp directory and extracted into the
+/// volume by a helper container, like during the backup.
+fn restore_docker_volume(
+    config: &Config,
+    env: &restic::ResticEnv,
+    snapshot_id: &str,
+    volume: &str,
+    options: &RestoreOptions,
+    limits: &restic::RestoreLimits,
+) -> Result<Outcome> {
+    let files = restic::list_snapshot_entries(env, snapshot_id, Duration::from_secs(60))?;
+    let archive = restic::find_volume_archive(&files, volume)
+        .context(format!("Snapshot '{}' contains no archive of volume '{}'", snapshot_id, volume))?;
+    say!("Volume archive: {} ({})", archive.path, restic::format_bytes(archive.size));
+
+    let docker_ops = docker_ops::for_backend(config.global.docker_backend, config.global.container_runtime)?;
+    let docker_timeout = Duration::from_secs(60);
+    let exists = docker_ops.volume_exists(volume, docker_timeout)?;
+    if !exists {
+        say!("Volume '{}' doesn't exist and will be created", volume);
+    } else if options.recreate_volume {
+        say!("Volume '{}' will be REMOVED and recreated (containers using it must be stopped)", volume);
+    } else {
+        say!("Volume '{}' exists; the archive is extracted over its current contents", volume);
+        say!("Use --recreate-volume to restore into an empty volume");
+    }
+    say!();
+
+    if !options.yes && !confirm("Do you want to proceed with the volume restore?")? {
+        say!("Restore cancelled.");
+        return Ok(Outcome::Cancelled);
+    }
+
+    let scratch = ScratchDir::create(&config.global.scratch_dir().join("restore"), &format!("{}-{}", volume, snapshot_id))?;
+    let temp_dir = scratch.path();
+
+    let restore_timeout = Duration::from_secs(1800);
+    let result = restic::restore_files(
+        env,
+        snapshot_id,
+        temp_dir.to_str(),
+        std::slice::from_ref(&archive.path),
+        limits,
+        restore_timeout,
+    )
+    .and_then(|_| {
+        let local_archive = temp_dir.join(archive.path.trim_start_matches('/'));
+        let recreate = exists && options.recreate_volume;
+        if !options.skip_space_check {
+            check_volume_space(docker_ops.as_ref(), volume, &local_archive, recreate, docker_timeout)?;
+        }
+        if recreate {
+            say!("Recreating volume '{}'...", volume);
+            docker_ops.recreate_volume(volume, docker_timeout)?;
+        }
+        say!("Extracting archive into volume '{}'...", volume);
+        docker_ops.restore_volume(volume, &local_archive, restore_timeout)
+    });
+
+    drop(scratch);
+
+    match result {
+        Ok(()) => {
+            say!("\n{} Volume '{}' restored from snapshot {}", output::ok(), volume, snapshot_id);
+            Ok(Outcome::Success)
+        }
+        Err(e) => {
+            say_error!("\n{} Volume restore failed: {:#}", output::fail(), e);
+            Ok(Outcome::Failed(1))
+        }
+    }
+}
+
+/// Fail before extracting if the volume's storage can't hold the unpacked
+/// archive, instead of filling the Docker data root halfway through
+///
+/// The current contents of a volume that gets recreated are freed first,
+/// so they count as available.
+fn check_volume_space(
+    docker_ops: &dyn DockerOperations,
+    volume: &str,
+    archive: &Path,
+    recreate: bool,
+    timeout: Duration,
+) -> Result<()> {
+    let required = docker::archive_unpacked_size(archive)?;
+    let mut available = docker_ops
+        .get_volume_free_space(volume, timeout)
+        .context(format!("Failed to check the free space of volume '{}'", volume))?;
+    if recreate {
+        available = available.saturating_add(docker_ops.get_volume_size(volume, timeout)?);
+    }
+
+    if required > available {
+        anyhow::bail!(
+            "Volume '{}' needs {} once extracted, but only {} is available (use --skip-space-check to extract anyway)",
+            volume,
+            restic::format_bytes(required),
+            restic::format_bytes(available)
+        );
+    }
+    say!(
+        "Space check: {} needed, {} available",
+        restic::format_bytes(required),
+        restic::format_bytes(available)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::docker_ops::mock::MockDockerOps;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_volume_space() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("volume.tar");
+        fs::write(&archive, vec![0u8; 10_000]).unwrap();
+        let timeout = Duration::from_secs(60);
+
+        let mock = MockDockerOps::new()
+            .with_volume_free_space("app_data", 4_000)
+            .with_volume_size("app_data", 8_000);
+        let err = check_volume_space(&mock, "app_data", &archive, false, timeout).unwrap_err();
+        assert!(err.to_string().contains("--skip-space-check"), "unexpected error: {}", err);
+
+        // Recreating the volume frees its current contents
+        assert!(check_volume_space(&mock, "app_data", &archive, true, timeout).is_ok());
+        assert!(check_volume_space(&MockDockerOps::new(), "app_data", &archive, false, timeout).is_ok());
+    }
+}
@@ -0,0 +1,80 @@
+//! `resume`: clear failure streaks and un-pause services
+
+use super::{Outcome, Services};
+use crate::managers::backoff::{FailureStreak, FailureTracker};
+use crate::utils::output;
+use crate::say;
+use anyhow::Result;
+
+/// Clear the streaks of the given services (all services with a streak if
+/// `None`), returning the cleared streaks by service name
+pub fn resume_services(
+    tracker: &FailureTracker,
+    services: &Services,
+    service: Option<&str>,
+) -> Result<Vec<(String, FailureStreak)>> {
+    let names: Vec<String> = match service {
+        Some(name) => {
+            super::find_service(services, name)?;
+            vec![name.to_string()]
+        }
+        None => {
+            let mut names: Vec<String> = tracker.load()?.into_keys().collect();
+            names.sort();
+            names
+        }
+    };
+
+    let mut resumed = Vec::new();
+    for name in names {
+        if let Some(streak) = tracker.resume(&name)? {
+            resumed.push((name, streak));
+        }
+    }
+    Ok(resumed)
+}
+
+/// Resume services and print what was cleared
+pub fn resume(tracker: &FailureTracker, services: &Services, service: Option<&str>) -> Result<Outcome> {
+    let resumed = resume_services(tracker, services, service)?;
+
+    for (name, streak) in &resumed {
+        say!(
+            "{} {}: cleared {} failed run(s){}",
+            output::ok(),
+            name,
+            streak.count,
+            if streak.paused { ", resumed" } else { "" }
+        );
+    }
+
+    if resumed.is_empty() {
+        say!("No failing or paused services");
+    }
+
+    Ok(Outcome::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resume_all_and_unknown_service() {
+        let temp_dir = TempDir::new().unwrap();
+        let tracker = FailureTracker::new(temp_dir.path().join("failures.json"));
+        tracker.record_failure("db", "disk full", 1, 1).unwrap();
+        tracker.record_failure("app", "timeout", 0, 1).unwrap();
+
+        let services = Services::new();
+        assert!(resume_services(&tracker, &services, Some("missing")).is_err());
+
+        let resumed = resume_services(&tracker, &services, None).unwrap();
+        let names: Vec<&str> = resumed.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["app", "db"]);
+        assert!(resumed[1].1.paused);
+
+        assert!(resume_services(&tracker, &services, None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! `run` and `serve`: back up services now or on webhook triggers
+
+use super::{exit_code, CommandContext, Outcome, Services};
+use crate::config::ResolvedServiceConfig;
+use crate::managers::backup::{BackupManager, RunOptions};
+use crate::utils::restic::format_bytes;
+use crate::utils::locker;
+use crate::utils::output::{self, Verbosity};
+use crate::{say, say_error};
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Files listed per destination by `run --dry-run` (all with `--verbose`)
+const DRY_RUN_MAX_ITEMS: usize = 50;
+
+/// Back up one service, or all enabled services
+pub fn run(backup_manager: &BackupManager, service: Option<&str>, options: &RunOptions) -> Result<Outcome> {
+    if let Some(service_name) = service {
+        say!("Running backup for service: {}", service_name);
+        match backup_manager.backup_service_with(service_name, options) {
+            Ok(()) => say!("{} Backup completed successfully", output::ok()),
+            Err(e) => match locker::already_running(&e) {
+                Some(running) => {
+                    say!("Skipped: {}", running);
+                    return Ok(Outcome::Failed(exit_code::LOCK_HELD));
+                }
+                None => return Err(e),
+            },
+        }
+    } else {
+        say!("Running backups for all enabled services...");
+        backup_manager.backup_all(options)?;
+        say!("{} All backups completed successfully", output::ok());
+    }
+
+    Ok(Outcome::Success)
+}
+
+/// Show what a backup of one service, or all enabled services, would add
+///
+/// Runs `restic backup --dry-run --verbose` per destination. Nothing is
+/// written to the repositories, retention is not applied and hooks and
+/// command sources are not run.
+pub fn dry_run(backup_manager: &BackupManager, services: &Services, service: Option<&str>) -> Result<Outcome> {
+    let mut names: Vec<&str> = match service {
+        Some(service_name) => vec![super::find_service(services, service_name)?.name.as_str()],
+        None => services.values().filter(|s| s.enabled).map(|s| s.name.as_str()).collect(),
+    };
+    names.sort_unstable();
+
+    let mut failed = 0;
+    for name in names {
+        say!("=== Dry Run: {} ===\n", name);
+        print_skipped_steps(&services[name]);
+
+        let forecast = match backup_manager.forecast_service(name, true) {
+            Ok(forecast) => forecast,
+            Err(e) => {
+                match locker::already_running(&e) {
+                    Some(running) => say_error!("{} Skipped: {}\n", output::fail(), running),
+                    None => say_error!("{} Dry run failed: {:#}\n", output::fail(), e),
+                }
+                failed += 1;
+                continue;
+            }
+        };
+
+        for (volume, size) in &forecast.volumes {
+            match size {
+                Ok(size) => say!("Volume {}: {} (archived at backup time, not included below)", volume, format_bytes(*size)),
+                Err(e) => say_error!("Volume {}: size unknown: {:#}", volume, e),
+            }
+        }
+        if !forecast.volumes.is_empty() {
+            say!();
+        }
+
+        for (target_name, result) in forecast.destinations {
+            say!("Destination: {}", target_name);
+            match result {
+                Ok(dry_run) => {
+                    let summary = &dry_run.summary;
+                    say!("  Files: {} new, {} changed, {} unmodified",
+                        summary.files_new, summary.files_changed, summary.files_unmodified);
+                    say!("  Would upload: {}", format_bytes(summary.data_added));
+                    let listed = if output::shows(Verbosity::Verbose) { dry_run.items.len() } else { DRY_RUN_MAX_ITEMS };
+                    for item in dry_run.items.iter().take(listed) {
+                        say!("    {:<8} {} ({})", item.action, item.path, format_bytes(item.size));
+                    }
+                    if dry_run.items.len() > listed {
+                        say!("    ... and {} more (--verbose lists all)", dry_run.items.len() - listed);
+                    }
+                }
+                Err(e) => {
+                    say_error!("  {} Dry run failed: {:#}", output::fail(), e);
+                    failed += 1;
+                }
+            }
+            say!();
+        }
+    }
+
+    if failed == 0 {
+        say!("{} Dry run completed, nothing was written", output::ok());
+        Ok(Outcome::Success)
+    } else {
+        say!("{} Dry run failed for {} service(s) or destination(s)", output::fail(), failed);
+        Ok(Outcome::Failed(exit_code::FAILURE))
+    }
+}
+
+/// Print the steps of a backup that a dry run leaves out
+fn print_skipped_steps(service: &ResolvedServiceConfig) {
+    if let Some(config) = &service.config {
+        let hooks = config.pre_backup_hooks.len()
+            + config.post_backup_hooks.len()
+            + config.on_success_hooks.len()
+            + config.on_failure_hooks.len();
+        if hooks > 0 {
+            say!("Hooks: {} not run (data they produce is only counted if already on disk)", hooks);
+        }
+        if !config.commands.is_empty() {
+            say!("Command sources: {} not run", config.commands.len());
+        }
+    }
+    say!("Retention: skipped\n");
+}
+
+/// Run the webhook server until it is stopped
+pub fn serve(ctx: &CommandContext, config_path: &Path, listen: Option<String>) -> Result<Outcome> {
+    let listen = listen.unwrap_or_else(|| ctx.config.server.listen.clone());
+    crate::managers::server::serve(ctx.config.clone(), config_path, &listen, Arc::clone(&ctx.ops))?;
+    Ok(Outcome::Success)
+}
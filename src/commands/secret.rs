@@ -0,0 +1,69 @@
+//! `secret`: manage repository passwords in the OS keyring (no config needed)
+
+use super::Outcome;
+use crate::utils::secrets;
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+use clap::Subcommand;
+
+/// `secret` subcommands
+#[derive(Subcommand)]
+pub enum SecretAction {
+    /// Store a repository password (prompts for the password)
+    Set {
+        /// Destination the password belongs to (defaults to the shared entry)
+        #[arg(short, long)]
+        destination: Option<String>,
+    },
+
+    /// Print a stored repository password
+    Get {
+        /// Destination the password belongs to (defaults to the shared entry)
+        #[arg(short, long)]
+        destination: Option<String>,
+    },
+
+    /// Remove a stored repository password
+    Delete {
+        /// Destination the password belongs to (defaults to the shared entry)
+        #[arg(short, long)]
+        destination: Option<String>,
+    },
+}
+
+/// Run a secret subcommand
+pub fn secret(action: &SecretAction) -> Result<Outcome> {
+    match action {
+        SecretAction::Set { destination } => {
+            let account = destination.as_deref().unwrap_or(secrets::SHARED_ACCOUNT);
+            let password = dialoguer::Password::new()
+                .with_prompt(format!("Repository password for '{}'", account))
+                .with_confirmation("Confirm password", "Passwords don't match")
+                .interact()?;
+
+            secrets::set_password(account, &password)?;
+            say!("{} Stored password for '{}' in the system keyring", output::ok(), account);
+        }
+        SecretAction::Get { destination } => {
+            let account = destination.as_deref().unwrap_or(secrets::SHARED_ACCOUNT);
+            match secrets::get_password(account)? {
+                Some(password) => println!("{}", password),
+                None => {
+                    say_error!("No password stored for '{}'", account);
+                    return Ok(Outcome::Failed(1));
+                }
+            }
+        }
+        SecretAction::Delete { destination } => {
+            let account = destination.as_deref().unwrap_or(secrets::SHARED_ACCOUNT);
+            if secrets::delete_password(account)? {
+                say!("{} Removed password for '{}'", output::ok(), account);
+            } else {
+                say!("No password stored for '{}'", account);
+            }
+        }
+    }
+
+    Ok(Outcome::Success)
+}
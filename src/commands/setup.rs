@@ -0,0 +1,241 @@
+//! `setup`: create directories, initialize repositories and install cron jobs
+
+use super::{CommandContext, Outcome};
+use crate::config::Config;
+use crate::utils;
+use crate::utils::output;
+use crate::{say, say_error, say_warn};
+use anyhow::Result;
+use std::path::Path;
+
+/// Which setup steps to run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SetupOptions {
+    /// Show what would be done without making changes
+    pub dry_run: bool,
+    /// Only install cron jobs
+    pub cron_only: bool,
+    /// Only create directories and repositories
+    pub dirs_only: bool,
+}
+
+/// Prepare the host for scheduled backups
+pub fn setup(ctx: &CommandContext, config_path: &Path, options: SetupOptions) -> Result<Outcome> {
+    let SetupOptions { dry_run, cron_only, dirs_only } = options;
+    let (config, services) = (&ctx.config, &ctx.resolved);
+
+    say!("=== Setting up restic-manager ===\n");
+
+    if dry_run {
+        say!("DRY RUN MODE - No changes will be made\n");
+    }
+
+    let mut _total_steps = 0;
+    let mut _completed_steps = 0;
+
+    // Step 1: Create directories (unless cron-only)
+    if !cron_only {
+        _total_steps += 1;
+        say!("[1/4] Creating directories...");
+
+        // Create log directory
+        let log_dir = &config.global.log_directory;
+        if dry_run {
+            say!("  [DRY RUN] Would create: {}", log_dir.display());
+        } else {
+            match std::fs::create_dir_all(log_dir) {
+                Ok(_) => {
+                    say!("  {} Created {}", output::ok(), log_dir.display());
+                    _completed_steps += 1;
+                }
+                Err(e) => say_error!("  {} Failed to create {}: {}", output::fail(), log_dir.display(), e),
+            }
+        }
+
+        // Create docker base directory if it doesn't exist
+        let docker_base = &config.global.docker_base;
+        if !docker_base.exists() {
+            if dry_run {
+                say!("  [DRY RUN] Would create: {}", docker_base.display());
+            } else {
+                match std::fs::create_dir_all(docker_base) {
+                    Ok(_) => say!("  {} Created {}", output::ok(), docker_base.display()),
+                    Err(e) => say_error!("  {} Failed to create {}: {}", output::fail(), docker_base.display(), e),
+                }
+            }
+        } else {
+            say!("  {} {} already exists", output::ok(), docker_base.display());
+        }
+
+        say!();
+    }
+
+    // Step 2: Initialize restic repositories (unless cron-only)
+    if !cron_only {
+        _total_steps += 1;
+        say!("[2/4] Initializing restic repositories...");
+
+        for (service_name, service_config) in services {
+            if !service_config.enabled {
+                continue;
+            }
+
+            for target_name in &service_config.targets {
+                let destination = match config.destinations.get(target_name) {
+                    Some(d) => d,
+                    None => {
+                        say_error!("  {} Destination '{}' not found", output::fail(), target_name);
+                        continue;
+                    }
+                };
+
+                let repo_url = utils::restic::build_repository_url(destination, service_name, None);
+
+                if dry_run {
+                    say!("  [DRY RUN] Would initialize: {} -> {}", service_name, repo_url);
+                } else {
+                    let result = utils::restic::ResticEnv::for_destination(&config.global, target_name, destination, &repo_url)
+                        .and_then(|env| ctx.ops.init_repository(&env, std::time::Duration::from_secs(300)));
+
+                    match result {
+                        Ok(_) => {
+                            say!("  {} Initialized {} at {} ({})", output::ok(), service_name, target_name, destination.url);
+                            _completed_steps += 1;
+                        }
+                        Err(e) => say_error!("  {} Failed to initialize {} at {}: {}", output::fail(), service_name, target_name, e),
+                    }
+                }
+            }
+        }
+
+        say!();
+    }
+
+    // Step 3: Install cron jobs (unless dirs-only)
+    if !dirs_only {
+        _total_steps += 1;
+        say!("[3/4] Installing cron jobs...");
+
+        #[cfg(unix)]
+        {
+            for (service_name, service_config) in services {
+                // Temporarily disabled services keep their job, the run
+                // skips them until the date
+                if !service_config.enabled && service_config.disabled_until.is_none() {
+                    say!("  - Skipping {} (disabled)", service_name);
+                    continue;
+                }
+
+                // Validate cron schedule
+                if !utils::cron::validate_cron_schedule(&service_config.schedule) {
+                    say_error!("  {} Invalid cron schedule for {}: {}", output::fail(), service_name, service_config.schedule);
+                    continue;
+                }
+
+                match utils::cron::add_cron_job(
+                    service_name,
+                    &service_config.schedule,
+                    config_path,
+                    dry_run,
+                ) {
+                    Ok(_) => {
+                        say!("  {} Added job for '{}' ({})", output::ok(), service_name, service_config.schedule);
+                        _completed_steps += 1;
+                    }
+                    Err(e) => say_error!("  {} Failed to add job for {}: {}", output::fail(), service_name, e),
+                }
+            }
+
+            install_verify_job(config, config_path, dry_run);
+        }
+
+        #[cfg(windows)]
+        {
+            let _ = config_path;
+            say_error!("  {} Cron job setup is not supported on Windows", output::fail());
+            say_error!("    Use Task Scheduler instead");
+        }
+
+        say!();
+    }
+
+    // Step 4: Verify setup
+    _total_steps += 1;
+    say!("[4/4] Verifying setup...");
+
+    if !dirs_only {
+        #[cfg(unix)]
+        {
+            if dry_run {
+                say!("  [DRY RUN] Would verify cron jobs");
+            } else {
+                match utils::cron::list_cron_jobs() {
+                    Ok(jobs) => {
+                        if jobs.is_empty() {
+                            say_warn!("  {} No cron jobs found", output::warn());
+                        } else {
+                            say!("  {} {} cron job(s) installed", output::ok(), jobs.len());
+                        }
+                    }
+                    Err(e) => say_error!("  {} Failed to list cron jobs: {}", output::fail(), e),
+                }
+            }
+        }
+    }
+
+    if !cron_only {
+        if config.global.log_directory.exists() {
+            say!("  {} Log directory accessible", output::ok());
+        } else {
+            say_error!("  {} Log directory not found", output::fail());
+        }
+
+        if config.global.docker_base.exists() {
+            say!("  {} Docker base directory accessible", output::ok());
+        } else {
+            say_error!("  {} Docker base directory not found", output::fail());
+        }
+    }
+
+    say!();
+
+    // Summary
+    if dry_run {
+        say!("=== Dry Run Complete ===");
+        say!("No changes were made. Run without --dry-run to apply changes.");
+    } else {
+        say!("=== Setup Complete ===");
+        say!();
+        say!("Next steps:");
+        say!("  1. View scheduled jobs: crontab -l");
+        say!("  2. Test a backup manually:");
+        say!("     restic-manager run --service <SERVICE_NAME>");
+        say!("  3. Check logs in: {}", config.global.log_directory.display());
+    }
+
+    Ok(Outcome::Success)
+}
+
+/// Install the scheduled verify job, or remove it once `verify_schedule`
+/// is no longer set
+#[cfg(unix)]
+fn install_verify_job(config: &Config, config_path: &Path, dry_run: bool) {
+    let subset = config.global.verify_read_data_subset.as_deref();
+    match config.global.verify_schedule {
+        Some(ref schedule) => match utils::cron::add_verify_cron_job(schedule, subset, config_path, dry_run) {
+            Ok(()) => say!(
+                "  {} Added verify job ({}, {})",
+                output::ok(),
+                schedule,
+                subset.map_or("metadata only".to_string(), |s| format!("reading {} of the data", s))
+            ),
+            Err(e) => say_error!("  {} Failed to add verify job: {}", output::fail(), e),
+        },
+        None if dry_run => {}
+        None => match utils::cron::remove_verify_cron_job() {
+            Ok(true) => say!("  {} Removed verify job (no verify_schedule)", output::ok()),
+            Ok(false) => {}
+            Err(e) => say_error!("  {} Failed to remove verify job: {}", output::fail(), e),
+        },
+    }
+}
@@ -0,0 +1,229 @@
+//! `snapshots`: list a service's snapshots per destination
+//!
+//! With `--sizes`, each snapshot's restore size and the data its backup
+//! added are shown too, to find the backup that made a repository grow.
+//! Both come from the summary restic 0.17+ stores in snapshots; for older
+//! snapshots the restore size is read with `restic stats` (one call per
+//! snapshot, for the newest 20 only, and never on cold storage) and the
+//! added data is unknown.
+
+use super::{CommandContext, Outcome};
+use crate::utils::restic::{self, ResticEnv, Snapshot};
+use crate::utils::output;
+use crate::{say, say_error, say_warn};
+use anyhow::Result;
+use std::time::Duration;
+
+/// Timeout of `restic stats` for one snapshot
+const SNAPSHOT_STATS_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Snapshots without a summary whose restore size is read with `restic
+/// stats`, newest first (each call walks the snapshot's tree)
+const MAX_STATS_LOOKUPS: usize = 20;
+
+/// Restore size and added data of one snapshot (None if unknown)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SnapshotSize {
+    restore_size: Option<u64>,
+    data_added: Option<u64>,
+}
+
+/// Print the snapshots of a service (optionally on one destination only)
+pub fn snapshots(
+    ctx: &CommandContext,
+    service: &str,
+    destination: Option<&str>,
+    tags: &[String],
+    sizes: bool,
+) -> Result<Outcome> {
+    let service_config = ctx.service(service)?;
+
+    say!("=== Snapshots for service: {} ===\n", service);
+
+    // Filter targets if destination is specified
+    let targets: Vec<String> = match destination {
+        Some(_) => super::choose_destination(service, service_config, destination)?
+            .into_iter()
+            .collect(),
+        None => service_config.targets.clone(),
+    };
+
+    // List snapshots for each destination
+    for target_name in &targets {
+        let destination = ctx.destination(target_name)?;
+
+        say!("Destination: {}", target_name);
+        say!("Repository: {}\n", destination.url);
+
+        let env = match ctx.repository_env(service_config, target_name) {
+            Ok(env) => env,
+            Err(e) => {
+                say_error!("  {} Failed to list snapshots: {}\n", output::fail(), e);
+                continue;
+            }
+        };
+
+        // List snapshots
+        match ctx.ops.list_snapshots(&env, Duration::from_secs(60)) {
+            Ok(mut snapshots) => {
+                snapshots.retain(|s| s.has_any_tag(tags));
+                if snapshots.is_empty() {
+                    say!("  No snapshots found.\n");
+                } else if sizes {
+                    print_with_sizes(ctx, &env, &snapshots);
+                } else {
+                    // Print table header
+                    say!("  {:<10} {:<20} {:<15} Tags", "ID", "Date", "Hostname");
+                    say!("  {}", "-".repeat(60));
+
+                    for snapshot in &snapshots {
+                        say!(
+                            "  {:<10} {:<20} {:<15} {}",
+                            &snapshot.short_id,
+                            super::format_snapshot_time(&snapshot.time),
+                            &snapshot.hostname,
+                            snapshot.tags.join(", ")
+                        );
+                    }
+                }
+
+                if !snapshots.is_empty() {
+                    say!("\n  Total: {} snapshots", snapshots.len());
+
+                    // Get repository stats
+                    if let Ok(stats) = ctx.ops.get_stats(&env, Duration::from_secs(30)) {
+                        say!(
+                            "  Repository size: {} ({} files)",
+                            restic::format_bytes(stats.total_size),
+                            stats.total_file_count
+                        );
+                    }
+
+                    say!();
+                }
+            }
+            Err(e) => {
+                say_error!("  {} Failed to list snapshots: {}\n", output::fail(), e);
+            }
+        }
+    }
+
+    Ok(Outcome::Success)
+}
+
+/// Print the snapshots with their restore size and added data
+fn print_with_sizes(ctx: &CommandContext, env: &ResticEnv, snapshots: &[Snapshot]) {
+    // Reading sizes from cold storage would read the archive tier
+    let limit = if env.is_cold_storage() { 0 } else { MAX_STATS_LOOKUPS };
+    let lookups = stats_lookups(snapshots, limit);
+    let sizes: Vec<SnapshotSize> = snapshots
+        .iter()
+        .zip(&lookups)
+        .map(|(snapshot, &lookup)| snapshot_size(ctx, env, snapshot, lookup))
+        .collect();
+    let largest = largest_addition(&sizes);
+
+    say!("  {:<10} {:<20} {:>12} {:>12}  Tags", "ID", "Date", "Restore size", "Added");
+    say!("  {}", "-".repeat(70));
+    for (index, (snapshot, size)) in snapshots.iter().zip(&sizes).enumerate() {
+        say!(
+            "  {:<10} {:<20} {:>12} {:>12}{} {}",
+            &snapshot.short_id,
+            super::format_snapshot_time(&snapshot.time),
+            size.restore_size.map_or_else(|| "-".to_string(), restic::format_bytes),
+            size.data_added.map_or_else(|| "-".to_string(), restic::format_bytes),
+            if Some(index) == largest { "*" } else { " " },
+            snapshot.tags.join(", ")
+        );
+    }
+    if largest.is_some() {
+        say!("\n  * = added the most data");
+    }
+
+    let skipped = snapshots.iter().zip(&lookups).filter(|(s, &lookup)| s.summary.is_none() && !lookup).count();
+    if skipped > 0 && env.is_cold_storage() {
+        say!("\n  Restore sizes of {} snapshots without a summary not read (cold storage)", skipped);
+    } else if skipped > 0 {
+        say!(
+            "\n  Restore sizes of the {} oldest snapshots without a summary not read (only the newest {} are)",
+            skipped, MAX_STATS_LOOKUPS
+        );
+    }
+}
+
+/// Which snapshots get their restore size from `restic stats`: the newest
+/// `limit` of those without a summary (snapshots are listed oldest first)
+fn stats_lookups(snapshots: &[Snapshot], limit: usize) -> Vec<bool> {
+    let mut remaining = limit;
+    let mut lookups: Vec<bool> = snapshots
+        .iter()
+        .rev()
+        .map(|snapshot| {
+            let lookup = snapshot.summary.is_none() && remaining > 0;
+            if lookup {
+                remaining -= 1;
+            }
+            lookup
+        })
+        .collect();
+    lookups.reverse();
+    lookups
+}
+
+/// Sizes from the snapshot's summary, else (if `lookup`) the restore size
+/// from `restic stats`
+fn snapshot_size(ctx: &CommandContext, env: &ResticEnv, snapshot: &Snapshot, lookup: bool) -> SnapshotSize {
+    if let Some(ref summary) = snapshot.summary {
+        return SnapshotSize {
+            restore_size: Some(summary.total_bytes_processed),
+            data_added: Some(summary.data_added),
+        };
+    }
+    if !lookup {
+        return SnapshotSize::default();
+    }
+
+    match ctx.ops.snapshot_stats(env, &snapshot.id, SNAPSHOT_STATS_TIMEOUT) {
+        Ok(stats) => SnapshotSize { restore_size: Some(stats.total_size), data_added: None },
+        Err(e) => {
+            say_warn!("  {} Failed to get the size of snapshot {}: {:#}", output::warn(), snapshot.short_id, e);
+            SnapshotSize::default()
+        }
+    }
+}
+
+/// Index of the snapshot that added the most data, if any added some
+fn largest_addition(sizes: &[SnapshotSize]) -> Option<usize> {
+    sizes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, size)| Some((index, size.data_added?)))
+        .filter(|(_, added)| *added > 0)
+        .max_by_key(|(_, added)| *added)
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_largest_addition() {
+        let size = |data_added| SnapshotSize { restore_size: Some(1000), data_added };
+        assert_eq!(largest_addition(&[size(Some(10)), size(None), size(Some(500)), size(Some(20))]), Some(2));
+        assert_eq!(largest_addition(&[size(None), size(Some(0))]), None);
+        assert_eq!(largest_addition(&[]), None);
+    }
+
+    #[test]
+    fn test_stats_lookups() {
+        let snapshot = |summary: bool| Snapshot {
+            summary: summary.then(Default::default),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(false), snapshot(false), snapshot(true), snapshot(false)];
+        assert_eq!(stats_lookups(&snapshots, 2), vec![false, true, false, true]);
+        assert_eq!(stats_lookups(&snapshots, 10), vec![true, true, false, true]);
+        assert_eq!(stats_lookups(&snapshots, 0), vec![false; 4]);
+    }
+}
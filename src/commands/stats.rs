@@ -0,0 +1,252 @@
+//! `stats`: compare repository sizes across services and destinations
+//!
+//! Restore size, stored (deduplicated and compressed) size and snapshot
+//! count of every repository are collected concurrently, then shown per
+//! repository and totalled per destination.
+
+use super::{CommandContext, Outcome};
+use crate::utils::restic::{self, format_bytes, RepositoryStats};
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Repositories queried at the same time
+const CONCURRENCY: usize = 4;
+
+/// Timeout of `restic stats` per repository and mode
+const STATS_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Stats of one repository (or why they are missing)
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoStats {
+    pub service: String,
+    pub destination: String,
+    pub stats: Option<RepositoryStats>,
+    pub error: Option<String>,
+    /// Not queried, the destination is cold storage
+    pub cold_storage: bool,
+}
+
+/// Sums over the repositories of one destination
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DestinationTotal {
+    pub destination: String,
+    pub repositories: usize,
+    pub snapshots_count: u64,
+    pub restore_size: u64,
+    pub raw_size: u64,
+    pub dedup_ratio: Option<f64>,
+}
+
+/// Show stats of every repository of one service (or all enabled services)
+pub fn stats(ctx: &CommandContext, service: Option<&str>, json: bool) -> Result<Outcome> {
+    let mut repos: Vec<(String, String)> = match service {
+        Some(name) => {
+            let service_config = ctx.service(name)?;
+            service_config.targets.iter().map(|t| (name.to_string(), t.clone())).collect()
+        }
+        None => ctx
+            .resolved
+            .values()
+            .filter(|s| s.enabled)
+            .flat_map(|s| s.targets.iter().map(|t| (s.name.clone(), t.clone())))
+            .collect(),
+    };
+    repos.sort();
+
+    if !json {
+        say!("Collecting stats of {} repositories...\n", repos.len());
+    }
+    let results = collect(ctx, repos);
+    let totals = destination_totals(&results);
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+
+    if json {
+        let output = serde_json::json!({ "repositories": results, "destinations": totals });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print_tables(&results, &totals);
+    }
+
+    if failed == 0 {
+        Ok(Outcome::Success)
+    } else {
+        if !json {
+            say_error!("\n{} Stats of {} repositories could not be collected", output::fail(), failed);
+        }
+        Ok(Outcome::Failed(1))
+    }
+}
+
+/// Query the repositories, `CONCURRENCY` at a time, keeping their order
+fn collect(ctx: &CommandContext, repos: Vec<(String, String)>) -> Vec<RepoStats> {
+    let queue = Mutex::new(repos.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..CONCURRENCY {
+            scope.spawn(|| loop {
+                let Some((index, (service, destination))) = queue.lock().unwrap_or_else(|e| e.into_inner()).next()
+                else {
+                    break;
+                };
+                let cold_storage = ctx.config.destinations.get(&destination).is_some_and(|d| d.cold_storage);
+                let (stats, error) = if cold_storage {
+                    (None, None)
+                } else {
+                    let result = ctx
+                        .service(&service)
+                        .and_then(|service| ctx.repository_env(service, &destination))
+                        .and_then(|env| restic::repository_stats(&env, STATS_TIMEOUT));
+                    match result {
+                        Ok(stats) => (Some(stats), None),
+                        Err(e) => (None, Some(format!("{:#}", e))),
+                    }
+                };
+                results
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((index, RepoStats { service, destination, stats, error, cold_storage }));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, stats)| stats).collect()
+}
+
+/// Total the repositories per destination (failed ones are left out)
+pub fn destination_totals(results: &[RepoStats]) -> Vec<DestinationTotal> {
+    let mut totals: BTreeMap<&str, DestinationTotal> = BTreeMap::new();
+    for result in results {
+        let Some(ref stats) = result.stats else {
+            continue;
+        };
+        let total = totals.entry(&result.destination).or_insert_with(|| DestinationTotal {
+            destination: result.destination.clone(),
+            ..Default::default()
+        });
+        total.repositories += 1;
+        total.snapshots_count += stats.snapshots_count;
+        total.restore_size += stats.restore_size;
+        total.raw_size += stats.raw_size;
+    }
+
+    totals
+        .into_values()
+        .map(|mut total| {
+            total.dedup_ratio = (total.raw_size > 0).then(|| total.restore_size as f64 / total.raw_size as f64);
+            total
+        })
+        .collect()
+}
+
+fn format_ratio(ratio: Option<f64>) -> String {
+    ratio.map_or_else(|| "-".to_string(), |r| format!("{:.1}x", r))
+}
+
+fn print_tables(results: &[RepoStats], totals: &[DestinationTotal]) {
+    say!(
+        "{:<20} {:<12} {:>9} {:>12} {:>12} {:>7}",
+        "Service", "Destination", "Snapshots", "Restore size", "Stored", "Ratio"
+    );
+    say!("{}", "-".repeat(77));
+    for result in results {
+        match result.stats {
+            Some(ref stats) => say!(
+                "{:<20} {:<12} {:>9} {:>12} {:>12} {:>7}",
+                result.service,
+                result.destination,
+                stats.snapshots_count,
+                format_bytes(stats.restore_size),
+                format_bytes(stats.raw_size),
+                format_ratio(stats.dedup_ratio())
+            ),
+            None if result.cold_storage => say!(
+                "{:<20} {:<12} - cold storage, not queried",
+                result.service, result.destination
+            ),
+            None => say!(
+                "{:<20} {:<12} {} {}",
+                result.service,
+                result.destination,
+                output::fail(),
+                result.error.as_deref().unwrap_or("unknown error").lines().next().unwrap_or_default()
+            ),
+        }
+    }
+
+    if totals.is_empty() {
+        return;
+    }
+
+    say!("\nPer destination:");
+    say!(
+        "{:<20} {:<12} {:>9} {:>12} {:>12} {:>7}",
+        "Destination", "Repositories", "Snapshots", "Restore size", "Stored", "Ratio"
+    );
+    say!("{}", "-".repeat(77));
+    for total in totals {
+        say!(
+            "{:<20} {:<12} {:>9} {:>12} {:>12} {:>7}",
+            total.destination,
+            total.repositories,
+            total.snapshots_count,
+            format_bytes(total.restore_size),
+            format_bytes(total.raw_size),
+            format_ratio(total.dedup_ratio)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(service: &str, destination: &str, restore_size: u64, raw_size: u64) -> RepoStats {
+        RepoStats {
+            service: service.to_string(),
+            destination: destination.to_string(),
+            stats: Some(RepositoryStats {
+                snapshots_count: 10,
+                restore_size,
+                raw_size,
+                ..Default::default()
+            }),
+            error: None,
+            cold_storage: false,
+        }
+    }
+
+    #[test]
+    fn test_destination_totals() {
+        let failed = RepoStats {
+            service: "db".to_string(),
+            destination: "remote".to_string(),
+            stats: None,
+            error: Some("connection refused".to_string()),
+            cold_storage: false,
+        };
+        let totals = destination_totals(&[
+            repo("app", "local", 800, 100),
+            repo("db", "local", 400, 200),
+            repo("app", "remote", 800, 100),
+            failed,
+        ]);
+
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].destination, "local");
+        assert_eq!(totals[0].repositories, 2);
+        assert_eq!(totals[0].snapshots_count, 20);
+        assert_eq!(totals[0].restore_size, 1200);
+        assert_eq!(totals[0].raw_size, 300);
+        assert_eq!(totals[0].dedup_ratio, Some(4.0));
+        assert_eq!(totals[1].repositories, 1);
+        assert_eq!(format_ratio(totals[1].dedup_ratio), "8.0x");
+    }
+}
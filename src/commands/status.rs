@@ -0,0 +1,333 @@
+//! `status` and `list`: service overview and per-destination health
+//!
+//! A destination is healthy while its latest backup is younger than the
+//! service's `max_age_hours`, or, without it, than the longest gap between
+//! two scheduled runs plus the timeout. Up to twice that age it's a warning,
+//! beyond that critical. `status --all` checks every service and
+//! destination (a few repositories at a time) and exits with the worst
+//! health, for monitoring scripts.
+
+use super::{exit_code, CommandContext, Outcome, Services};
+use crate::config::Config;
+use crate::managers::history::{self, sparkline};
+use crate::managers::outcome::Health;
+use crate::managers::notification::format_duration;
+use crate::utils::restic;
+use crate::utils::ResticOperations;
+use crate::utils::output;
+use crate::{say, say_error, say_warn};
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Repositories queried at the same time by `status --all`
+const CONCURRENCY: usize = 4;
+
+/// Show the overview, the freshness of every repository (`all`), or
+/// detailed status of one service
+pub fn status(ctx: &CommandContext, service: Option<&str>, all: bool) -> Result<Outcome> {
+    match service {
+        Some(service_name) => service_status(ctx, service_name)?,
+        None if all => return Ok(freshness_overview(ctx)),
+        None => overview(&ctx.config, &ctx.resolved),
+    }
+    Ok(Outcome::Success)
+}
+
+fn overview(config: &Config, services: &Services) {
+    say!("=== Backup Status Overview ===\n");
+    say!("Services configured: {}", services.len());
+    say!("Destinations: {}", config.destinations.len());
+    say!("\nServices:");
+    for (name, svc) in services {
+        let status = match svc.disabled_until {
+            Some(until) => format!("disabled until {}", until),
+            None if svc.enabled => "enabled".to_string(),
+            None => "disabled".to_string(),
+        };
+        say!("  {} - {} ({})", name, svc.description, status);
+    }
+    say!("\nRun `status --all` for the latest backup of every destination.");
+}
+
+/// Latest backup of one service on one destination
+#[derive(Debug, Clone)]
+struct Freshness {
+    service: String,
+    destination: String,
+    snapshots: usize,
+    /// Age of the latest snapshot
+    age: Option<Duration>,
+    max_age: Duration,
+    /// None if the repository was skipped (cold storage)
+    health: Option<Health>,
+    error: Option<String>,
+}
+
+/// Table of every enabled service's destinations with their health
+fn freshness_overview(ctx: &CommandContext) -> Outcome {
+    let mut repos: Vec<(String, String)> = ctx
+        .resolved
+        .values()
+        .filter(|s| s.enabled)
+        .flat_map(|s| s.targets.iter().map(|t| (s.name.clone(), t.clone())))
+        .collect();
+    repos.sort();
+
+    let rows = collect_freshness(ctx, repos);
+
+    say!("{:<20} {:<12} {:>9} {:>10} {:>10}  Health", "Service", "Destination", "Snapshots", "Age", "Expected");
+    say!("{}", "-".repeat(78));
+    for row in &rows {
+        let badge = match row.health {
+            Some(Health::Healthy) => format!("{} healthy", output::ok()),
+            Some(Health::Warning) => format!("{} warning", output::warn()),
+            Some(Health::Critical) => format!("{} critical", output::fail()),
+            None => "- skipped (cold storage)".to_string(),
+        };
+        let detail = match row.error {
+            Some(ref error) => format!(": {}", error.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim()),
+            None if row.health.is_some() && row.age.is_none() => ": no backups".to_string(),
+            None => String::new(),
+        };
+        say!(
+            "{:<20} {:<12} {:>9} {:>10} {:>10}  {}{}",
+            row.service,
+            row.destination,
+            if row.error.is_some() || row.health.is_none() { "-".to_string() } else { row.snapshots.to_string() },
+            row.age.map_or_else(|| "-".to_string(), |age| format_duration(age.as_secs())),
+            format_duration(row.max_age.as_secs()),
+            badge,
+            detail
+        );
+    }
+
+    let worst = rows.iter().filter_map(|row| row.health).max();
+    match worst {
+        None | Some(Health::Healthy) => Outcome::Success,
+        Some(health) => {
+            let count = rows.iter().filter(|row| row.health == Some(health)).count();
+            say_error!("\n{} {} destination(s) {}", output::fail(), count, if health == Health::Critical { "critical" } else { "overdue" });
+            Outcome::Failed(health_exit_code(health))
+        }
+    }
+}
+
+/// Exit code of `status --all` for the worst health found
+fn health_exit_code(health: Health) -> i32 {
+    match health {
+        Health::Healthy => 0,
+        Health::Warning => exit_code::HEALTH_WARNING,
+        Health::Critical => exit_code::HEALTH_CRITICAL,
+    }
+}
+
+/// Query the repositories, `CONCURRENCY` at a time, keeping their order
+fn collect_freshness(ctx: &CommandContext, repos: Vec<(String, String)>) -> Vec<Freshness> {
+    let queue = Mutex::new(repos.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..CONCURRENCY {
+            scope.spawn(|| loop {
+                let Some((index, (service, destination))) = queue.lock().unwrap_or_else(|e| e.into_inner()).next()
+                else {
+                    break;
+                };
+                let row = freshness(ctx, service, destination);
+                results.lock().unwrap_or_else(|e| e.into_inner()).push((index, row));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, row)| row).collect()
+}
+
+fn freshness(ctx: &CommandContext, service: String, destination: String) -> Freshness {
+    let service_config = ctx.resolved.get(&service);
+    let mut row = Freshness {
+        max_age: service_config.map(|s| s.max_backup_age()).unwrap_or_default(),
+        service,
+        destination,
+        snapshots: 0,
+        age: None,
+        health: Some(Health::Critical),
+        error: None,
+    };
+    // Listing snapshots would read from the archive tier
+    if ctx.config.destinations.get(&row.destination).is_some_and(|d| d.cold_storage) {
+        row.health = None;
+        return row;
+    }
+
+    let snapshots = ctx
+        .service(&row.service)
+        .and_then(|s| ctx.repository_env(s, &row.destination))
+        .and_then(|env| ctx.ops.list_snapshots(&env, Duration::from_secs(60)));
+    match snapshots {
+        Ok(snapshots) => {
+            row.snapshots = snapshots.len();
+            // Snapshots are listed oldest first
+            row.age = snapshots.last().and_then(|latest| snapshot_age(&latest.time));
+            if let Some(age) = row.age {
+                row.health = Some(Health::of_age(age, row.max_age));
+            }
+        }
+        Err(e) => row.error = Some(format!("{:#}", e)),
+    }
+    row
+}
+
+/// Time since a snapshot was taken
+fn snapshot_age(time: &str) -> Option<Duration> {
+    let taken = chrono::DateTime::parse_from_rfc3339(time).ok()?;
+    Some(chrono::Utc::now().signed_duration_since(taken).to_std().unwrap_or_default())
+}
+
+fn service_status(ctx: &CommandContext, service_name: &str) -> Result<()> {
+    let service_config = ctx.service(service_name)?;
+
+    say!("=== Status for service: {} ===\n", service_name);
+    say!("Description: {}", service_config.description);
+    match service_config.disabled_until {
+        Some(until) => say!("Enabled: No (until {})", until),
+        None => say!("Enabled: {}", if service_config.enabled { "Yes" } else { "No" }),
+    }
+    say!("Schedule: {}", service_config.schedule);
+    say!("Timeout: {}", format_duration(service_config.timeout_seconds));
+    say!("Targets: {}", service_config.targets.join(", "));
+    say!();
+
+    let history = history::open_store(&ctx.config.global);
+
+    // Show status for each destination
+    for target_name in &service_config.targets {
+        let destination = ctx.destination(target_name)?;
+
+        say!("Destination: {}", target_name);
+        say!("  Repository: {}", destination.url);
+
+        if destination.cold_storage {
+            // Size and snapshot queries would read from the archive tier
+            say!("  Cold storage: live status skipped");
+        } else {
+            match ctx.repository_env(service_config, target_name) {
+                Ok(env) => print_live_status(ctx.ops.as_ref(), &env, service_config.max_backup_age()),
+                Err(e) => {
+                    say_error!("  {} Failed to get status: {}\n", output::fail(), e);
+                    continue;
+                }
+            }
+        }
+
+        // Recent run trends from local history
+        match history.recent(service_name, target_name, 14) {
+            Ok(runs) if !runs.is_empty() => {
+                let durations: Vec<u64> = runs.iter().map(|r| r.duration_secs).collect();
+                let data_added: Vec<u64> = runs.iter().map(|r| r.data_added.unwrap_or(0)).collect();
+                let failed = runs.iter().filter(|r| !r.success).count();
+                let last = runs.last().unwrap();
+
+                say!("  History (last {} runs{}):", runs.len(),
+                    if failed > 0 { format!(", {} failed", failed) } else { String::new() });
+                say!("    Duration:   {}  latest {}",
+                    sparkline(&durations),
+                    format_duration(last.duration_secs));
+                say!("    Data added: {}  latest {}",
+                    sparkline(&data_added),
+                    restic::format_bytes(last.data_added.unwrap_or(0)));
+                if let Some(trigger) = last.trigger {
+                    match last.reason {
+                        Some(ref reason) => say!("    Last run:   {} ({})", trigger, reason),
+                        None => say!("    Last run:   {}", trigger),
+                    }
+                }
+                if let Some(ref error) = last.retention_error {
+                    say_warn!("    {} Retention failed: {}", output::warn(), error.lines().next().unwrap_or_default());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                say_warn!("  {} Failed to read backup history: {}", output::warn(), e);
+            }
+        }
+
+        say!();
+    }
+
+    Ok(())
+}
+
+/// Snapshot count, latest backup and size, queried from the repository
+fn print_live_status(ops: &dyn ResticOperations, env: &restic::ResticEnv, max_age: Duration) {
+    // Get snapshot count
+    match ops.count_snapshots(env, Duration::from_secs(30)) {
+        Ok(count) => {
+            say!("  Snapshots: {}", count);
+
+            if count > 0 {
+                // Get latest snapshot
+                if let Ok(Some(latest)) = ops.get_latest_snapshot(env, Duration::from_secs(30)) {
+                    say!("  Last Backup: {}", super::format_snapshot_time(&latest.time));
+
+                    // Calculate age and health
+                    if let Some(age) = snapshot_age(&latest.time) {
+                        say!("  Age: {} ago", format_duration(age.as_secs()));
+
+                        let limit = format_duration(max_age.as_secs());
+                        let health = match Health::of_age(age, max_age) {
+                            Health::Healthy => format!("{} Healthy (expected within {})", output::ok(), limit),
+                            Health::Warning => format!("{} Warning (overdue, expected within {})", output::warn(), limit),
+                            Health::Critical => {
+                                format!("{} Critical (more than twice the expected {})", output::fail(), limit)
+                            }
+                        };
+                        say!("  Health: {}", health);
+                    }
+                }
+
+                // Get repository size
+                if let Ok(stats) = ops.get_stats(env, Duration::from_secs(30)) {
+                    say!("  Repository Size: {}", restic::format_bytes(stats.total_size));
+                }
+            } else {
+                say!("  Health: {} No backups found", output::fail());
+            }
+        }
+        Err(e) => {
+            say_error!("  {} Failed to get status: {}", output::fail(), e);
+        }
+    }
+}
+
+/// List all configured services
+pub fn list(services: &Services) -> Result<Outcome> {
+    say!("Configured services:");
+    for (name, svc) in services {
+        say!("  {}", name);
+        say!("    Description: {}", svc.description);
+        match svc.disabled_until {
+            Some(until) => say!("    Enabled: false (until {})", until),
+            None => say!("    Enabled: {}", svc.enabled),
+        }
+        say!("    Schedule: {}", svc.schedule);
+        say!("    Targets: {}", svc.targets.join(", "));
+        say!();
+    }
+    Ok(Outcome::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worst_health_exit_code() {
+        let worst = [Health::Healthy, Health::Critical, Health::Warning].into_iter().max().unwrap();
+        assert_eq!(worst, Health::Critical);
+        assert_eq!(health_exit_code(worst), exit_code::HEALTH_CRITICAL);
+        assert_eq!(health_exit_code(Health::Warning), exit_code::HEALTH_WARNING);
+    }
+}
@@ -0,0 +1,257 @@
+//! `test-restore`: restore drill for a service
+//!
+//! The latest snapshot on each destination is restored into a throwaway
+//! temp directory (and, with `--volumes`, its volume archives into throwaway
+//! Docker volumes), the service's `restore_test_hooks` validate the result,
+//! and everything is removed again. Meant to run from cron, so the outcome
+//! is only in the exit code and the output.
+
+use super::{exit_code, CommandContext, Outcome};
+use crate::config::{Hook, ResolvedServiceConfig};
+use crate::utils::docker_ops::{self, DockerOperations};
+use crate::utils::restic::{self, ResticEnv, RestoreLimits};
+use crate::utils::output;
+use crate::{say, say_error, say_warn};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Timeout of restoring a snapshot (or a volume archive)
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Timeout of quick Docker calls
+const DOCKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Restore the latest snapshot of a service into throwaway locations and
+/// validate it, on one destination or all of the service's destinations
+pub fn test_restore(
+    ctx: &CommandContext,
+    service: &str,
+    destination: Option<&str>,
+    volumes: bool,
+) -> Result<Outcome> {
+    let service_config = ctx.service(service)?;
+    let targets = match super::choose_destination(service, service_config, destination)? {
+        Some(dest_name) => vec![dest_name],
+        None => service_config.targets.clone(),
+    };
+
+    say!("=== Test Restore: {} ===\n", service);
+
+    let docker: Option<Box<dyn DockerOperations>> = if volumes {
+        Some(docker_ops::for_backend(ctx.config.global.docker_backend, ctx.config.global.container_runtime)?)
+    } else {
+        None
+    };
+
+    let mut failed = 0;
+    for dest_name in &targets {
+        say!("Destination: {}", dest_name);
+        // Drills must not trigger archive retrievals; restore those by hand
+        if ctx.config.destinations.get(dest_name).is_some_and(|d| d.cold_storage) {
+            say!("  - Skipped: cold storage (use restore --acknowledge-cold-storage)\n");
+            continue;
+        }
+        match test_destination(ctx, service_config, dest_name, docker.as_deref()) {
+            Ok(snapshot_id) => say!("  {} Restore of snapshot {} verified\n", output::ok(), snapshot_id),
+            Err(e) => {
+                say_error!("  {} Test restore failed: {:#}\n", output::fail(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed == 0 {
+        say!("{} Test restore passed on {} destination(s)", output::ok(), targets.len());
+        Ok(Outcome::Success)
+    } else {
+        say!("{} Test restore failed on {} of {} destination(s)", output::fail(), failed, targets.len());
+        Ok(Outcome::Failed(exit_code::RESTIC))
+    }
+}
+
+/// Test-restore the latest snapshot on one destination, returning its ID
+fn test_destination(
+    ctx: &CommandContext,
+    service: &ResolvedServiceConfig,
+    dest_name: &str,
+    docker: Option<&dyn DockerOperations>,
+) -> Result<String> {
+    let env = ctx.repository_env(service, dest_name)?;
+
+    let snapshots = ctx.ops.list_snapshots(&env, Duration::from_secs(60))?;
+    // Snapshots are listed oldest first
+    let snapshot = snapshots.last().context("No snapshots to restore")?;
+    say!(
+        "  Snapshot: {} - {}",
+        snapshot.short_id,
+        super::format_snapshot_time(&snapshot.time)
+    );
+
+    let restore_dir = ctx
+        .config
+        .global
+        .scratch_dir()
+        .join("test-restore")
+        .join(format!("{}-{}-{}", service.name, dest_name, snapshot.short_id));
+    let mut drill = Drill { restore_dir, volumes: Vec::new(), docker };
+
+    let result = drill.run(service, dest_name, &env, &snapshot.short_id);
+    drill.clean_up();
+    result.map(|_| snapshot.short_id.clone())
+}
+
+/// Throwaway locations of one test restore, removed by `clean_up`
+struct Drill<'a> {
+    restore_dir: PathBuf,
+    /// Throwaway volumes (original name, throwaway name)
+    volumes: Vec<(String, String)>,
+    docker: Option<&'a dyn DockerOperations>,
+}
+
+impl Drill<'_> {
+    fn run(
+        &mut self,
+        service: &ResolvedServiceConfig,
+        dest_name: &str,
+        env: &ResticEnv,
+        snapshot_id: &str,
+    ) -> Result<()> {
+        // Start from an empty directory in case an earlier drill was killed
+        if self.restore_dir.exists() {
+            fs::remove_dir_all(&self.restore_dir).context("Failed to clear the restore directory")?;
+        }
+        fs::create_dir_all(&self.restore_dir).context("Failed to create the restore directory")?;
+
+        // The repository environment carries the configured bandwidth limits
+        let limits = RestoreLimits { limit_download_kbps: None, nice: true };
+        restic::restore_files(env, snapshot_id, self.restore_dir.to_str(), &[], &limits, RESTORE_TIMEOUT)?;
+        say!("  {} Restored to {}", output::ok(), self.restore_dir.display());
+
+        if let Some(docker) = self.docker {
+            let volumes = service.config.as_ref().map(|c| c.volumes.as_slice()).unwrap_or_default();
+            if !volumes.is_empty() {
+                let files = restic::list_snapshot_entries(env, snapshot_id, Duration::from_secs(60))?;
+                for volume in volumes {
+                    self.restore_volume(docker, &files, volume, snapshot_id)?;
+                }
+            }
+        }
+
+        let hooks = service
+            .config
+            .as_ref()
+            .map(|c| c.restore_test_hooks.as_slice())
+            .unwrap_or_default();
+        if hooks.is_empty() {
+            say!("  {} No restore_test_hooks configured, only the restore itself was tested", output::warn());
+            return Ok(());
+        }
+
+        let env = self.hook_env(&service.name, dest_name, snapshot_id);
+        for hook in hooks {
+            run_validation_hook(hook, service, &env)?;
+        }
+        Ok(())
+    }
+
+    /// Extract a volume's archive from the restore directory into a
+    /// throwaway volume
+    fn restore_volume(
+        &mut self,
+        docker: &dyn DockerOperations,
+        files: &[restic::SnapshotFile],
+        volume: &str,
+        snapshot_id: &str,
+    ) -> Result<()> {
+        let archive = restic::find_volume_archive(files, volume)
+            .context(format!("Snapshot contains no archive of volume '{}'", volume))?;
+        let throwaway = format!("rm-test-{}-{}", volume, snapshot_id);
+
+        docker.recreate_volume(&throwaway, DOCKER_TIMEOUT)?;
+        self.volumes.push((volume.to_string(), throwaway.clone()));
+
+        let local_archive = self.restore_dir.join(archive.path.trim_start_matches('/'));
+        docker
+            .restore_volume(&throwaway, &local_archive, RESTORE_TIMEOUT)
+            .context(format!("Failed to restore volume '{}'", volume))?;
+        say!("  {} Volume {} restored to {}", output::ok(), volume, throwaway);
+        Ok(())
+    }
+
+    /// Environment of the validation hooks
+    fn hook_env(&self, service: &str, dest_name: &str, snapshot_id: &str) -> Vec<(String, String)> {
+        let mut env = vec![
+            ("RM_SERVICE".to_string(), service.to_string()),
+            ("RM_DESTINATION".to_string(), dest_name.to_string()),
+            ("RM_SNAPSHOT_ID".to_string(), snapshot_id.to_string()),
+            ("RM_RESTORE_DIR".to_string(), self.restore_dir.display().to_string()),
+        ];
+        for (volume, throwaway) in &self.volumes {
+            env.push((volume_env_name(volume), throwaway.clone()));
+        }
+        env
+    }
+
+    /// Remove the restore directory and throwaway volumes (failures are
+    /// only warned about)
+    fn clean_up(&mut self) {
+        if let Some(docker) = self.docker {
+            for (_, throwaway) in self.volumes.drain(..) {
+                if let Err(e) = docker.remove_volume(&throwaway, DOCKER_TIMEOUT) {
+                    say_warn!("  {} Failed to remove volume {}: {:#}", output::warn(), throwaway, e);
+                }
+            }
+        }
+        if self.restore_dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.restore_dir) {
+                say_warn!("  {} Failed to remove {}: {}", output::warn(), self.restore_dir.display(), e);
+            }
+        }
+    }
+}
+
+/// Run one validation hook; its failure fails the drill unless
+/// `continue_on_error` is set
+fn run_validation_hook(hook: &Hook, service: &ResolvedServiceConfig, env: &[(String, String)]) -> Result<()> {
+    let name = if hook.name.is_empty() { &hook.command } else { &hook.name };
+    let timeout = Duration::from_secs(hook.timeout_seconds.unwrap_or(service.timeout_seconds));
+
+    match crate::utils::command::run_shell_command_with_env(
+        &hook.command,
+        hook.working_dir.as_deref(),
+        Some(timeout),
+        env,
+    ) {
+        Ok(_) => {
+            say!("  {} Validation: {}", output::ok(), name);
+            Ok(())
+        }
+        Err(e) if hook.continue_on_error => {
+            say_warn!("  {} Validation failed (continue_on_error): {}: {:#}", output::warn(), name, e);
+            Ok(())
+        }
+        Err(e) => Err(e).context(format!("Validation hook '{}' failed", name)),
+    }
+}
+
+/// `RM_VOLUME_<NAME>` variable holding the throwaway copy of a volume
+fn volume_env_name(volume: &str) -> String {
+    let name: String = volume
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("RM_VOLUME_{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_env_name() {
+        assert_eq!(volume_env_name("app_db"), "RM_VOLUME_APP_DB");
+        assert_eq!(volume_env_name("nextcloud-data.v2"), "RM_VOLUME_NEXTCLOUD_DATA_V2");
+    }
+}
@@ -0,0 +1,120 @@
+//! `validate`: check the configuration and password commands
+
+use super::{exit_code, CommandContext, Outcome};
+use crate::config::PasswordSource;
+use crate::utils::secrets;
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use std::time::Duration;
+
+/// Days after which an expired `disabled_until` is warned about
+const STALE_DISABLED_DAYS: i64 = 30;
+
+/// Print a config summary and test-run each distinct password command
+pub fn validate(ctx: &CommandContext) -> Result<Outcome> {
+    let config = &ctx.config;
+    say!("Configuration is valid!");
+    say!("Services: {}", ctx.resolved.len());
+    say!("Destinations: {}", config.destinations.len());
+    say!("Profiles: {}", config.profiles.len());
+
+    // Expired temporary disables are harmless but tend to be forgotten
+    let today = Local::now().date_naive();
+    let mut stale: Vec<_> = config
+        .services
+        .iter()
+        .filter_map(|(name, service)| Some((name, stale_days(service.disabled_until?, today)?)))
+        .collect();
+    stale.sort();
+    for (name, days) in stale {
+        say!(
+            "{} Service '{}': disabled_until expired {} days ago and can be removed",
+            output::warn(),
+            name, days
+        );
+    }
+
+    let mut credentials: Vec<_> = config
+        .destinations
+        .iter()
+        .filter_map(|(name, destination)| Some((name, destination.credentials_warning()?)))
+        .collect();
+    credentials.sort();
+    for (name, warning) in credentials {
+        say!("{} Destination '{}': {}", output::warn(), name, warning);
+    }
+
+    // Make it visible where retention of append-only destinations runs
+    let mut append_only: Vec<_> = config.destinations.iter().filter(|(_, d)| d.append_only).map(|(n, _)| n.as_str()).collect();
+    append_only.sort();
+    if !append_only.is_empty() {
+        if config.global.trusted_host {
+            say!(
+                "{} Trusted host: forget/prune run on append-only destinations ({}); this config must not be on the hosts being backed up",
+                output::warn(),
+                append_only.join(", ")
+            );
+        } else {
+            say!(
+                "Append-only destinations: {} (forget/prune are skipped here, run `maintain` from a trusted host)",
+                append_only.join(", ")
+            );
+        }
+    }
+
+    // Test-run password commands (each distinct command once)
+    let mut commands: Vec<(&str, &str)> = Vec::new();
+    for (name, destination) in &config.destinations {
+        if destination.password_source(&config.global) != PasswordSource::File {
+            continue;
+        }
+        if let Some(command) = destination.password_command(&config.global) {
+            if !commands.iter().any(|(c, _)| *c == command) {
+                commands.push((command, name));
+            }
+        }
+    }
+
+    if commands.is_empty() {
+        return Ok(Outcome::Success);
+    }
+
+    say!("\nPassword commands:");
+    let mut failed = false;
+    for (command, destination) in commands {
+        match secrets::check_password_command(command, Duration::from_secs(30)) {
+            Ok(()) => say!("  {} {} (used by '{}')", output::ok(), command, destination),
+            Err(e) => {
+                say_error!("  {} {} (used by '{}'): {}", output::fail(), command, destination, e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        Ok(Outcome::Failed(exit_code::CONFIG))
+    } else {
+        Ok(Outcome::Success)
+    }
+}
+
+/// Days since `disabled_until` expired, if longer than `STALE_DISABLED_DAYS`
+fn stale_days(disabled_until: NaiveDate, today: NaiveDate) -> Option<i64> {
+    let days = (today - disabled_until).num_days();
+    (days > STALE_DISABLED_DAYS).then_some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_days() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        assert_eq!(stale_days(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), today), Some(59));
+        assert_eq!(stale_days(NaiveDate::from_ymd_opt(2025, 2, 15).unwrap(), today), None);
+        assert_eq!(stale_days(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(), today), None);
+    }
+}
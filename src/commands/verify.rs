@@ -0,0 +1,223 @@
+//! `verify`: run `restic check` against service repositories
+//!
+//! Problems reported by restic are grouped by category (index, pack, tree,
+//! ...) with a suggested remediation each. With `--repair-index`,
+//! repositories with index or tree errors get `restic repair index` and are
+//! checked again.
+
+use super::{exit_code, CommandContext, Outcome};
+use crate::utils::restic::{self, CheckCategory, CheckResult, ReadData, ResticEnv};
+use crate::utils::output;
+use crate::{say, say_error};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Issues listed per category before the rest are summarized
+const MAX_LISTED_ISSUES: usize = 3;
+
+/// Counts of repository checks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifySummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Repositories whose index was rebuilt
+    pub repaired: usize,
+    /// Failed repositories per problem category
+    pub categories: BTreeMap<CheckCategory, usize>,
+}
+
+/// Check one service (or all enabled services) on every destination
+pub fn verify(
+    ctx: &CommandContext,
+    service: Option<&str>,
+    read_data: &ReadData,
+    repair_index: bool,
+) -> Result<Outcome> {
+    say!("=== Verifying Repositories ===\n");
+
+    match read_data {
+        ReadData::None => {}
+        ReadData::All => say!("{} Deep verification enabled (this will take longer)\n", output::warn()),
+        ReadData::Subset(subset) => say!("Reading back {} of the pack data\n", subset),
+    }
+
+    // Determine which services to verify
+    let services_to_verify: Vec<_> = if let Some(service_name) = service {
+        vec![(service_name, ctx.service(service_name)?)]
+    } else {
+        ctx.resolved
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .map(|(name, config)| (name.as_str(), config))
+            .collect()
+    };
+
+    let mut summary = VerifySummary::default();
+
+    // Timeout: 5 minutes for normal check, 30 minutes for deep check
+    let timeout = if read_data.reads_data() {
+        Duration::from_secs(1800)
+    } else {
+        Duration::from_secs(300)
+    };
+
+    for (service_name, service_config) in services_to_verify {
+        say!("Service: {}", service_name);
+
+        for target_name in &service_config.targets {
+            let destination = ctx.destination(target_name)?;
+
+            say!("  Destination: {} ({})", target_name, destination.url);
+            summary.total += 1;
+
+            let env = match ctx.repository_env(service_config, target_name) {
+                Ok(env) => env,
+                Err(e) => {
+                    say_error!("    {} Check failed: {}\n", output::fail(), e);
+                    summary.failed += 1;
+                    *summary.categories.entry(CheckCategory::Other).or_default() += 1;
+                    continue;
+                }
+            };
+
+            let mut report = run_check(&env, read_data, timeout);
+
+            print_report(&report);
+
+            if !report.passed() && repair_index && report.needs_index_repair() {
+                if let Some(repaired) = try_repair_index(&env, read_data, timeout) {
+                    summary.repaired += 1;
+                    report = repaired;
+                    print_report(&report);
+                }
+            }
+
+            if report.passed() {
+                summary.passed += 1;
+            } else {
+                summary.failed += 1;
+                for category in report.by_category().keys().filter(|c| c.is_error()) {
+                    *summary.categories.entry(*category).or_default() += 1;
+                }
+            }
+
+            say!();
+        }
+    }
+
+    say!("=== Verification Summary ===");
+    say!("Total checks: {}", summary.total);
+    say!("Passed: {}", summary.passed);
+    say!("Failed: {}", summary.failed);
+    if summary.repaired > 0 {
+        say!("Index rebuilt: {}", summary.repaired);
+    }
+
+    if !summary.categories.is_empty() {
+        say!("\nProblems by category:");
+        for (category, count) in &summary.categories {
+            say!("  {} ({} repositories)", category.name(), count);
+            say!("    → {}", category.remediation());
+        }
+    }
+
+    if summary.failed == 0 {
+        say!("\n{} All checks passed!", output::ok());
+        Ok(Outcome::Success)
+    } else {
+        say!("\n{} Some checks failed. Please review the errors above.", output::fail());
+        Ok(Outcome::Failed(exit_code::RESTIC))
+    }
+}
+
+/// Rebuild the index and check again, returning the new report
+///
+/// Returns `None` when the repair could not run (append-only destination or
+/// restic error); the original report stands then.
+fn try_repair_index(env: &ResticEnv, read_data: &ReadData, timeout: Duration) -> Option<CheckResult> {
+    if env.is_append_only() {
+        say!("    {} Skipping index repair: destination is append-only", output::warn());
+        return None;
+    }
+
+    say!("    Rebuilding index...");
+    if let Err(e) = restic::repair_index(env, timeout) {
+        say_error!("    {} Index repair failed: {:#}", output::fail(), e);
+        return None;
+    }
+
+    say!("    {} Index rebuilt, checking again", output::ok());
+    Some(run_check(env, read_data, timeout))
+}
+
+/// Check a repository; a check that couldn't run counts as failed
+fn run_check(env: &ResticEnv, read_data: &ReadData, timeout: Duration) -> CheckResult {
+    restic::check_repository_with(env, read_data, timeout).unwrap_or_else(|e| CheckResult::from_error(&e))
+}
+
+fn print_report(report: &CheckResult) {
+    match report {
+        CheckResult::Ok => {
+            say!("    {} Repository structure is OK", output::ok());
+            say!("    {} No errors found", output::ok());
+        }
+        CheckResult::Warnings(warnings) => {
+            say!("    {} Repository structure is OK", output::ok());
+            say!("    {} No errors found, {} warning(s):", output::warn(), warnings.len());
+        }
+        CheckResult::Errors(_) => say!("    {} Check found problems:", output::fail()),
+    }
+
+    for (category, issues) in report.by_category() {
+        let marker = if category.is_error() { output::fail() } else { "ℹ".to_string() };
+        say!("    {} {} ({})", marker, category.name(), issues.len());
+        for issue in issues.iter().take(MAX_LISTED_ISSUES) {
+            say!("        {}", issue.message);
+        }
+        if issues.len() > MAX_LISTED_ISSUES {
+            say!("        ... and {} more", issues.len() - MAX_LISTED_ISSUES);
+        }
+        say!("      → {}", category.remediation());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_result_from_output() {
+        let report = CheckResult::from_output(true, "load indexes\npack 5d4e21ef: not referenced in any index\nno errors were found");
+        assert!(report.passed());
+        assert!(matches!(report, CheckResult::Warnings(_)));
+        assert_eq!(report.by_category().keys().copied().collect::<Vec<_>>(), vec![CheckCategory::Unused]);
+        assert!(!report.needs_index_repair());
+
+        let report = CheckResult::from_output(
+            false,
+            "error: pack 3a5e02b1 contained in several indexes\nFatal: repository contains errors",
+        );
+        assert!(!report.passed());
+        assert!(report.needs_index_repair());
+
+        // An unrecognized failure still carries a reason
+        let report = CheckResult::from_error(&anyhow::anyhow!("Failed to execute restic check"));
+        assert!(!report.passed());
+        assert_eq!(report.issues()[0].category, CheckCategory::Other);
+        assert_eq!(report.issues()[0].message, "Failed to execute restic check");
+
+        // The exit status decides, not words in paths restic happened to print
+        let report = CheckResult::from_output(
+            true,
+            "using temporary cache in /tmp/error-pages/restic-check-cache-1\ncheck snapshots, trees and blobs\nno errors were found",
+        );
+        assert_eq!(report, CheckResult::Ok);
+        assert!(report.ensure_passed().is_ok());
+
+        let report = CheckResult::from_output(false, "error for tree 4b1c3f2e:\n  id 4b1c3f2e not found in repository");
+        let error = report.ensure_passed().unwrap_err().to_string();
+        assert!(error.contains("error for tree 4b1c3f2e:"));
+    }
+}
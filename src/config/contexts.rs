@@ -0,0 +1,175 @@
+//! Named configuration contexts
+//!
+//! Like kubectl contexts: a small TOML file maps names (e.g. `homelab`,
+//! `parents-nas`) to config file paths, with one of them marked current.
+//! The config path is chosen in this order:
+//! 1. `--config` given on the command line
+//! 2. `--context` given on the command line
+//! 3. The current context from the contexts file
+//! 4. The built-in default path
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Config path used when neither a config nor a context is selected
+pub const DEFAULT_CONFIG_PATH: &str = "/home/valerie/backup-config.toml";
+
+/// A registered configuration context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextEntry {
+    /// Path to the config file for this context
+    pub config: PathBuf,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// Contents of the contexts file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Contexts {
+    /// Name of the context used when `--context` isn't given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    #[serde(default)]
+    pub contexts: BTreeMap<String, ContextEntry>,
+}
+
+impl Contexts {
+    /// Default location (~/.config/restic-manager/contexts.toml)
+    pub fn default_path() -> PathBuf {
+        if let Some(config_dir) = dirs::config_dir() {
+            config_dir.join("restic-manager").join("contexts.toml")
+        } else {
+            PathBuf::from("/tmp/restic-manager-contexts.toml")
+        }
+    }
+
+    /// Load the contexts file (empty if it doesn't exist)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read contexts file: {:?}", path))?;
+        toml::from_str(&content).context(format!("Failed to parse contexts file: {:?}", path))
+    }
+
+    /// Save the contexts file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self).context("Failed to serialize contexts")?;
+        fs::write(path, content).context(format!("Failed to write contexts file: {:?}", path))
+    }
+
+    /// Register (or replace) a context
+    pub fn add(&mut self, name: &str, config: PathBuf, description: String) {
+        self.contexts
+            .insert(name.to_string(), ContextEntry { config, description });
+    }
+
+    /// Make a context the current one
+    pub fn use_context(&mut self, name: &str) -> Result<()> {
+        if !self.contexts.contains_key(name) {
+            anyhow::bail!("Unknown context '{}'", name);
+        }
+        self.current = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Config path of a context
+    pub fn config_path(&self, name: &str) -> Result<PathBuf> {
+        self.contexts
+            .get(name)
+            .map(|entry| entry.config.clone())
+            .context(format!(
+                "Unknown context '{}' (available: {})",
+                name,
+                self.contexts.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+    }
+}
+
+/// Resolve the config file to use from the command line options and contexts file
+pub fn resolve_config_path(
+    explicit: Option<&Path>,
+    context: Option<&str>,
+    contexts_path: &Path,
+) -> Result<PathBuf> {
+    if let Some(path) = explicit {
+        return Ok(path.to_path_buf());
+    }
+
+    let contexts = Contexts::load(contexts_path)?;
+
+    if let Some(name) = context {
+        return contexts.config_path(name);
+    }
+
+    match contexts.current {
+        Some(ref name) => contexts.config_path(name),
+        None => Ok(PathBuf::from(DEFAULT_CONFIG_PATH)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_contexts_roundtrip_and_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("contexts.toml");
+
+        let mut contexts = Contexts::load(&path).unwrap();
+        assert!(contexts.contexts.is_empty());
+
+        contexts.add("homelab", PathBuf::from("/etc/homelab.toml"), String::new());
+        contexts.add("nas", PathBuf::from("/etc/nas.toml"), "Parents' NAS".to_string());
+        assert!(contexts.use_context("missing").is_err());
+        contexts.use_context("nas").unwrap();
+        contexts.save(&path).unwrap();
+
+        let loaded = Contexts::load(&path).unwrap();
+        assert_eq!(loaded, contexts);
+        assert_eq!(loaded.current.as_deref(), Some("nas"));
+    }
+
+    #[test]
+    fn test_resolve_config_path_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("contexts.toml");
+
+        // No contexts file: built-in default
+        assert_eq!(
+            resolve_config_path(None, None, &path).unwrap(),
+            PathBuf::from(DEFAULT_CONFIG_PATH)
+        );
+
+        let mut contexts = Contexts::default();
+        contexts.add("homelab", PathBuf::from("/etc/homelab.toml"), String::new());
+        contexts.add("nas", PathBuf::from("/etc/nas.toml"), String::new());
+        contexts.use_context("homelab").unwrap();
+        contexts.save(&path).unwrap();
+
+        assert_eq!(
+            resolve_config_path(None, None, &path).unwrap(),
+            PathBuf::from("/etc/homelab.toml")
+        );
+        assert_eq!(
+            resolve_config_path(None, Some("nas"), &path).unwrap(),
+            PathBuf::from("/etc/nas.toml")
+        );
+        assert_eq!(
+            resolve_config_path(Some(Path::new("/tmp/explicit.toml")), Some("nas"), &path).unwrap(),
+            PathBuf::from("/tmp/explicit.toml")
+        );
+        assert!(resolve_config_path(None, Some("unknown"), &path).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Label-based service discovery
+//!
+//! With `[discovery] enabled = true`, containers and volumes labeled
+//! `restic-manager.enable=true` are turned into services when the config is
+//! loaded, similar to Traefik's label model. Optional labels refine them:
+//!
+//! - `restic-manager.name`: service name (default: Compose project, then
+//!   container or volume name). Containers and volumes with the same name
+//!   become one service
+//! - `restic-manager.schedule`, `restic-manager.targets` (comma-separated),
+//!   `restic-manager.profile`: override the `[discovery]` defaults
+//! - `restic-manager.paths`: comma-separated paths to back up as well
+//! - `restic-manager.volumes`: volumes to back up instead of the named
+//!   volumes the container mounts
+//! - `restic-manager.strategy`: `live` (default), `pause` or `stop` the
+//!   service's containers while their volumes are archived
+//!
+//! Services defined in the config file win over discovered ones with the
+//! same name; labeled objects that don't make a valid service are skipped
+//! with a warning instead of failing the load.
+
+use super::loader::validate_service;
+use super::types::*;
+use crate::utils::docker::{self, ContainerInfo, VolumeInfo};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Label selecting the containers and volumes to discover
+pub const ENABLE_LABEL: &str = "restic-manager.enable=true";
+
+const LABEL_PREFIX: &str = "restic-manager.";
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+
+/// Services found from labels
+#[derive(Debug, Default)]
+pub struct Discovered {
+    pub services: BTreeMap<String, ServiceConfig>,
+    /// Labeled objects that could not become a service, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Labeled containers and volumes making up one service
+#[derive(Default)]
+struct Group<'a> {
+    sources: Vec<String>,
+    labels: Vec<&'a HashMap<String, String>>,
+    /// Label selecting the group's containers, for pause/stop
+    pause_label: Option<String>,
+    volumes: Vec<String>,
+}
+
+/// Value of a `restic-manager.<key>` label (empty counts as unset)
+fn label<'a>(labels: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    labels
+        .get(&format!("{}{}", LABEL_PREFIX, key))
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+fn is_enabled(labels: &HashMap<String, String>) -> bool {
+    label(labels, "enable") == Some("true")
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn push_unique(list: &mut Vec<String>, values: impl IntoIterator<Item = String>) {
+    for value in values {
+        if !list.contains(&value) {
+            list.push(value);
+        }
+    }
+}
+
+impl Group<'_> {
+    /// The group's value of a label; containers and volumes must agree on it
+    fn single(&self, key: &str) -> Result<Option<String>, String> {
+        let mut values: Vec<&str> = self.labels.iter().filter_map(|l| label(l, key)).collect();
+        values.sort_unstable();
+        values.dedup();
+        match values.as_slice() {
+            [] => Ok(None),
+            [value] => Ok(Some(value.to_string())),
+            _ => Err(format!("conflicting {}{} labels ({})", LABEL_PREFIX, key, values.join(", "))),
+        }
+    }
+
+    fn into_service(self, discovery: &DiscoveryConfig) -> Result<ServiceConfig, String> {
+        let mut paths = Vec::new();
+        for labels in &self.labels {
+            push_unique(&mut paths, label(labels, "paths").map(split_list).unwrap_or_default());
+        }
+        if paths.is_empty() && self.volumes.is_empty() {
+            return Err("no volumes or paths to back up".to_string());
+        }
+
+        let pause_containers = match self.single("strategy")?.as_deref() {
+            None | Some("live") => None,
+            Some(strategy @ ("pause" | "stop")) => {
+                let label = self.pause_label.clone().ok_or_else(|| {
+                    format!(
+                        "{}strategy={} needs a {}name label to select the containers",
+                        LABEL_PREFIX, strategy, LABEL_PREFIX
+                    )
+                })?;
+                let mode = if strategy == "pause" { PauseMode::Pause } else { PauseMode::Stop };
+                Some(ContainerPause { label, mode, max_pause_seconds: default_max_pause_seconds() })
+            }
+            Some(other) => {
+                return Err(format!("unknown {}strategy '{}' (use live, pause or stop)", LABEL_PREFIX, other))
+            }
+        };
+
+        Ok(ServiceConfig {
+            enabled: true,
+            disabled_until: None,
+            tags: Vec::new(),
+            hostname: None,
+            profile: self.single("profile")?.or_else(|| discovery.profile.clone()),
+            description: format!("Discovered from {}", self.sources.join(", ")),
+            schedule: self.single("schedule")?.unwrap_or_else(|| discovery.schedule.clone()),
+            targets: self
+                .single("targets")?
+                .map(|t| split_list(&t))
+                .unwrap_or_else(|| discovery.targets.clone()),
+            timeout_seconds: None,
+            time_budget_minutes: None,
+            max_age_hours: None,
+            retention_daily: None,
+            retention_weekly: None,
+            retention_monthly: None,
+            retention_yearly: None,
+            retention_hourly: None,
+            retention_last: None,
+            retention_within: None,
+            retention: Default::default(),
+            retries: None,
+            retry_delay_seconds: None,
+            retry_backoff_multiplier: None,
+            post_backup_verify: None,
+            notify_on: vec![],
+            config: Some(BackupConfig {
+                paths,
+                files_from: vec![],
+                files_from_verbatim: vec![],
+                volumes: self.volumes,
+                containers: vec![],
+                compose_project: None,
+                compose_file: None,
+                stop_during_backup: false,
+                pause_containers,
+                excludes: vec![],
+                iexcludes: vec![],
+                exclude_files: vec![],
+                exclude_larger_than: None,
+                one_file_system: None,
+                pre_backup_hooks: vec![],
+                post_backup_hooks: vec![],
+                commands: vec![],
+                on_failure_hooks: vec![],
+                on_success_hooks: vec![],
+                restore_test_hooks: vec![],
+            }),
+        })
+    }
+}
+
+/// Turn labeled containers and volumes into service configs
+pub fn services_from_labels(
+    discovery: &DiscoveryConfig,
+    containers: &[ContainerInfo],
+    volumes: &[VolumeInfo],
+) -> Discovered {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+
+    for container in containers.iter().filter(|c| is_enabled(&c.labels)) {
+        let (name, pause_label) = if let Some(name) = label(&container.labels, "name") {
+            (name.to_string(), Some(format!("{}name={}", LABEL_PREFIX, name)))
+        } else if let Some(project) = container.labels.get(COMPOSE_PROJECT_LABEL) {
+            (project.clone(), Some(format!("{}={}", COMPOSE_PROJECT_LABEL, project)))
+        } else {
+            (container.name.clone(), None)
+        };
+
+        let group = groups.entry(name).or_default();
+        group.sources.push(format!("container {}", container.name));
+        group.labels.push(&container.labels);
+        group.pause_label = group.pause_label.take().or(pause_label);
+        let volumes = label(&container.labels, "volumes")
+            .map(split_list)
+            .unwrap_or_else(|| container.volumes.clone());
+        push_unique(&mut group.volumes, volumes);
+    }
+
+    for volume in volumes.iter().filter(|v| is_enabled(&v.labels)) {
+        let name = label(&volume.labels, "name").unwrap_or(&volume.name).to_string();
+
+        let group = groups.entry(name).or_default();
+        group.sources.push(format!("volume {}", volume.name));
+        group.labels.push(&volume.labels);
+        push_unique(&mut group.volumes, [volume.name.clone()]);
+    }
+
+    let mut discovered = Discovered::default();
+    for (name, group) in groups {
+        let sources = group.sources.join(", ");
+        match group.into_service(discovery) {
+            Ok(service) => {
+                discovered.services.insert(name, service);
+            }
+            Err(reason) => discovered.skipped.push(format!("{} ({}): {}", name, sources, reason)),
+        }
+    }
+    discovered
+}
+
+/// Add discovered services to the config, returning the names added
+///
+/// A service defined in the config file keeps its definition; a discovered
+/// service that fails validation is skipped.
+pub fn merge_discovered(config: &mut Config, discovered: Discovered) -> Vec<String> {
+    for skipped in &discovered.skipped {
+        warn!("Skipping discovered service {}", skipped);
+    }
+
+    let mut added = Vec::new();
+    for (name, service) in discovered.services {
+        if config.services.contains_key(&name) {
+            info!("Discovered service '{}' is defined in the config file, keeping that definition", name);
+            continue;
+        }
+        if let Err(e) = validate_service(&name, &service, config) {
+            warn!("Skipping discovered service '{}': {}", name, e);
+            continue;
+        }
+        config.services.insert(name.clone(), service);
+        added.push(name);
+    }
+    added
+}
+
+/// Discover services from labels if enabled
+///
+/// A container runtime that can't be queried only costs the discovered
+/// services; the configured ones are still backed up.
+pub fn discover(config: &mut Config) {
+    if !config.discovery.enabled {
+        return;
+    }
+
+    let runtime = config.global.container_runtime;
+    let timeout = Duration::from_secs(30);
+    let found = docker::inspect_containers_with_label(runtime, ENABLE_LABEL, timeout)
+        .and_then(|containers| Ok((containers, docker::inspect_volumes_with_label(runtime, ENABLE_LABEL, timeout)?)));
+
+    match found {
+        Ok((containers, volumes)) => {
+            let discovered = services_from_labels(&config.discovery, &containers, &volumes);
+            let added = merge_discovered(config, discovered);
+            info!("Discovered {} service(s) from labels: {}", added.len(), added.join(", "));
+        }
+        Err(e) => warn!("Service discovery failed, using the configured services only: {:#}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn container(name: &str, pairs: &[(&str, &str)], volumes: &[&str]) -> ContainerInfo {
+        ContainerInfo {
+            name: name.to_string(),
+            labels: labels(pairs),
+            volumes: volumes.iter().map(|v| v.to_string()).collect(),
+            binds: vec![],
+        }
+    }
+
+    fn discovery() -> DiscoveryConfig {
+        DiscoveryConfig { enabled: true, targets: vec!["local".to_string()], ..Default::default() }
+    }
+
+    #[test]
+    fn test_services_from_labels() {
+        let containers = vec![
+            container("plain", &[("restic-manager.enable", "true")], &["plain_data"]),
+            container(
+                "immich-server",
+                &[
+                    ("restic-manager.enable", "true"),
+                    ("com.docker.compose.project", "immich"),
+                    ("restic-manager.strategy", "stop"),
+                    ("restic-manager.paths", "/srv/immich/upload, /srv/immich/config"),
+                ],
+                &["immich_model-cache"],
+            ),
+            container(
+                "immich-db",
+                &[
+                    ("restic-manager.enable", "true"),
+                    ("com.docker.compose.project", "immich"),
+                    ("restic-manager.volumes", "immich_pgdata"),
+                    ("restic-manager.schedule", "0 4 * * *"),
+                ],
+                &["immich_pgdata", "immich_pgbackup"],
+            ),
+            container("ignored", &[("restic-manager.enable", "false")], &["ignored_data"]),
+        ];
+        let volumes = vec![VolumeInfo {
+            name: "photos".to_string(),
+            labels: labels(&[("restic-manager.enable", "true"), ("restic-manager.name", "immich")]),
+        }];
+
+        let discovered = services_from_labels(&discovery(), &containers, &volumes);
+        assert!(discovered.skipped.is_empty());
+        assert_eq!(discovered.services.keys().collect::<Vec<_>>(), vec!["immich", "plain"]);
+
+        let plain = &discovered.services["plain"];
+        assert_eq!(plain.schedule, "0 3 * * *");
+        assert_eq!(plain.targets, vec!["local"]);
+        let backup = plain.config.as_ref().unwrap();
+        assert_eq!(backup.volumes, vec!["plain_data"]);
+        assert!(backup.pause_containers.is_none());
+
+        let immich = &discovered.services["immich"];
+        assert_eq!(immich.schedule, "0 4 * * *");
+        let backup = immich.config.as_ref().unwrap();
+        assert_eq!(backup.volumes, vec!["immich_model-cache", "immich_pgdata", "photos"]);
+        assert_eq!(backup.paths, vec!["/srv/immich/upload", "/srv/immich/config"]);
+        let pause = backup.pause_containers.as_ref().unwrap();
+        assert_eq!(pause.label, "com.docker.compose.project=immich");
+        assert_eq!(pause.mode, PauseMode::Stop);
+    }
+
+    #[test]
+    fn test_invalid_labels_are_skipped() {
+        let containers = vec![
+            container("web", &[("restic-manager.enable", "true"), ("restic-manager.strategy", "pause")], &["web"]),
+            container("empty", &[("restic-manager.enable", "true")], &[]),
+            container(
+                "a",
+                &[("restic-manager.enable", "true"), ("restic-manager.name", "pair"), ("restic-manager.schedule", "0 1 * * *")],
+                &["a"],
+            ),
+            container(
+                "b",
+                &[("restic-manager.enable", "true"), ("restic-manager.name", "pair"), ("restic-manager.schedule", "0 2 * * *")],
+                &["b"],
+            ),
+        ];
+
+        let discovered = services_from_labels(&discovery(), &containers, &[]);
+        assert!(discovered.services.is_empty());
+        assert_eq!(discovered.skipped.len(), 3);
+        assert!(discovered.skipped[0].starts_with("empty (container empty): no volumes"));
+        assert!(discovered.skipped[1].contains("conflicting restic-manager.schedule labels"));
+        assert!(discovered.skipped[2].contains("needs a restic-manager.name label"));
+    }
+}
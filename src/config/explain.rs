@@ -0,0 +1,355 @@
+//! Inline documentation for configuration fields
+//!
+//! `restic-manager explain services.timeout_seconds` prints what a field
+//! means, its type and default, and which layer wins when it is set in more
+//! than one place. Everything is generated from the doc comments and serde
+//! attributes on the config structs, so it can't drift from the code.
+//!
+//! Map sections (destinations, profiles, services) accept either a concrete
+//! name or no name at all: `services.myapp.timeout_seconds` and
+//! `services.timeout_seconds` both describe the same field.
+
+use super::types::Config;
+use anyhow::Result;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use std::fmt;
+
+/// Documentation for a single config key
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDoc {
+    /// Key path as given
+    pub path: String,
+    pub description: Option<String>,
+    /// Which layer wins when the field is set in more than one place
+    pub precedence: Option<String>,
+    pub type_name: String,
+    /// Default value (TOML syntax), if the field has one
+    pub default: Option<String>,
+    /// Nested fields, for sections
+    pub fields: Vec<String>,
+}
+
+impl fmt::Display for FieldDoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path)?;
+        if let Some(ref description) = self.description {
+            writeln!(f, "  {}", description)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "  Type:       {}", self.type_name)?;
+        if let Some(ref default) = self.default {
+            writeln!(f, "  Default:    {}", default)?;
+        }
+        if let Some(ref precedence) = self.precedence {
+            writeln!(f, "  Precedence: {}", precedence)?;
+        }
+        if !self.fields.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "  Fields:")?;
+            for field in &self.fields {
+                writeln!(f, "    {}", field)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Look up the documentation for a dotted config key
+pub fn explain(path: &str) -> Result<FieldDoc> {
+    let root = schemars::schema_for!(Config);
+    let mut field = &root.schema;
+    let mut walked: Vec<&str> = Vec::new();
+
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let node = resolve(&root, field);
+
+        field = if let Some(prop) = node.object.as_ref().and_then(|o| o.properties.get(segment)) {
+            as_object(prop)
+        } else if let Some(value) = element_schema(node) {
+            // Map or list: the segment is either a field of the element
+            // or a map key (e.g. a service name) that we step over
+            let element = resolve(&root, value);
+            match element.object.as_ref().and_then(|o| o.properties.get(segment)) {
+                Some(prop) => as_object(prop),
+                None if is_map(node) => value,
+                None => anyhow::bail!(unknown_key(path, &walked, element)),
+            }
+        } else {
+            anyhow::bail!(unknown_key(path, &walked, node));
+        };
+
+        walked.push(segment);
+    }
+
+    let node = resolve(&root, field);
+    let description = field
+        .metadata
+        .as_ref()
+        .and_then(|m| m.description.clone())
+        .or_else(|| node.metadata.as_ref().and_then(|m| m.description.clone()));
+    let (description, precedence) = split_precedence(description);
+
+    let default = field
+        .metadata
+        .as_ref()
+        .and_then(|m| m.default.as_ref())
+        .map(format_default);
+
+    let section = element_schema(node).map(|v| resolve(&root, v)).unwrap_or(node);
+    let fields = section
+        .object
+        .as_ref()
+        .map(|o| o.properties.keys().cloned().collect())
+        .unwrap_or_default();
+
+    Ok(FieldDoc {
+        path: if path.is_empty() { "(root)".to_string() } else { path.to_string() },
+        description,
+        precedence,
+        type_name: type_name(&root, field),
+        default,
+        fields,
+    })
+}
+
+fn as_object(schema: &Schema) -> &SchemaObject {
+    match schema {
+        Schema::Object(obj) => obj,
+        // `true`/`false` schemas carry no documentation
+        Schema::Bool(_) => {
+            static EMPTY: std::sync::OnceLock<SchemaObject> = std::sync::OnceLock::new();
+            EMPTY.get_or_init(SchemaObject::default)
+        }
+    }
+}
+
+/// Follow `$ref`s and unwrap `allOf`/`anyOf` wrappers (used for documented
+/// struct fields and `Option<T>`) down to the schema describing the value
+fn resolve<'a>(root: &'a RootSchema, schema: &'a SchemaObject) -> &'a SchemaObject {
+    if let Some(ref reference) = schema.reference {
+        let name = reference.trim_start_matches("#/definitions/");
+        if let Some(definition) = root.definitions.get(name) {
+            return resolve(root, as_object(definition));
+        }
+    }
+
+    if let Some(ref subschemas) = schema.subschemas {
+        let wrapped = subschemas
+            .all_of
+            .iter()
+            .chain(subschemas.any_of.iter())
+            .flatten()
+            .map(as_object)
+            .find(|s| !is_null(s));
+        if let Some(inner) = wrapped {
+            if subschemas.one_of.is_none() {
+                return resolve(root, inner);
+            }
+        }
+    }
+
+    schema
+}
+
+fn is_null(schema: &SchemaObject) -> bool {
+    matches!(schema.instance_type, Some(SingleOrVec::Single(ref t)) if **t == InstanceType::Null)
+}
+
+fn is_map(schema: &SchemaObject) -> bool {
+    schema
+        .object
+        .as_ref()
+        .is_some_and(|o| o.additional_properties.is_some() && o.properties.is_empty())
+}
+
+/// Value schema of a map, or item schema of a list
+fn element_schema(schema: &SchemaObject) -> Option<&SchemaObject> {
+    if is_map(schema) {
+        return schema
+            .object
+            .as_ref()
+            .and_then(|o| o.additional_properties.as_deref())
+            .map(as_object);
+    }
+
+    match schema.array.as_ref().and_then(|a| a.items.as_ref()) {
+        Some(SingleOrVec::Single(item)) => Some(as_object(item)),
+        _ => None,
+    }
+}
+
+fn unknown_key(path: &str, walked: &[&str], node: &SchemaObject) -> String {
+    let parent = if walked.is_empty() { "the config root".to_string() } else { walked.join(".") };
+    let fields: Vec<&str> = node
+        .object
+        .as_ref()
+        .map(|o| o.properties.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+
+    if fields.is_empty() {
+        format!("Unknown config key '{}': {} has no nested fields", path, parent)
+    } else {
+        format!(
+            "Unknown config key '{}'. Fields of {}: {}",
+            path,
+            parent,
+            fields.join(", ")
+        )
+    }
+}
+
+/// Split a trailing "Precedence: ..." sentence off a description
+fn split_precedence(description: Option<String>) -> (Option<String>, Option<String>) {
+    match description {
+        Some(text) => match text.split_once("Precedence: ") {
+            Some((before, after)) => {
+                let before = before.trim().trim_end_matches('.').to_string();
+                (
+                    Some(before).filter(|s| !s.is_empty()),
+                    Some(after.trim().to_string()),
+                )
+            }
+            None => (Some(text), None),
+        },
+        None => (None, None),
+    }
+}
+
+fn format_default(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "not set".to_string(),
+        serde_json::Value::Object(map) if map.is_empty() => "{}".to_string(),
+        other => toml::Value::try_from(other)
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| other.to_string()),
+    }
+}
+
+fn type_name(root: &RootSchema, field: &SchemaObject) -> String {
+    let node = resolve(root, field);
+    let optional = is_optional(field);
+
+    let name = if let Some(values) = enum_values(node) {
+        format!("one of {}", values.join(", "))
+    } else if is_map(node) {
+        let value = element_schema(node).map(|v| type_name(root, v)).unwrap_or_default();
+        format!("table of name -> {}", value)
+    } else if let Some(item) = element_schema(node) {
+        format!("list of {}", type_name(root, item))
+    } else {
+        let types: Vec<&str> = match node.instance_type {
+            Some(SingleOrVec::Single(ref t)) => vec![instance_type_name(t)],
+            Some(SingleOrVec::Vec(ref types)) => types
+                .iter()
+                .filter(|t| **t != InstanceType::Null)
+                .map(instance_type_name)
+                .collect(),
+            None => vec![],
+        };
+        if types.is_empty() { "any".to_string() } else { types.join(" or ") }
+    };
+
+    if optional {
+        format!("{} (optional)", name)
+    } else {
+        name
+    }
+}
+
+fn is_optional(field: &SchemaObject) -> bool {
+    let nullable_type = matches!(
+        field.instance_type,
+        Some(SingleOrVec::Vec(ref types)) if types.contains(&InstanceType::Null)
+    );
+    let nullable_ref = field
+        .subschemas
+        .as_ref()
+        .and_then(|s| s.any_of.as_ref())
+        .is_some_and(|any_of| any_of.iter().map(as_object).any(is_null));
+    nullable_type || nullable_ref
+}
+
+fn enum_values(schema: &SchemaObject) -> Option<Vec<String>> {
+    if let Some(ref values) = schema.enum_values {
+        return Some(values.iter().map(|v| v.to_string()).collect());
+    }
+
+    // Enums with documented variants are rendered as `oneOf`
+    let one_of = schema.subschemas.as_ref()?.one_of.as_ref()?;
+    let values: Vec<String> = one_of
+        .iter()
+        .map(as_object)
+        .filter_map(|s| s.enum_values.as_ref())
+        .flatten()
+        .map(|v| v.to_string())
+        .collect();
+    Some(values).filter(|v| !v.is_empty())
+}
+
+fn instance_type_name(instance_type: &InstanceType) -> &'static str {
+    match instance_type {
+        InstanceType::Null => "null",
+        InstanceType::Boolean => "boolean",
+        InstanceType::Object => "table",
+        InstanceType::Array => "list",
+        InstanceType::Number => "number",
+        InstanceType::String => "string",
+        InstanceType::Integer => "integer",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_service_field_with_and_without_name() {
+        let doc = explain("services.timeout_seconds").unwrap();
+        assert_eq!(doc.description.as_deref(), Some("Backup timeout in seconds"));
+        assert_eq!(
+            doc.precedence.as_deref(),
+            Some("service > profile > global default_timeout_seconds")
+        );
+        assert_eq!(doc.type_name, "integer or string (optional)");
+
+        let named = explain("services.myapp.timeout_seconds").unwrap();
+        assert_eq!(named.description, doc.description);
+        assert_eq!(named.precedence, doc.precedence);
+    }
+
+    #[test]
+    fn test_explain_defaults() {
+        let doc = explain("global.default_timeout_seconds").unwrap();
+        assert_eq!(doc.default.as_deref(), Some("3600"));
+
+        let doc = explain("notifications.notify_on").unwrap();
+        assert_eq!(doc.default.as_deref(), Some(r#"["failure", "warning"]"#));
+        assert!(doc.type_name.starts_with("list of one of"));
+
+        let doc = explain("server.listen").unwrap();
+        assert_eq!(doc.default.as_deref(), Some(r#""127.0.0.1:8090""#));
+    }
+
+    #[test]
+    fn test_explain_sections() {
+        let doc = explain("services").unwrap();
+        assert!(doc.type_name.starts_with("table of name"));
+        assert!(doc.fields.contains(&"schedule".to_string()));
+
+        let doc = explain("services.app.config.pre_backup_hooks.command").unwrap();
+        assert_eq!(doc.description.as_deref(), Some("Command to execute"));
+        assert_eq!(doc.type_name, "string");
+
+        let doc = explain("destinations.type").unwrap();
+        assert!(doc.type_name.contains("\"sftp\""));
+    }
+
+    #[test]
+    fn test_explain_unknown_key() {
+        let err = explain("global.no_such_field").unwrap_err().to_string();
+        assert!(err.contains("Fields of global"));
+        assert!(err.contains("docker_base"));
+
+        assert!(explain("global.docker_base.deeper").is_err());
+    }
+}
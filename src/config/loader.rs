@@ -1,7 +1,7 @@
 use super::types::*;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
@@ -19,22 +19,137 @@ pub enum ConfigError {
 
     #[error("Destination '{0}' not found")]
     DestinationNotFound(String),
+
+    #[error("Failed to load included config {path:?}: {message}")]
+    IncludeError { path: PathBuf, message: String },
+
+    #[error("Duplicate {kind} '{name}' defined in {first:?} and {second:?}")]
+    DuplicateDefinition {
+        kind: &'static str,
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, ConfigError>;
 
 /// Load and validate configuration from a TOML file
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config> {
+    let path = path.as_ref();
     let contents = fs::read_to_string(path)?;
-    let config: Config = toml::from_str(&contents)?;
+    let mut config: Config = toml::from_str(&contents)?;
+    merge_includes(&mut config, path)?;
     validate_config(&config)?;
     Ok(config)
 }
 
+/// Merge all files matched by the root config's `include` patterns
+///
+/// Included files may define destinations, profiles and services. Defining
+/// the same name twice (in the root file or another include) is an error.
+fn merge_includes(config: &mut Config, root_path: &Path) -> Result<()> {
+    if config.include.is_empty() {
+        return Ok(());
+    }
+
+    let base_dir = root_path.parent().unwrap_or_else(|| Path::new("."));
+
+    // Track where each entry was defined for conflict messages
+    let mut origins: HashMap<(&'static str, String), PathBuf> = HashMap::new();
+    for name in config.destinations.keys() {
+        origins.insert(("destination", name.clone()), root_path.to_path_buf());
+    }
+    for name in config.profiles.keys() {
+        origins.insert(("profile", name.clone()), root_path.to_path_buf());
+    }
+    for name in config.services.keys() {
+        origins.insert(("service", name.clone()), root_path.to_path_buf());
+    }
+
+    for pattern in config.include.clone() {
+        for include_path in expand_include_pattern(base_dir, &pattern)? {
+            let fragment = load_fragment(&include_path)?;
+
+            merge_entries("destination", fragment.destinations, &mut config.destinations, &mut origins, &include_path)?;
+            merge_entries("profile", fragment.profiles, &mut config.profiles, &mut origins, &include_path)?;
+            merge_entries("service", fragment.services, &mut config.services, &mut origins, &include_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Expand an include pattern into a sorted list of files
+fn expand_include_pattern(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let full_pattern = if Path::new(pattern).is_absolute() {
+        PathBuf::from(pattern)
+    } else {
+        base_dir.join(pattern)
+    };
+
+    let entries = glob::glob(&full_pattern.to_string_lossy()).map_err(|e| {
+        ConfigError::ValidationError(format!("Invalid include pattern '{}': {}", pattern, e))
+    })?;
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| ConfigError::IncludeError {
+            path: e.path().to_path_buf(),
+            message: e.error().to_string(),
+        })?;
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    // Deterministic merge order regardless of filesystem
+    paths.sort();
+    Ok(paths)
+}
+
+fn load_fragment(path: &Path) -> Result<ConfigFragment> {
+    let contents = fs::read_to_string(path).map_err(|e| ConfigError::IncludeError {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+
+    toml::from_str(&contents).map_err(|e| ConfigError::IncludeError {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+fn merge_entries<T>(
+    kind: &'static str,
+    entries: HashMap<String, T>,
+    target: &mut HashMap<String, T>,
+    origins: &mut HashMap<(&'static str, String), PathBuf>,
+    source: &Path,
+) -> Result<()> {
+    for (name, value) in entries {
+        if let Some(first) = origins.get(&(kind, name.clone())) {
+            return Err(ConfigError::DuplicateDefinition {
+                kind,
+                name,
+                first: first.clone(),
+                second: source.to_path_buf(),
+            });
+        }
+        origins.insert((kind, name.clone()), source.to_path_buf());
+        target.insert(name, value);
+    }
+    Ok(())
+}
+
 /// Validate the configuration
 fn validate_config(config: &Config) -> Result<()> {
-    // Validate global settings
-    if !config.global.restic_password_file.exists() {
+    // Validate global settings (password file only matters if something reads it)
+    let uses_password_file = config
+        .destinations
+        .values()
+        .any(|d| d.password_source(&config.global) == PasswordSource::File && d.password_command(&config.global).is_none());
+    if uses_password_file && !config.global.restic_password_file.exists() {
         return Err(ConfigError::ValidationError(format!(
             "Restic password file does not exist: {:?}",
             config.global.restic_password_file
@@ -55,6 +170,122 @@ fn validate_config(config: &Config) -> Result<()> {
         ));
     }
 
+    for (name, destination) in &config.destinations {
+        validate_destination(name, destination)?;
+    }
+
+    let telegram = &config.notifications;
+    if telegram.telegram_bot_token.is_empty() != telegram.telegram_chat_id.is_empty() {
+        return Err(ConfigError::ValidationError(
+            "Telegram notifications need both telegram_bot_token and telegram_chat_id".to_string(),
+        ));
+    }
+
+    let sendmail = &config.notifications;
+    if !sendmail.sendmail_to.is_empty() && sendmail.sendmail_command.trim().is_empty() {
+        return Err(ConfigError::ValidationError(
+            "Email notifications (sendmail_to) need a sendmail_command".to_string(),
+        ));
+    }
+
+    let healthchecks = &config.notifications.healthchecks;
+    for (service, check) in &healthchecks.checks {
+        if !config.services.contains_key(service) {
+            return Err(ConfigError::ValidationError(format!(
+                "Healthchecks check configured for unknown service '{}'",
+                service
+            )));
+        }
+        if !is_uuid(check) && healthchecks.ping_key.is_empty() {
+            return Err(ConfigError::ValidationError(format!(
+                "Healthchecks check '{}' for service '{}' is a slug and needs notifications.healthchecks.ping_key",
+                check, service
+            )));
+        }
+    }
+
+    if let Some(ref mqtt) = config.integrations.mqtt {
+        if mqtt.host.trim().is_empty() {
+            return Err(ConfigError::ValidationError("integrations.mqtt.host must not be empty".to_string()));
+        }
+        if mqtt.password.is_some() && mqtt.username.is_none() {
+            return Err(ConfigError::ValidationError(
+                "integrations.mqtt.password needs integrations.mqtt.username".to_string(),
+            ));
+        }
+        if mqtt.topic_prefix.trim_matches('/').is_empty() {
+            return Err(ConfigError::ValidationError(
+                "integrations.mqtt.topic_prefix must not be empty".to_string(),
+            ));
+        }
+    }
+
+    for (name, api_token) in &config.server.tokens {
+        if api_token.token.trim().is_empty() {
+            return Err(ConfigError::ValidationError(format!("server.tokens.{}: token must not be empty", name)));
+        }
+        if api_token.scopes.is_empty() {
+            return Err(ConfigError::ValidationError(format!("server.tokens.{}: no scopes given", name)));
+        }
+        // The audit log names the token, so every secret must be unique
+        let duplicate = config.server.webhook_token == api_token.token
+            || config.server.tokens.iter().any(|(other, t)| other != name && t.token == api_token.token);
+        if duplicate {
+            return Err(ConfigError::ValidationError(format!(
+                "server.tokens.{}: token is also used by another token",
+                name
+            )));
+        }
+    }
+
+    if config.discovery.enabled {
+        let discovery = &config.discovery;
+        if let Some(ref profile) = discovery.profile {
+            if !config.profiles.contains_key(profile) {
+                return Err(ConfigError::ProfileNotFound(profile.clone()));
+            }
+        }
+        for target in &discovery.targets {
+            if !config.destinations.contains_key(target) {
+                return Err(ConfigError::DestinationNotFound(target.clone()));
+            }
+        }
+        if discovery.schedule.split_whitespace().count() != 5 {
+            return Err(ConfigError::ValidationError(format!(
+                "discovery: invalid cron schedule format (expected 5 fields): {}",
+                discovery.schedule
+            )));
+        }
+    }
+
+    if let Some(ref schedule) = config.global.verify_schedule {
+        if schedule.split_whitespace().count() != 5 {
+            return Err(ConfigError::ValidationError(format!(
+                "verify_schedule: invalid cron schedule format (expected 5 fields): {}",
+                schedule
+            )));
+        }
+    }
+    if let Some(ref subset) = config.global.verify_read_data_subset {
+        if !crate::utils::restic::is_valid_read_data_subset(subset) {
+            return Err(ConfigError::ValidationError(format!(
+                "verify_read_data_subset: expected a percentage (\"5%\"), a fraction (\"1/7\") or a size (\"500M\"), got '{}'",
+                subset
+            )));
+        }
+    }
+
+    validate_hostname("global", config.global.hostname.as_deref())?;
+    validate_limits("global", config.global.limit_upload_kbps, config.global.limit_download_kbps)?;
+    let global = &config.global;
+    validate_excludes("global", &global.default_iexcludes, &global.exclude_files, global.exclude_larger_than.as_deref())?;
+    validate_backoff_multiplier("global", Some(config.global.retry_backoff_multiplier))?;
+    for (name, profile) in &config.profiles {
+        validate_backoff_multiplier(&format!("Profile '{}'", name), profile.retry_backoff_multiplier)?;
+        validate_tags(&format!("Profile '{}'", name), &profile.tags)?;
+        validate_limits(&format!("Profile '{}'", name), profile.limit_upload_kbps, profile.limit_download_kbps)?;
+    }
+
     // Validate services
     for (name, service) in &config.services {
         validate_service(name, service, config)?;
@@ -63,7 +294,154 @@ fn validate_config(config: &Config) -> Result<()> {
     Ok(())
 }
 
-fn validate_service(name: &str, service: &ServiceConfig, config: &Config) -> Result<()> {
+/// A host name override must be usable as a single `--host` value
+fn validate_hostname(context: &str, hostname: Option<&str>) -> Result<()> {
+    match hostname {
+        Some(host) if host.is_empty() || host.chars().any(char::is_whitespace) => {
+            Err(ConfigError::ValidationError(format!(
+                "{}: invalid hostname '{}' (must be non-empty and contain no whitespace)",
+                context, host
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// restic treats a bandwidth limit of 0 as no limit, which is never what's meant
+fn validate_limits(context: &str, upload_kbps: Option<u32>, download_kbps: Option<u32>) -> Result<()> {
+    if upload_kbps == Some(0) || download_kbps == Some(0) {
+        return Err(ConfigError::ValidationError(format!(
+            "{}: bandwidth limits must be greater than 0 (leave them unset for no limit)",
+            context
+        )));
+    }
+    Ok(())
+}
+
+/// Exclude patterns and files must be non-empty, and the size limit must parse
+fn validate_excludes(
+    context: &str,
+    ipatterns: &[String],
+    files: &[String],
+    larger_than: Option<&str>,
+) -> Result<()> {
+    if ipatterns.iter().chain(files).any(|value| value.trim().is_empty()) {
+        return Err(ConfigError::ValidationError(format!(
+            "{}: exclude patterns and exclude files must not be empty",
+            context
+        )));
+    }
+    if let Some(size) = larger_than {
+        crate::utils::humanize::parse_size(size).map_err(|e| {
+            ConfigError::ValidationError(format!("{}: invalid exclude_larger_than: {:#}", context, e))
+        })?;
+    }
+    Ok(())
+}
+
+/// Whether a snapshot tag can be passed to restic, which splits `--tag`
+/// values on commas
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.trim().is_empty() && !tag.contains(',')
+}
+
+/// Parse a snapshot tag given on the command line
+pub fn parse_tag(value: &str) -> std::result::Result<String, String> {
+    if is_valid_tag(value) {
+        Ok(value.to_string())
+    } else {
+        Err("tags must be non-empty and contain no commas".to_string())
+    }
+}
+
+fn validate_tags(context: &str, tags: &[String]) -> Result<()> {
+    for tag in tags {
+        if !is_valid_tag(tag) {
+            return Err(ConfigError::ValidationError(format!(
+                "{}: invalid tag '{}' (tags must be non-empty and contain no commas)",
+                context, tag
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_backoff_multiplier(context: &str, multiplier: Option<f64>) -> Result<()> {
+    match multiplier {
+        Some(value) if !(1.0..=10.0).contains(&value) => Err(ConfigError::ValidationError(format!(
+            "{}: retry_backoff_multiplier must be between 1 and 10 (got {})",
+            context, value
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn validate_destination(name: &str, destination: &Destination) -> Result<()> {
+    // The keyring entry of a destination is named after it
+    if name == crate::utils::secrets::SHARED_ACCOUNT {
+        return Err(ConfigError::ValidationError(format!(
+            "Destination '{}': the name is reserved for the shared keyring password",
+            name
+        )));
+    }
+
+    validate_limits(
+        &format!("Destination '{}'", name),
+        destination.limit_upload_kbps,
+        destination.limit_download_kbps,
+    )?;
+
+    if let Some(size) = destination.pack_size_mb {
+        if !(4..=128).contains(&size) {
+            return Err(ConfigError::ValidationError(format!(
+                "Destination '{}': pack_size_mb must be between 4 and 128 (got {})",
+                name, size
+            )));
+        }
+    }
+
+    // Cloud backends need credentials, either in the destination's env table
+    // or in the environment restic-manager runs in
+    for var in destination.required_env_vars() {
+        if destination.env_var(var).is_none() {
+            return Err(ConfigError::ValidationError(format!(
+                "Destination '{}': {:?} requires {} (set it in [destinations.{}.env] or the environment)",
+                name, destination.dest_type, var, name
+            )));
+        }
+    }
+
+    match (&destination.dest_type, destination.repository_prefix.as_deref()) {
+        (DestinationType::Custom, None | Some("")) => {
+            return Err(ConfigError::ValidationError(format!(
+                "Destination '{}': type \"custom\" requires repository_prefix (e.g. \"swift:\")",
+                name
+            )));
+        }
+        (DestinationType::Custom, _) | (_, None) => {}
+        (dest_type, Some(_)) => {
+            return Err(ConfigError::ValidationError(format!(
+                "Destination '{}': repository_prefix is only used with type = \"custom\" (type is {:?})",
+                name, dest_type
+            )));
+        }
+    }
+
+    if destination.dest_type == DestinationType::Gcs {
+        if let Some(credentials) = destination.env_var("GOOGLE_APPLICATION_CREDENTIALS") {
+            if !Path::new(&credentials).exists() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Destination '{}': GOOGLE_APPLICATION_CREDENTIALS file does not exist: {}",
+                    name, credentials
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn validate_service(name: &str, service: &ServiceConfig, config: &Config) -> Result<()> {
     // Check that profile exists if specified
     if let Some(ref profile_name) = service.profile {
         if !config.profiles.contains_key(profile_name) {
@@ -87,6 +465,98 @@ fn validate_service(name: &str, service: &ServiceConfig, config: &Config) -> Res
         )));
     }
 
+    validate_backoff_multiplier(&format!("Service '{}'", name), service.retry_backoff_multiplier)?;
+    validate_tags(&format!("Service '{}'", name), &service.tags)?;
+    validate_hostname(&format!("Service '{}'", name), service.hostname.as_deref())?;
+
+    if service.time_budget_minutes == Some(0) {
+        return Err(ConfigError::ValidationError(format!(
+            "Service '{}': time_budget_minutes must be greater than 0",
+            name
+        )));
+    }
+
+    if service.max_age_hours == Some(0) {
+        return Err(ConfigError::ValidationError(format!(
+            "Service '{}': max_age_hours must be greater than 0",
+            name
+        )));
+    }
+
+    for (tag, rule) in &service.retention.tags {
+        validate_tag_retention(name, tag, rule)?;
+    }
+
+    if let Some(ref backup) = service.config {
+        validate_excludes(
+            &format!("Service '{}'", name),
+            &backup.iexcludes,
+            &backup.exclude_files,
+            backup.exclude_larger_than.as_deref(),
+        )?;
+        if backup.files_from.iter().chain(&backup.files_from_verbatim).any(|f| f.trim().is_empty()) {
+            return Err(ConfigError::ValidationError(format!(
+                "Service '{}': files_from entries must not be empty",
+                name
+            )));
+        }
+
+        let mut filenames = Vec::new();
+        for source in &backup.commands {
+            if source.name.is_empty() || source.command.trim().is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Service '{}': command sources need a name and a command",
+                    name
+                )));
+            }
+            let filename = source.stdin_filename();
+            if filenames.contains(&filename) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Service '{}': two command sources are stored as '{}'",
+                    name, filename
+                )));
+            }
+            filenames.push(filename);
+        }
+
+        if let Some(ref pause) = backup.pause_containers {
+            if pause.label.trim().is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Service '{}': pause_containers needs a label",
+                    name
+                )));
+            }
+        }
+
+        if backup.stop_during_backup && backup.compose_project.is_none() && backup.compose_file.is_none() {
+            return Err(ConfigError::ValidationError(format!(
+                "Service '{}': stop_during_backup needs compose_project or compose_file",
+                name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_tag_retention(service: &str, tag: &str, rule: &TagRetention) -> Result<()> {
+    let has_window = rule.within.is_some() || rule.last.is_some();
+    if rule.forever == has_window {
+        return Err(ConfigError::ValidationError(format!(
+            "Service '{}': retention for tag '{}' must set either forever = true or within/last",
+            service, tag
+        )));
+    }
+
+    if let Some(ref within) = rule.within {
+        if !is_restic_duration(within) {
+            return Err(ConfigError::ValidationError(format!(
+                "Service '{}': invalid retention duration '{}' for tag '{}' (expected e.g. 90d or 1y6m)",
+                service, within, tag
+            )));
+        }
+    }
+
     Ok(())
 }
 
@@ -153,7 +623,57 @@ pub fn resolve_service(
             .retention_yearly
             .or_else(|| profile.and_then(|p| p.retention_yearly))
             .unwrap_or(config.global.retention_yearly),
+        hourly: service
+            .retention_hourly
+            .or_else(|| profile.and_then(|p| p.retention_hourly))
+            .unwrap_or(config.global.retention_hourly),
+        last: service
+            .retention_last
+            .or_else(|| profile.and_then(|p| p.retention_last))
+            .unwrap_or(config.global.retention_last),
+        within: service
+            .retention_within
+            .clone()
+            .or_else(|| profile.and_then(|p| p.retention_within.clone()))
+            .or_else(|| config.global.retention_within.clone()),
+        tags: service.retention.tags.clone(),
     };
+    if !retention.keeps_any() {
+        return Err(ConfigError::ValidationError(format!(
+            "Service '{}': retention keeps no snapshots (set at least one of retention_daily, retention_weekly, \
+             retention_monthly, retention_yearly, retention_hourly, retention_last or retention_within)",
+            name
+        )));
+    }
+    if let Some(ref within) = retention.within {
+        if !is_restic_duration(within) {
+            return Err(ConfigError::ValidationError(format!(
+                "Service '{}': invalid retention_within '{}' (expected e.g. 14d or 1y6m)",
+                name, within
+            )));
+        }
+    }
+
+    // Resolve retry policy (service > profile > global)
+    let retry = RetryPolicy {
+        retries: service
+            .retries
+            .or_else(|| profile.and_then(|p| p.retries))
+            .unwrap_or(config.global.retries),
+        delay_seconds: service
+            .retry_delay_seconds
+            .or_else(|| profile.and_then(|p| p.retry_delay_seconds))
+            .unwrap_or(config.global.retry_delay_seconds),
+        backoff_multiplier: service
+            .retry_backoff_multiplier
+            .or_else(|| profile.and_then(|p| p.retry_backoff_multiplier))
+            .unwrap_or(config.global.retry_backoff_multiplier),
+    };
+
+    let post_backup_verify = service
+        .post_backup_verify
+        .or_else(|| profile.and_then(|p| p.post_backup_verify))
+        .unwrap_or(config.global.post_backup_verify);
 
     // Resolve notify_on (service > profile > global)
     let notify_on = if !service.notify_on.is_empty() {
@@ -168,15 +688,39 @@ pub fn resolve_service(
         config.notifications.notify_on.clone()
     };
 
+    // Tags add up: profile tags first, then the service's own
+    let mut tags: Vec<String> = profile.map(|p| p.tags.clone()).unwrap_or_default();
+    for tag in &service.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    // A temporary disable only holds before its date
+    let disabled_until = service
+        .disabled_until
+        .filter(|until| chrono::Local::now().date_naive() < *until);
+
     Ok(ResolvedServiceConfig {
         name: name.to_string(),
-        enabled: service.enabled,
+        enabled: service.enabled && disabled_until.is_none(),
+        disabled_until,
         description: service.description.clone(),
         schedule: service.schedule.clone(),
         targets,
         timeout_seconds,
+        time_budget_minutes: service
+            .time_budget_minutes
+            .or_else(|| profile.and_then(|p| p.time_budget_minutes)),
+        max_age_hours: service.max_age_hours.or_else(|| profile.and_then(|p| p.max_age_hours)),
         retention,
+        retry,
+        post_backup_verify,
         notify_on,
+        tags,
+        hostname: service.hostname.clone().or_else(|| config.global.hostname.clone()),
+        limit_upload_kbps: profile.and_then(|p| p.limit_upload_kbps),
+        limit_download_kbps: profile.and_then(|p| p.limit_download_kbps),
         config: service.config.clone(),
     })
 }
@@ -208,4 +752,14 @@ mod tests {
     fn test_config_validation() {
         // Test validation logic
     }
+
+    #[test]
+    fn test_restic_durations() {
+        assert!(is_restic_duration("90d"));
+        assert!(is_restic_duration("1y6m2h"));
+        assert!(!is_restic_duration(""));
+        assert!(!is_restic_duration("90"));
+        assert!(!is_restic_duration("d90"));
+        assert!(!is_restic_duration("90 days"));
+    }
 }
@@ -27,8 +27,13 @@
 
 mod loader;
 mod types;
+pub mod contexts;
+pub mod discovery;
+pub mod explain;
+pub mod reload;
+pub mod wizard;
 
-pub use loader::{load_config, resolve_all_services};
+pub use loader::{is_valid_tag, load_config, parse_tag, resolve_all_services, ConfigError};
 pub use types::*;
 
 /// Get the merged exclude patterns for a service
@@ -77,6 +82,7 @@ mod tests {
             long_running_threshold_minutes: 120,
             default_excludes: vec!["*.log".to_string(), "*.tmp".to_string()],
             use_system_restic: false,
+            ..Default::default()
         };
 
         // Create a resolved service with additional excludes
@@ -84,22 +90,47 @@ mod tests {
             name: "test".to_string(),
             description: "Test service".to_string(),
             enabled: true,
+            disabled_until: None,
             schedule: "0 2 * * *".to_string(),
             targets: vec!["local".to_string()],
             timeout_seconds: 3600,
+            time_budget_minutes: None,
+            max_age_hours: None,
             retention: RetentionPolicy {
                 daily: 7,
                 weekly: 4,
                 monthly: 6,
                 yearly: 1,
+                ..Default::default()
             },
+            retry: Default::default(),
+            post_backup_verify: Default::default(),
             notify_on: vec![],
+            tags: vec![],
+            hostname: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
             config: Some(BackupConfig {
                 paths: vec![],
+                files_from: vec![],
+                files_from_verbatim: vec![],
                 volumes: vec![],
+                containers: vec![],
                 pre_backup_hooks: vec![],
                 post_backup_hooks: vec![],
+                commands: vec![],
+                on_failure_hooks: vec![],
+                on_success_hooks: vec![],
+                restore_test_hooks: vec![],
+                compose_project: None,
+                compose_file: None,
+                stop_during_backup: false,
+                pause_containers: None,
                 excludes: vec!["*.cache".to_string()],
+                iexcludes: vec![],
+                exclude_files: vec![],
+                exclude_larger_than: None,
+                one_file_system: None,
             }),
         };
 
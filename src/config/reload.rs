@@ -0,0 +1,223 @@
+//! Configuration reloading for long-running modes
+//!
+//! A reload only replaces the active configuration after the new file has been
+//! loaded and validated; on error the previous configuration stays in effect.
+//! The returned change list describes what differs, for logging.
+//!
+//! [`watch`] reports edits of the configuration file and its includes, so
+//! serve mode picks them up without a restart.
+
+use super::discovery;
+use super::loader::load_config;
+use super::types::Config;
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Quiet time after a change before reloading, so an editor's burst of
+/// writes (temp file, rename, chmod) causes a single reload
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Load and validate a configuration file, describing how it differs from `current`
+pub fn reload_config(path: &Path, current: &Config) -> Result<(Config, Vec<String>)> {
+    let mut new_config = load_config(path)?;
+    discovery::discover(&mut new_config);
+    let changes = describe_changes(current, &new_config);
+    Ok((new_config, changes))
+}
+
+/// Describe the differences between two configurations
+pub fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    if to_value(&old.global) != to_value(&new.global) {
+        changes.push("Global settings changed".to_string());
+    }
+
+    diff_map("destination", &old.destinations, &new.destinations, &mut changes);
+    diff_map("profile", &old.profiles, &new.profiles, &mut changes);
+    diff_map("service", &old.services, &new.services, &mut changes);
+
+    if to_value(&old.notifications) != to_value(&new.notifications) {
+        changes.push("Notification settings changed".to_string());
+    }
+
+    if to_value(&old.server) != to_value(&new.server) {
+        changes.push("Server settings changed".to_string());
+    }
+
+    changes
+}
+
+/// Directories the configuration is read from: the file's own and those of
+/// its `include` patterns, up to the first glob component (watched
+/// recursively when the pattern continues below it)
+pub fn watched_dirs(path: &Path, config: &Config) -> Vec<(PathBuf, RecursiveMode)> {
+    let base = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut dirs = vec![(base.clone(), RecursiveMode::NonRecursive)];
+    for pattern in &config.include {
+        let mut dir = base.clone();
+        let mut mode = RecursiveMode::NonRecursive;
+        let components: Vec<_> = Path::new(pattern).components().collect();
+        // The last component is the file name (or its pattern)
+        for component in &components[..components.len().saturating_sub(1)] {
+            if component.as_os_str().to_string_lossy().contains(['*', '?', '[']) {
+                mode = RecursiveMode::Recursive;
+                break;
+            }
+            dir.push(component);
+        }
+        if !dirs.contains(&(dir.clone(), mode)) {
+            dirs.push((dir, mode));
+        }
+    }
+    dirs
+}
+
+/// Call `changed` whenever TOML files in the configuration's directories
+/// change; watching stops when the returned watcher is dropped
+///
+/// Directories of includes added by a later edit are only watched after a
+/// restart.
+pub fn watch(path: &Path, config: &Config, changed: impl Fn() + Send + 'static) -> Result<RecommendedWatcher> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+        Ok(event) if !event.kind.is_access() && event.paths.iter().any(|p| p.extension().is_some_and(|e| e == "toml")) => {
+            let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Config watcher error: {}", e),
+    })
+    .context("Failed to create config file watcher")?;
+
+    for (dir, mode) in watched_dirs(path, config) {
+        match watcher.watch(&dir, mode) {
+            Ok(()) => debug!("Watching {} for config changes", dir.display()),
+            Err(e) => warn!("Can't watch {} for config changes: {}", dir.display(), e),
+        }
+    }
+
+    thread::spawn(move || {
+        while rx.recv().is_ok() {
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            changed();
+        }
+    });
+    Ok(watcher)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Option<toml::Value> {
+    toml::Value::try_from(value).ok()
+}
+
+fn diff_map<T: Serialize>(
+    kind: &str,
+    old: &HashMap<String, T>,
+    new: &HashMap<String, T>,
+    changes: &mut Vec<String>,
+) {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (Some(_), None) => changes.push(format!("Removed {} '{}'", kind, name)),
+            (None, Some(_)) => changes.push(format!("Added {} '{}'", kind, name)),
+            (Some(before), Some(after)) if to_value(before) != to_value(after) => {
+                changes.push(format!("Changed {} '{}'", kind, name))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(services: &str) -> Config {
+        let content = format!(
+            r#"
+[global]
+restic_password_file = "/tmp/password"
+docker_base = "/docker"
+
+[destinations.local]
+type = "local"
+url = "/backups"
+
+{}
+"#,
+            services
+        );
+        toml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn test_watched_dirs() {
+        let mut config = parse("");
+        assert_eq!(watched_dirs(Path::new("config.toml"), &config), vec![(PathBuf::from("."), RecursiveMode::NonRecursive)]);
+
+        config.include = vec!["services.d/*.toml".to_string(), "extra.toml".to_string(), "hosts/*/services.toml".to_string()];
+        assert_eq!(
+            watched_dirs(Path::new("/etc/restic-manager/config.toml"), &config),
+            vec![
+                (PathBuf::from("/etc/restic-manager"), RecursiveMode::NonRecursive),
+                (PathBuf::from("/etc/restic-manager/services.d"), RecursiveMode::NonRecursive),
+                (PathBuf::from("/etc/restic-manager/hosts"), RecursiveMode::Recursive),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_changes_identical() {
+        let config = parse("[services.app]\nschedule = \"0 2 * * *\"\ntargets = [\"local\"]\n");
+        assert!(describe_changes(&config, &config.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_describe_changes_services() {
+        let old = parse(
+            r#"
+[services.app]
+schedule = "0 2 * * *"
+targets = ["local"]
+
+[services.old]
+schedule = "0 2 * * *"
+targets = ["local"]
+"#,
+        );
+        let new = parse(
+            r#"
+[services.app]
+schedule = "0 3 * * *"
+targets = ["local"]
+
+[services.new]
+schedule = "0 2 * * *"
+targets = ["local"]
+"#,
+        );
+
+        let changes = describe_changes(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                "Changed service 'app'".to_string(),
+                "Added service 'new'".to_string(),
+                "Removed service 'old'".to_string(),
+            ]
+        );
+    }
+}
@@ -1,110 +1,945 @@
+use chrono::NaiveDate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::humanize::serde_human;
 
 /// Root configuration structure
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
+    /// Additional config files to merge (glob patterns, relative to this file)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Settings shared by all services (lowest precedence layer)
     pub global: GlobalConfig,
+    /// Backup destinations (restic repository locations), by name
+    #[serde(default)]
     pub destinations: HashMap<String, Destination>,
+    /// Notification channels and events
     #[serde(default)]
     pub notifications: NotificationConfig,
+    /// Reusable settings services can inherit from, by name
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// Services to back up, by name
+    #[serde(default)]
+    pub services: HashMap<String, ServiceConfig>,
+    /// Settings for `restic-manager serve`
+    #[serde(default)]
+    pub server: ServerConfig,
+    /// Experimental subsystems, disabled unless switched on here
+    #[serde(default)]
+    pub experimental: FeatureFlags,
+    /// Connections to other systems (Home Assistant via MQTT)
+    #[serde(default)]
+    pub integrations: IntegrationsConfig,
+    /// Services discovered from container and volume labels
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+}
+
+/// Label-based service discovery
+///
+/// Containers and volumes labeled `restic-manager.enable=true` become
+/// services when the config is loaded. Services defined in the config file
+/// take precedence over discovered ones with the same name.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct DiscoveryConfig {
+    /// Turn labeled containers and volumes into services
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Schedule of discovered services without a `restic-manager.schedule` label
+    #[serde(default = "default_discovery_schedule")]
+    pub schedule: String,
+
+    /// Targets of discovered services without a `restic-manager.targets` label
+    #[serde(default)]
+    pub targets: Vec<String>,
+
+    /// Profile of discovered services without a `restic-manager.profile` label
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: default_discovery_schedule(),
+            targets: Vec::new(),
+            profile: None,
+        }
+    }
+}
+
+/// Connections to other systems
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct IntegrationsConfig {
+    /// Publish backup state to an MQTT broker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttConfig>,
+}
+
+/// MQTT broker receiving backup state
+///
+/// After each run the service's state is published (retained) to
+/// `<topic_prefix>/<service>/state`, together with Home Assistant discovery
+/// configs under `<discovery_prefix>` so the services show up as sensors.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct MqttConfig {
+    /// Broker host name or address
+    pub host: String,
+
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Prefix of the state topics
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub topic_prefix: String,
+
+    /// Home Assistant discovery prefix (empty disables discovery)
+    #[serde(default = "default_mqtt_discovery_prefix")]
+    pub discovery_prefix: String,
+
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+}
+
+/// Switches for experimental subsystems
+///
+/// New subsystems ship disabled and can be enabled per deployment in the
+/// `[experimental]` section.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct FeatureFlags {
+    /// Back up all services concurrently in `run` (one thread per service)
+    #[serde(default)]
+    pub parallel_backups: bool,
+
+    /// Pipe volume archives straight into `restic backup` instead of writing
+    /// temp files (CLI docker backend and restic 0.17+; temp files otherwise)
+    #[serde(default)]
+    pub stream_volumes: bool,
+}
+
+impl FeatureFlags {
+    /// Names of the enabled flags
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let mut enabled = Vec::new();
+        if self.parallel_backups {
+            enabled.push("parallel_backups");
+        }
+        if self.stream_volumes {
+            enabled.push("stream_volumes");
+        }
+        enabled
+    }
+}
+
+/// Settings for `restic-manager serve`
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ServerConfig {
+    /// Address to listen on
+    #[serde(default = "default_listen")]
+    pub listen: String,
+
+    /// Bearer token with every scope (empty: only `tokens` are accepted)
+    #[serde(default)]
+    pub webhook_token: String,
+
+    /// Named API tokens with limited scopes; with neither these nor
+    /// `webhook_token` the API is disabled
+    #[serde(default)]
+    pub tokens: BTreeMap<String, ApiToken>,
+
+    /// How often the cached snapshot lists are refreshed in the background
+    /// (0 refreshes only on `POST /snapshots/refresh`)
+    #[serde(default = "default_snapshot_cache_ttl", with = "serde_human::seconds")]
+    #[schemars(schema_with = "serde_human::schema")]
+    pub snapshot_cache_ttl_seconds: u64,
+
+    /// Serve the web dashboard at `/`
+    #[serde(default = "default_enabled")]
+    pub dashboard: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: default_listen(),
+            webhook_token: String::new(),
+            tokens: BTreeMap::new(),
+            snapshot_cache_ttl_seconds: default_snapshot_cache_ttl(),
+            dashboard: true,
+        }
+    }
+}
+
+/// A named bearer token for serve mode
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ApiToken {
+    /// The secret sent as `Authorization: Bearer <token>`
+    pub token: String,
+
+    /// What the token may do
+    pub scopes: Vec<ApiScope>,
+}
+
+/// Permission of an API token
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    /// Read snapshot lists and refresh the cache
+    Read,
+    /// Trigger backups
+    Trigger,
+    /// Restore endpoints (reserved, serve mode has none yet)
+    Restore,
+}
+
+impl ApiScope {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Trigger => "trigger",
+            ApiScope::Restore => "restore",
+        }
+    }
+}
+
+/// Partial configuration loaded from an included file
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFragment {
+    #[serde(default)]
+    pub destinations: HashMap<String, Destination>,
     #[serde(default)]
     pub profiles: HashMap<String, Profile>,
+    #[serde(default)]
     pub services: HashMap<String, ServiceConfig>,
 }
 
 /// Global configuration settings
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct GlobalConfig {
-    /// Path to restic password file
+    /// Path to restic password file (not needed with password_source = "keyring")
+    #[serde(default)]
     pub restic_password_file: PathBuf,
 
     /// Base directory for Docker services
     pub docker_base: PathBuf,
 
-    /// Default retention policy
+    /// Daily snapshots to keep. Precedence: service > profile > global
     #[serde(default = "default_retention_daily")]
     pub retention_daily: u32,
+    /// Weekly snapshots to keep. Precedence: service > profile > global
     #[serde(default = "default_retention_weekly")]
     pub retention_weekly: u32,
+    /// Monthly snapshots to keep. Precedence: service > profile > global
     #[serde(default = "default_retention_monthly")]
     pub retention_monthly: u32,
+    /// Yearly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_yearly: u32,
+    /// Hourly snapshots to keep. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_hourly: u32,
+    /// Keep this many of the most recent snapshots. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_last: u32,
+    /// Keep all snapshots newer than this restic duration (e.g. "14d").
+    /// Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_within: Option<String>,
 
-    /// Timeout settings
-    #[serde(default = "default_timeout")]
+    /// Backup timeout in seconds. Precedence: service > profile > global
+    #[serde(default = "default_timeout", with = "serde_human::seconds")]
+    #[schemars(schema_with = "serde_human::schema")]
     pub default_timeout_seconds: u64,
-    #[serde(default = "default_long_running_threshold")]
+    /// Send a long-running notification when a backup takes longer than this
+    #[serde(default = "default_long_running_threshold", with = "serde_human::minutes")]
+    #[schemars(schema_with = "serde_human::schema")]
     pub long_running_threshold_minutes: u64,
 
-    /// Logging configuration
+    /// Retry a failed repository operation (init, backup, forget) this many
+    /// times before giving up. Precedence: service > profile > global
+    #[serde(default)]
+    pub retries: u32,
+    /// Delay before the first retry. Precedence: service > profile > global
+    #[serde(default = "default_retry_delay_seconds", with = "serde_human::seconds")]
+    #[schemars(schema_with = "serde_human::schema")]
+    pub retry_delay_seconds: u64,
+    /// Each further retry waits this many times longer. Precedence: service > profile > global
+    #[serde(default = "default_retry_backoff_multiplier")]
+    pub retry_backoff_multiplier: f64,
+
+    /// After this many failed runs in a row, replace per-run failure
+    /// notifications with a summary at 1x, 2x, 4x, ... this many failures (0 disables)
+    #[serde(default = "default_failure_backoff_after")]
+    pub failure_backoff_after: u32,
+
+    /// Skip a service after this many failed runs in a row until
+    /// `restic-manager resume` or a change to its config (0 never skips)
+    #[serde(default)]
+    pub pause_after_failures: u32,
+
+    /// Backups running at the same time on this host, over all services
+    /// and restic-manager processes (0: no limit)
+    #[serde(default)]
+    pub max_concurrent_backups: u32,
+
+    /// How long `run` waits for a service another process is backing up
+    /// before skipping it (0: skip right away; `run --wait-lock` overrides)
+    #[serde(default, with = "serde_human::minutes")]
+    #[schemars(schema_with = "serde_human::schema")]
+    pub wait_lock_minutes: u64,
+
+    /// Directory for log files
     #[serde(default = "default_log_directory")]
     pub log_directory: PathBuf,
+    /// Log level (trace, debug, info, warn, error)
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    /// Number of rotated log files to keep
     #[serde(default = "default_log_max_files")]
     pub log_max_files: u32,
-    #[serde(default = "default_log_max_size_mb")]
+    /// Maximum size of a log file before rotation
+    #[serde(default = "default_log_max_size_mb", with = "serde_human::megabytes")]
+    #[schemars(schema_with = "serde_human::schema")]
     pub log_max_size_mb: u64,
+    /// Per-run log directories (`<log_directory>/runs/<run id>`) to keep;
+    /// 0 doesn't write them
+    #[serde(default = "default_keep_run_logs")]
+    pub keep_run_logs: u32,
 
-    /// Default exclusion patterns
+    /// Exclusion patterns applied to every service (service excludes are added to these)
     #[serde(default)]
     pub default_excludes: Vec<String>,
 
+    /// Case-insensitive exclusion patterns applied to every service (`--iexclude`)
+    #[serde(default)]
+    pub default_iexcludes: Vec<String>,
+
+    /// Files of exclusion patterns applied to every service (`--exclude-file`)
+    #[serde(default)]
+    pub exclude_files: Vec<String>,
+
+    /// Skip files larger than this, e.g. "2G" (`--exclude-larger-than`)
+    #[serde(default)]
+    pub exclude_larger_than: Option<String>,
+
+    /// Don't cross file system boundaries below the backed up paths
+    /// (`--one-file-system`)
+    #[serde(default)]
+    pub one_file_system: bool,
+
     /// Use system restic from PATH instead of managed binary
     #[serde(default)]
     pub use_system_restic: bool,
+
+    /// Directory for temporary volume archives and restores (default:
+    /// `restic-manager` in the system's temp directory, often a small tmpfs)
+    #[serde(default)]
+    pub work_dir: Option<PathBuf>,
+
+    /// Directory of restic's local cache (`RESTIC_CACHE_DIR`; unset: restic's
+    /// default, e.g. ~/.cache/restic)
+    #[serde(default)]
+    pub restic_cache_dir: Option<PathBuf>,
+
+    /// Where the repository password comes from (file or keyring)
+    #[serde(default)]
+    pub password_source: PasswordSource,
+
+    /// Command printing the repository password (e.g. `pass show restic`),
+    /// used instead of restic_password_file when set
+    #[serde(default)]
+    pub restic_password_command: Option<String>,
+
+    /// This host is trusted to delete snapshots on append-only destinations
+    /// (run forget/prune from here, with separate credentials)
+    #[serde(default)]
+    pub trusted_host: bool,
+
+    /// Forget snapshots without pruning, and prune from `maintain` only once
+    /// the first unpruned forget is this many days old (0 prunes right away)
+    #[serde(default)]
+    pub prune_delay_days: u32,
+
+    /// Fail the backup when the retention policy can't be applied (by
+    /// default the failure is only reported and the backup succeeds)
+    #[serde(default)]
+    pub fail_on_retention_error: bool,
+
+    /// Snapshot the current files (tagged `pre-restore`) before an in-place
+    /// restore overwrites them, so the restore itself can be rolled back
+    #[serde(default = "default_pre_restore_snapshot")]
+    pub pre_restore_snapshot: bool,
+
+    /// Upload bandwidth limit in KiB/s for backups and prunes
+    /// (`restic --limit-upload`). The lowest of global, profile and
+    /// destination limits applies
+    #[serde(default)]
+    pub limit_upload_kbps: Option<u32>,
+
+    /// Download bandwidth limit in KiB/s for restores, backups and prunes
+    /// (`restic --limit-download`). The lowest of global, profile and
+    /// destination limits applies
+    #[serde(default)]
+    pub limit_download_kbps: Option<u32>,
+
+    /// Host name recorded on snapshots (`restic --host`) instead of the
+    /// machine's, and the host snapshot listings and retention are limited to.
+    /// Precedence: service > global
+    #[serde(default)]
+    pub hostname: Option<String>,
+
+    /// Check each new snapshot right after the backup. Precedence: service > profile > global
+    #[serde(default)]
+    pub post_backup_verify: PostBackupVerify,
+
+    /// Cron schedule of `verify` over all services, installed by `setup`
+    #[serde(default)]
+    pub verify_schedule: Option<String>,
+    /// Part of the pack data scheduled verifies read back
+    /// (`--read-data-subset`: "5%", "1/7" or "500M"); unset checks metadata only
+    #[serde(default)]
+    pub verify_read_data_subset: Option<String>,
+
+    /// How Docker is reached: the `docker` CLI or the Engine API socket
+    #[serde(default)]
+    pub docker_backend: DockerBackend,
+
+    /// Container engine for volumes, containers and Compose stacks
+    #[serde(default)]
+    pub container_runtime: ContainerRuntime,
+
+    /// Compression of volume archives (none lets restic deduplicate them)
+    #[serde(default)]
+    pub volume_archive_compression: ArchiveCompression,
+
+    /// Where run history is kept: a JSON lines file or an SQLite database
+    #[serde(default)]
+    pub state_backend: StateBackend,
+    /// History file or database (default: `history.jsonl` or `state.db` in
+    /// the user's data directory)
+    #[serde(default)]
+    pub state_path: Option<PathBuf>,
+}
+
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        Self {
+            restic_password_file: PathBuf::new(),
+            docker_base: PathBuf::new(),
+            retention_daily: default_retention_daily(),
+            retention_weekly: default_retention_weekly(),
+            retention_monthly: default_retention_monthly(),
+            retention_yearly: 0,
+            retention_hourly: 0,
+            retention_last: 0,
+            retention_within: None,
+            default_timeout_seconds: default_timeout(),
+            long_running_threshold_minutes: default_long_running_threshold(),
+            retries: 0,
+            retry_delay_seconds: default_retry_delay_seconds(),
+            retry_backoff_multiplier: default_retry_backoff_multiplier(),
+            failure_backoff_after: default_failure_backoff_after(),
+            pause_after_failures: 0,
+            max_concurrent_backups: 0,
+            wait_lock_minutes: 0,
+            log_directory: default_log_directory(),
+            log_level: default_log_level(),
+            log_max_files: default_log_max_files(),
+            log_max_size_mb: default_log_max_size_mb(),
+            keep_run_logs: default_keep_run_logs(),
+            default_excludes: Vec::new(),
+            default_iexcludes: Vec::new(),
+            exclude_files: Vec::new(),
+            exclude_larger_than: None,
+            one_file_system: false,
+            use_system_restic: false,
+            restic_cache_dir: None,
+            work_dir: None,
+            password_source: PasswordSource::default(),
+            restic_password_command: None,
+            trusted_host: false,
+            prune_delay_days: 0,
+            fail_on_retention_error: false,
+            pre_restore_snapshot: default_pre_restore_snapshot(),
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            hostname: None,
+            post_backup_verify: PostBackupVerify::default(),
+            verify_schedule: None,
+            verify_read_data_subset: None,
+            docker_backend: DockerBackend::default(),
+            container_runtime: ContainerRuntime::default(),
+            volume_archive_compression: ArchiveCompression::default(),
+            state_backend: StateBackend::default(),
+            state_path: None,
+        }
+    }
+}
+
+impl GlobalConfig {
+    /// Directory for temporary volume archives and restores
+    pub fn work_dir(&self) -> PathBuf {
+        self.work_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("restic-manager"))
+    }
+
+    /// Fixed subdirectory of the work directory that archives and restores
+    /// go into, so nothing outside it is ever cleaned up
+    pub fn scratch_dir(&self) -> PathBuf {
+        self.work_dir().join(crate::utils::scratch::SCRATCH_SUBDIR)
+    }
+}
+
+/// Source of the restic repository password
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordSource {
+    /// Read from `restic_password_file`
+    #[default]
+    File,
+    /// Read from the OS keyring (see `restic-manager secret set`)
+    Keyring,
+}
+
+/// How Docker is reached
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DockerBackend {
+    /// Run the `docker` CLI
+    #[default]
+    Cli,
+    /// Talk to the Engine API socket directly (`DOCKER_HOST` or the
+    /// platform default), for hosts that have the socket but no CLI
+    Api,
+}
+
+/// Storage of run history
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StateBackend {
+    /// JSON lines file, one record per line
+    #[default]
+    File,
+    /// SQLite database, for long histories
+    Sqlite,
+}
+
+/// Compression restic applies to new pack data (`restic --compression`)
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepositoryCompression {
+    /// Fast compression, skipped for incompressible data (restic's default)
+    Auto,
+    /// Smallest repository at the cost of CPU time
+    Max,
+    /// No compression
+    Off,
+}
+
+impl RepositoryCompression {
+    /// Value of restic's `--compression` option
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepositoryCompression::Auto => "auto",
+            RepositoryCompression::Max => "max",
+            RepositoryCompression::Off => "off",
+        }
+    }
+}
+
+/// Compression of the volume archives backed up from temp files
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    /// Plain tar, so restic can deduplicate (and compress) unchanged files
+    None,
+    /// tar.gz
+    #[default]
+    Gzip,
+    /// tar.zst (compressed by restic-manager, not the helper container)
+    Zstd,
+}
+
+impl ArchiveCompression {
+    /// File extension of archives with this compression
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveCompression::None => "tar",
+            ArchiveCompression::Gzip => "tar.gz",
+            ArchiveCompression::Zstd => "tar.zst",
+        }
+    }
+
+    /// Compression of an archive, from its file name
+    pub fn of_path(path: &std::path::Path) -> Self {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        if name.ends_with(".tar.zst") {
+            ArchiveCompression::Zstd
+        } else if name.ends_with(".tar") {
+            ArchiveCompression::None
+        } else {
+            ArchiveCompression::Gzip
+        }
+    }
+}
+
+/// Container engine
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    /// Podman (rootful or rootless)
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// CLI binary of this runtime
+    pub fn binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+/// Check run on a new snapshot after a successful backup
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PostBackupVerify {
+    /// No verification
+    #[default]
+    Off,
+    /// `restic check` of the repository structure (no data is read)
+    Metadata,
+    /// Restore a few small files to a temporary directory and compare their
+    /// hashes with the originals
+    Sample,
 }
 
 /// Backup destination configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Default)]
 pub struct Destination {
+    /// Repository backend type
     #[serde(rename = "type")]
     pub dest_type: DestinationType,
+    /// Repository base URL; the service name is appended per service
     pub url: String,
+    /// Human-readable description
     #[serde(default)]
     pub description: String,
+
+    /// Extra environment variables passed to restic for this destination
+    /// (e.g. cloud provider credentials)
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Where the repository password comes from. Precedence: destination > global
+    #[serde(default)]
+    pub password_source: Option<PasswordSource>,
+
+    /// Command printing the repository password. Precedence: destination > global
+    #[serde(default)]
+    pub restic_password_command: Option<String>,
+
+    /// Repository only accepts new data (rest-server --append-only, S3 object lock);
+    /// forget/prune are never run against it unless `trusted_host` is set
+    #[serde(default)]
+    pub append_only: bool,
+
+    /// Archive tier storage (e.g. S3 Glacier via rclone) where reading data
+    /// is slow and costs money: status and stats skip live size queries,
+    /// checks never read pack data, and restores need
+    /// `--acknowledge-cold-storage`
+    #[serde(default)]
+    pub cold_storage: bool,
+
+    /// Backend prefix put in front of `url` for `type = "custom"`
+    /// (e.g. `swift:` or `rclone:`)
+    #[serde(default)]
+    pub repository_prefix: Option<String>,
+
+    /// Environment variables a `custom` backend needs, checked at load time
+    /// (set in `env` or the environment)
+    #[serde(default)]
+    pub required_env: Vec<String>,
+
+    /// Upload bandwidth limit in KiB/s for this destination
+    #[serde(default)]
+    pub limit_upload_kbps: Option<u32>,
+
+    /// Download bandwidth limit in KiB/s for this destination
+    #[serde(default)]
+    pub limit_download_kbps: Option<u32>,
+
+    /// Compression of new data (`auto`, `max` or `off`; unset: restic's
+    /// default, `auto`)
+    #[serde(default)]
+    pub compression: Option<RepositoryCompression>,
+
+    /// Target size of pack files in MiB (`restic --pack-size`, 4-128);
+    /// larger packs mean fewer objects on cold storage
+    #[serde(default)]
+    pub pack_size_mb: Option<u32>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum DestinationType {
     Sftp,
+    #[default]
     Local,
     S3,
     B2,
+    Azure,
+    Gcs,
+    /// Any other restic backend, described by `repository_prefix` and
+    /// `required_env`
+    Custom,
+}
+
+impl DestinationType {
+    /// Environment variables restic needs for this backend
+    pub fn required_env_vars(&self) -> &'static [&'static str] {
+        match self {
+            // The key is optional: AZURE_ACCOUNT_SAS or a managed identity work too
+            DestinationType::Azure => &["AZURE_ACCOUNT_NAME"],
+            DestinationType::Gcs => &["GOOGLE_APPLICATION_CREDENTIALS"],
+            _ => &[],
+        }
+    }
+}
+
+impl Destination {
+    /// Repository base URL, with the `custom` backend's prefix
+    pub fn base_url(&self) -> String {
+        match self.repository_prefix.as_deref() {
+            Some(prefix) if self.dest_type == DestinationType::Custom && !self.url.starts_with(prefix) => {
+                format!("{}{}", prefix, self.url)
+            }
+            _ => self.url.clone(),
+        }
+    }
+
+    /// Environment variables the backend needs (its type's and `required_env`)
+    pub fn required_env_vars(&self) -> Vec<&str> {
+        let mut vars = self.dest_type.required_env_vars().to_vec();
+        vars.extend(self.required_env.iter().map(String::as_str));
+        vars
+    }
+
+    /// Effective password source (destination override or global)
+    pub fn password_source(&self, global: &GlobalConfig) -> PasswordSource {
+        self.password_source.unwrap_or(global.password_source)
+    }
+
+    /// Effective password command (destination override or global)
+    ///
+    /// Only used when the password source is `file`; a command takes
+    /// precedence over the password file.
+    pub fn password_command<'a>(&'a self, global: &'a GlobalConfig) -> Option<&'a str> {
+        self.restic_password_command
+            .as_deref()
+            .or(global.restic_password_command.as_deref())
+            .filter(|c| !c.trim().is_empty())
+    }
+
+    /// Whether snapshots may be deleted (forget/prune) from this host
+    pub fn allows_deletion(&self, global: &GlobalConfig) -> bool {
+        !self.append_only || global.trusted_host
+    }
+
+    /// Look up an environment variable for this destination, falling back
+    /// to the process environment
+    pub fn env_var(&self, key: &str) -> Option<String> {
+        self.env
+            .get(key)
+            .cloned()
+            .or_else(|| std::env::var(key).ok())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Why restic may fail to authenticate, for credentials that are
+    /// optional but usually needed
+    pub fn credentials_warning(&self) -> Option<String> {
+        let has = |key: &str| self.env_var(key).is_some();
+        match self.dest_type {
+            DestinationType::Azure if !has("AZURE_ACCOUNT_KEY") && !has("AZURE_ACCOUNT_SAS") => Some(
+                "neither AZURE_ACCOUNT_KEY nor AZURE_ACCOUNT_SAS is set, restic relies on a managed identity or Azure CLI login"
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
 }
 
 /// Notification configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct NotificationConfig {
+    /// Discord webhook URL
     #[serde(default)]
     pub discord_webhook_url: String,
 
+    /// Post all notifications of a run into one Discord thread, created by
+    /// the first message (the webhook must belong to a forum channel)
+    #[serde(default)]
+    pub discord_thread_per_run: bool,
+
+    /// Slack incoming webhook URL
+    #[serde(default)]
+    pub slack_webhook_url: String,
+
+    /// Telegram bot token (from @BotFather)
+    #[serde(default)]
+    pub telegram_bot_token: String,
+
+    /// Telegram chat to post to (numeric id or @channelname)
+    #[serde(default)]
+    pub telegram_chat_id: String,
+
+    /// Generic JSON webhook URL
+    #[serde(default)]
+    pub webhook_url: String,
+
+    /// Payload template for the generic webhook; placeholders such as
+    /// `{{service}}` and `{{error}}` are replaced with JSON-escaped values
+    #[serde(default)]
+    pub webhook_template: Option<String>,
+
+    /// Email recipients; mail is handed to the local `sendmail_command`
+    #[serde(default)]
+    pub sendmail_to: Vec<String>,
+
+    /// `From:` header of the emails (left to the mail system if not set)
+    #[serde(default)]
+    pub sendmail_from: Option<String>,
+
+    /// sendmail-compatible command the message is piped to; the recipients
+    /// are appended as arguments
+    #[serde(default = "default_sendmail_command")]
+    pub sendmail_command: String,
+
+    /// Events to notify about. Precedence: service > profile > notifications
     #[serde(default = "default_notify_on")]
     pub notify_on: Vec<NotifyEvent>,
 
-    #[serde(default = "default_rate_limit")]
+    /// Minimum time between identical notifications
+    #[serde(default = "default_rate_limit", with = "serde_human::minutes")]
+    #[schemars(schema_with = "serde_human::schema")]
     pub rate_limit_minutes: u64,
 
+    /// File tracking recently sent notifications (for rate limiting)
     #[serde(default = "default_cache_file")]
     pub cache_file: PathBuf,
+
+    /// Healthchecks.io heartbeat pings (dead-man-switch monitoring)
+    #[serde(default)]
+    pub healthchecks: HealthchecksConfig,
+}
+
+/// Healthchecks.io integration
+///
+/// Each mapped service pings `/start` when its backup begins, the check URL
+/// when it succeeds and `/fail` when it fails. A check that stops receiving
+/// pings alerts on its own, which catches backups that silently stop running.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct HealthchecksConfig {
+    /// Ping server (change for self-hosted instances)
+    #[serde(default = "default_healthchecks_url")]
+    pub base_url: String,
+
+    /// Project ping key, required when checks are given as slugs
+    #[serde(default)]
+    pub ping_key: String,
+
+    /// Check per service: a check UUID or a slug (used with ping_key)
+    #[serde(default)]
+    pub checks: HashMap<String, String>,
+}
+
+impl Default for HealthchecksConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_healthchecks_url(),
+            ping_key: String::new(),
+            checks: HashMap::new(),
+        }
+    }
+}
+
+impl HealthchecksConfig {
+    /// Ping URL of a service's check, if the service has one
+    pub fn ping_url(&self, service: &str) -> Option<String> {
+        let check = self.checks.get(service)?;
+        let base = self.base_url.trim_end_matches('/');
+
+        if is_uuid(check) {
+            Some(format!("{}/{}", base, check))
+        } else {
+            Some(format!("{}/{}/{}", base, self.ping_key, check))
+        }
+    }
+}
+
+/// Whether a string looks like a UUID (8-4-4-4-12 hex digits)
+pub fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+impl NotificationConfig {
+    /// Whether at least one notification channel is configured
+    pub fn has_channels(&self) -> bool {
+        !self.discord_webhook_url.is_empty()
+            || !self.slack_webhook_url.is_empty()
+            || (!self.telegram_bot_token.is_empty() && !self.telegram_chat_id.is_empty())
+            || !self.webhook_url.is_empty()
+            || !self.sendmail_to.is_empty()
+    }
 }
 
 impl Default for NotificationConfig {
     fn default() -> Self {
         Self {
             discord_webhook_url: String::new(),
+            discord_thread_per_run: false,
+            slack_webhook_url: String::new(),
+            telegram_bot_token: String::new(),
+            telegram_chat_id: String::new(),
+            webhook_url: String::new(),
+            webhook_template: None,
+            sendmail_to: Vec::new(),
+            sendmail_from: None,
+            sendmail_command: default_sendmail_command(),
             notify_on: default_notify_on(),
             rate_limit_minutes: default_rate_limit(),
             cache_file: default_cache_file(),
+            healthchecks: HealthchecksConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum NotifyEvent {
     Failure,
@@ -114,65 +949,184 @@ pub enum NotifyEvent {
 }
 
 /// Profile for grouping common service settings
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Default)]
 pub struct Profile {
+    /// Backup targets (destination names). Precedence: service > profile
     #[serde(default)]
     pub targets: Vec<String>,
 
+    /// Daily snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_daily: Option<u32>,
+    /// Weekly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_weekly: Option<u32>,
+    /// Monthly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_monthly: Option<u32>,
+    /// Yearly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_yearly: Option<u32>,
-
+    /// Hourly snapshots to keep. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_hourly: Option<u32>,
+    /// Keep this many of the most recent snapshots. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_last: Option<u32>,
+    /// Keep all snapshots newer than this restic duration (e.g. "14d").
+    /// Precedence: service > profile > global
     #[serde(default)]
+    pub retention_within: Option<String>,
+
+    /// Backup timeout in seconds. Precedence: service > profile > global default_timeout_seconds
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
     pub timeout_seconds: Option<u64>,
 
+    /// Time budget in `run` (all services). Precedence: service > profile
+    #[serde(default, with = "serde_human::option_minutes")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub time_budget_minutes: Option<u64>,
+
+    /// Age of the latest backup at which `status` reports it as overdue.
+    /// Precedence: service > profile
+    #[serde(default, with = "serde_human::option_hours")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub max_age_hours: Option<u64>,
+
+    /// Retries of failed repository operations. Precedence: service > profile > global
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Delay before the first retry. Precedence: service > profile > global
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub retry_delay_seconds: Option<u64>,
+    /// Growth factor of the retry delay. Precedence: service > profile > global
+    #[serde(default)]
+    pub retry_backoff_multiplier: Option<f64>,
+
+    /// Check each new snapshot after the backup. Precedence: service > profile > global
+    #[serde(default)]
+    pub post_backup_verify: Option<PostBackupVerify>,
+
+    /// Events to notify about. Precedence: service > profile > notifications
     #[serde(default)]
     pub notify_on: Vec<NotifyEvent>,
+
+    /// Tags added to every snapshot of the profile's services
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Upload bandwidth limit in KiB/s for the profile's services
+    #[serde(default)]
+    pub limit_upload_kbps: Option<u32>,
+
+    /// Download bandwidth limit in KiB/s for the profile's services
+    #[serde(default)]
+    pub limit_download_kbps: Option<u32>,
 }
 
 /// Service configuration (raw, before profile merging)
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ServiceConfig {
+    /// Whether the service is backed up
     #[serde(default = "default_enabled")]
     pub enabled: bool,
 
+    /// Temporarily disable the service until this date (`YYYY-MM-DD`); it is
+    /// backed up again from that day on without a config change
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub disabled_until: Option<NaiveDate>,
+
     /// Profile to inherit from (optional)
     #[serde(default)]
     pub profile: Option<String>,
 
+    /// Human-readable description
     #[serde(default)]
     pub description: String,
 
-    /// Cron schedule
+    /// Cron schedule (minute hour day month weekday)
     pub schedule: String,
 
-    /// Backup targets (destination names)
+    /// Backup targets (destination names). Precedence: service > profile (one of them must set it)
     #[serde(default)]
     pub targets: Vec<String>,
 
-    /// Timeout override
-    #[serde(default)]
+    /// Backup timeout in seconds. Precedence: service > profile > global default_timeout_seconds
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
     pub timeout_seconds: Option<u64>,
 
-    /// Retention overrides
+    /// Time budget in `run` (all services): once exceeded, retention and
+    /// snapshot checks of this service are deferred to the next `maintain`
+    /// so the remaining services start sooner. Precedence: service > profile
+    #[serde(default, with = "serde_human::option_minutes")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub time_budget_minutes: Option<u64>,
+
+    /// Age of the latest backup at which `status` reports it as overdue
+    /// (default: the longest gap between two scheduled runs plus the
+    /// timeout). Precedence: service > profile
+    #[serde(default, with = "serde_human::option_hours")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub max_age_hours: Option<u64>,
+
+    /// Daily snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_daily: Option<u32>,
+    /// Weekly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_weekly: Option<u32>,
+    /// Monthly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_monthly: Option<u32>,
+    /// Yearly snapshots to keep. Precedence: service > profile > global
     #[serde(default)]
     pub retention_yearly: Option<u32>,
+    /// Hourly snapshots to keep. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_hourly: Option<u32>,
+    /// Keep this many of the most recent snapshots. Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_last: Option<u32>,
+    /// Keep all snapshots newer than this restic duration (e.g. "14d").
+    /// Precedence: service > profile > global
+    #[serde(default)]
+    pub retention_within: Option<String>,
+
+    /// Additional retention rules (tag-based)
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    /// Retries of failed repository operations. Precedence: service > profile > global
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Delay before the first retry. Precedence: service > profile > global
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub retry_delay_seconds: Option<u64>,
+    /// Growth factor of the retry delay. Precedence: service > profile > global
+    #[serde(default)]
+    pub retry_backoff_multiplier: Option<f64>,
 
-    /// Notification overrides
+    /// Check each new snapshot after the backup. Precedence: service > profile > global
+    #[serde(default)]
+    pub post_backup_verify: Option<PostBackupVerify>,
+
+    /// Events to notify about. Precedence: service > profile > notifications.notify_on
     #[serde(default)]
     pub notify_on: Vec<NotifyEvent>,
 
+    /// Tags added to every snapshot (after the profile's tags)
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Host name of the service's snapshots. Precedence: service > global
+    #[serde(default)]
+    pub hostname: Option<String>,
+
     /// Backup configuration (paths, volumes, hooks)
     #[serde(default)]
     pub config: Option<BackupConfig>,
@@ -182,27 +1136,141 @@ pub struct ServiceConfig {
 #[derive(Debug, Clone)]
 pub struct ResolvedServiceConfig {
     pub name: String,
+    /// False while `disabled_until` lies in the future
     pub enabled: bool,
+    /// Date a temporarily disabled service is enabled again
+    pub disabled_until: Option<NaiveDate>,
     pub description: String,
     pub schedule: String,
     pub targets: Vec<String>,
     pub timeout_seconds: u64,
+    pub time_budget_minutes: Option<u64>,
+    pub max_age_hours: Option<u64>,
     pub retention: RetentionPolicy,
+    pub retry: RetryPolicy,
+    pub post_backup_verify: PostBackupVerify,
     #[allow(dead_code)]
     pub notify_on: Vec<NotifyEvent>,
+    /// Snapshot tags from the profile and the service
+    pub tags: Vec<String>,
+    /// Host name of the snapshots (unset: the machine's)
+    pub hostname: Option<String>,
+    /// Bandwidth limits of the profile in KiB/s (global and destination
+    /// limits apply on top)
+    pub limit_upload_kbps: Option<u32>,
+    pub limit_download_kbps: Option<u32>,
     pub config: Option<BackupConfig>,
 }
 
-#[derive(Debug, Clone)]
+impl ResolvedServiceConfig {
+    /// Age of the latest backup at which it is overdue: `max_age_hours`,
+    /// else the longest gap between two scheduled runs plus the timeout
+    /// (a day if the schedule can't be read)
+    pub fn max_backup_age(&self) -> Duration {
+        match self.max_age_hours {
+            Some(hours) => Duration::from_secs(hours * 3600),
+            None => {
+                crate::utils::cron::schedule_interval(&self.schedule).unwrap_or(Duration::from_secs(24 * 3600))
+                    + Duration::from_secs(self.timeout_seconds)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct RetentionPolicy {
     pub daily: u32,
     pub weekly: u32,
     pub monthly: u32,
     pub yearly: u32,
+    pub hourly: u32,
+    /// Most recent snapshots to keep
+    pub last: u32,
+    /// Restic duration within which all snapshots are kept
+    pub within: Option<String>,
+    /// Rules for tagged snapshots, which the daily/weekly/... policy leaves alone
+    pub tags: BTreeMap<String, TagRetention>,
+}
+
+impl RetentionPolicy {
+    /// Whether any rule keeps snapshots (restic refuses to forget without one)
+    pub fn keeps_any(&self) -> bool {
+        [self.daily, self.weekly, self.monthly, self.yearly, self.hourly, self.last].iter().any(|&n| n > 0)
+            || self.within.is_some()
+    }
+}
+
+/// How often and how patiently failed repository operations are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub delay_seconds: u64,
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            delay_seconds: default_retry_delay_seconds(),
+            backoff_multiplier: default_retry_backoff_multiplier(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (1 = first retry)
+    pub fn delay(&self, retry: u32) -> std::time::Duration {
+        let factor = self.backoff_multiplier.powi(retry.saturating_sub(1) as i32);
+        std::time::Duration::from_secs_f64((self.delay_seconds as f64 * factor).max(0.0))
+    }
+}
+
+/// Service retention rules beyond the daily/weekly/monthly/yearly counts
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct RetentionConfig {
+    /// Retention per snapshot tag, e.g. `pre-upgrade = { forever = true }` or
+    /// `manual = { within = "90d" }`. Tagged snapshots are exempt from the
+    /// regular policy and only kept according to their tag's rule
+    #[serde(default)]
+    pub tags: BTreeMap<String, TagRetention>,
+}
+
+/// Retention rule for snapshots carrying a tag
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct TagRetention {
+    /// Never forget snapshots with this tag
+    #[serde(default)]
+    pub forever: bool,
+
+    /// Keep snapshots newer than this restic duration (e.g. "90d", "1y6m")
+    #[serde(default)]
+    pub within: Option<String>,
+
+    /// Keep this many of the most recent snapshots
+    #[serde(default)]
+    pub last: Option<u32>,
+}
+
+/// Whether a string is a restic duration like `90d` or `1y6m2h`
+pub fn is_restic_duration(value: &str) -> bool {
+    let mut digits = false;
+    let mut units = 0;
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits = true;
+        } else if digits && "ymdh".contains(c) {
+            digits = false;
+            units += 1;
+        } else {
+            return false;
+        }
+    }
+    !digits && units > 0
 }
 
 /// Hook to run before or after backup
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Hook {
     /// Name/description of the hook
     #[serde(default)]
@@ -216,7 +1284,8 @@ pub struct Hook {
     pub working_dir: Option<PathBuf>,
 
     /// Timeout in seconds (optional)
-    #[serde(default)]
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
     pub timeout_seconds: Option<u64>,
 
     /// Whether to continue on failure
@@ -229,20 +1298,68 @@ fn default_continue_on_error() -> bool {
 }
 
 /// Backup configuration (paths, volumes, hooks)
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct BackupConfig {
     /// File/directory paths to backup (relative to docker_base or absolute)
     #[serde(default)]
     pub paths: Vec<String>,
 
+    /// Files listing more paths to back up, one per line; patterns and `#`
+    /// comments are allowed (relative to docker_base or absolute)
+    #[serde(default)]
+    pub files_from: Vec<String>,
+
+    /// Files listing more paths to back up, taken exactly as written (for
+    /// generated lists whose names may contain `#` or leading spaces)
+    #[serde(default)]
+    pub files_from_verbatim: Vec<String>,
+
     /// Docker volumes to backup
     #[serde(default)]
     pub volumes: Vec<String>,
 
+    /// Containers whose named volumes and bind-mount sources are backed up
+    /// (inspected at backup time, in addition to `volumes` and `paths`)
+    #[serde(default)]
+    pub containers: Vec<String>,
+
+    /// Docker Compose project of the service (`docker compose -p`)
+    #[serde(default)]
+    pub compose_project: Option<String>,
+
+    /// Docker Compose file of the service (relative to docker_base or absolute)
+    #[serde(default)]
+    pub compose_file: Option<String>,
+
+    /// Stop the Compose stack while volumes and paths are backed up, and
+    /// start it again afterwards (also when the backup fails)
+    #[serde(default)]
+    pub stop_during_backup: bool,
+
+    /// Containers to pause (or stop) while the volumes are archived
+    #[serde(default)]
+    pub pause_containers: Option<ContainerPause>,
+
     /// Exclusion patterns
     #[serde(default)]
     pub excludes: Vec<String>,
 
+    /// Case-insensitive exclusion patterns
+    #[serde(default)]
+    pub iexcludes: Vec<String>,
+
+    /// Files of exclusion patterns, one per line (relative to docker_base or absolute)
+    #[serde(default, alias = "exclude_file")]
+    pub exclude_files: Vec<String>,
+
+    /// Skip files larger than this, e.g. "500M" (overrides the global setting)
+    #[serde(default)]
+    pub exclude_larger_than: Option<String>,
+
+    /// Don't cross file system boundaries (overrides the global setting)
+    #[serde(default)]
+    pub one_file_system: Option<bool>,
+
     /// Hooks to run before backup
     #[serde(default)]
     pub pre_backup_hooks: Vec<Hook>,
@@ -250,6 +1367,78 @@ pub struct BackupConfig {
     /// Hooks to run after backup
     #[serde(default)]
     pub post_backup_hooks: Vec<Hook>,
+
+    /// Commands whose output is backed up as a file (`restic backup --stdin`)
+    #[serde(default)]
+    pub commands: Vec<CommandSource>,
+
+    /// Hooks to run when the backup to a destination fails (RM_SERVICE,
+    /// RM_DESTINATION, RM_OUTCOME, RM_STAGE and RM_ERROR describe the failure)
+    #[serde(default)]
+    pub on_failure_hooks: Vec<Hook>,
+
+    /// Hooks to run after the backup to a destination succeeded
+    #[serde(default)]
+    pub on_success_hooks: Vec<Hook>,
+
+    /// Hooks validating a `test-restore` (RM_RESTORE_DIR holds the restored
+    /// files, RM_VOLUME_<NAME> the throwaway volumes when restored)
+    #[serde(default)]
+    pub restore_test_hooks: Vec<Hook>,
+}
+
+/// Containers paused while a service's volumes are archived
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ContainerPause {
+    /// Docker label selecting the containers (`key` or `key=value`)
+    pub label: String,
+
+    /// Pause the containers (freeze their processes) or stop them
+    #[serde(default)]
+    pub mode: PauseMode,
+
+    /// Resume the containers after this long even if archiving isn't done
+    /// (the backup then fails)
+    #[serde(default = "default_max_pause_seconds", with = "serde_human::seconds")]
+    #[schemars(schema_with = "serde_human::schema")]
+    pub max_pause_seconds: u64,
+}
+
+/// How containers are held still during volume archiving
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseMode {
+    /// `docker pause` / `docker unpause`
+    #[default]
+    Pause,
+    /// `docker stop` / `docker start` (flushes state to disk on shutdown)
+    Stop,
+}
+
+/// A command whose stdout is backed up, e.g. `crontab -l`
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CommandSource {
+    /// Name of the source (used for the default file name)
+    pub name: String,
+
+    /// Shell command producing the data on stdout
+    pub command: String,
+
+    /// File name in the snapshot (defaults to "<name>.txt")
+    #[serde(default)]
+    pub filename: Option<String>,
+
+    /// Timeout in seconds for the command. Precedence: source > service timeout
+    #[serde(default, with = "serde_human::option_seconds")]
+    #[schemars(schema_with = "serde_human::option_schema")]
+    pub timeout_seconds: Option<u64>,
+}
+
+impl CommandSource {
+    /// File name the output is stored under
+    pub fn stdin_filename(&self) -> String {
+        self.filename.clone().unwrap_or_else(|| format!("{}.txt", self.name))
+    }
 }
 
 // Default value functions
@@ -259,15 +1448,31 @@ fn default_retention_weekly() -> u32 { 4 }
 fn default_retention_monthly() -> u32 { 6 }
 fn default_timeout() -> u64 { 3600 }
 fn default_long_running_threshold() -> u64 { 120 }
+fn default_failure_backoff_after() -> u32 { 3 }
+
+fn default_mqtt_port() -> u16 { 1883 }
+fn default_mqtt_topic_prefix() -> String { "restic-manager".to_string() }
+fn default_mqtt_discovery_prefix() -> String { "homeassistant".to_string() }
+fn default_mqtt_client_id() -> String { "restic-manager".to_string() }
+fn default_discovery_schedule() -> String { "0 3 * * *".to_string() }
+fn default_retry_delay_seconds() -> u64 { 30 }
+fn default_retry_backoff_multiplier() -> f64 { 2.0 }
 fn default_log_directory() -> PathBuf { PathBuf::from("~/logs") }
 fn default_log_level() -> String { "info".to_string() }
 fn default_log_max_files() -> u32 { 10 }
 fn default_log_max_size_mb() -> u64 { 10 }
+fn default_keep_run_logs() -> u32 { 30 }
+pub(super) fn default_max_pause_seconds() -> u64 { 600 }
 fn default_enabled() -> bool { true }
+fn default_pre_restore_snapshot() -> bool { true }
 fn default_notify_on() -> Vec<NotifyEvent> {
     vec![NotifyEvent::Failure, NotifyEvent::Warning]
 }
 fn default_rate_limit() -> u64 { 60 }
+fn default_sendmail_command() -> String { "/usr/sbin/sendmail".to_string() }
 fn default_cache_file() -> PathBuf {
     PathBuf::from("~/.cache/restic-manager-notifications.json")
 }
+fn default_healthchecks_url() -> String { "https://hc-ping.com".to_string() }
+fn default_listen() -> String { "127.0.0.1:8090".to_string() }
+fn default_snapshot_cache_ttl() -> u64 { 300 }
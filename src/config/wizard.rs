@@ -0,0 +1,216 @@
+//! Rendering of a first configuration file from `restic-manager init` answers
+
+use super::types::DestinationType;
+use crate::utils::secrets;
+use anyhow::Result;
+use rand::Rng;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Length of generated repository passwords
+pub const GENERATED_PASSWORD_LENGTH: usize = 32;
+
+/// Destination entered in the init wizard
+#[derive(Debug, Clone)]
+pub struct WizardDestination {
+    pub name: String,
+    pub dest_type: DestinationType,
+    pub url: String,
+}
+
+/// Service entered in the init wizard
+#[derive(Debug, Clone)]
+pub struct WizardService {
+    pub name: String,
+    pub schedule: String,
+    pub targets: Vec<String>,
+    pub paths: Vec<String>,
+    pub volumes: Vec<String>,
+}
+
+/// All answers collected by the init wizard
+#[derive(Debug, Clone)]
+pub struct WizardAnswers {
+    pub password_file: PathBuf,
+    pub docker_base: PathBuf,
+    pub log_directory: PathBuf,
+    pub destinations: Vec<WizardDestination>,
+    pub services: Vec<WizardService>,
+}
+
+/// Generate a random alphanumeric repository password
+pub fn generate_password() -> String {
+    rand::rng()
+        .sample_iter(&rand::distr::Alphanumeric)
+        .take(GENERATED_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Write a password file readable only by the current user
+pub fn write_password_file(path: &Path, password: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    secrets::write_private(path, &format!("{}\n", password))
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+fn toml_array(values: &[String]) -> String {
+    let items: Vec<String> = values.iter().map(|v| toml_string(v)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn type_name(dest_type: &DestinationType) -> &'static str {
+    match dest_type {
+        DestinationType::Sftp => "sftp",
+        DestinationType::Local => "local",
+        DestinationType::S3 => "s3",
+        DestinationType::B2 => "b2",
+        DestinationType::Azure => "azure",
+        DestinationType::Gcs => "gcs",
+        DestinationType::Custom => "custom",
+    }
+}
+
+/// Render a commented TOML configuration from wizard answers
+pub fn render_config(answers: &WizardAnswers) -> String {
+    let mut out = String::new();
+
+    // Writing to a String cannot fail
+    let _ = writeln!(out, "# Restic Manager Configuration");
+    let _ = writeln!(out, "# Generated by `restic-manager init` - see config.example.toml for all options");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "[global]");
+    let _ = writeln!(out, "# File containing the repository password (keep it safe - without it backups are unreadable)");
+    let _ = writeln!(out, "restic_password_file = {}", toml_string(&answers.password_file.display().to_string()));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "# Relative service paths are resolved against this directory");
+    let _ = writeln!(out, "docker_base = {}", toml_string(&answers.docker_base.display().to_string()));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "log_directory = {}", toml_string(&answers.log_directory.display().to_string()));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "# Default retention (can be overridden per profile or service)");
+    let _ = writeln!(out, "retention_daily = 7");
+    let _ = writeln!(out, "retention_weekly = 4");
+    let _ = writeln!(out, "retention_monthly = 6");
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "# ============================================================================");
+    let _ = writeln!(out, "# BACKUP DESTINATIONS");
+    let _ = writeln!(out, "# ============================================================================");
+    for destination in &answers.destinations {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "[destinations.{}]", destination.name);
+        let _ = writeln!(out, "type = {}", toml_string(type_name(&destination.dest_type)));
+        let _ = writeln!(out, "url = {}", toml_string(&destination.url));
+        match destination.dest_type {
+            DestinationType::S3 => {
+                let _ = writeln!(out, "# Credentials: set AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY");
+                let _ = writeln!(out, "# [destinations.{}.env]", destination.name);
+            }
+            DestinationType::B2 => {
+                let _ = writeln!(out, "# Credentials: set B2_ACCOUNT_ID / B2_ACCOUNT_KEY");
+                let _ = writeln!(out, "# [destinations.{}.env]", destination.name);
+            }
+            _ => {}
+        }
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "# ============================================================================");
+    let _ = writeln!(out, "# SERVICES");
+    let _ = writeln!(out, "# ============================================================================");
+    for service in &answers.services {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "[services.{}]", service.name);
+        let _ = writeln!(out, "enabled = true");
+        let _ = writeln!(out, "# Cron format: minute hour day month weekday");
+        let _ = writeln!(out, "schedule = {}", toml_string(&service.schedule));
+        let _ = writeln!(out, "targets = {}", toml_array(&service.targets));
+        let _ = writeln!(out);
+        let _ = writeln!(out, "[services.{}.config]", service.name);
+        let _ = writeln!(out, "paths = {}", toml_array(&service.paths));
+        let _ = writeln!(out, "volumes = {}", toml_array(&service.volumes));
+        let _ = writeln!(out);
+        let _ = writeln!(out, "# Run commands before the backup (e.g. database dumps):");
+        let _ = writeln!(out, "# [[services.{}.config.pre_backup_hooks]]", service.name);
+        let _ = writeln!(out, "# name = \"Dump database\"");
+        let _ = writeln!(out, "# command = \"docker exec db pg_dumpall -U postgres > dump.sql\"");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_password() {
+        let password = generate_password();
+        assert_eq!(password.len(), GENERATED_PASSWORD_LENGTH);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(password, generate_password());
+    }
+
+    #[test]
+    fn test_write_password_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("secrets").join("restic-password");
+
+        write_password_file(&path, "secret").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret\n");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_render_config_parses_back() {
+        let answers = WizardAnswers {
+            password_file: PathBuf::from("/home/user/.restic-password"),
+            docker_base: PathBuf::from("/home/user/docker"),
+            log_directory: PathBuf::from("/home/user/logs"),
+            destinations: vec![
+                WizardDestination {
+                    name: "local".to_string(),
+                    dest_type: DestinationType::Local,
+                    url: "/mnt/backup".to_string(),
+                },
+                WizardDestination {
+                    name: "s3".to_string(),
+                    dest_type: DestinationType::S3,
+                    url: "s3:s3.amazonaws.com/bucket".to_string(),
+                },
+            ],
+            services: vec![WizardService {
+                name: "app".to_string(),
+                schedule: "0 2 * * *".to_string(),
+                targets: vec!["local".to_string(), "s3".to_string()],
+                paths: vec!["app/data".to_string()],
+                volumes: vec!["app_db".to_string()],
+            }],
+        };
+
+        let rendered = render_config(&answers);
+        let config: Config = toml::from_str(&rendered).unwrap();
+
+        assert_eq!(config.destinations.len(), 2);
+        assert_eq!(config.destinations["s3"].dest_type, DestinationType::S3);
+        let service = &config.services["app"];
+        assert_eq!(service.targets, vec!["local", "s3"]);
+        assert_eq!(service.config.as_ref().unwrap().volumes, vec!["app_db"]);
+    }
+}
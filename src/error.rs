@@ -0,0 +1,96 @@
+//! Errors of backup operations
+//!
+//! Functions of `BackupManager` and `utils::restic` return `anyhow::Result`
+//! so failures can carry context ("Failed to back up 'app' to 'nas'"), but
+//! the failures library users want to react to are raised as a
+//! [`BackupError`] somewhere in the chain. Find it with [`BackupError::of`]:
+//!
+//! ```no_run
+//! # use restic_manager::error::BackupError;
+//! # fn handle(error: anyhow::Error) {
+//! match BackupError::of(&error) {
+//!     Some(BackupError::LockHeld { .. }) => { /* try again later */ }
+//!     Some(BackupError::Timeout(after)) => { /* raise the timeout */ }
+//!     _ => { /* anything else */ }
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+
+/// A failure of a backup operation
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    /// The repository is locked by another restic process
+    #[error("Repository is locked: {stderr}")]
+    LockHeld { stderr: String },
+
+    /// `restic init` failed (an existing repository is not an error)
+    #[error("Failed to initialize repository: {stderr}")]
+    RepoInit { stderr: String },
+
+    /// A restic command exited with an error
+    #[error("{operation} failed: {stderr}")]
+    ResticFailed { operation: String, stderr: String },
+
+    /// A hook without `continue_on_error` failed (the cause is its source)
+    #[error("Failed to execute hook: {hook}")]
+    HookFailed { hook: String },
+
+    /// A configured Docker volume doesn't exist
+    #[error("Docker volume does not exist: {0}")]
+    VolumeMissing(String),
+
+    /// Some destinations (or services) of a run failed, others succeeded
+    #[error("{summary}")]
+    PartialFailure { summary: String },
+
+    /// A restic command ran longer than its timeout and was killed
+    #[error("restic timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+impl BackupError {
+    /// Find the backup error in an error's chain
+    pub fn of(error: &anyhow::Error) -> Option<&BackupError> {
+        // downcast_ref also finds errors attached with `.context()`
+        error
+            .downcast_ref::<BackupError>()
+            .or_else(|| error.chain().find_map(|cause| cause.downcast_ref::<BackupError>()))
+    }
+
+    /// Error of a failed restic command, recognizing a held lock
+    pub fn restic_failed(operation: impl Into<String>, stderr: &str) -> Self {
+        let stderr = stderr.trim().to_string();
+        if crate::utils::restic::is_lock_error(&stderr) {
+            BackupError::LockHeld { stderr }
+        } else {
+            BackupError::ResticFailed { operation: operation.into(), stderr }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_backup_error_of() {
+        let result: anyhow::Result<()> = Err(BackupError::restic_failed("Prune", "Fatal: wrong password\n").into());
+        let error = result.context("Failed to prune 'app'").unwrap_err();
+        assert!(matches!(
+            BackupError::of(&error),
+            Some(BackupError::ResticFailed { operation, stderr }) if operation == "Prune" && stderr == "Fatal: wrong password"
+        ));
+
+        let locked = BackupError::restic_failed("Forget", "unable to create lock in backend: repository is already locked");
+        assert!(matches!(locked, BackupError::LockHeld { .. }));
+
+        // Attached as context, the hook keeps its cause in the chain
+        let result: anyhow::Result<()> = Err(anyhow::anyhow!("exit status 1"));
+        let error = result.context(BackupError::HookFailed { hook: "dump".to_string() }).unwrap_err();
+        assert!(matches!(BackupError::of(&error), Some(BackupError::HookFailed { hook }) if hook == "dump"));
+        assert!(BackupError::of(&anyhow::anyhow!("other")).is_none());
+    }
+}
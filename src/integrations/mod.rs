@@ -0,0 +1,6 @@
+//! Integrations with other systems
+//!
+//! Like healthchecks pings, integrations are best effort: a failure is
+//! logged but never fails a backup.
+
+pub mod mqtt;
@@ -0,0 +1,233 @@
+//! Home Assistant integration over MQTT
+//!
+//! After each run the service's state is published as one retained JSON
+//! message to `<topic_prefix>/<service>/state`. Alongside it go retained
+//! discovery configs (`<discovery_prefix>/<component>/<object_id>/config`)
+//! that make Home Assistant create a device per service with status, last
+//! run, duration and size sensors, plus a problem binary sensor to trigger
+//! automations on.
+
+use crate::config::MqttConfig;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rumqttc::{Client, ConnectReturnCode, Event, MqttOptions, Outgoing, Packet, QoS, RecvTimeoutError};
+use serde::Serialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Time allowed for connecting and publishing
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// State of a service after a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceState {
+    /// `success`, or how the run failed (error, timeout, cancelled)
+    pub status: String,
+    pub last_run: DateTime<Utc>,
+    /// Run duration in seconds
+    pub duration: u64,
+    /// Data added to the repositories, in bytes
+    pub size: u64,
+    /// Destinations that succeeded / were attempted
+    pub destinations_ok: usize,
+    pub destinations_total: usize,
+}
+
+/// Publishes service state to an MQTT broker
+pub struct MqttPublisher {
+    config: MqttConfig,
+}
+
+impl MqttPublisher {
+    pub fn new(config: MqttConfig) -> Self {
+        Self { config }
+    }
+
+    /// Topic the state of a service is published to
+    pub fn state_topic(&self, service: &str) -> String {
+        format!("{}/{}/state", self.config.topic_prefix.trim_end_matches('/'), service)
+    }
+
+    /// Home Assistant discovery messages (topic, payload) for a service
+    pub fn discovery_messages(&self, service: &str) -> Vec<(String, String)> {
+        if self.config.discovery_prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let object_prefix = format!("restic_manager_{}", object_id(service));
+        let device = json!({
+            "identifiers": [object_prefix.clone()],
+            "name": format!("Backup {}", service),
+            "manufacturer": "restic-manager",
+        });
+        let state_topic = self.state_topic(service);
+
+        let sensors = [
+            ("sensor", "status", "Status", json!({ "value_template": "{{ value_json.status }}", "icon": "mdi:backup-restore" })),
+            ("sensor", "last_run", "Last run", json!({ "value_template": "{{ value_json.last_run }}", "device_class": "timestamp" })),
+            (
+                "sensor",
+                "duration",
+                "Duration",
+                json!({ "value_template": "{{ value_json.duration }}", "device_class": "duration", "unit_of_measurement": "s" }),
+            ),
+            (
+                "sensor",
+                "size",
+                "Data added",
+                json!({ "value_template": "{{ value_json.size }}", "device_class": "data_size", "unit_of_measurement": "B" }),
+            ),
+            (
+                "binary_sensor",
+                "problem",
+                "Problem",
+                json!({
+                    "value_template": "{{ 'OFF' if value_json.status == 'success' else 'ON' }}",
+                    "device_class": "problem",
+                }),
+            ),
+        ];
+
+        sensors
+            .into_iter()
+            .map(|(component, key, name, extra)| {
+                let unique_id = format!("{}_{}", object_prefix, key);
+                let mut payload = json!({
+                    "name": name,
+                    "unique_id": unique_id,
+                    "object_id": unique_id,
+                    "state_topic": state_topic,
+                    "device": device,
+                });
+                if let (Some(payload), Some(extra)) = (payload.as_object_mut(), extra.as_object()) {
+                    payload.extend(extra.clone());
+                }
+                (
+                    format!("{}/{}/{}/config", self.config.discovery_prefix.trim_end_matches('/'), component, unique_id),
+                    payload.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Publish a service's state (and discovery configs); failures are only logged
+    pub fn publish_state(&self, service: &str, state: &ServiceState) {
+        let mut messages = self.discovery_messages(service);
+        match serde_json::to_string(state) {
+            Ok(payload) => messages.push((self.state_topic(service), payload)),
+            Err(e) => {
+                warn!("Failed to serialize MQTT state for '{}': {}", service, e);
+                return;
+            }
+        }
+
+        match self.publish(&messages) {
+            Ok(()) => debug!("Published MQTT state for service '{}'", service),
+            Err(e) => warn!("Failed to publish MQTT state for '{}': {:#}", service, e),
+        }
+    }
+
+    /// Connect, publish retained messages and disconnect
+    fn publish(&self, messages: &[(String, String)]) -> Result<()> {
+        let mut options = MqttOptions::new(&self.config.client_id, &self.config.host, self.config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(ref username) = self.config.username {
+            options.set_credentials(username, self.config.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut connection) = Client::new(options, messages.len() + 1);
+        for (topic, payload) in messages {
+            client
+                .publish(topic.as_str(), QoS::AtLeastOnce, true, payload.as_bytes())
+                .context(format!("Invalid MQTT topic: {}", topic))?;
+        }
+        client.disconnect().context("Failed to queue MQTT disconnect")?;
+
+        // Drive the connection until the disconnect went out
+        let deadline = Instant::now() + PUBLISH_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match connection.recv_timeout(remaining) {
+                Ok(Ok(Event::Incoming(Packet::ConnAck(ack)))) if ack.code != ConnectReturnCode::Success => {
+                    anyhow::bail!("Broker refused the connection: {:?}", ack.code);
+                }
+                Ok(Ok(Event::Outgoing(Outgoing::Disconnect))) => return Ok(()),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    return Err(e).context(format!("MQTT connection to {}:{} failed", self.config.host, self.config.port))
+                }
+                Err(RecvTimeoutError::Timeout) => anyhow::bail!("Timed out publishing to {}:{}", self.config.host, self.config.port),
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Home Assistant object ids allow letters, digits and underscores
+fn object_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher() -> MqttPublisher {
+        MqttPublisher::new(MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            topic_prefix: "backups/".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            client_id: "restic-manager".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_discovery_messages() {
+        let publisher = publisher();
+        assert_eq!(publisher.state_topic("my-app"), "backups/my-app/state");
+
+        let messages = publisher.discovery_messages("my-app");
+        let topics: Vec<&str> = messages.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            topics,
+            vec![
+                "homeassistant/sensor/restic_manager_my_app_status/config",
+                "homeassistant/sensor/restic_manager_my_app_last_run/config",
+                "homeassistant/sensor/restic_manager_my_app_duration/config",
+                "homeassistant/sensor/restic_manager_my_app_size/config",
+                "homeassistant/binary_sensor/restic_manager_my_app_problem/config",
+            ]
+        );
+
+        let size: serde_json::Value = serde_json::from_str(&messages[3].1).unwrap();
+        assert_eq!(size["state_topic"], "backups/my-app/state");
+        assert_eq!(size["device_class"], "data_size");
+        assert_eq!(size["device"]["identifiers"][0], "restic_manager_my_app");
+
+        let mut publisher = publisher;
+        publisher.config.discovery_prefix.clear();
+        assert!(publisher.discovery_messages("my-app").is_empty());
+    }
+
+    #[test]
+    fn test_service_state_payload() {
+        let state = ServiceState {
+            status: "success".to_string(),
+            last_run: "2025-01-15T02:00:00Z".parse().unwrap(),
+            duration: 95,
+            size: 1024,
+            destinations_ok: 2,
+            destinations_total: 2,
+        };
+        let payload: serde_json::Value = serde_json::to_value(&state).unwrap();
+        assert_eq!(payload["status"], "success");
+        assert_eq!(payload["last_run"], "2025-01-15T02:00:00Z");
+        assert_eq!(payload["size"], 1024);
+    }
+}
@@ -2,11 +2,15 @@
 //!
 //! This library provides backup orchestration functionality wrapping restic.
 
+pub mod commands;
 pub mod config;
+pub mod error;
+pub mod integrations;
 pub mod managers;
 pub mod utils;
 
 // Re-export commonly used types
+pub use error::BackupError;
 pub use config::{load_config, resolve_all_services, Config, ResolvedServiceConfig};
 pub use managers::backup::BackupManager;
 pub use managers::logging::{init_logging, init_console_logging, LoggingConfig, LogGuard};
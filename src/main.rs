@@ -1,25 +1,52 @@
+mod commands;
 mod config;
+mod error;
+mod integrations;
 mod managers;
 mod utils;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use managers::backup::BackupManager;
+use commands::cache::CacheAction;
+use commands::key::KeyAction;
+use commands::context::ContextAction;
+use commands::secret::SecretAction;
+use commands::{CommandContext, Outcome};
+use managers::backup::{BackupManager, RunOptions};
+use managers::outcome::RunTrigger;
 use std::path::PathBuf;
+use utils::output::{self, Verbosity};
 
 #[derive(Parser)]
 #[command(name = "restic-manager")]
 #[command(about = "Backup orchestration tool wrapping restic", long_about = None)]
 #[command(version)]
 struct Cli {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "/home/valerie/backup-config.toml")]
-    config: PathBuf,
+    /// Path to configuration file (defaults to the current context's config)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Named configuration context to use (see `restic-manager context`)
+    #[arg(long)]
+    context: Option<String>,
 
     /// Use system restic from PATH instead of managed binary
     #[arg(long)]
     use_system_restic: bool,
 
+    /// Only print errors (for cron jobs)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print more detail, and debug logs on the console
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Don't color the output (also off when NO_COLOR is set or the output
+    /// isn't a terminal)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -31,6 +58,29 @@ enum Commands {
         /// Specific service to backup (defaults to all enabled services)
         #[arg(short, long)]
         service: Option<String>,
+
+        /// Tag the new snapshots (can be used multiple times)
+        #[arg(long, value_parser = config::parse_tag)]
+        tag: Vec<String>,
+
+        /// Mark the run as scheduled (used by cron jobs and timers); runs
+        /// are tagged `manual` otherwise
+        #[arg(long)]
+        scheduled: bool,
+
+        /// Why the backup is run; kept in the history and the run report
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Show what would be backed up without writing to the repositories,
+        /// applying retention or running hooks
+        #[arg(long, conflicts_with_all = ["tag", "scheduled", "reason", "wait_lock"])]
+        dry_run: bool,
+
+        /// Wait up to this long (e.g. "30m") for a service another process
+        /// is backing up instead of skipping it (overrides wait_lock_minutes)
+        #[arg(long, value_name = "DURATION", value_parser = utils::humanize::parse_duration)]
+        wait_lock: Option<std::time::Duration>,
     },
 
     /// Restore a service from backup
@@ -43,6 +93,11 @@ enum Commands {
         #[arg(long)]
         snapshot: Option<String>,
 
+        /// Restore the newest snapshot at or before this time
+        /// ("2024-06-01 03:00" in local time, or RFC 3339)
+        #[arg(long, value_name = "TIME", conflicts_with = "snapshot")]
+        at: Option<String>,
+
         /// Destination to restore from
         #[arg(short, long)]
         destination: Option<String>,
@@ -54,6 +109,69 @@ enum Commands {
         /// Restore specific paths only (can be used multiple times)
         #[arg(long)]
         path: Vec<String>,
+
+        /// Don't snapshot the current files before an in-place restore
+        #[arg(long)]
+        no_pre_restore_snapshot: bool,
+
+        /// Download bandwidth limit in KiB/s (overrides limit_download_kbps)
+        #[arg(long, value_name = "KBPS")]
+        limit_download: Option<u32>,
+
+        /// Run restic at the lowest CPU and I/O priority
+        #[arg(long)]
+        nice: bool,
+
+        /// Restore this Docker volume from its archive in the snapshot
+        #[arg(long, conflicts_with_all = ["target", "path"])]
+        volume: Option<String>,
+
+        /// Remove and recreate the volume before restoring into it
+        #[arg(long, requires = "volume")]
+        recreate_volume: bool,
+
+        /// Extract the volume archive without checking for enough free space
+        #[arg(long, requires = "volume")]
+        skip_space_check: bool,
+
+        /// Show what would be written or overwritten without restoring
+        #[arg(long, conflicts_with = "volume")]
+        dry_run: bool,
+
+        /// Don't prompt (for scripts): restores the latest snapshot unless
+        /// --snapshot is given and fails if --destination is ambiguous
+        #[arg(short, long, visible_alias = "non-interactive")]
+        yes: bool,
+
+        /// Restore from a cold storage destination despite retrieval time and cost
+        #[arg(long)]
+        acknowledge_cold_storage: bool,
+    },
+
+    /// Run repository maintenance (unlock, check, forget, prune, stats)
+    Maintain {
+        /// Service to maintain
+        #[arg(short, long)]
+        service: String,
+
+        /// Only maintain this destination
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Unused space prune may leave behind (e.g. 10% or 5G)
+        #[arg(long, default_value = "10%")]
+        max_unused: String,
+
+        /// Maximum data prune repacks in one run (e.g. 50G)
+        #[arg(long)]
+        max_repack_size: Option<String>,
+    },
+
+    /// Resume a service paused after repeated failures
+    Resume {
+        /// Service to resume (all services with failure streaks if omitted)
+        #[arg(short, long)]
+        service: Option<String>,
     },
 
     /// Show status and health of all services
@@ -61,6 +179,11 @@ enum Commands {
         /// Specific service to check
         #[arg(short, long)]
         service: Option<String>,
+
+        /// Check the latest backup of every service and destination; the
+        /// exit code reflects the worst health
+        #[arg(long, conflicts_with = "service")]
+        all: bool,
     },
 
     /// List all configured services
@@ -75,6 +198,74 @@ enum Commands {
         /// Optional destination filter
         #[arg(short, long)]
         destination: Option<String>,
+
+        /// Only snapshots with this tag (can be used multiple times; any of them matches)
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Show each snapshot's restore size and the data its backup added
+        #[arg(long)]
+        sizes: bool,
+    },
+
+    /// Compare repository sizes, snapshot counts and deduplication
+    Stats {
+        /// Only this service (default: all enabled services)
+        #[arg(short, long)]
+        service: Option<String>,
+
+        /// Print JSON instead of tables
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that every repository can be opened and how fast it answers
+    CheckDestinations {
+        /// Only check repositories on this destination
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Print JSON instead of tables
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Nagios/Icinga plugin: age of a service's latest backup, with perfdata
+    /// (exits 0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN)
+    CheckHealth {
+        /// Service name
+        #[arg(short, long)]
+        service: String,
+
+        /// Only this destination (default: all of the service's destinations)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Warning age, e.g. "26h" (default: the service's expected backup age)
+        #[arg(long, value_name = "DURATION", value_parser = utils::humanize::parse_duration)]
+        warn: Option<std::time::Duration>,
+
+        /// Critical age, e.g. "50h" (default: twice the expected backup age)
+        #[arg(long, value_name = "DURATION", value_parser = utils::humanize::parse_duration)]
+        crit: Option<std::time::Duration>,
+    },
+
+    /// Print the JSON report of a service's last run
+    Report {
+        /// Service whose last run to show
+        #[arg(short, long, required_unless_present = "schema")]
+        service: Option<String>,
+
+        /// Print the JSON Schema of run reports instead
+        #[arg(long, conflicts_with = "service")]
+        schema: bool,
+    },
+
+    /// Estimate how much new data the next backup would upload
+    Forecast {
+        /// Service to forecast
+        #[arg(short, long)]
+        service: String,
     },
 
     /// Verify repository integrity
@@ -86,6 +277,29 @@ enum Commands {
         /// Perform deep verification (reads all data - slower)
         #[arg(long)]
         read_data: bool,
+
+        /// Read back only part of the data: "5%", "1/7" (one of seven parts) or a size like "500M"
+        #[arg(long, value_name = "SUBSET", conflicts_with = "read_data")]
+        read_data_subset: Option<String>,
+
+        /// Rebuild the index of repositories with index or tree errors, then check again
+        #[arg(long)]
+        repair_index: bool,
+    },
+
+    /// Restore the latest snapshot into a throwaway location and validate it
+    TestRestore {
+        /// Service to test
+        #[arg(short, long)]
+        service: String,
+
+        /// Only test this destination (default: all of the service's destinations)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Also restore the service's volumes into throwaway Docker volumes
+        #[arg(long)]
+        volumes: bool,
     },
 
     /// Initialize directories and setup cron jobs
@@ -103,9 +317,78 @@ enum Commands {
         dirs_only: bool,
     },
 
+    /// Run restic directly with the service's repository environment
+    Raw {
+        /// Service whose repository to use
+        #[arg(short, long)]
+        service: String,
+
+        /// Destination (required if the service has multiple targets)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Skip confirmation for commands that modify the repository
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Arguments passed to restic (after --)
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+
+    /// Run as a long-running server accepting webhook backup triggers
+    Serve {
+        /// Address to listen on (overrides server.listen)
+        #[arg(long)]
+        listen: Option<String>,
+    },
+
     /// Validate configuration file
     Validate,
 
+    /// Check the host for everything scheduled backups need (restic,
+    /// passwords, directories, Docker, paths, cron jobs, repositories)
+    Doctor {
+        /// Apply safe corrections (password file permissions, missing directories)
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Interactively create a first configuration file
+    Init,
+
+    /// Manage restic's local cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Manage the keys (passwords) of the repositories
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
+
+    /// Manage repository passwords stored in the OS keyring
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Explain a configuration field (e.g. services.timeout_seconds)
+    Explain {
+        /// Dotted key path; map sections accept an optional name
+        /// (services.timeout_seconds or services.myapp.timeout_seconds)
+        #[arg(default_value = "")]
+        key: String,
+    },
+
+    /// Manage named configuration contexts
+    Context {
+        #[command(subcommand)]
+        action: ContextAction,
+    },
+
     /// Setup restic binary (download if needed)
     SetupRestic,
 
@@ -116,31 +399,84 @@ enum Commands {
     ResticVersion,
 }
 
-fn main() -> Result<()> {
+fn main() {
     let cli = Cli::parse();
+    let plugin = matches!(cli.command, Some(Commands::CheckHealth { .. }));
+
+    let code = match run_cli(cli) {
+        Ok(outcome) => outcome.exit_code(),
+        // Monitoring reads the first line; any error is UNKNOWN there
+        Err(e) if plugin => {
+            println!("BACKUP UNKNOWN - {:#}", e);
+            commands::check_health::PluginState::Unknown.exit_code()
+        }
+        Err(e) => {
+            say_error!("Error: {:?}", e);
+            commands::exit_code_of(&e)
+        }
+    };
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+fn run_cli(cli: Cli) -> Result<Outcome> {
+    let verbosity = if cli.quiet {
+        Verbosity::Quiet
+    } else if cli.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    output::init(verbosity, cli.no_color);
 
     // Commands that don't require a config file - use simple console logging
     match &cli.command {
         Some(Commands::SetupRestic) => {
             managers::logging::init_console_logging();
-            return handle_setup_restic();
+            return commands::restic::setup_restic();
         }
         Some(Commands::UpdateRestic) => {
             managers::logging::init_console_logging();
-            return handle_update_restic(cli.use_system_restic);
+            return commands::restic::update_restic(cli.use_system_restic);
         }
         Some(Commands::ResticVersion) => {
             managers::logging::init_console_logging();
-            return handle_restic_version(cli.use_system_restic);
+            return commands::restic::restic_version(cli.use_system_restic);
+        }
+        Some(Commands::Secret { action }) => {
+            managers::logging::init_console_logging();
+            return commands::secret::secret(action);
+        }
+        Some(Commands::Context { action }) => {
+            managers::logging::init_console_logging();
+            return commands::context::context(action);
+        }
+        Some(Commands::Explain { key }) => {
+            print!("{}", config::explain::explain(key)?);
+            return Ok(Outcome::Success);
+        }
+        Some(Commands::Report { schema: true, .. }) => {
+            return commands::report::schema();
         }
         _ => {
             // All other commands require config and full logging
         }
     }
 
+    let config_path = config::contexts::resolve_config_path(
+        cli.config.as_deref(),
+        cli.context.as_deref(),
+        &config::contexts::Contexts::default_path(),
+    )?;
+
+    if let Some(Commands::Init) = cli.command {
+        managers::logging::init_console_logging();
+        return commands::init::init(&config_path);
+    }
+
     // Load and validate configuration (needed for use_system_restic setting)
-    let config = config::load_config(&cli.config)?;
-    let resolved_services = config::resolve_all_services(&config)?;
+    let mut config = config::load_config(&config_path)?;
 
     // Setup logging with file rotation (must keep guard alive)
     let logging_config = managers::logging::LoggingConfig::from_config(
@@ -151,6 +487,10 @@ fn main() -> Result<()> {
     );
     let _log_guard = managers::logging::init_logging(&logging_config)?;
 
+    // Add services discovered from container labels (logged, so after logging setup)
+    config::discovery::discover(&mut config);
+    let resolved_services = config::resolve_all_services(&config)?;
+
     // Determine if we should use system restic (CLI arg overrides config)
     let use_system_restic = cli.use_system_restic || config.global.use_system_restic;
 
@@ -159,779 +499,179 @@ fn main() -> Result<()> {
 
     // Ensure restic is available (except for validate command)
     match cli.command {
-        Some(Commands::Validate) => {
-            // Skip restic check for validate
+        Some(Commands::Validate) | Some(Commands::Doctor { .. }) => {
+            // Skip restic check for validate, doctor reports it itself
         }
         _ => {
             // For all other commands, ensure restic is available
             if !utils::restic_installer::restic_exists(use_system_restic) {
+                if matches!(cli.command, Some(Commands::CheckHealth { .. })) {
+                    anyhow::bail!("restic binary not found");
+                }
                 if use_system_restic {
-                    eprintln!("⚠️  System restic not found in PATH!");
-                    eprintln!();
-                    eprintln!("You have use_system_restic enabled, but restic is not installed.");
-                    eprintln!("Either:");
-                    eprintln!("  1. Install restic system-wide, or");
-                    eprintln!("  2. Run: restic-manager setup-restic");
-                    eprintln!("     and set use_system_restic = false");
-                    eprintln!();
+                    say_error!("{} System restic not found in PATH!", output::fail());
+                    say_error!();
+                    say_error!("You have use_system_restic enabled, but restic is not installed.");
+                    say_error!("Either:");
+                    say_error!("  1. Install restic system-wide, or");
+                    say_error!("  2. Run: restic-manager setup-restic");
+                    say_error!("     and set use_system_restic = false");
+                    say_error!();
                 } else {
-                    eprintln!("⚠️  Restic binary not found!");
-                    eprintln!();
-                    eprintln!("Restic is required for backup operations.");
-                    eprintln!("Run the following command to download restic:");
-                    eprintln!();
-                    eprintln!("  restic-manager setup-restic");
-                    eprintln!();
-                    eprintln!("Or set use_system_restic = true in config to use system restic.");
-                    eprintln!();
+                    say_error!("{} Restic binary not found!", output::fail());
+                    say_error!();
+                    say_error!("Restic is required for backup operations.");
+                    say_error!("Run the following command to download restic:");
+                    say_error!();
+                    say_error!("  restic-manager setup-restic");
+                    say_error!();
+                    say_error!("Or set use_system_restic = true in config to use system restic.");
+                    say_error!();
                 }
-                std::process::exit(1);
+                return Ok(Outcome::Failed(1));
             }
         }
     }
 
     // Create backup manager
     let backup_manager = BackupManager::new(config.clone(), resolved_services.clone());
+    let ctx = CommandContext::new(config, resolved_services);
 
     // If no command specified, show status overview
-    let command = cli.command.unwrap_or(Commands::Status { service: None });
+    let command = cli.command.unwrap_or(Commands::Status { service: None, all: false });
 
     match command {
-        Commands::Run { service } => {
-            if let Some(service_name) = service {
-                println!("Running backup for service: {}", service_name);
-                backup_manager.backup_service(&service_name)?;
-                println!("✓ Backup completed successfully");
-            } else {
-                println!("Running backups for all enabled services...");
-                backup_manager.backup_all()?;
-                println!("✓ All backups completed successfully");
-            }
+        Commands::Run { service, dry_run: true, .. } => {
+            commands::run::dry_run(&backup_manager, &ctx.resolved, service.as_deref())
         }
 
-        Commands::Restore { service, snapshot, destination, target, path } => {
-            use dialoguer::{Confirm, Select};
-
-            // Get the service configuration
-            let service_config = resolved_services.get(&service).ok_or_else(|| {
-                anyhow::anyhow!("Service '{}' not found in configuration", service)
-            })?;
-
-            println!("=== Restore Service: {} ===\n", service);
-
-            // Determine which destination to use
-            let dest_name = if let Some(ref d) = destination {
-                if !service_config.targets.contains(d) {
-                    eprintln!("Error: Service '{}' does not use destination '{}'", service, d);
-                    eprintln!("Available destinations: {}", service_config.targets.join(", "));
-                    std::process::exit(1);
-                }
-                d.clone()
-            } else if service_config.targets.len() == 1 {
-                service_config.targets[0].clone()
-            } else {
-                // Interactive destination selection
-                println!("Multiple destinations available. Select one:");
-                let selection = Select::new()
-                    .items(&service_config.targets)
-                    .default(0)
-                    .interact()?;
-                service_config.targets[selection].clone()
+        Commands::Run { service, tag, scheduled, reason, dry_run: false, wait_lock } => {
+            let options = RunOptions {
+                trigger: if scheduled { RunTrigger::Scheduled } else { RunTrigger::Manual },
+                reason: reason.filter(|r| !r.trim().is_empty()),
+                tags: tag,
+                wait_lock,
+                ..Default::default()
             };
+            utils::shutdown::install()?;
+            commands::run::run(&backup_manager, service.as_deref(), &options)
+        }
 
-            let dest = config.destinations.get(&dest_name).ok_or_else(|| {
-                anyhow::anyhow!("Destination '{}' not found", dest_name)
-            })?;
-
-            println!("Using destination: {} ({})\n", dest_name, dest.url);
-
-            // Build repository URL and environment
-            let repo_url = utils::restic::build_repository_url(dest, &service, None);
-            let password_file = &config.global.restic_password_file;
-            let env = utils::restic::ResticEnv::new(password_file, &repo_url);
-
-            // Get snapshots
-            let snapshots = utils::restic::list_snapshots(&env, std::time::Duration::from_secs(60))?;
-
-            if snapshots.is_empty() {
-                eprintln!("No snapshots found for service '{}'", service);
-                std::process::exit(1);
-            }
-
-            // Determine which snapshot to restore
-            let snapshot_id = if let Some(ref snap_id) = snapshot {
-                // Verify snapshot exists
-                if !snapshots.iter().any(|s| s.id.starts_with(snap_id) || s.short_id == *snap_id) {
-                    eprintln!("Snapshot '{}' not found", snap_id);
-                    std::process::exit(1);
-                }
-                snap_id.clone()
-            } else {
-                // Interactive snapshot selection
-                println!("Available snapshots:");
-                let items: Vec<String> = snapshots.iter().map(|s| {
-                    let date = if let Some(date_part) = s.time.split('T').next() {
-                        let time_part = s.time.split('T').nth(1)
-                            .and_then(|t| t.split('.').next())
-                            .unwrap_or("");
-                        format!("{} {}", date_part, time_part)
-                    } else {
-                        s.time.clone()
-                    };
-                    format!("{} - {} ({})", s.short_id, date, s.hostname)
-                }).collect();
-
-                let selection = Select::new()
-                    .with_prompt("Select snapshot to restore")
-                    .items(&items)
-                    .default(items.len() - 1) // Default to most recent
-                    .interact()?;
-
-                snapshots[selection].short_id.clone()
+        Commands::Restore {
+            service,
+            snapshot,
+            at,
+            destination,
+            target,
+            path,
+            no_pre_restore_snapshot,
+            limit_download,
+            nice,
+            volume,
+            recreate_volume,
+            skip_space_check,
+            dry_run,
+            yes,
+            acknowledge_cold_storage,
+        } => {
+            let options = commands::restore::RestoreOptions {
+                service,
+                snapshot,
+                at,
+                destination,
+                target,
+                paths: path,
+                no_pre_restore_snapshot,
+                limit_download_kbps: limit_download,
+                nice,
+                volume,
+                recreate_volume,
+                skip_space_check,
+                dry_run,
+                yes,
+                acknowledge_cold_storage,
             };
+            commands::restore::restore(&ctx, &options)
+        }
 
-            println!("\nSelected snapshot: {}\n", snapshot_id);
-
-            // Show what will be restored
-            println!("Preview of snapshot contents:");
-            match utils::restic::list_snapshot_files(&env, &snapshot_id, std::time::Duration::from_secs(30)) {
-                Ok(files) => {
-                    let preview_count = 10;
-                    for file in files.iter().take(preview_count) {
-                        println!("  {}", file);
-                    }
-                    if files.len() > preview_count {
-                        println!("  ... and {} more files", files.len() - preview_count);
-                    }
-                    println!("\nTotal: {} items", files.len());
-                }
-                Err(e) => {
-                    eprintln!("Warning: Could not list snapshot contents: {}", e);
-                }
-            }
-
-            // Determine target directory
-            let target_dir = if let Some(ref t) = target {
-                Some(t.as_str())
-            } else {
-                println!("\nNo target directory specified.");
-                println!("Restore will overwrite original locations!");
-                None
+        Commands::Maintain { service, destination, max_unused, max_repack_size } => {
+            let options = utils::restic::PruneOptions {
+                max_unused,
+                max_repack_size,
             };
+            utils::shutdown::install()?;
+            commands::maintain::maintain(&backup_manager, &service, destination.as_deref(), &options)
+        }
 
-            if let Some(t) = target_dir {
-                println!("\nRestore target: {}", t);
-            } else {
-                println!("\nRestore target: Original locations (IN-PLACE)");
-            }
-
-            if !path.is_empty() {
-                println!("Restoring specific paths:");
-                for p in &path {
-                    println!("  {}", p);
-                }
-            }
+        Commands::Resume { service } => {
+            let tracker = managers::backoff::FailureTracker::new(managers::backoff::FailureTracker::default_path());
+            commands::resume::resume(&tracker, &ctx.resolved, service.as_deref())
+        }
 
-            println!();
+        Commands::Status { service, all } => commands::status::status(&ctx, service.as_deref(), all),
 
-            // Confirmation
-            let confirm = Confirm::new()
-                .with_prompt("Do you want to proceed with the restore?")
-                .default(false)
-                .interact()?;
+        Commands::List => commands::status::list(&ctx.resolved),
 
-            if !confirm {
-                println!("Restore cancelled.");
-                std::process::exit(0);
-            }
-
-            println!("\nStarting restore...\n");
-
-            // Perform restore with longer timeout (30 minutes)
-            let restore_timeout = std::time::Duration::from_secs(1800);
-
-            match utils::restic::restore_snapshot(
-                &env,
-                &snapshot_id,
-                target_dir,
-                &path,
-                restore_timeout,
-            ) {
-                Ok(()) => {
-                    println!("\n✓ Restore completed successfully!");
-                    if let Some(t) = target_dir {
-                        println!("Files restored to: {}", t);
-                    } else {
-                        println!("Files restored to original locations");
-                    }
-                }
-                Err(e) => {
-                    eprintln!("\n✗ Restore failed: {}", e);
-                    std::process::exit(1);
-                }
-            }
+        Commands::Snapshots { service, destination, tag, sizes } => {
+            commands::snapshots::snapshots(&ctx, &service, destination.as_deref(), &tag, sizes)
         }
 
-        Commands::Status { service } => {
-            if let Some(service_name) = service {
-                // Get the service configuration
-                let service_config = resolved_services.get(&service_name).ok_or_else(|| {
-                    anyhow::anyhow!("Service '{}' not found in configuration", service_name)
-                })?;
-
-                println!("=== Status for service: {} ===\n", service_name);
-                println!("Description: {}", service_config.description);
-                println!("Enabled: {}", if service_config.enabled { "Yes" } else { "No" });
-                println!("Schedule: {}", service_config.schedule);
-                println!("Timeout: {} seconds", service_config.timeout_seconds);
-                println!("Targets: {}", service_config.targets.join(", "));
-                println!();
-
-                // Show status for each destination
-                for target_name in &service_config.targets {
-                    let destination = config.destinations.get(target_name).ok_or_else(|| {
-                        anyhow::anyhow!("Destination '{}' not found", target_name)
-                    })?;
-
-                    println!("Destination: {}", target_name);
-                    println!("  Repository: {}", destination.url);
-
-                    // Build repository URL
-                    let repo_url = utils::restic::build_repository_url(destination, &service_name, None);
-                    let password_file = &config.global.restic_password_file;
-                    let env = utils::restic::ResticEnv::new(password_file, &repo_url);
-
-                    // Get snapshot count
-                    match utils::restic::count_snapshots(&env, std::time::Duration::from_secs(30)) {
-                        Ok(count) => {
-                            println!("  Snapshots: {}", count);
-
-                            if count > 0 {
-                                // Get latest snapshot
-                                if let Ok(Some(latest)) = utils::restic::get_latest_snapshot(&env, std::time::Duration::from_secs(30)) {
-                                    let date_str = if let Some(date_part) = latest.time.split('T').next() {
-                                        let time_part = latest.time.split('T').nth(1)
-                                            .and_then(|t| t.split('.').next())
-                                            .unwrap_or("");
-                                        format!("{} {}", date_part, time_part)
-                                    } else {
-                                        latest.time.clone()
-                                    };
-
-                                    println!("  Last Backup: {}", date_str);
-
-                                    // Calculate age and health
-                                    if let Ok(snapshot_time) = chrono::DateTime::parse_from_rfc3339(&latest.time) {
-                                        let now = chrono::Utc::now();
-                                        let age = now.signed_duration_since(snapshot_time);
-                                        let hours = age.num_hours();
-
-                                        println!("  Age: {} hours ago", hours);
-
-                                        // Health indicator based on age
-                                        let health = if hours < 24 {
-                                            "✓ Healthy (recent backup)"
-                                        } else if hours < 48 {
-                                            "⚠ Warning (backup is 1-2 days old)"
-                                        } else {
-                                            "✗ Critical (backup is over 2 days old)"
-                                        };
-                                        println!("  Health: {}", health);
-                                    }
-                                }
-
-                                // Get repository size
-                                if let Ok(size) = utils::restic::get_stats(&env, std::time::Duration::from_secs(30)) {
-                                    println!("  Repository Size: {}", size);
-                                }
-                            } else {
-                                println!("  Health: ✗ No backups found");
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("  ✗ Failed to get status: {}", e);
-                        }
-                    }
-
-                    println!();
-                }
-            } else {
-                println!("=== Backup Status Overview ===\n");
-                println!("Services configured: {}", resolved_services.len());
-                println!("Destinations: {}", config.destinations.len());
-                println!("\nServices:");
-                for (name, svc) in &resolved_services {
-                    let status = if svc.enabled { "enabled" } else { "disabled" };
-                    println!(
-                        "  {} - {} ({})",
-                        name,
-                        svc.description,
-                        status
-                    );
-                }
-            }
+        Commands::Stats { service, json } => {
+            commands::stats::stats(&ctx, service.as_deref(), json)
         }
 
-        Commands::List => {
-            println!("Configured services:");
-            for (name, svc) in &resolved_services {
-                println!("  {}", name);
-                println!("    Description: {}", svc.description);
-                println!("    Enabled: {}", svc.enabled);
-                println!("    Schedule: {}", svc.schedule);
-                println!("    Targets: {}", svc.targets.join(", "));
-                println!();
-            }
+        Commands::CheckDestinations { destination, json } => {
+            commands::check_destinations::check_destinations(&ctx, destination.as_deref(), json)
         }
 
-        Commands::Snapshots { service, destination } => {
-            // Get the service configuration
-            let service_config = resolved_services.get(&service).ok_or_else(|| {
-                anyhow::anyhow!("Service '{}' not found in configuration", service)
-            })?;
-
-            println!("=== Snapshots for service: {} ===\n", service);
-
-            // Filter targets if destination is specified
-            let targets: Vec<String> = if let Some(ref dest) = destination {
-                if service_config.targets.contains(dest) {
-                    vec![dest.clone()]
-                } else {
-                    eprintln!("Error: Service '{}' does not use destination '{}'", service, dest);
-                    eprintln!("Available destinations: {}", service_config.targets.join(", "));
-                    std::process::exit(1);
-                }
-            } else {
-                service_config.targets.clone()
-            };
-
-            // List snapshots for each destination
-            for target_name in &targets {
-                let destination = config.destinations.get(target_name).ok_or_else(|| {
-                    anyhow::anyhow!("Destination '{}' not found", target_name)
-                })?;
-
-                println!("Destination: {}", target_name);
-                println!("Repository: {}\n", destination.url);
-
-                // Build repository URL
-                let repo_url = utils::restic::build_repository_url(destination, &service, None);
-
-                // Get password file (destination-specific or global)
-                let password_file = destination.url.contains("sftp://")
-                    .then(|| config.global.restic_password_file.clone())
-                    .unwrap_or_else(|| config.global.restic_password_file.clone());
-
-                // Create restic environment
-                let env = utils::restic::ResticEnv::new(&password_file, &repo_url);
-
-                // List snapshots
-                match utils::restic::list_snapshots(&env, std::time::Duration::from_secs(60)) {
-                    Ok(snapshots) => {
-                        if snapshots.is_empty() {
-                            println!("  No snapshots found.\n");
-                        } else {
-                            // Print table header
-                            println!("  {:<10} {:<20} {:<15}", "ID", "Date", "Hostname");
-                            println!("  {}", "-".repeat(50));
-
-                            // Print snapshots
-                            for snapshot in &snapshots {
-                                // Parse and format the timestamp
-                                let date_str = if let Some(date_part) = snapshot.time.split('T').next() {
-                                    // Extract time part too
-                                    let time_part = snapshot.time.split('T').nth(1)
-                                        .and_then(|t| t.split('.').next())
-                                        .unwrap_or("");
-                                    format!("{} {}", date_part, time_part)
-                                } else {
-                                    snapshot.time.clone()
-                                };
-
-                                println!(
-                                    "  {:<10} {:<20} {:<15}",
-                                    &snapshot.short_id,
-                                    date_str,
-                                    &snapshot.hostname
-                                );
-                            }
-
-                            println!("\n  Total: {} snapshots", snapshots.len());
-
-                            // Get repository stats
-                            if let Ok(size) = utils::restic::get_stats(&env, std::time::Duration::from_secs(30)) {
-                                println!("  Repository size: {}", size);
-                            }
-
-                            println!();
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("  ✗ Failed to list snapshots: {}\n", e);
-                    }
-                }
-            }
+        Commands::CheckHealth { service, destination, warn, crit } => {
+            commands::check_health::check_health(&ctx, &service, destination.as_deref(), warn, crit)
         }
 
-        Commands::Verify { service, read_data } => {
-            println!("=== Verifying Repositories ===\n");
-
-            if read_data {
-                println!("⚠ Deep verification enabled (this will take longer)\n");
-            }
-
-            // Determine which services to verify
-            let services_to_verify: Vec<_> = if let Some(ref service_name) = service {
-                let service_config = resolved_services.get(service_name).ok_or_else(|| {
-                    anyhow::anyhow!("Service '{}' not found in configuration", service_name)
-                })?;
-                vec![(service_name.as_str(), service_config)]
-            } else {
-                resolved_services.iter().map(|(name, config)| (name.as_str(), config)).collect()
-            };
-
-            let mut total_checks = 0;
-            let mut passed_checks = 0;
-            let mut failed_checks = 0;
-
-            for (service_name, service_config) in services_to_verify {
-                if !service_config.enabled && service.is_none() {
-                    // Skip disabled services when verifying all
-                    continue;
-                }
-
-                println!("Service: {}", service_name);
-
-                for target_name in &service_config.targets {
-                    let destination = config.destinations.get(target_name).ok_or_else(|| {
-                        anyhow::anyhow!("Destination '{}' not found", target_name)
-                    })?;
-
-                    println!("  Destination: {} ({})", target_name, destination.url);
-
-                    // Build repository URL
-                    let repo_url = utils::restic::build_repository_url(destination, service_name, None);
-                    let password_file = &config.global.restic_password_file;
-                    let env = utils::restic::ResticEnv::new(password_file, &repo_url);
-
-                    total_checks += 1;
-
-                    // Timeout: 5 minutes for normal check, 30 minutes for deep check
-                    let timeout = if read_data {
-                        std::time::Duration::from_secs(1800)
-                    } else {
-                        std::time::Duration::from_secs(300)
-                    };
-
-                    match utils::restic::check_repository(&env, read_data, timeout) {
-                        Ok(output) => {
-                            // Check if output contains any errors
-                            if output.to_lowercase().contains("error") || output.to_lowercase().contains("fatal") {
-                                println!("    ✗ Check completed with warnings/errors");
-                                println!("    Output: {}", output);
-                                failed_checks += 1;
-                            } else {
-                                println!("    ✓ Repository structure is OK");
-                                println!("    ✓ No errors found");
-                                passed_checks += 1;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("    ✗ Check failed: {}", e);
-                            failed_checks += 1;
-                        }
-                    }
-
-                    println!();
-                }
-            }
-
-            // Summary
-            println!("=== Verification Summary ===");
-            println!("Total checks: {}", total_checks);
-            println!("Passed: {}", passed_checks);
-            println!("Failed: {}", failed_checks);
-
-            if failed_checks == 0 {
-                println!("\n✓ All checks passed!");
-            } else {
-                println!("\n✗ Some checks failed. Please review the errors above.");
-                std::process::exit(1);
-            }
+        Commands::Report { service, .. } => {
+            // --schema is handled before config loading, so a service is given
+            let service = service.context("--service is required")?;
+            commands::report::report(&ctx, &service)
         }
 
-        Commands::Setup { dry_run, cron_only, dirs_only } => {
-            println!("=== Setting up restic-manager ===\n");
-
-            if dry_run {
-                println!("DRY RUN MODE - No changes will be made\n");
-            }
-
-            let mut _total_steps = 0;
-            let mut _completed_steps = 0;
-
-            // Step 1: Create directories (unless cron-only)
-            if !cron_only {
-                _total_steps += 1;
-                println!("[1/4] Creating directories...");
-
-                // Create log directory
-                let log_dir = &config.global.log_directory;
-                if dry_run {
-                    println!("  [DRY RUN] Would create: {}", log_dir.display());
-                } else {
-                    match std::fs::create_dir_all(log_dir) {
-                        Ok(_) => {
-                            println!("  ✓ Created {}", log_dir.display());
-                            _completed_steps += 1;
-                        }
-                        Err(e) => eprintln!("  ✗ Failed to create {}: {}", log_dir.display(), e),
-                    }
-                }
-
-                // Create docker base directory if it doesn't exist
-                let docker_base = &config.global.docker_base;
-                if !docker_base.exists() {
-                    if dry_run {
-                        println!("  [DRY RUN] Would create: {}", docker_base.display());
-                    } else {
-                        match std::fs::create_dir_all(docker_base) {
-                            Ok(_) => println!("  ✓ Created {}", docker_base.display()),
-                            Err(e) => eprintln!("  ✗ Failed to create {}: {}", docker_base.display(), e),
-                        }
-                    }
-                } else {
-                    println!("  ✓ {} already exists", docker_base.display());
-                }
-
-                println!();
-            }
-
-            // Step 2: Initialize restic repositories (unless cron-only)
-            if !cron_only {
-                _total_steps += 1;
-                println!("[2/4] Initializing restic repositories...");
-
-                for (service_name, service_config) in &resolved_services {
-                    if !service_config.enabled {
-                        continue;
-                    }
-
-                    for target_name in &service_config.targets {
-                        let destination = match config.destinations.get(target_name) {
-                            Some(d) => d,
-                            None => {
-                                eprintln!("  ✗ Destination '{}' not found", target_name);
-                                continue;
-                            }
-                        };
-
-                        let repo_url = utils::restic::build_repository_url(destination, service_name, None);
-
-                        if dry_run {
-                            println!("  [DRY RUN] Would initialize: {} -> {}", service_name, repo_url);
-                        } else {
-                            let password_file = &config.global.restic_password_file;
-                            let env = utils::restic::ResticEnv::new(password_file, &repo_url);
-
-                            match utils::restic::init_repository(&env, std::time::Duration::from_secs(300)) {
-                                Ok(_) => {
-                                    println!("  ✓ Initialized {} at {} ({})", service_name, target_name, destination.url);
-                                    _completed_steps += 1;
-                                }
-                                Err(e) => eprintln!("  ✗ Failed to initialize {} at {}: {}", service_name, target_name, e),
-                            }
-                        }
-                    }
-                }
-
-                println!();
-            }
-
-            // Step 3: Install cron jobs (unless dirs-only)
-            if !dirs_only {
-                _total_steps += 1;
-                println!("[3/4] Installing cron jobs...");
-
-                #[cfg(unix)]
-                {
-                    let config_path = cli.config.clone();
-
-                    for (service_name, service_config) in &resolved_services {
-                        if !service_config.enabled {
-                            println!("  - Skipping {} (disabled)", service_name);
-                            continue;
-                        }
-
-                        // Validate cron schedule
-                        if !utils::cron::validate_cron_schedule(&service_config.schedule) {
-                            eprintln!("  ✗ Invalid cron schedule for {}: {}", service_name, service_config.schedule);
-                            continue;
-                        }
-
-                        match utils::cron::add_cron_job(
-                            service_name,
-                            &service_config.schedule,
-                            &config_path,
-                            dry_run,
-                        ) {
-                            Ok(_) => {
-                                println!("  ✓ Added job for '{}' ({})", service_name, service_config.schedule);
-                                _completed_steps += 1;
-                            }
-                            Err(e) => eprintln!("  ✗ Failed to add job for {}: {}", service_name, e),
-                        }
-                    }
-                }
-
-                #[cfg(windows)]
-                {
-                    eprintln!("  ✗ Cron job setup is not supported on Windows");
-                    eprintln!("    Use Task Scheduler instead");
-                }
-
-                println!();
-            }
-
-            // Step 4: Verify setup
-            _total_steps += 1;
-            println!("[4/4] Verifying setup...");
-
-            if !dirs_only {
-                #[cfg(unix)]
-                {
-                    if dry_run {
-                        println!("  [DRY RUN] Would verify cron jobs");
-                    } else {
-                        match utils::cron::list_cron_jobs() {
-                            Ok(jobs) => {
-                                if jobs.is_empty() {
-                                    eprintln!("  ⚠ No cron jobs found");
-                                } else {
-                                    println!("  ✓ {} cron job(s) installed", jobs.len());
-                                }
-                            }
-                            Err(e) => eprintln!("  ✗ Failed to list cron jobs: {}", e),
-                        }
-                    }
-                }
-            }
-
-            if !cron_only {
-                if config.global.log_directory.exists() {
-                    println!("  ✓ Log directory accessible");
-                } else {
-                    eprintln!("  ✗ Log directory not found");
-                }
+        Commands::Forecast { service } => commands::forecast::forecast(&backup_manager, &service),
 
-                if config.global.docker_base.exists() {
-                    println!("  ✓ Docker base directory accessible");
-                } else {
-                    eprintln!("  ✗ Docker base directory not found");
+        Commands::Verify { service, read_data, read_data_subset, repair_index } => {
+            if let Some(ref subset) = read_data_subset {
+                if !utils::restic::is_valid_read_data_subset(subset) {
+                    anyhow::bail!("Invalid --read-data-subset '{}': expected e.g. \"5%\", \"1/7\" or \"500M\"", subset);
                 }
             }
-
-            println!();
-
-            // Summary
-            if dry_run {
-                println!("=== Dry Run Complete ===");
-                println!("No changes were made. Run without --dry-run to apply changes.");
-            } else {
-                println!("=== Setup Complete ===");
-                println!();
-                println!("Next steps:");
-                println!("  1. View scheduled jobs: crontab -l");
-                println!("  2. Test a backup manually:");
-                println!("     restic-manager run --service <SERVICE_NAME>");
-                println!("  3. Check logs in: {}", config.global.log_directory.display());
-            }
+            let read_data = utils::restic::ReadData::from_flags(read_data, read_data_subset.as_deref());
+            commands::verify::verify(&ctx, service.as_deref(), &read_data, repair_index)
         }
 
-        Commands::Validate => {
-            println!("Configuration is valid!");
-            println!("Services: {}", resolved_services.len());
-            println!("Destinations: {}", config.destinations.len());
-            println!("Profiles: {}", config.profiles.len());
+        Commands::TestRestore { service, destination, volumes } => {
+            commands::test_restore::test_restore(&ctx, &service, destination.as_deref(), volumes)
         }
 
-        // SetupRestic, UpdateRestic, and ResticVersion are handled at the start of main()
-        Commands::SetupRestic | Commands::UpdateRestic | Commands::ResticVersion => {
-            unreachable!("These commands are handled before config loading")
+        Commands::Setup { dry_run, cron_only, dirs_only } => {
+            let options = commands::setup::SetupOptions { dry_run, cron_only, dirs_only };
+            commands::setup::setup(&ctx, &config_path, options)
         }
-    }
-
-    Ok(())
-}
-
-/// Handle setup-restic command (doesn't require config)
-fn handle_setup_restic() -> Result<()> {
-    println!("Setting up managed restic binary...");
-
-    if utils::restic_installer::local_restic_exists() {
-        println!("✓ Managed restic is already installed");
-        let version = utils::restic_installer::get_restic_version(false)?;
-        println!("  Version: {}", version);
-        println!("  Binary: {}", utils::restic_installer::get_restic_bin_path().display());
-    } else {
-        println!("Downloading restic from GitHub...");
-        utils::restic_installer::download_restic()?;
-        let version = utils::restic_installer::get_restic_version(false)?;
-        println!("✓ Restic installed successfully");
-        println!("  Version: {}", version);
-        println!("  Binary: {}", utils::restic_installer::get_restic_bin_path().display());
-        println!();
-        println!("To use this binary, ensure use_system_restic = false in your config (default).");
-    }
-
-    Ok(())
-}
 
-/// Handle update-restic command (doesn't require config)
-fn handle_update_restic(use_system_restic: bool) -> Result<()> {
-    println!("Updating restic...");
-
-    if !utils::restic_installer::restic_exists(use_system_restic) {
-        if use_system_restic {
-            println!("System restic not found in PATH.");
-        } else {
-            println!("Managed restic not found. Run 'restic-manager setup-restic' first.");
+        Commands::Raw { service, destination, yes, args } => {
+            commands::raw::raw(&ctx, &service, destination.as_deref(), yes, &args)
         }
-        std::process::exit(1);
-    }
 
-    let old_version = utils::restic_installer::get_restic_version(use_system_restic)?;
-    println!("Current version: {}", old_version);
+        Commands::Serve { listen } => commands::run::serve(&ctx, &config_path, listen),
 
-    utils::restic_installer::update_restic(use_system_restic)?;
+        Commands::Validate => commands::validate::validate(&ctx),
 
-    let new_version = utils::restic_installer::get_restic_version(use_system_restic)?;
-    println!("✓ Updated to: {}", new_version);
+        Commands::Doctor { fix } => commands::doctor::doctor(&ctx, use_system_restic, fix),
 
-    Ok(())
-}
+        Commands::Cache { action } => commands::cache::cache(&ctx, &action),
 
-/// Handle restic-version command (doesn't require config)
-fn handle_restic_version(use_system_restic: bool) -> Result<()> {
-    if !utils::restic_installer::restic_exists(use_system_restic) {
-        if use_system_restic {
-            println!("System restic not found in PATH.");
-        } else {
-            println!("Managed restic not found. Run 'restic-manager setup-restic' first.");
-        }
-        std::process::exit(1);
-    }
-
-    let version = utils::restic_installer::get_restic_version(use_system_restic)?;
-    println!("Restic version: {}", version);
-    println!("Binary location: {}", utils::restic_installer::get_restic_command(use_system_restic));
+        Commands::Key { action } => commands::key::key(&ctx, &action),
 
-    if use_system_restic {
-        println!("Source: System PATH (use_system_restic = true)");
-    } else {
-        println!("Source: Managed binary (use_system_restic = false)");
+        // SetupRestic, UpdateRestic, ResticVersion, and Secret are handled at the start of run_cli()
+        Commands::SetupRestic | Commands::UpdateRestic | Commands::ResticVersion | Commands::Secret { .. } | Commands::Init | Commands::Context { .. } | Commands::Explain { .. } => {
+            unreachable!("These commands are handled before config loading")
+        }
     }
-
-    Ok(())
 }
-
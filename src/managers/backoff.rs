@@ -0,0 +1,169 @@
+//! Backoff for services that keep failing
+//!
+//! A service failing every night would otherwise send the same failure
+//! notification every night. Once a service has failed
+//! `failure_backoff_after` runs in a row, per-run failure notifications are
+//! replaced by a summary sent at 1x, 2x, 4x, ... that many failures. With
+//! `pause_after_failures` the service is also skipped entirely until
+//! `restic-manager resume` is run or its configuration changes.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Consecutive failed runs of a service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureStreak {
+    /// Number of failed runs in a row
+    pub count: u32,
+    /// When the first failure of the streak happened
+    pub since: DateTime<Utc>,
+    pub last_error: String,
+    /// Runs are skipped until resumed
+    #[serde(default)]
+    pub paused: bool,
+    /// Hash of the service config when it was paused (a change resumes it)
+    #[serde(default)]
+    pub config_hash: Option<u64>,
+}
+
+impl FailureStreak {
+    /// Whether the service is paused for the given current config
+    pub fn is_paused(&self, config_hash: u64) -> bool {
+        self.paused && self.config_hash == Some(config_hash)
+    }
+}
+
+/// Whether the n-th failure in a row gets a summary notification
+///
+/// Summaries go out at `threshold`, `2 * threshold`, `4 * threshold`, ...
+pub fn is_escalation_point(count: u32, threshold: u32) -> bool {
+    threshold > 0 && count >= threshold && count.is_multiple_of(threshold) && (count / threshold).is_power_of_two()
+}
+
+/// Failure streaks of all services, persisted as JSON
+#[derive(Debug, Clone)]
+pub struct FailureTracker {
+    path: PathBuf,
+}
+
+impl FailureTracker {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default location (~/.local/share/restic-manager/failures.json)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("failures.json")
+        } else {
+            PathBuf::from("/tmp/restic-manager-failures.json")
+        }
+    }
+
+    /// Load all streaks (empty if the file doesn't exist)
+    pub fn load(&self) -> Result<HashMap<String, FailureStreak>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&self.path).context("Failed to read failure state")?;
+        serde_json::from_str(&content).context("Failed to parse failure state")
+    }
+
+    fn save(&self, streaks: &HashMap<String, FailureStreak>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(streaks).context("Failed to serialize failure state")?;
+        fs::write(&self.path, content).context("Failed to write failure state")
+    }
+
+    /// Current streak of a service
+    pub fn get(&self, service: &str) -> Result<Option<FailureStreak>> {
+        Ok(self.load()?.remove(service))
+    }
+
+    /// Record a failed run, pausing the service once `pause_after` is reached
+    /// (0 never pauses)
+    pub fn record_failure(
+        &self,
+        service: &str,
+        error: &str,
+        pause_after: u32,
+        config_hash: u64,
+    ) -> Result<FailureStreak> {
+        let mut streaks = self.load()?;
+        let streak = streaks.entry(service.to_string()).or_insert_with(|| FailureStreak {
+            count: 0,
+            since: Utc::now(),
+            last_error: String::new(),
+            paused: false,
+            config_hash: None,
+        });
+
+        streak.count += 1;
+        streak.last_error = error.to_string();
+        if pause_after > 0 && streak.count >= pause_after {
+            streak.paused = true;
+            streak.config_hash = Some(config_hash);
+        }
+
+        let streak = streak.clone();
+        self.save(&streaks)?;
+        Ok(streak)
+    }
+
+    /// Record a successful run, returning the streak it ended (if any)
+    pub fn record_success(&self, service: &str) -> Result<Option<FailureStreak>> {
+        self.resume(service)
+    }
+
+    /// Clear a service's streak (and pause), returning it
+    pub fn resume(&self, service: &str) -> Result<Option<FailureStreak>> {
+        let mut streaks = self.load()?;
+        let previous = streaks.remove(service);
+        if previous.is_some() {
+            self.save(&streaks)?;
+        }
+        Ok(previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_escalation_points() {
+        let points: Vec<u32> = (1..=24).filter(|&n| is_escalation_point(n, 3)).collect();
+        assert_eq!(points, vec![3, 6, 12, 24]);
+        assert!(!is_escalation_point(5, 0));
+    }
+
+    #[test]
+    fn test_failure_streak_and_pause() {
+        let temp_dir = TempDir::new().unwrap();
+        let tracker = FailureTracker::new(temp_dir.path().join("failures.json"));
+
+        let first = tracker.record_failure("app", "disk full", 2, 42).unwrap();
+        assert_eq!(first.count, 1);
+        assert!(!first.paused);
+
+        let second = tracker.record_failure("app", "still full", 2, 42).unwrap();
+        assert_eq!(second.count, 2);
+        assert_eq!(second.since, first.since);
+        assert!(second.is_paused(42));
+        // A changed config resumes the service
+        assert!(!second.is_paused(43));
+
+        assert_eq!(tracker.resume("app").unwrap().map(|s| s.count), Some(2));
+        assert!(tracker.get("app").unwrap().is_none());
+        assert!(tracker.record_success("app").unwrap().is_none());
+    }
+}
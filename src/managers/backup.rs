@@ -1,20 +1,269 @@
 //! Backup manager - orchestrates backup execution
 
-use crate::config::{Config, Destination, Hook, ResolvedServiceConfig};
-use crate::managers::notification::NotificationManager;
-use crate::utils::locker::BackupLock;
-use crate::utils::{docker, restic};
+use crate::config::{
+    CommandSource, Config, Destination, DockerBackend, FeatureFlags, Hook, PostBackupVerify, ResolvedServiceConfig,
+};
+use crate::error::BackupError;
+use crate::integrations::mqtt::{MqttPublisher, ServiceState};
+use crate::managers::backoff::{self, FailureStreak, FailureTracker};
+use crate::managers::chain::{self, ChainStore};
+use crate::managers::deferred::{DeferredPhase, DeferredStore};
+use crate::managers::healthchecks::{HealthchecksClient, PingSignal};
+use crate::managers::history::{self, RunRecord, StateStore};
+use crate::managers::manifest::{self, BackupManifest, ManifestStore};
+use crate::managers::maintenance::{self, MaintenanceReport};
+use crate::managers::notification::{format_duration, NotificationManager};
+use crate::managers::outcome::{self, BackupPhase, PhaseTimings, RunOutcome, RunTrigger};
+use crate::managers::prune_delay::PendingPruneStore;
+use crate::managers::verification;
+use crate::managers::run_logs::RunLogs;
+use crate::managers::run_report::{DestinationReport, RunReport};
+use crate::utils::locker::{self, BackupLock, BackupSlot, RepoLock};
+use crate::utils::docker_ops::{self, DockerOperations, PausedContainers};
+use crate::utils::scratch::{self, ScratchDir};
+use crate::utils::{compose, docker, humanize, ignore_file, restic, retry, shutdown};
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
-use tracing::{error, info, warn};
+use tracing::{error, info, info_span, warn};
+
+/// Options for a single backup run
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// What started the run (tagged on the snapshots)
+    pub trigger: RunTrigger,
+    /// Why the run was started (tagged on the snapshots and kept in the history)
+    pub reason: Option<String>,
+    /// Extra tags recorded on the snapshots
+    pub tags: Vec<String>,
+    /// Id shared by the notifications and history records of this run
+    /// (generated if not set)
+    pub run_id: Option<String>,
+    /// Defer optional phases of services over their `time_budget_minutes`
+    /// (set when backing up all services)
+    pub enforce_time_budget: bool,
+    /// How long to wait for the service lock (default: `wait_lock_minutes`)
+    pub wait_lock: Option<Duration>,
+}
+
+/// State of the backup to one destination
+#[derive(Debug, Default)]
+struct DestinationRun {
+    /// Retries of failed repository operations
+    retries: u32,
+    /// When the service runs over its time budget (if enforced)
+    budget_deadline: Option<Instant>,
+    /// Optional phases skipped because the budget was exceeded
+    deferred: Vec<DeferredPhase>,
+    /// Where the run's hook and restic output is kept
+    run_logs: RunLogs,
+    /// Time spent in each phase
+    phases: PhaseTimings,
+    /// Why retention failed, if it did without failing the backup
+    retention_error: Option<String>,
+    /// Patterns of the service's ignore files, collected once per run
+    ignore_patterns: Arc<OnceLock<Vec<String>>>,
+}
+
+impl DestinationRun {
+    fn over_budget(&self) -> bool {
+        self.budget_deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl RunOptions {
+    /// Tags of the run: the trigger, then the extra tags (the free-text
+    /// reason is only kept in the history)
+    pub fn snapshot_tags(&self) -> Vec<String> {
+        let mut tags = vec![self.trigger.tag().to_string()];
+        tags.extend(self.tags.iter().cloned());
+        tags
+    }
+}
+
+/// Tags of the service's snapshots: the configured ones, then those of the run
+fn snapshot_tags(service: &ResolvedServiceConfig, options: &RunOptions) -> Vec<String> {
+    let mut tags = service.tags.clone();
+    for tag in options.snapshot_tags() {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// What the next backup of a service would do, see
+/// [`BackupManager::forecast_service`]
+pub struct Forecast {
+    /// Docker volumes and their current size (archived or streamed only at
+    /// backup time, so not part of the dry runs)
+    pub volumes: Vec<(String, Result<u64>)>,
+    /// Dry run per destination
+    pub destinations: Vec<(String, Result<restic::DryRun>)>,
+}
+
+/// Generate a short run id (8 hex digits)
+pub fn new_run_id() -> String {
+    format!("{:08x}", rand::random::<u32>())
+}
+
+/// Docker volumes of a service
+fn service_volumes(service: &ResolvedServiceConfig) -> &[String] {
+    service.config.as_ref().map_or(&[], |c| c.volumes.as_slice())
+}
+
+/// The service with the named volumes and bind-mount sources of its
+/// `containers` added to its volumes and paths
+fn with_container_mounts(service: &ResolvedServiceConfig, docker_ops: &dyn DockerOperations) -> Result<ResolvedServiceConfig> {
+    let mut service = service.clone();
+    let Some(backup) = service.config.as_mut() else {
+        return Ok(service);
+    };
+
+    for name in &backup.containers {
+        let container = docker_ops.inspect_container(name, Duration::from_secs(30))?;
+        info!(
+            "Container '{}' mounts {} volume(s) and {} bind mount(s)",
+            name,
+            container.volumes.len(),
+            container.binds.len()
+        );
+        for volume in container.volumes {
+            if !backup.volumes.contains(&volume) {
+                backup.volumes.push(volume);
+            }
+        }
+        for bind in container.binds {
+            let bind = bind.display().to_string();
+            if !backup.paths.contains(&bind) {
+                backup.paths.push(bind);
+            }
+        }
+    }
+    Ok(service)
+}
+
+/// Environment every hook gets (`RM_PHASE` is added by `run_hook`)
+fn hook_env(service: &str, destination: &str, repo_url: &str) -> Vec<(String, String)> {
+    vec![
+        ("RM_SERVICE".to_string(), service.to_string()),
+        ("RM_DESTINATION".to_string(), destination.to_string()),
+        ("RM_REPO_URL".to_string(), repo_url.to_string()),
+    ]
+}
+
+/// File a pre-backup hook may write a list of paths to (`RM_FILES_FROM`),
+/// removed again when dropped
+struct GeneratedFileList {
+    path: PathBuf,
+}
+
+impl GeneratedFileList {
+    fn new(work_dir: &Path, service: &str, destination: &str) -> Result<Self> {
+        fs::create_dir_all(work_dir).context("Failed to create temporary directory")?;
+        let path = work_dir.join(format!("{}-{}.files-from", service, destination));
+        // A list left behind by a killed run must not be backed up again
+        if path.exists() {
+            fs::remove_file(&path).context("Failed to remove stale file list")?;
+        }
+        Ok(Self { path })
+    }
+
+    /// The list, if a hook wrote one
+    fn written(&self) -> Option<&Path> {
+        self.path.exists().then_some(self.path.as_path())
+    }
+}
+
+impl Drop for GeneratedFileList {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            if let Err(e) = fs::remove_file(&self.path) {
+                warn!("Failed to remove file list {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Fail before archiving a service's volumes if they likely don't fit into
+/// the work directory (the uncompressed size of the volumes is the estimate)
+///
+/// If sizes or free space can't be determined, the check is skipped.
+fn check_work_dir_space(service: &ResolvedServiceConfig, docker_ops: &dyn DockerOperations, dir: &Path) -> Result<()> {
+    let timeout = Duration::from_secs(60);
+    let mut needed = 0;
+    for volume in service_volumes(service) {
+        match docker_ops.get_volume_size(volume, timeout) {
+            Ok(size) => needed += size,
+            Err(e) => {
+                warn!("Failed to measure volume '{}', skipping the work directory space check: {:#}", volume, e);
+                return Ok(());
+            }
+        }
+    }
+
+    let available = match crate::utils::command::free_space(dir) {
+        Ok(available) => available,
+        Err(e) => {
+            warn!("Failed to check the free space of {}: {:#}", dir.display(), e);
+            return Ok(());
+        }
+    };
+    ensure_fits(needed, available, dir)
+}
+
+fn ensure_fits(needed: u64, available: u64, dir: &Path) -> Result<()> {
+    if needed > available {
+        anyhow::bail!(
+            "Volume archives need up to {} but only {} is free in {} (set work_dir to a larger disk)",
+            humanize::format_bytes(needed),
+            humanize::format_bytes(available),
+            dir.display()
+        );
+    }
+    info!("Work directory: {} needed at most, {} free", humanize::format_bytes(needed), humanize::format_bytes(available));
+    Ok(())
+}
+
+/// Environment for on-success/on-failure hooks
+///
+/// `RM_STAGE` is the phase that failed (`pre_hooks`, `volumes`, `backup`,
+/// `retention`, `post_hooks` or `other`).
+fn result_hook_env(
+    base: &[(String, String)],
+    outcome: RunOutcome,
+    snapshot_id: Option<&str>,
+    error: Option<&anyhow::Error>,
+) -> Vec<(String, String)> {
+    let mut env = base.to_vec();
+    env.push(("RM_OUTCOME".to_string(), outcome.to_string()));
+    if let Some(id) = snapshot_id {
+        env.push(("RM_SNAPSHOT_ID".to_string(), id.to_string()));
+    }
+    if let Some(e) = error {
+        let stage = BackupPhase::of(e).map(|p| p.key()).unwrap_or("other");
+        env.push(("RM_STAGE".to_string(), stage.to_string()));
+        env.push(("RM_ERROR".to_string(), format!("{:#}", e)));
+    }
+    env
+}
 
 pub struct BackupManager {
     config: Config,
     resolved_services: HashMap<String, ResolvedServiceConfig>,
     notification_manager: Option<NotificationManager>,
+    healthchecks: Option<HealthchecksClient>,
+    mqtt: Option<MqttPublisher>,
+    history: Box<dyn StateStore>,
+    manifests: ManifestStore,
+    failures: FailureTracker,
+    chain: ChainStore,
+    deferred: DeferredStore,
+    pending_prunes: PendingPruneStore,
 }
 
 impl BackupManager {
@@ -23,17 +272,34 @@ impl BackupManager {
         config: Config,
         resolved_services: HashMap<String, ResolvedServiceConfig>,
     ) -> Self {
-        // Create notification manager if webhook URL is configured
-        let notification_manager = if !config.notifications.discord_webhook_url.is_empty() {
-            Some(NotificationManager::new(config.notifications.clone()))
+        // Create notification manager if any channel is configured
+        let notification_manager = if config.notifications.has_channels() {
+            let manager = NotificationManager::new(config.notifications.clone());
+            Some(if config.global.keep_run_logs > 0 {
+                manager.with_run_logs(RunLogs::root(&config.global.log_directory))
+            } else {
+                manager
+            })
         } else {
             None
         };
 
+        let healthchecks = Self::healthchecks_client(&config);
+        let mqtt = config.integrations.mqtt.clone().map(MqttPublisher::new);
+        let history = history::open_store(&config.global);
+
         Self {
             config,
             resolved_services,
             notification_manager,
+            healthchecks,
+            mqtt,
+            history,
+            manifests: ManifestStore::new(ManifestStore::default_dir()),
+            failures: FailureTracker::new(FailureTracker::default_path()),
+            chain: ChainStore::new(ChainStore::default_path()),
+            deferred: DeferredStore::new(DeferredStore::default_path()),
+            pending_prunes: PendingPruneStore::new(PendingPruneStore::default_path()),
         }
     }
 
@@ -44,66 +310,280 @@ impl BackupManager {
         resolved_services: HashMap<String, ResolvedServiceConfig>,
         notification_manager: NotificationManager,
     ) -> Self {
+        let healthchecks = Self::healthchecks_client(&config);
+        let mqtt = config.integrations.mqtt.clone().map(MqttPublisher::new);
+        let history = history::open_store(&config.global);
+
         Self {
             config,
             resolved_services,
             notification_manager: Some(notification_manager),
+            healthchecks,
+            mqtt,
+            history,
+            manifests: ManifestStore::new(ManifestStore::default_dir()),
+            failures: FailureTracker::new(FailureTracker::default_path()),
+            chain: ChainStore::new(ChainStore::default_path()),
+            deferred: DeferredStore::new(DeferredStore::default_path()),
+            pending_prunes: PendingPruneStore::new(PendingPruneStore::default_path()),
+        }
+    }
+
+    /// Experimental subsystems enabled in the config
+    pub fn features(&self) -> &FeatureFlags {
+        &self.config.experimental
+    }
+
+    fn healthchecks_client(config: &Config) -> Option<HealthchecksClient> {
+        let healthchecks = &config.notifications.healthchecks;
+        if healthchecks.checks.is_empty() {
+            None
+        } else {
+            Some(HealthchecksClient::new(healthchecks.clone()))
+        }
+    }
+
+    /// Send a healthchecks.io ping (if the service has a check)
+    fn ping(&self, service: &str, signal: PingSignal, body: &str) {
+        if let Some(ref healthchecks) = self.healthchecks {
+            healthchecks.ping(service, signal, body);
+        }
+    }
+
+    /// Use a specific history store (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_history(mut self, history: Box<dyn StateStore>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Use a specific manifest store (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_manifest_store(mut self, manifests: ManifestStore) -> Self {
+        self.manifests = manifests;
+        self
+    }
+
+    /// Use a specific failure tracker (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_failure_tracker(mut self, failures: FailureTracker) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    /// Use a specific snapshot chain store (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_chain_store(mut self, chain: ChainStore) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Use a specific deferred work store (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_deferred_store(mut self, deferred: DeferredStore) -> Self {
+        self.deferred = deferred;
+        self
+    }
+
+    /// Use a specific pending prune store (instead of the default location)
+    #[allow(dead_code)]
+    pub fn with_pending_prune_store(mut self, pending_prunes: PendingPruneStore) -> Self {
+        self.pending_prunes = pending_prunes;
+        self
+    }
+
+    /// Hash of a service's raw config; a paused service resumes when it changes
+    fn service_config_hash(&self, service_name: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.config
+            .services
+            .get(service_name)
+            .and_then(|service| toml::to_string(service).ok())
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Update the failure streak after a run, sending a summary when due
+    fn track_failures(&self, service_name: &str, run_id: &str, errors: &[String], config_hash: u64) {
+        if errors.is_empty() {
+            match self.failures.record_success(service_name) {
+                Ok(Some(streak)) => info!(
+                    "Service '{}' recovered after {} failed run(s)",
+                    service_name, streak.count
+                ),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to update failure state: {}", e),
+            }
+            return;
+        }
+
+        let global = &self.config.global;
+        let streak = match self.failures.record_failure(
+            service_name,
+            &errors.join(", "),
+            global.pause_after_failures,
+            config_hash,
+        ) {
+            Ok(streak) => streak,
+            Err(e) => {
+                warn!("Failed to update failure state: {}", e);
+                return;
+            }
+        };
+
+        let just_paused = streak.paused && streak.count == global.pause_after_failures;
+        if just_paused || backoff::is_escalation_point(streak.count, global.failure_backoff_after) {
+            self.notify_failure_summary(service_name, run_id, &streak);
+        }
+    }
+
+    /// Send a summary for a repeatedly failing service (if manager is configured)
+    fn notify_failure_summary(&self, service: &str, run_id: &str, streak: &FailureStreak) {
+        if let Some(ref manager) = self.notification_manager {
+            if let Err(e) = manager.send_failure_summary(service, Some(run_id), streak) {
+                warn!("Failed to send failure summary notification: {}", e);
+            }
+        }
+    }
+
+    /// Append a run record to the history (failures are only logged)
+    fn record_run(&self, record: RunRecord) {
+        if let Err(e) = self.history.append(&record) {
+            warn!("Failed to record backup history: {}", e);
         }
     }
 
     /// Send a notification (if manager is configured)
-    fn notify_failure(&self, service: &str, destination: Option<&str>, error: &str, duration_secs: u64) {
+    fn notify_failure(&self, service: &str, run_id: &str, destination: Option<&str>, error: &str, duration_secs: u64) {
         if let Some(ref manager) = self.notification_manager {
-            if let Err(e) = manager.send_failure(service, destination, error, Some(duration_secs)) {
+            if let Err(e) = manager.send_failure(service, Some(run_id), destination, error, Some(duration_secs)) {
                 warn!("Failed to send failure notification: {}", e);
             }
         }
     }
 
+    /// Send a timeout notification with a hint about the phase (if manager is configured)
+    fn notify_timeout(&self, service: &ResolvedServiceConfig, run_id: &str, destination: Option<&str>, error: &anyhow::Error, duration_secs: u64) {
+        if let Some(ref manager) = self.notification_manager {
+            let hint = outcome::timeout_hint(BackupPhase::of(error), Duration::from_secs(service.timeout_seconds));
+            let error_msg = format!("{:#}", error);
+            if let Err(e) = manager.send_timeout(&service.name, Some(run_id), destination, &error_msg, &hint, Some(duration_secs)) {
+                warn!("Failed to send timeout notification: {}", e);
+            }
+        }
+    }
+
+    /// Send a cancellation notification (if manager is configured)
+    fn notify_cancelled(&self, service: &str, run_id: &str, destination: Option<&str>, duration_secs: u64) {
+        if let Some(ref manager) = self.notification_manager {
+            if let Err(e) = manager.send_cancelled(service, Some(run_id), destination, Some(duration_secs)) {
+                warn!("Failed to send cancellation notification: {}", e);
+            }
+        }
+    }
+
     /// Send a success notification (if manager is configured)
-    fn notify_success(&self, service: &str, destination: Option<&str>, duration_secs: u64) {
+    fn notify_success(&self, service: &str, run_id: &str, destination: Option<&str>, duration_secs: u64, phases: Option<&str>) {
         if let Some(ref manager) = self.notification_manager {
-            if let Err(e) = manager.send_success(service, destination, duration_secs) {
+            if let Err(e) = manager.send_success(service, Some(run_id), destination, duration_secs, phases) {
                 warn!("Failed to send success notification: {}", e);
             }
         }
     }
 
     /// Send a long-running notification (if manager is configured)
-    fn notify_long_running(&self, service: &str, destination: Option<&str>, duration_secs: u64) {
+    fn notify_long_running(&self, service: &str, run_id: &str, destination: Option<&str>, duration_secs: u64) {
         if let Some(ref manager) = self.notification_manager {
             let threshold = self.config.global.long_running_threshold_minutes;
-            if let Err(e) = manager.send_long_running(service, destination, duration_secs, threshold) {
+            if let Err(e) = manager.send_long_running(service, Some(run_id), destination, duration_secs, threshold) {
                 warn!("Failed to send long-running notification: {}", e);
             }
         }
     }
 
-    /// Run backup for a specific service
-    pub fn backup_service(&self, service_name: &str) -> Result<()> {
+    /// Run backup for a specific service with extra run options
+    pub fn backup_service_with(&self, service_name: &str, options: &RunOptions) -> Result<()> {
         let service = self
             .resolved_services
             .get(service_name)
             .context(format!("Service not found: {}", service_name))?;
 
         if !service.enabled {
-            info!("Service '{}' is disabled, skipping", service_name);
+            match service.disabled_until {
+                Some(until) => info!("Service '{}' is disabled until {}, skipping", service_name, until),
+                None => info!("Service '{}' is disabled, skipping", service_name),
+            }
             return Ok(());
         }
 
-        // Acquire lock to prevent concurrent backups
-        let _lock = BackupLock::acquire(service_name)
-            .context(format!("Failed to acquire lock for service '{}'", service_name))?;
+        // Services that keep failing can be paused until resumed or reconfigured
+        let config_hash = self.service_config_hash(service_name);
+        let streak = self.failures.get(service_name).unwrap_or_else(|e| {
+            warn!("Failed to read failure state: {}", e);
+            None
+        });
+        if let Some(ref streak) = streak {
+            if streak.is_paused(config_hash) {
+                anyhow::bail!(
+                    "Service '{}' is paused after {} failed runs in a row (last error: {}). Run `restic-manager resume --service {}` to resume",
+                    service_name,
+                    streak.count,
+                    streak.last_error,
+                    service_name
+                );
+            }
+        }
+
+        // Once in backoff, per-run failure notifications are replaced by summaries
+        let backoff_after = self.config.global.failure_backoff_after;
+        let backing_off = backoff_after > 0 && streak.map_or(0, |s| s.count) + 1 >= backoff_after;
+
+        let run_id = options.run_id.clone().unwrap_or_else(new_run_id);
+
+        // Acquire lock to prevent concurrent backups (a service another
+        // process is handling fails with `AlreadyRunning` and is skipped)
+        let wait_lock = options
+            .wait_lock
+            .unwrap_or(Duration::from_secs(self.config.global.wait_lock_minutes * 60));
+        let _lock = BackupLock::acquire_waiting(service_name, Some(&run_id), wait_lock)?;
+        let _slot = BackupSlot::acquire(
+            self.config.global.max_concurrent_backups,
+            Some(&run_id),
+            Duration::from_secs(service.timeout_seconds),
+        )?;
+        let run_logs = RunLogs::create(&self.config.global.log_directory, &run_id, self.config.global.keep_run_logs);
 
         let start_time = Instant::now();
         let long_running_threshold_secs = self.config.global.long_running_threshold_minutes * 60;
         let mut long_running_notified = false;
 
-        info!("Starting backup for service: {}", service_name);
+        let _run_span = info_span!("backup", service = service_name, run_id = %run_id).entered();
+        info!("Starting {} backup for service: {} (run {})", options.trigger, service_name, run_id);
+        if let Some(ref reason) = options.reason {
+            info!("Reason: {}", reason);
+        }
+        self.ping(service_name, PingSignal::Start, "");
 
         // Backup to each target
         let mut errors = Vec::new();
+        let mut last_error = None;
         let mut success_count = 0;
+        let mut files_processed = None;
+        let mut report = Vec::new();
+        let mut total_retries = 0;
+        let mut deferred_destinations = Vec::new();
+        let budget_deadline = service
+            .time_budget_minutes
+            .filter(|_| options.enforce_time_budget)
+            .map(|minutes| start_time + Duration::from_secs(minutes * 60));
+        let mut data_added = 0;
+        let mut failure_outcome = None;
+        let mut phases = PhaseTimings::default();
+        let ignore_patterns = Arc::new(OnceLock::new());
+        let mut run_report = RunReport::new(&run_id, service_name, chrono::Utc::now(), &snapshot_tags(service, options));
+        run_report.trigger = options.trigger;
+        run_report.reason = options.reason.clone();
 
         for target_name in &service.targets {
             let destination = self
@@ -120,40 +600,221 @@ impl BackupManager {
             // Check for long-running and notify once
             let elapsed = start_time.elapsed().as_secs();
             if !long_running_notified && elapsed > long_running_threshold_secs {
-                self.notify_long_running(service_name, Some(target_name), elapsed);
+                self.notify_long_running(service_name, &run_id, Some(target_name), elapsed);
                 long_running_notified = true;
             }
 
-            match self.backup_to_destination(service, destination) {
-                Ok(_) => {
+            let _destination_span = info_span!("destination", name = %target_name).entered();
+            let destination_started_at = chrono::Utc::now();
+            let destination_start = Instant::now();
+            let mut run = DestinationRun {
+                budget_deadline,
+                run_logs: run_logs.clone(),
+                ignore_patterns: Arc::clone(&ignore_patterns),
+                ..Default::default()
+            };
+
+            let repo_url = restic::build_repository_url(destination, service_name, None);
+            let hook_env = hook_env(service_name, target_name, &repo_url);
+
+            let chain_note = self.check_chain(service, &run_id, target_name, destination, &repo_url);
+            if let Some(ref note) = chain_note {
+                report.push(format!("{}: {}", target_name, note));
+            }
+
+            // Services sharing the repository take turns
+            let repo_wait = Duration::from_secs(service.timeout_seconds);
+            let result = match RepoLock::acquire(&repo_url, Some(&run_id), repo_wait) {
+                Ok(_repo_lock) => self.backup_to_destination(service, target_name, destination, options, &mut run),
+                Err(e) => Err(e),
+            };
+            total_retries += run.retries;
+            phases.merge(&run.phases);
+            let phases_note = (!run.phases.is_empty())
+                .then(|| format!("{}: time per phase: {}", target_name, run.phases.summary(destination_start.elapsed())));
+            let retry_note = match run.retries {
+                0 => String::new(),
+                1 => " (after 1 retry)".to_string(),
+                n => format!(" (after {} retries)", n),
+            };
+
+            match result {
+                Ok(summary) => {
                     info!(
                         "Successfully backed up '{}' to '{}'",
                         service_name, target_name
                     );
+                    run_logs.restic(
+                        service_name,
+                        target_name,
+                        "Backup summary",
+                        &serde_json::to_string_pretty(&summary).unwrap_or_default(),
+                    );
                     success_count += 1;
+                    data_added += summary.data_added;
+                    files_processed.get_or_insert(summary.total_files_processed);
+
+                    if let Some(ref snapshot_id) = summary.snapshot_id {
+                        if let Err(e) = self.chain.record(service_name, target_name, snapshot_id) {
+                            warn!("Failed to record snapshot chain: {}", e);
+                        }
+                    }
+
+                    let verification = if run.over_budget() && service.post_backup_verify != PostBackupVerify::Off {
+                        run.deferred.push(DeferredPhase::Check);
+                        None
+                    } else {
+                        summary
+                            .snapshot_id
+                            .as_deref()
+                            .and_then(|id| self.verify_snapshot(service, &run_id, target_name, destination, id))
+                    };
+                    let retention_failure = run
+                        .retention_error
+                        .as_deref()
+                        .map(|error| self.notify_retention_failed(service_name, &run_id, target_name, error));
+                    let verify_note = verification.as_ref().map(|note| format!(", {}", note)).unwrap_or_default();
+                    let retention_note = retention_failure.as_ref().map(|note| format!(", {}", note)).unwrap_or_default();
+                    let deferred_note = self.defer_phases(service_name, &run_id, target_name, &run.deferred);
+                    if !run.deferred.is_empty() {
+                        deferred_destinations.push(target_name.as_str());
+                    }
+
+                    report.push(format!(
+                        "{}: ok, snapshot {}, {} files, {} added{}{}{}{}",
+                        target_name,
+                        summary.snapshot_id.as_deref().unwrap_or("-"),
+                        summary.total_files_processed,
+                        restic::format_bytes(summary.data_added),
+                        retry_note,
+                        verify_note,
+                        retention_note,
+                        deferred_note
+                    ));
+                    report.extend(phases_note);
+
+                    let mut destination_report =
+                        DestinationReport::new(target_name, RunOutcome::Success, destination_start.elapsed().as_secs(), run.retries)
+                            .with_phases(&run.phases);
+                    destination_report.snapshot_id = summary.snapshot_id.clone();
+                    destination_report.files_processed = Some(summary.total_files_processed);
+                    destination_report.data_added = Some(summary.data_added);
+                    destination_report.retention_error = run.retention_error.clone();
+                    destination_report.notes = chain_note
+                        .into_iter()
+                        .chain(verification)
+                        .chain(retention_failure)
+                        .chain((!deferred_note.is_empty()).then(|| deferred_note.trim_start_matches(", ").to_string()))
+                        .collect();
+                    run_report.push(destination_report);
+
+                    self.run_result_hooks(
+                        service,
+                        true,
+                        &result_hook_env(&hook_env, RunOutcome::Success, summary.snapshot_id.as_deref(), None),
+                        &run_logs,
+                    );
+
+                    self.record_run(RunRecord {
+                        service: service_name.to_string(),
+                        destination: target_name.clone(),
+                        started_at: destination_started_at,
+                        duration_secs: destination_start.elapsed().as_secs(),
+                        success: true,
+                        outcome: Some(RunOutcome::Success),
+                        data_added: Some(summary.data_added),
+                        snapshot_id: summary.snapshot_id,
+                        error: None,
+                        run_id: Some(run_id.clone()),
+                        log_dir: run_logs.dir().map(Path::to_path_buf),
+                        trigger: Some(options.trigger),
+                        reason: options.reason.clone(),
+                        retention_error: run.retention_error.clone(),
+                    });
                 }
                 Err(e) => {
                     let error_msg = format!("{}", e);
+                    let run_outcome = RunOutcome::from_error(&e);
+                    run_logs.restic(service_name, target_name, &format!("Backup {}", run_outcome), &format!("{:#}", e));
+
+                    self.record_run(RunRecord {
+                        service: service_name.to_string(),
+                        destination: target_name.clone(),
+                        started_at: destination_started_at,
+                        duration_secs: destination_start.elapsed().as_secs(),
+                        success: false,
+                        outcome: Some(run_outcome),
+                        data_added: None,
+                        snapshot_id: None,
+                        error: Some(error_msg.clone()),
+                        run_id: Some(run_id.clone()),
+                        log_dir: run_logs.dir().map(Path::to_path_buf),
+                        trigger: Some(options.trigger),
+                        reason: options.reason.clone(),
+                        retention_error: None,
+                    });
                     error!(
-                        "Failed to backup '{}' to '{}': {}",
-                        service_name, target_name, error_msg
+                        "Failed to backup '{}' to '{}' ({}): {:#}",
+                        service_name, target_name, run_outcome, e
                     );
                     errors.push(format!("{}: {}", target_name, e));
-
-                    // Send failure notification for this destination
-                    self.notify_failure(
-                        service_name,
-                        Some(target_name),
-                        &error_msg,
-                        start_time.elapsed().as_secs(),
+                    failure_outcome = Some(run_outcome);
+                    report.push(format!("{}: {}{} - {:#}", target_name, run_outcome, retry_note, e));
+                    report.extend(phases_note);
+
+                    let mut destination_report =
+                        DestinationReport::new(target_name, run_outcome, destination_start.elapsed().as_secs(), run.retries)
+                            .with_phases(&run.phases);
+                    destination_report.error = Some(format!("{:#}", e));
+                    destination_report.notes = chain_note.into_iter().collect();
+                    run_report.push(destination_report);
+
+                    self.run_result_hooks(
+                        service,
+                        false,
+                        &result_hook_env(&hook_env, run_outcome, None, Some(&e)),
+                        &run_logs,
                     );
 
-                    // Try to unlock repository on failure
-                    let repo_url = restic::build_repository_url(destination, service_name, None);
-                    let env = restic::ResticEnv::new(&self.config.global.restic_password_file, &repo_url);
-                    if let Err(unlock_err) = restic::unlock_repository(&env, Duration::from_secs(30)) {
-                        warn!("Failed to unlock repository after error: {}", unlock_err);
+                    // Send notification for this destination, depending on what went wrong
+                    if backing_off {
+                        info!("Failure notification for '{}' suppressed (failure backoff)", service_name);
+                    } else {
+                        match run_outcome {
+                            RunOutcome::Timeout => self.notify_timeout(
+                                service,
+                                &run_id,
+                                Some(target_name),
+                                &e,
+                                start_time.elapsed().as_secs(),
+                            ),
+                            RunOutcome::Cancelled => self.notify_cancelled(
+                                service_name,
+                                &run_id,
+                                Some(target_name),
+                                start_time.elapsed().as_secs(),
+                            ),
+                            _ => self.notify_failure(
+                                service_name,
+                                &run_id,
+                                Some(target_name),
+                                &error_msg,
+                                start_time.elapsed().as_secs(),
+                            ),
+                        }
                     }
+
+                    // Try to unlock repository on failure (a cancelled restic
+                    // removed its own lock, and no new command would run)
+                    if run_outcome != RunOutcome::Cancelled {
+                        let unlock_result =
+                            restic::ResticEnv::for_destination(&self.config.global, target_name, destination, &repo_url)
+                                .and_then(|env| restic::unlock_repository(&env, Duration::from_secs(30)));
+                        if let Err(unlock_err) = unlock_result {
+                            warn!("Failed to unlock repository after error: {}", unlock_err);
+                        }
+                    }
+                    last_error = Some(e);
                 }
             }
         }
@@ -162,52 +823,387 @@ impl BackupManager {
         let duration_secs = duration.as_secs();
 
         info!(
-            "Backup for service '{}' completed in {:.2}s",
+            "Backup for service '{}' completed in {:.2}s ({} retries)",
             service_name,
-            duration.as_secs_f64()
+            duration.as_secs_f64(),
+            total_retries
         );
 
+        if !deferred_destinations.is_empty() {
+            self.notify_over_budget(service, &run_id, &deferred_destinations, duration_secs);
+        }
+
+        // Compare the backup scope with the previous run
+        if success_count > 0 {
+            self.check_manifest_drift(service, &run_id, files_processed, ignore_patterns.get().map(Vec::as_slice));
+        }
+
         // Send success notification if all destinations succeeded
         if errors.is_empty() && success_count > 0 {
-            self.notify_success(service_name, None, duration_secs);
+            let phases = (!phases.is_empty()).then(|| phases.summary(duration));
+            self.notify_success(service_name, &run_id, None, duration_secs, phases.as_deref());
         }
 
-        if !errors.is_empty() {
-            anyhow::bail!(
-                "Backup failed for {} destination(s): {}",
-                errors.len(),
-                errors.join(", ")
+        self.track_failures(service_name, &run_id, &errors, config_hash);
+
+        let signal = if errors.is_empty() { PingSignal::Success } else { PingSignal::Fail };
+        report.insert(
+            0,
+            format!(
+                "Backup of '{}': {}/{} destination(s) succeeded in {}{}",
+                service_name,
+                success_count,
+                service.targets.len(),
+                format_duration(duration_secs),
+                if total_retries > 0 { format!(", {} retries", total_retries) } else { String::new() }
+            ),
+        );
+        let report = report.join("\n");
+        run_logs.report(service_name, &report);
+        run_report.duration_secs = duration_secs;
+        run_logs.report_json(service_name, &run_report);
+        self.ping(service_name, signal, &report);
+
+        if let Some(ref mqtt) = self.mqtt {
+            mqtt.publish_state(
+                service_name,
+                &ServiceState {
+                    status: failure_outcome.unwrap_or(RunOutcome::Success).to_string(),
+                    last_run: chrono::Utc::now(),
+                    duration: duration_secs,
+                    size: data_added,
+                    destinations_ok: success_count,
+                    destinations_total: service.targets.len(),
+                },
             );
         }
 
+        if let Some(error) = last_error {
+            let summary = format!("Backup failed for {} destination(s): {}", errors.len(), errors.join(", "));
+            if success_count > 0 {
+                return Err(BackupError::PartialFailure { summary }.into());
+            }
+            // Every destination failed: keep the failure's type (e.g. for exit codes)
+            return Err(error.context(summary));
+        }
+
         Ok(())
     }
 
+    /// Run repository maintenance for a service (all destinations, or one)
+    ///
+    /// Holds the service's backup lock so maintenance never overlaps a backup,
+    /// and sends one notification covering every destination.
+    pub fn maintain_service(
+        &self,
+        service_name: &str,
+        destination: Option<&str>,
+        options: &restic::PruneOptions,
+    ) -> Result<Vec<MaintenanceReport>> {
+        let service = self
+            .resolved_services
+            .get(service_name)
+            .context(format!("Service not found: {}", service_name))?;
+
+        let targets: Vec<&String> = match destination {
+            Some(name) => {
+                let target = service.targets.iter().find(|t| *t == name);
+                vec![target.context(format!(
+                    "Service '{}' does not use destination '{}'",
+                    service_name, name
+                ))?]
+            }
+            None => service.targets.iter().collect(),
+        };
+
+        let _lock = BackupLock::acquire(service_name)
+            .context(format!("Failed to acquire lock for service '{}'", service_name))?;
+
+        let start_time = Instant::now();
+        let mut reports = Vec::new();
+        for target_name in targets {
+            let dest = self
+                .config
+                .destinations
+                .get(target_name)
+                .context(format!("Destination not found: {}", target_name))?;
+
+            info!("Running maintenance for '{}' on '{}'", service_name, target_name);
+            let repo_url = restic::build_repository_url(dest, service_name, None);
+            let _repo_lock = RepoLock::acquire(&repo_url, None, Duration::from_secs(service.timeout_seconds))?;
+            match self.deferred.get(service_name, target_name) {
+                Ok(Some(work)) => info!(
+                    "Catching up on {} deferred by run {}",
+                    work.phases.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(" and "),
+                    work.run_id
+                ),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read deferred work: {}", e),
+            }
+
+            let report = maintenance::run_maintenance(
+                &self.config.global,
+                service,
+                target_name,
+                dest,
+                options,
+                &self.pending_prunes,
+            );
+            if report.success() {
+                if let Err(e) = self.deferred.clear(service_name, target_name) {
+                    warn!("Failed to clear deferred work: {}", e);
+                }
+            }
+            reports.push(report);
+        }
+
+        if let Some(ref manager) = self.notification_manager {
+            let success = reports.iter().all(MaintenanceReport::success);
+            let text: Vec<String> = reports.iter().map(|r| r.to_string()).collect();
+            if let Err(e) = manager.send_maintenance(service_name, Some(&new_run_id()), success, &text.join("\n"), start_time.elapsed().as_secs()) {
+                warn!("Failed to send maintenance notification: {}", e);
+            }
+        }
+
+        Ok(reports)
+    }
+
+    /// Build the manifest describing the effective scope of a backup
+    ///
+    /// `ignore_patterns` are the patterns the backup used, collected again
+    /// if not given.
+    fn build_manifest(
+        &self,
+        service: &ResolvedServiceConfig,
+        files_processed: Option<u64>,
+        ignore_patterns: Option<&[String]>,
+    ) -> BackupManifest {
+        let mut manifest = BackupManifest {
+            files_processed,
+            ..Default::default()
+        };
+
+        if let Some(ref backup_config) = service.config {
+            for path in &backup_config.paths {
+                let full_path = self.resolve_path(path);
+                if full_path.exists() {
+                    manifest.paths.push(full_path.display().to_string());
+                } else {
+                    manifest.missing_paths.push(full_path.display().to_string());
+                }
+            }
+
+            manifest.volumes = backup_config.volumes.clone();
+
+            let hook_name = |h: &Hook| if h.name.is_empty() { h.command.clone() } else { h.name.clone() };
+            manifest.pre_backup_hooks = backup_config.pre_backup_hooks.iter().map(hook_name).collect();
+            manifest.post_backup_hooks = backup_config.post_backup_hooks.iter().map(hook_name).collect();
+        }
+
+        manifest.excludes = crate::config::get_effective_excludes(service, &self.config.global);
+        match ignore_patterns {
+            Some(patterns) => manifest.excludes.extend(patterns.iter().cloned()),
+            None => {
+                let existing: Vec<PathBuf> = manifest.paths.iter().map(PathBuf::from).collect();
+                match ignore_file::collect_ignore_patterns(&existing, &manifest.excludes, self.one_file_system(service)) {
+                    Ok(patterns) => manifest.excludes.extend(patterns),
+                    Err(e) => warn!("Failed to collect ignore patterns for manifest: {}", e),
+                }
+            }
+        }
+
+        manifest
+    }
+
+    /// Save the manifest for this run and report changes since the previous run
+    fn check_manifest_drift(
+        &self,
+        service: &ResolvedServiceConfig,
+        run_id: &str,
+        files_processed: Option<u64>,
+        ignore_patterns: Option<&[String]>,
+    ) {
+        let current = self.build_manifest(service, files_processed, ignore_patterns);
+
+        match self.manifests.load(&service.name) {
+            Ok(Some(previous)) => {
+                let changes = manifest::diff(&previous, &current);
+                if !changes.is_empty() {
+                    for change in &changes {
+                        warn!("Backup scope changed for '{}': {}", service.name, change);
+                    }
+
+                    if let Some(ref manager) = self.notification_manager {
+                        let message = format!(
+                            "Backup scope changed since the previous run:\n{}",
+                            changes.join("\n")
+                        );
+                        if let Err(e) = manager.send_warning(&service.name, Some(run_id), None, &message) {
+                            warn!("Failed to send warning notification: {}", e);
+                        }
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to load previous backup manifest: {}", e),
+        }
+
+        if let Err(e) = self.manifests.save(&service.name, &current) {
+            warn!("Failed to save backup manifest: {}", e);
+        }
+    }
+
+    /// Resolve a configured path (relative to docker_base or absolute)
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        if PathBuf::from(path).is_absolute() {
+            PathBuf::from(path)
+        } else {
+            self.config.global.docker_base.join(path)
+        }
+    }
+
+    /// Lists of files to back up: the service's `files_from` lists plus the
+    /// list a pre-backup hook generated
+    fn files_from(&self, service: &ResolvedServiceConfig, generated: Option<&Path>) -> Result<restic::FilesFrom> {
+        let mut files_from = restic::FilesFrom::default();
+        if let Some(config) = service.config.as_ref() {
+            files_from.lists = config.files_from.iter().map(|f| self.resolve_path(f)).collect();
+            files_from.verbatim = config.files_from_verbatim.iter().map(|f| self.resolve_path(f)).collect();
+        }
+        if let Some(missing) = files_from.lists.iter().chain(&files_from.verbatim).find(|f| !f.exists()) {
+            anyhow::bail!("File list does not exist: {}", missing.display());
+        }
+        // Generated lists hold exact paths
+        files_from.verbatim.extend(generated.map(Path::to_path_buf));
+        Ok(files_from)
+    }
+
+    /// What the backup of a service leaves out: global and service settings
+    /// plus the patterns of per-directory ignore files
+    ///
+    /// The ignore files are searched once and the patterns kept in
+    /// `ignore_patterns` for the other destinations of the run.
+    fn excludes(
+        &self,
+        service: &ResolvedServiceConfig,
+        paths: &[PathBuf],
+        ignore_patterns: &OnceLock<Vec<String>>,
+    ) -> Result<restic::Excludes> {
+        let global = &self.config.global;
+        let backup_config = service.config.as_ref();
+
+        let mut patterns = crate::config::get_effective_excludes(service, global);
+        let one_file_system = self.one_file_system(service);
+        let found = match ignore_patterns.get() {
+            Some(found) => found,
+            None => {
+                let found = ignore_file::collect_ignore_patterns(paths, &patterns, one_file_system)?;
+                ignore_patterns.get_or_init(|| found)
+            }
+        };
+        patterns.extend(found.iter().cloned());
+
+        let mut ipatterns = global.default_iexcludes.clone();
+        let mut files: Vec<PathBuf> = global.exclude_files.iter().map(|f| self.resolve_path(f)).collect();
+        if let Some(config) = backup_config {
+            ipatterns.extend(config.iexcludes.iter().cloned());
+            files.extend(config.exclude_files.iter().map(|f| self.resolve_path(f)));
+        }
+        // restic would fail the whole backup with a less helpful message
+        if let Some(missing) = files.iter().find(|f| !f.exists()) {
+            anyhow::bail!("Exclude file does not exist: {}", missing.display());
+        }
+
+        let larger_than = backup_config
+            .and_then(|c| c.exclude_larger_than.as_deref())
+            .or(global.exclude_larger_than.as_deref())
+            .map(humanize::parse_size)
+            .transpose()?;
+
+        Ok(restic::Excludes { patterns, ipatterns, files, larger_than, one_file_system })
+    }
+
+    /// Whether restic stays on the file system of each backup path
+    fn one_file_system(&self, service: &ResolvedServiceConfig) -> bool {
+        service
+            .config
+            .as_ref()
+            .and_then(|c| c.one_file_system)
+            .unwrap_or(self.config.global.one_file_system)
+    }
+
+    /// Compose stack to stop during the backup, if the service asks for it
+    fn compose_stack_to_stop(&self, service: &ResolvedServiceConfig) -> Option<compose::ComposeStack> {
+        let backup = service.config.as_ref().filter(|c| c.stop_during_backup)?;
+        Some(compose::ComposeStack {
+            project: backup.compose_project.clone(),
+            file: backup.compose_file.as_deref().map(|f| self.resolve_path(f)),
+            runtime: self.config.global.container_runtime,
+        })
+    }
+
     /// Perform backup to a specific destination
     fn backup_to_destination(
         &self,
         service: &ResolvedServiceConfig,
+        destination_name: &str,
         destination: &Destination,
-    ) -> Result<()> {
+        options: &RunOptions,
+        run: &mut DestinationRun,
+    ) -> Result<restic::BackupSummary> {
         info!(
             "Starting backup for service '{}' to '{}'",
             service.name, destination.url
         );
+        // Don't start another destination after SIGINT/SIGTERM
+        shutdown::check()?;
+
+        let repo_url = restic::build_repository_url(destination, &service.name, None);
+        let hook_env = hook_env(&service.name, destination_name, &repo_url);
+        let tags = snapshot_tags(service, options);
+
+        // Run pre-backup hooks (they may write a list of files to back up)
+        let generated_list = GeneratedFileList::new(&self.config.global.scratch_dir(), &service.name, destination_name)?;
+        let mut pre_hook_env = hook_env.clone();
+        pre_hook_env.push(("RM_FILES_FROM".to_string(), generated_list.path.display().to_string()));
+        run.phases
+            .time(BackupPhase::PreHooks, || self.run_pre_hooks(service, &pre_hook_env, &run.run_logs))
+            .context(BackupPhase::PreHooks)?;
+
+        // Containers listed in `containers` are inspected on every run, so
+        // their mounts are backed up as they are now
+        let expanded;
+        let service = if service.config.as_ref().is_some_and(|c| !c.containers.is_empty()) {
+            let docker_ops = docker_ops::for_backend(self.config.global.docker_backend, self.config.global.container_runtime)?;
+            expanded = run
+                .phases
+                .time(BackupPhase::Volumes, || with_container_mounts(service, docker_ops.as_ref()))
+                .context(BackupPhase::Volumes)?;
+            &expanded
+        } else {
+            service
+        };
 
-        // Run pre-backup hooks
-        self.run_pre_hooks(service)
-            .context("Pre-backup hooks failed")?;
+        // Stop the Compose stack while its data is backed up (the guard
+        // starts it again if anything below fails)
+        let stopped_stack = match self.compose_stack_to_stop(service) {
+            Some(stack) => Some(stack.stop()?),
+            None => None,
+        };
 
         // Create temporary directory for volume archives
-        let temp_dir = std::env::temp_dir()
-            .join("restic-manager")
-            .join(&service.name);
-        fs::create_dir_all(&temp_dir)
-            .context("Failed to create temporary directory")?;
+        let temp_dir = ScratchDir::create(&self.config.global.scratch_dir(), &service.name)?;
 
-        // Backup Docker volumes to temp directory
-        let volume_archives = self.backup_volumes(service, &temp_dir)
-            .context("Failed to backup Docker volumes")?;
+        // Backup Docker volumes to temp directory (unless they are streamed
+        // into restic after the paths)
+        let stream_volumes = self.streams_volumes() && !service_volumes(service).is_empty();
+        let volume_archives = if stream_volumes {
+            Vec::new()
+        } else {
+            run.phases
+                .time(BackupPhase::Volumes, || self.backup_volumes(service, temp_dir.path()))
+                .context(BackupPhase::Volumes)?
+        };
 
         // Collect file paths
         let mut paths_to_backup = self.collect_paths(service)?;
@@ -215,51 +1211,203 @@ impl BackupManager {
         // Add volume archives to backup
         paths_to_backup.extend(volume_archives);
 
-        if paths_to_backup.is_empty() {
+        let empty_commands = vec![];
+        let command_sources = service
+            .config
+            .as_ref()
+            .map(|c| &c.commands)
+            .unwrap_or(&empty_commands);
+
+        let files_from = self.files_from(service, generated_list.written())?;
+
+        if paths_to_backup.is_empty() && files_from.is_empty() && command_sources.is_empty() && !stream_volumes {
             warn!("No paths to backup for service '{}'", service.name);
-            return Ok(());
+            return Ok(restic::BackupSummary::default());
         }
 
         // Setup restic environment
-        let repo_url = restic::build_repository_url(destination, &service.name, None);
-        let env = restic::ResticEnv::new(&self.config.global.restic_password_file, &repo_url);
+        let env = restic::ResticEnv::for_destination(&self.config.global, destination_name, destination, &repo_url)?
+            .with_host(service.hostname.as_deref())
+            .with_limits(service.limit_upload_kbps, service.limit_download_kbps);
 
         let timeout = Duration::from_secs(service.timeout_seconds);
 
+        let retry = &service.retry;
+
         // Initialize repository if needed
-        restic::init_repository(&env, timeout)
+        retry::with_retry(retry, "Repository init", &mut run.retries, || restic::init_repository(&env, timeout))
             .context("Failed to initialize repository")?;
 
-        // Get excludes
-        let excludes = crate::config::get_effective_excludes(service, &self.config.global);
+        // Get excludes (config + per-directory ignore files)
+        let excludes = self.excludes(service, &paths_to_backup, &run.ignore_patterns)?;
 
         // Perform backup
-        restic::backup(&env, &paths_to_backup, &excludes, timeout)
-            .context("Failed to backup to restic")?;
+        let mut summary = if paths_to_backup.is_empty() && files_from.is_empty() {
+            restic::BackupSummary::default()
+        } else {
+            run.phases
+                .time(BackupPhase::Backup, || {
+                    retry::with_retry(retry, "Backup", &mut run.retries, || {
+                        restic::backup_with_tags(&env, &paths_to_backup, &files_from, &excludes, &tags, timeout)
+                    })
+                })
+                .context(BackupPhase::Backup)?
+        };
 
-        // Apply retention policy
-        restic::apply_retention(&env, &service.retention, timeout)
-            .context("Failed to apply retention policy")?;
+        if stream_volumes {
+            let volume_summary = run
+                .phases
+                .time(BackupPhase::Volumes, || self.stream_volumes(service, &env, &tags, &mut run.retries))
+                .context(BackupPhase::Volumes)?;
+            summary.merge(&volume_summary);
+        }
 
-        // Cleanup temporary directory
-        if let Err(e) = fs::remove_dir_all(&temp_dir) {
-            warn!("Failed to cleanup temporary directory: {}", e);
+        if let Some(stopped) = stopped_stack {
+            stopped.start()?;
         }
 
-        // Run post-backup hooks
-        self.run_post_hooks(service)
-            .context("Post-backup hooks failed")?;
+        // Back up command output (one snapshot per command)
+        for source in command_sources {
+            let what = format!("Command source '{}'", source.name);
+            let command_summary = run
+                .phases
+                .time(BackupPhase::Backup, || {
+                    retry::with_retry(retry, &what, &mut run.retries, || {
+                        self.backup_command_source(&env, source, service, &tags)
+                    })
+                })
+                .context(BackupPhase::Backup)?;
+            summary.merge(&command_summary);
+        }
+
+        // Apply retention policy (append-only repositories are pruned from a trusted host)
+        if env.is_append_only() {
+            info!(
+                "Skipping retention for append-only destination '{}'",
+                destination_name
+            );
+        } else if run.over_budget() {
+            info!(
+                "Service '{}' is over its time budget, deferring retention on '{}' to maintenance",
+                service.name, destination_name
+            );
+            run.deferred.push(DeferredPhase::Retention);
+        } else {
+            let applied = if self.config.global.prune_delay_days > 0 {
+                // Forget only; `maintain` prunes once the delay has passed
+                let forgotten = run.phases.time(BackupPhase::Retention, || {
+                    retry::with_retry(retry, "Retention", &mut run.retries, || {
+                        restic::forget(&env, &service.retention, false, timeout)
+                    })
+                });
+                forgotten.map(|removed| {
+                    if removed > 0 {
+                        info!("Forgot {} snapshot(s), pruning is delayed", removed);
+                        if let Err(e) = self.pending_prunes.record(&service.name, destination_name, removed) {
+                            warn!("Failed to record pending prune: {}", e);
+                        }
+                    }
+                })
+            } else {
+                run.phases.time(BackupPhase::Retention, || {
+                    retry::with_retry(retry, "Retention", &mut run.retries, || {
+                        restic::apply_retention(&env, &service.retention, timeout)
+                    })
+                })
+            };
+            if let Err(e) = applied {
+                // The snapshot is stored either way; unless configured
+                // otherwise a failed retention is reported, not fatal
+                if self.config.global.fail_on_retention_error || RunOutcome::from_error(&e) == RunOutcome::Cancelled {
+                    return Err(e.context(BackupPhase::Retention));
+                }
+                warn!("Failed to apply retention policy on '{}': {:#}", destination_name, e);
+                run.retention_error = Some(format!("{:#}", e));
+            }
+        }
+
+        // Cleanup temporary directory
+        drop(temp_dir);
+
+        // Run post-backup hooks (they also get the new snapshot's ID)
+        let mut post_hook_env = hook_env;
+        if let Some(ref id) = summary.snapshot_id {
+            post_hook_env.push(("RM_SNAPSHOT_ID".to_string(), id.clone()));
+        }
+        run.phases
+            .time(BackupPhase::PostHooks, || self.run_post_hooks(service, &post_hook_env, &run.run_logs))
+            .context(BackupPhase::PostHooks)?;
 
         info!(
             "Successfully completed backup for service '{}' to '{}'",
             service.name, destination.url
         );
 
-        Ok(())
+        Ok(summary)
+    }
+
+    /// Estimate how much data the next backup would upload to each destination
+    ///
+    /// Runs `restic backup --dry-run` against every target of the service
+    /// (with `verbose`, also listing the files that would be added). Hooks
+    /// are not run and retention is not applied, so data produced by
+    /// pre-backup hooks (e.g. database dumps) is only counted if it already
+    /// exists on disk.
+    ///
+    /// Nothing is changed on the host: volumes are measured instead of
+    /// archived (no containers are paused or stopped), and the service lock
+    /// is held so a backup can't start meanwhile.
+    pub fn forecast_service(&self, service_name: &str, verbose: bool) -> Result<Forecast> {
+        let service = self
+            .resolved_services
+            .get(service_name)
+            .context(format!("Service not found: {}", service_name))?;
+
+        let _lock = BackupLock::acquire(service_name)
+            .context(format!("Failed to acquire lock for service '{}'", service_name))?;
+
+        let volumes = self.measure_volumes(service)?;
+
+        let paths_to_backup = self.collect_paths(service)?;
+        let files_from = self.files_from(service, None)?;
+        let excludes = self.excludes(service, &paths_to_backup, &OnceLock::new())?;
+        let timeout = Duration::from_secs(service.timeout_seconds);
+
+        let mut destinations = Vec::new();
+        for target_name in &service.targets {
+            let destination = self
+                .config
+                .destinations
+                .get(target_name)
+                .context(format!("Destination not found: {}", target_name))?;
+
+            let repo_url = restic::build_repository_url(destination, &service.name, None);
+            let summary = restic::ResticEnv::for_destination(&self.config.global, target_name, destination, &repo_url)
+                .map(|env| env.with_host(service.hostname.as_deref()))
+                .and_then(|env| restic::forecast_backup(&env, &paths_to_backup, &files_from, &excludes, verbose, timeout));
+            destinations.push((target_name.clone(), summary));
+        }
+
+        Ok(Forecast { volumes, destinations })
+    }
+
+    /// Current size of each Docker volume of a service
+    fn measure_volumes(&self, service: &ResolvedServiceConfig) -> Result<Vec<(String, Result<u64>)>> {
+        let volumes = service_volumes(service);
+        if volumes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let docker_ops = docker_ops::for_backend(self.config.global.docker_backend, self.config.global.container_runtime)?;
+        let timeout = Duration::from_secs(60);
+        Ok(volumes
+            .iter()
+            .map(|volume| (volume.clone(), docker_ops.get_volume_size(volume, timeout)))
+            .collect())
     }
 
     /// Run pre-backup hooks
-    fn run_pre_hooks(&self, service: &ResolvedServiceConfig) -> Result<()> {
+    fn run_pre_hooks(&self, service: &ResolvedServiceConfig, env: &[(String, String)], run_logs: &RunLogs) -> Result<()> {
         let empty_hooks = vec![];
         let hooks = service
             .config
@@ -274,14 +1422,14 @@ impl BackupManager {
         info!("Running {} pre-backup hooks", hooks.len());
 
         for hook in hooks {
-            self.run_hook(hook, service, "pre-backup")?;
+            self.run_hook(hook, service, "pre-backup", env, run_logs)?;
         }
 
         Ok(())
     }
 
     /// Run post-backup hooks
-    fn run_post_hooks(&self, service: &ResolvedServiceConfig) -> Result<()> {
+    fn run_post_hooks(&self, service: &ResolvedServiceConfig, env: &[(String, String)], run_logs: &RunLogs) -> Result<()> {
         let empty_hooks = vec![];
         let hooks = service
             .config
@@ -296,14 +1444,185 @@ impl BackupManager {
         info!("Running {} post-backup hooks", hooks.len());
 
         for hook in hooks {
-            self.run_hook(hook, service, "post-backup")?;
+            self.run_hook(hook, service, "post-backup", env, run_logs)?;
         }
 
         Ok(())
     }
 
+    /// Check that the snapshot recorded by the previous run is still in the
+    /// repository
+    ///
+    /// Returns a note for the run report when history was truncated. This is
+    /// logged as an error and sent as a warning, but doesn't stop the backup.
+    fn check_chain(
+        &self,
+        service: &ResolvedServiceConfig,
+        run_id: &str,
+        destination_name: &str,
+        destination: &Destination,
+        repo_url: &str,
+    ) -> Option<String> {
+        let service_name = service.name.as_str();
+        let expected = match self.chain.get(service_name, destination_name) {
+            Ok(Some(link)) => link,
+            Ok(None) => return None,
+            Err(e) => {
+                warn!("Failed to read snapshot chain: {}", e);
+                return None;
+            }
+        };
+
+        let snapshots = restic::ResticEnv::for_destination(&self.config.global, destination_name, destination, repo_url)
+            .map(|env| env.with_host(service.hostname.as_deref()))
+            .and_then(|env| restic::list_snapshots(&env, Duration::from_secs(300)));
+        let snapshots = match snapshots {
+            Ok(snapshots) => snapshots,
+            Err(e) => {
+                // An unreachable repository fails the backup itself
+                warn!("Could not check snapshot chain of '{}' on '{}': {:#}", service_name, destination_name, e);
+                return None;
+            }
+        };
+
+        if chain::contains_snapshot(&snapshots, &expected.snapshot_id) {
+            return None;
+        }
+
+        let message = format!(
+            "Snapshot {} from the previous backup ({}) is missing from the repository ({} snapshots left). History was truncated or the repository was recreated",
+            expected.snapshot_id,
+            expected.recorded_at.format("%Y-%m-%d %H:%M UTC"),
+            snapshots.len()
+        );
+        error!("Backup of '{}' to '{}': {}", service_name, destination_name, message);
+        if let Some(ref manager) = self.notification_manager {
+            if let Err(e) = manager.send_warning(service_name, Some(run_id), Some(destination_name), &message) {
+                warn!("Failed to send snapshot chain warning: {}", e);
+            }
+        }
+        Some(format!("HISTORY TRUNCATED: {}", message))
+    }
+
+    /// Record phases skipped for the time budget, returning a note for the run report
+    fn defer_phases(&self, service_name: &str, run_id: &str, destination_name: &str, phases: &[DeferredPhase]) -> String {
+        if phases.is_empty() {
+            return String::new();
+        }
+
+        if let Err(e) = self.deferred.defer(service_name, destination_name, run_id, phases) {
+            warn!("Failed to record deferred work: {}", e);
+        }
+        let names: Vec<String> = phases.iter().map(|p| p.to_string()).collect();
+        format!(", {} deferred (over time budget)", names.join(" and "))
+    }
+
+    /// Warn that a service ran over its time budget and work was deferred
+    fn notify_over_budget(&self, service: &ResolvedServiceConfig, run_id: &str, destinations: &[&str], duration_secs: u64) {
+        let message = format!(
+            "Backup took {}, over its time budget of {}. Retention/check on {} deferred to the next maintenance run",
+            format_duration(duration_secs),
+            format_duration(service.time_budget_minutes.unwrap_or_default() * 60),
+            destinations.join(", ")
+        );
+        warn!("{}: {}", service.name, message);
+
+        if let Some(ref manager) = self.notification_manager {
+            if let Err(e) = manager.send_warning(&service.name, Some(run_id), None, &message) {
+                warn!("Failed to send time budget warning: {}", e);
+            }
+        }
+    }
+
+    /// Report a retention failure of a successful backup
+    ///
+    /// Returns a note for the run report. Until retention succeeds again
+    /// the repository only grows.
+    fn notify_retention_failed(&self, service: &str, run_id: &str, destination_name: &str, error: &str) -> String {
+        if let Some(ref manager) = self.notification_manager {
+            let message = format!("Retention policy could not be applied, old snapshots are kept: {}", error);
+            if let Err(e) = manager.send_warning(service, Some(run_id), Some(destination_name), &message) {
+                warn!("Failed to send retention warning: {}", e);
+            }
+        }
+        format!("RETENTION FAILED: {}", error)
+    }
+
+    /// Verify a new snapshot as configured by `post_backup_verify`
+    ///
+    /// Returns a note for the run report. A failed verification is reported
+    /// and sent as a warning, but doesn't fail the backup.
+    fn verify_snapshot(
+        &self,
+        service: &ResolvedServiceConfig,
+        run_id: &str,
+        destination_name: &str,
+        destination: &Destination,
+        snapshot_id: &str,
+    ) -> Option<String> {
+        if service.post_backup_verify == PostBackupVerify::Off {
+            return None;
+        }
+
+        let repo_url = restic::build_repository_url(destination, &service.name, None);
+        let timeout = Duration::from_secs(service.timeout_seconds);
+        let result = restic::ResticEnv::for_destination(&self.config.global, destination_name, destination, &repo_url)
+            .and_then(|env| {
+                let scratch_dir = self.config.global.scratch_dir();
+                verification::verify_snapshot(&env, service.post_backup_verify, snapshot_id, &scratch_dir, timeout)
+            });
+
+        match result {
+            Ok(note) => note.map(|note| format!("verified ({})", note)),
+            Err(e) => {
+                warn!(
+                    "Verification of snapshot {} of '{}' on '{}' failed: {:#}",
+                    snapshot_id, service.name, destination_name, e
+                );
+                if let Some(ref manager) = self.notification_manager {
+                    let message = format!("Verification of snapshot {} failed: {:#}", snapshot_id, e);
+                    if let Err(e) = manager.send_warning(&service.name, Some(run_id), Some(destination_name), &message) {
+                        warn!("Failed to send verification warning: {}", e);
+                    }
+                }
+                Some(format!("VERIFICATION FAILED: {:#}", e))
+            }
+        }
+    }
+
+    /// Run on-success or on-failure hooks for a destination
+    ///
+    /// Failures are only logged: these hooks react to the result of a backup
+    /// and never change it.
+    fn run_result_hooks(&self, service: &ResolvedServiceConfig, success: bool, env: &[(String, String)], run_logs: &RunLogs) {
+        let (hooks, hook_type) = match service.config.as_ref() {
+            Some(c) if success => (&c.on_success_hooks, "on-success"),
+            Some(c) => (&c.on_failure_hooks, "on-failure"),
+            None => return,
+        };
+
+        if hooks.is_empty() {
+            return;
+        }
+
+        info!("Running {} {} hooks", hooks.len(), hook_type);
+
+        for hook in hooks {
+            if let Err(e) = self.run_hook(hook, service, hook_type, env, run_logs) {
+                warn!("{} hook failed: {:#}", hook_type, e);
+            }
+        }
+    }
+
     /// Execute a single hook
-    fn run_hook(&self, hook: &Hook, service: &ResolvedServiceConfig, hook_type: &str) -> Result<()> {
+    fn run_hook(
+        &self,
+        hook: &Hook,
+        service: &ResolvedServiceConfig,
+        hook_type: &str,
+        env: &[(String, String)],
+        run_logs: &RunLogs,
+    ) -> Result<()> {
         let hook_name = if hook.name.is_empty() {
             &hook.command
         } else {
@@ -319,70 +1638,201 @@ impl BackupManager {
 
         let working_dir = hook.working_dir.as_deref();
 
-        let result = crate::utils::command::run_shell_command(
+        let mut env = env.to_vec();
+        env.push(("RM_PHASE".to_string(), hook_type.to_string()));
+
+        let result = crate::utils::command::run_shell_command_with_env(
             &hook.command,
             working_dir,
             timeout,
+            &env,
         );
 
         match result {
-            Ok(_) => {
+            Ok(output) => {
                 info!("Hook completed successfully: {}", hook_name);
+                run_logs.hook(
+                    &service.name,
+                    hook_type,
+                    hook_name,
+                    &format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)),
+                );
                 Ok(())
             }
             Err(e) => {
+                run_logs.hook(&service.name, hook_type, hook_name, &format!("FAILED: {:#}", e));
                 if hook.continue_on_error {
                     warn!("Hook failed but continue_on_error=true: {} - {}", hook_name, e);
                     Ok(())
                 } else {
                     error!("Hook failed: {} - {}", hook_name, e);
-                    Err(e).context(format!("Failed to execute hook: {}", hook_name))
+                    Err(e).context(BackupError::HookFailed { hook: hook_name.clone() })
                 }
             }
         }
     }
 
-    /// Backup Docker volumes
-    fn backup_volumes(
+    /// Run a command source and back up its stdout
+    fn backup_command_source(
         &self,
+        env: &restic::ResticEnv,
+        source: &CommandSource,
         service: &ResolvedServiceConfig,
-        temp_dir: &PathBuf,
-    ) -> Result<Vec<PathBuf>> {
-        let empty_volumes = vec![];
-        let volumes = service
-            .config
-            .as_ref()
-            .map(|c| &c.volumes)
-            .unwrap_or(&empty_volumes);
+        tags: &[String],
+    ) -> Result<restic::BackupSummary> {
+        let timeout = Duration::from_secs(source.timeout_seconds.unwrap_or(service.timeout_seconds));
+
+        let output = crate::utils::command::run_shell_command(&source.command, None, Some(timeout))
+            .context(format!("Command source '{}' failed", source.name))?;
+
+        restic::backup_stdin(
+            env,
+            output.stdout,
+            &source.stdin_filename(),
+            tags,
+            Duration::from_secs(service.timeout_seconds),
+        )
+    }
 
-        if volumes.is_empty() {
-            return Ok(vec![]);
+    /// Whether volumes are streamed into restic instead of archived to temp files
+    ///
+    /// Falls back to temp files when the Docker API backend is used or restic
+    /// is too old for `--stdin-from-command`.
+    fn streams_volumes(&self) -> bool {
+        if !self.features().stream_volumes {
+            return false;
+        }
+        if self.config.global.docker_backend != DockerBackend::Cli {
+            info!("Volume streaming needs the CLI docker backend, archiving to temp files");
+            return false;
         }
+        if !restic::supports_stdin_from_command() {
+            warn!("Volume streaming needs restic 0.17 or newer, archiving to temp files");
+            return false;
+        }
+        true
+    }
 
+    /// Run `f` on each of the service's volumes while the labeled containers are held still
+    ///
+    /// `f` gets the volume name and the time it may take, which the maximum
+    /// pause duration limits. The containers are resumed afterwards, also on
+    /// errors.
+    fn with_volumes_held(
+        &self,
+        service: &ResolvedServiceConfig,
+        docker_ops: &dyn DockerOperations,
+        mut f: impl FnMut(&str, Duration) -> Result<()>,
+    ) -> Result<()> {
+        let volumes = service_volumes(service);
         info!("Backing up {} Docker volumes", volumes.len());
 
         let timeout = Duration::from_secs(service.timeout_seconds);
-        let mut archived_paths = Vec::new();
 
         // First, verify all volumes exist
         for volume_name in volumes {
-            if !docker::volume_exists(volume_name, Duration::from_secs(30))? {
-                anyhow::bail!("Docker volume does not exist: {}", volume_name);
+            if !docker_ops.volume_exists(volume_name, Duration::from_secs(30))? {
+                return Err(BackupError::VolumeMissing(volume_name.to_string()).into());
             }
         }
 
-        // Archive each volume
+        // Hold the labeled containers still (resumed when `paused` is
+        // dropped, also on errors)
+        let paused = match service.config.as_ref().and_then(|c| c.pause_containers.as_ref()) {
+            Some(pause) => Some(PausedContainers::pause(
+                docker_ops,
+                &pause.label,
+                pause.mode,
+                Duration::from_secs(pause.max_pause_seconds),
+                Duration::from_secs(120),
+            )?),
+            None => None,
+        };
+
         for volume_name in volumes {
-            let archive_path = temp_dir.join(format!("{}.tar.gz", volume_name));
-            docker::archive_volume(volume_name, &archive_path, timeout)
+            let volume_timeout = match paused {
+                Some(ref paused) => {
+                    let remaining = paused.remaining();
+                    if remaining.is_zero() {
+                        anyhow::bail!(
+                            "Containers were suspended for the maximum pause duration before '{}' was archived",
+                            volume_name
+                        );
+                    }
+                    timeout.min(remaining)
+                }
+                None => timeout,
+            };
+
+            f(volume_name, volume_timeout)?;
+        }
+
+        if let Some(paused) = paused {
+            paused.resume()?;
+        }
+
+        Ok(())
+    }
+
+    /// Backup Docker volumes
+    fn backup_volumes(
+        &self,
+        service: &ResolvedServiceConfig,
+        temp_dir: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        if service_volumes(service).is_empty() {
+            return Ok(vec![]);
+        }
+
+        let docker_ops = docker_ops::for_backend(self.config.global.docker_backend, self.config.global.container_runtime)?;
+        check_work_dir_space(service, docker_ops.as_ref(), temp_dir)?;
+        let mut archived_paths = Vec::new();
+
+        self.with_volumes_held(service, docker_ops.as_ref(), |volume_name, timeout| {
+            let archive_path = temp_dir.join(format!(
+                "{}.{}",
+                volume_name,
+                self.config.global.volume_archive_compression.extension()
+            ));
+            docker_ops
+                .archive_volume(volume_name, &archive_path, timeout)
                 .context(format!("Failed to archive volume: {}", volume_name))?;
 
             archived_paths.push(archive_path);
-        }
+            Ok(())
+        })?;
 
         Ok(archived_paths)
     }
 
+    /// Stream each volume into restic as `<volume>.tar` (one snapshot per volume)
+    fn stream_volumes(
+        &self,
+        service: &ResolvedServiceConfig,
+        env: &restic::ResticEnv,
+        tags: &[String],
+        retries: &mut u32,
+    ) -> Result<restic::BackupSummary> {
+        let runtime = self.config.global.container_runtime;
+        let docker_ops = docker_ops::for_backend(self.config.global.docker_backend, runtime)?;
+        let mut summary = restic::BackupSummary::default();
+
+        self.with_volumes_held(service, docker_ops.as_ref(), |volume_name, timeout| {
+            let command = docker::volume_tar_command(runtime, volume_name);
+            let filename = format!("{}.tar", volume_name);
+            let what = format!("Streaming volume '{}'", volume_name);
+            let volume_summary = retry::with_retry(&service.retry, &what, retries, || {
+                restic::backup_from_command(env, &command, &filename, tags, timeout)
+            })
+            .context(format!("Failed to back up volume: {}", volume_name))?;
+
+            summary.merge(&volume_summary);
+            Ok(())
+        })?;
+
+        Ok(summary)
+    }
+
     /// Collect file paths to backup
     fn collect_paths(&self, service: &ResolvedServiceConfig) -> Result<Vec<PathBuf>> {
         let empty_paths = vec![];
@@ -395,11 +1845,7 @@ impl BackupManager {
         let mut full_paths = Vec::new();
 
         for path in paths {
-            let full_path = if PathBuf::from(path).is_absolute() {
-                PathBuf::from(path)
-            } else {
-                self.config.global.docker_base.join(path)
-            };
+            let full_path = self.resolve_path(path);
 
             if !full_path.exists() {
                 warn!("Path does not exist: {:?}", full_path);
@@ -412,8 +1858,30 @@ impl BackupManager {
         Ok(full_paths)
     }
 
-    /// Run backups for all enabled services
-    pub fn backup_all(&self) -> Result<()> {
+    /// Remove work directories that crashed runs left behind
+    ///
+    /// A service's directory is only removed while its lock can be taken,
+    /// so a backup running in another process keeps its archives.
+    fn remove_orphaned_work_dirs(&self) {
+        let scratch_dir = self.config.global.scratch_dir();
+        for name in self.resolved_services.keys() {
+            let dir = scratch_dir.join(name);
+            if !dir.exists() {
+                continue;
+            }
+            let Ok(_lock) = BackupLock::acquire(name) else {
+                continue;
+            };
+            match scratch::remove_stale(&dir) {
+                Ok(true) => info!("Removed orphaned work directory {}", dir.display()),
+                Ok(false) => warn!("Leaving {} alone, it wasn't created by restic-manager", dir.display()),
+                Err(e) => warn!("Failed to remove orphaned work directory {}: {:#}", dir.display(), e),
+            }
+        }
+    }
+
+    /// Run backups for all enabled services (one run id for all of them)
+    pub fn backup_all(&self, options: &RunOptions) -> Result<()> {
         info!("Starting backup for all enabled services");
 
         let enabled_services: Vec<_> = self
@@ -428,35 +1896,84 @@ impl BackupManager {
         }
 
         info!("Found {} enabled services", enabled_services.len());
+        self.remove_orphaned_work_dirs();
 
         let mut success_count = 0;
         let mut failure_count = 0;
+        let mut skipped_count = 0;
         let mut errors = Vec::new();
+        let mut last_error = None;
+
+        // One run id for the whole run, so its notifications group together
+        let options = RunOptions {
+            run_id: Some(new_run_id()),
+            enforce_time_budget: true,
+            ..options.clone()
+        };
+
+        let enabled_features = self.features().enabled();
+        if !enabled_features.is_empty() {
+            info!("Experimental features enabled: {}", enabled_features.join(", "));
+        }
+
+        let results: Vec<(&String, Result<()>)> = if self.features().parallel_backups {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = enabled_services
+                    .iter()
+                    .map(|(name, _)| {
+                        let options = &options;
+                        (*name, scope.spawn(move || self.backup_service_with(name, options)))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|(name, handle)| {
+                        let result = handle
+                            .join()
+                            .unwrap_or_else(|_| Err(anyhow::anyhow!("Backup thread panicked")));
+                        (name, result)
+                    })
+                    .collect()
+            })
+        } else {
+            enabled_services
+                .iter()
+                .map(|(name, _)| {
+                    let result = shutdown::check().and_then(|_| self.backup_service_with(name, &options));
+                    (*name, result)
+                })
+                .collect()
+        };
 
-        for (name, _) in enabled_services {
-            match self.backup_service(name) {
+        for (name, result) in results {
+            match result {
                 Ok(_) => {
                     success_count += 1;
                 }
+                Err(e) if locker::already_running(&e).is_some() => {
+                    skipped_count += 1;
+                    warn!("Skipped '{}': {}", name, e);
+                }
                 Err(e) => {
                     failure_count += 1;
                     errors.push(format!("{}: {}", name, e));
                     error!("Failed to backup service '{}': {}", name, e);
+                    last_error = Some(e);
                 }
             }
         }
 
         info!(
-            "Backup summary: {} succeeded, {} failed",
-            success_count, failure_count
+            "Backup summary: {} succeeded, {} failed, {} skipped (already running elsewhere)",
+            success_count, failure_count, skipped_count
         );
 
-        if failure_count > 0 {
-            anyhow::bail!(
-                "{} service(s) failed to backup:\n{}",
-                failure_count,
-                errors.join("\n")
-            );
+        if let Some(error) = last_error {
+            let summary = format!("{} service(s) failed to backup:\n{}", failure_count, errors.join("\n"));
+            if success_count > 0 {
+                return Err(BackupError::PartialFailure { summary }.into());
+            }
+            return Err(error.context(summary));
         }
 
         Ok(())
@@ -474,3 +1991,137 @@ impl BackupManager {
         self.resolved_services.get(name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GlobalConfig;
+    use anyhow::Context;
+
+    #[test]
+    fn test_result_hook_env() {
+        let base = hook_env("app", "local", "/backups/app");
+        assert!(base.contains(&("RM_REPO_URL".to_string(), "/backups/app".to_string())));
+
+        let env = result_hook_env(&base, RunOutcome::Success, Some("abc123"), None);
+        assert!(env.contains(&("RM_OUTCOME".to_string(), "success".to_string())));
+        assert!(env.contains(&("RM_SNAPSHOT_ID".to_string(), "abc123".to_string())));
+        assert!(!env.iter().any(|(k, _)| k == "RM_ERROR"));
+
+        let failed: Result<()> = Err(anyhow::anyhow!("disk full"));
+        let error = failed.context(BackupPhase::Retention).unwrap_err();
+        let env = result_hook_env(&base, RunOutcome::Error, None, Some(&error));
+        assert!(env.contains(&("RM_SERVICE".to_string(), "app".to_string())));
+        assert!(env.contains(&("RM_DESTINATION".to_string(), "local".to_string())));
+        assert!(env.contains(&("RM_STAGE".to_string(), "retention".to_string())));
+        assert!(env.iter().any(|(k, v)| k == "RM_ERROR" && v.contains("disk full")));
+    }
+
+    #[test]
+    fn test_generated_file_list() {
+        let path = {
+            let list = GeneratedFileList::new(&GlobalConfig::default().scratch_dir(), "files-from-test", "local").unwrap();
+            assert!(list.written().is_none());
+            fs::write(&list.path, "/srv/export/a.csv\n").unwrap();
+            assert_eq!(list.written(), Some(list.path.as_path()));
+            list.path.clone()
+        };
+        // Removed when the backup is done
+        assert!(!path.exists());
+
+        // A stale list from a killed run is not picked up
+        fs::write(&path, "/stale\n").unwrap();
+        let list = GeneratedFileList::new(&GlobalConfig::default().scratch_dir(), "files-from-test", "local").unwrap();
+        assert!(list.written().is_none());
+    }
+
+    #[test]
+    fn test_ensure_fits() {
+        let dir = Path::new("/tmp/restic-manager");
+        assert!(ensure_fits(1024, 4096, dir).is_ok());
+        let err = ensure_fits(8 * 1024 * 1024, 1024, dir).unwrap_err();
+        assert!(err.to_string().contains("work_dir"));
+    }
+
+    #[test]
+    fn test_run_options_snapshot_tags() {
+        assert_eq!(RunOptions::default().snapshot_tags(), vec!["manual"]);
+
+        let options = RunOptions {
+            trigger: RunTrigger::Scheduled,
+            reason: Some("before upgrade".to_string()),
+            tags: vec!["db".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(options.snapshot_tags(), vec!["scheduled", "db"]);
+    }
+
+    #[test]
+    fn test_with_container_mounts() {
+        use crate::config::BackupConfig;
+        use crate::utils::docker::ContainerInfo;
+        use crate::utils::docker_ops::mock::MockDockerOps;
+
+        let service = ResolvedServiceConfig {
+            name: "app".to_string(),
+            description: String::new(),
+            enabled: true,
+            disabled_until: None,
+            schedule: "0 2 * * *".to_string(),
+            targets: vec!["local".to_string()],
+            timeout_seconds: 3600,
+            time_budget_minutes: None,
+            max_age_hours: None,
+            retention: crate::config::RetentionPolicy {
+                daily: 7,
+                weekly: 4,
+                monthly: 6,
+                yearly: 1,
+                ..Default::default()
+            },
+            retry: Default::default(),
+            post_backup_verify: Default::default(),
+            notify_on: vec![],
+            tags: vec![],
+            hostname: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            config: Some(BackupConfig {
+                paths: vec!["/srv/app/config".to_string()],
+                files_from: vec![],
+                files_from_verbatim: vec![],
+                volumes: vec!["app_db".to_string()],
+                containers: vec!["app".to_string()],
+                compose_project: None,
+                compose_file: None,
+                stop_during_backup: false,
+                pause_containers: None,
+                excludes: vec![],
+                iexcludes: vec![],
+                exclude_files: vec![],
+                exclude_larger_than: None,
+                one_file_system: None,
+                pre_backup_hooks: vec![],
+                post_backup_hooks: vec![],
+                commands: vec![],
+                on_failure_hooks: vec![],
+                on_success_hooks: vec![],
+                restore_test_hooks: vec![],
+            }),
+        };
+
+        let ops = MockDockerOps::new().with_inspected_container(ContainerInfo {
+            name: "app".to_string(),
+            labels: HashMap::new(),
+            volumes: vec!["app_db".to_string(), "app_uploads".to_string()],
+            binds: vec![PathBuf::from("/srv/app/config"), PathBuf::from("/srv/app/themes")],
+        });
+        let expanded = with_container_mounts(&service, &ops).unwrap();
+        let backup = expanded.config.unwrap();
+        assert_eq!(backup.volumes, vec!["app_db", "app_uploads"]);
+        assert_eq!(backup.paths, vec!["/srv/app/config", "/srv/app/themes"]);
+
+        let missing = MockDockerOps::new();
+        assert!(with_container_mounts(&service, &missing).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Snapshot chain - continuity of a repository's history between runs
+//!
+//! After each successful backup the new snapshot id is recorded per service
+//! and destination. The next run checks that the repository still contains
+//! it before backing up. Retention always keeps the newest snapshot, so a
+//! missing one means history was lost outside of restic-manager: an
+//! aggressive manual `forget`, or a repository that was wiped and recreated.
+
+use crate::utils::restic::Snapshot;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// The last snapshot written to a repository
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainLink {
+    pub snapshot_id: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Whether a snapshot list contains the given (full or short) snapshot id
+pub fn contains_snapshot(snapshots: &[Snapshot], snapshot_id: &str) -> bool {
+    snapshots
+        .iter()
+        .any(|s| s.id == snapshot_id || s.short_id == snapshot_id || (!s.id.is_empty() && snapshot_id.starts_with(&s.id)))
+}
+
+/// Last snapshot per service and destination, persisted as JSON
+#[derive(Debug, Clone)]
+pub struct ChainStore {
+    path: PathBuf,
+}
+
+impl ChainStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default location (~/.local/share/restic-manager/chain.json)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("chain.json")
+        } else {
+            PathBuf::from("/tmp/restic-manager-chain.json")
+        }
+    }
+
+    fn key(service: &str, destination: &str) -> String {
+        format!("{}/{}", service, destination)
+    }
+
+    fn load(&self) -> Result<HashMap<String, ChainLink>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&self.path).context("Failed to read snapshot chain")?;
+        serde_json::from_str(&content).context("Failed to parse snapshot chain")
+    }
+
+    fn save(&self, links: &HashMap<String, ChainLink>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(links).context("Failed to serialize snapshot chain")?;
+        fs::write(&self.path, content).context("Failed to write snapshot chain")
+    }
+
+    /// Snapshot expected to be in a service's repository on a destination
+    pub fn get(&self, service: &str, destination: &str) -> Result<Option<ChainLink>> {
+        Ok(self.load()?.remove(&Self::key(service, destination)))
+    }
+
+    /// Record the snapshot written by a successful backup
+    pub fn record(&self, service: &str, destination: &str, snapshot_id: &str) -> Result<()> {
+        let mut links = self.load()?;
+        links.insert(
+            Self::key(service, destination),
+            ChainLink { snapshot_id: snapshot_id.to_string(), recorded_at: Utc::now() },
+        );
+        self.save(&links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn snapshot(id: &str) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            short_id: id[..8].to_string(),
+            time: String::new(),
+            hostname: String::new(),
+            paths: Vec::new(),
+            tags: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_contains_snapshot() {
+        let snapshots = vec![snapshot("4f9a2c1e77d0b3a5"), snapshot("a1b2c3d4e5f60718")];
+        assert!(contains_snapshot(&snapshots, "4f9a2c1e77d0b3a5"));
+        assert!(contains_snapshot(&snapshots, "a1b2c3d4"));
+        assert!(!contains_snapshot(&snapshots, "deadbeef"));
+        assert!(!contains_snapshot(&[], "4f9a2c1e"));
+    }
+
+    #[test]
+    fn test_chain_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ChainStore::new(temp_dir.path().join("chain.json"));
+
+        assert!(store.get("app", "local").unwrap().is_none());
+
+        store.record("app", "local", "4f9a2c1e").unwrap();
+        store.record("app", "remote", "a1b2c3d4").unwrap();
+        store.record("app", "local", "b5c6d7e8").unwrap();
+
+        assert_eq!(store.get("app", "local").unwrap().unwrap().snapshot_id, "b5c6d7e8");
+        assert_eq!(store.get("app", "remote").unwrap().unwrap().snapshot_id, "a1b2c3d4");
+        assert!(store.get("other", "local").unwrap().is_none());
+    }
+}
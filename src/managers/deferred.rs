@@ -0,0 +1,147 @@
+//! Work deferred from backups to the maintenance window
+//!
+//! When a service runs over its `time_budget_minutes` in `run`, its optional
+//! phases (retention, snapshot check) are skipped so the remaining services
+//! start sooner. What was skipped is recorded per service and destination
+//! until `maintain` has caught up on it.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// An optional backup phase that can be deferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeferredPhase {
+    /// `restic forget` with the service's retention policy
+    Retention,
+    /// Post-backup snapshot verification
+    Check,
+}
+
+impl fmt::Display for DeferredPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DeferredPhase::Retention => "retention",
+            DeferredPhase::Check => "check",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Phases skipped for one service and destination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeferredWork {
+    pub phases: Vec<DeferredPhase>,
+    pub run_id: String,
+    pub deferred_at: DateTime<Utc>,
+}
+
+/// Deferred work per service and destination, persisted as JSON
+#[derive(Debug, Clone)]
+pub struct DeferredStore {
+    path: PathBuf,
+}
+
+impl DeferredStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default location (~/.local/share/restic-manager/deferred.json)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("deferred.json")
+        } else {
+            PathBuf::from("/tmp/restic-manager-deferred.json")
+        }
+    }
+
+    fn key(service: &str, destination: &str) -> String {
+        format!("{}/{}", service, destination)
+    }
+
+    fn load(&self) -> Result<HashMap<String, DeferredWork>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&self.path).context("Failed to read deferred work")?;
+        serde_json::from_str(&content).context("Failed to parse deferred work")
+    }
+
+    fn save(&self, work: &HashMap<String, DeferredWork>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(work).context("Failed to serialize deferred work")?;
+        fs::write(&self.path, content).context("Failed to write deferred work")
+    }
+
+    /// Work pending for a service on a destination
+    pub fn get(&self, service: &str, destination: &str) -> Result<Option<DeferredWork>> {
+        Ok(self.load()?.remove(&Self::key(service, destination)))
+    }
+
+    /// Record skipped phases (merged with work that is still pending)
+    pub fn defer(&self, service: &str, destination: &str, run_id: &str, phases: &[DeferredPhase]) -> Result<()> {
+        let mut work = self.load()?;
+        let mut entry_phases = work
+            .remove(&Self::key(service, destination))
+            .map(|w| w.phases)
+            .unwrap_or_default();
+        for phase in phases {
+            if !entry_phases.contains(phase) {
+                entry_phases.push(*phase);
+            }
+        }
+
+        work.insert(
+            Self::key(service, destination),
+            DeferredWork { phases: entry_phases, run_id: run_id.to_string(), deferred_at: Utc::now() },
+        );
+        self.save(&work)
+    }
+
+    /// Forget pending work after maintenance caught up on it
+    pub fn clear(&self, service: &str, destination: &str) -> Result<()> {
+        let mut work = self.load()?;
+        if work.remove(&Self::key(service, destination)).is_some() {
+            self.save(&work)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_deferred_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = DeferredStore::new(temp_dir.path().join("deferred.json"));
+
+        assert!(store.get("app", "local").unwrap().is_none());
+
+        store.defer("app", "local", "run-1", &[DeferredPhase::Retention]).unwrap();
+        store
+            .defer("app", "local", "run-2", &[DeferredPhase::Retention, DeferredPhase::Check])
+            .unwrap();
+        store.defer("app", "remote", "run-2", &[DeferredPhase::Check]).unwrap();
+
+        let work = store.get("app", "local").unwrap().unwrap();
+        assert_eq!(work.phases, vec![DeferredPhase::Retention, DeferredPhase::Check]);
+        assert_eq!(work.run_id, "run-2");
+
+        store.clear("app", "local").unwrap();
+        assert!(store.get("app", "local").unwrap().is_none());
+        assert!(store.get("app", "remote").unwrap().is_some());
+    }
+}
@@ -0,0 +1,134 @@
+//! Healthchecks.io heartbeat pings
+//!
+//! Pings are best effort: a failing ping is logged but never fails a backup.
+
+use crate::config::HealthchecksConfig;
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Request bodies are capped (healthchecks.io stores up to 100 KB)
+const MAX_BODY_BYTES: usize = 10_000;
+
+/// Kind of ping sent for a backup run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingSignal {
+    Start,
+    Success,
+    Fail,
+}
+
+impl PingSignal {
+    /// Suffix appended to the check URL
+    fn suffix(&self) -> &'static str {
+        match self {
+            PingSignal::Start => "/start",
+            PingSignal::Success => "",
+            PingSignal::Fail => "/fail",
+        }
+    }
+}
+
+/// Sends heartbeat pings for services mapped to a check
+pub struct HealthchecksClient {
+    config: HealthchecksConfig,
+}
+
+impl HealthchecksClient {
+    pub fn new(config: HealthchecksConfig) -> Self {
+        Self { config }
+    }
+
+    /// URL to ping for a service and signal, if the service has a check
+    pub fn url(&self, service: &str, signal: PingSignal) -> Option<String> {
+        self.config
+            .ping_url(service)
+            .map(|url| format!("{}{}", url, signal.suffix()))
+    }
+
+    /// Ping a service's check (failures are only logged)
+    pub fn ping(&self, service: &str, signal: PingSignal, body: &str) {
+        let Some(url) = self.url(service, signal) else {
+            return;
+        };
+
+        match send_ping(&url, body) {
+            Ok(()) => debug!("Sent {:?} ping for service '{}'", signal, service),
+            Err(e) => warn!("Failed to send healthchecks {:?} ping for '{}': {:#}", signal, service, e),
+        }
+    }
+}
+
+fn send_ping(url: &str, body: &str) -> Result<()> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let mut end = body.len().min(MAX_BODY_BYTES);
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let response = client
+        .post(url)
+        .body(body[..end].to_string())
+        .send()
+        // The URL contains the check UUID or ping key
+        .map_err(|e| e.without_url())
+        .context("Failed to send ping")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Ping failed with status {}", response.status());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_urls() {
+        let mut config = HealthchecksConfig {
+            ping_key: "key123".to_string(),
+            ..Default::default()
+        };
+        config
+            .checks
+            .insert("postgres".to_string(), "5bf66975-d4c7-4bf5-bcc8-b8d8a82ea278".to_string());
+        config.checks.insert("nextcloud".to_string(), "nextcloud-backup".to_string());
+        let client = HealthchecksClient::new(config);
+
+        assert_eq!(
+            client.url("postgres", PingSignal::Start).as_deref(),
+            Some("https://hc-ping.com/5bf66975-d4c7-4bf5-bcc8-b8d8a82ea278/start")
+        );
+        assert_eq!(
+            client.url("nextcloud", PingSignal::Success).as_deref(),
+            Some("https://hc-ping.com/key123/nextcloud-backup")
+        );
+        assert_eq!(
+            client.url("nextcloud", PingSignal::Fail).as_deref(),
+            Some("https://hc-ping.com/key123/nextcloud-backup/fail")
+        );
+        assert_eq!(client.url("unmapped", PingSignal::Start), None);
+    }
+
+    #[test]
+    fn test_ping_url_self_hosted() {
+        let mut config = HealthchecksConfig {
+            base_url: "https://hc.example.com/ping/".to_string(),
+            ..Default::default()
+        };
+        config
+            .checks
+            .insert("app".to_string(), "5BF66975-D4C7-4BF5-BCC8-B8D8A82EA278".to_string());
+
+        assert_eq!(
+            config.ping_url("app").as_deref(),
+            Some("https://hc.example.com/ping/5BF66975-D4C7-4BF5-BCC8-B8D8A82EA278")
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Backup run history
+//!
+//! Every backup attempt (per service and destination) is recorded, so trends
+//! can be shown without querying restic. Records go to a `StateStore`: by
+//! default a JSON lines file, or an SQLite database (`state_backend`).
+
+mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+use anyhow::{Context, Result};
+use crate::config::{GlobalConfig, StateBackend};
+use crate::managers::outcome::{RunOutcome, RunTrigger};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// A single backup attempt to one destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub service: String,
+    pub destination: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    pub success: bool,
+    /// Outcome of the run (missing in records written before outcomes existed)
+    #[serde(default)]
+    pub outcome: Option<RunOutcome>,
+    /// Bytes added to the repository (from restic's backup summary)
+    #[serde(default)]
+    pub data_added: Option<u64>,
+    #[serde(default)]
+    pub snapshot_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Id shared by all records (and notifications) of one run
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Per-run log directory with the run's report and restic/hook output
+    #[serde(default)]
+    pub log_dir: Option<PathBuf>,
+    /// What started the run (missing in records written before triggers existed)
+    #[serde(default)]
+    pub trigger: Option<RunTrigger>,
+    /// Why the run was started (`run --reason`, a webhook's note)
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Why the retention policy failed on an otherwise successful run
+    #[serde(default)]
+    pub retention_error: Option<String>,
+}
+
+/// Storage of run history
+///
+/// Implementations must be safe to use from several processes at once (cron
+/// runs overlap with serve mode). A store shared between hosts can be added
+/// as another implementation without touching the callers.
+pub trait StateStore: Send + Sync {
+    /// Append a record to the history
+    fn append(&self, record: &RunRecord) -> Result<()>;
+
+    /// Load all records (oldest first)
+    fn load_all(&self) -> Result<Vec<RunRecord>>;
+
+    /// Most recent records for a service/destination (oldest first)
+    fn recent(&self, service: &str, destination: &str, limit: usize) -> Result<Vec<RunRecord>> {
+        let matching: Vec<RunRecord> = self
+            .load_all()?
+            .into_iter()
+            .filter(|r| r.service == service && r.destination == destination)
+            .collect();
+
+        let skip = matching.len().saturating_sub(limit);
+        Ok(matching.into_iter().skip(skip).collect())
+    }
+}
+
+/// Open the history store configured by `state_backend` and `state_path`
+pub fn open_store(global: &GlobalConfig) -> Box<dyn StateStore> {
+    match global.state_backend {
+        StateBackend::File => Box::new(HistoryStore::new(
+            global.state_path.clone().unwrap_or_else(HistoryStore::default_path),
+        )),
+        StateBackend::Sqlite => Box::new(SqliteStore::new(
+            global.state_path.clone().unwrap_or_else(SqliteStore::default_path),
+        )),
+    }
+}
+
+/// Append-only JSON-lines store for run records
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    /// Create a store backed by the given file
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default history location (~/.local/share/restic-manager/history.jsonl)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("history.jsonl")
+        } else {
+            PathBuf::from("/tmp/restic-manager-history.jsonl")
+        }
+    }
+
+    /// Path of the history file
+    #[allow(dead_code)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+}
+
+impl StateStore for HistoryStore {
+    fn append(&self, record: &RunRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let line = serde_json::to_string(record)
+            .context("Failed to serialize run record")?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open history file")?;
+        writeln!(file, "{}", line).context("Failed to write history file")?;
+
+        Ok(())
+    }
+
+    /// Unreadable lines are skipped
+    fn load_all(&self) -> Result<Vec<RunRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)
+            .context("Failed to read history file")?;
+
+        let mut records = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!("Skipping invalid history line {}: {}", index + 1, e),
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+/// Render values as a unicode sparkline scaled between min and max
+pub fn sparkline(values: &[u64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let (min, max) = match (values.iter().min(), values.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return String::new(),
+    };
+
+    let range = max - min;
+    values
+        .iter()
+        .map(|&v| {
+            if range == 0 {
+                BARS[BARS.len() / 2]
+            } else {
+                let index = ((v - min) as f64 / range as f64 * (BARS.len() - 1) as f64).round();
+                BARS[index as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    pub(super) fn record(service: &str, destination: &str, duration_secs: u64) -> RunRecord {
+        RunRecord {
+            service: service.to_string(),
+            destination: destination.to_string(),
+            started_at: Utc::now(),
+            duration_secs,
+            success: true,
+            outcome: Some(RunOutcome::Success),
+            data_added: Some(duration_secs * 1024),
+            snapshot_id: None,
+            error: None,
+            run_id: None,
+            log_dir: None,
+            trigger: Some(RunTrigger::Scheduled),
+            reason: None,
+            retention_error: None,
+        }
+    }
+
+    #[test]
+    fn test_sparkline_scaling() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[1, 8]), "▁█");
+        assert_eq!(sparkline(&[5, 5, 5]), "▅▅▅");
+        assert_eq!(sparkline(&[0, 50, 100]).chars().count(), 3);
+    }
+
+    #[test]
+    fn test_history_append_and_recent() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = HistoryStore::new(temp_dir.path().join("history.jsonl"));
+
+        for i in 0..20 {
+            store.append(&record("postgres", "local", i)).unwrap();
+        }
+        store.append(&record("postgres", "remote", 99)).unwrap();
+        store.append(&record("other", "local", 42)).unwrap();
+
+        let recent = store.recent("postgres", "local", 14).unwrap();
+        assert_eq!(recent.len(), 14);
+        assert_eq!(recent.first().unwrap().duration_secs, 6);
+        assert_eq!(recent.last().unwrap().duration_secs, 19);
+    }
+
+    #[test]
+    fn test_history_missing_file_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = HistoryStore::new(temp_dir.path().join("missing.jsonl"));
+        assert!(store.load_all().unwrap().is_empty());
+    }
+}
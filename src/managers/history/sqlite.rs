@@ -0,0 +1,144 @@
+//! SQLite history store
+//!
+//! Records are kept as JSON (so new `RunRecord` fields need no migration)
+//! next to indexed service and destination columns. A connection is opened
+//! per operation; SQLite's locking lets cron runs and serve mode share the
+//! database.
+
+use super::{RunRecord, StateStore};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long to wait for another process holding the write lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    service TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    record TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_by_repository ON runs (service, destination, id);
+";
+
+/// Run records in an SQLite database
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    /// Create a store backed by the given database file
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default database location (~/.local/share/restic-manager/state.db)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("state.db")
+        } else {
+            PathBuf::from("/tmp/restic-manager-state.db")
+        }
+    }
+
+    /// Path of the database file
+    #[allow(dead_code)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(&self.path)
+            .with_context(|| format!("Failed to open state database {}", self.path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA).context("Failed to create state database schema")?;
+        Ok(conn)
+    }
+
+    /// Decode rows of `record` JSON, skipping unreadable ones
+    fn decode(rows: Vec<(i64, String)>) -> Vec<RunRecord> {
+        rows.into_iter()
+            .filter_map(|(id, json)| match serde_json::from_str(&json) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping invalid history row {}: {}", id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn append(&self, record: &RunRecord) -> Result<()> {
+        let json = serde_json::to_string(record).context("Failed to serialize run record")?;
+        self.connect()?
+            .execute(
+                "INSERT INTO runs (service, destination, started_at, record) VALUES (?1, ?2, ?3, ?4)",
+                params![record.service, record.destination, record.started_at.to_rfc3339(), json],
+            )
+            .context("Failed to write run record")?;
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<RunRecord>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT id, record FROM runs ORDER BY id")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read run history")?;
+        Ok(Self::decode(rows))
+    }
+
+    fn recent(&self, service: &str, destination: &str, limit: usize) -> Result<Vec<RunRecord>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, record FROM runs WHERE service = ?1 AND destination = ?2 ORDER BY id DESC LIMIT ?3",
+        )?;
+        let mut rows = stmt
+            .query_map(params![service, destination, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read run history")?;
+        rows.reverse();
+        Ok(Self::decode(rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::managers::history::tests::record;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_store_append_and_recent() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SqliteStore::new(temp_dir.path().join("state").join("state.db"));
+        assert!(store.load_all().unwrap().is_empty());
+
+        for i in 0..20 {
+            store.append(&record("postgres", "local", i)).unwrap();
+        }
+        store.append(&record("postgres", "remote", 99)).unwrap();
+
+        let recent = store.recent("postgres", "local", 14).unwrap();
+        assert_eq!(recent.len(), 14);
+        assert_eq!(recent.first().unwrap().duration_secs, 6);
+        assert_eq!(recent.last().unwrap().duration_secs, 19);
+
+        // A second handle (another process) sees the same history
+        let other = SqliteStore::new(store.path());
+        assert_eq!(other.load_all().unwrap().len(), 21);
+    }
+}
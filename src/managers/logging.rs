@@ -7,6 +7,7 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
+use crate::utils::output::{self, Verbosity};
 use tracing::Level;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
@@ -98,13 +99,14 @@ pub fn init_logging(config: &LoggingConfig) -> Result<LogGuard> {
         .with_thread_names(false)
         .with_file(false)
         .with_line_number(false)
-        .with_span_events(FmtSpan::NONE)
+        // Closing spans log how long each backup phase took
+        .with_span_events(FmtSpan::CLOSE)
         .with_filter(level_filter(config.log_level));
 
-    // Console layer: INFO level, concise format
+    // Console layer: INFO level (or per --quiet/--verbose), concise format
     let console_layer = fmt::layer()
         .with_writer(std::io::stderr)
-        .with_ansi(true) // Colors on console
+        .with_ansi(output::color())
         .with_target(false)
         .with_level(true)
         .with_thread_ids(false)
@@ -112,7 +114,7 @@ pub fn init_logging(config: &LoggingConfig) -> Result<LogGuard> {
         .with_file(false)
         .with_line_number(false)
         .with_span_events(FmtSpan::NONE)
-        .with_filter(level_filter(Level::INFO));
+        .with_filter(level_filter(console_level()));
 
     // Combine layers with base subscriber
     tracing_subscriber::registry()
@@ -131,15 +133,25 @@ pub fn init_logging(config: &LoggingConfig) -> Result<LogGuard> {
 /// Initialize simple console-only logging (for when config isn't available)
 pub fn init_console_logging() {
     let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
+        .unwrap_or_else(|_| EnvFilter::new(console_level().to_string()));
 
     tracing_subscriber::fmt()
         .with_env_filter(filter)
+        .with_ansi(output::color())
         .with_target(false)
         .with_level(true)
         .init();
 }
 
+/// Console log level for the global --quiet/--verbose flags
+fn console_level() -> Level {
+    match output::verbosity() {
+        Verbosity::Quiet => Level::ERROR,
+        Verbosity::Normal => Level::INFO,
+        Verbosity::Verbose => Level::DEBUG,
+    }
+}
+
 /// Create a level filter for tracing layers
 fn level_filter(level: Level) -> EnvFilter {
     EnvFilter::try_from_default_env()
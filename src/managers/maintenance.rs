@@ -0,0 +1,275 @@
+//! Repository maintenance runs
+//!
+//! `restic-manager maintain` bundles the housekeeping a repository needs
+//! every now and then (e.g. monthly from cron) into one run per destination:
+//! remove stale locks, check integrity, forget old snapshots (dry run first),
+//! prune with tuned options and report the resulting repository size.
+//! A failed integrity check stops the run before anything is deleted.
+//! With `prune_delay_days`, pruning waits until the delay has passed since
+//! the oldest unpruned forget (see [`crate::managers::prune_delay`]).
+
+use crate::config::{Destination, GlobalConfig, ResolvedServiceConfig};
+use crate::managers::notification::format_duration;
+use crate::managers::prune_delay::PendingPruneStore;
+use crate::utils::restic::{self, CheckResult, PruneOptions, ResticEnv};
+use chrono::Utc;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Result of one maintenance step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Ok,
+    Skipped,
+    Failed,
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            StepStatus::Ok => "ok",
+            StepStatus::Skipped => "skipped",
+            StepStatus::Failed => "FAILED",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// A maintenance step and what it did
+#[derive(Debug, Clone)]
+pub struct MaintenanceStep {
+    pub name: &'static str,
+    pub status: StepStatus,
+    pub detail: String,
+}
+
+/// Report of a maintenance run against one destination
+#[derive(Debug, Clone)]
+pub struct MaintenanceReport {
+    pub service: String,
+    pub destination: String,
+    pub steps: Vec<MaintenanceStep>,
+    pub duration_secs: u64,
+}
+
+impl MaintenanceReport {
+    /// Whether no step failed
+    pub fn success(&self) -> bool {
+        self.steps.iter().all(|step| step.status != StepStatus::Failed)
+    }
+
+    fn push(&mut self, name: &'static str, status: StepStatus, detail: impl Into<String>) {
+        self.steps.push(MaintenanceStep {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl fmt::Display for MaintenanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Maintenance of '{}' on '{}' ({}):",
+            self.service,
+            self.destination,
+            format_duration(self.duration_secs)
+        )?;
+        for step in &self.steps {
+            write!(f, "  {:<16} {:<8}", step.name, step.status)?;
+            let mut lines = step.detail.lines();
+            if let Some(first) = lines.next() {
+                write!(f, " {}", first)?;
+            }
+            writeln!(f)?;
+            for line in lines {
+                writeln!(f, "  {:<25} {}", "", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run maintenance against one destination of a service
+pub fn run_maintenance(
+    global: &GlobalConfig,
+    service: &ResolvedServiceConfig,
+    dest_name: &str,
+    destination: &Destination,
+    options: &PruneOptions,
+    pending: &PendingPruneStore,
+) -> MaintenanceReport {
+    let start = Instant::now();
+    let mut report = MaintenanceReport {
+        service: service.name.clone(),
+        destination: dest_name.to_string(),
+        steps: Vec::new(),
+        duration_secs: 0,
+    };
+
+    run_steps(global, service, dest_name, destination, options, pending, &mut report);
+
+    report.duration_secs = start.elapsed().as_secs();
+    if report.success() {
+        info!("Maintenance of '{}' on '{}' completed", service.name, dest_name);
+    } else {
+        warn!("Maintenance of '{}' on '{}' failed", service.name, dest_name);
+    }
+    report
+}
+
+fn run_steps(
+    global: &GlobalConfig,
+    service: &ResolvedServiceConfig,
+    dest_name: &str,
+    destination: &Destination,
+    options: &PruneOptions,
+    pending: &PendingPruneStore,
+    report: &mut MaintenanceReport,
+) {
+    let timeout = Duration::from_secs(service.timeout_seconds);
+    let short_timeout = Duration::from_secs(60);
+
+    let repo_url = restic::build_repository_url(destination, &service.name, None);
+    let env = match ResticEnv::for_destination(global, dest_name, destination, &repo_url) {
+        Ok(env) => env
+            .with_host(service.hostname.as_deref())
+            .with_limits(service.limit_upload_kbps, service.limit_download_kbps),
+        Err(e) => {
+            report.push("connect", StepStatus::Failed, format!("{:#}", e));
+            return;
+        }
+    };
+
+    // Only removes locks whose process is gone
+    match restic::unlock_repository(&env, short_timeout) {
+        Ok(()) => report.push("unlock", StepStatus::Ok, "stale locks removed"),
+        Err(e) => report.push("unlock", StepStatus::Failed, format!("{:#}", e)),
+    }
+
+    match restic::check_repository(&env, false, timeout).and_then(|result| result.ensure_passed()) {
+        Ok(CheckResult::Warnings(warnings)) => {
+            report.push("check", StepStatus::Ok, format!("no errors found, {} warning(s)", warnings.len()))
+        }
+        Ok(_) => report.push("check", StepStatus::Ok, "no errors found"),
+        Err(e) => {
+            report.push("check", StepStatus::Failed, format!("{:#}", e));
+            report.push("forget", StepStatus::Skipped, "repository check failed");
+            report.push("prune", StepStatus::Skipped, "repository check failed");
+            return;
+        }
+    }
+
+    if !destination.allows_deletion(global) {
+        let reason = "append-only destination (run maintenance from a trusted host)";
+        report.push("forget", StepStatus::Skipped, reason);
+        report.push("prune", StepStatus::Skipped, reason);
+    } else {
+        match restic::forget(&env, &service.retention, true, timeout) {
+            Ok(0) => report.push("forget (dry run)", StepStatus::Ok, "nothing to forget"),
+            Ok(count) => {
+                report.push("forget (dry run)", StepStatus::Ok, format!("{} snapshot(s) to forget", count));
+                match restic::forget(&env, &service.retention, false, timeout) {
+                    Ok(removed) => {
+                        report.push("forget", StepStatus::Ok, format!("{} snapshot(s) forgotten", removed));
+                        if global.prune_delay_days > 0 && removed > 0 {
+                            if let Err(e) = pending.record(&service.name, dest_name, removed) {
+                                warn!("Failed to record pending prune: {}", e);
+                            }
+                        }
+                    }
+                    Err(e) => report.push("forget", StepStatus::Failed, format!("{:#}", e)),
+                }
+            }
+            Err(e) => report.push("forget (dry run)", StepStatus::Failed, format!("{:#}", e)),
+        }
+
+        if let Some(reason) = prune_postponed(global, pending, &service.name, dest_name) {
+            report.push("prune", StepStatus::Skipped, reason);
+        } else {
+            match restic::prune(&env, options, timeout) {
+                Ok(summary) => {
+                    report.push("prune", StepStatus::Ok, summary);
+                    if let Err(e) = pending.clear(&service.name, dest_name) {
+                        warn!("Failed to clear pending prune: {}", e);
+                    }
+                }
+                Err(e) => report.push("prune", StepStatus::Failed, format!("{:#}", e)),
+            }
+        }
+    }
+
+    match restic::get_stats(&env, short_timeout) {
+        Ok(stats) => report.push(
+            "stats",
+            StepStatus::Ok,
+            format!("total size {}", restic::format_bytes(stats.total_size)),
+        ),
+        Err(e) => report.push("stats", StepStatus::Failed, format!("{:#}", e)),
+    }
+}
+
+/// Why pruning has to wait for `prune_delay_days`, if it does
+fn prune_postponed(global: &GlobalConfig, pending: &PendingPruneStore, service: &str, dest_name: &str) -> Option<String> {
+    let delay = global.prune_delay_days;
+    if delay == 0 {
+        return None;
+    }
+
+    match pending.get(service, dest_name) {
+        Ok(Some(prune)) if !prune.is_due(delay, Utc::now()) => Some(format!(
+            "{} forgotten snapshot(s) recoverable until {} (prune_delay_days = {})",
+            prune.snapshots,
+            prune.due_at(delay).format("%Y-%m-%d %H:%M UTC"),
+            delay
+        )),
+        Ok(_) => None,
+        // Rather keep data than prune what may still be in its delay
+        Err(e) => Some(format!("pending prunes unreadable: {:#}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_display_and_success() {
+        let mut report = MaintenanceReport {
+            service: "app".to_string(),
+            destination: "local".to_string(),
+            steps: Vec::new(),
+            duration_secs: 65,
+        };
+        report.push("check", StepStatus::Ok, "no errors found");
+        report.push("prune", StepStatus::Skipped, "append-only destination");
+        assert!(report.success());
+
+        report.push("stats", StepStatus::Failed, "connection refused\nretry later");
+        assert!(!report.success());
+
+        let text = report.to_string();
+        assert!(text.starts_with("Maintenance of 'app' on 'local' (1m 5s):"));
+        assert!(text.contains("check"));
+        assert!(text.contains("FAILED"));
+        assert!(text.contains("retry later"));
+    }
+
+    #[test]
+    fn test_prune_postponed() {
+        let temp_dir = TempDir::new().unwrap();
+        let pending = PendingPruneStore::new(temp_dir.path().join("pending-prune.json"));
+        let mut global = GlobalConfig::default();
+
+        pending.record("app", "local", 3).unwrap();
+        assert!(prune_postponed(&global, &pending, "app", "local").is_none());
+
+        global.prune_delay_days = 7;
+        let reason = prune_postponed(&global, &pending, "app", "local").unwrap();
+        assert!(reason.contains("3 forgotten snapshot(s)"));
+        assert!(prune_postponed(&global, &pending, "app", "remote").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! Backup manifests - the effective scope of a backup run
+//!
+//! After each run the manifest (paths, volumes, excludes, hooks and the number
+//! of files restic processed) is saved per service and compared with the
+//! previous one, so silent scope changes are logged and reported.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Drop in processed files (relative to the previous run) that counts as drift
+pub const FILE_COUNT_DROP_THRESHOLD: f64 = 0.5;
+
+/// Effective scope of one backup run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Paths that existed and were backed up
+    pub paths: Vec<String>,
+    /// Configured paths that did not exist at backup time
+    #[serde(default)]
+    pub missing_paths: Vec<String>,
+    pub volumes: Vec<String>,
+    pub excludes: Vec<String>,
+    pub pre_backup_hooks: Vec<String>,
+    pub post_backup_hooks: Vec<String>,
+    /// Files processed by restic (first successful destination)
+    #[serde(default)]
+    pub files_processed: Option<u64>,
+}
+
+/// Compare two manifests and describe what changed
+pub fn diff(previous: &BackupManifest, current: &BackupManifest) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    diff_list("path", &previous.paths, &current.paths, &mut changes);
+    diff_list("volume", &previous.volumes, &current.volumes, &mut changes);
+    diff_list("exclude", &previous.excludes, &current.excludes, &mut changes);
+    diff_list("pre-backup hook", &previous.pre_backup_hooks, &current.pre_backup_hooks, &mut changes);
+    diff_list("post-backup hook", &previous.post_backup_hooks, &current.post_backup_hooks, &mut changes);
+
+    for path in &current.missing_paths {
+        if !previous.missing_paths.contains(path) {
+            changes.push(format!("Configured path no longer exists: {}", path));
+        }
+    }
+
+    if let (Some(before), Some(after)) = (previous.files_processed, current.files_processed) {
+        if before > 0 && (after as f64) < before as f64 * (1.0 - FILE_COUNT_DROP_THRESHOLD) {
+            let drop = 100.0 - (after as f64 / before as f64 * 100.0);
+            changes.push(format!(
+                "Files processed dropped by {:.0}% ({} -> {})",
+                drop, before, after
+            ));
+        }
+    }
+
+    changes
+}
+
+fn diff_list(kind: &str, previous: &[String], current: &[String], changes: &mut Vec<String>) {
+    for item in previous {
+        if !current.contains(item) {
+            changes.push(format!("Removed {}: {}", kind, item));
+        }
+    }
+    for item in current {
+        if !previous.contains(item) {
+            changes.push(format!("Added {}: {}", kind, item));
+        }
+    }
+}
+
+/// Stores the last manifest per service as JSON files
+#[derive(Debug, Clone)]
+pub struct ManifestStore {
+    dir: PathBuf,
+}
+
+impl ManifestStore {
+    /// Create a store in the given directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default location (~/.local/share/restic-manager/manifests)
+    pub fn default_dir() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("manifests")
+        } else {
+            PathBuf::from("/tmp/restic-manager-manifests")
+        }
+    }
+
+    fn path_for(&self, service: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", service))
+    }
+
+    /// Load the last saved manifest for a service
+    pub fn load(&self, service: &str) -> Result<Option<BackupManifest>> {
+        let path = self.path_for(service);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .context("Failed to read backup manifest")?;
+        let manifest = serde_json::from_str(&content)
+            .context("Failed to parse backup manifest")?;
+        Ok(Some(manifest))
+    }
+
+    /// Save the manifest for a service, replacing the previous one
+    pub fn save(&self, service: &str, manifest: &BackupManifest) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let content = serde_json::to_string_pretty(manifest)
+            .context("Failed to serialize backup manifest")?;
+        fs::write(self.path_for(service), content)
+            .context("Failed to write backup manifest")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn manifest() -> BackupManifest {
+        BackupManifest {
+            paths: vec!["/docker/app".to_string(), "/docker/config".to_string()],
+            volumes: vec!["app_data".to_string()],
+            excludes: vec!["*.log".to_string()],
+            pre_backup_hooks: vec!["Dump database".to_string()],
+            files_processed: Some(1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_identical_manifests() {
+        assert!(diff(&manifest(), &manifest()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_detects_scope_changes() {
+        let previous = manifest();
+        let mut current = manifest();
+        current.paths.retain(|p| p != "/docker/config");
+        current.missing_paths.push("/docker/config".to_string());
+        current.excludes.push("*.db".to_string());
+        current.files_processed = Some(100);
+
+        let changes = diff(&previous, &current);
+        assert!(changes.contains(&"Removed path: /docker/config".to_string()));
+        assert!(changes.contains(&"Configured path no longer exists: /docker/config".to_string()));
+        assert!(changes.contains(&"Added exclude: *.db".to_string()));
+        assert!(changes.iter().any(|c| c.starts_with("Files processed dropped by 90%")));
+    }
+
+    #[test]
+    fn test_diff_ignores_small_file_count_changes() {
+        let previous = manifest();
+        let mut current = manifest();
+        current.files_processed = Some(800);
+
+        assert!(diff(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_store_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ManifestStore::new(temp_dir.path());
+
+        assert!(store.load("app").unwrap().is_none());
+        store.save("app", &manifest()).unwrap();
+        assert_eq!(store.load("app").unwrap().unwrap(), manifest());
+    }
+}
@@ -1,3 +1,17 @@
+pub mod backoff;
 pub mod backup;
+pub mod chain;
+pub mod deferred;
+pub mod healthchecks;
+pub mod history;
 pub mod logging;
+pub mod maintenance;
+pub mod manifest;
 pub mod notification;
+pub mod outcome;
+pub mod prune_delay;
+pub mod run_logs;
+pub mod run_report;
+pub mod server;
+pub mod snapshot_cache;
+pub mod verification;
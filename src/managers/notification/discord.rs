@@ -0,0 +1,269 @@
+//! Discord webhook channel
+//!
+//! With `discord_thread_per_run` the first notification of a run creates a
+//! thread (a post, since webhooks can only create threads in forum channels)
+//! and later notifications of the same run are posted into it.
+
+use super::{format_duration, post_json, post_json_response, Notification, NotificationChannel};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Sends notifications as Discord embeds
+pub struct DiscordChannel {
+    webhook_url: String,
+    thread_per_run: bool,
+    /// Thread created for each run (run id -> thread id)
+    threads: Mutex<HashMap<String, String>>,
+}
+
+/// Discord webhook payload
+#[derive(Debug, Serialize)]
+struct DiscordPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avatar_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    embeds: Vec<DiscordEmbed>,
+    /// Creates a forum post (thread) with this name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordEmbed {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    color: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<DiscordField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footer: Option<DiscordFooter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordFooter {
+    text: String,
+}
+
+impl DiscordChannel {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            webhook_url: webhook_url.to_string(),
+            thread_per_run: false,
+            threads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Group the notifications of each run into a thread
+    pub fn with_thread_per_run(mut self, enabled: bool) -> Self {
+        self.thread_per_run = enabled;
+        self
+    }
+
+    /// Webhook URL with an extra query parameter
+    fn url_with_query(&self, query: &str) -> String {
+        let separator = if self.webhook_url.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", self.webhook_url, separator, query)
+    }
+
+    /// Send into the run's thread, creating it with the first message
+    fn send_to_thread(&self, mut payload: DiscordPayload, run_id: &str) -> Result<()> {
+        // Held while creating the thread so concurrent sends don't create two
+        let mut threads = self.threads.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(thread_id) = threads.get(run_id) {
+            let url = self.url_with_query(&format!("thread_id={}", thread_id));
+            return post_json(&url, &payload, self.name());
+        }
+
+        payload.thread_name = Some(format!(
+            "Backup run {} ({})",
+            run_id,
+            chrono::Local::now().format("%Y-%m-%d")
+        ));
+        let body = post_json_response(&self.url_with_query("wait=true"), &payload, self.name())?;
+
+        // The first message of a new post lives in the thread, so its channel is the thread
+        let thread_id = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|message| message["channel_id"].as_str().map(String::from));
+        if let Some(thread_id) = thread_id {
+            threads.insert(run_id.to_string(), thread_id);
+        }
+
+        Ok(())
+    }
+
+    /// Build Discord webhook payload
+    fn build_payload(&self, notification: &Notification) -> DiscordPayload {
+        let (color, _, _) = notification.style();
+
+        let mut fields = vec![
+            DiscordField {
+                name: "Service".to_string(),
+                value: notification.service_name.clone(),
+                inline: true,
+            },
+        ];
+
+        if let Some(ref dest) = notification.destination {
+            fields.push(DiscordField {
+                name: "Destination".to_string(),
+                value: dest.clone(),
+                inline: true,
+            });
+        }
+
+        if let Some(duration) = notification.duration_secs {
+            fields.push(DiscordField {
+                name: "Duration".to_string(),
+                value: format_duration(duration),
+                inline: true,
+            });
+        }
+
+        if let Some(error_display) = notification.truncated_error() {
+            fields.push(DiscordField {
+                name: "Error".to_string(),
+                value: format!("```\n{}\n```", error_display),
+                inline: false,
+            });
+        }
+
+        if let Some(ref hint) = notification.hint {
+            fields.push(DiscordField {
+                name: "Hint".to_string(),
+                value: hint.clone(),
+                inline: false,
+            });
+        }
+
+        let timestamp = Some(chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string());
+
+        let embed = DiscordEmbed {
+            title: notification.title(),
+            description: Some(notification.message.clone()),
+            color: color.as_decimal(),
+            fields,
+            footer: Some(DiscordFooter {
+                text: match notification.run_id {
+                    Some(ref run_id) => format!("restic-manager \u{2022} run {}", run_id),
+                    None => "restic-manager".to_string(),
+                },
+            }),
+            timestamp,
+        };
+
+        DiscordPayload {
+            username: Some("Restic Manager".to_string()),
+            avatar_url: None,
+            content: None,
+            embeds: vec![embed],
+            thread_name: None,
+        }
+    }
+}
+
+impl NotificationChannel for DiscordChannel {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let payload = self.build_payload(notification);
+
+        match notification.run_id {
+            Some(ref run_id) if self.thread_per_run => self.send_to_thread(payload, run_id),
+            _ => post_json(&self.webhook_url, &payload, self.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyEvent;
+    use crate::managers::notification::NotificationColor;
+    use crate::managers::outcome::RunOutcome;
+
+    #[test]
+    fn test_build_failure_payload() {
+        let channel = DiscordChannel::new("https://discord.com/api/webhooks/test");
+
+        let notification = Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "postgres".to_string(),
+            destination: Some("local".to_string()),
+            message: "Backup failed".to_string(),
+            error: Some("Connection refused".to_string()),
+            duration_secs: Some(120),
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: Some("3f9a2c1b".to_string()),
+        };
+
+        let payload = channel.build_payload(&notification);
+
+        assert_eq!(payload.embeds.len(), 1);
+        assert!(payload.thread_name.is_none());
+        assert!(payload.embeds[0].footer.as_ref().unwrap().text.ends_with("run 3f9a2c1b"));
+        assert!(payload.embeds[0].title.contains("Failure"));
+        assert_eq!(payload.embeds[0].color, NotificationColor::Failure.as_decimal());
+        assert!(payload.embeds[0].fields.iter().any(|f| f.name == "Service" && f.value == "postgres"));
+        assert!(payload.embeds[0].fields.iter().any(|f| f.name == "Destination" && f.value == "local"));
+        assert!(payload.embeds[0].fields.iter().any(|f| f.name == "Duration" && f.value == "2m"));
+        assert!(payload.embeds[0].fields.iter().any(|f| f.name == "Error"));
+    }
+
+    #[test]
+    fn test_build_timeout_payload() {
+        let channel = DiscordChannel::new("https://discord.com/api/webhooks/test");
+
+        let notification = Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "postgres".to_string(),
+            destination: Some("local".to_string()),
+            message: "Backup timed out".to_string(),
+            error: Some("Command timed out after 3600s".to_string()),
+            duration_secs: Some(3600),
+            outcome: Some(RunOutcome::Timeout),
+            hint: Some("Consider raising timeout_seconds".to_string()),
+            run_id: None,
+        };
+
+        let payload = channel.build_payload(&notification);
+
+        assert!(payload.embeds[0].title.contains("Timeout"));
+        assert_eq!(payload.embeds[0].color, NotificationColor::Timeout.as_decimal());
+        assert!(payload.embeds[0].fields.iter().any(|f| f.name == "Hint"));
+    }
+
+    #[test]
+    fn test_thread_url_query() {
+        let channel = DiscordChannel::new("https://discord.com/api/webhooks/1/abc");
+        assert_eq!(
+            channel.url_with_query("wait=true"),
+            "https://discord.com/api/webhooks/1/abc?wait=true"
+        );
+
+        let channel = DiscordChannel::new("https://discord.com/api/webhooks/1/abc?thread_id=9");
+        assert_eq!(
+            channel.url_with_query("wait=true"),
+            "https://discord.com/api/webhooks/1/abc?thread_id=9&wait=true"
+        );
+    }
+}
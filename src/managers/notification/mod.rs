@@ -0,0 +1,731 @@
+//! Notification manager
+//!
+//! Sends notifications for backup events to every configured channel
+//! (Discord, Slack, Telegram, generic JSON webhook, email via sendmail).
+//! Rate limiting is tracked per channel, so a channel that failed is retried
+//! on the next event.
+
+mod discord;
+mod sendmail;
+mod slack;
+mod telegram;
+mod webhook;
+
+pub use discord::DiscordChannel;
+pub use sendmail::SendmailChannel;
+pub use slack::SlackChannel;
+pub use telegram::TelegramChannel;
+pub use webhook::WebhookChannel;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{NotificationConfig, NotifyEvent};
+use crate::managers::backoff::FailureStreak;
+use crate::managers::outcome::RunOutcome;
+
+/// A place notifications are delivered to (chat service, webhook, ...)
+pub trait NotificationChannel: Send + Sync {
+    /// Channel name used in logs
+    fn name(&self) -> &str;
+
+    /// Deliver a notification
+    fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Notification manager fanning out events to all channels
+pub struct NotificationManager {
+    config: NotificationConfig,
+    cache_path: PathBuf,
+    channels: Vec<Box<dyn NotificationChannel>>,
+    /// Root of the per-run log directories, pointed to by failure notifications
+    run_logs_root: Option<PathBuf>,
+}
+
+/// Discord embed color codes (decimal)
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum NotificationColor {
+    /// Red - for failures
+    Failure = 15158332,    // #E74C3C
+    /// Orange - for warnings
+    Warning = 15105570,    // #E67E22
+    /// Yellow - for long-running operations
+    LongRunning = 16776960, // #FFFF00
+    /// Green - for success
+    Success = 3066993,     // #2ECC71
+    /// Purple - for timeouts
+    Timeout = 10181046,    // #9B59B6
+    /// Grey - for cancelled runs
+    Cancelled = 9807270,   // #95A5A6
+    /// Blue - for info
+    Info = 3447003,        // #3498DB
+}
+
+impl NotificationColor {
+    fn as_decimal(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Hex color string (e.g. `#E74C3C`)
+    fn as_hex(&self) -> String {
+        format!("#{:06X}", self.as_decimal())
+    }
+}
+
+/// Notification payload to send
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event_type: NotifyEvent,
+    pub service_name: String,
+    pub destination: Option<String>,
+    pub message: String,
+    pub error: Option<String>,
+    pub duration_secs: Option<u64>,
+    /// Outcome of a failed run (timeout/cancelled are shown differently)
+    pub outcome: Option<RunOutcome>,
+    /// Suggestion on how to fix the problem
+    pub hint: Option<String>,
+    /// Short id shared by all notifications of one run
+    pub run_id: Option<String>,
+}
+
+impl Notification {
+    /// Color, emoji and label used to present this notification
+    fn style(&self) -> (NotificationColor, &'static str, String) {
+        match (&self.event_type, self.outcome) {
+            (NotifyEvent::Failure, Some(RunOutcome::Timeout)) => {
+                (NotificationColor::Timeout, "\u{23F1}\u{FE0F}", "Timeout".to_string()) // Stopwatch
+            }
+            (NotifyEvent::Failure, Some(RunOutcome::Cancelled)) => {
+                (NotificationColor::Cancelled, "\u{1F6AB}", "Cancelled".to_string()) // No entry sign
+            }
+            (event, _) => {
+                let (color, emoji) = match event {
+                    NotifyEvent::Failure => (NotificationColor::Failure, "\u{274C}"), // Red X
+                    NotifyEvent::Warning => (NotificationColor::Warning, "\u{26A0}\u{FE0F}"), // Warning
+                    NotifyEvent::LongRunning => (NotificationColor::LongRunning, "\u{23F0}"), // Alarm clock
+                    NotifyEvent::Success => (NotificationColor::Success, "\u{2705}"), // Green check
+                };
+                (color, emoji, format!("{:?}", event))
+            }
+        }
+    }
+
+    /// Title line, e.g. "❌ Restic Manager: Failure"
+    fn title(&self) -> String {
+        let (_, emoji, label) = self.style();
+        format!("{} Restic Manager: {}", emoji, label)
+    }
+
+    /// Error message truncated to a length chat services accept
+    fn truncated_error(&self) -> Option<String> {
+        self.error.as_ref().map(|error| {
+            if error.len() > 500 {
+                format!("{}...", &error[..497])
+            } else {
+                error.clone()
+            }
+        })
+    }
+}
+
+/// POST a JSON payload to a webhook URL
+fn post_json<T: Serialize + ?Sized>(url: &str, payload: &T, channel: &str) -> Result<()> {
+    post_json_response(url, payload, channel).map(|_| ())
+}
+
+/// POST a JSON payload to a webhook URL and return the response body
+fn post_json_response<T: Serialize + ?Sized>(url: &str, payload: &T, channel: &str) -> Result<String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .json(payload)
+        .send()
+        // Webhook URLs and bot tokens are secrets, keep them out of logs
+        .map_err(|e| e.without_url())
+        .context(format!("Failed to send {} webhook", channel))?;
+
+    let status = response.status();
+    if status.is_success() {
+        debug!("{} webhook sent successfully", channel);
+        Ok(response.text().unwrap_or_default())
+    } else {
+        let body = response.text().unwrap_or_default();
+        error!("{} webhook failed with status {}: {}", channel, status, body);
+        anyhow::bail!("{} webhook failed with status {}: {}", channel, status, body)
+    }
+}
+
+/// Build the channels configured in the notification settings
+pub fn channels_from_config(config: &NotificationConfig) -> Vec<Box<dyn NotificationChannel>> {
+    let mut channels: Vec<Box<dyn NotificationChannel>> = Vec::new();
+
+    if !config.discord_webhook_url.is_empty() {
+        channels.push(Box::new(
+            DiscordChannel::new(&config.discord_webhook_url).with_thread_per_run(config.discord_thread_per_run),
+        ));
+    }
+    if !config.slack_webhook_url.is_empty() {
+        channels.push(Box::new(SlackChannel::new(&config.slack_webhook_url)));
+    }
+    if !config.telegram_bot_token.is_empty() && !config.telegram_chat_id.is_empty() {
+        channels.push(Box::new(TelegramChannel::new(
+            &config.telegram_bot_token,
+            &config.telegram_chat_id,
+        )));
+    }
+    if !config.webhook_url.is_empty() {
+        channels.push(Box::new(WebhookChannel::new(
+            &config.webhook_url,
+            config.webhook_template.clone(),
+        )));
+    }
+    if !config.sendmail_to.is_empty() {
+        channels.push(Box::new(SendmailChannel::new(
+            &config.sendmail_command,
+            config.sendmail_from.clone(),
+            config.sendmail_to.clone(),
+        )));
+    }
+
+    channels
+}
+
+/// Rate limit cache entry
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Unix timestamp of last notification
+    last_sent: u64,
+    /// Count of notifications sent in current window
+    count: u32,
+}
+
+/// Rate limit cache
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct NotificationCache {
+    /// Map of cache key to entry
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl NotificationManager {
+    /// Create a new notification manager
+    pub fn new(config: NotificationConfig) -> Self {
+        let channels = channels_from_config(&config);
+        Self::with_channels(config, channels)
+    }
+
+    /// Create a notification manager with explicit channels
+    pub fn with_channels(config: NotificationConfig, channels: Vec<Box<dyn NotificationChannel>>) -> Self {
+        let cache_path = Self::get_cache_path();
+        Self { config, cache_path, channels, run_logs_root: None }
+    }
+
+    /// Point failure notifications to the run's log directory under `root`
+    pub fn with_run_logs(mut self, root: PathBuf) -> Self {
+        self.run_logs_root = Some(root);
+        self
+    }
+
+    /// Get the cache file path
+    fn get_cache_path() -> PathBuf {
+        if let Some(cache_dir) = dirs::cache_dir() {
+            cache_dir.join("restic-manager-notifications.json")
+        } else {
+            PathBuf::from("/tmp/restic-manager-notifications.json")
+        }
+    }
+
+    /// Check if notifications are enabled for an event type
+    pub fn is_enabled(&self, event: &NotifyEvent) -> bool {
+        if self.channels.is_empty() {
+            return false;
+        }
+        self.config.notify_on.contains(event)
+    }
+
+    /// Send a notification if enabled and not rate-limited
+    pub fn send(&self, mut notification: Notification) -> Result<()> {
+        // Check if this event type is enabled
+        if !self.is_enabled(&notification.event_type) {
+            debug!(
+                "Notification type {:?} not enabled, skipping",
+                notification.event_type
+            );
+            return Ok(());
+        }
+
+        if notification.event_type == NotifyEvent::Failure {
+            if let (Some(root), Some(run_id)) = (&self.run_logs_root, &notification.run_id) {
+                let logs = format!("Run logs: {}", root.join(run_id).display());
+                notification.hint = Some(match notification.hint.take() {
+                    Some(hint) => format!("{}\n{}", hint, logs),
+                    None => logs,
+                });
+            }
+        }
+
+        // Deliver to every channel; one failing channel doesn't block the others
+        let mut attempted = 0;
+        let mut failures = Vec::new();
+        for channel in &self.channels {
+            let cache_key = Self::cache_key(channel.name(), &notification);
+            if self.is_rate_limited(&cache_key)? {
+                debug!("Notification rate-limited for key: {}", cache_key);
+                continue;
+            }
+
+            attempted += 1;
+            match channel.send(&notification) {
+                Ok(()) => self.update_cache(&cache_key)?,
+                Err(e) => {
+                    warn!("Failed to send {} notification: {}", channel.name(), e);
+                    failures.push(format!("{}: {}", channel.name(), e));
+                }
+            }
+        }
+
+        if attempted > 0 && failures.len() == attempted {
+            anyhow::bail!("All notification channels failed: {}", failures.join("; "));
+        }
+
+        if attempted > failures.len() {
+            info!(
+                "Sent {:?} notification for service '{}'",
+                notification.event_type, notification.service_name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Rate limit cache key for a notification on one channel
+    fn cache_key(channel: &str, notification: &Notification) -> String {
+        format!(
+            "{}:{}:{}:{:?}",
+            channel,
+            notification.service_name,
+            notification.destination.as_deref().unwrap_or("all"),
+            notification.event_type
+        )
+    }
+
+    /// Send a failure notification
+    pub fn send_failure(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        error: &str,
+        duration_secs: Option<u64>,
+    ) -> Result<()> {
+        self.send(Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message: format!("Backup failed for service '{}'", service_name),
+            error: Some(error.to_string()),
+            duration_secs,
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a warning notification
+    pub fn send_warning(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        message: &str,
+    ) -> Result<()> {
+        self.send(Notification {
+            event_type: NotifyEvent::Warning,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message: message.to_string(),
+            error: None,
+            duration_secs: None,
+            outcome: None,
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a long-running notification
+    pub fn send_long_running(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        duration_secs: u64,
+        threshold_minutes: u64,
+    ) -> Result<()> {
+        self.send(Notification {
+            event_type: NotifyEvent::LongRunning,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message: format!(
+                "Backup is taking longer than expected (>{})",
+                format_duration(threshold_minutes * 60)
+            ),
+            error: None,
+            duration_secs: Some(duration_secs),
+            outcome: None,
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a timeout notification (enabled by the `failure` event)
+    pub fn send_timeout(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        error: &str,
+        hint: &str,
+        duration_secs: Option<u64>,
+    ) -> Result<()> {
+        self.send(Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message: format!("Backup timed out for service '{}'", service_name),
+            error: Some(error.to_string()),
+            duration_secs,
+            outcome: Some(RunOutcome::Timeout),
+            hint: Some(hint.to_string()),
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a cancellation notification (enabled by the `failure` event)
+    pub fn send_cancelled(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        duration_secs: Option<u64>,
+    ) -> Result<()> {
+        self.send(Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message: format!("Backup was cancelled for service '{}'", service_name),
+            error: None,
+            duration_secs,
+            outcome: Some(RunOutcome::Cancelled),
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a success notification
+    pub fn send_success(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        destination: Option<&str>,
+        duration_secs: u64,
+        phases: Option<&str>,
+    ) -> Result<()> {
+        let mut message = format!("Backup completed successfully for service '{}'", service_name);
+        if let Some(phases) = phases {
+            message.push_str(&format!("\nTime per phase: {}", phases));
+        }
+        self.send(Notification {
+            event_type: NotifyEvent::Success,
+            service_name: service_name.to_string(),
+            destination: destination.map(String::from),
+            message,
+            error: None,
+            duration_secs: Some(duration_secs),
+            outcome: Some(RunOutcome::Success),
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send a summary for a service that keeps failing (replaces per-run
+    /// failure notifications while it is in backoff)
+    pub fn send_failure_summary(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        streak: &FailureStreak,
+    ) -> Result<()> {
+        let since = streak.since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+        let (message, hint) = if streak.paused {
+            (
+                format!(
+                    "Backups of '{}' are paused after {} failed runs in a row (since {})",
+                    service_name, streak.count, since
+                ),
+                format!("Fix the problem, then run `restic-manager resume --service {}`", service_name),
+            )
+        } else {
+            (
+                format!(
+                    "Backup of '{}' has failed {} runs in a row (since {})",
+                    service_name, streak.count, since
+                ),
+                "Further failures are only summarised until the next successful run".to_string(),
+            )
+        };
+
+        self.send(Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: service_name.to_string(),
+            destination: None,
+            message,
+            error: Some(streak.last_error.clone()),
+            duration_secs: None,
+            outcome: Some(RunOutcome::Error),
+            hint: Some(hint),
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Send the consolidated report of a maintenance run
+    pub fn send_maintenance(
+        &self,
+        service_name: &str,
+        run_id: Option<&str>,
+        success: bool,
+        report: &str,
+        duration_secs: u64,
+    ) -> Result<()> {
+        let (event_type, message, error, outcome) = if success {
+            (
+                NotifyEvent::Success,
+                format!("Maintenance completed for service '{}'\n{}", service_name, report),
+                None,
+                RunOutcome::Success,
+            )
+        } else {
+            (
+                NotifyEvent::Failure,
+                format!("Maintenance failed for service '{}'", service_name),
+                Some(report.to_string()),
+                RunOutcome::Error,
+            )
+        };
+
+        self.send(Notification {
+            event_type,
+            service_name: service_name.to_string(),
+            destination: None,
+            message,
+            error,
+            duration_secs: Some(duration_secs),
+            outcome: Some(outcome),
+            hint: None,
+            run_id: run_id.map(String::from),
+        })
+    }
+
+    /// Check if a notification is rate-limited
+    fn is_rate_limited(&self, cache_key: &str) -> Result<bool> {
+        let cache = self.load_cache()?;
+
+        if let Some(entry) = cache.entries.get(cache_key) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+
+            let rate_limit_secs = self.config.rate_limit_minutes * 60;
+
+            if now - entry.last_sent < rate_limit_secs {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Update the rate limit cache
+    fn update_cache(&self, cache_key: &str) -> Result<()> {
+        let mut cache = self.load_cache()?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        cache.entries.insert(
+            cache_key.to_string(),
+            CacheEntry {
+                last_sent: now,
+                count: cache.entries.get(cache_key).map_or(1, |e| e.count + 1),
+            },
+        );
+
+        // Clean up old entries (older than 24 hours)
+        let cutoff = now.saturating_sub(86400);
+        cache.entries.retain(|_, v| v.last_sent > cutoff);
+
+        self.save_cache(&cache)?;
+        Ok(())
+    }
+
+    /// Load the notification cache from disk
+    fn load_cache(&self) -> Result<NotificationCache> {
+        if !self.cache_path.exists() {
+            return Ok(NotificationCache::default());
+        }
+
+        let content = fs::read_to_string(&self.cache_path)
+            .context("Failed to read notification cache")?;
+
+        serde_json::from_str(&content)
+            .context("Failed to parse notification cache")
+    }
+
+    /// Save the notification cache to disk
+    fn save_cache(&self, cache: &NotificationCache) -> Result<()> {
+        // Ensure parent directory exists
+        if let Some(parent) = self.cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(cache)
+            .context("Failed to serialize notification cache")?;
+
+        fs::write(&self.cache_path, content)
+            .context("Failed to write notification cache")?;
+
+        Ok(())
+    }
+}
+
+pub use crate::utils::humanize::format_duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration_seconds() {
+        assert_eq!(format_duration(45), "45s");
+    }
+
+    #[test]
+    fn test_format_duration_minutes() {
+        assert_eq!(format_duration(120), "2m");
+        assert_eq!(format_duration(125), "2m 5s");
+    }
+
+    #[test]
+    fn test_format_duration_hours() {
+        assert_eq!(format_duration(3600), "1h");
+        assert_eq!(format_duration(3720), "1h 2m");
+        assert_eq!(format_duration(7320), "2h 2m");
+    }
+
+    #[test]
+    fn test_notification_color_values() {
+        assert_eq!(NotificationColor::Failure.as_decimal(), 15158332);
+        assert_eq!(NotificationColor::Warning.as_decimal(), 15105570);
+        assert_eq!(NotificationColor::Success.as_decimal(), 3066993);
+    }
+
+    #[test]
+    fn test_notification_manager_disabled_when_no_url() {
+        let config = NotificationConfig {
+            discord_webhook_url: String::new(),
+            notify_on: vec![NotifyEvent::Failure],
+            rate_limit_minutes: 60,
+            cache_file: std::path::PathBuf::from("/tmp/test-cache.json"),
+            ..Default::default()
+        };
+        let manager = NotificationManager::new(config);
+        assert!(!manager.is_enabled(&NotifyEvent::Failure));
+    }
+
+    #[test]
+    fn test_notification_manager_disabled_for_unregistered_events() {
+        let config = NotificationConfig {
+            discord_webhook_url: "https://discord.com/api/webhooks/test".to_string(),
+            notify_on: vec![NotifyEvent::Failure],
+            rate_limit_minutes: 60,
+            cache_file: std::path::PathBuf::from("/tmp/test-cache.json"),
+            ..Default::default()
+        };
+        let manager = NotificationManager::new(config);
+        assert!(manager.is_enabled(&NotifyEvent::Failure));
+        assert!(!manager.is_enabled(&NotifyEvent::Warning));
+        assert!(!manager.is_enabled(&NotifyEvent::Success));
+    }
+
+    struct CountingChannel {
+        name: &'static str,
+        fail: bool,
+        sent: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl NotificationChannel for CountingChannel {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn send(&self, _notification: &Notification) -> Result<()> {
+            self.sent.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if self.fail {
+                anyhow::bail!("unreachable");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_is_tracked_per_channel() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ok_count = Arc::new(AtomicUsize::new(0));
+        let failing_count = Arc::new(AtomicUsize::new(0));
+
+        let manager = NotificationManager {
+            config: NotificationConfig {
+                notify_on: vec![NotifyEvent::Warning],
+                rate_limit_minutes: 60,
+                ..Default::default()
+            },
+            cache_path: temp_dir.path().join("cache.json"),
+            channels: vec![
+                Box::new(CountingChannel { name: "ok", fail: false, sent: Arc::clone(&ok_count) }),
+                Box::new(CountingChannel { name: "down", fail: true, sent: Arc::clone(&failing_count) }),
+            ],
+            run_logs_root: None,
+        };
+
+        // One channel delivered, so the send succeeds
+        manager.send_warning("app", None, None, "disk almost full").unwrap();
+
+        // The working channel is rate limited now, the failing one is retried
+        // and is the only channel tried, so the send is an error
+        assert!(manager.send_warning("app", None, None, "disk almost full").is_err());
+        assert_eq!(ok_count.load(Ordering::SeqCst), 1);
+        assert_eq!(failing_count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_cache_path_creation() {
+        let path = NotificationManager::get_cache_path();
+        assert!(path.to_string_lossy().contains("restic-manager-notifications"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Email channel through the local sendmail
+//!
+//! For hosts that already relay mail (postfix, msmtp, nullmailer, ...): the
+//! message is piped to a sendmail-compatible command, so no SMTP credentials
+//! are needed in the config.
+
+use super::{format_duration, Notification, NotificationChannel};
+use crate::utils::{CommandExecutor, RealExecutor};
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long the mail command may take to accept a message
+const SENDMAIL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends notifications as plain-text emails through a sendmail command
+pub struct SendmailChannel {
+    command: String,
+    from: Option<String>,
+    to: Vec<String>,
+    executor: Arc<dyn CommandExecutor>,
+}
+
+impl SendmailChannel {
+    pub fn new(command: &str, from: Option<String>, to: Vec<String>) -> Self {
+        Self {
+            command: command.to_string(),
+            from,
+            to,
+            executor: Arc::new(RealExecutor::new()),
+        }
+    }
+
+    /// Use a different executor (for testing)
+    #[allow(dead_code)]
+    pub fn with_executor(mut self, executor: Arc<dyn CommandExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Build the message, headers included
+    fn build_message(&self, notification: &Notification) -> String {
+        let (_, _, label) = notification.style();
+        let mut subject = format!("[restic-manager] {}: {}", label, notification.service_name);
+        if let Some(ref dest) = notification.destination {
+            subject.push_str(&format!(" -> {}", dest));
+        }
+
+        let mut lines = Vec::new();
+        if let Some(ref from) = self.from {
+            lines.push(format!("From: {}", from));
+        }
+        lines.push(format!("To: {}", self.to.join(", ")));
+        lines.push(format!("Subject: {}", single_line(&subject)));
+        lines.push("MIME-Version: 1.0".to_string());
+        lines.push("Content-Type: text/plain; charset=utf-8".to_string());
+        lines.push("Content-Transfer-Encoding: 8bit".to_string());
+        lines.push(String::new());
+
+        lines.push(notification.message.clone());
+        lines.push(String::new());
+        lines.push(format!("Service:     {}", notification.service_name));
+        if let Some(ref dest) = notification.destination {
+            lines.push(format!("Destination: {}", dest));
+        }
+        if let Some(duration) = notification.duration_secs {
+            lines.push(format!("Duration:    {}", format_duration(duration)));
+        }
+        if let Some(ref run_id) = notification.run_id {
+            lines.push(format!("Run:         {}", run_id));
+        }
+        if let Some(ref error) = notification.error {
+            lines.push(String::new());
+            lines.push("Error:".to_string());
+            lines.push(error.clone());
+        }
+        if let Some(ref hint) = notification.hint {
+            lines.push(String::new());
+            lines.push(format!("Hint: {}", hint));
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Collapse line breaks so a value can't end a header early
+fn single_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl NotificationChannel for SendmailChannel {
+    fn name(&self) -> &str {
+        "Email"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let mut words = self.command.split_whitespace();
+        let program = words.next().context("sendmail_command is empty")?;
+        let mut args: Vec<&str> = words.collect();
+        // -i: a line with a single dot doesn't end the message
+        args.push("-i");
+        args.extend(self.to.iter().map(String::as_str));
+
+        self.executor
+            .run_command_with_input(program, &args, &self.build_message(notification), Some(SENDMAIL_TIMEOUT))
+            .with_context(|| format!("Failed to send email with {}", program))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyEvent;
+    use crate::managers::outcome::RunOutcome;
+    use crate::utils::executor::mock::MockExecutor;
+
+    #[test]
+    fn test_sendmail_pipes_message() {
+        let executor = Arc::new(MockExecutor::new());
+        let channel = SendmailChannel::new(
+            "/usr/sbin/sendmail",
+            Some("backups@host.example".to_string()),
+            vec!["ops@example.com".to_string()],
+        )
+        .with_executor(executor.clone());
+
+        let notification = Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "my_app".to_string(),
+            destination: Some("home-nas".to_string()),
+            message: "Backup failed".to_string(),
+            error: Some("Fatal: unable to open repo".to_string()),
+            duration_secs: Some(125),
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: Some("3f9a2c1b".to_string()),
+        };
+
+        channel.send(&notification).unwrap();
+
+        let calls = executor.get_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].program, "/usr/sbin/sendmail");
+        assert_eq!(calls[0].args, vec!["-i", "ops@example.com"]);
+
+        let message = calls[0].input.as_deref().unwrap();
+        assert!(message.starts_with("From: backups@host.example\nTo: ops@example.com\n"));
+        assert!(message.contains("Subject: [restic-manager] Failure: my_app -> home-nas\n"));
+        assert!(message.contains("\n\nBackup failed\n"));
+        assert!(message.contains("Duration:    2m 5s"));
+        assert!(message.contains("Error:\nFatal: unable to open repo\n"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Slack incoming-webhook channel
+
+use super::{format_duration, post_json, Notification, NotificationChannel};
+use anyhow::Result;
+use serde::Serialize;
+
+/// Sends notifications as Slack attachments
+pub struct SlackChannel {
+    webhook_url: String,
+}
+
+/// Slack incoming webhook payload
+#[derive(Debug, Serialize)]
+struct SlackPayload {
+    text: String,
+    attachments: Vec<SlackAttachment>,
+}
+
+#[derive(Debug, Serialize)]
+struct SlackAttachment {
+    color: String,
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<SlackField>,
+    footer: String,
+    ts: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SlackField {
+    title: String,
+    value: String,
+    short: bool,
+}
+
+impl SlackChannel {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            webhook_url: webhook_url.to_string(),
+        }
+    }
+
+    /// Build Slack webhook payload
+    fn build_payload(&self, notification: &Notification) -> SlackPayload {
+        let (color, _, _) = notification.style();
+
+        let mut fields = vec![SlackField {
+            title: "Service".to_string(),
+            value: notification.service_name.clone(),
+            short: true,
+        }];
+
+        if let Some(ref dest) = notification.destination {
+            fields.push(SlackField {
+                title: "Destination".to_string(),
+                value: dest.clone(),
+                short: true,
+            });
+        }
+
+        if let Some(duration) = notification.duration_secs {
+            fields.push(SlackField {
+                title: "Duration".to_string(),
+                value: format_duration(duration),
+                short: true,
+            });
+        }
+
+        if let Some(error) = notification.truncated_error() {
+            fields.push(SlackField {
+                title: "Error".to_string(),
+                value: format!("```{}```", error),
+                short: false,
+            });
+        }
+
+        if let Some(ref hint) = notification.hint {
+            fields.push(SlackField {
+                title: "Hint".to_string(),
+                value: hint.clone(),
+                short: false,
+            });
+        }
+
+        if let Some(ref run_id) = notification.run_id {
+            fields.push(SlackField {
+                title: "Run".to_string(),
+                value: run_id.clone(),
+                short: true,
+            });
+        }
+
+        SlackPayload {
+            text: notification.title(),
+            attachments: vec![SlackAttachment {
+                color: color.as_hex(),
+                text: notification.message.clone(),
+                fields,
+                footer: "restic-manager".to_string(),
+                ts: chrono::Utc::now().timestamp(),
+            }],
+        }
+    }
+}
+
+impl NotificationChannel for SlackChannel {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        post_json(&self.webhook_url, &self.build_payload(notification), self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyEvent;
+    use crate::managers::outcome::RunOutcome;
+
+    #[test]
+    fn test_build_slack_payload() {
+        let channel = SlackChannel::new("https://hooks.slack.com/services/test");
+
+        let notification = Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "postgres".to_string(),
+            destination: Some("local".to_string()),
+            message: "Backup failed".to_string(),
+            error: Some("Connection refused".to_string()),
+            duration_secs: Some(120),
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: None,
+        };
+
+        let payload = channel.build_payload(&notification);
+
+        assert!(payload.text.contains("Failure"));
+        assert_eq!(payload.attachments[0].color, "#E74C3C");
+        assert!(payload.attachments[0].fields.iter().any(|f| f.title == "Service" && f.value == "postgres"));
+        assert!(payload.attachments[0].fields.iter().any(|f| f.title == "Error"));
+    }
+}
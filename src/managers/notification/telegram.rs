@@ -0,0 +1,137 @@
+//! Telegram bot channel
+
+use super::{format_duration, post_json, Notification, NotificationChannel};
+use anyhow::Result;
+use serde::Serialize;
+
+/// Telegram Bot API endpoint
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// Sends notifications as MarkdownV2 messages through a Telegram bot
+pub struct TelegramChannel {
+    bot_token: String,
+    chat_id: String,
+}
+
+/// `sendMessage` request body
+#[derive(Debug, Serialize)]
+struct TelegramMessage {
+    chat_id: String,
+    text: String,
+    parse_mode: &'static str,
+    disable_web_page_preview: bool,
+}
+
+/// Escape text for MarkdownV2
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape text for a MarkdownV2 code block (only ` and \ are special)
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+impl TelegramChannel {
+    pub fn new(bot_token: &str, chat_id: &str) -> Self {
+        Self {
+            bot_token: bot_token.to_string(),
+            chat_id: chat_id.to_string(),
+        }
+    }
+
+    /// Build the sendMessage request
+    fn build_message(&self, notification: &Notification) -> TelegramMessage {
+        let mut lines = vec![
+            format!("*{}*", escape_markdown(&notification.title())),
+            escape_markdown(&notification.message),
+            String::new(),
+            format!("*Service:* {}", escape_markdown(&notification.service_name)),
+        ];
+
+        if let Some(ref dest) = notification.destination {
+            lines.push(format!("*Destination:* {}", escape_markdown(dest)));
+        }
+
+        if let Some(duration) = notification.duration_secs {
+            lines.push(format!("*Duration:* {}", escape_markdown(&format_duration(duration))));
+        }
+
+        if let Some(ref run_id) = notification.run_id {
+            lines.push(format!("*Run:* `{}`", escape_code(run_id)));
+        }
+
+        if let Some(error) = notification.truncated_error() {
+            lines.push(format!("*Error:*\n```\n{}\n```", escape_code(&error)));
+        }
+
+        if let Some(ref hint) = notification.hint {
+            lines.push(format!("_{}_", escape_markdown(hint)));
+        }
+
+        TelegramMessage {
+            chat_id: self.chat_id.clone(),
+            text: lines.join("\n"),
+            parse_mode: "MarkdownV2",
+            disable_web_page_preview: true,
+        }
+    }
+}
+
+impl NotificationChannel for TelegramChannel {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", TELEGRAM_API_URL, self.bot_token);
+        post_json(&url, &self.build_message(notification), self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyEvent;
+    use crate::managers::outcome::RunOutcome;
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("my-app (v1.2)!"), "my\\-app \\(v1\\.2\\)\\!");
+        assert_eq!(escape_code("a `b` \\c"), "a \\`b\\` \\\\c");
+    }
+
+    #[test]
+    fn test_build_telegram_message() {
+        let channel = TelegramChannel::new("123456:ABC", "-1001234");
+
+        let notification = Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "my_app".to_string(),
+            destination: Some("home-nas".to_string()),
+            message: "Backup failed".to_string(),
+            error: Some("Fatal: unable to open repo".to_string()),
+            duration_secs: Some(125),
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: Some("3f9a2c1b".to_string()),
+        };
+
+        let message = channel.build_message(&notification);
+
+        assert_eq!(message.chat_id, "-1001234");
+        assert_eq!(message.parse_mode, "MarkdownV2");
+        assert!(message.text.contains("*Service:* my\\_app"));
+        assert!(message.text.contains("*Destination:* home\\-nas"));
+        assert!(message.text.contains("*Duration:* 2m 5s"));
+        assert!(message.text.contains("*Run:* `3f9a2c1b`"));
+        assert!(message.text.contains("```\nFatal: unable to open repo\n```"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Generic JSON webhook channel
+//!
+//! Without a template the notification is posted as a flat JSON object.
+//! With `webhook_template`, placeholders in the template are replaced and the
+//! result is posted as-is:
+//!
+//! ```toml
+//! webhook_template = '{"text": "{{title}}: {{service}} - {{message}} {{error}}"}'
+//! ```
+//!
+//! Available placeholders: `{{event}}`, `{{outcome}}`, `{{title}}`, `{{service}}`,
+//! `{{destination}}`, `{{message}}`, `{{error}}`, `{{hint}}`, `{{duration}}`,
+//! `{{duration_secs}}`, `{{run_id}}`, `{{timestamp}}`. Values are JSON-escaped (without quotes)
+//! so they can be placed inside string literals; missing values are empty.
+
+use super::{format_duration, post_json, Notification, NotificationChannel};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Posts notifications as JSON to an arbitrary URL
+pub struct WebhookChannel {
+    url: String,
+    template: Option<String>,
+}
+
+/// Default payload when no template is configured
+#[derive(Debug, Serialize)]
+struct WebhookPayload {
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<String>,
+    title: String,
+    service: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destination: Option<String>,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<String>,
+    timestamp: String,
+}
+
+impl WebhookChannel {
+    pub fn new(url: &str, template: Option<String>) -> Self {
+        Self {
+            url: url.to_string(),
+            template,
+        }
+    }
+
+    fn event_name(notification: &Notification) -> String {
+        serde_json::to_value(&notification.event_type)
+            .ok()
+            .and_then(|v| v.as_str().map(String::from))
+            .unwrap_or_default()
+    }
+
+    /// Build the JSON payload (from the template if one is configured)
+    fn build_payload(&self, notification: &Notification) -> Result<serde_json::Value> {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+
+        let Some(ref template) = self.template else {
+            let payload = WebhookPayload {
+                event: Self::event_name(notification),
+                outcome: notification.outcome.map(|o| o.to_string()),
+                title: notification.title(),
+                service: notification.service_name.clone(),
+                destination: notification.destination.clone(),
+                message: notification.message.clone(),
+                error: notification.error.clone(),
+                hint: notification.hint.clone(),
+                duration_secs: notification.duration_secs,
+                run_id: notification.run_id.clone(),
+                timestamp,
+            };
+            return serde_json::to_value(payload).context("Failed to serialize webhook payload");
+        };
+
+        let values = [
+            ("event", Self::event_name(notification)),
+            ("outcome", notification.outcome.map(|o| o.to_string()).unwrap_or_default()),
+            ("title", notification.title()),
+            ("service", notification.service_name.clone()),
+            ("destination", notification.destination.clone().unwrap_or_default()),
+            ("message", notification.message.clone()),
+            ("error", notification.error.clone().unwrap_or_default()),
+            ("hint", notification.hint.clone().unwrap_or_default()),
+            ("duration", notification.duration_secs.map(format_duration).unwrap_or_default()),
+            ("duration_secs", notification.duration_secs.map(|d| d.to_string()).unwrap_or_default()),
+            ("run_id", notification.run_id.clone().unwrap_or_default()),
+            ("timestamp", timestamp),
+        ];
+
+        let mut rendered = template.clone();
+        for (key, value) in values {
+            rendered = rendered.replace(&format!("{{{{{}}}}}", key), &json_escape(&value));
+        }
+
+        serde_json::from_str(&rendered).context("Webhook template does not produce valid JSON")
+    }
+}
+
+/// Escape a value for use inside a JSON string literal (without the quotes)
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        "Webhook"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let payload = self.build_payload(notification)?;
+        post_json(&self.url, &payload, self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyEvent;
+    use crate::managers::outcome::RunOutcome;
+
+    fn notification() -> Notification {
+        Notification {
+            event_type: NotifyEvent::Failure,
+            service_name: "postgres".to_string(),
+            destination: Some("local".to_string()),
+            message: "Backup failed".to_string(),
+            error: Some("exit \"1\"\nstderr".to_string()),
+            duration_secs: Some(90),
+            outcome: Some(RunOutcome::Error),
+            hint: None,
+            run_id: Some("3f9a2c1b".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_default_payload() {
+        let channel = WebhookChannel::new("https://example.com/hook", None);
+        let payload = channel.build_payload(&notification()).unwrap();
+
+        assert_eq!(payload["event"], "failure");
+        assert_eq!(payload["outcome"], "error");
+        assert_eq!(payload["service"], "postgres");
+        assert_eq!(payload["duration_secs"], 90);
+        assert_eq!(payload["run_id"], "3f9a2c1b");
+        assert!(payload.get("hint").is_none());
+    }
+
+    #[test]
+    fn test_template_payload_escapes_values() {
+        let template = r#"{"text": "{{service}} on {{destination}} ({{duration}}): {{error}}", "hint": "{{hint}}"}"#;
+        let channel = WebhookChannel::new("https://example.com/hook", Some(template.to_string()));
+        let payload = channel.build_payload(&notification()).unwrap();
+
+        assert_eq!(payload["text"], "postgres on local (1m 30s): exit \"1\"\nstderr");
+        assert_eq!(payload["hint"], "");
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        let channel = WebhookChannel::new("https://example.com/hook", Some("{not json".to_string()));
+        assert!(channel.build_payload(&notification()).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Backup run outcomes
+//!
+//! A failed destination backup is classified as a timeout, a cancellation
+//! (the process was interrupted) or a plain error, together with the phase
+//! of the backup that failed, so notifications can say what actually happened.
+
+use crate::error::BackupError;
+use crate::utils::command::{CommandCancelled, CommandTimeout};
+use crate::utils::humanize::format_duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+/// Result of a backup run to one destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunOutcome {
+    Success,
+    Timeout,
+    Cancelled,
+    Error,
+}
+
+impl RunOutcome {
+    /// Classify a backup error
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if error.downcast_ref::<CommandTimeout>().is_some()
+            || matches!(BackupError::of(error), Some(BackupError::Timeout(_)))
+        {
+            RunOutcome::Timeout
+        } else if error.downcast_ref::<CommandCancelled>().is_some() {
+            RunOutcome::Cancelled
+        } else {
+            RunOutcome::Error
+        }
+    }
+}
+
+impl fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            RunOutcome::Success => "success",
+            RunOutcome::Timeout => "timeout",
+            RunOutcome::Cancelled => "cancelled",
+            RunOutcome::Error => "error",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// What started a backup run, recorded as a snapshot tag and in the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunTrigger {
+    /// Started by hand (`run` without `--scheduled`)
+    #[default]
+    Manual,
+    /// Started by cron or a timer (`run --scheduled`)
+    Scheduled,
+    /// Started by a serve mode trigger
+    Webhook,
+}
+
+impl RunTrigger {
+    /// Tag recorded on the run's snapshots
+    pub fn tag(&self) -> &'static str {
+        match self {
+            RunTrigger::Manual => "manual",
+            RunTrigger::Scheduled => "scheduled",
+            RunTrigger::Webhook => "webhook",
+        }
+    }
+}
+
+impl fmt::Display for RunTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tag())
+    }
+}
+
+/// Phase of a destination backup
+///
+/// Used as error context: the `Display` text is the message shown when the
+/// phase fails, and the phase can be recovered from the error afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupPhase {
+    PreHooks,
+    Volumes,
+    Backup,
+    Retention,
+    PostHooks,
+}
+
+impl BackupPhase {
+    /// Find the phase a backup error occurred in
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        error.downcast_ref::<BackupPhase>().copied()
+    }
+
+    /// Identifier of the phase (e.g. for hook environment variables)
+    pub fn key(&self) -> &'static str {
+        match self {
+            BackupPhase::PreHooks => "pre_hooks",
+            BackupPhase::Volumes => "volumes",
+            BackupPhase::Backup => "backup",
+            BackupPhase::Retention => "retention",
+            BackupPhase::PostHooks => "post_hooks",
+        }
+    }
+
+    /// Short name of the phase
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackupPhase::PreHooks => "pre-backup hooks",
+            BackupPhase::Volumes => "Docker volume archiving",
+            BackupPhase::Backup => "restic backup",
+            BackupPhase::Retention => "retention policy",
+            BackupPhase::PostHooks => "post-backup hooks",
+        }
+    }
+}
+
+impl fmt::Display for BackupPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            BackupPhase::PreHooks => "Pre-backup hooks failed",
+            BackupPhase::Volumes => "Failed to backup Docker volumes",
+            BackupPhase::Backup => "Failed to backup to restic",
+            BackupPhase::Retention => "Failed to apply retention policy",
+            BackupPhase::PostHooks => "Post-backup hooks failed",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Time spent in each phase of one or more destination backups
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    phases: Vec<(BackupPhase, Duration)>,
+}
+
+impl PhaseTimings {
+    /// Run a phase inside a `phase` tracing span and add its duration
+    pub fn time<T>(&mut self, phase: BackupPhase, f: impl FnOnce() -> T) -> T {
+        let _span = info_span!("phase", name = phase.key()).entered();
+        let start = Instant::now();
+        let result = f();
+        self.add(phase, start.elapsed());
+        result
+    }
+
+    /// Add time to a phase (phases that run more than once are summed)
+    pub fn add(&mut self, phase: BackupPhase, duration: Duration) {
+        match self.phases.iter_mut().find(|(p, _)| *p == phase) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((phase, duration)),
+        }
+    }
+
+    /// Add the timings of another destination
+    pub fn merge(&mut self, other: &PhaseTimings) {
+        for (phase, duration) in &other.phases {
+            self.add(*phase, *duration);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phases.is_empty()
+    }
+
+    /// Phases and their durations in the order they ran
+    pub fn iter(&self) -> impl Iterator<Item = (BackupPhase, Duration)> + '_ {
+        self.phases.iter().copied()
+    }
+
+    /// Phases in the order they ran with their share of `total`, e.g.
+    /// "restic backup 1m 5s (20%), retention policy 4m 20s (80%)"
+    pub fn summary(&self, total: Duration) -> String {
+        self.phases
+            .iter()
+            .map(|(phase, duration)| {
+                let time = format_duration(duration.as_secs());
+                if total.is_zero() {
+                    format!("{} {}", phase.name(), time)
+                } else {
+                    let share = duration.as_secs_f64() / total.as_secs_f64() * 100.0;
+                    format!("{} {} ({:.0}%)", phase.name(), time, share)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Suggestion shown with a timeout notification
+pub fn timeout_hint(phase: Option<BackupPhase>, timeout: Duration) -> String {
+    let in_hook = matches!(phase, Some(BackupPhase::PreHooks | BackupPhase::PostHooks));
+    format!(
+        "Timed out during {} (timeout_seconds = {}). Consider raising timeout_seconds for this service{}.",
+        phase.map(|p| p.name()).unwrap_or("backup"),
+        timeout.as_secs(),
+        if in_hook { " or the hook's timeout_seconds" } else { "" }
+    )
+}
+
+/// Health of a destination, by the age of its latest backup (ordered
+/// from best to worst)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Healthy,
+    Warning,
+    Critical,
+}
+
+impl Health {
+    /// Healthy up to `max_age`, a warning up to twice that, then critical
+    pub fn of_age(age: Duration, max_age: Duration) -> Self {
+        if age <= max_age {
+            Health::Healthy
+        } else if age <= max_age * 2 {
+            Health::Warning
+        } else {
+            Health::Critical
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_classify_timeout_with_phase() {
+        let result: anyhow::Result<()> = Err(CommandTimeout(Duration::from_secs(60)).into());
+        let error = result.context(BackupPhase::Backup).unwrap_err();
+
+        assert_eq!(RunOutcome::from_error(&error), RunOutcome::Timeout);
+        assert_eq!(BackupPhase::of(&error), Some(BackupPhase::Backup));
+        assert!(error.to_string().contains("Failed to backup to restic"));
+
+        // restic commands time out with a typed backup error
+        let restic: anyhow::Result<()> = Err(BackupError::Timeout(Duration::from_secs(60)).into());
+        let error = restic.context(BackupPhase::Retention).unwrap_err();
+        assert_eq!(RunOutcome::from_error(&error), RunOutcome::Timeout);
+    }
+
+    #[test]
+    fn test_classify_cancelled_and_error() {
+        let cancelled = anyhow::Error::new(CommandCancelled).context("Backup interrupted");
+        assert_eq!(RunOutcome::from_error(&cancelled), RunOutcome::Cancelled);
+        assert_eq!(BackupPhase::of(&cancelled), None);
+
+        let error = anyhow::anyhow!("Fatal: wrong password");
+        assert_eq!(RunOutcome::from_error(&error), RunOutcome::Error);
+    }
+
+    #[test]
+    fn test_phase_timings() {
+        let mut timings = PhaseTimings::default();
+        assert!(timings.is_empty());
+        assert_eq!(timings.time(BackupPhase::PreHooks, || 42), 42);
+        timings.add(BackupPhase::Backup, Duration::from_secs(15));
+        timings.add(BackupPhase::Retention, Duration::from_secs(80));
+
+        let mut other = PhaseTimings::default();
+        other.add(BackupPhase::Backup, Duration::from_secs(5));
+        timings.merge(&other);
+
+        // Phases keep the order they first ran in
+        let summary = timings.summary(Duration::from_secs(100));
+        assert!(summary.starts_with("pre-backup hooks 0s (0%), restic backup 20s (20%)"));
+        assert!(summary.ends_with("retention policy 1m 20s (80%)"));
+    }
+
+    #[test]
+    fn test_timeout_hint() {
+        let hint = timeout_hint(Some(BackupPhase::PreHooks), Duration::from_secs(300));
+        assert!(hint.contains("pre-backup hooks"));
+        assert!(hint.contains("timeout_seconds = 300"));
+        assert!(hint.contains("hook's timeout_seconds"));
+
+        let hint = timeout_hint(None, Duration::from_secs(3600));
+        assert!(hint.contains("during backup"));
+    }
+
+    #[test]
+    fn test_health_of_age() {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+        let weekly = hours(7 * 24 + 1);
+        assert_eq!(Health::of_age(hours(30), hours(25)), Health::Warning);
+        assert_eq!(Health::of_age(hours(3 * 24), weekly), Health::Healthy);
+        assert_eq!(Health::of_age(hours(10 * 24), weekly), Health::Warning);
+        assert_eq!(Health::of_age(hours(15 * 24), weekly), Health::Critical);
+    }
+}
@@ -0,0 +1,130 @@
+//! Delayed pruning (`prune_delay_days`)
+//!
+//! With a prune delay, retention only forgets snapshots. Their data stays in
+//! the repository (and `restic recover` can bring them back) until `maintain`
+//! prunes, which it does once the oldest unpruned forget is old enough. What
+//! was forgotten is recorded per service and destination until then.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Snapshots forgotten on one destination since its last prune
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingPrune {
+    /// Snapshots forgotten so far
+    pub snapshots: usize,
+    /// When the first of them was forgotten
+    pub since: DateTime<Utc>,
+}
+
+impl PendingPrune {
+    /// Whether the delay has passed, so the data may be pruned
+    pub fn is_due(&self, delay_days: u32, now: DateTime<Utc>) -> bool {
+        now - self.since >= Duration::days(i64::from(delay_days))
+    }
+
+    /// When the delay has passed
+    pub fn due_at(&self, delay_days: u32) -> DateTime<Utc> {
+        self.since + Duration::days(i64::from(delay_days))
+    }
+}
+
+/// Pending prunes per service and destination, persisted as JSON
+#[derive(Debug, Clone)]
+pub struct PendingPruneStore {
+    path: PathBuf,
+}
+
+impl PendingPruneStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default location (~/.local/share/restic-manager/pending-prune.json)
+    pub fn default_path() -> PathBuf {
+        if let Some(data_dir) = dirs::data_local_dir() {
+            data_dir.join("restic-manager").join("pending-prune.json")
+        } else {
+            PathBuf::from("/tmp/restic-manager-pending-prune.json")
+        }
+    }
+
+    fn key(service: &str, destination: &str) -> String {
+        format!("{}/{}", service, destination)
+    }
+
+    fn load(&self) -> Result<HashMap<String, PendingPrune>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&self.path).context("Failed to read pending prunes")?;
+        serde_json::from_str(&content).context("Failed to parse pending prunes")
+    }
+
+    fn save(&self, pending: &HashMap<String, PendingPrune>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(pending).context("Failed to serialize pending prunes")?;
+        fs::write(&self.path, content).context("Failed to write pending prunes")
+    }
+
+    /// Snapshots forgotten on a destination and not pruned yet
+    pub fn get(&self, service: &str, destination: &str) -> Result<Option<PendingPrune>> {
+        Ok(self.load()?.remove(&Self::key(service, destination)))
+    }
+
+    /// Record forgotten snapshots (the delay keeps counting from the first forget)
+    pub fn record(&self, service: &str, destination: &str, snapshots: usize) -> Result<()> {
+        let mut pending = self.load()?;
+        pending
+            .entry(Self::key(service, destination))
+            .and_modify(|p| p.snapshots += snapshots)
+            .or_insert(PendingPrune { snapshots, since: Utc::now() });
+        self.save(&pending)
+    }
+
+    /// Forget pending work after a prune
+    pub fn clear(&self, service: &str, destination: &str) -> Result<()> {
+        let mut pending = self.load()?;
+        if pending.remove(&Self::key(service, destination)).is_some() {
+            self.save(&pending)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pending_prune_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PendingPruneStore::new(temp_dir.path().join("pending-prune.json"));
+
+        assert!(store.get("app", "local").unwrap().is_none());
+
+        store.record("app", "local", 2).unwrap();
+        let first = store.get("app", "local").unwrap().unwrap();
+        store.record("app", "local", 3).unwrap();
+        let pending = store.get("app", "local").unwrap().unwrap();
+        assert_eq!(pending.snapshots, 5);
+        assert_eq!(pending.since, first.since);
+
+        assert!(pending.is_due(0, Utc::now()));
+        assert!(!pending.is_due(7, pending.since + Duration::days(6)));
+        assert!(pending.is_due(7, pending.since + Duration::days(7)));
+        assert_eq!(pending.due_at(7), pending.since + Duration::days(7));
+
+        store.clear("app", "local").unwrap();
+        assert!(store.get("app", "local").unwrap().is_none());
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-run log directories
+//!
+//! Every backup run gets `<log_directory>/runs/<run id>/`, holding each
+//! service's run report, the restic output per destination and the output
+//! of its hooks, so everything about one run is in one place. History
+//! records and failure notifications point to the directory; the oldest
+//! directories are removed beyond `keep_run_logs`.
+
+use crate::config::expand_tilde;
+use crate::managers::run_report::RunReport;
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// The log directory of one run (writes are no-ops when disabled)
+///
+/// Failing to write a log never fails the backup, it is only warned about.
+#[derive(Debug, Clone, Default)]
+pub struct RunLogs {
+    dir: Option<PathBuf>,
+}
+
+impl RunLogs {
+    /// Directory holding the per-run directories
+    pub fn root(log_directory: &Path) -> PathBuf {
+        expand_tilde(log_directory).join("runs")
+    }
+
+    /// Create (or reopen) the directory of a run, removing the oldest run
+    /// directories beyond `keep`; `keep = 0` disables per-run logs
+    pub fn create(log_directory: &Path, run_id: &str, keep: u32) -> Self {
+        if keep == 0 {
+            return Self::default();
+        }
+
+        let root = Self::root(log_directory);
+        let dir = root.join(run_id);
+        if let Err(e) = fs::create_dir_all(&dir) {
+            warn!("Failed to create run log directory {}: {}", dir.display(), e);
+            return Self::default();
+        }
+
+        if let Err(e) = remove_old_runs(&root, keep as usize) {
+            warn!("Failed to remove old run logs: {}", e);
+        }
+
+        Self { dir: Some(dir) }
+    }
+
+    /// The run's directory, unless per-run logs are disabled
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Append a timestamped section to a file in the run directory
+    fn append(&self, file_name: &str, heading: &str, text: &str) {
+        let Some(ref dir) = self.dir else {
+            return;
+        };
+
+        let path = dir.join(file_name);
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| {
+                writeln!(file, "=== {} [{}] ===", heading, Utc::now().to_rfc3339())?;
+                writeln!(file, "{}", text.trim_end())?;
+                writeln!(file)
+            });
+        if let Err(e) = result {
+            warn!("Failed to write run log {}: {}", path.display(), e);
+        }
+    }
+
+    /// Restic output of a service's backup to one destination
+    pub fn restic(&self, service: &str, destination: &str, heading: &str, text: &str) {
+        self.append(&format!("{}.{}.restic.log", service, destination), heading, text);
+    }
+
+    /// Output of a hook
+    pub fn hook(&self, service: &str, hook_type: &str, hook_name: &str, text: &str) {
+        self.append(&format!("{}.hooks.log", service), &format!("{} hook: {}", hook_type, hook_name), text);
+    }
+
+    /// Final report of a service's run
+    pub fn report(&self, service: &str, report: &str) {
+        self.append(&format!("{}.report.txt", service), "Run report", report);
+    }
+
+    /// Machine-readable report of a service's run (replaces an earlier one)
+    pub fn report_json(&self, service: &str, report: &RunReport) {
+        let Some(ref dir) = self.dir else {
+            return;
+        };
+
+        let path = dir.join(format!("{}.report.json", service));
+        let result = serde_json::to_string_pretty(report)
+            .map_err(std::io::Error::from)
+            .and_then(|json| fs::write(&path, json + "\n"));
+        if let Err(e) = result {
+            warn!("Failed to write run report {}: {}", path.display(), e);
+        }
+    }
+
+    /// The newest JSON report of a service among the kept runs
+    pub fn latest_report(log_directory: &Path, service: &str) -> Option<PathBuf> {
+        let file_name = format!("{}.report.json", service);
+        fs::read_dir(Self::root(log_directory))
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(&file_name))
+            .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+            .max()
+            .map(|(_, path)| path)
+    }
+}
+
+/// Remove the oldest run directories so at most `keep` remain
+fn remove_old_runs(root: &Path, keep: usize) -> std::io::Result<()> {
+    let mut runs: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+
+    if runs.len() <= keep {
+        return Ok(());
+    }
+
+    runs.sort();
+    for (_, dir) in &runs[..runs.len() - keep] {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_logs() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let logs = RunLogs::create(temp_dir.path(), "run-1", 2);
+        logs.restic("app", "local", "backup", "{\"snapshot_id\":\"abc\"}");
+        logs.hook("app", "pre-backup", "dump", "dumped 3 tables\n");
+        logs.report("app", "Backup of 'app': 1/1 destination(s) succeeded");
+        logs.report_json("app", &RunReport::new("run-1", "app", Utc::now(), &[]));
+
+        let dir = logs.dir().unwrap();
+        assert_eq!(dir, RunLogs::root(temp_dir.path()).join("run-1"));
+        let restic_log = fs::read_to_string(dir.join("app.local.restic.log")).unwrap();
+        assert!(restic_log.starts_with("=== backup ["));
+        assert!(restic_log.contains("{\"snapshot_id\":\"abc\"}\n"));
+        let hooks = fs::read_to_string(dir.join("app.hooks.log")).unwrap();
+        assert!(hooks.contains("pre-backup hook: dump"));
+        assert!(dir.join("app.report.txt").exists());
+        assert_eq!(RunLogs::latest_report(temp_dir.path(), "app"), Some(dir.join("app.report.json")));
+        assert_eq!(RunLogs::latest_report(temp_dir.path(), "db"), None);
+
+        // Only the newest `keep` runs remain
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        RunLogs::create(temp_dir.path(), "run-2", 2);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        RunLogs::create(temp_dir.path(), "run-3", 2);
+        let root = RunLogs::root(temp_dir.path());
+        assert!(!root.join("run-1").exists());
+        assert!(root.join("run-2").exists());
+        assert!(root.join("run-3").exists());
+
+        assert!(RunLogs::create(temp_dir.path(), "run-4", 0).dir().is_none());
+    }
+}
@@ -0,0 +1,250 @@
+//! Machine-readable run report
+//!
+//! Next to the text report, every backup run writes `<service>.report.json`
+//! to its run log directory. The JSON is a stable contract for scripts and
+//! dashboards: `schema_version` only changes when a field is removed,
+//! renamed or changes its meaning. New fields may appear in any release, so
+//! consumers should ignore fields they don't know. `report --schema` prints
+//! the JSON Schema of the current version.
+
+use crate::managers::outcome::{PhaseTimings, RunOutcome, RunTrigger};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Version of the run report format (bumped on breaking changes only)
+pub const RUN_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Report of one service's backup run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RunReport {
+    /// Version of this format
+    pub schema_version: u32,
+    /// Id shared with the run's notifications, history and log directory
+    pub run_id: String,
+    pub service: String,
+    /// When the run started (RFC 3339)
+    #[schemars(with = "String")]
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    /// `success` if every destination succeeded, else the outcome of the
+    /// last failed destination
+    pub outcome: RunOutcome,
+    /// What started the run
+    #[serde(default)]
+    pub trigger: RunTrigger,
+    /// Why the run was started (`run --reason`, a webhook's note)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Tags added to the snapshots
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Bytes added over all destinations
+    #[serde(default)]
+    pub data_added: u64,
+    /// Retries over all destinations
+    #[serde(default)]
+    pub retries: u32,
+    /// One entry per destination, in the order they were backed up
+    pub destinations: Vec<DestinationReport>,
+}
+
+/// Backup of a service to one destination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DestinationReport {
+    pub destination: String,
+    pub outcome: RunOutcome,
+    pub duration_secs: u64,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_processed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_added: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the retention policy couldn't be applied (the backup itself
+    /// succeeded unless `fail_on_retention_error` is set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_error: Option<String>,
+    /// Seconds spent per phase (`pre_hooks`, `volumes`, `backup`,
+    /// `retention`, `post_hooks`), in the order they ran
+    #[serde(default)]
+    pub phases: Vec<PhaseReport>,
+    /// Other remarks (verification, deferred phases, snapshot chain)
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+/// Time spent in one phase of a destination backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PhaseReport {
+    pub phase: String,
+    pub secs: f64,
+}
+
+impl RunReport {
+    /// Empty report of a run, filled in as its destinations finish
+    pub fn new(run_id: &str, service: &str, started_at: DateTime<Utc>, tags: &[String]) -> Self {
+        Self {
+            schema_version: RUN_REPORT_SCHEMA_VERSION,
+            run_id: run_id.to_string(),
+            service: service.to_string(),
+            started_at,
+            duration_secs: 0,
+            outcome: RunOutcome::Success,
+            trigger: RunTrigger::default(),
+            reason: None,
+            tags: tags.to_vec(),
+            data_added: 0,
+            retries: 0,
+            destinations: Vec::new(),
+        }
+    }
+
+    /// Add a finished destination, updating the run's totals
+    pub fn push(&mut self, destination: DestinationReport) {
+        self.retries += destination.retries;
+        self.data_added += destination.data_added.unwrap_or_default();
+        if destination.outcome != RunOutcome::Success {
+            self.outcome = destination.outcome;
+        }
+        self.destinations.push(destination);
+    }
+
+    /// JSON Schema of the report, pretty-printed
+    pub fn json_schema() -> String {
+        let schema = schemars::schema_for!(RunReport);
+        serde_json::to_string_pretty(&schema).unwrap_or_default()
+    }
+}
+
+impl DestinationReport {
+    /// Report of a destination without results yet
+    pub fn new(destination: &str, outcome: RunOutcome, duration_secs: u64, retries: u32) -> Self {
+        Self {
+            destination: destination.to_string(),
+            outcome,
+            duration_secs,
+            retries,
+            snapshot_id: None,
+            files_processed: None,
+            data_added: None,
+            error: None,
+            retention_error: None,
+            phases: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Set the phase timings
+    pub fn with_phases(mut self, phases: &PhaseTimings) -> Self {
+        self.phases = phases
+            .iter()
+            .map(|(phase, duration)| PhaseReport { phase: phase.key().to_string(), secs: duration.as_secs_f64() })
+            .collect();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::managers::outcome::BackupPhase;
+    use std::time::Duration;
+
+    /// A version 1 report as consumers have seen it; it must keep parsing
+    const V1_REPORT: &str = r#"{
+        "schema_version": 1,
+        "run_id": "1a2b3c4d",
+        "service": "postgres",
+        "started_at": "2025-12-28T02:00:00Z",
+        "duration_secs": 95,
+        "outcome": "error",
+        "tags": ["scheduled"],
+        "data_added": 1048576,
+        "retries": 1,
+        "destinations": [
+            {
+                "destination": "local",
+                "outcome": "success",
+                "duration_secs": 60,
+                "retries": 0,
+                "snapshot_id": "abc123",
+                "files_processed": 42,
+                "data_added": 1048576,
+                "phases": [{"phase": "backup", "secs": 55.2}],
+                "notes": []
+            },
+            {
+                "destination": "remote",
+                "outcome": "error",
+                "duration_secs": 35,
+                "retries": 1,
+                "error": "Failed to backup to restic"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_v1_report_still_parses() {
+        let report: RunReport = serde_json::from_str(V1_REPORT).unwrap();
+        assert_eq!(report.schema_version, 1);
+        assert_eq!(report.outcome, RunOutcome::Error);
+        assert_eq!(report.trigger, RunTrigger::Manual);
+        assert_eq!(report.destinations[0].phases[0].phase, "backup");
+        assert_eq!(report.destinations[1].error.as_deref(), Some("Failed to backup to restic"));
+        assert!(report.destinations[1].phases.is_empty());
+
+        // Written reports parse back to the same report
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<RunReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn test_run_report_totals() {
+        let mut timings = PhaseTimings::default();
+        timings.add(BackupPhase::Backup, Duration::from_millis(1500));
+
+        let mut report = RunReport::new("run-1", "app", Utc::now(), &["manual".to_string()]);
+        let mut local = DestinationReport::new("local", RunOutcome::Success, 2, 1).with_phases(&timings);
+        local.data_added = Some(100);
+        report.push(local);
+        assert_eq!(report.outcome, RunOutcome::Success);
+        report.push(DestinationReport::new("remote", RunOutcome::Timeout, 30, 2));
+
+        assert_eq!(report.schema_version, RUN_REPORT_SCHEMA_VERSION);
+        assert_eq!(report.outcome, RunOutcome::Timeout);
+        assert_eq!((report.retries, report.data_added), (3, 100));
+        assert_eq!(report.destinations[0].phases, vec![PhaseReport { phase: "backup".to_string(), secs: 1.5 }]);
+    }
+
+    #[test]
+    fn test_retention_error_only_when_set() {
+        let mut destination = DestinationReport::new("local", RunOutcome::Success, 2, 0);
+        let json = serde_json::to_value(&destination).unwrap();
+        assert!(json.get("retention_error").is_none());
+
+        destination.retention_error = Some("Retention failed: repository is locked".to_string());
+        let json = serde_json::to_value(&destination).unwrap();
+        assert_eq!(json["retention_error"], "Retention failed: repository is locked");
+        assert_eq!(json["outcome"], "success");
+    }
+
+    #[test]
+    fn test_run_report_schema() {
+        let schema: serde_json::Value = serde_json::from_str(&RunReport::json_schema()).unwrap();
+        assert_eq!(schema["title"], "RunReport");
+
+        // Fields of version 1 that consumers may rely on being present
+        let required = schema["required"].as_array().unwrap();
+        for field in ["schema_version", "run_id", "service", "started_at", "duration_secs", "outcome", "destinations"] {
+            assert!(required.iter().any(|r| r == field), "{} is not required", field);
+        }
+        assert_eq!(schema["properties"]["started_at"]["type"], "string");
+        let outcomes = &schema["definitions"]["RunOutcome"]["enum"];
+        assert_eq!(outcomes, &serde_json::json!(["success", "timeout", "cancelled", "error"]));
+    }
+}
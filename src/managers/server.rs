@@ -0,0 +1,899 @@
+//! Serve mode - long-running HTTP listener
+//!
+//! `restic-manager serve` accepts authenticated webhook triggers, e.g. from CI
+//! after a deployment, and starts an immediate backup of the named service:
+//!
+//! ```text
+//! POST /trigger/<service>
+//! Authorization: Bearer <token>
+//!
+//! {"note": "post-deploy v1.4.2", "tags": ["ci"]}
+//! ```
+//!
+//! The snapshot is tagged `webhook`, the given tags and the note (recorded as
+//! the run's reason in the history). The backup
+//! runs in the background and the request is answered with `202 Accepted`.
+//!
+//! Snapshot lists are served from a cache refreshed in the background (see
+//! [`SnapshotCache`]):
+//!
+//! ```text
+//! GET  /snapshots            all repositories
+//! GET  /snapshots/<service>  the repositories of one service
+//! POST /snapshots/refresh    refresh the cache now
+//! ```
+//!
+//! A read-only status API for dashboards, built from the same cache and the
+//! run history:
+//!
+//! ```text
+//! GET  /api/services                     services with per-destination health
+//! GET  /api/services/<service>/snapshots the repositories of one service
+//! GET  /api/history?service=&destination=&limit=  recent runs (newest last)
+//! ```
+//!
+//! Every endpoint but `/health` (and `/healthz`) needs a token with its
+//! scope: `trigger` for triggers, `read` for the snapshot and status API.
+//! Tokens are named in `server.tokens`; `server.webhook_token` has every
+//! scope. Which token did what is logged to the `audit` target.
+//!
+//! `GET /` serves a small dashboard bundled into the binary (the `dashboard/`
+//! directory). The page itself holds no data; it asks for a token and calls
+//! the status API and the trigger endpoint from the browser.
+//!
+//! Edits of the configuration file are picked up without a restart: the new
+//! file is validated, the [`ServeState`] rebuilt from it and swapped in, and
+//! the changes logged. An invalid file leaves the running state untouched.
+
+use crate::config::reload::{self, reload_config};
+use crate::config::{is_valid_tag, resolve_all_services, ApiScope, Config, ResolvedServiceConfig, ServerConfig};
+use crate::managers::backup::{BackupManager, RunOptions};
+use crate::managers::history::{self, StateStore};
+use crate::managers::outcome::{Health, RunTrigger};
+use crate::managers::snapshot_cache::{RepoSnapshots, SnapshotCache};
+use crate::utils::restic_ops::ResticOperations;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use include_dir::{include_dir, Dir, File};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Maximum accepted request body size
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// Name of `server.webhook_token` in the audit log
+const WEBHOOK_TOKEN_NAME: &str = "webhook_token";
+
+/// Static files of the web dashboard
+static DASHBOARD: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/dashboard");
+
+/// Runs returned by `/api/history` unless `limit` is given
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// A validated backup trigger
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerRequest {
+    pub service: String,
+    /// Name of the token that sent the trigger
+    pub token: String,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl TriggerRequest {
+    /// Options of the triggered run (the note is its reason)
+    pub fn run_options(&self) -> RunOptions {
+        RunOptions {
+            trigger: RunTrigger::Webhook,
+            reason: self.note.clone(),
+            tags: self.tags.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TriggerBody {
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Error response with HTTP status
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Compare tokens without short-circuiting on the first differing byte
+fn tokens_match(expected: &str, given: &str) -> bool {
+    if expected.len() != given.len() {
+        return false;
+    }
+    expected
+        .bytes()
+        .zip(given.bytes())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Bearer tokens accepted by serve mode
+#[derive(Debug, Clone, Default)]
+pub struct ApiTokens {
+    /// (name, token, scopes)
+    tokens: Vec<(String, String, Vec<ApiScope>)>,
+}
+
+impl ApiTokens {
+    /// `server.tokens` plus `server.webhook_token` (every scope) if set
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let mut tokens: Vec<_> = config
+            .tokens
+            .iter()
+            .map(|(name, t)| (name.clone(), t.token.clone(), t.scopes.clone()))
+            .collect();
+        if !config.webhook_token.is_empty() {
+            tokens.push((
+                WEBHOOK_TOKEN_NAME.to_string(),
+                config.webhook_token.clone(),
+                vec![ApiScope::Read, ApiScope::Trigger, ApiScope::Restore],
+            ));
+        }
+        Self { tokens }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Check the bearer token has `scope`, returning the token's name
+    pub fn authorize(&self, authorization: Option<&str>, scope: ApiScope) -> Result<&str, HttpError> {
+        if self.is_empty() {
+            return Err(HttpError::new(403, "The API is disabled (set server.tokens or server.webhook_token)"));
+        }
+
+        let given = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default()
+            .trim();
+        // Compare against every token so the timing doesn't reveal which matched
+        let mut matched = None;
+        for entry in &self.tokens {
+            if tokens_match(&entry.1, given) {
+                matched = Some(entry);
+            }
+        }
+        let (name, _, scopes) = matched.ok_or_else(|| HttpError::new(401, "Unauthorized"))?;
+
+        if !scopes.contains(&scope) {
+            warn!(target: "audit", token = %name, scope = scope.name(), "request denied, missing scope");
+            return Err(HttpError::new(403, format!("Token '{}' lacks the '{}' scope", name, scope.name())));
+        }
+        Ok(name)
+    }
+}
+
+/// Answer a `/snapshots` request from the cache
+pub fn snapshot_request(
+    method: &str,
+    url: &str,
+    authorization: Option<&str>,
+    tokens: &ApiTokens,
+    cache: &SnapshotCache,
+) -> Result<(u16, serde_json::Value), HttpError> {
+    let path = url.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let rest = path.strip_prefix("/snapshots").ok_or_else(|| HttpError::new(404, "Not found"))?;
+
+    let token = tokens.authorize(authorization, ApiScope::Read)?;
+
+    match (method, rest.strip_prefix('/')) {
+        ("POST", Some("refresh")) => {
+            if !cache.request_refresh() {
+                return Err(HttpError::new(503, "Snapshot cache refresh is not running"));
+            }
+            info!(target: "audit", token = %token, "snapshot cache refresh");
+            Ok((202, serde_json::json!({ "status": "refreshing" })))
+        }
+        (_, Some("refresh")) => Err(HttpError::new(405, "Method not allowed")),
+        ("GET", None) => {
+            debug!(target: "audit", token = %token, "snapshot list");
+            Ok((200, serde_json::json!({ "repositories": cache.get(None) })))
+        }
+        ("GET", Some(service)) if !service.contains('/') => {
+            if !cache.has_service(service) {
+                return Err(HttpError::new(404, format!("Unknown service '{}'", service)));
+            }
+            debug!(target: "audit", token = %token, service = %service, "snapshot list");
+            Ok((200, serde_json::json!({ "repositories": cache.get(Some(service)) })))
+        }
+        ("GET", Some(_)) => Err(HttpError::new(404, "Not found")),
+        _ => Err(HttpError::new(405, "Method not allowed")),
+    }
+}
+
+/// Service in `/api/services`
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    pub schedule: String,
+    /// Age of the latest backup at which it is overdue
+    pub max_age_secs: u64,
+    /// Worst health of the destinations (none before the first refresh)
+    pub health: Option<Health>,
+    pub destinations: Vec<DestinationStatus>,
+}
+
+/// Destination of a service in `/api/services`
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationStatus {
+    pub destination: String,
+    pub snapshots: usize,
+    pub latest_snapshot: Option<String>,
+    pub latest_time: Option<String>,
+    pub age_secs: Option<u64>,
+    /// None until the repository was listed
+    pub health: Option<Health>,
+    /// Restore size in bytes
+    pub total_size: Option<u64>,
+    pub refreshed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl DestinationStatus {
+    fn from_cache(repo: RepoSnapshots, max_age: std::time::Duration, now: DateTime<Utc>) -> Self {
+        // Snapshots are listed oldest first
+        let latest = repo.snapshots.last();
+        let age = latest
+            .and_then(|s| DateTime::parse_from_rfc3339(&s.time).ok())
+            .map(|time| now.signed_duration_since(time).to_std().unwrap_or_default());
+        let listed = repo.refreshed_at.is_some() || repo.error.is_some();
+        let health = match age {
+            Some(age) => Some(Health::of_age(age, max_age)),
+            None if listed => Some(Health::Critical),
+            None => None,
+        };
+
+        Self {
+            destination: repo.destination,
+            snapshots: repo.snapshots.len(),
+            latest_snapshot: latest.map(|s| s.short_id.clone()),
+            latest_time: latest.map(|s| s.time.clone()),
+            age_secs: age.map(|age| age.as_secs()),
+            health,
+            total_size: repo.total_size,
+            refreshed_at: repo.refreshed_at,
+            error: repo.error,
+        }
+    }
+}
+
+/// Status of every service from the snapshot cache, sorted by name
+pub fn service_statuses(services: &HashMap<String, ResolvedServiceConfig>, cache: &SnapshotCache) -> Vec<ServiceStatus> {
+    let now = Utc::now();
+    let mut statuses: Vec<ServiceStatus> = services
+        .values()
+        .map(|service| {
+            let max_age = service.max_backup_age();
+            // Disabled services aren't cached
+            let destinations: Vec<DestinationStatus> = if service.enabled {
+                cache
+                    .get(Some(&service.name))
+                    .into_iter()
+                    .map(|repo| DestinationStatus::from_cache(repo, max_age, now))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            ServiceStatus {
+                name: service.name.clone(),
+                description: service.description.clone(),
+                enabled: service.enabled,
+                schedule: service.schedule.clone(),
+                max_age_secs: max_age.as_secs(),
+                health: destinations.iter().filter_map(|d| d.health).max(),
+                destinations,
+            }
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.name.cmp(&b.name));
+    statuses
+}
+
+/// Value of a query parameter
+fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    url.split_once('?')?
+        .1
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Answer a `/api/...` request from the cache and the run history
+pub fn api_request(
+    method: &str,
+    url: &str,
+    authorization: Option<&str>,
+    tokens: &ApiTokens,
+    services: &HashMap<String, ResolvedServiceConfig>,
+    cache: &SnapshotCache,
+    history: &dyn StateStore,
+) -> Result<(u16, serde_json::Value), HttpError> {
+    let path = url.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let rest = path.strip_prefix("/api/").ok_or_else(|| HttpError::new(404, "Not found"))?;
+
+    let token = tokens.authorize(authorization, ApiScope::Read)?;
+    if method != "GET" {
+        return Err(HttpError::new(405, "Method not allowed"));
+    }
+    debug!(target: "audit", token = %token, path = %path, "status API request");
+
+    let parts: Vec<&str> = rest.split('/').collect();
+    match parts.as_slice() {
+        ["services"] => Ok((200, serde_json::json!({ "services": service_statuses(services, cache) }))),
+        ["services", service, "snapshots"] => {
+            if !services.contains_key(*service) {
+                return Err(HttpError::new(404, format!("Unknown service '{}'", service)));
+            }
+            Ok((200, serde_json::json!({ "repositories": cache.get(Some(service)) })))
+        }
+        ["history"] => {
+            let service = query_param(url, "service");
+            let destination = query_param(url, "destination");
+            let limit = match query_param(url, "limit") {
+                Some(limit) => limit.parse().map_err(|_| HttpError::new(400, format!("Invalid limit '{}'", limit)))?,
+                None => DEFAULT_HISTORY_LIMIT,
+            };
+            let records = history.load_all().map_err(|e| HttpError::new(500, format!("Failed to read history: {:#}", e)))?;
+            let matching: Vec<_> = records
+                .into_iter()
+                .filter(|r| service.is_none_or(|s| s == r.service) && destination.is_none_or(|d| d == r.destination))
+                .collect();
+            let skip = matching.len().saturating_sub(limit);
+            Ok((200, serde_json::json!({ "runs": matching[skip..] })))
+        }
+        _ => Err(HttpError::new(404, "Not found")),
+    }
+}
+
+/// Dashboard file for a request (`/` is `index.html`)
+pub fn dashboard_asset(method: &str, url: &str) -> Option<&'static File<'static>> {
+    if method != "GET" {
+        return None;
+    }
+    let path = url.split('?').next().unwrap_or_default().trim_start_matches('/');
+    DASHBOARD.get_file(if path.is_empty() { "index.html" } else { path })
+}
+
+/// Content type of a dashboard file
+fn content_type(file: &File) -> &'static str {
+    match file.path().extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Validate a trigger request (method, path, token, body)
+pub fn parse_trigger(
+    method: &str,
+    url: &str,
+    authorization: Option<&str>,
+    body: &str,
+    tokens: &ApiTokens,
+    services: &HashMap<String, ResolvedServiceConfig>,
+) -> Result<TriggerRequest, HttpError> {
+    let path = url.split('?').next().unwrap_or_default();
+    let service = path
+        .strip_prefix("/trigger/")
+        .filter(|s| !s.is_empty() && !s.contains('/'))
+        .ok_or_else(|| HttpError::new(404, "Not found"))?;
+
+    if method != "POST" {
+        return Err(HttpError::new(405, "Method not allowed"));
+    }
+
+    let token = tokens.authorize(authorization, ApiScope::Trigger)?;
+
+    let config = match services.get(service) {
+        Some(config) if config.enabled => config,
+        Some(_) => return Err(HttpError::new(409, format!("Service '{}' is disabled", service))),
+        None => return Err(HttpError::new(404, format!("Unknown service '{}'", service))),
+    };
+
+    let body: TriggerBody = if body.trim().is_empty() {
+        TriggerBody::default()
+    } else {
+        serde_json::from_str(body).map_err(|e| HttpError::new(400, format!("Invalid JSON body: {}", e)))?
+    };
+
+    // Tags reach `restic --tag`; one with a retention rule (e.g. a `forever`
+    // tag) would let a trigger token decide how long snapshots are kept
+    for tag in &body.tags {
+        if !is_valid_tag(tag) {
+            return Err(HttpError::new(400, format!("Invalid tag '{}' (tags must be non-empty and contain no commas)", tag)));
+        }
+        if config.retention.tags.contains_key(tag) {
+            return Err(HttpError::new(400, format!("Tag '{}' has a retention rule and can't be set by a trigger", tag)));
+        }
+    }
+
+    Ok(TriggerRequest {
+        service: service.to_string(),
+        token: token.to_string(),
+        note: body.note.filter(|n| !n.trim().is_empty()),
+        tags: body.tags,
+    })
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("static header is valid");
+    tiny_http::Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn asset_response(file: &File) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type(file).as_bytes())
+        .expect("static header is valid");
+    tiny_http::Response::from_data(file.contents().to_vec()).with_header(header)
+}
+
+/// Everything serve mode answers from, built from one configuration and
+/// replaced as a whole when it is reloaded
+pub struct ServeState {
+    pub manager: Arc<BackupManager>,
+    pub services: HashMap<String, ResolvedServiceConfig>,
+    pub tokens: ApiTokens,
+    pub cache: Arc<SnapshotCache>,
+    pub history: Box<dyn StateStore>,
+    pub dashboard: bool,
+}
+
+impl ServeState {
+    /// State of a configuration; the snapshot cache starts from the entries
+    /// of `previous`, whose background refresh is stopped
+    pub fn new(
+        config: &Config,
+        services: HashMap<String, ResolvedServiceConfig>,
+        restic: Arc<dyn ResticOperations>,
+        previous: Option<&ServeState>,
+    ) -> Self {
+        let cache = Arc::new(SnapshotCache::new(&config.global, &config.destinations, &services, restic));
+        if let Some(previous) = previous {
+            cache.carry_over(&previous.cache);
+            previous.cache.stop_refresher();
+        }
+        cache.spawn_refresher(Duration::from_secs(config.server.snapshot_cache_ttl_seconds));
+
+        Self {
+            manager: Arc::new(BackupManager::new(config.clone(), services.clone())),
+            tokens: ApiTokens::from_config(&config.server),
+            history: history::open_store(&config.global),
+            dashboard: config.server.dashboard,
+            services,
+            cache,
+        }
+    }
+}
+
+/// Reload the configuration file and swap in a new state if it changed
+fn reload(path: &Path, current: &mut Config, state: &RwLock<Arc<ServeState>>, restic: &Arc<dyn ResticOperations>) {
+    let reloaded = reload_config(path, current).and_then(|(config, changes)| {
+        let services = resolve_all_services(&config)?;
+        Ok((config, services, changes))
+    });
+
+    match reloaded {
+        Ok((_, _, changes)) if changes.is_empty() => debug!("Configuration file changed, nothing to reload"),
+        Ok((config, services, changes)) => {
+            let previous = Arc::clone(&state.read().unwrap_or_else(|e| e.into_inner()));
+            let next = ServeState::new(&config, services, Arc::clone(restic), Some(&previous));
+            *state.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(next);
+
+            info!("Reloaded configuration from {}", path.display());
+            for change in &changes {
+                info!("  {}", change);
+            }
+            if config.server.listen != current.server.listen {
+                warn!("server.listen changed, restart serve mode to listen on {}", config.server.listen);
+            }
+            *current = config;
+        }
+        Err(e) => error!("Configuration reload failed, keeping the current configuration: {:#}", e),
+    }
+}
+
+/// Run the HTTP listener until the process is stopped, reloading the
+/// configuration at `config_path` when it changes
+pub fn serve(config: Config, config_path: &Path, listen: &str, restic: Arc<dyn ResticOperations>) -> Result<()> {
+    let server = tiny_http::Server::http(listen)
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", listen, e))?;
+
+    let services = resolve_all_services(&config)?;
+    let state = Arc::new(RwLock::new(Arc::new(ServeState::new(&config, services, Arc::clone(&restic), None))));
+
+    info!("Listening on http://{}", listen);
+    if config.server.webhook_token.is_empty() && config.server.tokens.is_empty() {
+        warn!("Neither server.tokens nor server.webhook_token is set, the API is disabled");
+    }
+    if config.server.dashboard {
+        info!("Dashboard at http://{}/", listen);
+    }
+
+    // Kept alive for as long as the listener runs
+    let _watcher = {
+        let state = Arc::clone(&state);
+        let path = config_path.to_path_buf();
+        let current = std::sync::Mutex::new(config.clone());
+        reload::watch(config_path, &config, move || {
+            let mut current = current.lock().unwrap_or_else(|e| e.into_inner());
+            reload(&path, &mut current, &state, &restic);
+        })
+        .inspect_err(|e| warn!("Configuration changes need a restart: {:#}", e))
+        .ok()
+    };
+
+    for mut request in server.incoming_requests() {
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .map(|h| h.value.as_str().to_string());
+
+        let mut body = String::new();
+        if let Err(e) = request.as_reader().take(MAX_BODY_BYTES).read_to_string(&mut body) {
+            warn!("Failed to read request body: {}", e);
+        }
+
+        let method = request.method().as_str().to_string();
+        let url = request.url().to_string();
+        // One state per request, even if a reload swaps it meanwhile
+        let state = Arc::clone(&state.read().unwrap_or_else(|e| e.into_inner()));
+        let tokens = &state.tokens;
+        let services = &state.services;
+
+        let response = if method == "GET" && (url == "/health" || url == "/healthz") {
+            json_response(200, serde_json::json!({ "status": "ok" }))
+        } else if url.starts_with("/api/") {
+            match api_request(&method, &url, authorization.as_deref(), tokens, services, &state.cache, state.history.as_ref()) {
+                Ok((status, body)) => json_response(status, body),
+                Err(e) => {
+                    if e.status == 401 {
+                        warn!("Rejected unauthorized API request for {}", url);
+                    }
+                    json_response(e.status, serde_json::json!({ "error": e.message }))
+                }
+            }
+        } else if url.starts_with("/snapshots") {
+            match snapshot_request(&method, &url, authorization.as_deref(), tokens, &state.cache) {
+                Ok((status, body)) => json_response(status, body),
+                Err(e) => {
+                    if e.status == 401 {
+                        warn!("Rejected unauthorized snapshot request for {}", url);
+                    }
+                    json_response(e.status, serde_json::json!({ "error": e.message }))
+                }
+            }
+        } else if let Some(file) = dashboard_asset(&method, &url).filter(|_| state.dashboard) {
+            asset_response(file)
+        } else {
+            match parse_trigger(&method, &url, authorization.as_deref(), &body, tokens, services) {
+                Ok(trigger) => {
+                    info!(
+                        target: "audit",
+                        token = %trigger.token,
+                        service = %trigger.service,
+                        note = trigger.note.as_deref().unwrap_or(""),
+                        "webhook backup trigger"
+                    );
+
+                    let options = trigger.run_options();
+                    let manager = Arc::clone(&state.manager);
+                    let service = trigger.service.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = manager.backup_service_with(&service, &options) {
+                            error!("Triggered backup of '{}' failed: {:#}", service, e);
+                        }
+                    });
+
+                    json_response(
+                        202,
+                        serde_json::json!({ "status": "accepted", "service": trigger.service }),
+                    )
+                }
+                Err(e) => {
+                    if e.status == 401 {
+                        warn!("Rejected unauthorized trigger request for {}", url);
+                    }
+                    json_response(e.status, serde_json::json!({ "error": e.message }))
+                }
+            }
+        };
+
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiToken, RetentionPolicy, TagRetention};
+    use std::collections::BTreeMap;
+
+    /// Tokens with only `server.webhook_token` set
+    fn webhook_token(token: &str) -> ApiTokens {
+        ApiTokens::from_config(&ServerConfig { webhook_token: token.to_string(), ..Default::default() })
+    }
+
+    fn services() -> HashMap<String, ResolvedServiceConfig> {
+        let service = |name: &str, enabled: bool| ResolvedServiceConfig {
+            name: name.to_string(),
+            description: String::new(),
+            enabled,
+            disabled_until: None,
+            schedule: "0 2 * * *".to_string(),
+            targets: vec!["local".to_string()],
+            timeout_seconds: 3600,
+            time_budget_minutes: None,
+            max_age_hours: None,
+            retention: RetentionPolicy {
+                daily: 7,
+                weekly: 4,
+                monthly: 6,
+                yearly: 0,
+                tags: BTreeMap::from([("forever".to_string(), TagRetention { forever: true, ..Default::default() })]),
+                ..Default::default()
+            },
+            retry: Default::default(),
+            post_backup_verify: Default::default(),
+            notify_on: vec![],
+            tags: vec![],
+            hostname: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            config: None,
+        };
+
+        let mut services = HashMap::new();
+        services.insert("app".to_string(), service("app", true));
+        services.insert("old".to_string(), service("old", false));
+        services
+    }
+
+    #[test]
+    fn test_parse_trigger_success() {
+        let trigger = parse_trigger(
+            "POST",
+            "/trigger/app",
+            Some("Bearer secret"),
+            r#"{"note": "post-deploy v1.4.2", "tags": ["ci"]}"#,
+            &webhook_token("secret"),
+            &services(),
+        )
+        .unwrap();
+
+        assert_eq!(trigger.service, "app");
+        assert_eq!(trigger.token, "webhook_token");
+        let options = trigger.run_options();
+        assert_eq!(options.reason.as_deref(), Some("post-deploy v1.4.2"));
+        assert_eq!(options.snapshot_tags(), vec!["webhook", "ci"]);
+    }
+
+    #[test]
+    fn test_parse_trigger_empty_body() {
+        let trigger = parse_trigger("POST", "/trigger/app", Some("Bearer secret"), "", &webhook_token("secret"), &services()).unwrap();
+        assert_eq!(trigger.run_options().snapshot_tags(), vec!["webhook"]);
+    }
+
+    #[test]
+    fn test_parse_trigger_errors() {
+        let status = |method: &str, url: &str, auth: Option<&str>, body: &str, token: &str| {
+            parse_trigger(method, url, auth, body, &webhook_token(token), &services()).unwrap_err().status
+        };
+
+        assert_eq!(status("POST", "/other", Some("Bearer secret"), "", "secret"), 404);
+        assert_eq!(status("GET", "/trigger/app", Some("Bearer secret"), "", "secret"), 405);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer secret"), "", ""), 403);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer wrong!"), "", "secret"), 401);
+        assert_eq!(status("POST", "/trigger/app", None, "", "secret"), 401);
+        assert_eq!(status("POST", "/trigger/missing", Some("Bearer secret"), "", "secret"), 404);
+        assert_eq!(status("POST", "/trigger/old", Some("Bearer secret"), "", "secret"), 409);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer secret"), "{bad", "secret"), 400);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer secret"), r#"{"tags": ["a,b"]}"#, "secret"), 400);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer secret"), r#"{"tags": [""]}"#, "secret"), 400);
+        assert_eq!(status("POST", "/trigger/app", Some("Bearer secret"), r#"{"tags": ["forever"]}"#, "secret"), 400);
+    }
+
+    #[test]
+    fn test_snapshot_request() {
+        use crate::config::{Destination, GlobalConfig};
+        use crate::utils::restic_ops::mock::MockResticOps;
+
+        let destinations = HashMap::from([("local".to_string(), Destination { url: "/backups".to_string(), ..Default::default() })]);
+        let cache = SnapshotCache::new(&GlobalConfig::default(), &destinations, &services(), Arc::new(MockResticOps::new()));
+        let auth = Some("Bearer secret");
+        let status = |method: &str, url: &str, authorization: Option<&str>, token: &str| {
+            snapshot_request(method, url, authorization, &webhook_token(token), &cache).map_or_else(|e| e.status, |(status, _)| status)
+        };
+
+        let (_, body) = snapshot_request("GET", "/snapshots", auth, &webhook_token("secret"), &cache).unwrap();
+        assert_eq!(body["repositories"][0]["service"], "app");
+        assert_eq!(status("GET", "/snapshots/app", auth, "secret"), 200);
+        assert_eq!(status("GET", "/snapshots/old", auth, "secret"), 404);
+        assert_eq!(status("GET", "/snapshots", None, "secret"), 401);
+        assert_eq!(status("GET", "/snapshots", auth, ""), 403);
+        assert_eq!(status("DELETE", "/snapshots/app", auth, "secret"), 405);
+        assert_eq!(status("GET", "/snapshots/refresh", auth, "secret"), 405);
+        // The refresher isn't running
+        assert_eq!(status("POST", "/snapshots/refresh", auth, "secret"), 503);
+    }
+
+    #[test]
+    fn test_api_request() {
+        use crate::config::{Destination, GlobalConfig};
+        use crate::managers::history::{HistoryStore, RunRecord};
+        use crate::utils::restic_ops::mock::MockResticOps;
+
+        let destinations = HashMap::from([("local".to_string(), Destination { url: "/backups".to_string(), ..Default::default() })]);
+        let cache = SnapshotCache::new(&GlobalConfig::default(), &destinations, &services(), Arc::new(MockResticOps::new()));
+        let dir = tempfile::TempDir::new().unwrap();
+        let history = HistoryStore::new(dir.path().join("history.jsonl"));
+        for (service, duration_secs) in [("app", 10), ("other", 20), ("app", 30)] {
+            history
+                .append(&RunRecord {
+                    service: service.to_string(),
+                    destination: "local".to_string(),
+                    started_at: Utc::now(),
+                    duration_secs,
+                    success: true,
+                    outcome: None,
+                    data_added: None,
+                    snapshot_id: None,
+                    error: None,
+                    run_id: None,
+                    log_dir: None,
+                    trigger: None,
+                    reason: None,
+                    retention_error: None,
+                })
+                .unwrap();
+        }
+
+        let auth = Some("Bearer secret");
+        let request = |method: &str, url: &str, authorization: Option<&str>| {
+            api_request(method, url, authorization, &webhook_token("secret"), &services(), &cache, &history)
+        };
+        let status = |method: &str, url: &str, authorization: Option<&str>| request(method, url, authorization).map_or_else(|e| e.status, |(status, _)| status);
+
+        let (_, body) = request("GET", "/api/services", auth).unwrap();
+        assert_eq!(body["services"][0]["name"], "app");
+        assert_eq!(body["services"][0]["max_age_secs"], 25 * 3600);
+        assert_eq!(body["services"][0]["destinations"][0]["destination"], "local");
+        // Not refreshed yet
+        assert!(body["services"][0]["health"].is_null());
+        assert_eq!(body["services"][1]["name"], "old");
+        assert_eq!(body["services"][1]["destinations"].as_array().unwrap().len(), 0);
+
+        let (_, body) = request("GET", "/api/history?service=app&limit=1", auth).unwrap();
+        let runs = body["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0]["duration_secs"], 30);
+        let (_, body) = request("GET", "/api/history", auth).unwrap();
+        assert_eq!(body["runs"].as_array().unwrap().len(), 3);
+
+        assert_eq!(status("GET", "/api/services/app/snapshots", auth), 200);
+        assert_eq!(status("GET", "/api/services/missing/snapshots", auth), 404);
+        assert_eq!(status("GET", "/api/history?limit=many", auth), 400);
+        assert_eq!(status("GET", "/api/other", auth), 404);
+        assert_eq!(status("POST", "/api/services", auth), 405);
+        assert_eq!(status("GET", "/api/services", None), 401);
+    }
+
+    #[test]
+    fn test_reload_swaps_state() {
+        use crate::config::load_config;
+        use crate::utils::restic_ops::mock::MockResticOps;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let password_file = dir.path().join("password");
+        std::fs::write(&password_file, "secret").unwrap();
+        let path = dir.path().join("config.toml");
+        let write = |services: &str| {
+            let content = format!(
+                "[global]\nrestic_password_file = {:?}\ndocker_base = {:?}\nlog_directory = {:?}\n\n\
+                 [destinations.local]\ntype = \"local\"\nurl = \"/backups\"\n\n{}",
+                password_file,
+                dir.path(),
+                dir.path().join("logs"),
+                services
+            );
+            std::fs::write(&path, content).unwrap();
+        };
+
+        write("[services.app]\nschedule = \"0 2 * * *\"\ntargets = [\"local\"]\n");
+        let mut current = load_config(&path).unwrap();
+        let restic: Arc<dyn ResticOperations> = Arc::new(MockResticOps::new());
+        let services = resolve_all_services(&current).unwrap();
+        let state = RwLock::new(Arc::new(ServeState::new(&current, services, Arc::clone(&restic), None)));
+        let services = || {
+            let mut names: Vec<String> = state.read().unwrap().services.keys().cloned().collect();
+            names.sort();
+            names
+        };
+
+        write("[services.app]\nschedule = \"0 2 * * *\"\ntargets = [\"local\"]\n\n[services.db]\nschedule = \"0 3 * * *\"\ntargets = [\"local\"]\n");
+        reload(&path, &mut current, &state, &restic);
+        assert_eq!(services(), vec!["app", "db"]);
+        assert!(current.services.contains_key("db"));
+
+        // An invalid file keeps the running configuration
+        write("[services.app]\nschedule = \"0 2 * * *\"\ntargets = [\"missing\"]\n");
+        reload(&path, &mut current, &state, &restic);
+        assert_eq!(services(), vec!["app", "db"]);
+    }
+
+    #[test]
+    fn test_dashboard_asset() {
+        let index = dashboard_asset("GET", "/").unwrap();
+        assert_eq!(index.path().to_str(), Some("index.html"));
+        assert_eq!(content_type(index), "text/html; charset=utf-8");
+        assert_eq!(content_type(dashboard_asset("GET", "/dashboard.js?v=1").unwrap()), "text/javascript; charset=utf-8");
+        assert!(dashboard_asset("GET", "/missing.html").is_none());
+        assert!(dashboard_asset("POST", "/").is_none());
+    }
+
+    #[test]
+    fn test_api_token_scopes() {
+        let config = ServerConfig {
+            tokens: [
+                ("dashboard", "read-secret", vec![ApiScope::Read]),
+                ("ci", "ci-secret", vec![ApiScope::Trigger]),
+            ]
+            .into_iter()
+            .map(|(name, token, scopes)| (name.to_string(), ApiToken { token: token.to_string(), scopes }))
+            .collect(),
+            ..Default::default()
+        };
+        let tokens = ApiTokens::from_config(&config);
+
+        assert_eq!(tokens.authorize(Some("Bearer read-secret"), ApiScope::Read).unwrap(), "dashboard");
+        assert_eq!(tokens.authorize(Some("Bearer ci-secret"), ApiScope::Trigger).unwrap(), "ci");
+
+        let denied = tokens.authorize(Some("Bearer read-secret"), ApiScope::Trigger).unwrap_err();
+        assert_eq!(denied.status, 403);
+        assert!(denied.message.contains("'dashboard' lacks the 'trigger' scope"));
+        assert_eq!(tokens.authorize(Some("Bearer other"), ApiScope::Read).unwrap_err().status, 401);
+
+        // A read-only token can't trigger backups
+        let err = parse_trigger("POST", "/trigger/app", Some("Bearer read-secret"), "", &tokens, &services()).unwrap_err();
+        assert_eq!(err.status, 403);
+        let trigger = parse_trigger("POST", "/trigger/app", Some("Bearer ci-secret"), "", &tokens, &services()).unwrap();
+        assert_eq!(trigger.token, "ci");
+    }
+}
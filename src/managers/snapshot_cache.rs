@@ -0,0 +1,303 @@
+//! Snapshot cache for serve mode
+//!
+//! Listing snapshots of a remote repository can take seconds (SFTP round
+//! trips, cold S3 buckets). Serve mode keeps the snapshot list and size of
+//! every repository (service and destination) in memory and refreshes them
+//! in a background thread every `snapshot_cache_ttl_seconds`, or when a
+//! refresh is requested, so reads never wait for restic.
+
+use crate::config::{Destination, GlobalConfig, ResolvedServiceConfig};
+use crate::utils::restic::{self, Snapshot};
+use crate::utils::restic_ops::ResticOperations;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How long one repository may take to list its snapshots
+const LIST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Cached state of one repository
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoSnapshots {
+    pub service: String,
+    pub destination: String,
+    pub snapshots: Vec<Snapshot>,
+    /// Restore size in bytes reported by `restic stats`
+    pub total_size: Option<u64>,
+    /// When the entry was last refreshed successfully
+    pub refreshed_at: Option<DateTime<Utc>>,
+    /// Error of the last refresh (the previous snapshots are kept)
+    pub error: Option<String>,
+}
+
+/// A repository the cache tracks
+struct Repo {
+    service: String,
+    /// Host name the snapshot list is limited to
+    hostname: Option<String>,
+    destination_name: String,
+    destination: Destination,
+}
+
+/// In-memory snapshot lists, refreshed in the background
+pub struct SnapshotCache {
+    global: GlobalConfig,
+    repos: Vec<Repo>,
+    restic: Arc<dyn ResticOperations>,
+    entries: RwLock<HashMap<String, RepoSnapshots>>,
+    refresh_tx: Mutex<Option<Sender<()>>>,
+}
+
+impl SnapshotCache {
+    /// Cache for every destination of every enabled service
+    pub fn new(
+        global: &GlobalConfig,
+        destinations: &HashMap<String, Destination>,
+        services: &HashMap<String, ResolvedServiceConfig>,
+        restic: Arc<dyn ResticOperations>,
+    ) -> Self {
+        let mut repos: Vec<Repo> = services
+            .values()
+            .filter(|service| service.enabled)
+            .flat_map(|service| {
+                service.targets.iter().filter_map(|target| {
+                    Some(Repo {
+                        service: service.name.clone(),
+                        hostname: service.hostname.clone(),
+                        destination_name: target.clone(),
+                        destination: destinations.get(target)?.clone(),
+                    })
+                })
+            })
+            .collect();
+        repos.sort_by(|a, b| (&a.service, &a.destination_name).cmp(&(&b.service, &b.destination_name)));
+
+        Self {
+            global: global.clone(),
+            repos,
+            restic,
+            entries: RwLock::new(HashMap::new()),
+            refresh_tx: Mutex::new(None),
+        }
+    }
+
+    fn key(service: &str, destination: &str) -> String {
+        format!("{}/{}", service, destination)
+    }
+
+    /// Cached repositories, optionally of one service only
+    ///
+    /// Repositories that weren't listed yet are returned without snapshots
+    /// and without `refreshed_at`.
+    pub fn get(&self, service: Option<&str>) -> Vec<RepoSnapshots> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        self.repos
+            .iter()
+            .filter(|repo| service.is_none_or(|s| s == repo.service))
+            .map(|repo| {
+                entries
+                    .get(&Self::key(&repo.service, &repo.destination_name))
+                    .cloned()
+                    .unwrap_or_else(|| RepoSnapshots {
+                        service: repo.service.clone(),
+                        destination: repo.destination_name.clone(),
+                        snapshots: Vec::new(),
+                        total_size: None,
+                        refreshed_at: None,
+                        error: None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Whether a service has any cached repositories
+    pub fn has_service(&self, service: &str) -> bool {
+        self.repos.iter().any(|repo| repo.service == service)
+    }
+
+    /// List snapshots and size of one repository (outside the lock)
+    fn fetch(&self, repo: &Repo) -> anyhow::Result<(Vec<Snapshot>, Option<u64>)> {
+        let url = restic::build_repository_url(&repo.destination, &repo.service, None);
+        let env = restic::ResticEnv::for_destination(&self.global, &repo.destination_name, &repo.destination, &url)?
+            .with_host(repo.hostname.as_deref());
+        let snapshots = self.restic.list_snapshots(&env, LIST_TIMEOUT)?;
+        // Sizing a cold storage repository reads from the archive tier
+        let size = if env.is_cold_storage() {
+            None
+        } else {
+            self.restic.get_stats(&env, LIST_TIMEOUT).ok().map(|stats| stats.total_size)
+        };
+        Ok((snapshots, size))
+    }
+
+    /// Refresh every repository now (blocks until done)
+    pub fn refresh_all(&self) {
+        debug!("Refreshing snapshot cache ({} repositories)", self.repos.len());
+        for repo in &self.repos {
+            let result = self.fetch(repo);
+            let key = Self::key(&repo.service, &repo.destination_name);
+
+            let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+            let entry = entries.entry(key).or_insert_with(|| RepoSnapshots {
+                service: repo.service.clone(),
+                destination: repo.destination_name.clone(),
+                snapshots: Vec::new(),
+                total_size: None,
+                refreshed_at: None,
+                error: None,
+            });
+            match result {
+                Ok((snapshots, size)) => {
+                    entry.snapshots = snapshots;
+                    entry.total_size = size;
+                    entry.refreshed_at = Some(Utc::now());
+                    entry.error = None;
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to refresh snapshots of '{}' on '{}': {:#}",
+                        repo.service, repo.destination_name, e
+                    );
+                    entry.error = Some(format!("{:#}", e));
+                }
+            }
+        }
+    }
+
+    /// Take over the entries of repositories `previous` also tracked, so a
+    /// rebuilt cache (config reload) doesn't start out empty
+    pub fn carry_over(&self, previous: &SnapshotCache) {
+        let old = previous.entries.read().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        for repo in &self.repos {
+            let key = Self::key(&repo.service, &repo.destination_name);
+            if let Some(entry) = old.get(&key) {
+                entries.insert(key, entry.clone());
+            }
+        }
+    }
+
+    /// Stop the background thread (after its current refresh)
+    pub fn stop_refresher(&self) {
+        self.refresh_tx.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    /// Ask the background thread for a refresh; returns false if it isn't running
+    pub fn request_refresh(&self) -> bool {
+        let tx = self.refresh_tx.lock().unwrap_or_else(|e| e.into_inner());
+        tx.as_ref().is_some_and(|tx| tx.send(()).is_ok())
+    }
+
+    /// Start the background refresh, every `ttl` (0: only when requested)
+    pub fn spawn_refresher(self: &Arc<Self>, ttl: Duration) {
+        let (tx, rx) = mpsc::channel();
+        *self.refresh_tx.lock().unwrap_or_else(|e| e.into_inner()) = Some(tx);
+
+        let cache = Arc::clone(self);
+        thread::spawn(move || {
+            info!("Snapshot cache: refreshing {} repositories", cache.repos.len());
+            cache.refresh_all();
+            while wait_for_refresh(&rx, ttl) {
+                cache.refresh_all();
+            }
+        });
+    }
+}
+
+/// Wait until the next refresh is due or requested; false once the cache is gone
+fn wait_for_refresh(rx: &Receiver<()>, ttl: Duration) -> bool {
+    let result = if ttl.is_zero() {
+        rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+    } else {
+        rx.recv_timeout(ttl)
+    };
+    match result {
+        Ok(()) => {
+            // Requests that piled up during a refresh need only one more
+            while rx.try_recv().is_ok() {}
+            true
+        }
+        Err(RecvTimeoutError::Timeout) => true,
+        Err(RecvTimeoutError::Disconnected) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DestinationType, RetentionPolicy};
+    use crate::utils::restic_ops::mock::MockResticOps;
+
+    fn service(name: &str, targets: &[&str], enabled: bool) -> ResolvedServiceConfig {
+        ResolvedServiceConfig {
+            name: name.to_string(),
+            enabled,
+            disabled_until: None,
+            description: String::new(),
+            schedule: "0 2 * * *".to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            retention: RetentionPolicy { daily: 7, weekly: 4, monthly: 6, yearly: 1, ..Default::default() },
+            timeout_seconds: 60,
+            retry: Default::default(),
+            notify_on: Vec::new(),
+            tags: Vec::new(),
+            hostname: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            config: None,
+            post_backup_verify: Default::default(),
+            time_budget_minutes: None,
+            max_age_hours: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_cache_refresh() {
+        let destinations = HashMap::from([(
+            "local".to_string(),
+            Destination { dest_type: DestinationType::Local, url: "/backups".to_string(), ..Default::default() },
+        )]);
+        let services = HashMap::from([
+            ("app".to_string(), service("app", &["local"], true)),
+            ("old".to_string(), service("old", &["local"], false)),
+        ]);
+        let snapshot = Snapshot {
+            id: "abc123def456".to_string(),
+            short_id: "abc123".to_string(),
+            time: "2025-12-28T10:30:00Z".to_string(),
+            hostname: "testhost".to_string(),
+            paths: vec!["/data".to_string()],
+            tags: vec![],
+            ..Default::default()
+        };
+        let restic = Arc::new(MockResticOps::new().with_snapshots(vec![snapshot]));
+        let cache = SnapshotCache::new(&GlobalConfig::default(), &destinations, &services, restic);
+
+        assert!(cache.has_service("app"));
+        assert!(!cache.has_service("old"));
+
+        let before = cache.get(Some("app"));
+        assert_eq!(before.len(), 1);
+        assert!(before[0].refreshed_at.is_none());
+        assert!(!cache.request_refresh());
+
+        cache.refresh_all();
+        let after = cache.get(None);
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].destination, "local");
+        assert_eq!(after[0].snapshots[0].short_id, "abc123");
+        assert!(after[0].refreshed_at.is_some());
+        assert!(after[0].error.is_none());
+
+        // A rebuilt cache keeps the entries of repositories it still tracks
+        let services = HashMap::from([("app".to_string(), service("app", &["local"], true))]);
+        let rebuilt = SnapshotCache::new(&GlobalConfig::default(), &destinations, &services, Arc::new(MockResticOps::new()));
+        rebuilt.carry_over(&cache);
+        assert_eq!(rebuilt.get(Some("app"))[0].snapshots[0].short_id, "abc123");
+    }
+}
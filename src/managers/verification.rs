@@ -0,0 +1,188 @@
+//! Verification of a new snapshot right after the backup
+//!
+//! `metadata` runs `restic check` without reading pack data. `sample`
+//! restores a few small files into a temporary directory and compares their
+//! SHA-256 hashes with the files on disk, which shows the data can actually
+//! be read back.
+
+use crate::config::PostBackupVerify;
+use crate::utils::restic::{self, ResticEnv, SnapshotFile};
+use anyhow::{Context, Result};
+use rand::seq::IndexedRandom;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+/// Number of files restored by a sample verification
+pub const SAMPLE_FILES: usize = 5;
+
+/// Only files up to this size are sampled
+pub const SAMPLE_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Verify a snapshot, returning a short description of what was checked
+///
+/// Returns `None` when verification is off and an error when it failed.
+pub fn verify_snapshot(
+    env: &ResticEnv,
+    mode: PostBackupVerify,
+    snapshot_id: &str,
+    work_dir: &Path,
+    timeout: Duration,
+) -> Result<Option<String>> {
+    match mode {
+        PostBackupVerify::Off => Ok(None),
+        PostBackupVerify::Metadata => {
+            restic::check_repository(env, false, timeout)
+                .and_then(|result| result.ensure_passed())
+                .context("Repository check failed")?;
+            Ok(Some("repository check passed".to_string()))
+        }
+        PostBackupVerify::Sample if env.is_cold_storage() => {
+            // Restoring samples would retrieve data from the archive tier
+            info!("Cold storage repository: checking metadata instead of sampling files");
+            restic::check_repository(env, false, timeout)
+                .and_then(|result| result.ensure_passed())
+                .context("Repository check failed")?;
+            Ok(Some("repository check passed (cold storage, no sample)".to_string()))
+        }
+        PostBackupVerify::Sample => verify_sample(env, snapshot_id, work_dir, timeout).map(Some),
+    }
+}
+
+/// Restore a random sample of small files and compare them with the originals
+fn verify_sample(env: &ResticEnv, snapshot_id: &str, work_dir: &Path, timeout: Duration) -> Result<String> {
+    let files = restic::list_snapshot_entries(env, snapshot_id, timeout)?;
+    let candidates = sample_candidates(&files);
+
+    if candidates.is_empty() {
+        return Ok("no unchanged small files to sample".to_string());
+    }
+
+    let sample: Vec<&SnapshotFile> = candidates
+        .choose_multiple(&mut rand::rng(), SAMPLE_FILES)
+        .copied()
+        .collect();
+
+    let restore_dir = work_dir.join("verify").join(snapshot_id);
+    fs::create_dir_all(&restore_dir).context("Failed to create verification directory")?;
+
+    let result = restore_and_compare(env, snapshot_id, &sample, &restore_dir, timeout);
+
+    if let Err(e) = fs::remove_dir_all(&restore_dir) {
+        tracing::warn!("Failed to remove verification directory: {}", e);
+    }
+
+    result
+}
+
+fn restore_and_compare(
+    env: &ResticEnv,
+    snapshot_id: &str,
+    sample: &[&SnapshotFile],
+    restore_dir: &Path,
+    timeout: Duration,
+) -> Result<String> {
+    let includes: Vec<String> = sample.iter().map(|f| f.path.clone()).collect();
+    restic::restore_files(env, snapshot_id, restore_dir.to_str(), &includes, &restic::RestoreLimits::default(), timeout)
+        .context("Failed to restore sample files")?;
+
+    let mut mismatches = Vec::new();
+    for file in sample {
+        let restored = restored_path(restore_dir, &file.path);
+        let matches = match (file_hash(&restored), file_hash(Path::new(&file.path))) {
+            (Ok(restored), Ok(original)) => restored == original,
+            _ => false,
+        };
+        if !matches {
+            mismatches.push(file.path.as_str());
+        }
+    }
+
+    if !mismatches.is_empty() {
+        anyhow::bail!(
+            "{} of {} sampled files differ from the originals: {}",
+            mismatches.len(),
+            sample.len(),
+            mismatches.join(", ")
+        );
+    }
+
+    info!("Sample verification of snapshot {}: {} files match", snapshot_id, sample.len());
+    Ok(format!("{} sampled files match", sample.len()))
+}
+
+/// Files that can be compared with the originals
+///
+/// Small regular files whose original still exists with the size recorded in
+/// the snapshot (files changed since the backup would differ anyway).
+pub fn sample_candidates(files: &[SnapshotFile]) -> Vec<&SnapshotFile> {
+    files
+        .iter()
+        .filter(|f| f.size <= SAMPLE_MAX_FILE_SIZE)
+        .filter(|f| {
+            fs::metadata(&f.path)
+                .map(|m| m.is_file() && m.len() == f.size)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Where `restic restore --target <dir>` puts a snapshot path
+fn restored_path(restore_dir: &Path, snapshot_path: &str) -> PathBuf {
+    restore_dir.join(snapshot_path.trim_start_matches('/'))
+}
+
+/// SHA-256 of a file's contents
+fn file_hash(path: &Path) -> Result<Vec<u8>> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sample_candidates() {
+        let dir = TempDir::new().unwrap();
+        let small = dir.path().join("small.txt");
+        let changed = dir.path().join("changed.txt");
+        fs::write(&small, "hello").unwrap();
+        fs::write(&changed, "grown since the backup").unwrap();
+
+        let file = |path: &Path, size| SnapshotFile { path: path.to_string_lossy().into_owned(), size };
+        let files = vec![
+            file(&small, 5),
+            file(&changed, 3),
+            file(&dir.path().join("deleted.txt"), 5),
+            file(&dir.path().join("huge.bin"), SAMPLE_MAX_FILE_SIZE + 1),
+        ];
+
+        let candidates = sample_candidates(&files);
+        assert_eq!(candidates, vec![&files[0]]);
+    }
+
+    #[test]
+    fn test_file_hash_and_restored_path() {
+        let dir = TempDir::new().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        fs::write(&a, "same").unwrap();
+        fs::write(&b, "same").unwrap();
+        assert_eq!(file_hash(&a).unwrap(), file_hash(&b).unwrap());
+
+        fs::write(&b, "different").unwrap();
+        assert_ne!(file_hash(&a).unwrap(), file_hash(&b).unwrap());
+
+        assert_eq!(
+            restored_path(Path::new("/tmp/verify"), "/data/app/config.yml"),
+            PathBuf::from("/tmp/verify/data/app/config.yml")
+        );
+    }
+}
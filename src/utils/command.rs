@@ -1,22 +1,76 @@
 //! Utilities for running commands with proper error handling and timeouts
 
 use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Write;
 use std::path::Path;
-use std::process::{Command, Output, Stdio};
+use std::process::{Command, ExitStatus, Output, Stdio};
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 use tracing::{debug, error, info};
 
+/// Error returned when a command exceeds its timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandTimeout(pub Duration);
+
+impl fmt::Display for CommandTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command timed out after {:?}", self.0)
+    }
+}
+
+impl std::error::Error for CommandTimeout {}
+
+/// Error returned when a command was interrupted (SIGINT/SIGTERM)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandCancelled;
+
+impl fmt::Display for CommandCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command was interrupted")
+    }
+}
+
+impl std::error::Error for CommandCancelled {}
+
+/// Whether a process exited because it was interrupted or terminated
+///
+/// Covers both death by SIGINT/SIGTERM and the conventional exit codes
+/// 130/143 used by programs (like restic) that handle the signal themselves.
+pub fn is_interrupted(status: &ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if matches!(status.signal(), Some(2) | Some(15)) {
+            return true;
+        }
+    }
+
+    matches!(status.code(), Some(130) | Some(143))
+}
+
 /// Run a command with optional timeout using thread-based implementation
 pub fn run_command(
     program: &str,
     args: &[&str],
     working_dir: Option<&Path>,
     timeout: Option<Duration>,
+) -> Result<Output> {
+    run_command_with_env(program, args, working_dir, timeout, &[])
+}
+
+/// Run a command with extra environment variables
+pub fn run_command_with_env(
+    program: &str,
+    args: &[&str],
+    working_dir: Option<&Path>,
+    timeout: Option<Duration>,
+    env: &[(String, String)],
 ) -> Result<Output> {
     let mut cmd = Command::new(program);
     cmd.args(args);
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
 
@@ -26,26 +80,108 @@ pub fn run_command(
 
     debug!("Running command: {} {}", program, args.join(" "));
 
+    let output = output_within(cmd, program, timeout)?;
+    check_output(program, args, output)
+}
+
+/// Run a command that prints a secret (e.g. a password command)
+///
+/// Like `run_command`, but stdin is closed and the output is never logged.
+pub fn run_command_secret(program: &str, args: &[&str], timeout: Duration) -> Result<Output> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    debug!("Running command: {} {}", program, args.join(" "));
+
+    let output = output_within(cmd, program, Some(timeout))?;
+    check_status(program, args, output)
+}
+
+/// Wait for a command's output, failing with `CommandTimeout` after `timeout`
+fn output_within(mut cmd: Command, program: &str, timeout: Option<Duration>) -> Result<Output> {
+    let Some(timeout_duration) = timeout else {
+        return cmd.output().context(format!("Failed to execute {}", program));
+    };
+
+    // Thread-based timeout implementation
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let result = cmd.output();
+        let _ = tx.send(result);
+    });
+
+    match rx.recv_timeout(timeout_duration) {
+        Ok(result) => result.context(format!("Failed to execute {}", program)),
+        Err(_) => Err(CommandTimeout(timeout_duration).into()),
+    }
+}
+
+/// Run a command with `input` written to its stdin
+pub fn run_command_with_input(
+    program: &str,
+    args: &[&str],
+    input: &str,
+    timeout: Option<Duration>,
+) -> Result<Output> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd.stdin(Stdio::piped());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    debug!("Running command with input: {} {}", program, args.join(" "));
+
+    let input = input.to_string();
+    let mut run = move || -> std::io::Result<Output> {
+        let mut child = cmd.spawn()?;
+        // Dropping stdin after writing closes it, so the program sees EOF
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes())?;
+        }
+        child.wait_with_output()
+    };
+
     let output = if let Some(timeout_duration) = timeout {
-        // Thread-based timeout implementation
         let (tx, rx) = mpsc::channel();
 
         thread::spawn(move || {
-            let result = cmd.output();
-            let _ = tx.send(result);
+            let _ = tx.send(run());
         });
 
         match rx.recv_timeout(timeout_duration) {
             Ok(result) => result.context(format!("Failed to execute {}", program))?,
-            Err(_) => {
-                anyhow::bail!("Command timed out after {:?}", timeout_duration);
-            }
+            Err(_) => return Err(CommandTimeout(timeout_duration).into()),
         }
     } else {
-        cmd.output()
-            .context(format!("Failed to execute {}", program))?
+        run().context(format!("Failed to execute {}", program))?
     };
 
+    check_output(program, args, output)
+}
+
+/// Turn an interrupted or failed command into an error, logging its output
+fn check_output(program: &str, args: &[&str], output: Output) -> Result<Output> {
+    let output = check_status(program, args, output)?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.is_empty() {
+        debug!("Command output: {}", stdout);
+    }
+
+    Ok(output)
+}
+
+/// Turn an interrupted or failed command into an error
+fn check_status(program: &str, args: &[&str], output: Output) -> Result<Output> {
+    if is_interrupted(&output.status) {
+        error!("Command interrupted: {} {}", program, args.join(" "));
+        return Err(CommandCancelled.into());
+    }
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         error!("Command failed: {} {}", program, args.join(" "));
@@ -57,11 +193,6 @@ pub fn run_command(
         );
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    if !stdout.is_empty() {
-        debug!("Command output: {}", stdout);
-    }
-
     Ok(output)
 }
 
@@ -76,11 +207,28 @@ pub fn run_command_stdout(
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// Free bytes of the filesystem holding `path` (`df`)
+pub fn free_space(path: &Path) -> Result<u64> {
+    let path = path.to_string_lossy();
+    let output = run_command_stdout("df", &["-Pk", &path], None, Some(Duration::from_secs(30)))?;
+    super::docker::parse_df_available(&output)
+}
+
 /// Run a shell command (useful for hooks)
 pub fn run_shell_command(
     command: &str,
     working_dir: Option<&Path>,
     timeout: Option<Duration>,
+) -> Result<Output> {
+    run_shell_command_with_env(command, working_dir, timeout, &[])
+}
+
+/// Run a shell command with extra environment variables
+pub fn run_shell_command_with_env(
+    command: &str,
+    working_dir: Option<&Path>,
+    timeout: Option<Duration>,
+    env: &[(String, String)],
 ) -> Result<Output> {
     info!("Running shell command: {}", command);
 
@@ -90,5 +238,5 @@ pub fn run_shell_command(
     #[cfg(windows)]
     let (shell, flag) = ("cmd", "/C");
 
-    run_command(shell, &[flag, command], working_dir, timeout)
+    run_command_with_env(shell, &[flag, command], working_dir, timeout, env)
 }
@@ -0,0 +1,119 @@
+//! Docker Compose stack control (stopping a stack for the duration of a backup)
+
+use super::command::run_command;
+use crate::config::ContainerRuntime;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Time allowed for `docker compose stop` / `start`
+pub const COMPOSE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A Docker Compose stack, identified by project name and/or compose file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposeStack {
+    pub project: Option<String>,
+    pub file: Option<PathBuf>,
+    /// Runtime whose `compose` subcommand manages the stack
+    pub runtime: ContainerRuntime,
+}
+
+impl ComposeStack {
+    /// Arguments for `docker compose <action>` on this stack
+    pub fn args(&self, action: &str) -> Vec<String> {
+        let mut args = vec!["compose".to_string()];
+        if let Some(ref project) = self.project {
+            args.push("-p".to_string());
+            args.push(project.clone());
+        }
+        if let Some(ref file) = self.file {
+            args.push("-f".to_string());
+            args.push(file.display().to_string());
+        }
+        args.push(action.to_string());
+        args
+    }
+
+    /// Name used in log messages
+    pub fn label(&self) -> String {
+        match (&self.project, &self.file) {
+            (Some(project), _) => project.clone(),
+            (None, Some(file)) => file.display().to_string(),
+            (None, None) => "default".to_string(),
+        }
+    }
+
+    fn run(&self, action: &str) -> Result<()> {
+        let args = self.args(action);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_command(self.runtime.binary(), &args, None, Some(COMPOSE_TIMEOUT))?;
+        Ok(())
+    }
+
+    /// Stop the stack; it is started again when the returned guard is
+    /// dropped, including on early returns and panics
+    pub fn stop(&self) -> Result<StoppedStack> {
+        info!("Stopping Docker Compose stack: {}", self.label());
+        self.run("stop")
+            .context(format!("Failed to stop Docker Compose stack '{}'", self.label()))?;
+        Ok(StoppedStack { stack: self.clone(), started: false })
+    }
+}
+
+/// A stopped stack, started again on drop
+#[derive(Debug)]
+pub struct StoppedStack {
+    stack: ComposeStack,
+    started: bool,
+}
+
+impl StoppedStack {
+    /// Start the stack now, reporting failures to the caller
+    pub fn start(mut self) -> Result<()> {
+        self.started = true;
+        info!("Starting Docker Compose stack: {}", self.stack.label());
+        self.stack
+            .run("start")
+            .context(format!("Failed to start Docker Compose stack '{}'", self.stack.label()))
+    }
+}
+
+impl Drop for StoppedStack {
+    fn drop(&mut self) {
+        if self.started {
+            return;
+        }
+        info!("Starting Docker Compose stack (backup ended early): {}", self.stack.label());
+        if let Err(e) = self.stack.run("start") {
+            error!("Failed to start Docker Compose stack '{}': {:#}", self.stack.label(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_args() {
+        let stack = ComposeStack {
+            project: Some("immich".to_string()),
+            file: Some(PathBuf::from("/opt/immich/docker-compose.yml")),
+            runtime: ContainerRuntime::Docker,
+        };
+        assert_eq!(
+            stack.args("stop").join(" "),
+            "compose -p immich -f /opt/immich/docker-compose.yml stop"
+        );
+        assert_eq!(stack.label(), "immich");
+
+        let stack = ComposeStack {
+            project: None,
+            file: Some(PathBuf::from("compose.yml")),
+            runtime: ContainerRuntime::Podman,
+        };
+        assert_eq!(stack.args("start").join(" "), "compose -f compose.yml start");
+        assert_eq!(stack.label(), "compose.yml");
+    }
+}
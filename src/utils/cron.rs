@@ -4,10 +4,12 @@
 
 #![allow(dead_code)]
 
+use crate::say;
 use anyhow::{Context, Result};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 use tracing::{info, warn};
 
 /// Get the path to the restic-manager binary
@@ -65,34 +67,75 @@ pub fn set_crontab(content: &str) -> Result<()> {
     Ok(())
 }
 
+/// Marker comment above the job of a service
+fn service_marker(service_name: &str) -> String {
+    format!("# Restic Manager - Service: {}", service_name)
+}
+
+/// Marker comment above the scheduled verify job
+const VERIFY_MARKER: &str = "# Restic Manager - Verify";
+
 /// Add a cron job for a service
 pub fn add_cron_job(
     service_name: &str,
     schedule: &str,
-    config_path: &PathBuf,
+    config_path: &Path,
     dry_run: bool,
 ) -> Result<()> {
     let binary_path = get_binary_path()?;
     let log_file = format!("/var/log/restic-manager/{}.log", service_name);
 
-    // Build the cron command
+    // Build the cron command (its snapshots are tagged `scheduled`)
     let cron_command = format!(
-        "{} --config {} run --service {} >> {} 2>&1",
+        "{} --config {} run --service {} --scheduled >> {} 2>&1",
         binary_path.display(),
         config_path.display(),
         service_name,
         log_file
     );
 
-    // Build the cron entry
-    let cron_entry = format!(
-        "# Restic Manager - Service: {}\n{} {}",
-        service_name, schedule, cron_command
+    install_entry(&service_marker(service_name), schedule, &cron_command, dry_run)?;
+    if !dry_run {
+        info!("Added cron job for service: {}", service_name);
+    }
+    Ok(())
+}
+
+/// Add the cron job running `verify` over all services, reading back
+/// `read_data_subset` of the data when set
+pub fn add_verify_cron_job(
+    schedule: &str,
+    read_data_subset: Option<&str>,
+    config_path: &Path,
+    dry_run: bool,
+) -> Result<()> {
+    let binary_path = get_binary_path()?;
+    let subset_arg = read_data_subset
+        .map(|subset| format!(" --read-data-subset={}", subset))
+        .unwrap_or_default();
+
+    let cron_command = format!(
+        "{} --config {} verify{} >> /var/log/restic-manager/verify.log 2>&1",
+        binary_path.display(),
+        config_path.display(),
+        subset_arg
     );
 
+    install_entry(VERIFY_MARKER, schedule, &cron_command, dry_run)?;
+    if !dry_run {
+        info!("Added scheduled verify cron job");
+    }
+    Ok(())
+}
+
+/// Add (or replace) the entry below a marker comment
+fn install_entry(marker: &str, schedule: &str, command: &str, dry_run: bool) -> Result<()> {
+    // Build the cron entry
+    let cron_entry = format!("{}\n{} {}", marker, schedule, crontab_escape(command));
+
     if dry_run {
-        println!("  [DRY RUN] Would add cron job:");
-        println!("    {}", cron_entry.replace('\n', "\n    "));
+        say!("  [DRY RUN] Would add cron job:");
+        say!("    {}", cron_entry.replace('\n', "\n    "));
         return Ok(());
     }
 
@@ -100,28 +143,10 @@ pub fn add_cron_job(
     let existing = get_crontab()?;
 
     // Check if job already exists
-    let marker = format!("# Restic Manager - Service: {}", service_name);
-    if existing.contains(&marker) {
-        warn!("Cron job for service '{}' already exists, updating...", service_name);
-
-        // Remove old entry
-        let lines: Vec<&str> = existing.lines().collect();
-        let mut new_lines = Vec::new();
-        let mut skip_next = false;
-
-        for line in lines {
-            if line.contains(&marker) {
-                skip_next = true;
-                continue;
-            }
-            if skip_next {
-                skip_next = false;
-                continue;
-            }
-            new_lines.push(line);
-        }
+    if has_marker(&existing, marker) {
+        warn!("Cron job '{}' already exists, updating...", marker.trim_start_matches("# Restic Manager - "));
 
-        // Add new entry
+        let mut new_lines = without_entry(&existing, marker);
         new_lines.push(&cron_entry);
 
         let new_content = new_lines.join("\n") + "\n";
@@ -137,27 +162,21 @@ pub fn add_cron_job(
         set_crontab(&new_content)?;
     }
 
-    info!("Added cron job for service: {}", service_name);
     Ok(())
 }
 
-/// Remove cron job for a service
-pub fn remove_cron_job(service_name: &str) -> Result<()> {
-    let existing = get_crontab()?;
-    let marker = format!("# Restic Manager - Service: {}", service_name);
-
-    if !existing.contains(&marker) {
-        warn!("No cron job found for service '{}'", service_name);
-        return Ok(());
-    }
+/// Whether the crontab has an entry with exactly this marker
+fn has_marker(crontab: &str, marker: &str) -> bool {
+    crontab.lines().any(|line| line.trim_end() == marker)
+}
 
-    // Remove the entry
-    let lines: Vec<&str> = existing.lines().collect();
+/// Crontab lines without the marker and the job line following it
+fn without_entry<'a>(crontab: &'a str, marker: &str) -> Vec<&'a str> {
     let mut new_lines = Vec::new();
     let mut skip_next = false;
 
-    for line in lines {
-        if line.contains(&marker) {
+    for line in crontab.lines() {
+        if line.trim_end() == marker {
             skip_next = true;
             continue;
         }
@@ -167,27 +186,158 @@ pub fn remove_cron_job(service_name: &str) -> Result<()> {
         }
         new_lines.push(line);
     }
+    new_lines
+}
 
-    let new_content = new_lines.join("\n") + "\n";
-    set_crontab(&new_content)?;
+/// cron treats `%` in a command as a newline unless escaped
+fn crontab_escape(command: &str) -> String {
+    command.replace('%', "\\%")
+}
 
-    info!("Removed cron job for service: {}", service_name);
+/// Remove cron job for a service
+pub fn remove_cron_job(service_name: &str) -> Result<()> {
+    if remove_entry(&service_marker(service_name))? {
+        info!("Removed cron job for service: {}", service_name);
+    } else {
+        warn!("No cron job found for service '{}'", service_name);
+    }
     Ok(())
 }
 
+/// Remove the scheduled verify job, returning whether there was one
+pub fn remove_verify_cron_job() -> Result<bool> {
+    let removed = remove_entry(VERIFY_MARKER)?;
+    if removed {
+        info!("Removed scheduled verify cron job");
+    }
+    Ok(removed)
+}
+
+fn remove_entry(marker: &str) -> Result<bool> {
+    let existing = get_crontab()?;
+    if !has_marker(&existing, marker) {
+        return Ok(false);
+    }
+
+    let new_content = without_entry(&existing, marker).join("\n") + "\n";
+    set_crontab(&new_content)?;
+    Ok(true)
+}
+
+/// Services of `service_names` without a job in the crontab
+pub fn missing_cron_jobs<'a>(service_names: &[&'a str]) -> Result<Vec<&'a str>> {
+    let crontab = get_crontab()?;
+    Ok(service_names
+        .iter()
+        .copied()
+        .filter(|name| {
+            let marker = service_marker(name);
+            !crontab.lines().any(|line| line.trim_end() == marker)
+        })
+        .collect())
+}
+
 /// Validate cron schedule syntax
 pub fn validate_cron_schedule(schedule: &str) -> bool {
     // Basic validation: should have 5 fields
     schedule.split_whitespace().count() == 5
 }
 
+/// Longest gap between two runs of a cron schedule
+///
+/// Understands the five standard fields with `*`, lists, ranges, steps and
+/// month/weekday names. The runs of four years (one leap day) are walked,
+/// so monthly and yearly schedules are measured too. None if the schedule
+/// can't be read or runs less than twice in that time.
+pub fn schedule_interval(schedule: &str) -> Option<Duration> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    let minutes = parse_cron_field(fields[0], 0, 59, &[])?;
+    let hours = parse_cron_field(fields[1], 0, 23, &[])?;
+    let days = parse_cron_field(fields[2], 1, 31, &[])?;
+    let months = parse_cron_field(fields[3], 1, 12, &MONTHS)?;
+    // 0 and 7 are both Sunday
+    let weekdays = parse_cron_field(fields[4], 0, 7, &WEEKDAYS)?;
+    let weekdays = (weekdays | (weekdays >> 7)) & 0x7f;
+    // With both day fields restricted, cron runs on days matching either
+    let either_day = !fields[2].starts_with('*') && !fields[4].starts_with('*');
+
+    let start = chrono::NaiveDate::from_ymd_opt(2023, 1, 1)?;
+    let mut previous: Option<i64> = None;
+    let mut longest: i64 = 0;
+    for date in start.iter_days().take(4 * 365 + 1) {
+        use chrono::Datelike;
+        let day_matches = days & (1 << date.day()) != 0;
+        let weekday_matches = weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        let runs_today = months & (1 << date.month()) != 0
+            && if either_day { day_matches || weekday_matches } else { day_matches && weekday_matches };
+        if !runs_today {
+            continue;
+        }
+
+        let day_start = (date - start).num_minutes();
+        for hour in (0..24).filter(|h| hours & (1 << h) != 0) {
+            for minute in (0..60).filter(|m| minutes & (1 << m) != 0) {
+                let at = day_start + hour * 60 + minute;
+                if let Some(previous) = previous {
+                    longest = longest.max(at - previous);
+                }
+                previous = Some(at);
+            }
+        }
+    }
+
+    (longest > 0).then(|| Duration::from_secs(longest as u64 * 60))
+}
+
+/// Bit set of the values a cron field matches (bit n = value n)
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Option<u64> {
+    let value = |text: &str| -> Option<u32> {
+        let value = match names.iter().position(|name| text.eq_ignore_ascii_case(name)) {
+            // Names count from the field's minimum (jan = 1, sun = 0)
+            Some(index) => index as u32 + min,
+            None => text.parse().ok()?,
+        };
+        (min..=max).contains(&value).then_some(value)
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (value(first)?, value(last)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if first > last {
+            return None;
+        }
+        for n in (first..=last).step_by(step as usize) {
+            set |= 1 << n;
+        }
+    }
+    Some(set)
+}
+
 /// List all restic-manager cron jobs
 pub fn list_cron_jobs() -> Result<Vec<String>> {
     let existing = get_crontab()?;
     let mut jobs = Vec::new();
 
     for line in existing.lines() {
-        if line.contains("# Restic Manager - Service:") {
+        if line.contains("# Restic Manager - Service:") || line.trim_end() == VERIFY_MARKER {
             jobs.push(line.to_string());
         }
     }
@@ -208,4 +358,37 @@ mod tests {
         assert!(!validate_cron_schedule("0 2 * *"));
         assert!(!validate_cron_schedule("0 2 * * * *"));
     }
+
+    #[test]
+    fn test_schedule_interval() {
+        let hours = |h: u64| Some(Duration::from_secs(h * 3600));
+        assert_eq!(schedule_interval("0 2 * * *"), hours(24));
+        assert_eq!(schedule_interval("*/15 * * * *"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(schedule_interval("0 3 * * 0"), hours(7 * 24));
+        assert_eq!(schedule_interval("30 1 * * sun"), hours(7 * 24));
+        // Weekdays only: the gap over the weekend counts
+        assert_eq!(schedule_interval("0 2 * * 1-5"), hours(3 * 24));
+        assert_eq!(schedule_interval("0 2,14 * * *"), hours(12));
+        // Monthly: the longest month
+        assert_eq!(schedule_interval("0 4 1 * *"), hours(31 * 24));
+        assert_eq!(schedule_interval("0 4 1 jan,jul *"), hours(184 * 24));
+
+        assert_eq!(schedule_interval("invalid"), None);
+        assert_eq!(schedule_interval("0 25 * * *"), None);
+        assert_eq!(schedule_interval("0 2 * * mon-sun-x"), None);
+    }
+
+    #[test]
+    fn test_cron_entries() {
+        let crontab = "# Restic Manager - Service: app\n0 2 * * * run app\n# Restic Manager - Service: app-db\n0 3 * * * run app-db\n# Restic Manager - Verify\n0 4 * * 0 verify";
+
+        // Markers match whole lines, so "app" doesn't remove "app-db"
+        assert_eq!(
+            without_entry(crontab, &service_marker("app")),
+            vec!["# Restic Manager - Service: app-db", "0 3 * * * run app-db", "# Restic Manager - Verify", "0 4 * * 0 verify"]
+        );
+        assert!(has_marker(crontab, VERIFY_MARKER));
+        assert_eq!(without_entry(crontab, VERIFY_MARKER).len(), 4);
+        assert_eq!(crontab_escape("verify --read-data-subset=5%"), "verify --read-data-subset=5\\%");
+    }
 }
@@ -1,16 +1,56 @@
 //! Docker utilities for volume backup and restore
+//!
+//! Every function takes the [`ContainerRuntime`] whose CLI is run, so the
+//! same code drives Docker and Podman.
 
-use super::command::run_command_stdout;
+use super::command::{run_command, run_command_stdout, CommandTimeout};
+use crate::config::{ArchiveCompression, ContainerRuntime};
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tracing::info;
 
+/// Image of the helper container that reads and writes volumes
+///
+/// Fully qualified for Podman, which may refuse to resolve short names.
+pub fn helper_image(runtime: ContainerRuntime) -> &'static str {
+    match runtime {
+        ContainerRuntime::Docker => "alpine:latest",
+        ContainerRuntime::Podman => "docker.io/library/alpine:latest",
+    }
+}
+
+/// `run` arguments for a throwaway helper container
+fn helper_run_args(runtime: ContainerRuntime) -> Vec<&'static str> {
+    match runtime {
+        ContainerRuntime::Docker => vec!["run", "--rm"],
+        // SELinux hosts deny access to unlabeled bind mounts; disabling
+        // labeling for the helper avoids relabeling the host directory
+        ContainerRuntime::Podman => vec!["run", "--rm", "--security-opt", "label=disable"],
+    }
+}
+
+/// Podman's API socket
+///
+/// Rootless Podman listens under `$XDG_RUNTIME_DIR`; otherwise (and when
+/// that socket doesn't exist) the rootful socket is used.
+pub fn podman_socket(runtime_dir: Option<&Path>) -> PathBuf {
+    if let Some(dir) = runtime_dir {
+        let rootless = dir.join("podman").join("podman.sock");
+        if rootless.exists() {
+            return rootless;
+        }
+    }
+    PathBuf::from("/run/podman/podman.sock")
+}
+
 /// List all Docker volumes
-pub fn list_volumes(timeout: Duration) -> Result<Vec<String>> {
+pub fn list_volumes(runtime: ContainerRuntime, timeout: Duration) -> Result<Vec<String>> {
     let output = run_command_stdout(
-        "docker",
+        runtime.binary(),
         &["volume", "ls", "--format", "{{.Name}}"],
         None,
         Some(timeout),
@@ -20,14 +60,76 @@ pub fn list_volumes(timeout: Duration) -> Result<Vec<String>> {
 }
 
 /// Check if a Docker volume exists
-pub fn volume_exists(volume_name: &str, timeout: Duration) -> Result<bool> {
-    let volumes = list_volumes(timeout)?;
+pub fn volume_exists(runtime: ContainerRuntime, volume_name: &str, timeout: Duration) -> Result<bool> {
+    let volumes = list_volumes(runtime, timeout)?;
     Ok(volumes.iter().any(|v| v == volume_name))
 }
 
-/// Archive a Docker volume to a tar.gz file
+/// Plain tar the helper container writes or reads for an archive
+///
+/// The helper image has no zstd, so `.tar.zst` archives are (de)compressed
+/// on the host next to the plain tar.
+pub fn staged_tar_path(archive_path: &Path) -> PathBuf {
+    match ArchiveCompression::of_path(archive_path) {
+        ArchiveCompression::Zstd => archive_path.with_extension(""),
+        _ => archive_path.to_path_buf(),
+    }
+}
+
+/// `tar` flags creating (or extracting) the staged tar of an archive
+pub fn tar_flags(archive_path: &Path, create: bool) -> &'static str {
+    match (ArchiveCompression::of_path(archive_path), create) {
+        (ArchiveCompression::Gzip, true) => "czf",
+        (ArchiveCompression::Gzip, false) => "xzf",
+        (_, true) => "cf",
+        (_, false) => "xf",
+    }
+}
+
+/// Compress a staged tar into its `.tar.zst` archive, removing the tar
+pub fn compress_staged_tar(tar_path: &Path, archive_path: &Path) -> Result<()> {
+    if tar_path == archive_path {
+        return Ok(());
+    }
+
+    let mut input = fs::File::open(tar_path).context(format!("Failed to open {:?}", tar_path))?;
+    let output = fs::File::create(archive_path).context(format!("Failed to create {:?}", archive_path))?;
+    zstd::stream::copy_encode(&mut input, output, 0).context(format!("Failed to compress {:?}", tar_path))?;
+    fs::remove_file(tar_path).context(format!("Failed to remove {:?}", tar_path))
+}
+
+/// Decompress a `.tar.zst` archive into its staged tar
+pub fn decompress_to_staged_tar(archive_path: &Path, tar_path: &Path) -> Result<()> {
+    if tar_path == archive_path {
+        return Ok(());
+    }
+
+    let input = fs::File::open(archive_path).context(format!("Failed to open {:?}", archive_path))?;
+    let mut output = fs::File::create(tar_path).context(format!("Failed to create {:?}", tar_path))?;
+    zstd::stream::copy_decode(input, &mut output).context(format!("Failed to decompress {:?}", archive_path))
+}
+
+/// Size of an archive's tar once decompressed, in bytes
+///
+/// Compressed archives are decompressed to count, so this reads the whole
+/// file. The tar's headers and padding make it a little larger than the
+/// extracted files.
+pub fn archive_unpacked_size(archive_path: &Path) -> Result<u64> {
+    let file = fs::File::open(archive_path).context(format!("Failed to open {:?}", archive_path))?;
+    let size = match ArchiveCompression::of_path(archive_path) {
+        ArchiveCompression::None => file.metadata()?.len(),
+        ArchiveCompression::Gzip => std::io::copy(&mut flate2::read::MultiGzDecoder::new(file), &mut std::io::sink())
+            .context(format!("Failed to decompress {:?}", archive_path))?,
+        ArchiveCompression::Zstd => std::io::copy(&mut zstd::stream::read::Decoder::new(file)?, &mut std::io::sink())
+            .context(format!("Failed to decompress {:?}", archive_path))?,
+    };
+    Ok(size)
+}
+
+/// Archive a Docker volume to a tar, tar.gz or tar.zst file (by extension)
 /// Uses a temporary Alpine container to access the volume
 pub fn archive_volume(
+    runtime: ContainerRuntime,
     volume_name: &str,
     output_path: &Path,
     timeout: Duration,
@@ -41,8 +143,9 @@ pub fn archive_volume(
     }
 
     // Use docker run to mount volume and create archive
-    let output_dir = output_path.parent().unwrap_or(Path::new("."));
-    let output_file = output_path
+    let tar_path = staged_tar_path(output_path);
+    let output_dir = tar_path.parent().unwrap_or(Path::new("."));
+    let output_file = tar_path
         .file_name()
         .context("Invalid output path")?
         .to_str()
@@ -52,23 +155,22 @@ pub fn archive_volume(
     let backup_mount = format!("{}:/backup", output_dir.display());
     let output_arg = format!("/backup/{}", output_file);
 
-    let args = vec![
-        "run",
-        "--rm",
+    let mut args = helper_run_args(runtime);
+    args.extend([
         "-v",
         &volume_mount,
         "-v",
         &backup_mount,
-        "alpine",
+        helper_image(runtime),
         "tar",
-        "czf",
+        tar_flags(output_path, true),
         &output_arg,
         "-C",
         "/data",
         ".",
-    ];
+    ]);
 
-    let mut cmd = std::process::Command::new("docker");
+    let mut cmd = std::process::Command::new(runtime.binary());
     for arg in &args {
         cmd.arg(arg);
     }
@@ -82,23 +184,27 @@ pub fn archive_volume(
     });
 
     let output = match rx.recv_timeout(timeout) {
-        Ok(result) => result.context("Failed to execute docker run")?,
-        Err(_) => anyhow::bail!("Volume archiving timed out"),
+        Ok(result) => result.context(format!("Failed to execute {} run", runtime.binary()))?,
+        Err(_) => {
+            return Err(anyhow::Error::new(CommandTimeout(timeout)).context("Volume archiving timed out"))
+        }
     };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!("Failed to archive volume {}: {}", volume_name, stderr);
     }
+    compress_staged_tar(&tar_path, output_path)?;
 
     info!("Successfully archived volume: {}", volume_name);
     Ok(())
 }
 
-/// Extract a Docker volume from a tar.gz file
+/// Extract a Docker volume from a tar, tar.gz or tar.zst file (by extension)
 /// Uses a temporary Alpine container to restore the volume
 #[allow(dead_code)]
 pub fn restore_volume(
+    runtime: ContainerRuntime,
     volume_name: &str,
     archive_path: &Path,
     timeout: Duration,
@@ -109,8 +215,27 @@ pub fn restore_volume(
         anyhow::bail!("Archive file does not exist: {:?}", archive_path);
     }
 
-    let archive_dir = archive_path.parent().unwrap_or(Path::new("."));
-    let archive_file = archive_path
+    let tar_path = staged_tar_path(archive_path);
+    decompress_to_staged_tar(archive_path, &tar_path)?;
+    let result = extract_archive(runtime, volume_name, archive_path, &tar_path, timeout);
+    if tar_path != archive_path {
+        let _ = fs::remove_file(&tar_path);
+    }
+    result?;
+
+    info!("Successfully restored volume: {}", volume_name);
+    Ok(())
+}
+
+fn extract_archive(
+    runtime: ContainerRuntime,
+    volume_name: &str,
+    archive_path: &Path,
+    tar_path: &Path,
+    timeout: Duration,
+) -> Result<()> {
+    let archive_dir = tar_path.parent().unwrap_or(Path::new("."));
+    let archive_file = tar_path
         .file_name()
         .context("Invalid archive path")?
         .to_str()
@@ -120,22 +245,21 @@ pub fn restore_volume(
     let backup_mount = format!("{}:/backup", archive_dir.display());
     let archive_arg = format!("/backup/{}", archive_file);
 
-    let args = vec![
-        "run",
-        "--rm",
+    let mut args = helper_run_args(runtime);
+    args.extend([
         "-v",
         &volume_mount,
         "-v",
         &backup_mount,
-        "alpine",
+        helper_image(runtime),
         "tar",
-        "xzf",
+        tar_flags(archive_path, false),
         &archive_arg,
         "-C",
         "/data",
-    ];
+    ]);
 
-    let mut cmd = std::process::Command::new("docker");
+    let mut cmd = std::process::Command::new(runtime.binary());
     for arg in &args {
         cmd.arg(arg);
     }
@@ -149,36 +273,64 @@ pub fn restore_volume(
     });
 
     let output = match rx.recv_timeout(timeout) {
-        Ok(result) => result.context("Failed to execute docker run")?,
-        Err(_) => anyhow::bail!("Volume restoration timed out"),
+        Ok(result) => result.context(format!("Failed to execute {} run", runtime.binary()))?,
+        Err(_) => {
+            return Err(anyhow::Error::new(CommandTimeout(timeout)).context("Volume restoration timed out"))
+        }
     };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!("Failed to restore volume {}: {}", volume_name, stderr);
     }
+    Ok(())
+}
 
-    info!("Successfully restored volume: {}", volume_name);
+/// Remove a volume (if it exists) and create it again, empty
+pub fn recreate_volume(runtime: ContainerRuntime, volume_name: &str, timeout: Duration) -> Result<()> {
+    if volume_exists(runtime, volume_name, timeout)? {
+        run_command(runtime.binary(), &["volume", "rm", volume_name], None, Some(timeout))
+            .context(format!("Failed to remove volume {} (is it still in use?)", volume_name))?;
+    }
+    run_command(runtime.binary(), &["volume", "create", volume_name], None, Some(timeout))
+        .context(format!("Failed to create volume {}", volume_name))?;
+    Ok(())
+}
+
+/// Remove a volume if it exists
+pub fn remove_volume(runtime: ContainerRuntime, volume_name: &str, timeout: Duration) -> Result<()> {
+    if volume_exists(runtime, volume_name, timeout)? {
+        run_command(runtime.binary(), &["volume", "rm", volume_name], None, Some(timeout))
+            .context(format!("Failed to remove volume {} (is it still in use?)", volume_name))?;
+    }
     Ok(())
 }
 
+/// Command writing an uncompressed tar of a volume to stdout
+///
+/// Uncompressed so restic can deduplicate unchanged files between runs; the
+/// volume is mounted read-only.
+pub fn volume_tar_command(runtime: ContainerRuntime, volume_name: &str) -> Vec<String> {
+    let mut command = vec![runtime.binary().to_string()];
+    command.extend(helper_run_args(runtime).iter().map(|arg| arg.to_string()));
+    command.extend([
+        "-v".to_string(),
+        format!("{}:/data:ro", volume_name),
+        helper_image(runtime).to_string(),
+    ]);
+    command.extend(["tar", "cf", "-", "-C", "/data", "."].map(String::from));
+    command
+}
+
 /// Get the size of a Docker volume in bytes
 #[allow(dead_code)]
-pub fn get_volume_size(volume_name: &str, timeout: Duration) -> Result<u64> {
+pub fn get_volume_size(runtime: ContainerRuntime, volume_name: &str, timeout: Duration) -> Result<u64> {
     let volume_mount = format!("{}:/data", volume_name);
 
-    let args = vec![
-        "run",
-        "--rm",
-        "-v",
-        &volume_mount,
-        "alpine",
-        "du",
-        "-sb",
-        "/data",
-    ];
+    let mut args = helper_run_args(runtime);
+    args.extend(["-v", &volume_mount, helper_image(runtime), "du", "-sb", "/data"]);
 
-    let output = run_command_stdout("docker", &args, None, Some(timeout))?;
+    let output = run_command_stdout(runtime.binary(), &args, None, Some(timeout))?;
 
     // Parse output: "12345\t/data"
     let size_str = output
@@ -191,6 +343,193 @@ pub fn get_volume_size(volume_name: &str, timeout: Duration) -> Result<u64> {
         .context("Failed to parse volume size as number")
 }
 
+/// Free space in bytes of the filesystem backing a volume
+///
+/// Measured by `df` in a helper container with the volume mounted, so it is
+/// the Docker data root for the local driver and the driver's mount for
+/// others. A missing volume is created, as a restore into it would.
+pub fn get_volume_free_space(runtime: ContainerRuntime, volume_name: &str, timeout: Duration) -> Result<u64> {
+    let volume_mount = format!("{}:/data:ro", volume_name);
+
+    let mut args = helper_run_args(runtime);
+    args.extend(["-v", &volume_mount, helper_image(runtime), "df", "-Pk", "/data"]);
+
+    let output = run_command_stdout(runtime.binary(), &args, None, Some(timeout))?;
+    parse_df_available(&output)
+}
+
+/// Available bytes in the output of `df -Pk <path>`
+pub fn parse_df_available(output: &str) -> Result<u64> {
+    // Filesystem 1024-blocks Used Available Capacity Mounted-on; the
+    // filesystem name may contain spaces, so count from the end
+    let fields: Vec<&str> = output
+        .lines()
+        .nth(1)
+        .context("Failed to parse df output")?
+        .split_whitespace()
+        .collect();
+    let available = fields
+        .len()
+        .checked_sub(3)
+        .and_then(|i| fields[i].parse::<u64>().ok())
+        .context("Failed to parse available space from df output")?;
+    Ok(available * 1024)
+}
+
+/// Names of the running containers carrying a label (`key` or `key=value`)
+pub fn list_containers_with_label(runtime: ContainerRuntime, label: &str, timeout: Duration) -> Result<Vec<String>> {
+    let filter = format!("label={}", label);
+    let output = run_command_stdout(
+        runtime.binary(),
+        &["ps", "--filter", &filter, "--format", "{{.Names}}"],
+        None,
+        Some(timeout),
+    )?;
+
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// A container with its labels, the named volumes it mounts and the host
+/// directories bind-mounted into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerInfo {
+    pub name: String,
+    pub labels: HashMap<String, String>,
+    pub volumes: Vec<String>,
+    pub binds: Vec<PathBuf>,
+}
+
+/// A volume with its labels
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VolumeInfo {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Labels", default, deserialize_with = "null_as_default")]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct InspectedContainer {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Config")]
+    config: InspectedContainerConfig,
+    #[serde(rename = "Mounts", default, deserialize_with = "null_as_default")]
+    mounts: Vec<InspectedMount>,
+}
+
+#[derive(Deserialize)]
+struct InspectedContainerConfig {
+    #[serde(rename = "Labels", default, deserialize_with = "null_as_default")]
+    labels: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct InspectedMount {
+    #[serde(rename = "Type", default)]
+    kind: String,
+    #[serde(rename = "Name", default)]
+    name: Option<String>,
+    #[serde(rename = "Source", default)]
+    source: Option<String>,
+}
+
+/// Docker reports missing labels and mounts as `null`
+fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Parse `docker inspect` output of containers
+pub fn parse_container_inspect(json: &str) -> Result<Vec<ContainerInfo>> {
+    let containers: Vec<InspectedContainer> =
+        serde_json::from_str(json).context("Failed to parse container inspect output")?;
+
+    Ok(containers
+        .into_iter()
+        .map(|c| {
+            let mut volumes = Vec::new();
+            let mut binds = Vec::new();
+            for mount in c.mounts {
+                match mount.kind.as_str() {
+                    "volume" => volumes.extend(mount.name),
+                    "bind" => binds.extend(mount.source.map(PathBuf::from)),
+                    _ => {}
+                }
+            }
+            ContainerInfo { name: c.name.trim_start_matches('/').to_string(), labels: c.config.labels, volumes, binds }
+        })
+        .collect())
+}
+
+/// Parse `docker volume inspect` output
+pub fn parse_volume_inspect(json: &str) -> Result<Vec<VolumeInfo>> {
+    serde_json::from_str(json).context("Failed to parse volume inspect output")
+}
+
+/// Containers (running or stopped) carrying a label, with their labels and volumes
+pub fn inspect_containers_with_label(
+    runtime: ContainerRuntime,
+    label: &str,
+    timeout: Duration,
+) -> Result<Vec<ContainerInfo>> {
+    let filter = format!("label={}", label);
+    let ids = run_command_stdout(runtime.binary(), &["ps", "-aq", "--filter", &filter], None, Some(timeout))?;
+    let ids: Vec<&str> = ids.lines().map(str::trim).filter(|s| !s.is_empty()).collect();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["inspect"];
+    args.extend(ids);
+    parse_container_inspect(&run_command_stdout(runtime.binary(), &args, None, Some(timeout))?)
+}
+
+/// A container (running or stopped) by name, with its labels and mounts
+pub fn inspect_container(runtime: ContainerRuntime, name: &str, timeout: Duration) -> Result<ContainerInfo> {
+    let output = run_command_stdout(runtime.binary(), &["container", "inspect", name], None, Some(timeout))
+        .context(format!("Failed to inspect container {}", name))?;
+    parse_container_inspect(&output)?
+        .into_iter()
+        .next()
+        .context(format!("Container not found: {}", name))
+}
+
+/// Volumes carrying a label, with their labels
+pub fn inspect_volumes_with_label(runtime: ContainerRuntime, label: &str, timeout: Duration) -> Result<Vec<VolumeInfo>> {
+    let filter = format!("label={}", label);
+    let names = run_command_stdout(runtime.binary(), &["volume", "ls", "-q", "--filter", &filter], None, Some(timeout))?;
+    let names: Vec<&str> = names.lines().map(str::trim).filter(|s| !s.is_empty()).collect();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["volume", "inspect"];
+    args.extend(names);
+    parse_volume_inspect(&run_command_stdout(runtime.binary(), &args, None, Some(timeout))?)
+}
+
+/// Run `docker <action>` (pause, unpause, stop, start) on containers
+pub fn container_action(runtime: ContainerRuntime, action: &str, containers: &[String], timeout: Duration) -> Result<()> {
+    if containers.is_empty() {
+        return Ok(());
+    }
+
+    let mut args = vec![action];
+    args.extend(containers.iter().map(String::as_str));
+    run_command(runtime.binary(), &args, None, Some(timeout))
+        .context(format!("Failed to {} containers: {}", action, containers.join(", ")))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,8 +557,8 @@ mod tests {
 
     impl Drop for VolumeTestGuard {
         fn drop(&mut self) {
-            let out = std::process::Command::new("docker")
-                .args(&["volume", "rm", &self.name])
+            let _out = std::process::Command::new("docker")
+                .args(["volume", "rm", &self.name])
                 .output();
         }
     }
@@ -231,7 +570,7 @@ mod tests {
     #[ignore] // Requires Docker
     fn test_list_volumes_returns_vector() {
         let timeout = Duration::from_secs(10);
-        let result = list_volumes(timeout);
+        let result = list_volumes(ContainerRuntime::Docker, timeout);
         assert!(result.is_ok(), "Should list volumes successfully");
         // Result might be empty, but should be a valid Vec
         let _volumes = result.unwrap();
@@ -243,12 +582,12 @@ mod tests {
     fn test_volume_exists_with_timeout() {
         let timeout = Duration::from_nanos(1); // Very short timeout
         let guard = VolumeTestGuard::random();
-        let result = volume_exists(&guard.name, timeout);
+        let result = volume_exists(ContainerRuntime::Docker, &guard.name, timeout);
 
         // Should timeout or return false
-        match result {
-            Ok(exists) => assert!(!exists, "Volume should not exist"),
-            Err(_) => (), // Timeout error is acceptable
+        // Timeout error is acceptable
+        if let Ok(exists) = result {
+            assert!(!exists, "Volume should not exist");
         }
     }
 
@@ -257,9 +596,9 @@ mod tests {
     fn test_volume_exists_nonexistent_volume() {
         let timeout = Duration::from_secs(10);
         let guard = VolumeTestGuard::random();
-        let result = volume_exists(&guard.name, timeout);
+        let result = volume_exists(ContainerRuntime::Docker, &guard.name, timeout);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false);
+        assert!(!result.unwrap());
     }
 
     #[test]
@@ -271,7 +610,7 @@ mod tests {
 
         // Create a test volume
         let create_result = std::process::Command::new("docker")
-            .args(&["volume", "create", volume_name])
+            .args(["volume", "create", volume_name])
             .output();
 
         if create_result.is_err() {
@@ -283,7 +622,7 @@ mod tests {
 
         // Add some test data to the volume
         let write_result = std::process::Command::new("docker")
-            .args(&[
+            .args([
                 "run", "--rm",
                 "-v", &format!("{}:/data", volume_name),
                 "alpine",
@@ -296,25 +635,25 @@ mod tests {
 
         // Archive the volume
         let archive_path = temp_dir.path().join("test-volume.tar.gz");
-        let archive_result = archive_volume(volume_name, &archive_path, timeout);
+        let archive_result = archive_volume(ContainerRuntime::Docker, volume_name, &archive_path, timeout);
         assert!(archive_result.is_ok(), "Should archive volume successfully");
         assert!(archive_path.exists(), "Archive file should exist");
 
         // Create a new volume for restoration
         let restore_volume_name = "restic-test-volume-restore";
         let _ = std::process::Command::new("docker")
-            .args(&["volume", "create", restore_volume_name])
+            .args(["volume", "create", restore_volume_name])
             .output();
 
         let _restore_guard = VolumeTestGuard::new(restore_volume_name.to_string());
 
         // Restore to the new volume
-        let restore_result = restore_volume(restore_volume_name, &archive_path, timeout);
+        let restore_result = restore_volume(ContainerRuntime::Docker, restore_volume_name, &archive_path, timeout);
         assert!(restore_result.is_ok(), "Should restore volume successfully");
 
         // Verify the restored data
         let verify_result = std::process::Command::new("docker")
-            .args(&[
+            .args([
                 "run", "--rm",
                 "-v", &format!("{}:/data", restore_volume_name),
                 "alpine",
@@ -329,10 +668,10 @@ mod tests {
 
         // Cleanup
         let _ = std::process::Command::new("docker")
-            .args(&["volume", "rm", volume_name])
+            .args(["volume", "rm", volume_name])
             .output();
         let _ = std::process::Command::new("docker")
-            .args(&["volume", "rm", restore_volume_name])
+            .args(["volume", "rm", restore_volume_name])
             .output();
     }
 
@@ -344,7 +683,7 @@ mod tests {
 
         // Create a test volume
         let create_result = std::process::Command::new("docker")
-            .args(&["volume", "create", volume_name])
+            .args(["volume", "create", volume_name])
             .output();
 
         if create_result.is_err() {
@@ -356,7 +695,7 @@ mod tests {
 
         // Add some data
         let _ = std::process::Command::new("docker")
-            .args(&[
+            .args([
                 "run", "--rm",
                 "-v", &format!("{}:/data", volume_name),
                 "alpine",
@@ -366,14 +705,14 @@ mod tests {
             .output();
 
         // Get size
-        let size_result = get_volume_size(volume_name, timeout);
+        let size_result = get_volume_size(ContainerRuntime::Docker, volume_name, timeout);
         assert!(size_result.is_ok(), "Should get volume size successfully");
         let size = size_result.unwrap();
         assert!(size > 0, "Volume size should be greater than 0");
 
         // Cleanup
         let _ = std::process::Command::new("docker")
-            .args(&["volume", "rm", volume_name])
+            .args(["volume", "rm", volume_name])
             .output();
     }
 
@@ -393,7 +732,7 @@ mod tests {
         let guard = VolumeTestGuard::random();
 
         // This should fail because volume doesn't exist, but it should create directories first
-        let _result = archive_volume(&guard.name, &nested_path, timeout);
+        let _result = archive_volume(ContainerRuntime::Docker, &guard.name, &nested_path, timeout);
 
         // The key test: parent directories should be created even if Docker fails
         assert!(nested_path.parent().unwrap().exists(), "Parent directories should be created");
@@ -406,16 +745,108 @@ mod tests {
 
         let guard = VolumeTestGuard::random();
 
-        let result = restore_volume(&guard.name, &nonexistent_path, timeout);
+        let result = restore_volume(ContainerRuntime::Docker, &guard.name, &nonexistent_path, timeout);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("does not exist"));
     }
 
+    #[test]
+    fn test_podman_helper_and_socket() {
+        let runtime = ContainerRuntime::Podman;
+        assert_eq!(runtime.binary(), "podman");
+        assert!(helper_run_args(runtime).contains(&"label=disable"));
+        assert_eq!(helper_image(runtime), "docker.io/library/alpine:latest");
+
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(podman_socket(Some(temp_dir.path())), PathBuf::from("/run/podman/podman.sock"));
+        assert_eq!(podman_socket(None), PathBuf::from("/run/podman/podman.sock"));
+
+        let rootless = temp_dir.path().join("podman").join("podman.sock");
+        fs::create_dir_all(rootless.parent().unwrap()).unwrap();
+        fs::write(&rootless, "").unwrap();
+        assert_eq!(podman_socket(Some(temp_dir.path())), rootless);
+
+        assert_eq!(
+            volume_tar_command(runtime, "app_data").join(" "),
+            "podman run --rm --security-opt label=disable -v app_data:/data:ro docker.io/library/alpine:latest tar cf - -C /data ."
+        );
+    }
+
+    #[test]
+    fn test_archive_compression_staging() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("data.tar.zst");
+        let tar = staged_tar_path(&archive);
+        assert_eq!(tar, temp_dir.path().join("data.tar"));
+        assert_eq!(tar_flags(&archive, true), "cf");
+        assert_eq!(tar_flags(Path::new("data.tar"), false), "xf");
+        assert_eq!(tar_flags(Path::new("data.tar.gz"), false), "xzf");
+        assert_eq!(staged_tar_path(Path::new("data.tar.gz")), PathBuf::from("data.tar.gz"));
+
+        fs::write(&tar, b"tar contents").unwrap();
+        compress_staged_tar(&tar, &archive).unwrap();
+        assert!(!tar.exists());
+        decompress_to_staged_tar(&archive, &tar).unwrap();
+        assert_eq!(fs::read(&tar).unwrap(), b"tar contents");
+    }
+
+    #[test]
+    fn test_archive_unpacked_size() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let contents = vec![b'x'; 100_000];
+
+        let tar = temp_dir.path().join("data.tar");
+        fs::write(&tar, &contents).unwrap();
+        assert_eq!(archive_unpacked_size(&tar).unwrap(), 100_000);
+
+        let gzip = temp_dir.path().join("data.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&gzip).unwrap(), flate2::Compression::default());
+        encoder.write_all(&contents).unwrap();
+        encoder.finish().unwrap();
+        assert!(fs::metadata(&gzip).unwrap().len() < 100_000);
+        assert_eq!(archive_unpacked_size(&gzip).unwrap(), 100_000);
+
+        let zstd = temp_dir.path().join("data.tar.zst");
+        compress_staged_tar(&tar, &zstd).unwrap();
+        assert_eq!(archive_unpacked_size(&zstd).unwrap(), 100_000);
+    }
+
+    #[test]
+    fn test_parse_df_available() {
+        let output = "Filesystem           1024-blocks    Used Available Capacity Mounted on\n\
+                      /dev/mapper/vg root    102400   81920     20480  80% /data\n";
+        assert_eq!(parse_df_available(output).unwrap(), 20480 * 1024);
+        assert!(parse_df_available("Filesystem 1024-blocks Used Available Capacity Mounted on\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_inspect_output() {
+        let containers = parse_container_inspect(
+            r#"[{"Name": "/nextcloud", "Config": {"Labels": {"restic-manager.enable": "true"}},
+                 "Mounts": [{"Type": "volume", "Name": "nextcloud_data"}, {"Type": "bind", "Source": "/srv"}]},
+                {"Name": "redis", "Config": {"Labels": null}, "Mounts": null}]"#,
+        )
+        .unwrap();
+        assert_eq!(containers[0].name, "nextcloud");
+        assert_eq!(containers[0].labels["restic-manager.enable"], "true");
+        assert_eq!(containers[0].volumes, vec!["nextcloud_data"]);
+        assert_eq!(containers[0].binds, vec![PathBuf::from("/srv")]);
+        assert!(containers[1].labels.is_empty());
+        assert!(containers[1].volumes.is_empty());
+        assert!(containers[1].binds.is_empty());
+
+        let volumes = parse_volume_inspect(r#"[{"Name": "media", "Labels": null, "Driver": "local"}]"#).unwrap();
+        assert_eq!(volumes[0].name, "media");
+        assert!(volumes[0].labels.is_empty());
+    }
+
     #[test]
     #[ignore] // Requires Docker - tests timeout handling
     fn test_list_volumes_timeout() {
         let timeout = Duration::from_nanos(1); // Impossibly short timeout
-        let result = list_volumes(timeout);
+        let result = list_volumes(ContainerRuntime::Docker, timeout);
 
         assert!(result.is_err(), "Should timeout listing volumes");
     }
@@ -0,0 +1,426 @@
+//! Docker Engine API backend (`docker_backend = "api"`)
+//!
+//! Implements [`DockerOperations`] over the API socket with bollard, for hosts
+//! (or containers) that can reach the socket but have no `docker` CLI. Like
+//! the CLI backend, volumes are read and written by a throwaway Alpine
+//! container, but archives are streamed over the API instead of going through
+//! a bind mount, so this also works when restic-manager itself runs in a
+//! container.
+
+use super::command::CommandTimeout;
+use super::docker::{self, ContainerInfo};
+use super::docker_ops::DockerOperations;
+use crate::config::{ContainerRuntime, PauseMode};
+use anyhow::{Context, Result};
+use bollard::container::{
+    AttachContainerOptions, AttachContainerResults, Config, CreateContainerOptions, ListContainersOptions,
+    LogOutput, RemoveContainerOptions,
+};
+use bollard::errors::Error as DockerError;
+use bollard::image::CreateImageOptions;
+use bollard::models::{HostConfig, MountPointTypeEnum};
+use bollard::volume::CreateVolumeOptions;
+use bollard::Docker;
+use futures_util::{StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Runtime;
+use tracing::{info, warn};
+
+/// Docker operations over the Engine API socket
+pub struct ApiDockerOps {
+    docker: Docker,
+    runtime: Runtime,
+    helper_image: &'static str,
+}
+
+impl ApiDockerOps {
+    /// Connect to the API socket
+    ///
+    /// `DOCKER_HOST` wins if set. Otherwise Docker uses the platform default
+    /// and Podman its rootless or rootful socket.
+    pub fn connect(container_runtime: ContainerRuntime) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start the Docker API runtime")?;
+        let docker = {
+            let _guard = runtime.enter();
+            match container_runtime {
+                ContainerRuntime::Podman if std::env::var_os("DOCKER_HOST").is_none() => {
+                    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(std::path::PathBuf::from);
+                    let socket = docker::podman_socket(runtime_dir.as_deref());
+                    Docker::connect_with_socket(&socket.to_string_lossy(), 120, bollard::API_DEFAULT_VERSION)
+                        .context(format!("Failed to connect to the Podman API socket {}", socket.display()))?
+                }
+                _ => Docker::connect_with_local_defaults().context("Failed to connect to the Docker API socket")?,
+            }
+        };
+        Ok(Self { docker, runtime, helper_image: docker::helper_image(container_runtime) })
+    }
+
+    /// Run a request, failing with [`CommandTimeout`] after `timeout`
+    fn block_on<T>(&self, what: &str, timeout: Duration, future: impl Future<Output = Result<T>>) -> Result<T> {
+        match self.runtime.block_on(tokio::time::timeout(timeout, future)) {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::Error::new(CommandTimeout(timeout)).context(format!("{} timed out", what))),
+        }
+    }
+
+    /// Run a command in a throwaway helper container with `volume` at /data
+    ///
+    /// `input` is streamed to the command's stdin; without input the volume
+    /// is mounted read-only. Its stdout is written to `output`, or returned
+    /// when there is none. The container is removed afterwards, also when the
+    /// command fails or times out.
+    fn run_helper(
+        &self,
+        what: &str,
+        volume: &str,
+        cmd: &[&str],
+        input: Option<&Path>,
+        output: Option<&Path>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        let name = format!("restic-manager-{:08x}", rand::random::<u32>());
+        let mount = if input.is_none() {
+            format!("{}:/data:ro", volume)
+        } else {
+            format!("{}:/data", volume)
+        };
+
+        let result = self.block_on(
+            what,
+            timeout,
+            run_helper(&self.docker, self.helper_image, &name, &mount, cmd, input, output),
+        );
+
+        let removed = self.runtime.block_on(self.docker.remove_container(
+            &name,
+            Some(RemoveContainerOptions { force: true, ..Default::default() }),
+        ));
+        if let Err(e) = removed {
+            if !is_not_found(&e) {
+                warn!("Failed to remove helper container {}: {}", name, e);
+            }
+        }
+
+        result
+    }
+
+    fn for_each_container<'a, F, Fut>(&'a self, containers: &'a [String], action: &str, timeout: Duration, f: F) -> Result<()>
+    where
+        F: Fn(&'a Docker, &'a str) -> Fut,
+        Fut: Future<Output = Result<(), DockerError>>,
+    {
+        self.block_on(&format!("docker {}", action), timeout, async {
+            for container in containers {
+                f(&self.docker, container)
+                    .await
+                    .context(format!("Failed to {} container {}", action, container))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl DockerOperations for ApiDockerOps {
+    fn list_volumes(&self, timeout: Duration) -> Result<Vec<String>> {
+        self.block_on("Listing volumes", timeout, async {
+            let response = self
+                .docker
+                .list_volumes::<String>(None)
+                .await
+                .context("Failed to list Docker volumes")?;
+            Ok(response.volumes.unwrap_or_default().into_iter().map(|v| v.name).collect())
+        })
+    }
+
+    fn volume_exists(&self, volume_name: &str, timeout: Duration) -> Result<bool> {
+        self.block_on("Inspecting volume", timeout, async {
+            match self.docker.inspect_volume(volume_name).await {
+                Ok(_) => Ok(true),
+                Err(e) if is_not_found(&e) => Ok(false),
+                Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to inspect volume {}", volume_name))),
+            }
+        })
+    }
+
+    fn archive_volume(&self, volume_name: &str, output_path: &Path, timeout: Duration) -> Result<()> {
+        info!("Archiving Docker volume: {} to {:?}", volume_name, output_path);
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).context(format!("Failed to create directory: {:?}", parent))?;
+        }
+
+        let tar_path = docker::staged_tar_path(output_path);
+        self.run_helper(
+            "Volume archiving",
+            volume_name,
+            &["tar", docker::tar_flags(output_path, true), "-", "-C", "/data", "."],
+            None,
+            Some(&tar_path),
+            timeout,
+        )
+        .context(format!("Failed to archive volume {}", volume_name))?;
+        docker::compress_staged_tar(&tar_path, output_path)?;
+
+        info!("Successfully archived volume: {}", volume_name);
+        Ok(())
+    }
+
+    fn restore_volume(&self, volume_name: &str, archive_path: &Path, timeout: Duration) -> Result<()> {
+        info!("Restoring Docker volume: {} from {:?}", volume_name, archive_path);
+
+        if !archive_path.exists() {
+            anyhow::bail!("Archive file does not exist: {:?}", archive_path);
+        }
+
+        let tar_path = docker::staged_tar_path(archive_path);
+        docker::decompress_to_staged_tar(archive_path, &tar_path)?;
+        let result = self.run_helper(
+            "Volume restoration",
+            volume_name,
+            &["tar", docker::tar_flags(archive_path, false), "-", "-C", "/data"],
+            Some(&tar_path),
+            None,
+            timeout,
+        );
+        if tar_path != archive_path {
+            let _ = fs::remove_file(&tar_path);
+        }
+        result.context(format!("Failed to restore volume {}", volume_name))?;
+
+        info!("Successfully restored volume: {}", volume_name);
+        Ok(())
+    }
+
+    fn recreate_volume(&self, volume_name: &str, timeout: Duration) -> Result<()> {
+        self.block_on("Recreating volume", timeout, async {
+            match self.docker.remove_volume(volume_name, None).await {
+                Ok(()) => {}
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => {
+                    return Err(anyhow::Error::new(e)
+                        .context(format!("Failed to remove volume {} (is it still in use?)", volume_name)))
+                }
+            }
+
+            let options = CreateVolumeOptions { name: volume_name, driver: "local", ..Default::default() };
+            self.docker
+                .create_volume(options)
+                .await
+                .context(format!("Failed to create volume {}", volume_name))?;
+            Ok(())
+        })
+    }
+
+    fn remove_volume(&self, volume_name: &str, timeout: Duration) -> Result<()> {
+        self.block_on("Removing volume", timeout, async {
+            match self.docker.remove_volume(volume_name, None).await {
+                Ok(()) => Ok(()),
+                Err(e) if is_not_found(&e) => Ok(()),
+                Err(e) => Err(anyhow::Error::new(e)
+                    .context(format!("Failed to remove volume {} (is it still in use?)", volume_name))),
+            }
+        })
+    }
+
+    fn get_volume_size(&self, volume_name: &str, timeout: Duration) -> Result<u64> {
+        let output = self.run_helper("Volume size", volume_name, &["du", "-sb", "/data"], None, None, timeout)?;
+
+        String::from_utf8_lossy(&output)
+            .split_whitespace()
+            .next()
+            .context("Failed to parse volume size")?
+            .parse::<u64>()
+            .context("Failed to parse volume size as number")
+    }
+
+    fn get_volume_free_space(&self, volume_name: &str, timeout: Duration) -> Result<u64> {
+        let output = self.run_helper("Volume free space", volume_name, &["df", "-Pk", "/data"], None, None, timeout)?;
+        docker::parse_df_available(&String::from_utf8_lossy(&output))
+    }
+
+    fn list_containers_with_label(&self, label: &str, timeout: Duration) -> Result<Vec<String>> {
+        self.block_on("Listing containers", timeout, async {
+            let options = ListContainersOptions {
+                filters: HashMap::from([("label", vec![label])]),
+                ..Default::default()
+            };
+            let containers = self
+                .docker
+                .list_containers(Some(options))
+                .await
+                .context("Failed to list containers")?;
+
+            Ok(containers
+                .into_iter()
+                .filter_map(|c| c.names.and_then(|names| names.into_iter().next()))
+                .map(|name| name.trim_start_matches('/').to_string())
+                .collect())
+        })
+    }
+
+    fn inspect_container(&self, name: &str, timeout: Duration) -> Result<ContainerInfo> {
+        self.block_on("Inspecting container", timeout, async {
+            let container = self
+                .docker
+                .inspect_container(name, None)
+                .await
+                .context(format!("Failed to inspect container {}", name))?;
+
+            let mut volumes = Vec::new();
+            let mut binds = Vec::new();
+            for mount in container.mounts.unwrap_or_default() {
+                match mount.typ {
+                    Some(MountPointTypeEnum::VOLUME) => volumes.extend(mount.name),
+                    Some(MountPointTypeEnum::BIND) => binds.extend(mount.source.map(PathBuf::from)),
+                    _ => {}
+                }
+            }
+
+            Ok(ContainerInfo {
+                name: container.name.as_deref().unwrap_or(name).trim_start_matches('/').to_string(),
+                labels: container.config.and_then(|c| c.labels).unwrap_or_default(),
+                volumes,
+                binds,
+            })
+        })
+    }
+
+    fn suspend_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()> {
+        match mode {
+            PauseMode::Pause => {
+                self.for_each_container(containers, "pause", timeout, |docker, name| docker.pause_container(name))
+            }
+            PauseMode::Stop => self.for_each_container(containers, "stop", timeout, |docker, name| {
+                docker.stop_container(name, None)
+            }),
+        }
+    }
+
+    fn resume_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()> {
+        match mode {
+            PauseMode::Pause => self.for_each_container(containers, "unpause", timeout, |docker, name| {
+                docker.unpause_container(name)
+            }),
+            PauseMode::Stop => self.for_each_container(containers, "start", timeout, |docker, name| {
+                docker.start_container::<String>(name, None)
+            }),
+        }
+    }
+}
+
+fn is_not_found(error: &DockerError) -> bool {
+    matches!(error, DockerError::DockerResponseServerError { status_code: 404, .. })
+}
+
+/// Pull the helper image unless it is present
+async fn ensure_helper_image(docker: &Docker, image: &str) -> Result<()> {
+    if docker.inspect_image(image).await.is_ok() {
+        return Ok(());
+    }
+
+    info!("Pulling {}", image);
+    let options = CreateImageOptions { from_image: image, ..Default::default() };
+    docker
+        .create_image(Some(options), None, None)
+        .try_collect::<Vec<_>>()
+        .await
+        .context(format!("Failed to pull {}", image))?;
+    Ok(())
+}
+
+async fn run_helper(
+    docker: &Docker,
+    image: &str,
+    name: &str,
+    mount: &str,
+    cmd: &[&str],
+    input: Option<&Path>,
+    output: Option<&Path>,
+) -> Result<Vec<u8>> {
+    ensure_helper_image(docker, image).await?;
+
+    let with_stdin = input.is_some();
+    let config = Config {
+        image: Some(image),
+        cmd: Some(cmd.to_vec()),
+        attach_stdin: Some(with_stdin),
+        open_stdin: Some(with_stdin),
+        stdin_once: Some(with_stdin),
+        attach_stdout: Some(true),
+        attach_stderr: Some(true),
+        host_config: Some(HostConfig { binds: Some(vec![mount.to_string()]), ..Default::default() }),
+        ..Default::default()
+    };
+    docker
+        .create_container(Some(CreateContainerOptions { name, platform: None }), config)
+        .await
+        .context("Failed to create helper container")?;
+
+    let AttachContainerResults { output: mut stream, input: mut stdin } = docker
+        .attach_container::<String>(
+            name,
+            Some(AttachContainerOptions {
+                stdin: Some(with_stdin),
+                stdout: Some(true),
+                stderr: Some(true),
+                stream: Some(true),
+                ..Default::default()
+            }),
+        )
+        .await
+        .context("Failed to attach to helper container")?;
+
+    docker
+        .start_container::<String>(name, None)
+        .await
+        .context("Failed to start helper container")?;
+
+    if let Some(path) = input {
+        let mut file = fs::File::open(path).context(format!("Failed to open {:?}", path))?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            stdin.write_all(&buffer[..read]).await.context("Failed to stream to helper container")?;
+        }
+        stdin.shutdown().await?;
+    }
+
+    let mut output_file = output
+        .map(|path| fs::File::create(path).context(format!("Failed to create {:?}", path)))
+        .transpose()?;
+    let mut captured = Vec::new();
+    let mut stderr = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        match chunk.context("Failed to read from helper container")? {
+            LogOutput::StdOut { message } => match output_file {
+                Some(ref mut file) => file.write_all(&message)?,
+                None => captured.extend_from_slice(&message),
+            },
+            LogOutput::StdErr { message } => stderr.extend_from_slice(&message),
+            _ => {}
+        }
+    }
+
+    let exit_code = match docker.wait_container::<String>(name, None).try_collect::<Vec<_>>().await {
+        Ok(responses) => responses.last().map_or(0, |r| r.status_code),
+        Err(DockerError::DockerContainerWaitError { code, .. }) => code,
+        Err(e) => return Err(anyhow::Error::new(e).context("Failed to wait for helper container")),
+    };
+    if exit_code != 0 {
+        anyhow::bail!("exit code {}: {}", exit_code, String::from_utf8_lossy(&stderr).trim());
+    }
+
+    Ok(captured)
+}
@@ -5,9 +5,12 @@
 
 #![allow(dead_code)]
 
+use super::docker::ContainerInfo;
+use crate::config::{ContainerRuntime, DockerBackend, PauseMode};
 use anyhow::Result;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
 
 /// Abstraction for Docker operations, enabling mocking in tests
 pub trait DockerOperations: Send + Sync {
@@ -17,7 +20,7 @@ pub trait DockerOperations: Send + Sync {
     /// Check if a Docker volume exists (exact match)
     fn volume_exists(&self, volume_name: &str, timeout: Duration) -> Result<bool>;
 
-    /// Archive a Docker volume to a tar.gz file
+    /// Archive a Docker volume to a tar, tar.gz or tar.zst file (by extension)
     fn archive_volume(
         &self,
         volume_name: &str,
@@ -25,7 +28,7 @@ pub trait DockerOperations: Send + Sync {
         timeout: Duration,
     ) -> Result<()>;
 
-    /// Restore a Docker volume from a tar.gz file
+    /// Restore a Docker volume from a tar, tar.gz or tar.zst file (by extension)
     fn restore_volume(
         &self,
         volume_name: &str,
@@ -33,27 +36,56 @@ pub trait DockerOperations: Send + Sync {
         timeout: Duration,
     ) -> Result<()>;
 
+    /// Remove a volume (if it exists) and create it again, empty
+    fn recreate_volume(&self, volume_name: &str, timeout: Duration) -> Result<()>;
+
+    /// Remove a volume (nothing to do if it doesn't exist)
+    fn remove_volume(&self, volume_name: &str, timeout: Duration) -> Result<()>;
+
     /// Get the size of a Docker volume in bytes
     fn get_volume_size(&self, volume_name: &str, timeout: Duration) -> Result<u64>;
+
+    /// Free space in bytes where the volume's data is stored (creates a
+    /// missing volume)
+    fn get_volume_free_space(&self, volume_name: &str, timeout: Duration) -> Result<u64>;
+
+    /// Names of the running containers carrying a label
+    fn list_containers_with_label(&self, label: &str, timeout: Duration) -> Result<Vec<String>>;
+
+    /// A container by name, with its named volumes and bind mounts
+    fn inspect_container(&self, name: &str, timeout: Duration) -> Result<ContainerInfo>;
+
+    /// Pause (or stop) containers
+    fn suspend_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()>;
+
+    /// Unpause (or start) containers suspended with the same mode
+    fn resume_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()>;
 }
 
-/// Default implementation using real Docker CLI calls
+/// Default implementation using real Docker (or Podman) CLI calls
 #[derive(Debug, Clone, Default)]
-pub struct RealDockerOps;
+pub struct RealDockerOps {
+    runtime: ContainerRuntime,
+}
 
 impl RealDockerOps {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Use another container runtime's CLI
+    pub fn with_runtime(runtime: ContainerRuntime) -> Self {
+        Self { runtime }
     }
 }
 
 impl DockerOperations for RealDockerOps {
     fn list_volumes(&self, timeout: Duration) -> Result<Vec<String>> {
-        super::docker::list_volumes(timeout)
+        super::docker::list_volumes(self.runtime, timeout)
     }
 
     fn volume_exists(&self, volume_name: &str, timeout: Duration) -> Result<bool> {
-        super::docker::volume_exists(volume_name, timeout)
+        super::docker::volume_exists(self.runtime, volume_name, timeout)
     }
 
     fn archive_volume(
@@ -62,7 +94,7 @@ impl DockerOperations for RealDockerOps {
         output_path: &Path,
         timeout: Duration,
     ) -> Result<()> {
-        super::docker::archive_volume(volume_name, output_path, timeout)
+        super::docker::archive_volume(self.runtime, volume_name, output_path, timeout)
     }
 
     fn restore_volume(
@@ -71,11 +103,141 @@ impl DockerOperations for RealDockerOps {
         archive_path: &Path,
         timeout: Duration,
     ) -> Result<()> {
-        super::docker::restore_volume(volume_name, archive_path, timeout)
+        super::docker::restore_volume(self.runtime, volume_name, archive_path, timeout)
+    }
+
+    fn recreate_volume(&self, volume_name: &str, timeout: Duration) -> Result<()> {
+        super::docker::recreate_volume(self.runtime, volume_name, timeout)
+    }
+
+    fn remove_volume(&self, volume_name: &str, timeout: Duration) -> Result<()> {
+        super::docker::remove_volume(self.runtime, volume_name, timeout)
     }
 
     fn get_volume_size(&self, volume_name: &str, timeout: Duration) -> Result<u64> {
-        super::docker::get_volume_size(volume_name, timeout)
+        super::docker::get_volume_size(self.runtime, volume_name, timeout)
+    }
+
+    fn get_volume_free_space(&self, volume_name: &str, timeout: Duration) -> Result<u64> {
+        super::docker::get_volume_free_space(self.runtime, volume_name, timeout)
+    }
+
+    fn list_containers_with_label(&self, label: &str, timeout: Duration) -> Result<Vec<String>> {
+        super::docker::list_containers_with_label(self.runtime, label, timeout)
+    }
+
+    fn inspect_container(&self, name: &str, timeout: Duration) -> Result<ContainerInfo> {
+        super::docker::inspect_container(self.runtime, name, timeout)
+    }
+
+    fn suspend_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()> {
+        let action = match mode {
+            PauseMode::Pause => "pause",
+            PauseMode::Stop => "stop",
+        };
+        super::docker::container_action(self.runtime, action, containers, timeout)
+    }
+
+    fn resume_containers(&self, containers: &[String], mode: PauseMode, timeout: Duration) -> Result<()> {
+        let action = match mode {
+            PauseMode::Pause => "unpause",
+            PauseMode::Stop => "start",
+        };
+        super::docker::container_action(self.runtime, action, containers, timeout)
+    }
+}
+
+/// Docker operations for the configured `docker_backend` and `container_runtime`
+pub fn for_backend(backend: DockerBackend, runtime: ContainerRuntime) -> Result<Box<dyn DockerOperations>> {
+    match backend {
+        DockerBackend::Cli => Ok(Box::new(RealDockerOps::with_runtime(runtime))),
+        DockerBackend::Api => Ok(Box::new(super::docker_api::ApiDockerOps::connect(runtime)?)),
+    }
+}
+
+/// Containers paused (or stopped) while their volumes are archived
+///
+/// The containers are resumed when this is dropped, so they come back even
+/// when archiving fails.
+pub struct PausedContainers<'a> {
+    ops: &'a dyn DockerOperations,
+    containers: Vec<String>,
+    mode: PauseMode,
+    deadline: Instant,
+    timeout: Duration,
+    resumed: bool,
+}
+
+impl<'a> PausedContainers<'a> {
+    /// Suspend the running containers carrying `label`
+    ///
+    /// `max_pause` is how long the containers may stay suspended; callers
+    /// bound their work with [`PausedContainers::remaining`].
+    pub fn pause(
+        ops: &'a dyn DockerOperations,
+        label: &str,
+        mode: PauseMode,
+        max_pause: Duration,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let containers = ops.list_containers_with_label(label, timeout)?;
+        if containers.is_empty() {
+            info!("No running containers with label '{}' to {}", label, mode_name(mode));
+        } else {
+            info!("Suspending containers ({}): {}", mode_name(mode), containers.join(", "));
+            ops.suspend_containers(&containers, mode, timeout)?;
+        }
+
+        Ok(Self {
+            ops,
+            containers,
+            mode,
+            deadline: Instant::now() + max_pause,
+            timeout,
+            resumed: false,
+        })
+    }
+
+    /// The suspended containers
+    pub fn containers(&self) -> &[String] {
+        &self.containers
+    }
+
+    /// Time left before the maximum pause duration is reached
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// Resume the containers now, reporting failures to the caller
+    pub fn resume(mut self) -> Result<()> {
+        self.resumed = true;
+        self.resume_now()
+    }
+
+    fn resume_now(&self) -> Result<()> {
+        if self.containers.is_empty() {
+            return Ok(());
+        }
+        info!("Resuming containers: {}", self.containers.join(", "));
+        self.ops.resume_containers(&self.containers, self.mode, self.timeout)
+    }
+}
+
+impl Drop for PausedContainers<'_> {
+    fn drop(&mut self) {
+        if self.resumed {
+            return;
+        }
+        if let Err(e) = self.resume_now() {
+            error!("Failed to resume containers {}: {:#}", self.containers.join(", "), e);
+        }
+    }
+}
+
+fn mode_name(mode: PauseMode) -> &'static str {
+    match mode {
+        PauseMode::Pause => "pause",
+        PauseMode::Stop => "stop",
     }
 }
 
@@ -94,7 +256,14 @@ pub mod mock {
         VolumeExists { name: String },
         ArchiveVolume { name: String, path: String },
         RestoreVolume { name: String, path: String },
+        RecreateVolume { name: String },
+        RemoveVolume { name: String },
         GetVolumeSize { name: String },
+        GetVolumeFreeSpace { name: String },
+        ListContainers { label: String },
+        InspectContainer { name: String },
+        SuspendContainers { names: Vec<String>, mode: PauseMode },
+        ResumeContainers { names: Vec<String>, mode: PauseMode },
     }
 
     /// Mock Docker operations for testing
@@ -106,12 +275,18 @@ pub mod mock {
         pub volumes: Arc<Mutex<Vec<String>>>,
         /// Pre-configured volume sizes
         pub volume_sizes: Arc<Mutex<HashMap<String, u64>>>,
+        /// Pre-configured free space per volume (unlimited if not set)
+        pub volume_free_space: Arc<Mutex<HashMap<String, u64>>>,
         /// Whether archive should fail
         pub should_fail_archive: Arc<Mutex<bool>>,
         /// Whether restore should fail
         pub should_fail_restore: Arc<Mutex<bool>>,
         /// Whether list_volumes should fail
         pub should_fail_list: Arc<Mutex<bool>>,
+        /// Pre-configured running containers by label
+        pub containers: Arc<Mutex<HashMap<String, Vec<String>>>>,
+        /// Pre-configured containers by name
+        pub inspected: Arc<Mutex<HashMap<String, ContainerInfo>>>,
     }
 
     impl MockDockerOps {
@@ -134,6 +309,12 @@ pub mod mock {
             self
         }
 
+        /// Configure the free space where a volume is stored
+        pub fn with_volume_free_space(self, name: &str, free: u64) -> Self {
+            self.volume_free_space.lock().unwrap().insert(name.to_string(), free);
+            self
+        }
+
         /// Configure archive to fail
         pub fn with_failing_archive(self) -> Self {
             *self.should_fail_archive.lock().unwrap() = true;
@@ -152,6 +333,24 @@ pub mod mock {
             self
         }
 
+        /// Configure running containers carrying a label
+        pub fn with_containers(self, label: &str, containers: Vec<String>) -> Self {
+            self.containers
+                .lock()
+                .unwrap()
+                .insert(label.to_string(), containers);
+            self
+        }
+
+        /// Configure a container that can be inspected
+        pub fn with_inspected_container(self, container: ContainerInfo) -> Self {
+            self.inspected
+                .lock()
+                .unwrap()
+                .insert(container.name.clone(), container);
+            self
+        }
+
         /// Get all recorded calls
         pub fn get_calls(&self) -> Vec<DockerCall> {
             self.calls.lock().unwrap().clone()
@@ -257,6 +456,25 @@ pub mod mock {
             Ok(())
         }
 
+        fn recreate_volume(&self, volume_name: &str, _timeout: Duration) -> Result<()> {
+            self.record_call(DockerCall::RecreateVolume {
+                name: volume_name.to_string(),
+            });
+            let mut volumes = self.volumes.lock().unwrap();
+            if !volumes.iter().any(|v| v == volume_name) {
+                volumes.push(volume_name.to_string());
+            }
+            Ok(())
+        }
+
+        fn remove_volume(&self, volume_name: &str, _timeout: Duration) -> Result<()> {
+            self.record_call(DockerCall::RemoveVolume {
+                name: volume_name.to_string(),
+            });
+            self.volumes.lock().unwrap().retain(|v| v != volume_name);
+            Ok(())
+        }
+
         fn get_volume_size(&self, volume_name: &str, _timeout: Duration) -> Result<u64> {
             self.record_call(DockerCall::GetVolumeSize {
                 name: volume_name.to_string(),
@@ -268,6 +486,59 @@ pub mod mock {
                 .get(volume_name)
                 .unwrap_or(&1024))
         }
+
+        fn get_volume_free_space(&self, volume_name: &str, _timeout: Duration) -> Result<u64> {
+            self.record_call(DockerCall::GetVolumeFreeSpace {
+                name: volume_name.to_string(),
+            });
+            Ok(*self
+                .volume_free_space
+                .lock()
+                .unwrap()
+                .get(volume_name)
+                .unwrap_or(&u64::MAX))
+        }
+
+        fn list_containers_with_label(&self, label: &str, _timeout: Duration) -> Result<Vec<String>> {
+            self.record_call(DockerCall::ListContainers {
+                label: label.to_string(),
+            });
+            Ok(self
+                .containers
+                .lock()
+                .unwrap()
+                .get(label)
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        fn inspect_container(&self, name: &str, _timeout: Duration) -> Result<ContainerInfo> {
+            self.record_call(DockerCall::InspectContainer {
+                name: name.to_string(),
+            });
+            self.inspected
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Container not found: {}", name))
+        }
+
+        fn suspend_containers(&self, containers: &[String], mode: PauseMode, _timeout: Duration) -> Result<()> {
+            self.record_call(DockerCall::SuspendContainers {
+                names: containers.to_vec(),
+                mode,
+            });
+            Ok(())
+        }
+
+        fn resume_containers(&self, containers: &[String], mode: PauseMode, _timeout: Duration) -> Result<()> {
+            self.record_call(DockerCall::ResumeContainers {
+                names: containers.to_vec(),
+                mode,
+            });
+            Ok(())
+        }
     }
 }
 
@@ -281,6 +552,21 @@ mod tests {
         let _ = ops;
     }
 
+    #[test]
+    fn test_mock_docker_ops_recreate_volume() {
+        use mock::*;
+
+        let mock = MockDockerOps::new();
+        let timeout = Duration::from_secs(10);
+        mock.recreate_volume("app_db", timeout).unwrap();
+
+        assert!(mock.volume_exists("app_db", timeout).unwrap());
+        assert!(matches!(&mock.get_calls()[0], DockerCall::RecreateVolume { name } if name == "app_db"));
+
+        mock.remove_volume("app_db", timeout).unwrap();
+        assert!(!mock.volume_exists("app_db", timeout).unwrap());
+    }
+
     #[test]
     fn test_mock_docker_ops_list_volumes() {
         use mock::*;
@@ -363,6 +649,79 @@ mod tests {
         assert_eq!(size, 104857600);
     }
 
+    #[test]
+    fn test_paused_containers_resume_on_drop() {
+        use mock::*;
+
+        let mock = MockDockerOps::new()
+            .with_containers("backup.pause", vec!["db".to_string(), "app".to_string()]);
+        let timeout = Duration::from_secs(10);
+
+        {
+            let paused = PausedContainers::pause(
+                &mock,
+                "backup.pause",
+                PauseMode::Stop,
+                Duration::from_secs(600),
+                timeout,
+            )
+            .unwrap();
+            assert_eq!(paused.containers(), ["db", "app"]);
+            assert!(paused.remaining() > Duration::from_secs(590));
+            // Dropped without resume(), e.g. because archiving failed
+        }
+
+        let calls = mock.get_calls();
+        assert!(matches!(
+            &calls[1],
+            DockerCall::SuspendContainers { names, mode: PauseMode::Stop } if names.len() == 2
+        ));
+        assert!(matches!(
+            &calls[2],
+            DockerCall::ResumeContainers { names, mode: PauseMode::Stop } if names.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_paused_containers_explicit_resume() {
+        use mock::*;
+
+        let mock = MockDockerOps::new().with_containers("backup.pause", vec!["db".to_string()]);
+        let paused = PausedContainers::pause(
+            &mock,
+            "backup.pause",
+            PauseMode::Pause,
+            Duration::ZERO,
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(paused.remaining(), Duration::ZERO);
+        paused.resume().unwrap();
+
+        let resumes = mock
+            .get_calls()
+            .iter()
+            .filter(|c| matches!(c, DockerCall::ResumeContainers { .. }))
+            .count();
+        assert_eq!(resumes, 1);
+
+        // Nothing labeled: nothing to suspend or resume
+        let paused = PausedContainers::pause(
+            &mock,
+            "other",
+            PauseMode::Pause,
+            Duration::from_secs(60),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert!(paused.containers().is_empty());
+        drop(paused);
+        assert!(!mock
+            .get_calls()
+            .iter()
+            .any(|c| matches!(c, DockerCall::SuspendContainers { names, .. } if names.is_empty())));
+    }
+
     #[test]
     fn test_mock_docker_ops_restore() {
         use mock::*;
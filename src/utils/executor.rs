@@ -37,6 +37,15 @@ pub trait CommandExecutor: Send + Sync {
         working_dir: Option<&Path>,
         timeout: Option<Duration>,
     ) -> Result<Output>;
+
+    /// Run a command with `input` piped to its stdin
+    fn run_command_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Output>;
 }
 
 /// Default implementation using real subprocess calls
@@ -78,6 +87,16 @@ impl CommandExecutor for RealExecutor {
     ) -> Result<Output> {
         super::command::run_shell_command(command, working_dir, timeout)
     }
+
+    fn run_command_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Output> {
+        super::command::run_command_with_input(program, args, input, timeout)
+    }
 }
 
 /// A mock executor for testing that records calls and returns configured responses
@@ -94,6 +113,8 @@ pub mod mock {
         pub program: String,
         pub args: Vec<String>,
         pub working_dir: Option<String>,
+        /// Data written to stdin, if any
+        pub input: Option<String>,
     }
 
     /// Response configuration for mock
@@ -168,11 +189,12 @@ pub mod mock {
                 .count()
         }
 
-        fn record_call(&self, program: &str, args: &[&str], working_dir: Option<&Path>) {
+        fn record_call(&self, program: &str, args: &[&str], working_dir: Option<&Path>, input: Option<&str>) {
             self.calls.lock().unwrap().push(CommandCall {
                 program: program.to_string(),
                 args: args.iter().map(|s| s.to_string()).collect(),
                 working_dir: working_dir.map(|p| p.display().to_string()),
+                input: input.map(|s| s.to_string()),
             });
         }
 
@@ -210,7 +232,7 @@ pub mod mock {
             working_dir: Option<&Path>,
             _timeout: Option<Duration>,
         ) -> Result<Output> {
-            self.record_call(program, args, working_dir);
+            self.record_call(program, args, working_dir, None);
             let response = self.get_response(program);
             self.execute_response(response)
         }
@@ -239,6 +261,18 @@ pub mod mock {
 
             self.run_command(shell, &[flag, command], working_dir, timeout)
         }
+
+        fn run_command_with_input(
+            &self,
+            program: &str,
+            args: &[&str],
+            input: &str,
+            _timeout: Option<Duration>,
+        ) -> Result<Output> {
+            self.record_call(program, args, None, Some(input));
+            let response = self.get_response(program);
+            self.execute_response(response)
+        }
     }
 }
 
@@ -0,0 +1,318 @@
+//! Human-readable durations and sizes
+//!
+//! Parsing for config values such as `"90m"`, `"2h30m"` or `"2G"`, and the
+//! formatting used by status output, notifications and run reports.
+
+use anyhow::Result;
+use std::time::Duration;
+
+/// Parse a duration such as `"45s"`, `"90m"`, `"2h30m"` or `"1d"`
+///
+/// Units are `s`, `m`, `h`, `d` and `w`; a bare number is seconds.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        anyhow::bail!("Empty duration");
+    }
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            anyhow::bail!("Invalid duration '{}' (expected e.g. \"90m\" or \"2h30m\")", value);
+        }
+        let number: u64 = rest[..digits].parse()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let multiplier = match &rest[..unit_len] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            "d" => 86400,
+            "w" => 604800,
+            "" => anyhow::bail!("Invalid duration '{}': missing unit after {}", value, number),
+            unit => anyhow::bail!("Invalid duration '{}': unknown unit '{}' (use s, m, h, d or w)", value, unit),
+        };
+        rest = &rest[unit_len..];
+
+        total = number
+            .checked_mul(multiplier)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| anyhow::anyhow!("Duration '{}' is too large", value))?;
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Parse a size such as `"512M"`, `"2G"`, `"1.5GiB"` or `"100KB"`
+///
+/// Units are binary (1K = 1024 bytes, as restic reports sizes); a bare
+/// number is bytes.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{}' (expected e.g. \"512M\" or \"2G\")", value))?;
+
+    let exponent = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        other => anyhow::bail!("Invalid size '{}': unknown unit '{}' (use K, M, G or T)", value, other),
+    };
+
+    Ok((number * 1024f64.powi(exponent)).round() as u64)
+}
+
+/// Format duration in human-readable form
+pub fn format_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        let minutes = seconds / 60;
+        let secs = seconds % 60;
+        if secs == 0 {
+            format!("{}m", minutes)
+        } else {
+            format!("{}m {}s", minutes, secs)
+        }
+    } else {
+        let hours = seconds / 3600;
+        let minutes = (seconds % 3600) / 60;
+        if minutes == 0 {
+            format!("{}h", hours)
+        } else {
+            format!("{}h {}m", hours, minutes)
+        }
+    }
+}
+
+/// Format a byte count using binary units (as restic does)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.2} {}", value, UNITS[unit])
+    }
+}
+
+/// Serde adapters for config fields that take a number or a human-readable value
+///
+/// Numbers keep the unit in the field's name (`*_seconds`, `*_minutes`,
+/// `*_mb`); strings are parsed and converted to that unit. Values are always
+/// written back as numbers.
+pub mod serde_human {
+    use super::{parse_duration, parse_size};
+    use schemars::gen::SchemaGenerator;
+    use schemars::schema::{InstanceType, Schema, SchemaObject};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrText {
+        Number(u64),
+        Text(String),
+    }
+
+    fn duration_in<'de, D: Deserializer<'de>>(deserializer: D, unit_secs: u64) -> Result<Option<u64>, D::Error> {
+        match Option::<NumberOrText>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(NumberOrText::Number(n)) => Ok(Some(n)),
+            Some(NumberOrText::Text(text)) => parse_duration(&text)
+                .map(|d| Some(d.as_secs() / unit_secs))
+                .map_err(|e| D::Error::custom(format!("{:#}", e))),
+        }
+    }
+
+    fn required<'de, D: Deserializer<'de>>(value: Option<u64>) -> Result<u64, D::Error> {
+        value.ok_or_else(|| D::Error::custom("expected a number or a string"))
+    }
+
+    /// Seconds, or a duration like `"90m"`
+    pub mod seconds {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+            required::<D>(duration_in(deserializer, 1)?)
+        }
+
+        pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// Optional seconds, or a duration like `"90m"`
+    pub mod option_seconds {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+            duration_in(deserializer, 1)
+        }
+
+        pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// Minutes, or a duration like `"2h"` (rounded down to whole minutes)
+    pub mod minutes {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+            required::<D>(duration_in(deserializer, 60)?)
+        }
+
+        pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// Optional minutes, or a duration like `"2h"` (rounded down to whole minutes)
+    pub mod option_minutes {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+            duration_in(deserializer, 60)
+        }
+
+        pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// Optional hours, or a duration like `"7d"` (rounded down to whole hours)
+    pub mod option_hours {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+            duration_in(deserializer, 3600)
+        }
+
+        pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// Megabytes, or a size like `"1G"` (rounded down to whole megabytes)
+    pub mod megabytes {
+        use super::*;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+            match NumberOrText::deserialize(deserializer)? {
+                NumberOrText::Number(n) => Ok(n),
+                NumberOrText::Text(text) => parse_size(&text)
+                    .map(|bytes| bytes / (1024 * 1024))
+                    .map_err(|e| D::Error::custom(format!("{:#}", e))),
+            }
+        }
+
+        pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+    }
+
+    /// JSON schema of a number-or-string field
+    pub fn schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// JSON schema of an optional number-or-string field
+    pub fn option_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(vec![InstanceType::Integer, InstanceType::String, InstanceType::Null].into()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("90m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("2h30m").unwrap(), Duration::from_secs(9000));
+        assert_eq!(parse_duration(" 1d ").unwrap(), Duration::from_secs(86400));
+        assert_eq!(parse_duration("1w2d").unwrap(), Duration::from_secs(9 * 86400));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("10").is_ok());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("2h30").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 1536 * 1024 * 1024);
+        assert_eq!(parse_size("1t").unwrap(), 1024u64.pow(4));
+
+        assert!(parse_size("").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("5X").is_err());
+    }
+
+    #[test]
+    fn test_serde_human_fields() {
+        #[derive(serde::Deserialize, serde::Serialize)]
+        struct Fields {
+            #[serde(with = "serde_human::seconds")]
+            timeout: u64,
+            #[serde(default, with = "serde_human::option_seconds")]
+            delay: Option<u64>,
+            #[serde(with = "serde_human::minutes")]
+            threshold: u64,
+            #[serde(with = "serde_human::megabytes")]
+            max_size: u64,
+        }
+
+        let fields: Fields =
+            toml::from_str("timeout = \"2h30m\"\nthreshold = \"2h\"\nmax_size = \"1G\"").unwrap();
+        assert_eq!(fields.timeout, 9000);
+        assert_eq!(fields.delay, None);
+        assert_eq!(fields.threshold, 120);
+        assert_eq!(fields.max_size, 1024);
+
+        let fields: Fields =
+            toml::from_str("timeout = 60\ndelay = \"30s\"\nthreshold = 5\nmax_size = 10").unwrap();
+        assert_eq!((fields.timeout, fields.delay, fields.threshold, fields.max_size), (60, Some(30), 5, 10));
+        assert!(toml::to_string(&fields).unwrap().contains("delay = 30"));
+
+        let err = toml::from_str::<Fields>("timeout = \"soon\"\nthreshold = 1\nmax_size = 1").err().unwrap();
+        assert!(err.to_string().contains("Invalid duration"));
+    }
+}
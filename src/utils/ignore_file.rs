@@ -0,0 +1,274 @@
+//! Per-directory `.resticmanagerignore` files
+//!
+//! Application teams can drop a `.resticmanagerignore` file into any directory
+//! inside a backed-up path to exclude files without touching the central config.
+//! Each non-empty line is a pattern relative to the directory containing the file:
+//!
+//! - `*.log` matches at any depth below that directory
+//! - `/cache` is anchored to that directory
+//! - `!keep.log` re-includes a previously excluded path
+//! - lines starting with `#` are comments
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Name of the per-directory ignore file
+pub const IGNORE_FILE_NAME: &str = ".resticmanagerignore";
+
+/// Collect exclude patterns from all ignore files found below the given paths
+///
+/// Directories that `excludes` (or the ignore files above them) already
+/// exclude aren't searched, and with `one_file_system` neither are other
+/// file systems mounted below a path.
+pub fn collect_ignore_patterns(paths: &[PathBuf], excludes: &[String], one_file_system: bool) -> Result<Vec<String>> {
+    let mut patterns = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            let device = if one_file_system { device_of(path) } else { None };
+            collect_from_dir(path, excludes, device, &mut patterns)?;
+        }
+    }
+
+    Ok(patterns)
+}
+
+fn collect_from_dir(dir: &Path, excludes: &[String], device: Option<u64>, patterns: &mut Vec<String>) -> Result<()> {
+    let ignore_file = dir.join(IGNORE_FILE_NAME);
+    if ignore_file.is_file() {
+        let contents = fs::read_to_string(&ignore_file)
+            .context(format!("Failed to read ignore file: {:?}", ignore_file))?;
+        let translated = translate_patterns(dir, &contents);
+        debug!("Loaded {} patterns from {:?}", translated.len(), ignore_file);
+        patterns.extend(translated);
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read directory {:?}: {}", dir, e);
+            return Ok(());
+        }
+    };
+
+    for entry in entries.flatten() {
+        // Don't follow symlinks - restic doesn't either
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !is_dir {
+            continue;
+        }
+        let path = entry.path();
+        if device.is_some() && device_of(&path) != device {
+            debug!("Not searching {:?} for ignore files, it's on another file system", path);
+            continue;
+        }
+        if is_excluded(&path, excludes.iter().chain(patterns.iter())) {
+            continue;
+        }
+        collect_from_dir(&path, excludes, device, patterns)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether restic leaves out a directory, and nothing below it can be
+/// included again (the last matching pattern wins, as in restic)
+fn is_excluded<'a>(dir: &Path, patterns: impl Iterator<Item = &'a String> + Clone) -> bool {
+    let mut excluded = false;
+    for pattern in patterns.clone() {
+        match pattern.strip_prefix('!') {
+            Some(negated) if pattern_matches(negated, dir) => excluded = false,
+            None if pattern_matches(pattern, dir) => excluded = true,
+            _ => {}
+        }
+    }
+    excluded && !patterns.filter_map(|p| p.strip_prefix('!')).any(|negated| may_match_below(negated, dir))
+}
+
+/// Whether a restic pattern matches a path; patterns not starting with `/`
+/// match at any depth
+fn pattern_matches(pattern: &str, path: &Path) -> bool {
+    let pattern = if pattern.starts_with('/') { pattern.to_string() } else { format!("/**/{}", pattern) };
+    let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+    glob::Pattern::new(&to_glob(&pattern)).is_ok_and(|p| p.matches_path_with(path, options))
+}
+
+/// Whether a pattern may match something below `dir`
+fn may_match_below(pattern: &str, dir: &Path) -> bool {
+    if !pattern.starts_with('/') {
+        return true;
+    }
+    let mut prefix = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' | '[' => break,
+            '\\' => prefix.extend(chars.next()),
+            c => prefix.push(c),
+        }
+    }
+    let dir = dir.display().to_string();
+    prefix.starts_with(&dir) || dir.starts_with(&prefix)
+}
+
+/// restic escapes with a backslash, the glob crate with brackets
+fn to_glob(pattern: &str) -> String {
+    let mut glob = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    glob.push('[');
+                    glob.push(escaped);
+                    glob.push(']');
+                }
+            }
+            c => glob.push(c),
+        }
+    }
+    glob
+}
+
+/// Escape the characters restic patterns give a meaning to (a backslash is
+/// the path separator on Windows, not an escape)
+fn escape_pattern(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if matches!(c, '*' | '?' | '[' | ']') || (c == '\\' && cfg!(unix)) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Translate the contents of an ignore file into restic exclude patterns
+/// scoped to `base_dir`
+pub fn translate_patterns(base_dir: &Path, contents: &str) -> Vec<String> {
+    let base = base_dir.display().to_string();
+    let base = escape_pattern(base.trim_end_matches(['/', '\\']));
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (negate, pattern) = match line.strip_prefix('!') {
+                Some(rest) => ("!", rest),
+                None => ("", line),
+            };
+
+            let scoped = if let Some(anchored) = pattern.strip_prefix('/') {
+                format!("{}/{}", base, anchored)
+            } else {
+                format!("{}/**/{}", base, pattern)
+            };
+
+            format!("{}{}", negate, scoped)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_translate_patterns() {
+        let contents = "# comment\n\n*.log\n/cache\n!keep.log\n";
+        let patterns = translate_patterns(Path::new("/data/app/"), contents);
+
+        assert_eq!(
+            patterns,
+            vec![
+                "/data/app/**/*.log".to_string(),
+                "/data/app/cache".to_string(),
+                "!/data/app/**/keep.log".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_ignore_patterns_nested() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let nested = root.join("uploads");
+        fs::create_dir_all(&nested).unwrap();
+
+        fs::write(root.join(IGNORE_FILE_NAME), "*.tmp\n").unwrap();
+        fs::write(nested.join(IGNORE_FILE_NAME), "/thumbnails\n").unwrap();
+
+        let patterns = collect_ignore_patterns(std::slice::from_ref(&root), &[], false).unwrap();
+
+        assert_eq!(patterns.len(), 2);
+        assert!(patterns.contains(&format!("{}/**/*.tmp", root.display())));
+        assert!(patterns.contains(&format!("{}/thumbnails", nested.display())));
+    }
+
+    #[test]
+    fn test_collect_ignore_patterns_skips_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("volume.tar.gz");
+        fs::write(&archive, "data").unwrap();
+
+        let patterns = collect_ignore_patterns(&[archive], &[], false).unwrap();
+        assert!(patterns.is_empty());
+    }
+
+    #[test]
+    fn test_translate_patterns_escapes_base_dir() {
+        let patterns = translate_patterns(Path::new("/photos [2020]"), "*.tmp\n/cache\n");
+        assert_eq!(patterns, vec![r"/photos \[2020\]/**/*.tmp".to_string(), r"/photos \[2020\]/cache".to_string()]);
+        assert!(pattern_matches(&patterns[1], Path::new("/photos [2020]/cache")));
+        assert!(!pattern_matches(&patterns[1], Path::new("/photos 2/cache")));
+    }
+
+    #[test]
+    fn test_collect_ignore_patterns_skips_excluded_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        for dir in ["node_modules/pkg", "cache/deep", "kept"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join(IGNORE_FILE_NAME), "/cache\n").unwrap();
+        fs::write(root.join("node_modules/pkg").join(IGNORE_FILE_NAME), "*.js\n").unwrap();
+        fs::write(root.join("cache/deep").join(IGNORE_FILE_NAME), "*.bin\n").unwrap();
+        fs::write(root.join("kept").join(IGNORE_FILE_NAME), "*.tmp\n").unwrap();
+
+        let excludes = vec!["node_modules".to_string()];
+        let patterns = collect_ignore_patterns(std::slice::from_ref(&root), &excludes, true).unwrap();
+
+        assert_eq!(patterns.len(), 2);
+        assert!(patterns.contains(&format!("{}/cache", root.display())));
+        assert!(patterns.contains(&format!("{}/**/*.tmp", root.join("kept").display())));
+    }
+
+    #[test]
+    fn test_is_excluded_respects_negation() {
+        let dir = Path::new("/data/app/cache");
+        let excludes = ["/data/app/cache".to_string()];
+        assert!(is_excluded(dir, excludes.iter()));
+
+        let reincluded = ["/data/app/cache".to_string(), "!/data/app/cache".to_string()];
+        assert!(!is_excluded(dir, reincluded.iter()));
+
+        // Something below may be included again, so keep searching
+        let below = ["/data/app/cache".to_string(), "!/data/app/cache/keep".to_string()];
+        assert!(!is_excluded(dir, below.iter()));
+    }
+}
@@ -1,28 +1,113 @@
 //! File-based locking to prevent concurrent backups
+//!
+//! The lock file of a service doubles as the coordination file between
+//! overlapping invocations (e.g. `run` from cron while a per-service job is
+//! still busy): its holder records its pid and run id in it, so the others
+//! can skip the service and say who is handling it.
+//!
+//! Two more kinds of locks keep backups apart: a [`RepoLock`] per repository
+//! URL, so services sharing a repository take turns, and the
+//! [`BackupSlot`]s limiting how many backups run at once
+//! (`max_concurrent_backups`).
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use fd_lock::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
 use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
-/// Lock guard for a service backup
-pub struct BackupLock {
+/// The process holding a service's lock, as recorded in the lock file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+/// The service is already being handled by another process
+#[derive(Debug, Clone)]
+pub struct AlreadyRunning {
+    pub service: String,
+    pub holder: Option<LockHolder>,
+}
+
+impl fmt::Display for AlreadyRunning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Service '{}' is already running elsewhere", self.service)?;
+        if let Some(ref holder) = self.holder {
+            write!(f, " (pid {}", holder.pid)?;
+            if let Some(ref run_id) = holder.run_id {
+                write!(f, ", run {}", run_id)?;
+            }
+            write!(f, ", since {})", holder.started_at.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AlreadyRunning {}
+
+/// Whether an error means the service was skipped because another process
+/// is already handling it
+pub fn already_running(error: &anyhow::Error) -> Option<&AlreadyRunning> {
+    error.downcast_ref::<AlreadyRunning>()
+}
+
+/// A repository is used by another backup
+#[derive(Debug, Clone)]
+pub struct RepositoryBusy {
+    pub repository: String,
+    pub holder: Option<LockHolder>,
+}
+
+impl fmt::Display for RepositoryBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Repository {} is in use by another backup", self.repository)?;
+        if let Some(ref holder) = self.holder {
+            write!(f, " (pid {}", holder.pid)?;
+            if let Some(ref run_id) = holder.run_id {
+                write!(f, ", run {}", run_id)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RepositoryBusy {}
+
+/// Whether an error means a repository stayed locked by another backup
+pub fn repository_busy(error: &anyhow::Error) -> Option<&RepositoryBusy> {
+    error.downcast_ref::<RepositoryBusy>()
+}
+
+/// How often a waiting lock is tried again
+const WAIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An exclusive lock on a file, released when dropped
+struct FileLock {
     // Store the lock and file together
     _lock: Box<(RwLock<File>, Option<fd_lock::RwLockWriteGuard<'static, File>>)>,
-    lock_path: PathBuf,
+    path: PathBuf,
+    /// Remove the file on release (only safe for files nobody waits on)
+    remove_on_drop: bool,
 }
 
-impl BackupLock {
-    /// Acquire an exclusive lock for a service
-    /// Returns error if the service is already being backed up
-    pub fn acquire(service_name: &str) -> Result<Self> {
-        let lock_path = Self::lock_path(service_name);
-
-        debug!("Attempting to acquire lock: {:?}", lock_path);
+impl FileLock {
+    /// Lock `path` if it's free, recording `holder` in it
+    fn try_acquire(path: &Path, holder: &LockHolder, remove_on_drop: bool) -> Result<Option<Self>> {
+        debug!("Attempting to acquire lock: {:?}", path);
 
         // Create parent directory if it doesn't exist
-        if let Some(parent) = lock_path.parent() {
+        if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .context("Failed to create lock directory")?;
         }
@@ -32,8 +117,9 @@ impl BackupLock {
             .read(true)
             .write(true)
             .create(true)
-            .open(&lock_path)
-            .context(format!("Failed to open lock file: {:?}", lock_path))?;
+            .truncate(false)
+            .open(path)
+            .context(format!("Failed to open lock file: {:?}", path))?;
 
         // Create boxed lock
         let mut boxed_lock = Box::new((RwLock::new(file), None));
@@ -45,51 +131,256 @@ impl BackupLock {
         // 2. The guard and RwLock will be dropped together
         // 3. The guard is dropped before the RwLock in the tuple drop order
         let lock_ptr = &mut boxed_lock.0 as *mut RwLock<File>;
-        let guard = unsafe { (*lock_ptr).try_write() }
-            .context(format!(
-                "Service '{}' is already being backed up (lock held)",
-                service_name
-            ))?;
+        let mut guard = match unsafe { (*lock_ptr).try_write() } {
+            Ok(guard) => guard,
+            Err(_) => return Ok(None),
+        };
+
+        // The previous holder may have removed the file after we opened it;
+        // a lock on the removed file would exclude nobody
+        if remove_on_drop && !Self::is_current(path, &guard) {
+            return Ok(None);
+        }
+
+        // Record who holds the lock (best effort, only used for reporting)
+        if let Ok(json) = serde_json::to_string(holder) {
+            let file: &mut File = &mut guard;
+            if let Err(e) = file.set_len(0).and_then(|_| file.write_all(json.as_bytes())) {
+                debug!("Failed to record lock holder: {}", e);
+            }
+        }
 
         // Store the guard - casting to 'static is safe because we control the lifetime
         let static_guard: fd_lock::RwLockWriteGuard<'static, File> = unsafe { std::mem::transmute(guard) };
         boxed_lock.1 = Some(static_guard);
 
-        info!("Acquired backup lock for service: {}", service_name);
+        Ok(Some(Self { _lock: boxed_lock, path: path.to_path_buf(), remove_on_drop }))
+    }
 
-        Ok(Self {
-            _lock: boxed_lock,
-            lock_path,
-        })
+    /// Whether `file` is still the file at `path`
+    #[cfg(unix)]
+    fn is_current(path: &Path, file: &File) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(path), file.metadata()) {
+            (Ok(on_disk), Ok(opened)) => on_disk.dev() == opened.dev() && on_disk.ino() == opened.ino(),
+            _ => false,
+        }
     }
 
-    /// Get the lock file path for a service
-    fn lock_path(service_name: &str) -> PathBuf {
-        #[cfg(unix)]
-        let base = Path::new("/tmp");
+    #[cfg(not(unix))]
+    fn is_current(path: &Path, _file: &File) -> bool {
+        path.exists()
+    }
+
+    /// Try `try_lock` until it succeeds or `wait` has passed
+    fn wait_for<T>(wait: Duration, mut try_lock: impl FnMut() -> Result<Option<T>>) -> Result<Option<T>> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(lock) = try_lock()? {
+                return Ok(Some(lock));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(WAIT_INTERVAL.min(deadline - now));
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        info!("Released lock: {:?}", self.path);
+
+        // Try to remove the lock file (best effort)
+        if self.remove_on_drop {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                debug!("Failed to remove lock file: {}", e);
+            }
+        }
+    }
+}
 
-        #[cfg(windows)]
-        let base = std::env::temp_dir();
+/// The process holding a lock, as recorded in its file
+fn read_holder(lock_path: &Path) -> Option<LockHolder> {
+    let content = std::fs::read_to_string(lock_path).ok()?;
+    serde_json::from_str(content.trim()).ok()
+}
 
-        base.join(format!("restic-manager-{}.lock", service_name))
+/// Holder record of this process
+fn this_process(run_id: Option<&str>) -> LockHolder {
+    LockHolder {
+        pid: std::process::id(),
+        run_id: run_id.map(String::from),
+        started_at: Utc::now(),
+    }
+}
+
+/// Directory of the lock files
+fn lock_dir() -> PathBuf {
+    #[cfg(unix)]
+    let base = PathBuf::from("/tmp");
+
+    #[cfg(windows)]
+    let base = std::env::temp_dir();
+
+    base
+}
+
+/// Lock guard for a service backup
+pub struct BackupLock {
+    lock: FileLock,
+}
+
+impl BackupLock {
+    /// Acquire an exclusive lock for a service
+    /// Returns [`AlreadyRunning`] if the service is already being backed up
+    pub fn acquire(service_name: &str) -> Result<Self> {
+        Self::acquire_for_run(service_name, None)
+    }
+
+    /// Acquire an exclusive lock for a service on behalf of a run
+    pub fn acquire_for_run(service_name: &str, run_id: Option<&str>) -> Result<Self> {
+        Self::acquire_waiting(service_name, run_id, Duration::ZERO)
+    }
+
+    /// Acquire a service's lock, waiting up to `wait` for another process
+    /// to release it
+    pub fn acquire_waiting(service_name: &str, run_id: Option<&str>, wait: Duration) -> Result<Self> {
+        let lock_path = Self::lock_path(service_name);
+        let holder = this_process(run_id);
+
+        let mut announced = false;
+        let lock = FileLock::wait_for(wait, || {
+            let lock = FileLock::try_acquire(&lock_path, &holder, true)?;
+            if lock.is_none() && !wait.is_zero() && !announced {
+                info!("Service '{}' is already running elsewhere, waiting up to {:?}", service_name, wait);
+                announced = true;
+            }
+            Ok(lock)
+        })?;
+
+        match lock {
+            Some(lock) => {
+                info!("Acquired backup lock for service: {}", service_name);
+                Ok(Self { lock })
+            }
+            None => Err(AlreadyRunning {
+                service: service_name.to_string(),
+                holder: read_holder(&lock_path),
+            }
+            .into()),
+        }
+    }
+
+    /// Get the lock file path for a service
+    fn lock_path(service_name: &str) -> PathBuf {
+        lock_dir().join(format!("restic-manager-{}.lock", service_name))
     }
 
     /// Get the lock file path (for cleanup or inspection)
     #[allow(dead_code)]
     pub fn path(&self) -> &Path {
-        &self.lock_path
+        &self.lock.path
     }
 }
 
-impl Drop for BackupLock {
-    fn drop(&mut self) {
-        info!("Released backup lock: {:?}", self.lock_path);
+/// Repository URL as a lock key: the same repository written in different
+/// ways (`local:` prefix, trailing slashes, surrounding spaces) gives the
+/// same key
+pub fn normalize_repo_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.strip_prefix("local:").unwrap_or(url);
+    let trimmed = url.trim_end_matches('/');
+    if trimmed.is_empty() { url.to_string() } else { trimmed.to_string() }
+}
 
-        // Try to remove the lock file (best effort)
-        if let Err(e) = std::fs::remove_file(&self.lock_path) {
-            debug!("Failed to remove lock file: {}", e);
+/// Lock of a repository, held while a backup writes to it
+///
+/// Services sharing a repository have separate [`BackupLock`]s, so this
+/// keeps their backups (and retention) from running into each other. The
+/// lock file is kept on release: a file removed while another process
+/// waits on it could let two processes lock different files.
+pub struct RepoLock {
+    _lock: FileLock,
+}
+
+impl RepoLock {
+    /// Lock a repository, waiting up to `wait` for another backup to finish
+    ///
+    /// Returns [`RepositoryBusy`] if it's still locked after `wait`.
+    pub fn acquire(repository_url: &str, run_id: Option<&str>, wait: Duration) -> Result<Self> {
+        let key = normalize_repo_url(repository_url);
+        let lock_path = Self::lock_path(&key);
+        let holder = this_process(run_id);
+
+        let mut announced = false;
+        let lock = FileLock::wait_for(wait, || {
+            let lock = FileLock::try_acquire(&lock_path, &holder, false)?;
+            if lock.is_none() && !announced {
+                info!("Repository {} is in use by another backup, waiting up to {:?}", key, wait);
+                announced = true;
+            }
+            Ok(lock)
+        })?;
+
+        match lock {
+            Some(lock) => {
+                debug!("Acquired repository lock: {}", key);
+                Ok(Self { _lock: lock })
+            }
+            None => Err(RepositoryBusy { repository: key, holder: read_holder(&lock_path) }.into()),
+        }
+    }
+
+    /// Lock file of a (normalized) repository URL
+    fn lock_path(key: &str) -> PathBuf {
+        let digest = Sha256::digest(key.as_bytes());
+        let hash: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+        lock_dir().join(format!("restic-manager-repo-{}.lock", hash))
+    }
+}
+
+/// One of the `max_concurrent_backups` slots of this host
+///
+/// Slots are lock files too, so the limit holds across processes (e.g.
+/// per-service cron jobs) as well as parallel backups within one.
+pub struct BackupSlot {
+    _lock: FileLock,
+}
+
+impl BackupSlot {
+    /// Take a free slot of `max` (none if `max` is 0), waiting up to `wait`
+    pub fn acquire(max: u32, run_id: Option<&str>, wait: Duration) -> Result<Option<Self>> {
+        if max == 0 {
+            return Ok(None);
+        }
+        let holder = this_process(run_id);
+
+        let mut announced = false;
+        let lock = FileLock::wait_for(wait, || {
+            for slot in 0..max {
+                if let Some(lock) = FileLock::try_acquire(&Self::lock_path(slot), &holder, false)? {
+                    debug!("Acquired backup slot {} of {}", slot + 1, max);
+                    return Ok(Some(lock));
+                }
+            }
+            if !announced {
+                info!("{} backups are running already (max_concurrent_backups), waiting", max);
+                announced = true;
+            }
+            Ok(None)
+        })?;
+
+        match lock {
+            Some(lock) => Ok(Some(Self { _lock: lock })),
+            None => anyhow::bail!("No backup slot became free within {:?} (max_concurrent_backups = {})", wait, max),
         }
     }
+
+    fn lock_path(slot: u32) -> PathBuf {
+        lock_dir().join(format!("restic-manager-slot-{}.lock", slot))
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +406,67 @@ mod tests {
         let lock2 = BackupLock::acquire(service).expect("Failed to acquire lock after release");
         drop(lock2);
     }
+
+    #[test]
+    fn test_lock_reports_holder() {
+        let service = "test-service-holder";
+
+        let lock = BackupLock::acquire_for_run(service, Some("abcd1234")).expect("Failed to acquire lock");
+
+        let err = BackupLock::acquire(service).err().expect("Lock should be held");
+        let running = already_running(&err).expect("Should be AlreadyRunning");
+        let holder = running.holder.as_ref().expect("Holder should be recorded");
+        assert_eq!(holder.pid, std::process::id());
+        assert_eq!(holder.run_id.as_deref(), Some("abcd1234"));
+        assert!(err.to_string().contains("already running elsewhere"));
+
+        drop(lock);
+    }
+
+    #[test]
+    fn test_lock_waits_for_release() {
+        let service = "test-service-wait";
+
+        let lock = BackupLock::acquire(service).expect("Failed to acquire lock");
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+
+        let waited = BackupLock::acquire_waiting(service, None, Duration::from_secs(10));
+        assert!(waited.is_ok());
+        releaser.join().unwrap();
+
+        // Without waiting, a held lock fails right away
+        let err = BackupLock::acquire(service).err().expect("Lock should be held");
+        assert!(already_running(&err).is_some());
+    }
+
+    #[test]
+    fn test_normalize_repo_url() {
+        assert_eq!(normalize_repo_url("/backups/shared/"), "/backups/shared");
+        assert_eq!(normalize_repo_url(" local:/backups/shared"), "/backups/shared");
+        assert_eq!(normalize_repo_url("sftp:nas:/restic//"), "sftp:nas:/restic");
+        assert_eq!(normalize_repo_url("/"), "/");
+    }
+
+    #[test]
+    fn test_repo_lock_shared_between_urls() {
+        let url = format!("/tmp/restic-manager-test-repo-{}", std::process::id());
+
+        let lock = RepoLock::acquire(&url, Some("run-1"), Duration::ZERO).expect("Failed to lock repository");
+        let err = RepoLock::acquire(&format!("local:{}/", url), None, Duration::ZERO)
+            .err()
+            .expect("Repository should be locked");
+        let busy = repository_busy(&err).expect("Should be RepositoryBusy");
+        assert_eq!(busy.holder.as_ref().and_then(|h| h.run_id.as_deref()), Some("run-1"));
+
+        drop(lock);
+        assert!(RepoLock::acquire(&url, None, Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_backup_slot_unlimited() {
+        assert!(BackupSlot::acquire(0, None, Duration::ZERO).unwrap().is_none());
+    }
 }
@@ -1,9 +1,18 @@
 pub mod restic;
 pub mod docker;
+pub mod docker_api;
+pub mod compose;
 pub mod locker;
 pub mod command;
 pub mod cron;
 pub mod restic_installer;
+pub mod ignore_file;
+pub mod secrets;
+pub mod retry;
+pub mod humanize;
+pub mod output;
+pub mod shutdown;
+pub mod scratch;
 
 // Trait-based abstractions for testability
 pub mod executor;
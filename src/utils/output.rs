@@ -0,0 +1,144 @@
+//! Console output of commands
+//!
+//! Commands print through these macros instead of `println!`, so the global
+//! `--quiet`, `--verbose` and `--no-color` flags apply everywhere:
+//!
+//! - `say!`: normal output on stdout (hidden with `--quiet`)
+//! - `say_verbose!`: details on stdout (only with `--verbose`)
+//! - `say_warn!`: warnings on stderr (hidden with `--quiet`)
+//! - `say_error!`: errors on stderr (always shown)
+//!
+//! Status markers come from `ok()`, `warn()` and `fail()`, which are only
+//! colored on a terminal. Machine-readable output (JSON, schemas, restic
+//! passthrough) is printed with plain `println!` so `--quiet` never hides
+//! what a script asked for.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// How much commands print
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Errors only
+    Quiet = 0,
+    Normal = 1,
+    /// Normal output plus details
+    Verbose = 2,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Set up output from the global flags
+///
+/// Colors are used unless `no_color` is set, `NO_COLOR` is set in the
+/// environment or stdout isn't a terminal (cron mails, CI logs).
+pub fn init(verbosity: Verbosity, no_color: bool) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    let color = !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+/// Current verbosity
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// Whether output of the given level is shown
+pub fn shows(level: Verbosity) -> bool {
+    verbosity() >= level
+}
+
+/// Whether output is colored
+pub fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+fn paint(symbol: &str, color: &str) -> String {
+    if self::color() {
+        format!("{}{}{}", color, symbol, RESET)
+    } else {
+        symbol.to_string()
+    }
+}
+
+/// Marker of a step that succeeded
+pub fn ok() -> String {
+    paint("✓", GREEN)
+}
+
+/// Marker of a warning
+pub fn warn() -> String {
+    paint("⚠", YELLOW)
+}
+
+/// Marker of a step that failed
+pub fn fail() -> String {
+    paint("✗", RED)
+}
+
+/// Print a line on stdout unless `--quiet`
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::utils::output::shows($crate::utils::output::Verbosity::Normal) {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Print a line on stdout with `--verbose` only
+#[macro_export]
+macro_rules! say_verbose {
+    ($($arg:tt)*) => {
+        if $crate::utils::output::shows($crate::utils::output::Verbosity::Verbose) {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Print a warning on stderr unless `--quiet`
+#[macro_export]
+macro_rules! say_warn {
+    ($($arg:tt)*) => {
+        if $crate::utils::output::shows($crate::utils::output::Verbosity::Normal) {
+            eprintln!($($arg)*)
+        }
+    };
+}
+
+/// Print an error on stderr (shown even with `--quiet`)
+#[macro_export]
+macro_rules! say_error {
+    ($($arg:tt)*) => {
+        eprintln!($($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_levels() {
+        init(Verbosity::Quiet, true);
+        assert!(!shows(Verbosity::Normal));
+        assert_eq!(ok(), "✓");
+
+        init(Verbosity::Verbose, true);
+        assert!(shows(Verbosity::Normal) && shows(Verbosity::Verbose));
+        assert_eq!(fail(), "✗");
+
+        init(Verbosity::Normal, true);
+        assert!(shows(Verbosity::Normal) && !shows(Verbosity::Verbose));
+    }
+}
@@ -1,29 +1,108 @@
 //! Restic subprocess utilities
-
-use super::restic_installer;
-use crate::config::{Destination, RetentionPolicy};
+//!
+//! Everything here is blocking and needs no async runtime: restic runs as a
+//! child process that the calling thread waits for, and timeouts kill it.
+//! Async applications embedding the library should call these functions
+//! from a blocking task (e.g. `tokio::task::spawn_blocking`).
+
+use super::command::{is_interrupted, CommandCancelled};
+use crate::error::BackupError;
+use super::{restic_installer, secrets, shutdown};
+use crate::config::{
+    ArchiveCompression, Destination, GlobalConfig, PasswordSource, RepositoryCompression, RetentionPolicy, TagRetention,
+};
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
-use std::sync::{mpsc, OnceLock};
+use std::ffi::{OsStr, OsString};
+use std::process::{Command, ExitStatus, Output};
+use std::sync::OnceLock;
 use std::thread;
 use std::time::Duration;
 use tracing::{info, warn};
 
-/// Execute a command with timeout using thread-based implementation
-fn execute_with_timeout(mut cmd: Command, timeout: Duration, error_msg: &str) -> Result<Output> {
-    let (tx, rx) = mpsc::channel();
+/// Longest pause between checks whether a command has exited
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(200);
 
-    thread::spawn(move || {
-        let result = cmd.output();
-        let _ = tx.send(result);
-    });
+/// Execute a command with timeout
+fn execute_with_timeout(cmd: Command, timeout: Duration, error_msg: &str) -> Result<Output> {
+    run_to_completion(cmd, None, timeout, error_msg)
+}
+
+/// Execute a command with timeout, writing `input` to its stdin
+fn execute_with_input(cmd: Command, input: Vec<u8>, timeout: Duration, error_msg: &str) -> Result<Output> {
+    run_to_completion(cmd, Some(input), timeout, error_msg)
+}
+
+/// Run a command to completion, killing it once it exceeds `timeout`
+///
+/// The calling thread waits; stdin, stdout and stderr are served by helper
+/// threads so a full pipe can't stall the command. A timed-out command is
+/// killed instead of being left running, where restic would keep holding
+/// the repository lock.
+fn run_to_completion(mut cmd: Command, input: Option<Vec<u8>>, timeout: Duration, error_msg: &str) -> Result<Output> {
+    use std::io::Write;
+    use std::process::Stdio;
+    use std::time::Instant;
+
+    let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+    cmd.stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd.spawn().context(error_msg.to_string())?;
+
+    // Closing stdin (dropping it) signals the end of the data
+    let writer = input.zip(child.stdin.take()).map(|(input, mut stdin)| thread::spawn(move || stdin.write_all(&input)));
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let mut interval = Duration::from_millis(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().context(error_msg.to_string())? {
+            break status;
+        }
+        if shutdown::requested() {
+            shutdown::terminate(&mut child);
+            return Err(CommandCancelled.into());
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                warn!("Failed to kill timed out command: {}", e);
+            }
+            return Err(BackupError::Timeout(timeout).into());
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    };
 
-    match rx.recv_timeout(timeout) {
-        Ok(result) => result.context(error_msg.to_string()),
-        Err(_) => anyhow::bail!("Command timed out after {:?}", timeout),
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .unwrap_or_else(|_| Err(std::io::Error::other("stdin writer panicked")))
+            .context(error_msg.to_string())?;
     }
+    Ok(Output { status, stdout: join_pipe(stdout), stderr: join_pipe(stderr) })
+}
+
+/// Read a child's output pipe to the end on a helper thread
+fn read_pipe(pipe: Option<impl std::io::Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn join_pipe(reader: thread::JoinHandle<Vec<u8>>) -> Vec<u8> {
+    reader.join().unwrap_or_default()
+}
+
+/// Whether restic failed because the repository is locked
+pub fn is_lock_error(stderr: &str) -> bool {
+    stderr.contains("repository is already locked") || stderr.contains("unable to create lock")
 }
 
 /// Global flag for using system restic
@@ -40,9 +119,194 @@ fn get_restic_binary() -> String {
     restic_installer::get_restic_command(use_system)
 }
 
+/// One restic invocation
+///
+/// Every restic call in this module is built here, so the binary (managed
+/// or system restic), the repository environment (including the cache
+/// directory) and the options derived from it are applied uniformly.
+/// Options go after the subcommand's arguments and before `--` and the
+/// arguments passed with [`after_separator`](Self::after_separator).
+pub struct ResticCommandBuilder<'a> {
+    env: &'a ResticEnv,
+    args: Vec<OsString>,
+    trailing: Vec<OsString>,
+    json: bool,
+    verbose: bool,
+    host: bool,
+    limits: bool,
+    storage: bool,
+    nice: bool,
+}
+
+impl<'a> ResticCommandBuilder<'a> {
+    /// Restic in the repository environment `env`
+    pub fn new(env: &'a ResticEnv) -> Self {
+        Self {
+            env,
+            args: Vec::new(),
+            trailing: Vec::new(),
+            json: false,
+            verbose: false,
+            host: false,
+            limits: false,
+            storage: false,
+            nice: false,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, args: I) -> Self {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Arguments after `--` (e.g. the producer of `--stdin-from-command`)
+    pub fn after_separator<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, args: I) -> Self {
+        self.trailing.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Machine-readable output (`--json`)
+    pub fn json(mut self) -> Self {
+        self.json = true;
+        self
+    }
+
+    /// List what restic does (`--verbose`)
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Limit to the environment's host name (`--host`)
+    pub fn host(mut self) -> Self {
+        self.host = true;
+        self
+    }
+
+    /// Apply the environment's bandwidth limits
+    pub fn limits(mut self) -> Self {
+        self.limits = true;
+        self
+    }
+
+    /// Apply the environment's compression and pack size
+    pub fn storage(mut self) -> Self {
+        self.storage = true;
+        self
+    }
+
+    /// Run restic at the lowest CPU and I/O priority
+    pub fn nice(mut self, nice: bool) -> Self {
+        self.nice = nice;
+        self
+    }
+
+    /// Arguments restic is called with
+    pub fn build_args(&self) -> Vec<OsString> {
+        let mut args = self.args.clone();
+        let mut options = Vec::new();
+        if self.json {
+            options.push("--json".to_string());
+        }
+        if self.verbose {
+            options.push("--verbose".to_string());
+        }
+        if self.host {
+            options.extend(self.env.host_args());
+        }
+        if self.limits {
+            options.extend(self.env.limit_args());
+        }
+        if self.storage {
+            options.extend(self.env.storage_args());
+        }
+        args.extend(options.into_iter().map(OsString::from));
+        if !self.trailing.is_empty() {
+            args.push("--".into());
+            args.extend(self.trailing.iter().cloned());
+        }
+        args
+    }
+
+    /// The command, ready to run
+    pub fn build(&self) -> Command {
+        let mut cmd = if self.nice { low_priority_command() } else { Command::new(get_restic_binary()) };
+        cmd.args(self.build_args());
+        for (key, value) in self.env.vars() {
+            cmd.env(key, value);
+        }
+        cmd
+    }
+
+    /// Run restic, killing it after `timeout`
+    pub fn run(&self, timeout: Duration, error_msg: &str) -> Result<Output> {
+        execute_with_timeout(self.build(), timeout, error_msg)
+    }
+
+    /// Run restic with `input` on its stdin
+    pub fn run_with_input(&self, input: Vec<u8>, timeout: Duration, error_msg: &str) -> Result<Output> {
+        execute_with_input(self.build(), input, timeout, error_msg)
+    }
+
+    /// Run restic, retrying once after removing stale locks
+    ///
+    /// `restic unlock` (without `--remove-all`) only removes locks whose
+    /// process is gone, so a lock held by a running backup is left alone and
+    /// the retry fails with the same error.
+    pub fn run_with_lock_retry(&self, timeout: Duration, error_msg: &str) -> Result<Output> {
+        let output = self.run(timeout, error_msg)?;
+
+        if output.status.success() || !is_lock_error(&String::from_utf8_lossy(&output.stderr)) {
+            return Ok(output);
+        }
+
+        warn!("Repository is locked, removing stale locks and retrying");
+        unlock_repository(self.env, Duration::from_secs(30))?;
+
+        self.run(timeout, error_msg)
+    }
+
+    /// Run restic attached to the terminal, without a timeout
+    pub fn status(&self) -> Result<ExitStatus> {
+        self.build().status().context("Failed to execute restic")
+    }
+}
+
 /// Environment variables for restic
 pub struct ResticEnv {
     vars: HashMap<String, String>,
+    append_only: bool,
+    cold_storage: bool,
+    host: Option<String>,
+    limit_upload_kbps: Option<u32>,
+    limit_download_kbps: Option<u32>,
+    compression: Option<RepositoryCompression>,
+    pack_size_mb: Option<u32>,
+}
+
+/// The lower of two optional bandwidth limits
+fn lowest_limit(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `--limit-upload` / `--limit-download` arguments
+fn bandwidth_args(upload_kbps: Option<u32>, download_kbps: Option<u32>) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(kbps) = upload_kbps {
+        args.extend(["--limit-upload".to_string(), kbps.to_string()]);
+    }
+    if let Some(kbps) = download_kbps {
+        args.extend(["--limit-download".to_string(), kbps.to_string()]);
+    }
+    args
 }
 
 impl ResticEnv {
@@ -54,11 +318,116 @@ impl ResticEnv {
             password_file.display().to_string(),
         );
         vars.insert("RESTIC_REPOSITORY".to_string(), repository_url.to_string());
-        Self { vars }
+        Self::from_vars(vars)
+    }
+
+    fn from_vars(vars: HashMap<String, String>) -> Self {
+        Self {
+            vars,
+            append_only: false,
+            cold_storage: false,
+            host: None,
+            limit_upload_kbps: None,
+            limit_download_kbps: None,
+            compression: None,
+            pack_size_mb: None,
+        }
+    }
+
+    /// Create ResticEnv for a destination, resolving the password source and
+    /// including the destination's extra environment variables (cloud credentials etc.)
+    pub fn for_destination(
+        global: &GlobalConfig,
+        destination_name: &str,
+        destination: &Destination,
+        repository_url: &str,
+    ) -> Result<Self> {
+        let mut env = match destination.password_source(global) {
+            PasswordSource::File => match destination.password_command(global) {
+                Some(command) => {
+                    let mut vars = HashMap::new();
+                    vars.insert("RESTIC_PASSWORD_COMMAND".to_string(), command.to_string());
+                    vars.insert("RESTIC_REPOSITORY".to_string(), repository_url.to_string());
+                    Self::from_vars(vars)
+                }
+                None => Self::new(&global.restic_password_file, repository_url),
+            },
+            PasswordSource::Keyring => {
+                let password = secrets::resolve_password(destination_name)?;
+                let mut vars = HashMap::new();
+                vars.insert("RESTIC_PASSWORD".to_string(), password);
+                vars.insert("RESTIC_REPOSITORY".to_string(), repository_url.to_string());
+                Self::from_vars(vars)
+            }
+        };
+
+        // Before the destination's env, which may point somewhere else
+        if let Some(ref cache_dir) = global.restic_cache_dir {
+            env.add("RESTIC_CACHE_DIR".to_string(), cache_dir.display().to_string());
+        }
+        for (key, value) in &destination.env {
+            env.add(key.clone(), value.clone());
+        }
+        env.append_only = !destination.allows_deletion(global);
+        env.cold_storage = destination.cold_storage;
+        env.limit_upload_kbps = lowest_limit(global.limit_upload_kbps, destination.limit_upload_kbps);
+        env.limit_download_kbps = lowest_limit(global.limit_download_kbps, destination.limit_download_kbps);
+        env.compression = destination.compression;
+        env.pack_size_mb = destination.pack_size_mb;
+        Ok(env)
+    }
+
+    /// Whether the repository is on cold storage, where reading data is slow
+    /// and costly
+    pub fn is_cold_storage(&self) -> bool {
+        self.cold_storage
+    }
+
+    /// Whether snapshots must not be deleted through this environment
+    pub fn is_append_only(&self) -> bool {
+        self.append_only
+    }
+
+    /// Record snapshots under `host` and only list and forget that host's
+    /// snapshots (`None` keeps restic's default: the machine's host name)
+    pub fn with_host(mut self, host: Option<&str>) -> Self {
+        self.host = host.map(String::from);
+        self
+    }
+
+    /// Apply further bandwidth limits in KiB/s (e.g. a profile's); the
+    /// lowest limit wins
+    pub fn with_limits(mut self, upload_kbps: Option<u32>, download_kbps: Option<u32>) -> Self {
+        self.limit_upload_kbps = lowest_limit(self.limit_upload_kbps, upload_kbps);
+        self.limit_download_kbps = lowest_limit(self.limit_download_kbps, download_kbps);
+        self
+    }
+
+    /// Bandwidth limit arguments of commands moving pack data (backup,
+    /// restore, prune)
+    fn limit_args(&self) -> Vec<String> {
+        bandwidth_args(self.limit_upload_kbps, self.limit_download_kbps)
+    }
+
+    /// `--compression` / `--pack-size` arguments of commands writing pack
+    /// data (init, backup, prune)
+    fn storage_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(compression) = self.compression {
+            args.extend(["--compression".to_string(), compression.as_str().to_string()]);
+        }
+        if let Some(size) = self.pack_size_mb {
+            args.extend(["--pack-size".to_string(), size.to_string()]);
+        }
+        args
+    }
+
+    /// `--host` arguments of backup, forget and snapshots
+    fn host_args(&self) -> Vec<String> {
+        self.host.iter().flat_map(|host| ["--host".to_string(), host.clone()]).collect()
     }
 
     /// Add custom environment variable
-    #[allow(dead_code)]
     pub fn add(&mut self, key: String, value: String) {
         self.vars.insert(key, value);
     }
@@ -73,14 +442,10 @@ impl ResticEnv {
 pub fn init_repository(env: &ResticEnv, timeout: Duration) -> Result<()> {
     info!("Initializing restic repository...");
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("init");
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
-    }
-
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic init")?;
+    let output = ResticCommandBuilder::new(env)
+        .arg("init")
+        .storage()
+        .run(timeout, "Failed to execute restic init")?;
 
     // Repository might already exist - that's okay
     if output.status.success() {
@@ -92,285 +457,1364 @@ pub fn init_repository(env: &ResticEnv, timeout: Duration) -> Result<()> {
             info!("Repository already initialized");
             Ok(())
         } else {
-            anyhow::bail!("Failed to initialize repository: {}", stderr)
+            Err(BackupError::RepoInit { stderr: stderr.trim().to_string() }.into())
         }
     }
 }
 
-/// Backup files to restic repository
-pub fn backup(
-    env: &ResticEnv,
-    paths: &[PathBuf],
-    excludes: &[String],
-    timeout: Duration,
-) -> Result<()> {
-    if paths.is_empty() {
-        warn!("No paths to backup");
-        return Ok(());
+/// What `restic backup` leaves out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Excludes {
+    /// `--exclude` patterns
+    pub patterns: Vec<String>,
+    /// `--iexclude` patterns (case-insensitive)
+    pub ipatterns: Vec<String>,
+    /// `--exclude-file` files holding patterns
+    pub files: Vec<PathBuf>,
+    /// `--exclude-larger-than` size in bytes
+    pub larger_than: Option<u64>,
+    /// `--one-file-system`
+    pub one_file_system: bool,
+}
+
+impl Excludes {
+    /// Plain `--exclude` patterns only
+    pub fn from_patterns(patterns: &[String]) -> Self {
+        Self { patterns: patterns.to_vec(), ..Default::default() }
     }
 
-    info!("Starting restic backup for {} paths", paths.len());
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for pattern in &self.patterns {
+            args.push("--exclude".to_string());
+            args.push(pattern.clone());
+        }
+        for pattern in &self.ipatterns {
+            args.push("--iexclude".to_string());
+            args.push(pattern.clone());
+        }
+        for file in &self.files {
+            args.push("--exclude-file".to_string());
+            args.push(file.display().to_string());
+        }
+        if let Some(bytes) = self.larger_than {
+            args.push("--exclude-larger-than".to_string());
+            args.push(bytes.to_string());
+        }
+        if self.one_file_system {
+            args.push("--one-file-system".to_string());
+        }
+        args
+    }
+}
+
+/// Files listing what `restic backup` should back up, next to its paths
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilesFrom {
+    /// Lists that may hold patterns and `#` comments (`--files-from`)
+    pub lists: Vec<PathBuf>,
+    /// Lists of exact paths, one per line (`--files-from-verbatim`)
+    pub verbatim: Vec<PathBuf>,
+}
 
+impl FilesFrom {
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty() && self.verbatim.is_empty()
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for list in &self.lists {
+            args.push("--files-from".to_string());
+            args.push(list.display().to_string());
+        }
+        for list in &self.verbatim {
+            args.push("--files-from-verbatim".to_string());
+            args.push(list.display().to_string());
+        }
+        args
+    }
+}
+
+/// Build the shared `restic backup` arguments for paths, file lists,
+/// excludes and tags
+fn backup_args(paths: &[PathBuf], files_from: &FilesFrom, excludes: &Excludes, tags: &[String]) -> Vec<String> {
     let mut args = vec!["backup".to_string()];
 
     // Add paths
     for path in paths {
         args.push(path.display().to_string());
     }
+    args.extend(files_from.args());
 
-    // Add excludes
-    for exclude in excludes {
-        args.push("--exclude".to_string());
-        args.push(exclude.clone());
-    }
+    args.extend(excludes.args());
 
     // Always exclude cache directories
     args.push("--exclude-caches".to_string());
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    for arg in &args {
-        cmd.arg(arg);
+    // restic splits --tag values on commas, so commas can't be part of a tag
+    for tag in tags {
+        args.push("--tag".to_string());
+        args.push(tag.replace(',', ";"));
     }
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
+
+    args
+}
+
+/// Backup files to restic repository
+pub fn backup(
+    env: &ResticEnv,
+    paths: &[PathBuf],
+    excludes: &[String],
+    timeout: Duration,
+) -> Result<BackupSummary> {
+    backup_with_tags(env, paths, &FilesFrom::default(), &Excludes::from_patterns(excludes), &[], timeout)
+}
+
+/// Backup files to restic repository, tagging the snapshot
+pub fn backup_with_tags(
+    env: &ResticEnv,
+    paths: &[PathBuf],
+    files_from: &FilesFrom,
+    excludes: &Excludes,
+    tags: &[String],
+    timeout: Duration,
+) -> Result<BackupSummary> {
+    if paths.is_empty() && files_from.is_empty() {
+        warn!("No paths to backup");
+        return Ok(BackupSummary::default());
     }
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic backup")?;
+    info!("Starting restic backup for {} paths", paths.len());
+
+    let output = ResticCommandBuilder::new(env)
+        .args(backup_args(paths, files_from, excludes, tags))
+        .json()
+        .host()
+        .limits()
+        .storage()
+        .run(timeout, "Failed to execute restic backup")?;
+
+    if is_interrupted(&output.status) {
+        return Err(anyhow::Error::new(CommandCancelled).context("Backup interrupted"));
+    }
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Backup failed: {}", stderr);
+        return Err(BackupError::restic_failed("Backup", &stderr).into());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    info!("Backup completed successfully");
-    println!("{}", stdout);
+    let summary = summary_from_output(&output);
+    info!(
+        "Backup completed successfully: {} new, {} changed files, {} added",
+        summary.files_new,
+        summary.files_changed,
+        format_bytes(summary.data_added)
+    );
 
-    Ok(())
+    Ok(summary)
 }
 
-/// Apply retention policy to repository
-pub fn apply_retention(
-    env: &ResticEnv,
-    retention: &RetentionPolicy,
-    timeout: Duration,
-) -> Result<()> {
-    info!("Applying retention policy...");
+/// `--tag` arguments (commas would split a tag into several)
+fn tag_args(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .flat_map(|tag| ["--tag".to_string(), tag.replace(',', ";")])
+        .collect()
+}
 
-    let daily_str = retention.daily.to_string();
-    let weekly_str = retention.weekly.to_string();
-    let monthly_str = retention.monthly.to_string();
-    let yearly_str = retention.yearly.to_string();
-
-    let args = vec![
-        "forget",
-        "--prune",
-        "--keep-daily",
-        &daily_str,
-        "--keep-weekly",
-        &weekly_str,
-        "--keep-monthly",
-        &monthly_str,
-        "--keep-yearly",
-        &yearly_str,
+/// `restic backup --stdin` arguments storing the data as `filename`
+fn stdin_backup_args(filename: &str, tags: &[String]) -> Vec<String> {
+    let mut args = vec![
+        "backup".to_string(),
+        "--stdin".to_string(),
+        "--stdin-filename".to_string(),
+        filename.to_string(),
     ];
+    args.extend(tag_args(tags));
+    args
+}
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    for arg in &args {
-        cmd.arg(arg);
-    }
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
-    }
+/// `restic backup --stdin-from-command` storing `command`'s output as
+/// `filename` (restic's own options must come before the command)
+fn command_backup<'a>(env: &'a ResticEnv, filename: &str, tags: &[String], command: &[String]) -> ResticCommandBuilder<'a> {
+    ResticCommandBuilder::new(env)
+        .args(["backup", "--stdin-from-command", "--stdin-filename", filename])
+        .args(tag_args(tags))
+        .json()
+        .host()
+        .limits()
+        .storage()
+        .after_separator(command)
+}
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic forget")?;
+/// Back up data (e.g. a command's output) as a single file, without a temp file
+pub fn backup_stdin(
+    env: &ResticEnv,
+    data: Vec<u8>,
+    filename: &str,
+    tags: &[String],
+    timeout: Duration,
+) -> Result<BackupSummary> {
+    info!("Starting restic stdin backup as '{}' ({})", filename, format_bytes(data.len() as u64));
+
+    let output = ResticCommandBuilder::new(env)
+        .args(stdin_backup_args(filename, tags))
+        .json()
+        .host()
+        .limits()
+        .storage()
+        .run_with_input(data, timeout, "Failed to execute restic backup")?;
+
+    if is_interrupted(&output.status) {
+        return Err(anyhow::Error::new(CommandCancelled).context("Backup interrupted"));
+    }
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        warn!("Failed to apply retention policy: {}", stderr);
-        // Don't fail the entire backup if retention fails
-    } else {
-        info!("Retention policy applied successfully");
+        return Err(BackupError::restic_failed(format!("Backup of '{}'", filename), &stderr).into());
     }
 
-    Ok(())
+    Ok(summary_from_output(&output))
 }
 
-/// Unlock repository (useful after failures)
-pub fn unlock_repository(env: &ResticEnv, timeout: Duration) -> Result<()> {
-    info!("Unlocking restic repository...");
+/// Back up the stdout of a command as a single file, streamed by restic
+///
+/// Restic runs the command itself and creates no snapshot if it exits with
+/// an error, so a failed producer never leaves a truncated file behind.
+/// Needs restic 0.17 or newer (see [`supports_stdin_from_command`]).
+pub fn backup_from_command(
+    env: &ResticEnv,
+    command: &[String],
+    filename: &str,
+    tags: &[String],
+    timeout: Duration,
+) -> Result<BackupSummary> {
+    info!("Starting restic backup of '{}' streamed from {}", filename, command.join(" "));
 
-    let mut cmd = std::process::Command::new("restic");
-    cmd.arg("unlock");
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
-    }
+    let output = command_backup(env, filename, tags, command)
+        .run_with_lock_retry(timeout, "Failed to execute restic backup")?;
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic unlock")?;
+    if is_interrupted(&output.status) {
+        return Err(anyhow::Error::new(CommandCancelled).context("Backup interrupted"));
+    }
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        warn!("Failed to unlock repository: {}", stderr);
-        // Don't fail - repository might not be locked
-    } else {
-        info!("Repository unlocked successfully");
+        return Err(BackupError::restic_failed(format!("Backup of '{}'", filename), &stderr).into());
     }
 
-    Ok(())
+    Ok(summary_from_output(&output))
 }
 
-/// Build repository URL for a destination and service
-pub fn build_repository_url(destination: &Destination, service_name: &str, suffix: Option<&str>) -> String {
-    let base_url = &destination.url;
-    let repo_name = if let Some(sfx) = suffix {
-        format!("{}{}", service_name, sfx)
-    } else {
-        service_name.to_string()
-    };
+/// Parse the version out of `restic version` output ("restic 0.18.1 compiled with ...")
+pub fn parse_restic_version(output: &str) -> Option<(u32, u32, u32)> {
+    let version = output.split_whitespace().nth(1)?;
+    let mut parts = version.trim_start_matches('v').split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
 
-    // Append service name to URL
-    if base_url.ends_with('/') {
-        format!("{}{}", base_url, repo_name)
-    } else {
-        format!("{}/{}", base_url, repo_name)
+/// Whether the restic binary supports `backup --stdin-from-command` (0.17+)
+pub fn supports_stdin_from_command() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        let use_system = USE_SYSTEM_RESTIC.get().copied().unwrap_or(false);
+        restic_installer::get_restic_version(use_system)
+            .ok()
+            .and_then(|output| parse_restic_version(&output))
+            .is_some_and(|version| version >= (0, 17, 0))
+    })
+}
+
+/// Summary message emitted by `restic backup --json`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BackupSummary {
+    #[serde(default)]
+    pub files_new: u64,
+    #[serde(default)]
+    pub files_changed: u64,
+    #[serde(default)]
+    pub files_unmodified: u64,
+    #[serde(default)]
+    pub data_added: u64,
+    #[serde(default)]
+    pub total_files_processed: u64,
+    #[serde(default)]
+    pub total_bytes_processed: u64,
+    #[serde(default)]
+    pub snapshot_id: Option<String>,
+    /// What restic printed to stderr (unreadable files and other warnings)
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl BackupSummary {
+    /// Add the counts of another backup of the same run (keeps this snapshot id)
+    pub fn merge(&mut self, other: &BackupSummary) {
+        self.files_new += other.files_new;
+        self.files_changed += other.files_changed;
+        self.files_unmodified += other.files_unmodified;
+        self.data_added += other.data_added;
+        self.total_files_processed += other.total_files_processed;
+        self.total_bytes_processed += other.total_bytes_processed;
+        if self.snapshot_id.is_none() {
+            self.snapshot_id = other.snapshot_id.clone();
+        }
+        self.warnings.extend(other.warnings.iter().cloned());
     }
 }
 
-/// Snapshot information
-#[derive(Debug, Clone)]
-pub struct Snapshot {
-    pub id: String,
-    pub short_id: String,
-    pub time: String,
-    pub hostname: String,
-    #[allow(dead_code)]
-    pub paths: Vec<String>,
+/// Summary of a successful `restic backup --json`, with its stderr lines
+fn summary_from_output(output: &Output) -> BackupSummary {
+    let mut summary = parse_backup_summary(&String::from_utf8_lossy(&output.stdout)).unwrap_or_default();
+    summary.warnings = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    summary
 }
 
-/// List snapshots in a repository
-pub fn list_snapshots(env: &ResticEnv, timeout: Duration) -> Result<Vec<Snapshot>> {
-    info!("Listing snapshots from repository...");
+/// Find the summary message in `restic backup --json` output
+///
+/// restic prints one JSON object per line (status updates, verbose file
+/// messages, errors); only the `summary` message is of interest here.
+pub fn parse_backup_summary(output: &str) -> Option<BackupSummary> {
+    output
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+        .find(|msg| msg["message_type"] == "summary")
+        .and_then(|msg| serde_json::from_value(msg).ok())
+}
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("snapshots")
-        .arg("--json");
+/// A file a dry-run backup would add or change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRunItem {
+    /// `new` or `modified`
+    pub action: String,
+    pub path: String,
+    /// Size of the file's data in bytes
+    pub size: u64,
+}
+
+/// Result of `restic backup --dry-run`
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    pub summary: BackupSummary,
+    /// Files that would be added or changed (only with `verbose`)
+    pub items: Vec<DryRunItem>,
+}
+
+/// New and modified files from `restic backup --dry-run --verbose --json`
+pub fn parse_dry_run_items(output: &str) -> Vec<DryRunItem> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|msg| msg["message_type"] == "verbose_status")
+        .filter_map(|msg| {
+            let action = msg["action"].as_str()?;
+            if action != "new" && action != "modified" {
+                return None;
+            }
+            Some(DryRunItem {
+                action: action.to_string(),
+                path: msg["item"].as_str()?.to_string(),
+                size: msg["data_size"].as_u64().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
 
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
+/// Run a dry-run backup and report how much data would be uploaded, and
+/// with `verbose` which files would be added or changed
+pub fn forecast_backup(
+    env: &ResticEnv,
+    paths: &[PathBuf],
+    files_from: &FilesFrom,
+    excludes: &Excludes,
+    verbose: bool,
+    timeout: Duration,
+) -> Result<DryRun> {
+    if paths.is_empty() && files_from.is_empty() {
+        return Ok(DryRun::default());
     }
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic snapshots")?;
+    info!("Forecasting restic backup for {} paths", paths.len());
+
+    let output = ResticCommandBuilder::new(env)
+        .args(backup_args(paths, files_from, excludes, &[]))
+        .arg("--dry-run")
+        .json()
+        .verbose(verbose)
+        .host()
+        .run(timeout, "Failed to execute restic backup --dry-run")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to list snapshots: {}", stderr);
+        return Err(BackupError::restic_failed("Dry-run backup", &stderr).into());
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-
-    // Parse JSON output
-    let snapshots_json: Vec<serde_json::Value> = serde_json::from_str(&stdout)
-        .context("Failed to parse snapshots JSON")?;
-
-    let mut snapshots = Vec::new();
-    for snapshot in snapshots_json {
-        let id = snapshot["id"].as_str().unwrap_or("").to_string();
-        let short_id = snapshot["short_id"].as_str().unwrap_or("").to_string();
-        let time = snapshot["time"].as_str().unwrap_or("").to_string();
-        let hostname = snapshot["hostname"].as_str().unwrap_or("").to_string();
-
-        let paths = snapshot["paths"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        snapshots.push(Snapshot {
-            id,
-            short_id,
-            time,
-            hostname,
-            paths,
-        });
-    }
-
-    info!("Found {} snapshots", snapshots.len());
-    Ok(snapshots)
+    let summary = parse_backup_summary(&stdout).context("No summary found in restic dry-run output")?;
+    Ok(DryRun { summary, items: parse_dry_run_items(&stdout) })
 }
 
-/// Get repository stats
-pub fn get_stats(env: &ResticEnv, timeout: Duration) -> Result<String> {
-    info!("Getting repository statistics...");
-
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("stats")
-        .arg("--mode")
-        .arg("restore-size");
+pub use super::humanize::format_bytes;
 
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
+/// Apply retention policy to repository
+pub fn apply_retention(
+    env: &ResticEnv,
+    retention: &RetentionPolicy,
+    timeout: Duration,
+) -> Result<()> {
+    if env.is_append_only() {
+        anyhow::bail!("Refusing to apply retention policy: repository is append-only");
     }
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic stats")?;
+    info!("Applying retention policy...");
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        warn!("Failed to get repository stats: {}", stderr);
-        return Ok("Unknown".to_string());
-    }
+    // Tag rules first; the last invocation (regular policy) also prunes
+    let arg_sets = forget_arg_sets(retention);
+    let last = arg_sets.len() - 1;
+    for (index, args) in arg_sets.into_iter().enumerate() {
+        let mut cmd = ResticCommandBuilder::new(env).arg("forget").args(args).host();
+        if index == last {
+            cmd = cmd.arg("--prune").limits().storage();
+        }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+        let output = cmd.run(timeout, "Failed to execute restic forget")?;
 
-    // Extract total size from output
-    for line in stdout.lines() {
-        if line.contains("Total Size:") {
-            let size = line.split(':').nth(1).unwrap_or("Unknown").trim();
-            return Ok(size.to_string());
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(BackupError::restic_failed("Retention", &stderr).into());
         }
     }
 
-    Ok("Unknown".to_string())
+    info!("Retention policy applied successfully");
+    Ok(())
 }
 
-/// Check repository integrity
-pub fn check_repository(env: &ResticEnv, read_data: bool, timeout: Duration) -> Result<String> {
-    info!("Checking repository integrity...");
-
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("check");
+/// `--keep-*` arguments for a retention policy
+///
+/// Snapshots with a tag that has its own rule are always kept here; their
+/// rule is applied by a separate `forget --tag` (see [`forget_arg_sets`]).
+pub fn retention_args(retention: &RetentionPolicy) -> Vec<String> {
+    let mut args = vec![
+        "--keep-daily".to_string(),
+        retention.daily.to_string(),
+        "--keep-weekly".to_string(),
+        retention.weekly.to_string(),
+        "--keep-monthly".to_string(),
+        retention.monthly.to_string(),
+        "--keep-yearly".to_string(),
+        retention.yearly.to_string(),
+    ];
+    if retention.hourly > 0 {
+        args.push("--keep-hourly".to_string());
+        args.push(retention.hourly.to_string());
+    }
+    if retention.last > 0 {
+        args.push("--keep-last".to_string());
+        args.push(retention.last.to_string());
+    }
+    if let Some(ref within) = retention.within {
+        args.push("--keep-within".to_string());
+        args.push(within.clone());
+    }
+    for tag in retention.tags.keys() {
+        args.push("--keep-tag".to_string());
+        args.push(tag.clone());
+    }
+    args
+}
 
-    if read_data {
-        cmd.arg("--read-data");
-        info!("Deep verification enabled (this may take a while)");
+/// `forget` arguments applying a tag's rule, or `None` for tags kept forever
+pub fn tag_retention_args(tag: &str, rule: &TagRetention) -> Option<Vec<String>> {
+    if rule.forever {
+        return None;
     }
 
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
+    let mut args = vec!["--tag".to_string(), tag.to_string()];
+    if let Some(ref within) = rule.within {
+        args.push("--keep-within".to_string());
+        args.push(within.clone());
     }
+    if let Some(last) = rule.last {
+        args.push("--keep-last".to_string());
+        args.push(last.to_string());
+    }
+    Some(args)
+}
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic check")?;
+/// Argument sets for the `forget` invocations applying a retention policy:
+/// one per tag rule, then the regular policy last
+pub fn forget_arg_sets(retention: &RetentionPolicy) -> Vec<Vec<String>> {
+    let mut sets: Vec<Vec<String>> = retention
+        .tags
+        .iter()
+        .filter_map(|(tag, rule)| tag_retention_args(tag, rule))
+        .collect();
+    sets.push(retention_args(retention));
+    sets
+}
+
+/// Count the snapshots `restic forget --json` removes (or would remove)
+pub fn parse_forget_removals(output: &str) -> usize {
+    // One JSON array of snapshot groups, each with `keep` and `remove` lists
+    serde_json::from_str::<Vec<serde_json::Value>>(output.trim())
+        .map(|groups| {
+            groups
+                .iter()
+                .filter_map(|group| group["remove"].as_array())
+                .map(|remove| remove.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Forget snapshots outside the retention policy (without pruning data)
+///
+/// Returns the number of snapshots removed, or that would be removed
+/// with `dry_run`.
+pub fn forget(
+    env: &ResticEnv,
+    retention: &RetentionPolicy,
+    dry_run: bool,
+    timeout: Duration,
+) -> Result<usize> {
+    if env.is_append_only() && !dry_run {
+        anyhow::bail!("Refusing to forget snapshots: repository is append-only");
+    }
+
+    let mut removed = 0;
+    for args in forget_arg_sets(retention) {
+        let mut cmd = ResticCommandBuilder::new(env).arg("forget").args(&args).json().host();
+        if dry_run {
+            cmd = cmd.arg("--dry-run");
+        }
+
+        let output = cmd.run_with_lock_retry(timeout, "Failed to execute restic forget")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(BackupError::restic_failed("Forget", &stderr).into());
+        }
+
+        removed += parse_forget_removals(&String::from_utf8_lossy(&output.stdout));
+    }
+
+    Ok(removed)
+}
+
+/// Tuning options for `restic prune`
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    /// Unused space to tolerate, e.g. `10%` or `5G` (less repacking)
+    pub max_unused: String,
+    /// Upper limit for repacked data per run, e.g. `50G`
+    pub max_repack_size: Option<String>,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            max_unused: "10%".to_string(),
+            max_repack_size: None,
+        }
+    }
+}
+
+/// Lines of `restic prune` output worth reporting
+const PRUNE_SUMMARY_PREFIXES: &[&str] = &["to repack:", "total prune:", "remaining:", "unused size after prune:"];
+
+/// Remove unreferenced data from the repository
+///
+/// Returns restic's summary of what was removed and repacked.
+pub fn prune(env: &ResticEnv, options: &PruneOptions, timeout: Duration) -> Result<String> {
+    if env.is_append_only() {
+        anyhow::bail!("Refusing to prune: repository is append-only");
+    }
+
+    info!("Pruning repository...");
+
+    let mut cmd = ResticCommandBuilder::new(env)
+        .args(["prune", "--max-unused", &options.max_unused])
+        .limits()
+        .storage();
+    if let Some(ref size) = options.max_repack_size {
+        cmd = cmd.args(["--max-repack-size", size]);
+    }
+
+    let output = cmd.run_with_lock_retry(timeout, "Failed to execute restic prune")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Prune", &stderr).into());
+    }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    let summary: Vec<&str> = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| PRUNE_SUMMARY_PREFIXES.iter().any(|prefix| line.starts_with(prefix)))
+        .collect();
+
+    info!("Prune completed successfully");
+    Ok(summary.join("\n"))
+}
+
+/// Unlock repository (useful after failures)
+pub fn unlock_repository(env: &ResticEnv, timeout: Duration) -> Result<()> {
+    info!("Unlocking restic repository...");
+
+    let output = ResticCommandBuilder::new(env)
+        .arg("unlock")
+        .run(timeout, "Failed to execute restic unlock")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!("Failed to unlock repository: {}", stderr);
+        // Don't fail - repository might not be locked
+    } else {
+        info!("Repository unlocked successfully");
+    }
+
+    Ok(())
+}
+
+/// Check that a repository can be opened: reachable, initialized and
+/// unlocked by the password (`restic cat config`)
+pub fn check_access(env: &ResticEnv, timeout: Duration) -> Result<()> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["cat", "config"])
+        .run(timeout, "Failed to execute restic cat config")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Opening the repository", &stderr).into());
+    }
+    Ok(())
+}
+
+/// A key (password) of a repository, from `restic key list --json`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RepositoryKey {
+    /// The key the repository was opened with
+    #[serde(default)]
+    pub current: bool,
+    pub id: String,
+    #[serde(default, rename = "userName")]
+    pub user_name: String,
+    #[serde(default, rename = "hostName")]
+    pub host_name: String,
+    #[serde(default)]
+    pub created: String,
+}
+
+/// Parse the output of `restic key list --json`
+pub fn parse_key_list(json: &str) -> Result<Vec<RepositoryKey>> {
+    serde_json::from_str(json).context("Failed to parse key list JSON")
+}
+
+/// List the keys of a repository
+pub fn list_keys(env: &ResticEnv, timeout: Duration) -> Result<Vec<RepositoryKey>> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["key", "list"])
+        .json()
+        .run(timeout, "Failed to execute restic key list")?;
 
     if !output.status.success() {
-        anyhow::bail!("Repository check failed:\n{}\n{}", stdout, stderr);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Listing keys", &stderr).into());
     }
+    parse_key_list(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Add a key with the password in `new_password_file`
+pub fn add_key(env: &ResticEnv, new_password_file: &Path, timeout: Duration) -> Result<()> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["key", "add", "--new-password-file"])
+        .arg(new_password_file)
+        .run(timeout, "Failed to execute restic key add")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Adding a key", &stderr).into());
+    }
+    Ok(())
+}
+
+/// Replace the current key with one for the password in `new_password_file`
+pub fn change_key_password(env: &ResticEnv, new_password_file: &Path, timeout: Duration) -> Result<()> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["key", "passwd", "--new-password-file"])
+        .arg(new_password_file)
+        .run(timeout, "Failed to execute restic key passwd")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Changing the password", &stderr).into());
+    }
+    Ok(())
+}
+
+/// Remove a key (restic refuses to remove the current one)
+pub fn remove_key(env: &ResticEnv, id: &str, timeout: Duration) -> Result<()> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["key", "remove", id])
+        .run(timeout, "Failed to execute restic key remove")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed(format!("Removing key {}", id), &stderr).into());
+    }
+    Ok(())
+}
+
+/// Remove caches of repositories not used for `max_age_days`
+/// (`restic cache --cleanup`) from `cache_dir`, or restic's default cache
+/// location
+///
+/// Returns restic's report of what was removed.
+pub fn cleanup_cache(cache_dir: Option<&Path>, max_age_days: u32, timeout: Duration) -> Result<String> {
+    let mut env = ResticEnv::from_vars(HashMap::new());
+    if let Some(dir) = cache_dir {
+        env.add("RESTIC_CACHE_DIR".to_string(), dir.display().to_string());
+    }
+
+    let output = ResticCommandBuilder::new(&env)
+        .args(["cache", "--cleanup", "--max-age", &max_age_days.to_string()])
+        .run(timeout, "Failed to execute restic cache")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Cache cleanup", &stderr).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Restic subcommands that never modify the repository or local files
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "cat", "check", "diff", "find", "help", "list", "ls", "mount", "snapshots", "stats", "version",
+];
+
+/// Subcommands that delete snapshots or data (refused on append-only repositories)
+const DELETING_COMMANDS: &[&str] = &["forget", "prune"];
+
+/// Global restic flags that take the next argument as their value
+const GLOBAL_VALUE_FLAGS: &[&str] = &[
+    "-r", "--repo", "--repository-file", "-p", "--password-file", "--password-command", "--key-hint",
+    "--cache-dir", "--cacert", "--tls-client-cert", "-o", "--option", "--limit-upload", "--limit-download",
+    "--compression", "--pack-size", "--retry-lock", "--http-user-agent", "--stuck-request-timeout",
+];
+
+/// Global restic flags without a value
+const GLOBAL_SWITCHES: &[&str] = &[
+    "-v", "--verbose", "-q", "--quiet", "--json", "--no-cache", "--no-lock", "--cleanup-cache",
+    "--insecure-tls", "--insecure-no-password", "--no-extra-verify", "-h", "--help",
+];
+
+/// Subcommand of a raw restic invocation, skipping global flags and their
+/// values (`-r /repo forget` is `forget`)
+///
+/// Errs with an unknown flag before the subcommand, since whether it takes
+/// a value (and so what the subcommand is) can't be told.
+pub fn raw_subcommand(args: &[String]) -> std::result::Result<Option<&str>, &str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let arg = arg.as_str();
+        if !arg.starts_with('-') {
+            return Ok(Some(arg));
+        }
+        if arg == "--" {
+            return Ok(iter.next().map(String::as_str));
+        }
+
+        let repeated_verbose = arg.len() > 2 && arg[1..].chars().all(|c| c == 'v');
+        // `--flag=value`, or a short flag with its value attached (`-r/srv/repo`)
+        let inline_value = arg.contains('=') || (!arg.starts_with("--") && arg.len() > 2 && GLOBAL_VALUE_FLAGS.contains(&&arg[..2]));
+        if GLOBAL_SWITCHES.contains(&arg) || repeated_verbose || inline_value {
+            continue;
+        }
+        if GLOBAL_VALUE_FLAGS.contains(&arg) {
+            iter.next();
+            continue;
+        }
+        return Err(arg);
+    }
+    Ok(None)
+}
+
+/// Whether a raw restic invocation deletes snapshots or data
+///
+/// An unknown flag before the subcommand counts as deleting.
+pub fn is_deleting_command(args: &[String]) -> bool {
+    match raw_subcommand(args) {
+        Ok(command) => command.is_some_and(|command| DELETING_COMMANDS.contains(&command)),
+        Err(_) => true,
+    }
+}
+
+/// Whether a raw restic invocation only reads from the repository
+///
+/// Unknown subcommands (and unknown flags before the subcommand) are treated
+/// as mutating. `check` with `--read-data` still only reads, but `check` is
+/// listed here because it never writes.
+pub fn is_read_only_command(args: &[String]) -> bool {
+    match raw_subcommand(args) {
+        Ok(Some(command)) => READ_ONLY_COMMANDS.contains(&command),
+        Ok(None) => true,
+        Err(_) => false,
+    }
+}
+
+/// Run restic with arbitrary arguments, inheriting stdin/stdout/stderr
+///
+/// Used by `restic-manager raw` for operations that aren't wrapped yet.
+/// No timeout is applied since the command may be interactive.
+pub fn run_passthrough(env: &ResticEnv, args: &[String]) -> Result<std::process::ExitStatus> {
+    ResticCommandBuilder::new(env).args(args).status()
+}
+
+/// Build repository URL for a destination and service
+pub fn build_repository_url(destination: &Destination, service_name: &str, suffix: Option<&str>) -> String {
+    let base_url = destination.base_url();
+    let repo_name = if let Some(sfx) = suffix {
+        format!("{}{}", service_name, sfx)
+    } else {
+        service_name.to_string()
+    };
+
+    // Append service name to URL
+    if base_url.ends_with('/') {
+        format!("{}{}", base_url, repo_name)
+    } else {
+        format!("{}/{}", base_url, repo_name)
+    }
+}
+
+/// Snapshot as listed by `restic snapshots --json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    #[serde(default)]
+    pub short_id: String,
+    pub time: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Snapshot this one was based on (none for the first backup of a path set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// ID of the snapshot's root tree
+    #[serde(default)]
+    pub tree: String,
+    /// restic version that created the snapshot (restic 0.17+)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_version: Option<String>,
+    /// Statistics of the backup that created the snapshot (restic 0.17+)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SnapshotSummary>,
+}
+
+/// Backup statistics stored in a snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    #[serde(default)]
+    pub backup_start: String,
+    #[serde(default)]
+    pub backup_end: String,
+    #[serde(default)]
+    pub files_new: u64,
+    #[serde(default)]
+    pub files_changed: u64,
+    #[serde(default)]
+    pub files_unmodified: u64,
+    #[serde(default)]
+    pub dirs_new: u64,
+    #[serde(default)]
+    pub dirs_changed: u64,
+    #[serde(default)]
+    pub dirs_unmodified: u64,
+    #[serde(default)]
+    pub data_blobs: u64,
+    #[serde(default)]
+    pub tree_blobs: u64,
+    #[serde(default)]
+    pub data_added: u64,
+    #[serde(default)]
+    pub data_added_packed: u64,
+    #[serde(default)]
+    pub total_files_processed: u64,
+    #[serde(default)]
+    pub total_bytes_processed: u64,
+}
+
+impl Snapshot {
+    /// Whether the snapshot carries any of `tags` (all snapshots match none)
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag))
+    }
+}
+
+/// The newest snapshot taken at or before `at`
+///
+/// Snapshot times carry their UTC offset, so snapshots taken in different
+/// timezones (or across a DST change) compare correctly.
+pub fn snapshot_at(snapshots: &[Snapshot], at: chrono::DateTime<chrono::Utc>) -> Option<&Snapshot> {
+    snapshots
+        .iter()
+        .filter_map(|s| Some((chrono::DateTime::parse_from_rfc3339(&s.time).ok()?, s)))
+        .filter(|(time, _)| *time <= at)
+        .max_by_key(|(time, _)| *time)
+        .map(|(_, s)| s)
+}
+
+/// List snapshots in a repository
+pub fn list_snapshots(env: &ResticEnv, timeout: Duration) -> Result<Vec<Snapshot>> {
+    info!("Listing snapshots from repository...");
+
+    let output = ResticCommandBuilder::new(env)
+        .args(["snapshots", "--no-lock"])
+        .json()
+        .host()
+        .run(timeout, "Failed to execute restic snapshots")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Listing snapshots", &stderr).into());
+    }
+
+    let snapshots = parse_snapshots(&String::from_utf8_lossy(&output.stdout))?;
+    info!("Found {} snapshots", snapshots.len());
+    Ok(snapshots)
+}
+
+/// Parse the output of `restic snapshots --json`
+pub fn parse_snapshots(json: &str) -> Result<Vec<Snapshot>> {
+    let mut snapshots: Vec<Snapshot> = serde_json::from_str(json).context("Failed to parse snapshots JSON")?;
+    // Older restic versions don't list the short ID
+    for snapshot in &mut snapshots {
+        if snapshot.short_id.is_empty() {
+            snapshot.short_id = snapshot.id.chars().take(8).collect();
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Size of all snapshots of a repository when restored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoStats {
+    #[serde(default)]
+    pub total_size: u64,
+    #[serde(default)]
+    pub total_file_count: u64,
+    #[serde(default)]
+    pub snapshots_count: u64,
+}
+
+/// Output of `restic stats --json` in the given mode, over the given
+/// snapshots (all snapshots if none are given)
+fn stats_json(env: &ResticEnv, mode: &str, snapshot_ids: &[&str], timeout: Duration) -> Result<String> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["stats", "--no-lock", "--mode", mode])
+        .args(snapshot_ids.iter().copied())
+        .json()
+        .run(timeout, "Failed to execute restic stats")?;
+    if !output.status.success() {
+        return Err(BackupError::restic_failed(format!("restic stats --mode {}", mode), &String::from_utf8_lossy(&output.stderr)).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `restic stats --json --mode restore-size`
+pub fn parse_repo_stats(json: &str) -> Result<RepoStats> {
+    serde_json::from_str(json.trim()).context("Failed to parse stats JSON")
+}
+
+/// Get repository stats (restore size, file and snapshot count)
+pub fn get_stats(env: &ResticEnv, timeout: Duration) -> Result<RepoStats> {
+    info!("Getting repository statistics...");
+    parse_repo_stats(&stats_json(env, "restore-size", &[], timeout)?)
+}
+
+/// Restore size and file count of one snapshot
+pub fn snapshot_stats(env: &ResticEnv, snapshot_id: &str, timeout: Duration) -> Result<RepoStats> {
+    parse_repo_stats(&stats_json(env, "restore-size", &[snapshot_id], timeout)?)
+}
+
+/// Sizes of a repository, from `restic stats --json` in both modes
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RepositoryStats {
+    pub snapshots_count: u64,
+    /// Size of all snapshots when restored (`restore-size` mode)
+    pub restore_size: u64,
+    /// Files in all snapshots
+    pub file_count: u64,
+    /// Stored data, deduplicated and compressed (`raw-data` mode)
+    pub raw_size: u64,
+    /// Deduplicated data before compression (repository format 2 only)
+    pub raw_uncompressed_size: Option<u64>,
+}
+
+impl RepositoryStats {
+    /// Restore size per stored byte (deduplication and compression together)
+    pub fn dedup_ratio(&self) -> Option<f64> {
+        (self.raw_size > 0).then(|| self.restore_size as f64 / self.raw_size as f64)
+    }
+}
+
+/// `restic stats --json --mode raw-data` result
+#[derive(Debug, Deserialize)]
+struct RawDataStats {
+    #[serde(default)]
+    total_size: u64,
+    #[serde(default)]
+    total_uncompressed_size: Option<u64>,
+}
+
+/// Combine the JSON output of `restic stats` in restore-size and raw-data mode
+pub fn parse_repository_stats(restore_size_json: &str, raw_data_json: &str) -> Result<RepositoryStats> {
+    let restore = parse_repo_stats(restore_size_json).context("Failed to parse restore-size stats")?;
+    let raw: RawDataStats = serde_json::from_str(raw_data_json.trim()).context("Failed to parse raw-data stats JSON")?;
+
+    Ok(RepositoryStats {
+        snapshots_count: restore.snapshots_count,
+        restore_size: restore.total_size,
+        file_count: restore.total_file_count,
+        raw_size: raw.total_size,
+        raw_uncompressed_size: raw.total_uncompressed_size.filter(|size| *size > 0),
+    })
+}
+
+/// Restore size, stored size and snapshot count of a repository
+pub fn repository_stats(env: &ResticEnv, timeout: Duration) -> Result<RepositoryStats> {
+    parse_repository_stats(
+        &stats_json(env, "restore-size", &[], timeout)?,
+        &stats_json(env, "raw-data", &[], timeout)?,
+    )
+}
+
+/// How much pack data `restic check` reads back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadData {
+    /// Repository structure only
+    None,
+    /// Every pack (`--read-data`)
+    All,
+    /// Part of the packs (`--read-data-subset`): "5%", "1/7" or a size like "500M"
+    Subset(String),
+}
+
+impl ReadData {
+    pub fn from_flags(read_data: bool, subset: Option<&str>) -> Self {
+        match subset {
+            Some(subset) => ReadData::Subset(subset.to_string()),
+            None if read_data => ReadData::All,
+            None => ReadData::None,
+        }
+    }
+
+    pub fn reads_data(&self) -> bool {
+        *self != ReadData::None
+    }
+}
+
+/// Whether a value is a `--read-data-subset` restic accepts: "n/t" with
+/// 1 <= n <= t, a percentage up to 100%, or a size with an optional K/M/G/T unit
+pub fn is_valid_read_data_subset(subset: &str) -> bool {
+    let subset = subset.trim();
+    if let Some((n, t)) = subset.split_once('/') {
+        return matches!((n.parse::<u32>(), t.parse::<u32>()), (Ok(n), Ok(t)) if n >= 1 && n <= t);
+    }
+    if let Some(percent) = subset.strip_suffix('%') {
+        return percent.parse::<f64>().is_ok_and(|p| p > 0.0 && p <= 100.0);
+    }
+    let digits = subset.trim_end_matches(['K', 'M', 'G', 'T', 'k', 'm', 'g', 't']);
+    subset.len() - digits.len() <= 1 && digits.parse::<u64>().is_ok_and(|size| size > 0)
+}
+
+/// Check repository integrity
+///
+/// Returns an error only when restic couldn't run; problems in the
+/// repository are `CheckResult::Errors`.
+pub fn check_repository(env: &ResticEnv, read_data: bool, timeout: Duration) -> Result<CheckResult> {
+    let read_data = if read_data { ReadData::All } else { ReadData::None };
+    check_repository_with(env, &read_data, timeout)
+}
+
+/// Check repository integrity, reading back all or part of the pack data
+pub fn check_repository_with(env: &ResticEnv, read_data: &ReadData, timeout: Duration) -> Result<CheckResult> {
+    info!("Checking repository integrity...");
+
+    // Reading pack data back from cold storage is slow and billed
+    let cold_storage = env.is_cold_storage();
+    let read_data = if read_data.reads_data() && cold_storage {
+        warn!("Cold storage repository: checking metadata only, pack data is not read");
+        &ReadData::None
+    } else {
+        read_data
+    };
+
+    match read_data {
+        ReadData::None => {}
+        ReadData::All => info!("Deep verification enabled (this may take a while)"),
+        ReadData::Subset(subset) => info!("Reading a subset of the pack data ({})", subset),
+    }
+
+    // check needs a lock, so retry after clearing stale ones instead of --no-lock
+    let mut cmd = ResticCommandBuilder::new(env).arg("check");
+    match read_data {
+        ReadData::None => {}
+        ReadData::All => cmd = cmd.arg("--read-data"),
+        ReadData::Subset(subset) => cmd = cmd.arg(format!("--read-data-subset={}", subset)),
+    }
+    if cold_storage {
+        // Tree blobs come from the local cache instead of the backend
+        cmd = cmd.arg("--with-cache");
+    }
+
+    let output = cmd.run_with_lock_retry(timeout, "Failed to execute restic check")?;
 
     // Combine stdout and stderr for complete output
-    let full_output = format!("{}{}", stdout, stderr);
+    let full_output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let result = CheckResult::from_output(output.status.success(), &full_output);
+    match result {
+        CheckResult::Ok => info!("Repository check completed successfully"),
+        CheckResult::Warnings(ref warnings) => info!("Repository check passed with {} warning(s)", warnings.len()),
+        CheckResult::Errors(ref errors) => warn!("Repository check found {} problem(s)", errors.len()),
+    }
+    Ok(result)
+}
 
-    info!("Repository check completed successfully");
-    Ok(full_output)
+/// Kind of problem reported by `restic check`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CheckCategory {
+    /// Index entries that are missing, duplicated or unreadable
+    Index,
+    /// Pack files that are missing, unreadable or don't match their hash
+    Pack,
+    /// Trees (directories) referencing data that can't be found
+    Tree,
+    /// Data no snapshot references any more (not an error)
+    Unused,
+    /// Anything not recognized
+    Other,
+}
+
+impl CheckCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckCategory::Index => "index errors",
+            CheckCategory::Pack => "pack errors",
+            CheckCategory::Tree => "tree errors",
+            CheckCategory::Unused => "unused data",
+            CheckCategory::Other => "other errors",
+        }
+    }
+
+    /// Suggested remediation
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            CheckCategory::Index => "rebuild the index: restic-manager verify --repair-index",
+            CheckCategory::Pack => {
+                "run verify --read-data to find every damaged pack, then `restic repair packs <ids>` and `restic repair snapshots --forget`"
+            }
+            CheckCategory::Tree => {
+                "rebuild the index (verify --repair-index); if errors remain, `restic repair snapshots --forget` removes the broken parts"
+            }
+            CheckCategory::Unused => "not critical: restic-manager maintain (prune) removes it",
+            CheckCategory::Other => "review the check output",
+        }
+    }
+
+    /// Whether this is an actual error (unused data is only a hint)
+    pub fn is_error(&self) -> bool {
+        *self != CheckCategory::Unused
+    }
+}
+
+/// One problem line from `restic check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckIssue {
+    pub category: CheckCategory,
+    pub message: String,
+}
+
+/// Outcome of `restic check`
+///
+/// The exit status decides between passing and failing; the output only
+/// provides the details, so a path that happens to contain "error" can't
+/// fail a check.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CheckResult {
+    /// No problems
+    #[default]
+    Ok,
+    /// The check passed with hints, e.g. unused data
+    Warnings(Vec<CheckIssue>),
+    /// The repository has errors
+    Errors(Vec<CheckIssue>),
+}
+
+impl CheckResult {
+    /// Classify the output of `restic check` and whether it exited successfully
+    ///
+    /// A failed check whose output matches no known problem still gets an
+    /// issue, so it is never reported without a reason.
+    pub fn from_output(success: bool, output: &str) -> Self {
+        let mut issues = parse_check_issues(output);
+        if success {
+            return if issues.is_empty() { CheckResult::Ok } else { CheckResult::Warnings(issues) };
+        }
+
+        if !issues.iter().any(|i| i.category.is_error()) {
+            let message = output
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or("check failed")
+                .to_string();
+            issues.push(CheckIssue { category: CheckCategory::Other, message });
+        }
+        CheckResult::Errors(issues)
+    }
+
+    /// A check that couldn't run (restic missing, timeout, ...)
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        Self::from_output(false, &format!("{:#}", error))
+    }
+
+    /// Whether the repository passed (possibly with warnings)
+    pub fn passed(&self) -> bool {
+        !matches!(self, CheckResult::Errors(_))
+    }
+
+    pub fn issues(&self) -> &[CheckIssue] {
+        match self {
+            CheckResult::Ok => &[],
+            CheckResult::Warnings(issues) | CheckResult::Errors(issues) => issues,
+        }
+    }
+
+    /// Issues grouped by category
+    pub fn by_category(&self) -> BTreeMap<CheckCategory, Vec<&CheckIssue>> {
+        let mut groups: BTreeMap<CheckCategory, Vec<&CheckIssue>> = BTreeMap::new();
+        for issue in self.issues() {
+            groups.entry(issue.category).or_default().push(issue);
+        }
+        groups
+    }
+
+    /// Whether rebuilding the index may fix this repository
+    pub fn needs_index_repair(&self) -> bool {
+        !self.passed()
+            && self
+                .issues()
+                .iter()
+                .any(|i| matches!(i.category, CheckCategory::Index | CheckCategory::Tree))
+    }
+
+    /// Turn errors into an `Err` listing them, for callers that only need pass/fail
+    pub fn ensure_passed(self) -> Result<Self> {
+        match self {
+            CheckResult::Errors(ref issues) => {
+                let lines: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+                anyhow::bail!("Repository check found errors:\n{}", lines.join("\n"))
+            }
+            result => Ok(result),
+        }
+    }
+}
+
+/// Categorize the problems in `restic check` output
+///
+/// Only restic's problem lines count: `error...` and `Fatal: ...` lines,
+/// `pack <id>: ...` reports and the indented lines continuing them, plus the
+/// unused-data hints. Progress lines and anything else (such as file paths)
+/// are skipped, whatever words they contain.
+pub fn parse_check_issues(output: &str) -> Vec<CheckIssue> {
+    let mut issues: Vec<CheckIssue> = Vec::new();
+    let mut previous: Option<CheckCategory> = None;
+
+    for raw_line in output.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let continued = raw_line.starts_with([' ', '\t']).then_some(previous).flatten();
+        let category = continued.or_else(|| check_line_category(line));
+        previous = category;
+
+        if let Some(category) = category {
+            issues.push(CheckIssue { category, message: line.to_string() });
+        }
+    }
+
+    issues
+}
+
+fn check_line_category(line: &str) -> Option<CheckCategory> {
+    let lower = line.to_lowercase();
+
+    // Hints restic prints even when the check passes
+    let is_pack_report = lower.starts_with("pack ") && lower.contains(':');
+    if (is_pack_report && lower.contains("not referenced in any index"))
+        || lower.contains("additional files were found in the repo")
+        || (lower.starts_with("unused blobs") || lower.contains(" unused blobs"))
+    {
+        return Some(CheckCategory::Unused);
+    }
+
+    // Progress and status lines ("load indexes", "check all packs") name
+    // packs and indexes too, so only restic's problem lines are classified
+    let is_problem = lower.starts_with("error") || lower.starts_with("fatal:") || is_pack_report;
+    if !is_problem {
+        return None;
+    }
+    if lower.contains("repository contains errors") || lower.starts_with("fatal: repository check failed") {
+        return None;
+    }
+
+    Some(if lower.contains("index") {
+        CheckCategory::Index
+    } else if lower.contains("tree") {
+        CheckCategory::Tree
+    } else if lower.contains("pack") || lower.contains("blob") || lower.contains("ciphertext") {
+        CheckCategory::Pack
+    } else {
+        CheckCategory::Other
+    })
+}
+
+/// Rebuild the repository index (`restic repair index`)
+pub fn repair_index(env: &ResticEnv, timeout: Duration) -> Result<String> {
+    info!("Rebuilding repository index...");
+
+    let output = ResticCommandBuilder::new(env)
+        .args(["repair", "index"])
+        .run_with_lock_retry(timeout, "Failed to execute restic repair index")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Index repair", &stderr).into());
+    }
+
+    info!("Repository index rebuilt");
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
 
 /// Get the latest snapshot for a repository
@@ -387,47 +1831,185 @@ pub fn count_snapshots(env: &ResticEnv, timeout: Duration) -> Result<usize> {
     Ok(snapshots.len())
 }
 
+/// Resource limits for a restore, so it doesn't saturate the network or disk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreLimits {
+    /// Download bandwidth limit in KiB/s (`--limit-download`), replacing
+    /// the environment's limit
+    pub limit_download_kbps: Option<u32>,
+    /// Run restic at the lowest CPU and I/O priority
+    pub nice: bool,
+}
+
+/// Programs to prefix a command with to run it at the lowest CPU priority
+/// and, where `ionice` exists, idle I/O priority
+pub fn low_priority_prefix(has_nice: bool, has_ionice: bool) -> Vec<&'static str> {
+    let mut prefix = Vec::new();
+    if has_nice {
+        prefix.extend(["nice", "-n", "19"]);
+    }
+    if has_ionice {
+        prefix.extend(["ionice", "-c", "3"]);
+    }
+    prefix
+}
+
+/// A restic command at the lowest CPU and I/O priority
+fn low_priority_command() -> Command {
+    let restic_bin = get_restic_binary();
+    let prefix = if cfg!(unix) {
+        low_priority_prefix(which::which("nice").is_ok(), which::which("ionice").is_ok())
+    } else {
+        Vec::new()
+    };
+    match prefix.split_first() {
+        Some((program, args)) => {
+            let mut cmd = Command::new(program);
+            cmd.args(args).arg(&restic_bin);
+            cmd
+        }
+        None => {
+            warn!("Can't lower the priority of restic on this system, running it normally");
+            Command::new(&restic_bin)
+        }
+    }
+}
+
 /// Restore from a snapshot
 pub fn restore_snapshot(
     env: &ResticEnv,
     snapshot_id: &str,
     target_dir: Option<&str>,
     include_paths: &[String],
+    limits: &RestoreLimits,
     timeout: Duration,
 ) -> Result<()> {
+    let stdout = restore_files(env, snapshot_id, target_dir, include_paths, limits, timeout)?;
+    info!("Restore completed successfully");
+    println!("{}", stdout);
+
+    Ok(())
+}
+
+/// Run `restic restore` and return its output
+pub fn restore_files(
+    env: &ResticEnv,
+    snapshot_id: &str,
+    target_dir: Option<&str>,
+    include_paths: &[String],
+    limits: &RestoreLimits,
+    timeout: Duration,
+) -> Result<String> {
     info!("Restoring from snapshot: {}", snapshot_id);
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("restore")
-        .arg(snapshot_id);
+    // An explicit download limit (restore --limit-download) replaces the configured one
+    let download_kbps = limits.limit_download_kbps.or(env.limit_download_kbps);
 
-    // Add target directory if specified
-    if let Some(target) = target_dir {
-        cmd.arg("--target").arg(target);
-    }
+    // restic always needs a target; snapshot paths are absolute, so
+    // restoring into / puts files back at their original locations
+    let output = ResticCommandBuilder::new(env)
+        .args(["restore", snapshot_id, "--target", target_dir.unwrap_or("/")])
+        .args(include_paths.iter().flat_map(|path| ["--include", path.as_str()]))
+        .args(bandwidth_args(env.limit_upload_kbps, download_kbps))
+        .nice(limits.nice)
+        .run(timeout, "Failed to execute restic restore")?;
 
-    // Add specific paths to restore if specified
-    for path in include_paths {
-        cmd.arg("--include").arg(path);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Restore", &stderr).into());
     }
 
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
-    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// What a restore would do to its target, from `restic restore --dry-run`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestorePreview {
+    /// Files in the snapshot (after `--include` filters)
+    pub total_files: u64,
+    pub total_bytes: u64,
+    /// Files that don't exist at the target yet
+    pub new_files: Vec<String>,
+    /// Existing files at the target that would be replaced
+    pub overwritten_files: Vec<String>,
+    /// Existing files that already match the snapshot
+    pub unchanged_files: u64,
+    /// Bytes that would be written
+    pub bytes_to_write: u64,
+}
+
+/// Preview a restore without writing anything (`restic restore --dry-run`)
+pub fn preview_restore(
+    env: &ResticEnv,
+    snapshot_id: &str,
+    target_dir: Option<&str>,
+    include_paths: &[String],
+    timeout: Duration,
+) -> Result<RestorePreview> {
+    info!("Previewing restore of snapshot: {}", snapshot_id);
 
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic restore")?;
+    let output = ResticCommandBuilder::new(env)
+        .args(["restore", snapshot_id, "--target", target_dir.unwrap_or("/"), "--dry-run", "-vv"])
+        .args(include_paths.iter().flat_map(|path| ["--include", path.as_str()]))
+        .json()
+        .run(timeout, "Failed to execute restic restore --dry-run")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Restore failed: {}", stderr);
+        return Err(BackupError::restic_failed("Restore preview", &stderr).into());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    info!("Restore completed successfully");
-    println!("{}", stdout);
+    Ok(parse_restore_preview(&String::from_utf8_lossy(&output.stdout)))
+}
 
-    Ok(())
+/// Summarize `restic restore --dry-run --json -vv` output
+///
+/// Every item gets a `verbose_status` line whose action is `restored`
+/// (new), `updated` (overwritten) or `unchanged`; the final `summary` line
+/// has the totals.
+pub fn parse_restore_preview(output: &str) -> RestorePreview {
+    let mut preview = RestorePreview::default();
+
+    for msg in output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+    {
+        match msg["message_type"].as_str() {
+            Some("verbose_status") => {
+                let item = msg["item"].as_str().unwrap_or_default().to_string();
+                match msg["action"].as_str() {
+                    Some("restored") => preview.new_files.push(item),
+                    Some("updated") => preview.overwritten_files.push(item),
+                    Some("unchanged") => preview.unchanged_files += 1,
+                    _ => {}
+                }
+            }
+            Some("summary") => {
+                preview.total_files = msg["total_files"].as_u64().unwrap_or(0);
+                preview.total_bytes = msg["total_bytes"].as_u64().unwrap_or(0);
+                preview.bytes_to_write = msg["bytes_restored"].as_u64().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    preview
+}
+
+/// Tag on the snapshot taken of the current files before an in-place restore
+pub const PRE_RESTORE_TAG: &str = "pre-restore";
+
+/// Existing paths an in-place restore would overwrite
+///
+/// With `--include` filters only those paths are restored, otherwise
+/// everything the snapshot contains.
+pub fn pre_restore_paths(snapshot_paths: &[String], include_paths: &[String]) -> Vec<PathBuf> {
+    let candidates = if include_paths.is_empty() { snapshot_paths } else { include_paths };
+    candidates
+        .iter()
+        .map(PathBuf::from)
+        .filter(|path| path.exists())
+        .collect()
 }
 
 /// List files in a snapshot
@@ -438,21 +2020,13 @@ pub fn list_snapshot_files(
 ) -> Result<Vec<String>> {
     info!("Listing files in snapshot: {}", snapshot_id);
 
-    let restic_bin = get_restic_binary();
-    let mut cmd = std::process::Command::new(&restic_bin);
-    cmd.arg("ls")
-        .arg(snapshot_id)
-        .arg("--long");
-
-    for (key, value) in env.vars() {
-        cmd.env(key, value);
-    }
-
-    let output = execute_with_timeout(cmd, timeout, "Failed to execute restic ls")?;
+    let output = ResticCommandBuilder::new(env)
+        .args(["ls", snapshot_id, "--long", "--no-lock"])
+        .run(timeout, "Failed to execute restic ls")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to list files: {}", stderr);
+        return Err(BackupError::restic_failed("Listing files", &stderr).into());
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -461,18 +2035,271 @@ pub fn list_snapshot_files(
     Ok(files)
 }
 
+/// A regular file in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// List the regular files in a snapshot (`restic ls --json`)
+pub fn list_snapshot_entries(
+    env: &ResticEnv,
+    snapshot_id: &str,
+    timeout: Duration,
+) -> Result<Vec<SnapshotFile>> {
+    let output = ResticCommandBuilder::new(env)
+        .args(["ls", snapshot_id, "--no-lock"])
+        .json()
+        .run(timeout, "Failed to execute restic ls")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::restic_failed("Listing files", &stderr).into());
+    }
+
+    Ok(parse_ls_files(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The archive of a Docker volume among a snapshot's files
+///
+/// Volumes are backed up as `<volume>.tar.gz`, `<volume>.tar.zst` or (when
+/// uncompressed or streamed) `<volume>.tar`.
+pub fn find_volume_archive<'a>(files: &'a [SnapshotFile], volume: &str) -> Option<&'a SnapshotFile> {
+    let names = [ArchiveCompression::Gzip, ArchiveCompression::Zstd, ArchiveCompression::None]
+        .map(|compression| format!("{}.{}", volume, compression.extension()));
+    files.iter().find(|file| {
+        let name = file.path.rsplit('/').next().unwrap_or(&file.path);
+        names.iter().any(|n| n == name)
+    })
+}
+
+/// Regular files in `restic ls --json` output
+///
+/// The first line describes the snapshot, every further line is one node.
+pub fn parse_ls_files(output: &str) -> Vec<SnapshotFile> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+        .filter(|node| node["type"] == "file")
+        .filter_map(|node| {
+            Some(SnapshotFile {
+                path: node["path"].as_str()?.to_string(),
+                size: node["size"].as_u64().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_parse_repo_stats() {
+        let stats = parse_repo_stats("{\"total_size\":2684354560,\"total_file_count\":1200,\"snapshots_count\":14}\n").unwrap();
+        assert_eq!(stats, RepoStats { total_size: 2684354560, total_file_count: 1200, snapshots_count: 14 });
+
+        // The human-readable output of older callers is rejected, not misread
+        assert!(parse_repo_stats("Stats in restore-size mode:\n  Total Size: 2.500 GiB").is_err());
+    }
+
+    #[test]
+    fn test_parse_repository_stats() {
+        let stats = parse_repository_stats(
+            r#"{"total_size":10737418240,"total_file_count":52000,"snapshots_count":30}"#,
+            r#"{"total_size":1073741824,"total_uncompressed_size":2147483648,"compression_ratio":2,"total_blob_count":9000,"snapshots_count":30}"#,
+        )
+        .unwrap();
+        assert_eq!(stats.snapshots_count, 30);
+        assert_eq!(stats.restore_size, 10 * 1024 * 1024 * 1024);
+        assert_eq!(stats.file_count, 52000);
+        assert_eq!(stats.raw_uncompressed_size, Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(stats.dedup_ratio(), Some(10.0));
+
+        // Repository format 1 has no compression figures; empty repositories no ratio
+        let stats = parse_repository_stats(
+            r#"{"total_size":0,"snapshots_count":0}"#,
+            r#"{"total_size":0,"total_blob_count":0,"snapshots_count":0}"#,
+        )
+        .unwrap();
+        assert_eq!(stats.raw_uncompressed_size, None);
+        assert_eq!(stats.dedup_ratio(), None);
+
+        assert!(parse_repository_stats("Total Size: 10 GiB", "{}").is_err());
+    }
     use tempfile::TempDir;
     use std::fs;
 
+    #[test]
+    fn test_parse_restore_preview() {
+        let output = concat!(
+            "{\"message_type\":\"verbose_status\",\"action\":\"restored\",\"item\":\"/data/new.txt\",\"size\":10}\n",
+            "{\"message_type\":\"verbose_status\",\"action\":\"updated\",\"item\":\"/data/db.sqlite\",\"size\":200}\n",
+            "{\"message_type\":\"verbose_status\",\"action\":\"unchanged\",\"item\":\"/data/same.txt\",\"size\":5}\n",
+            "{\"message_type\":\"summary\",\"total_files\":3,\"files_restored\":2,\"files_skipped\":1,",
+            "\"total_bytes\":215,\"bytes_restored\":210,\"bytes_skipped\":5}\n",
+        );
+
+        let preview = parse_restore_preview(output);
+        assert_eq!(preview.new_files, vec!["/data/new.txt"]);
+        assert_eq!(preview.overwritten_files, vec!["/data/db.sqlite"]);
+        assert_eq!(preview.unchanged_files, 1);
+        assert_eq!(preview.total_files, 3);
+        assert_eq!(preview.total_bytes, 215);
+        assert_eq!(preview.bytes_to_write, 210);
+
+        assert_eq!(parse_restore_preview("not json"), RestorePreview::default());
+    }
+
+    #[test]
+    fn test_parse_forget_removals() {
+        let output = r#"[
+            {"tags": null, "host": "nas", "paths": ["/data"], "keep": [{"id": "a"}], "remove": [{"id": "b"}, {"id": "c"}]},
+            {"tags": null, "host": "nas", "paths": ["/other"], "keep": [{"id": "d"}], "remove": null}
+        ]"#;
+        assert_eq!(parse_forget_removals(output), 2);
+        assert_eq!(parse_forget_removals("not json"), 0);
+    }
+
+    #[test]
+    fn test_read_data_subset() {
+        for valid in ["5%", "2.5%", "100%", "1/7", "7/7", "500M", "2G", "1024"] {
+            assert!(is_valid_read_data_subset(valid), "{}", valid);
+        }
+        for invalid in ["0%", "150%", "0/7", "8/7", "M", "500MB", "-1G", ""] {
+            assert!(!is_valid_read_data_subset(invalid), "{}", invalid);
+        }
+        assert_eq!(ReadData::from_flags(true, Some("1/7")), ReadData::Subset("1/7".to_string()));
+        assert_eq!(ReadData::from_flags(true, None), ReadData::All);
+        assert!(!ReadData::from_flags(false, None).reads_data());
+    }
+
+    #[test]
+    fn test_parse_check_issues() {
+        let output = "using temporary cache in /tmp/restic-check-cache-1234
+create exclusive lock for repository
+load indexes
+check all packs
+pack 5d4e21ef: not referenced in any index
+1 additional files were found in the repo, which likely contain duplicate data.
+error: pack 3a5e02b1 contained in several indexes
+check snapshots, trees and blobs
+error for tree 8c0f1a2b:
+  id 8c0f1a2b not found in repository
+[0:01] 100.00%  3 / 3 snapshots
+read all data
+pack 9b7c3d4e: Pack ID does not match, want 9b7c3d4e, got 1f2e3d4c
+Fatal: wrong password or no key found
+Fatal: repository contains errors";
+
+        let issues = parse_check_issues(output);
+        let categories: Vec<CheckCategory> = issues.iter().map(|i| i.category).collect();
+        assert_eq!(
+            categories,
+            vec![
+                CheckCategory::Unused,
+                CheckCategory::Unused,
+                CheckCategory::Index,
+                CheckCategory::Tree,
+                CheckCategory::Tree,
+                CheckCategory::Pack,
+                CheckCategory::Other,
+            ]
+        );
+        assert_eq!(issues[3].message, "error for tree 8c0f1a2b:");
+        assert!(!CheckCategory::Unused.is_error());
+        assert!(CheckCategory::Index.remediation().contains("--repair-index"));
+
+        assert!(parse_check_issues("load indexes\ncheck all packs\nno errors were found").is_empty());
+        // Problem words outside restic's problem lines don't count
+        assert!(parse_check_issues("using temporary cache in /tmp/error-missing/restic-check-cache-1").is_empty());
+    }
+
+    #[test]
+    fn test_parse_ls_files() {
+        let output = r#"{"time":"2025-01-15T02:00:13Z","paths":["/data"],"id":"abc","short_id":"abc","struct_type":"snapshot"}
+{"name":"data","type":"dir","path":"/data","struct_type":"node"}
+{"name":"a.txt","type":"file","path":"/data/a.txt","size":12,"struct_type":"node"}
+{"name":"link","type":"symlink","path":"/data/link","struct_type":"node"}
+{"name":"b.db","type":"file","path":"/data/b.db","size":4096,"struct_type":"node"}"#;
+
+        assert_eq!(
+            parse_ls_files(output),
+            vec![
+                SnapshotFile { path: "/data/a.txt".to_string(), size: 12 },
+                SnapshotFile { path: "/data/b.db".to_string(), size: 4096 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retention_args() {
+        let retention = RetentionPolicy { daily: 7, weekly: 4, monthly: 6, yearly: 1, ..Default::default() };
+        assert_eq!(
+            retention_args(&retention).join(" "),
+            "--keep-daily 7 --keep-weekly 4 --keep-monthly 6 --keep-yearly 1"
+        );
+
+        let retention = RetentionPolicy {
+            daily: 7,
+            hourly: 24,
+            last: 3,
+            within: Some("2d".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            retention_args(&retention).join(" "),
+            "--keep-daily 7 --keep-weekly 0 --keep-monthly 0 --keep-yearly 0 --keep-hourly 24 --keep-last 3 --keep-within 2d"
+        );
+    }
+
+    #[test]
+    fn test_tag_retention_arg_sets() {
+        let mut retention = RetentionPolicy { daily: 7, weekly: 4, monthly: 0, yearly: 0, ..Default::default() };
+        retention.tags.insert(
+            "pre-upgrade".to_string(),
+            TagRetention { forever: true, ..Default::default() },
+        );
+        retention.tags.insert(
+            "manual".to_string(),
+            TagRetention { within: Some("90d".to_string()), last: Some(3), ..Default::default() },
+        );
+
+        let sets: Vec<String> = forget_arg_sets(&retention).iter().map(|args| args.join(" ")).collect();
+        assert_eq!(
+            sets,
+            vec![
+                "--tag manual --keep-within 90d --keep-last 3".to_string(),
+                "--keep-daily 7 --keep-weekly 4 --keep-monthly 0 --keep-yearly 0 \
+                 --keep-tag manual --keep-tag pre-upgrade"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pre_restore_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let existing = temp_dir.path().join("data");
+        fs::create_dir(&existing).unwrap();
+        let existing = existing.to_string_lossy().to_string();
+        let missing = temp_dir.path().join("gone").to_string_lossy().to_string();
+
+        let snapshot_paths = vec![existing.clone(), missing.clone()];
+        assert_eq!(pre_restore_paths(&snapshot_paths, &[]), vec![PathBuf::from(&existing)]);
+
+        // --include filters replace the snapshot paths
+        assert!(pre_restore_paths(&snapshot_paths, &[missing]).is_empty());
+    }
+
     #[test]
     fn test_build_repository_url_with_trailing_slash() {
         let destination = Destination {
             dest_type: crate::config::DestinationType::Sftp,
             url: "sftp://user@host/backups/".to_string(),
             description: "Test destination".to_string(),
+            ..Default::default()
         };
 
         let url = build_repository_url(&destination, "postgres", None);
@@ -485,6 +2312,7 @@ mod tests {
             dest_type: crate::config::DestinationType::Sftp,
             url: "sftp://user@host/backups".to_string(),
             description: "Test destination".to_string(),
+            ..Default::default()
         };
 
         let url = build_repository_url(&destination, "postgres", None);
@@ -497,6 +2325,7 @@ mod tests {
             dest_type: crate::config::DestinationType::Local,
             url: "/tmp/backups".to_string(),
             description: "Test destination".to_string(),
+            ..Default::default()
         };
 
         let url = build_repository_url(&destination, "postgres", Some("-prod"));
@@ -509,6 +2338,7 @@ mod tests {
             dest_type: crate::config::DestinationType::Local,
             url: "/tmp/backups///".to_string(),
             description: "Test destination".to_string(),
+            ..Default::default()
         };
 
         let url = build_repository_url(&destination, "postgres", None);
@@ -559,6 +2389,452 @@ mod tests {
         assert_eq!(env.vars().get("RESTIC_REPOSITORY").unwrap(), "/tmp/new-repo");
     }
 
+    #[test]
+    fn test_restic_env_for_destination_includes_destination_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let password_file = temp_dir.path().join("password.txt");
+        fs::write(&password_file, "test-password").unwrap();
+
+        let mut destination = Destination {
+            dest_type: crate::config::DestinationType::Azure,
+            url: "azure:backups:/".to_string(),
+            ..Default::default()
+        };
+        destination.env.insert("AZURE_ACCOUNT_NAME".to_string(), "account".to_string());
+        destination.env.insert("AZURE_ACCOUNT_KEY".to_string(), "secret".to_string());
+
+        let global = GlobalConfig {
+            restic_password_file: password_file,
+            ..Default::default()
+        };
+
+        let env = ResticEnv::for_destination(&global, "azure", &destination, "azure:backups:/postgres").unwrap();
+
+        assert_eq!(env.vars().len(), 4);
+        assert_eq!(env.vars().get("AZURE_ACCOUNT_NAME").unwrap(), "account");
+        assert_eq!(env.vars().get("RESTIC_REPOSITORY").unwrap(), "azure:backups:/postgres");
+    }
+
+    #[test]
+    fn test_parse_backup_summary_dry_run() {
+        let output = r#"{"message_type":"status","percent_done":0.5,"total_files":10}
+{"message_type":"verbose_status","action":"new","item":"/data/a.txt"}
+{"message_type":"summary","files_new":3,"files_changed":1,"files_unmodified":6,"data_added":2048,"total_files_processed":10,"total_bytes_processed":8192,"dry_run":true}
+"#;
+
+        let summary = parse_backup_summary(output).unwrap();
+        assert_eq!(summary.files_new, 3);
+        assert_eq!(summary.files_changed, 1);
+        assert_eq!(summary.data_added, 2048);
+        assert_eq!(summary.total_bytes_processed, 8192);
+        assert!(summary.snapshot_id.is_none());
+    }
+
+    #[test]
+    fn test_parse_dry_run_items() {
+        let output = r#"{"message_type":"verbose_status","action":"new","item":"/data/a.txt","duration":0.01,"data_size":1024,"metadata_size":0,"total_files":1}
+{"message_type":"verbose_status","action":"unchanged","item":"/data/b.txt","duration":0,"data_size":0,"metadata_size":0,"total_files":1}
+{"message_type":"verbose_status","action":"modified","item":"/data/c.txt","duration":0.02,"data_size":2048,"metadata_size":0,"total_files":1}
+{"message_type":"summary","files_new":1,"files_changed":1,"files_unmodified":1,"data_added":3072,"dry_run":true}
+"#;
+
+        let items = parse_dry_run_items(output);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0], DryRunItem { action: "new".to_string(), path: "/data/a.txt".to_string(), size: 1024 });
+        assert_eq!(items[1].action, "modified");
+        assert_eq!(items[1].size, 2048);
+    }
+
+    #[test]
+    fn test_parse_backup_summary_missing() {
+        let output = "{\"message_type\":\"status\",\"percent_done\":1.0}\nnot json\n";
+        assert!(parse_backup_summary(output).is_none());
+    }
+
+    fn joined(args: Vec<OsString>) -> String {
+        args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_command_backup_args() {
+        let command = vec!["docker".to_string(), "run".to_string(), "alpine".to_string()];
+        let env = ResticEnv::new(Path::new("/tmp/password"), "/tmp/repo");
+        assert_eq!(
+            joined(command_backup(&env, "data.tar", &["a,b".to_string()], &command).build_args()),
+            "backup --stdin-from-command --stdin-filename data.tar --tag a;b --json -- docker run alpine"
+        );
+
+        // Restic options must come before the command
+        let env = env.with_host(Some("nas"));
+        assert_eq!(
+            joined(command_backup(&env, "data.tar", &[], &command).build_args()),
+            "backup --stdin-from-command --stdin-filename data.tar --json --host nas -- docker run alpine"
+        );
+    }
+
+    #[test]
+    fn test_restic_command_builder() {
+        let mut env = ResticEnv::new(Path::new("/tmp/password"), "/tmp/repo").with_host(Some("nas"));
+        env.limit_upload_kbps = Some(1000);
+        let builder = ResticCommandBuilder::new(&env).args(["forget", "--keep-daily", "7"]).json().host().limits();
+        assert_eq!(
+            joined(builder.build_args()),
+            "forget --keep-daily 7 --json --host nas --limit-upload 1000"
+        );
+
+        // Every command gets the repository environment
+        let cmd = builder.build();
+        assert!(cmd
+            .get_envs()
+            .any(|(key, value)| key == "RESTIC_REPOSITORY" && value == Some(OsStr::new("/tmp/repo"))));
+        assert_eq!(joined(ResticCommandBuilder::new(&env).arg("unlock").build_args()), "unlock");
+    }
+
+    #[test]
+    fn test_restic_env_host() {
+        let env = ResticEnv::new(Path::new("/tmp/password"), "/tmp/repo");
+        assert!(env.host_args().is_empty());
+        assert_eq!(env.with_host(Some("nas")).host_args(), vec!["--host", "nas"]);
+    }
+
+    #[test]
+    fn test_restic_env_storage_and_cache() {
+        let destination = Destination {
+            dest_type: crate::config::DestinationType::Sftp,
+            url: "sftp:coldbox:/backups".to_string(),
+            compression: Some(RepositoryCompression::Max),
+            pack_size_mb: Some(64),
+            ..Default::default()
+        };
+        let global = GlobalConfig { restic_cache_dir: Some(PathBuf::from("/srv/cache/restic")), ..Default::default() };
+        let env = ResticEnv::for_destination(&global, "coldbox", &destination, "sftp:coldbox:/backups/svc").unwrap();
+        assert_eq!(env.storage_args(), vec!["--compression", "max", "--pack-size", "64"]);
+        assert_eq!(env.vars().get("RESTIC_CACHE_DIR").unwrap(), "/srv/cache/restic");
+    }
+
+    #[test]
+    fn test_restic_env_limits() {
+        let global = GlobalConfig { limit_upload_kbps: Some(2000), limit_download_kbps: Some(8000), ..Default::default() };
+        let destination = Destination {
+            dest_type: crate::config::DestinationType::Sftp,
+            url: "sftp:offsite:/backups".to_string(),
+            limit_upload_kbps: Some(500),
+            ..Default::default()
+        };
+        let env = ResticEnv::for_destination(&global, "offsite", &destination, "sftp:offsite:/backups/svc").unwrap();
+        assert_eq!(env.limit_args(), vec!["--limit-upload", "500", "--limit-download", "8000"]);
+
+        // A profile's limits only apply where they are lower
+        let env = env.with_limits(Some(1000), Some(4000));
+        assert_eq!(env.limit_args(), vec!["--limit-upload", "500", "--limit-download", "4000"]);
+
+        assert!(env.storage_args().is_empty());
+
+        let unlimited = ResticEnv::new(Path::new("/tmp/password"), "/tmp/repo");
+        assert!(unlimited.limit_args().is_empty());
+        assert_eq!(unlimited.with_limits(None, Some(100)).limit_args(), vec!["--limit-download", "100"]);
+    }
+
+    #[test]
+    fn test_parse_key_list() {
+        let json = r#"[
+            {"current":true,"id":"4a2f6e1b","userName":"root","hostName":"nas","created":"2024-05-01 03:00:00"},
+            {"current":false,"id":"9c0d1e2f","userName":"admin","hostName":"laptop","created":"2023-01-10 12:00:00"}
+        ]"#;
+        let keys = parse_key_list(json).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(keys[0].current);
+        assert_eq!(keys[1].id, "9c0d1e2f");
+        assert_eq!(keys[1].user_name, "admin");
+        assert_eq!(keys[1].host_name, "laptop");
+        assert!(parse_key_list("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_restic_version() {
+        assert_eq!(
+            parse_restic_version("restic 0.18.1 compiled with go1.24.1 on linux/amd64"),
+            Some((0, 18, 1))
+        );
+        assert_eq!(parse_restic_version("restic 0.16"), Some((0, 16, 0)));
+        assert_eq!(parse_restic_version("restic (v0.17.0-dev)"), None);
+        assert_eq!(parse_restic_version(""), None);
+    }
+
+    #[test]
+    fn test_find_volume_archive() {
+        let file = |path: &str| SnapshotFile { path: path.to_string(), size: 1 };
+        let files = vec![
+            file("/tmp/restic-manager/app/app_db_backup.tar.gz"),
+            file("/tmp/restic-manager/app/app_db.tar.gz"),
+            file("/app_uploads.tar"),
+        ];
+        assert_eq!(find_volume_archive(&files, "app_db").unwrap().path, "/tmp/restic-manager/app/app_db.tar.gz");
+        assert_eq!(find_volume_archive(&files, "app_uploads").unwrap().path, "/app_uploads.tar");
+        assert!(find_volume_archive(&files, "app").is_none());
+    }
+
+    #[test]
+    fn test_low_priority_prefix() {
+        assert_eq!(low_priority_prefix(true, true), vec!["nice", "-n", "19", "ionice", "-c", "3"]);
+        assert_eq!(low_priority_prefix(true, false), vec!["nice", "-n", "19"]);
+        assert!(low_priority_prefix(false, false).is_empty());
+    }
+
+    #[test]
+    fn test_stdin_backup_args() {
+        assert_eq!(
+            stdin_backup_args("crontab.txt", &["manual".to_string()]).join(" "),
+            "backup --stdin --stdin-filename crontab.txt --tag manual"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_with_input() {
+        let output = execute_with_input(Command::new("cat"), b"hello".to_vec(), Duration::from_secs(5), "cat").unwrap();
+        assert_eq!(output.stdout, b"hello");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_with_timeout_kills_command() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("10");
+        let started = std::time::Instant::now();
+        let err = execute_with_timeout(cmd, Duration::from_millis(200), "sleep").unwrap_err();
+        assert!(matches!(BackupError::of(&err), Some(BackupError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let output = execute_with_timeout(cmd, Duration::from_secs(5), "sh").unwrap();
+        assert_eq!((output.stdout.as_slice(), output.stderr.as_slice()), (&b"out\n"[..], &b"err\n"[..]));
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_backup_summary_merge() {
+        let mut summary = BackupSummary {
+            files_new: 2,
+            data_added: 100,
+            snapshot_id: None,
+            ..Default::default()
+        };
+        summary.merge(&BackupSummary {
+            files_new: 1,
+            data_added: 50,
+            snapshot_id: Some("abc".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(summary.files_new, 3);
+        assert_eq!(summary.data_added, 150);
+        assert_eq!(summary.snapshot_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_backup_args_tags() {
+        let tags = ["webhook".to_string(), "v1.4.2, hotfix".to_string()];
+        let args = backup_args(&[PathBuf::from("/data")], &FilesFrom::default(), &Excludes::default(), &tags);
+        let tail: Vec<&str> = args.iter().skip(3).map(String::as_str).collect();
+        assert_eq!(tail, vec!["--tag", "webhook", "--tag", "v1.4.2; hotfix"]);
+    }
+
+    #[test]
+    fn test_backup_args_excludes() {
+        let excludes = Excludes {
+            patterns: vec!["*.log".to_string()],
+            ipatterns: vec!["thumbs.db".to_string()],
+            files: vec![PathBuf::from("/etc/restic/excludes.txt")],
+            larger_than: Some(2 * 1024 * 1024 * 1024),
+            one_file_system: true,
+        };
+        assert_eq!(
+            backup_args(&[PathBuf::from("/data")], &FilesFrom::default(), &excludes, &[]).join(" "),
+            "backup /data --exclude *.log --iexclude thumbs.db --exclude-file /etc/restic/excludes.txt \
+             --exclude-larger-than 2147483648 --one-file-system --exclude-caches"
+        );
+        assert_eq!(
+            backup_args(&[PathBuf::from("/data")], &FilesFrom::default(), &Excludes::from_patterns(&["*.tmp".to_string()]), &[])
+                .join(" "),
+            "backup /data --exclude *.tmp --exclude-caches"
+        );
+    }
+
+    #[test]
+    fn test_backup_args_files_from() {
+        let files_from = FilesFrom {
+            lists: vec![PathBuf::from("/srv/export.list")],
+            verbatim: vec![PathBuf::from("/tmp/generated.list")],
+        };
+        assert!(!files_from.is_empty());
+        assert_eq!(
+            backup_args(&[], &files_from, &Excludes::default(), &[]).join(" "),
+            "backup --files-from /srv/export.list --files-from-verbatim /tmp/generated.list --exclude-caches"
+        );
+    }
+
+    #[test]
+    fn test_is_read_only_command() {
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(is_read_only_command(&args(&["snapshots", "--json"])));
+        assert!(is_read_only_command(&args(&["--no-lock", "ls", "latest"])));
+        assert!(!is_read_only_command(&args(&["forget", "abc123"])));
+        assert!(!is_read_only_command(&args(&["prune"])));
+        assert!(!is_read_only_command(&args(&["some-future-command"])));
+
+        // Values of global flags aren't the subcommand
+        assert!(!is_read_only_command(&args(&["-r", "/repo", "forget", "abc123"])));
+        assert!(!is_read_only_command(&args(&["--password-file", "snapshots", "prune"])));
+        assert!(is_read_only_command(&args(&["-o", "s3.region=eu", "-vv", "--cache-dir=/tmp/c", "snapshots"])));
+        assert!(!is_read_only_command(&args(&["--unknown-flag", "x", "snapshots"])));
+    }
+
+    #[test]
+    fn test_is_lock_error() {
+        assert!(is_lock_error("unable to create lock in backend: repository is already locked by PID 1234"));
+        assert!(!is_lock_error("Fatal: wrong password or no key found"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.00 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.00 GiB");
+    }
+
+    #[test]
+    fn test_restic_env_for_destination_password_command() {
+        let global = GlobalConfig {
+            restic_password_file: PathBuf::from("/nonexistent/password"),
+            restic_password_command: Some("pass show restic/global".to_string()),
+            ..Default::default()
+        };
+        let mut destination = Destination {
+            url: "/tmp/backups".to_string(),
+            ..Default::default()
+        };
+
+        let env = ResticEnv::for_destination(&global, "local", &destination, "/tmp/backups/svc").unwrap();
+        assert_eq!(env.vars().get("RESTIC_PASSWORD_COMMAND").unwrap(), "pass show restic/global");
+        assert!(!env.vars().contains_key("RESTIC_PASSWORD_FILE"));
+
+        // Destination-level command overrides the global one
+        destination.restic_password_command = Some("op read op://vault/restic".to_string());
+        let env = ResticEnv::for_destination(&global, "local", &destination, "/tmp/backups/svc").unwrap();
+        assert_eq!(env.vars().get("RESTIC_PASSWORD_COMMAND").unwrap(), "op read op://vault/restic");
+    }
+
+    #[test]
+    fn test_cold_storage_env() {
+        let global = GlobalConfig {
+            restic_password_file: PathBuf::from("/nonexistent/password"),
+            ..Default::default()
+        };
+        let glacier = Destination { url: "rclone:glacier:restic".to_string(), cold_storage: true, ..Default::default() };
+        let env = ResticEnv::for_destination(&global, "glacier", &glacier, "rclone:glacier:restic/svc").unwrap();
+        assert!(env.is_cold_storage());
+        assert!(!env.is_append_only());
+
+        let local = Destination { url: "/backups".to_string(), ..Default::default() };
+        let env = ResticEnv::for_destination(&global, "local", &local, "/backups/svc").unwrap();
+        assert!(!env.is_cold_storage());
+    }
+
+    #[test]
+    fn test_append_only_destination_refuses_retention() {
+        let mut global = GlobalConfig {
+            restic_password_file: PathBuf::from("/nonexistent/password"),
+            ..Default::default()
+        };
+        let destination = Destination {
+            url: "rest:https://backup.example.com".to_string(),
+            append_only: true,
+            ..Default::default()
+        };
+
+        let env = ResticEnv::for_destination(&global, "worm", &destination, "rest:https://backup.example.com/svc").unwrap();
+        assert!(env.is_append_only());
+        let retention = RetentionPolicy { daily: 7, weekly: 4, monthly: 6, yearly: 0, ..Default::default() };
+        let err = apply_retention(&env, &retention, Duration::from_secs(1)).unwrap_err();
+        assert!(err.to_string().contains("append-only"));
+
+        // A trusted maintenance host may prune
+        global.trusted_host = true;
+        let env = ResticEnv::for_destination(&global, "worm", &destination, "rest:https://backup.example.com/svc").unwrap();
+        assert!(!env.is_append_only());
+    }
+
+    #[test]
+    fn test_is_deleting_command() {
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(is_deleting_command(&args(&["forget", "--keep-last", "1"])));
+        assert!(is_deleting_command(&args(&["--verbose", "prune"])));
+        assert!(!is_deleting_command(&args(&["snapshots"])));
+        assert!(!is_deleting_command(&args(&["tag", "--add", "forget"])));
+
+        // The append-only guard can't be bypassed with global flags
+        assert!(is_deleting_command(&args(&["-r", "/repo", "forget", "--keep-last", "1"])));
+        assert!(is_deleting_command(&args(&["--password-file", "f", "prune"])));
+        assert!(is_deleting_command(&args(&["-r/repo", "--no-lock", "prune"])));
+        assert!(is_deleting_command(&args(&["--", "forget"])));
+        assert!(is_deleting_command(&args(&["--unknown-flag", "value", "forget"])));
+        assert!(!is_deleting_command(&args(&["-r", "forget", "snapshots"])));
+        assert_eq!(raw_subcommand(&args(&["--unknown-flag", "snapshots"])), Err("--unknown-flag"));
+    }
+
+    #[test]
+    fn test_snapshot_at() {
+        let snapshot = |id: &str, time: &str| Snapshot {
+            id: id.to_string(),
+            short_id: id.to_string(),
+            time: time.to_string(),
+            hostname: "testhost".to_string(),
+            paths: vec![],
+            tags: vec![],
+            ..Default::default()
+        };
+        let snapshots = vec![
+            snapshot("a", "2024-05-31T03:00:12.5+02:00"),
+            snapshot("b", "2024-06-01T03:00:09.1+02:00"),
+            snapshot("c", "2024-06-02T03:00:11+02:00"),
+        ];
+        let at = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&chrono::Utc);
+
+        assert_eq!(snapshot_at(&snapshots, at("2024-06-01T03:00:00+02:00")).unwrap().id, "a");
+        assert_eq!(snapshot_at(&snapshots, at("2024-06-01T01:05:00Z")).unwrap().id, "b");
+        assert_eq!(snapshot_at(&snapshots, at("2030-01-01T00:00:00Z")).unwrap().id, "c");
+        assert!(snapshot_at(&snapshots, at("2024-05-01T00:00:00Z")).is_none());
+    }
+
+    #[test]
+    fn test_parse_snapshots() {
+        let json = r#"[
+            {"time":"2024-06-01T03:00:09.1+02:00","tree":"9f1d4c","paths":["/srv/app"],"hostname":"nas","username":"root","id":"0f2a9b7c1e","short_id":"0f2a9b7c"},
+            {"time":"2024-06-02T03:00:11+02:00","parent":"0f2a9b7c1e","tree":"a3e8d0","paths":["/srv/app"],"hostname":"nas","username":"root","tags":["scheduled"],"program_version":"restic 0.17.1","summary":{"backup_start":"2024-06-02T03:00:11+02:00","backup_end":"2024-06-02T03:00:40+02:00","files_new":3,"files_changed":1,"files_unmodified":120,"dirs_new":0,"dirs_changed":2,"dirs_unmodified":30,"data_blobs":5,"tree_blobs":3,"data_added":4096,"data_added_packed":2048,"total_files_processed":124,"total_bytes_processed":1048576},"id":"7c41e2d9aa"}
+        ]"#;
+        let snapshots = parse_snapshots(json).unwrap();
+        assert_eq!(snapshots.len(), 2);
+
+        let first = &snapshots[0];
+        assert_eq!((first.short_id.as_str(), first.username.as_str(), first.tree.as_str()), ("0f2a9b7c", "root", "9f1d4c"));
+        assert!(first.tags.is_empty());
+        assert!(first.parent.is_none() && first.summary.is_none() && first.program_version.is_none());
+
+        // Missing short IDs are derived from the ID
+        let second = &snapshots[1];
+        assert_eq!(second.short_id, "7c41e2d9");
+        assert_eq!(second.parent.as_deref(), Some("0f2a9b7c1e"));
+        assert_eq!(second.tags, vec!["scheduled"]);
+        assert_eq!(second.program_version.as_deref(), Some("restic 0.17.1"));
+        let summary = second.summary.as_ref().unwrap();
+        assert_eq!((summary.files_new, summary.data_added_packed, summary.total_files_processed), (3, 2048, 124));
+
+        assert!(parse_snapshots("not json").is_err());
+    }
+
     #[test]
     fn test_snapshot_struct_creation() {
         let snapshot = Snapshot {
@@ -567,10 +2843,15 @@ mod tests {
             time: "2025-12-28T10:30:00Z".to_string(),
             hostname: "testhost".to_string(),
             paths: vec!["/data".to_string(), "/home".to_string()],
+            tags: vec!["manual".to_string()],
+            ..Default::default()
         };
 
         assert_eq!(snapshot.id, "abc123def456");
         assert_eq!(snapshot.paths.len(), 2);
+        assert!(snapshot.has_any_tag(&[]));
+        assert!(snapshot.has_any_tag(&["scheduled".to_string(), "manual".to_string()]));
+        assert!(!snapshot.has_any_tag(&["scheduled".to_string()]));
     }
 
     #[test]
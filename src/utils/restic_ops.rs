@@ -11,7 +11,7 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 // Re-export types from restic module
-pub use super::restic::{ResticEnv, Snapshot};
+pub use super::restic::{BackupSummary, CheckResult, RepoStats, ResticEnv, RestoreLimits, Snapshot};
 
 /// Abstraction for restic operations, enabling mocking in tests
 pub trait ResticOperations: Send + Sync {
@@ -25,7 +25,7 @@ pub trait ResticOperations: Send + Sync {
         paths: &[PathBuf],
         excludes: &[String],
         timeout: Duration,
-    ) -> Result<()>;
+    ) -> Result<BackupSummary>;
 
     /// List snapshots in a repository
     fn list_snapshots(&self, env: &ResticEnv, timeout: Duration) -> Result<Vec<Snapshot>>;
@@ -37,6 +37,7 @@ pub trait ResticOperations: Send + Sync {
         snapshot_id: &str,
         target_dir: Option<&str>,
         include_paths: &[String],
+        limits: &RestoreLimits,
         timeout: Duration,
     ) -> Result<()>;
 
@@ -54,13 +55,16 @@ pub trait ResticOperations: Send + Sync {
         env: &ResticEnv,
         read_data: bool,
         timeout: Duration,
-    ) -> Result<String>;
+    ) -> Result<CheckResult>;
 
     /// Unlock repository (useful after failures)
     fn unlock_repository(&self, env: &ResticEnv, timeout: Duration) -> Result<()>;
 
     /// Get repository stats
-    fn get_stats(&self, env: &ResticEnv, timeout: Duration) -> Result<String>;
+    fn get_stats(&self, env: &ResticEnv, timeout: Duration) -> Result<RepoStats>;
+
+    /// Get the restore size of one snapshot
+    fn snapshot_stats(&self, env: &ResticEnv, snapshot_id: &str, timeout: Duration) -> Result<RepoStats>;
 
     /// Count snapshots in a repository
     fn count_snapshots(&self, env: &ResticEnv, timeout: Duration) -> Result<usize>;
@@ -102,7 +106,7 @@ impl ResticOperations for RealResticOps {
         paths: &[PathBuf],
         excludes: &[String],
         timeout: Duration,
-    ) -> Result<()> {
+    ) -> Result<BackupSummary> {
         super::restic::backup(env, paths, excludes, timeout)
     }
 
@@ -116,9 +120,10 @@ impl ResticOperations for RealResticOps {
         snapshot_id: &str,
         target_dir: Option<&str>,
         include_paths: &[String],
+        limits: &RestoreLimits,
         timeout: Duration,
     ) -> Result<()> {
-        super::restic::restore_snapshot(env, snapshot_id, target_dir, include_paths, timeout)
+        super::restic::restore_snapshot(env, snapshot_id, target_dir, include_paths, limits, timeout)
     }
 
     fn apply_retention(
@@ -135,7 +140,7 @@ impl ResticOperations for RealResticOps {
         env: &ResticEnv,
         read_data: bool,
         timeout: Duration,
-    ) -> Result<String> {
+    ) -> Result<CheckResult> {
         super::restic::check_repository(env, read_data, timeout)
     }
 
@@ -143,10 +148,14 @@ impl ResticOperations for RealResticOps {
         super::restic::unlock_repository(env, timeout)
     }
 
-    fn get_stats(&self, env: &ResticEnv, timeout: Duration) -> Result<String> {
+    fn get_stats(&self, env: &ResticEnv, timeout: Duration) -> Result<RepoStats> {
         super::restic::get_stats(env, timeout)
     }
 
+    fn snapshot_stats(&self, env: &ResticEnv, snapshot_id: &str, timeout: Duration) -> Result<RepoStats> {
+        super::restic::snapshot_stats(env, snapshot_id, timeout)
+    }
+
     fn count_snapshots(&self, env: &ResticEnv, timeout: Duration) -> Result<usize> {
         super::restic::count_snapshots(env, timeout)
     }
@@ -187,6 +196,7 @@ pub mod mock {
         Check { read_data: bool },
         Unlock,
         GetStats,
+        SnapshotStats { snapshot_id: String },
         CountSnapshots,
         GetLatestSnapshot,
         ListSnapshotFiles { snapshot_id: String },
@@ -210,9 +220,9 @@ pub mod mock {
         /// Whether check should fail
         pub should_fail_check: Arc<Mutex<bool>>,
         /// Stats to return
-        pub stats: Arc<Mutex<String>>,
+        pub stats: Arc<Mutex<RepoStats>>,
         /// Check result to return
-        pub check_result: Arc<Mutex<String>>,
+        pub check_result: Arc<Mutex<CheckResult>>,
         /// Snapshot files (snapshot_id -> files)
         pub snapshot_files: Arc<Mutex<std::collections::HashMap<String, Vec<String>>>>,
     }
@@ -220,8 +230,10 @@ pub mod mock {
     impl MockResticOps {
         pub fn new() -> Self {
             Self {
-                stats: Arc::new(Mutex::new("1.0 GiB".to_string())),
-                check_result: Arc::new(Mutex::new("no errors found".to_string())),
+                stats: Arc::new(Mutex::new(RepoStats {
+                    total_size: 1024 * 1024 * 1024,
+                    ..Default::default()
+                })),
                 ..Default::default()
             }
         }
@@ -251,14 +263,14 @@ pub mod mock {
         }
 
         /// Configure stats response
-        pub fn with_stats(self, stats: &str) -> Self {
-            *self.stats.lock().unwrap() = stats.to_string();
+        pub fn with_stats(self, stats: RepoStats) -> Self {
+            *self.stats.lock().unwrap() = stats;
             self
         }
 
         /// Configure check result
-        pub fn with_check_result(self, result: &str) -> Self {
-            *self.check_result.lock().unwrap() = result.to_string();
+        pub fn with_check_result(self, result: CheckResult) -> Self {
+            *self.check_result.lock().unwrap() = result;
             self
         }
 
@@ -353,14 +365,14 @@ pub mod mock {
             paths: &[PathBuf],
             _excludes: &[String],
             _timeout: Duration,
-        ) -> Result<()> {
+        ) -> Result<BackupSummary> {
             self.record_call(ResticCall::Backup {
                 paths: paths.to_vec(),
             });
             if *self.should_fail_backup.lock().unwrap() {
                 anyhow::bail!("Mock backup failure");
             }
-            Ok(())
+            Ok(BackupSummary::default())
         }
 
         fn list_snapshots(&self, _env: &ResticEnv, _timeout: Duration) -> Result<Vec<Snapshot>> {
@@ -377,6 +389,7 @@ pub mod mock {
             snapshot_id: &str,
             _target_dir: Option<&str>,
             _include_paths: &[String],
+            _limits: &RestoreLimits,
             _timeout: Duration,
         ) -> Result<()> {
             self.record_call(ResticCall::Restore {
@@ -403,7 +416,7 @@ pub mod mock {
             _env: &ResticEnv,
             read_data: bool,
             _timeout: Duration,
-        ) -> Result<String> {
+        ) -> Result<CheckResult> {
             self.record_call(ResticCall::Check { read_data });
             if *self.should_fail_check.lock().unwrap() {
                 anyhow::bail!("Mock check failure");
@@ -416,11 +429,18 @@ pub mod mock {
             Ok(())
         }
 
-        fn get_stats(&self, _env: &ResticEnv, _timeout: Duration) -> Result<String> {
+        fn get_stats(&self, _env: &ResticEnv, _timeout: Duration) -> Result<RepoStats> {
             self.record_call(ResticCall::GetStats);
             Ok(self.stats.lock().unwrap().clone())
         }
 
+        fn snapshot_stats(&self, _env: &ResticEnv, snapshot_id: &str, _timeout: Duration) -> Result<RepoStats> {
+            self.record_call(ResticCall::SnapshotStats {
+                snapshot_id: snapshot_id.to_string(),
+            });
+            Ok(self.stats.lock().unwrap().clone())
+        }
+
         fn count_snapshots(&self, _env: &ResticEnv, _timeout: Duration) -> Result<usize> {
             self.record_call(ResticCall::CountSnapshots);
             Ok(self.snapshots.lock().unwrap().len())
@@ -483,6 +503,8 @@ mod tests {
             time: "2025-01-01T00:00:00Z".to_string(),
             hostname: "test".to_string(),
             paths: vec!["/data".to_string()],
+            tags: vec![],
+            ..Default::default()
         }]);
 
         let env = ResticEnv::new(&password_file, "/tmp/repo");
@@ -526,11 +548,16 @@ mod tests {
         let password_file = temp_dir.path().join("password");
         std::fs::write(&password_file, "test").unwrap();
 
-        let mock = MockResticOps::new().with_stats("2.5 GiB");
+        let mock = MockResticOps::new().with_stats(RepoStats {
+            total_size: 2_684_354_560,
+            total_file_count: 1200,
+            snapshots_count: 14,
+        });
         let env = ResticEnv::new(&password_file, "/tmp/repo");
         let timeout = Duration::from_secs(30);
 
         let stats = mock.get_stats(&env, timeout).unwrap();
-        assert_eq!(stats, "2.5 GiB");
+        assert_eq!(super::super::restic::format_bytes(stats.total_size), "2.50 GiB");
+        assert_eq!(stats.snapshots_count, 14);
     }
 }
@@ -0,0 +1,100 @@
+//! Retrying operations that fail transiently (e.g. an SFTP connection blip)
+
+use super::command::CommandCancelled;
+use crate::config::RetryPolicy;
+use anyhow::Result;
+use std::thread;
+use tracing::warn;
+
+/// Whether a failed operation is worth retrying
+///
+/// Interrupted commands are not: the user (or system) asked us to stop.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    !error.chain().any(|cause| cause.downcast_ref::<CommandCancelled>().is_some())
+}
+
+/// Run `op`, retrying failures according to `policy`
+///
+/// Every retry is logged and added to `retries`, so callers can report how
+/// many were needed across several operations.
+pub fn with_retry<T>(
+    policy: &RetryPolicy,
+    what: &str,
+    retries: &mut u32,
+    mut op: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.retries && is_retryable(&e) => {
+                attempt += 1;
+                *retries += 1;
+                let delay = policy.delay(attempt);
+                warn!(
+                    "{} failed (attempt {}/{}), retrying in {:?}: {:#}",
+                    what,
+                    attempt,
+                    policy.retries + 1,
+                    delay,
+                    e
+                );
+                thread::sleep(delay);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            delay_seconds: 0,
+            backoff_multiplier: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let mut calls = 0;
+        let mut retries = 0;
+        let result = with_retry(&policy(3), "backup", &mut retries, || {
+            calls += 1;
+            if calls < 3 { anyhow::bail!("connection reset") } else { Ok(calls) }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(retries, 2);
+    }
+
+    #[test]
+    fn test_retry_gives_up_and_skips_cancelled() {
+        let mut retries = 0;
+        let result: Result<()> = with_retry(&policy(2), "backup", &mut retries, || anyhow::bail!("down"));
+        assert!(result.is_err());
+        assert_eq!(retries, 2);
+
+        let mut retries = 0;
+        let result: Result<()> = with_retry(&policy(2), "backup", &mut retries, || {
+            Err(anyhow::Error::new(CommandCancelled).context("Backup interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(retries, 0);
+    }
+
+    #[test]
+    fn test_retry_delays_grow() {
+        let policy = RetryPolicy {
+            retries: 3,
+            delay_seconds: 30,
+            backoff_multiplier: 2.0,
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(30));
+        assert_eq!(policy.delay(2), Duration::from_secs(60));
+        assert_eq!(policy.delay(3), Duration::from_secs(120));
+    }
+}
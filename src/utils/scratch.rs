@@ -0,0 +1,91 @@
+//! Temporary directories below the work directory
+//!
+//! `work_dir` may point at any directory (e.g. `/srv`), so scratch
+//! directories live in a fixed `restic-manager-scratch` subdirectory of it
+//! and carry a marker file. Only directories with the marker are ever
+//! removed, so a service named like an existing directory can't delete it.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Subdirectory of `work_dir` holding all scratch directories
+pub const SCRATCH_SUBDIR: &str = "restic-manager-scratch";
+
+/// File marking a directory as created by restic-manager
+const MARKER: &str = ".restic-manager-scratch";
+
+/// Temporary directory, removed when dropped (also when the operation using
+/// it fails or is cancelled)
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Create `parent/name`, replacing one left behind by a crashed run
+    pub fn create(parent: &Path, name: &str) -> Result<Self> {
+        let path = parent.join(name);
+        // Archives left behind by a crashed run must not be backed up again
+        if path.exists() {
+            info!("Removing work directory left behind by an earlier run: {}", path.display());
+            if !remove_stale(&path)? {
+                anyhow::bail!(
+                    "{} exists but wasn't created by restic-manager; refusing to remove it",
+                    path.display()
+                );
+            }
+        }
+        fs::create_dir_all(&path).context("Failed to create temporary directory")?;
+        fs::write(path.join(MARKER), "").context("Failed to mark temporary directory")?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = remove_stale(&self.path) {
+            warn!("Failed to cleanup temporary directory: {}", e);
+        }
+    }
+}
+
+/// Remove a scratch directory if it carries the marker
+///
+/// Returns whether it was removed.
+pub fn remove_stale(path: &Path) -> Result<bool> {
+    if !path.join(MARKER).is_file() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(path).context(format!("Failed to remove {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_dir_only_removes_marked_dirs() {
+        let parent = std::env::temp_dir().join(format!("scratch-test-{}", std::process::id()));
+        let path = {
+            let dir = ScratchDir::create(&parent, "app").unwrap();
+            fs::write(dir.path().join("volume.tar.gz"), "data").unwrap();
+            dir.path().to_path_buf()
+        };
+        assert!(!path.exists());
+
+        // A directory that isn't ours is left alone
+        fs::create_dir_all(parent.join("nextcloud")).unwrap();
+        fs::write(parent.join("nextcloud").join("user-data"), "keep").unwrap();
+        assert!(ScratchDir::create(&parent, "nextcloud").is_err());
+        assert!(!remove_stale(&parent.join("nextcloud")).unwrap());
+        assert!(parent.join("nextcloud").join("user-data").exists());
+
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
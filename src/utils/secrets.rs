@@ -0,0 +1,203 @@
+//! Repository password sources other than a plain password file
+//!
+//! Keyring: uses secret-service on Linux, Keychain on macOS and Credential
+//! Manager on Windows. Passwords are stored under the `restic-manager` service
+//! name, with the destination name as account. The `@shared` account (not a
+//! valid destination name) is used for destinations without their own entry.
+//!
+//! Password commands (`restic_password_command`) are passed to restic as
+//! `RESTIC_PASSWORD_COMMAND`; they are only executed here for validation,
+//! split into arguments without a shell just as restic does.
+
+use super::command;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+/// Keyring service name for all restic-manager entries
+pub const KEYRING_SERVICE: &str = "restic-manager";
+
+/// Account used when no destination-specific entry exists
+pub const SHARED_ACCOUNT: &str = "@shared";
+
+fn entry(account: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, account)
+        .context(format!("Failed to open keyring entry '{}'", account))
+}
+
+/// Get the password stored for an account (None if no entry exists)
+pub fn get_password(account: &str) -> Result<Option<String>> {
+    match entry(account)?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).context(format!("Failed to read keyring entry '{}'", account)),
+    }
+}
+
+/// Store a password for an account, replacing any existing one
+pub fn set_password(account: &str, password: &str) -> Result<()> {
+    entry(account)?
+        .set_password(password)
+        .context(format!("Failed to write keyring entry '{}'", account))
+}
+
+/// Delete the password for an account, returns false if none was stored
+pub fn delete_password(account: &str) -> Result<bool> {
+    match entry(account)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context(format!("Failed to delete keyring entry '{}'", account)),
+    }
+}
+
+/// Resolve the repository password for a destination
+///
+/// Looks up the destination's own entry first, then the shared entry.
+pub fn resolve_password(destination_name: &str) -> Result<String> {
+    if let Some(password) = get_password(destination_name)? {
+        debug!("Using keyring password for destination '{}'", destination_name);
+        return Ok(password);
+    }
+
+    get_password(SHARED_ACCOUNT)?.context(format!(
+        "No keyring password for destination '{}' (run: restic-manager secret set --destination {})",
+        destination_name, destination_name
+    ))
+}
+
+/// Write a file only the current user can read
+///
+/// New files are created with mode 0600, so the content is never readable by
+/// others; an existing file is restricted before it is overwritten.
+pub fn write_private(path: &Path, content: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).context(format!("Failed to create {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .context(format!("Failed to restrict permissions of {}", path.display()))?;
+    }
+    file.write_all(content.as_bytes()).context(format!("Failed to write {}", path.display()))
+}
+
+/// Tokens only a shell would interpret
+const SHELL_OPERATORS: &[&str] = &["|", "||", "&", "&&", ";", "<", ">", ">>", "2>", "2>&1"];
+
+/// Split a password command into program and arguments like restic does
+///
+/// restic doesn't run `RESTIC_PASSWORD_COMMAND` through a shell, so pipes,
+/// redirections and `$VARIABLES` would reach the program as literal
+/// arguments; they are rejected here with a hint instead.
+pub fn split_password_command(command: &str) -> Result<Vec<String>> {
+    let args = shell_words::split(command).context("Invalid password command quoting")?;
+    if args.is_empty() {
+        anyhow::bail!("Password command is empty");
+    }
+
+    if let Some(arg) = args.iter().find(|a| SHELL_OPERATORS.contains(&a.as_str()) || a.starts_with('$')) {
+        anyhow::bail!(
+            "Password command uses shell syntax ('{}'), but restic runs it without a shell; wrap it as sh -c '...'",
+            arg
+        );
+    }
+
+    Ok(args)
+}
+
+/// Test-run a password command, checking that it succeeds and prints a password
+///
+/// The command is run without a shell, as restic runs it. The output is
+/// discarded and never logged.
+pub fn check_password_command(command: &str, timeout: Duration) -> Result<()> {
+    let args = split_password_command(command)?;
+    let arg_refs: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+    let output = command::run_command_secret(&args[0], &arg_refs, timeout).context("Password command failed")?;
+
+    if String::from_utf8_lossy(&output.stdout).trim().is_empty() {
+        anyhow::bail!("Password command printed no password");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("password");
+        write_private(&path, "secret\n").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret\n");
+
+        // An existing world-readable file is restricted
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "other\n").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "other\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_password_command_success() {
+        assert!(check_password_command("echo secret", Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_password_command_empty_output() {
+        let result = check_password_command("true", Duration::from_secs(5));
+        assert!(result.unwrap_err().to_string().contains("no password"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_password_command_failure() {
+        assert!(check_password_command("false", Duration::from_secs(5)).is_err());
+        assert!(check_password_command("no-such-password-manager show x", Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_password_command_timeout() {
+        let err = check_password_command("sleep 5", Duration::from_millis(100)).unwrap_err();
+        assert!(err.downcast_ref::<command::CommandTimeout>().is_some(), "unexpected error: {:#}", err);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_password_command_without_shell() {
+        let err = check_password_command("echo secret | cat", Duration::from_secs(5)).unwrap_err();
+        assert!(err.to_string().contains("shell syntax ('|')"), "unexpected error: {}", err);
+        assert!(check_password_command("cat $HOME/.restic-password", Duration::from_secs(5)).is_err());
+
+        // An explicit shell is fine
+        assert!(check_password_command("sh -c 'echo secret | cat'", Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_split_password_command() {
+        assert_eq!(
+            split_password_command("op read 'op://Backups/restic offsite/password'").unwrap(),
+            vec!["op", "read", "op://Backups/restic offsite/password"]
+        );
+        assert!(split_password_command("pass show 'unterminated").is_err());
+        assert!(split_password_command("  ").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Graceful shutdown on SIGINT/SIGTERM
+//!
+//! Without a handler, Ctrl-C or a `systemctl stop` kills restic-manager
+//! mid-backup, and nothing runs that would resume paused containers, start a
+//! stopped Compose stack, remove the temporary volume archives or release
+//! the locks. With [`install`]ed, the first signal only asks the backup to
+//! stop: the running restic command is terminated (restic removes its
+//! repository lock itself), the backup fails with [`CommandCancelled`] so
+//! the guards clean up as it unwinds, and the run is reported and notified
+//! as cancelled. A second signal exits right away.
+
+use super::command::CommandCancelled;
+use anyhow::{Context, Result};
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// How long restic gets to exit (and remove its lock) after SIGTERM
+const TERMINATE_GRACE: Duration = Duration::from_secs(10);
+
+/// Exit code of a second signal (128 + SIGINT)
+const FORCED_EXIT: i32 = 130;
+
+/// Handle SIGINT and SIGTERM by asking running operations to stop
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            eprintln!("Interrupted again, exiting without cleaning up");
+            std::process::exit(FORCED_EXIT);
+        }
+        eprintln!("Interrupted, stopping the backup and cleaning up (interrupt again to exit right away)");
+    })
+    .context("Failed to install the signal handler")
+}
+
+/// Whether a shutdown was requested
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Fail with [`CommandCancelled`] once a shutdown was requested, so no
+/// new work is started
+pub fn check() -> Result<()> {
+    if requested() {
+        return Err(CommandCancelled.into());
+    }
+    Ok(())
+}
+
+/// Stop a child process: SIGTERM first, so it can clean up, then kill it
+/// if it hasn't exited after a grace period
+pub fn terminate(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+        use std::thread;
+        use std::time::Instant;
+
+        if kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).is_ok() {
+            let deadline = Instant::now() + TERMINATE_GRACE;
+            while Instant::now() < deadline {
+                match child.try_wait() {
+                    Ok(Some(_)) => return,
+                    Ok(None) => thread::sleep(Duration::from_millis(100)),
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
+    if let Err(e) = child.kill().and_then(|_| child.wait()) {
+        warn!("Failed to kill command: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_stops_child() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let start = Instant::now();
+        terminate(&mut child);
+        assert!(child.try_wait().unwrap().is_some());
+        assert!(start.elapsed() < TERMINATE_GRACE);
+    }
+}